@@ -8,10 +8,15 @@ use crate::filetree::*;
 use crate::fileeditor::*;
 use crate::buildmanager::*;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use crate::builder;
 use crate::livemacro::*;
 
+// fields missing from a settings file get their value from AppSettings::default and
+// ones it no longer has are skipped, so settings can change without breaking existing files
 #[derive(Debug, Clone, SerRon, DeRon)]
+#[default]
+#[ignore_unknown]
 pub struct AppSettings { 
     pub build_on_save: bool,
     pub exec_when_done: bool,
@@ -55,7 +60,10 @@ impl AppSettings {
                         let mut workspace = HashMap::new();
                         workspace.insert("makepad".to_string(), ".".to_string());
                         workspace
-                    }
+                    },
+                    build_profiles: HubBuildProfile::defaults(),
+                    debug_adapter: HubDebugAdapter::default_adapter(),
+                    language_server: HubLanguageServer::default_server()
                 });
                 cfg
             },
@@ -81,6 +89,7 @@ pub struct BuildTarget {
     pub builder: String,
     pub workspace: String,
     pub package: String,
+    // the name of one of the build profiles of the builder
     pub config: String
 }
 
@@ -90,12 +99,24 @@ pub struct AppStorage {
     pub builder_sync_uid: HubUid,
     pub hub_router: Option<HubRouter>,
     pub hub_server: Option<HubServer>,
+    // builders on the LAN announcing themselves, listened for while the hub server is on the network
+    pub hub_discovery: Option<HubDiscovery>,
+    pub hub_discovery_changed: Signal,
+    pub discovered_builders: Vec<HubDiscoveredBuilder>,
+    // running syncs of settings.sync, and what each source and mirror looked like after the last one
+    pub syncs: Vec<HubSync>,
+    pub sync_bases: HashMap<(String, String), SyncDigests>,
+    // the packages of each builder, with the build profiles they have
+    pub packages: Vec<(String, HubPackage)>,
+    pub packages_request_uids: Vec<(HubUid, String)>,
     pub builder_route_send: Option<HubRouteSend>,
     pub hub_ui: Option<HubUI>,
     pub hub_ui_message: Signal,
     pub settings_changed: Signal,
     pub settings_old: AppSettings,
     pub settings: AppSettings,
+    // why the settings file could not be loaded, for the log list
+    pub settings_error: Option<LocMessage>,
     pub file_tree_file_read: FileRead,
     pub app_state_file_read: FileRead,
     pub app_settings_file_read: FileRead,
@@ -129,11 +150,19 @@ impl AppStorage {
             builder_route_send: None,
             hub_router: None,
             hub_server: None,
+            hub_discovery: None,
+            hub_discovery_changed: cx.new_signal(),
+            discovered_builders: Vec::new(),
+            syncs: Vec::new(),
+            sync_bases: HashMap::new(),
+            packages: Vec::new(),
+            packages_request_uids: Vec::new(),
             hub_ui: None,
             hub_ui_message: cx.new_signal(),
             settings_changed: cx.new_signal(),
             settings_old: AppSettings::default(),
             settings: AppSettings::default(),
+            settings_error: None,
             //rust_compiler: RustCompiler::style(cx),
             text_buffer_path_to_id: HashMap::new(),
             text_buffer_id_to_path: HashMap::new(),
@@ -146,6 +175,8 @@ impl AppStorage {
      
     pub fn status_new_message() -> StatusId {uid!()}
     pub fn status_settings_changed() -> StatusId {uid!()}
+    pub fn status_settings_error() -> StatusId {uid!()}
+    pub fn status_builders_discovered() -> StatusId {uid!()}
     
     pub fn init(&mut self, cx: &mut Cx) {
         if cx.platform_type.is_desktop() {
@@ -188,8 +219,25 @@ impl AppStorage {
                     }
                 }
             },
-            Err(e) => {
-                println!("Cannot deserialize settings {:?}", e);
+            Err(err) => {
+                println!("Cannot deserialize settings {}", err);
+                let body = if err.path.len() != 0 {
+                    format!("Cannot load settings, {} at {}", err.msg, err.path)
+                }
+                else {
+                    format!("Cannot load settings, {}", err.msg)
+                };
+                self.settings_error = Some(LocMessage {
+                    path: "makepad_settings.ron".to_string(),
+                    line: err.line + 1,
+                    col: err.col + 1,
+                    body: body,
+                    range: None,
+                    rendered: Some(err.to_string()),
+                    explanation: None,
+                    fixes: Vec::new()
+                });
+                cx.send_signal(self.settings_changed, Self::status_settings_error());
             }
         }
     }
@@ -209,27 +257,85 @@ impl AppStorage {
         if let Some(hub_server) = &mut self.hub_server {
             hub_server.terminate();
         }
+        if let Some(hub_discovery) = &mut self.hub_discovery {
+            hub_discovery.terminate();
+        }
+        self.hub_discovery = None;
+        self.discovered_builders.truncate(0);
         
         if let Some(hub_router) = &mut self.hub_router {
-            let digest = Self::read_or_generate_key_ron();
+            let digest = if let Some(digest) = Self::read_or_generate_key_ron() {digest} else {return};
             // start the server
-            self.hub_server = HubServer::start_hub_server(digest, &self.settings.hub_server, hub_router);
+            self.hub_server = HubServer::start_hub_server(digest.clone(), &self.settings.hub_server, hub_router);
+            // and listen for builders that want to be invited to it
+            if self.hub_server.is_some() {
+                let signal = self.hub_discovery_changed.clone();
+                self.hub_discovery = HubDiscovery::start_hub_discovery(&digest, SocketAddr::from(([0, 0, 0, 0], HUB_ANNOUNCE_PORT)), move || {
+                    Cx::post_signal(signal, Self::status_builders_discovered());
+                });
+            }
+        }
+    }
+    
+    // takes in the builders discovery knows about now, and returns the ones that are new to us
+    pub fn update_discovered_builders(&mut self) -> Vec<HubDiscoveredBuilder> {
+        let builders = if let Some(hub_discovery) = &self.hub_discovery {hub_discovery.builders()}else {Vec::new()};
+        let new_builders = builders.iter().filter( | b | {
+            self.discovered_builders.iter().find( | old | old.builder == b.builder && old.addr == b.addr).is_none()
+        }).cloned().collect();
+        self.discovered_builders = builders;
+        new_builders
+    }
+    
+    // invites the discovered builders that share our key.ron to our hub, and gives the ones we
+    // have no settings for an entry in makepad_settings.ron to put their workspaces in
+    pub fn connect_discovered_builders(&mut self, cx: &mut Cx) -> Vec<String> {
+        let hub_port = if let Some(addr) = self.hub_server.as_ref().and_then( | s | s.listen_address) {addr.port()}else {return Vec::new()};
+        let hub_discovery = if let Some(hub_discovery) = &self.hub_discovery {hub_discovery}else {return Vec::new()};
+        let mut invited = Vec::new();
+        let mut settings_changed = false;
+        for discovered in &self.discovered_builders {
+            if !discovered.same_key || !hub_discovery.invite(&discovered.builder, hub_port) {
+                continue;
+            }
+            if !self.settings.builders.contains_key(&discovered.builder) {
+                self.settings.builders.insert(discovered.builder.clone(), HubBuilderConfig {
+                    http_server: HttpServerConfig::Offline,
+                    workspaces: HashMap::new(),
+                    build_profiles: HubBuildProfile::defaults(),
+                    debug_adapter: HubDebugAdapter::default_adapter(),
+                    language_server: HubLanguageServer::default_server()
+                });
+                settings_changed = true;
+            }
+            invited.push(discovered.builder.clone());
+        }
+        if settings_changed {
+            self.save_settings(cx);
         }
+        invited
     }
     
-    pub fn read_or_generate_key_ron() -> Digest {
+    // without a key.ron and no way to make a random one the hub server isn't started
+    pub fn read_or_generate_key_ron() -> Option<Digest> {
         // read or generate key.ron
         if let Ok(utf8_data) = std::fs::read_to_string("key.ron") {
             if let Ok(digest) = DeRon::deserialize_ron(&utf8_data) {
-                return digest
+                return Some(digest)
             }
         }
-        let digest = Digest::generate();
+        let digest = match Digest::generate() {
+            Ok(digest) => digest,
+            Err(err) => {
+                println!("Cannot generate key.ron: {}", err.msg);
+                return None
+            }
+        };
         let utf8_data = digest.serialize_ron();
         if std::fs::write("key.ron", utf8_data.as_bytes()).is_err() {
             println!("Cannot generate key.ron");
         }
-        digest
+        Some(digest)
     }
     
     pub fn save_state(&mut self, cx: &mut Cx, state: &AppState) {
@@ -386,8 +492,27 @@ impl AppStorage {
         self.builders_request_uid = uid;
     }
     
+    // syncs every workspace in settings.sync to its mirrors
+    pub fn start_sync(&mut self) {
+        let hub_ui = self.hub_ui.as_mut().unwrap();
+        for (source, targets) in &self.settings.sync {
+            for target in targets {
+                if self.syncs.iter().find( | s | s.source == *source && s.target == *target).is_some() {
+                    continue;
+                }
+                let base = self.sync_bases.get(&(source.clone(), target.clone())).cloned();
+                let mut sync = HubSync::new(hub_ui.route_send.alloc_uid(), source, target, base);
+                sync.start(&hub_ui.route_send);
+                self.syncs.push(sync);
+            }
+        }
+    }
+    
     pub fn handle_hub_msg(&mut self, cx: &mut Cx, htc: &FromHubMsg, windows: &mut Vec<AppWindow>, state: &AppState, build_manager: &mut BuildManager) {
         let hub_ui = self.hub_ui.as_mut().unwrap();
+        if self.syncs.iter_mut().any( | sync | sync.handle_hub_msg(&hub_ui.route_send, htc)) {
+            return
+        }
         // only in ConnectUI of ourselves do we list the workspaces
         match &htc.msg {
             // our own connectUI message, means we are ready to talk to the hub
@@ -400,6 +525,23 @@ impl AppStorage {
                     self.reload_builders();
                 }
             },
+            HubMsg::SyncEnd {uid, result} => if let Some(index) = self.syncs.iter().position( | s | s.uid == *uid) {
+                let sync = self.syncs.remove(index);
+                build_manager.add_log_message(cx, format!(
+                    "Synced {} to {}: {} written, {} deleted, {} renamed, {} conflicts, {} failed, {} bytes sent",
+                    sync.source,
+                    sync.target,
+                    result.written,
+                    result.deleted,
+                    result.renamed,
+                    result.conflicts,
+                    result.failed,
+                    result.bytes_sent
+                ));
+                if let Some(synced) = sync.synced_digests() {
+                    self.sync_bases.insert((sync.source, sync.target), synced);
+                }
+            },
             HubMsg::ListBuildersResponse {uid, builders} => if *uid == self.builders_request_uid {
                 let uid = hub_ui.route_send.alloc_uid();
                 // from these workspaces query filetrees
@@ -416,11 +558,14 @@ impl AppStorage {
                         to: HubMsgTo::Builder(builder.clone()),
                         msg: HubMsg::BuilderFileTreeRequest {uid: uid, create_digest: false}
                     });
+                    let packages_uid = hub_ui.route_send.alloc_uid();
                     hub_ui.route_send.send(ToHubMsg {
                         to: HubMsgTo::Builder(builder.clone()),
-                        msg: HubMsg::ListPackagesRequest {uid: uid}
+                        msg: HubMsg::ListPackagesRequest {uid: packages_uid}
                     });
+                    self.packages_request_uids.push((packages_uid, builder.clone()));
                 }
+                self.packages.retain( | (builder, _) | builders.contains(builder));
                 self.builders_request_uid = uid;
                 // add all workspace nodes
                 for window in windows {
@@ -449,6 +594,14 @@ impl AppStorage {
                     }
                 }
             },
+            HubMsg::ListPackagesResponse {uid, packages} => if let Some(pos) = self.packages_request_uids.iter().position( | (u, _) | u == uid) {
+                let (_, builder) = self.packages_request_uids.remove(pos);
+                self.packages.retain( | (b, _) | *b != builder);
+                self.packages.extend(packages.iter().map( | p | (builder.clone(), p.clone())));
+                for window in windows.iter_mut() {
+                    window.package_list.view.redraw_view_area(cx);
+                }
+            },
             HubMsg::BuilderFileTreeResponse {uid, tree} => if *uid == self.builders_request_uid {
                 // replace a workspace node
                 if let BuilderFileTreeNode::Folder {name, ..} = &tree {
@@ -18,6 +18,11 @@ use crate::rusteditor::*;
 use crate::jseditor::*;
 use crate::plaineditor::*;
 use crate::shaderview::*;
+use crate::testexplorer::*;
+use crate::packagelist::*;
+use crate::debugpanel::*;
+use crate::languageclient::*;
+use crate::terminalpanel::*;
 
 #[derive(Debug, Clone, SerRon, DeRon)]
 pub enum Panel {
@@ -28,7 +33,11 @@ pub enum Panel {
     ShaderView,
     FileTree,
     FileEditorTarget,
-    FileEditor {path: String, scroll_pos: Vec2, editor_id: u64}
+    FileEditor {path: String, scroll_pos: Vec2, editor_id: u64},
+    TestExplorer,
+    PackageList,
+    DebugPanel,
+    Terminal
 }
 
 #[derive(Clone)]
@@ -38,6 +47,10 @@ pub struct AppWindow {
     pub home_page: HomePage,
     pub item_display: ItemDisplay,
     pub log_list: LogList,
+    pub test_explorer: TestExplorer,
+    pub package_list: PackageList,
+    pub debug_panel: DebugPanel,
+    pub terminal_panel: TerminalPanel,
     pub search_results: SearchResults,
     pub shader_view: ShaderView,
     pub keyboard: Keyboard,
@@ -78,6 +91,10 @@ impl AppWindow {
             keyboard: Keyboard::new(cx),
             item_display: ItemDisplay::new(cx),
             log_list: LogList::new(cx),
+            test_explorer: TestExplorer::new(cx),
+            package_list: PackageList::new(cx),
+            debug_panel: DebugPanel::new(cx),
+            terminal_panel: TerminalPanel::new(cx),
             search_results: SearchResults::new(cx),
             file_panel: FilePanel::new(cx),
             xr_control: XRControl::new(cx),
@@ -150,6 +167,10 @@ impl AppWindow {
             self.show_search_tab(cx, window_index, state);
         }
         
+        if self.log_list.handle_filter_input(cx, event) {
+            self.show_log_tab(cx, window_index, state);
+        }
+        
         let dock_items = &mut state.windows[window_index].dock_items;
         let mut dock_walker = self.dock.walker(dock_items);
         let mut file_tree_event = FileTreeEvent::None;
@@ -159,6 +180,23 @@ impl AppWindow {
         let mut show_item_display_tab = false;
         let mut do_display_rust_file = None;
         
+        // answers of the language server that go to the window
+        if let Event::Signal(se) = event {
+            if let Some(statusses) = se.signals.get(&build_manager.signal) {
+                if statusses.contains(&BuildManager::status_language_update()) {
+                    if let Some(hover) = build_manager.language_client.hover.take() {
+                        self.item_display.display_plain_text(cx, &hover);
+                        show_item_display_tab = true;
+                    }
+                    if let Some(loc_message) = build_manager.language_client.jump_to.take() {
+                        let (loc_message, offset) = LanguageClient::loc_message_offset(cx, storage, &loc_message);
+                        file_tree_event = FileTreeEvent::SelectFile {path: loc_message.path.clone()};
+                        set_last_cursor = Some((offset, offset));
+                    }
+                }
+            }
+        }
+        
         while let Some(item) = dock_walker.walk_handle_dock(cx, event) {
             match item {
                 Panel::LogList => {
@@ -181,6 +219,47 @@ impl AppWindow {
                         _ => ()
                     }
                 }
+                Panel::TestExplorer => {
+                    match self.test_explorer.handle_test_explorer(cx, event, storage, build_manager) {
+                        TestExplorerEvent::SelectLocMessage {loc_message, jump_to_offset} => {
+                            file_tree_event = FileTreeEvent::SelectFile {path: storage.remap_sync_path(&loc_message.path)};
+                            self.item_display.display_message(cx, &loc_message);
+                            set_last_cursor = Some((jump_to_offset, jump_to_offset));
+                            show_item_display_tab = true;
+                        },
+                        TestExplorerEvent::SelectMessages {items} => {
+                            self.item_display.display_plain_text(cx, &items);
+                            show_item_display_tab = true;
+                        }
+                        _ => ()
+                    }
+                }
+                Panel::PackageList => {
+                    match self.package_list.handle_package_list(cx, event, storage, build_manager) {
+                        PackageListEvent::SelectMessages {items} => {
+                            self.item_display.display_plain_text(cx, &items);
+                            show_item_display_tab = true;
+                        }
+                        _ => ()
+                    }
+                }
+                Panel::DebugPanel => {
+                    match self.debug_panel.handle_debug_panel(cx, event, storage, build_manager) {
+                        DebugPanelEvent::SelectLocMessage {loc_message, jump_to_offset} => {
+                            file_tree_event = FileTreeEvent::SelectFile {path: loc_message.path.clone()};
+                            self.item_display.display_message(cx, &loc_message);
+                            set_last_cursor = Some((jump_to_offset, jump_to_offset));
+                        },
+                        DebugPanelEvent::SelectMessages {items} => {
+                            self.item_display.display_plain_text(cx, &items);
+                            show_item_display_tab = true;
+                        }
+                        _ => ()
+                    }
+                }
+                Panel::Terminal => {
+                    self.terminal_panel.handle_terminal_panel(cx, event, storage, build_manager);
+                }
                 Panel::ShaderView => {
                     self.shader_view.handle_shader_view(cx, event)
                 },
@@ -227,8 +306,28 @@ impl AppWindow {
                             }
                             TextEditorEvent::Change => {
                                 do_search = Some((None, AppTextBufferId(0), false, false));
+                                build_manager.language_client.sync(cx, storage, path);
+                            }
+                            TextEditorEvent::KeyFocus => {
+                                build_manager.language_client.sync(cx, storage, path);
+                            }
+                            TextEditorEvent::Hover(pos) => {
+                                build_manager.language_client.request(cx, storage, path, LanguageRequest::Hover, pos);
+                            }
+                            TextEditorEvent::Definition(pos) => {
+                                build_manager.language_client.request(cx, storage, path, LanguageRequest::Definition, pos);
+                            }
+                            TextEditorEvent::References(pos) => {
+                                build_manager.language_client.request(cx, storage, path, LanguageRequest::References, pos);
                             }
+                            TextEditorEvent::Completion(pos) => {
+                                build_manager.language_client.request(cx, storage, path, LanguageRequest::Completion, pos);
+                            }
+                            TextEditorEvent::Breakpoints => {
+                                build_manager.debugger.breakpoints_changed(storage, path);
+                            },
                             TextEditorEvent::LagChange => {
+                                build_manager.language_client.sync(cx, storage, path);
                                 storage.text_buffer_file_write(cx, path);
                                 if storage.settings.build_on_save {
                                     build_manager.restart_build(cx, storage);
@@ -364,6 +463,7 @@ impl AppWindow {
         let file_panel = &mut self.file_panel;
         let search_results = &mut self.search_results;
         let item_display = &mut self.item_display;
+        let log_list = &mut self.log_list;
         while let Some(item) = dock_walker.walk_draw_dock(cx, | cx, tab_control, tab, selected | {
             // this draws the tabs, so we can customimze it
             match tab.item {
@@ -381,6 +481,13 @@ impl AppWindow {
                         tab.end_tab(cx);
                     };
                 }
+                Panel::LogList => {
+                    let tab = tab_control.get_draw_tab(cx, &tab.title, selected, tab.closeable);
+                    if tab.begin_tab(cx).is_ok() {
+                        log_list.draw_log_list_tab(cx);
+                        tab.end_tab(cx);
+                    };
+                }
                 _ => tab_control.draw_tab(cx, &tab.title, selected, tab.closeable)
             }
         }) {
@@ -389,7 +496,19 @@ impl AppWindow {
                     self.shader_view.draw_shader_view(cx);
                 },
                 Panel::LogList => {
-                    self.log_list.draw_log_list(cx, build_manager);
+                    log_list.draw_log_list(cx, build_manager);
+                }
+                Panel::TestExplorer => {
+                    self.test_explorer.draw_test_explorer(cx, build_manager);
+                }
+                Panel::PackageList => {
+                    self.package_list.draw_package_list(cx, storage);
+                }
+                Panel::DebugPanel => {
+                    self.debug_panel.draw_debug_panel(cx, build_manager);
+                }
+                Panel::Terminal => {
+                    self.terminal_panel.draw_terminal_panel(cx, storage, build_manager);
                 }
                 Panel::SearchResults => {
                     search_results.draw_search_results(cx, storage);
@@ -3,6 +3,10 @@ use makepad_widget::*;
 use makepad_hub::*;
 use crate::appstorage::*;
 use crate::searchindex::*;
+use crate::testrunner::*;
+use crate::debugger::*;
+use crate::languageclient::*;
+use crate::terminal::*;
 
 #[derive(Clone)]
 pub struct BuildManager {
@@ -11,6 +15,10 @@ pub struct BuildManager {
     pub exec_when_done: bool,
     pub log_items: Vec<HubLogItem>,
     pub search_index: SearchIndex,
+    pub test_runner: TestRunner,
+    pub debugger: Debugger,
+    pub language_client: LanguageClient,
+    pub terminal: Terminal,
     pub tail_log_items: bool,
     pub artifacts: Vec<String>,
 }
@@ -25,6 +33,10 @@ impl BuildManager {
             artifacts: Vec::new(),
             active_builds: Vec::new(),
             search_index: SearchIndex::new(),
+            test_runner: TestRunner::new(),
+            debugger: Debugger::new(),
+            language_client: LanguageClient::new(),
+            terminal: Terminal::new(),
         }
     }
     
@@ -32,6 +44,10 @@ impl BuildManager {
     pub fn status_new_artifact()->StatusId{uid!()}
     pub fn status_cargo_end()->StatusId{uid!()}
     pub fn status_program_end()->StatusId{uid!()}
+    pub fn status_test_update()->StatusId{uid!()}
+    pub fn status_debug_update()->StatusId{uid!()}
+    pub fn status_language_update()->StatusId{uid!()}
+    pub fn status_terminal_update()->StatusId{uid!()}
 }
 
 #[derive(Clone)]
@@ -125,7 +141,15 @@ impl BuildManager {
             
             let msg = TextBufferMessage {
                 body: loc_message.body.clone(),
-                level: level
+                level: level,
+                fixes: loc_message.fixes.iter().map( | fix | TextBufferFix {
+                    label: fix.label.clone(),
+                    edits: fix.edits.iter().map( | edit | TextBufferEdit {
+                        start: TextPos {row: edit.start_line.max(1) - 1, col: edit.start_col.max(1) - 1},
+                        end: TextPos {row: edit.end_line.max(1) - 1, col: edit.end_col.max(1) - 1},
+                        replacement: edit.replacement.clone()
+                    }).collect()
+                }).collect()
             };
             if let Some(pos) = inserted {
                 atb.text_buffer.markers.message_bodies.insert(pos, msg);
@@ -158,22 +182,26 @@ impl BuildManager {
         match &htc.msg {
             HubMsg::ListBuildersResponse {..} => {
                 self.restart_build(cx, storage);
+                self.terminal.find_running(storage);
             },
             HubMsg::CargoBegin {uid} => if self.is_running_uid(uid) {
             },
-            HubMsg::LogItem {uid, item} => if self.is_running_uid(uid) {
+            HubMsg::LogItem {uid, item} => if self.is_running_uid(uid) || self.test_runner.is_running_uid(uid) || self.debugger.is_running_uid(uid) {
                 
                 self.handle_log_item_limit(cx);
                 self.log_items.push(item.clone());
                 if let Some(loc_message) = item.get_loc_message() {
                     let level = match item {
-                        HubLogItem::LocPanic(_) => TextBufferMessageLevel::Log,
+                        HubLogItem::LocPanic(_) | HubLogItem::Backtrace {..} => TextBufferMessageLevel::Log,
                         HubLogItem::LocError(_) => TextBufferMessageLevel::Error,
                         HubLogItem::LocWarning(_) => TextBufferMessageLevel::Warning,
                         HubLogItem::LocMessage(_) => TextBufferMessageLevel::Log,
                         HubLogItem::Error(_) => TextBufferMessageLevel::Error,
                         HubLogItem::Warning(_) => TextBufferMessageLevel::Warning,
                         HubLogItem::Message(_) => TextBufferMessageLevel::Log,
+                        HubLogItem::Styled {level: HubLogLevel::Error, ..} => TextBufferMessageLevel::Error,
+                        HubLogItem::Styled {level: HubLogLevel::Warning, ..} => TextBufferMessageLevel::Warning,
+                        HubLogItem::Styled {level: HubLogLevel::Message, ..} => TextBufferMessageLevel::Log,
                     };
                     self.process_loc_message_for_textbuffers(cx, loc_message, level, storage)
                 }
@@ -204,7 +232,56 @@ impl BuildManager {
                 }
                 cx.send_signal(self.signal, BuildManager::status_cargo_end());
             },
-            HubMsg::ProgramEnd {uid} => if self.is_running_uid(uid) {
+            HubMsg::TestListResponse {..} | HubMsg::TestResult {..} | HubMsg::TestEnd {..} => {
+                if let Some(summary) = self.test_runner.handle_hub_msg(htc) {
+                    self.add_log_message(cx, summary);
+                }
+                cx.send_signal(self.signal, BuildManager::status_test_update());
+            },
+            HubMsg::SyncConflict {path, ..} => {
+                self.handle_log_item_limit(cx);
+                self.log_items.push(HubLogItem::Warning(format!("Sync conflict, {} was changed on the mirror and is left alone", path)));
+                cx.send_signal(self.signal, BuildManager::status_new_log_item());
+            },
+            HubMsg::DebugStopped {..} | HubMsg::DebugContinued {..} | HubMsg::DebugVariablesResponse {..} => {
+                self.debugger.handle_hub_msg(cx, storage, htc);
+                cx.send_signal(self.signal, BuildManager::status_debug_update());
+            },
+            HubMsg::LspDiagnostics {path, items, ..} => {
+                LanguageClient::set_diagnostics(cx, storage, path, items);
+            },
+            HubMsg::LspHoverResponse {uid, text} => if self.language_client.take_request(uid).is_some() {
+                self.language_client.hover = Some(if text.len() == 0 {"No information here".to_string()} else {text.clone()});
+                cx.send_signal(self.signal, BuildManager::status_language_update());
+            },
+            HubMsg::LspLocationsResponse {uid, locations} => match self.language_client.take_request(uid) {
+                Some((LanguageRequest::References, _)) => {
+                    self.handle_log_item_limit(cx);
+                    self.log_items.push(HubLogItem::Message(format!("{} references", locations.len())));
+                    for location in locations {
+                        self.log_items.push(HubLogItem::LocMessage(location.clone()));
+                    }
+                    cx.send_signal(self.signal, BuildManager::status_new_log_item());
+                },
+                Some(_) => if let Some(location) = locations.first() {
+                    self.language_client.jump_to = Some(location.clone());
+                    cx.send_signal(self.signal, BuildManager::status_language_update());
+                },
+                None => ()
+            },
+            HubMsg::LspCompletionResponse {uid, items} => if let Some((_, path)) = self.language_client.take_request(uid) {
+                LanguageClient::set_completions(cx, storage, &path, items);
+            },
+            HubMsg::TerminalListResponse {..} | HubMsg::TerminalOutput {..} | HubMsg::TerminalEnd {..} => {
+                if self.terminal.handle_hub_msg(storage, htc) {
+                    cx.send_signal(self.signal, BuildManager::status_terminal_update());
+                }
+            },
+            HubMsg::ProgramEnd {uid} => if self.debugger.is_running_uid(uid) {
+                self.debugger.handle_hub_msg(cx, storage, htc);
+                cx.send_signal(self.signal, BuildManager::status_debug_update());
+            }
+            else if self.is_running_uid(uid) {
                 // if we didnt have any errors, check if we need to run
                 for ab in &mut self.active_builds {
                     if ab.run_uid == Some(*uid) {
@@ -222,6 +299,12 @@ impl BuildManager {
         self.log_items.push(HubLogItem::Message(msg));
         cx.send_signal(self.signal, BuildManager::status_new_log_item());
     }
+    
+    pub fn add_log_loc_error(&mut self, cx:&mut Cx, loc_message:LocMessage){
+        self.handle_log_item_limit(cx);
+        self.log_items.push(HubLogItem::LocError(loc_message));
+        cx.send_signal(self.signal, BuildManager::status_new_log_item());
+    }
 
     pub fn handle_shader_recompile_event(&mut self, cx:&mut Cx, re:&ShaderRecompileEvent, storage:&mut AppStorage){
         // we are running in loopback mode
@@ -257,7 +340,8 @@ impl BuildManager {
                         body: err.msg.clone(),
                         range: Some((off, off+err.len)),
                         rendered:None,
-                        explanation:None
+                        explanation:None,
+                        fixes:Vec::new()
                     };
                     self.process_loc_message_for_textbuffers(cx, &msg, TextBufferMessageLevel::Error, storage);
                     self.log_items.push(HubLogItem::LocError(msg));
@@ -295,6 +379,21 @@ impl BuildManager {
         }
     }
     
+    // debugs the executable of the first build that has one
+    pub fn artifact_debug(&mut self, cx: &mut Cx, storage: &mut AppStorage) {
+        let executable = self.active_builds.iter().find_map( | ab | match &ab.build_result {
+            Some(BuildResult::Executable {path}) => Some((ab.build_target.builder.clone(), path.clone())),
+            _ => None
+        });
+        if let Some((builder, path)) = executable {
+            self.debugger.start(cx, storage, &builder, &path);
+            cx.send_signal(self.signal, BuildManager::status_debug_update());
+        }
+        else {
+            self.add_log_message(cx, "Nothing to debug, build a package with an executable first".to_string());
+        }
+    }
+    
     pub fn artifact_run(&mut self, storage: &mut AppStorage) {
         if self.is_any_cargo_running() {
             self.exec_when_done = true;
@@ -0,0 +1,245 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use crate::appstorage::*;
+
+#[derive(Clone)]
+pub struct DebugVariableItem {
+    pub depth: usize,
+    pub expanded: bool,
+    pub variable: HubVariable,
+}
+
+// a program running under the debug adapter of its builder, where it stopped and what
+// the variables of the selected frame are. the breakpoints live in the textbuffer markers
+#[derive(Clone)]
+pub struct Debugger {
+    pub uid: Option<HubUid>,
+    pub builder: String,
+    pub stopped: Option<String>,
+    pub frames: Vec<HubStackFrame>,
+    pub frame_index: usize,
+    pub variables: Vec<DebugVariableItem>,
+    // the editor jumps to the frame once after a stop
+    pub jump_to_frame: bool,
+    pub debug_row_path: Option<String>,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            uid: None,
+            builder: String::new(),
+            stopped: None,
+            frames: Vec::new(),
+            frame_index: 0,
+            variables: Vec::new(),
+            jump_to_frame: false,
+            debug_row_path: None,
+        }
+    }
+
+    pub fn is_running_uid(&self, uid: &HubUid) -> bool {
+        self.uid == Some(*uid)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.uid.is_some()
+    }
+
+    // the workspace/path a builder knows a file by, files of a synced workspace go to its mirror
+    fn builder_path(storage: &AppStorage, builder: &str, path: &str) -> Option<String> {
+        let prefix = format!("{}/", builder);
+        if let Some(rest) = path.strip_prefix(&prefix) {
+            return Some(rest.to_string())
+        }
+        for (key, sync_to) in &storage.settings.sync {
+            if !path.starts_with(key) {
+                continue
+            }
+            for sync in sync_to {
+                if let Some(rest) = sync.strip_prefix(&prefix) {
+                    return Some(format!("{}{}", rest, &path[key.len()..]))
+                }
+            }
+        }
+        None
+    }
+
+    fn breakpoints(storage: &AppStorage, builder: &str) -> Vec<HubBreakpoint> {
+        let mut breakpoints = Vec::new();
+        for (path, id) in &storage.text_buffer_path_to_id {
+            if let Some(builder_path) = Self::builder_path(storage, builder, path) {
+                for row in &storage.text_buffers[id.as_index()].text_buffer.markers.breakpoints {
+                    breakpoints.push(HubBreakpoint {path: builder_path.clone(), line: row + 1});
+                }
+            }
+        }
+        breakpoints
+    }
+
+    fn send(&self, storage: &mut AppStorage, msg: HubMsg) {
+        let hub_ui = storage.hub_ui.as_mut().unwrap();
+        hub_ui.route_send.send(ToHubMsg {
+            to: HubMsgTo::Builder(self.builder.clone()),
+            msg: msg
+        });
+    }
+
+    pub fn start(&mut self, cx: &mut Cx, storage: &mut AppStorage, builder: &str, path: &str) {
+        if let Some(uid) = self.uid {
+            self.send(storage, HubMsg::ProgramKill {uid: uid});
+        }
+        self.clear_stopped(cx, storage);
+        let uid = storage.hub_ui.as_mut().unwrap().route_send.alloc_uid();
+        self.uid = Some(uid);
+        self.builder = builder.to_string();
+        self.send(storage, HubMsg::DebugRun {
+            uid: uid,
+            path: path.to_string(),
+            args: Vec::new(),
+            breakpoints: Self::breakpoints(storage, builder)
+        });
+    }
+
+    pub fn command(&mut self, storage: &mut AppStorage, command: HubDebugCommand) {
+        if let Some(uid) = self.uid {
+            self.send(storage, HubMsg::DebugCommand {uid: uid, command: command});
+        }
+    }
+
+    // the breakpoints of a file changed in its editor
+    pub fn breakpoints_changed(&mut self, storage: &mut AppStorage, path: &str) {
+        let uid = if let Some(uid) = self.uid {uid} else {return};
+        let builder_path = if let Some(p) = Self::builder_path(storage, &self.builder, path) {p} else {return};
+        let lines = if let Some(id) = storage.text_buffer_path_to_id.get(path) {
+            storage.text_buffers[id.as_index()].text_buffer.markers.breakpoints.iter().map( | row | row + 1).collect()
+        }
+        else {
+            Vec::new()
+        };
+        self.send(storage, HubMsg::DebugSetBreakpoints {uid: uid, path: builder_path, lines: lines});
+    }
+
+    pub fn select_frame(&mut self, cx: &mut Cx, storage: &mut AppStorage, frame_index: usize) {
+        let uid = if let Some(uid) = self.uid {uid} else {return};
+        if frame_index >= self.frames.len() {
+            return
+        }
+        self.frame_index = frame_index;
+        self.variables.truncate(0);
+        self.set_debug_row(cx, storage);
+        self.send(storage, HubMsg::DebugVariablesRequest {uid: uid, frame_id: self.frames[frame_index].id, reference: 0});
+    }
+
+    // expands a variable with children, or folds it back up
+    pub fn toggle_variable(&mut self, storage: &mut AppStorage, index: usize) {
+        let uid = if let Some(uid) = self.uid {uid} else {return};
+        let item = if let Some(item) = self.variables.get_mut(index) {item} else {return};
+        if item.variable.reference == 0 {
+            return
+        }
+        if item.expanded {
+            item.expanded = false;
+            let depth = item.depth;
+            let end = (index + 1..self.variables.len()).find( | i | self.variables[*i].depth <= depth).unwrap_or(self.variables.len());
+            self.variables.drain(index + 1..end);
+        }
+        else if let Some(frame) = self.frames.get(self.frame_index) {
+            let msg = HubMsg::DebugVariablesRequest {uid: uid, frame_id: frame.id, reference: item.variable.reference};
+            self.send(storage, msg);
+        }
+    }
+
+    // where the editor should go for the selected frame
+    pub fn frame_loc_message(&self, cx: &mut Cx, storage: &mut AppStorage) -> Option<(LocMessage, usize)> {
+        let frame = self.frames.get(self.frame_index) ?;
+        let path = storage.remap_sync_path(frame.path.as_ref() ?);
+        let text_buffer = &storage.text_buffer_from_path(cx, &path).text_buffer;
+        let offset = text_buffer.text_pos_to_offset(TextPos {row: frame.line.max(1) - 1, col: frame.col.max(1) - 1});
+        Some((LocMessage {
+            path: path,
+            line: frame.line,
+            col: frame.col,
+            range: None,
+            body: format!("Stopped in {}, {}", frame.name, self.stopped.as_ref().map(String::as_str).unwrap_or("")),
+            rendered: None,
+            explanation: None,
+            fixes: Vec::new()
+        }, offset))
+    }
+
+    fn set_debug_row(&mut self, cx: &mut Cx, storage: &mut AppStorage) {
+        self.clear_debug_row(cx, storage);
+        let frame = if let Some(frame) = self.frames.get(self.frame_index) {frame} else {return};
+        if let Some(path) = &frame.path {
+            let path = storage.remap_sync_path(path);
+            let atb = storage.text_buffer_from_path(cx, &path);
+            atb.text_buffer.markers.debug_row = Some(frame.line.max(1) - 1);
+            cx.send_signal(atb.text_buffer.signal, TextBuffer::status_debug_update());
+            self.debug_row_path = Some(path);
+        }
+    }
+
+    fn clear_debug_row(&mut self, cx: &mut Cx, storage: &mut AppStorage) {
+        if let Some(path) = self.debug_row_path.take() {
+            let atb = storage.text_buffer_from_path(cx, &path);
+            atb.text_buffer.markers.debug_row = None;
+            cx.send_signal(atb.text_buffer.signal, TextBuffer::status_debug_update());
+        }
+    }
+
+    fn clear_stopped(&mut self, cx: &mut Cx, storage: &mut AppStorage) {
+        self.stopped = None;
+        self.frames.truncate(0);
+        self.frame_index = 0;
+        self.variables.truncate(0);
+        self.jump_to_frame = false;
+        self.clear_debug_row(cx, storage);
+    }
+
+    fn variable_items(variables: &[HubVariable], depth: usize) -> Vec<DebugVariableItem> {
+        variables.iter().map( | v | DebugVariableItem {depth: depth, expanded: false, variable: v.clone()}).collect()
+    }
+
+    pub fn handle_hub_msg(&mut self, cx: &mut Cx, storage: &mut AppStorage, htc: &FromHubMsg) {
+        match &htc.msg {
+            HubMsg::DebugStopped {uid, reason, frames, variables, ..} => if self.is_running_uid(uid) {
+                self.stopped = Some(reason.clone());
+                self.frames = frames.clone();
+                // the top frames can be in the standard library, start at the first one of ours
+                self.frame_index = frames.iter().position( | f | f.path.is_some()).unwrap_or(0);
+                self.variables = if self.frame_index == 0 {Self::variable_items(variables, 0)} else {Vec::new()};
+                self.jump_to_frame = true;
+                if self.frame_index != 0 {
+                    self.select_frame(cx, storage, self.frame_index);
+                }
+                else {
+                    self.set_debug_row(cx, storage);
+                }
+            },
+            HubMsg::DebugContinued {uid} => if self.is_running_uid(uid) {
+                self.clear_stopped(cx, storage);
+            },
+            HubMsg::DebugVariablesResponse {uid, frame_id, reference, variables} => if self.is_running_uid(uid) {
+                if self.frames.get(self.frame_index).map( | f | f.id) != Some(*frame_id) {
+                    return
+                }
+                if *reference == 0 {
+                    self.variables = Self::variable_items(variables, 0);
+                }
+                else if let Some(index) = self.variables.iter().position( | v | v.variable.reference == *reference && !v.expanded) {
+                    let item = &mut self.variables[index];
+                    item.expanded = true;
+                    let children = Self::variable_items(variables, item.depth + 1);
+                    self.variables.splice(index + 1..index + 1, children);
+                }
+            },
+            HubMsg::ProgramEnd {uid} => if self.is_running_uid(uid) {
+                self.uid = None;
+                self.clear_stopped(cx, storage);
+            },
+            _ => ()
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use crate::appstorage::*;
+use crate::buildmanager::*;
+use crate::codeicon::*;
+use crate::loglist::*;
+use crate::debugger::*;
+
+// the stack of a stopped program followed by the variables of the selected frame.
+// selecting a frame jumps to it, double clicking a variable expands it
+#[derive(Clone)]
+pub struct DebugPanel {
+    pub view: ScrollView,
+    pub item_draw: LogItemDraw,
+    pub list: ListLogic,
+}
+
+#[derive(Clone)]
+pub enum DebugPanelEvent {
+    SelectLocMessage {
+        loc_message: LocMessage,
+        jump_to_offset: usize
+    },
+    SelectMessages {
+        items: String
+    },
+    None,
+}
+
+impl DebugPanel {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            item_draw: LogItemDraw::new(cx),
+            list: ListLogic::default(),
+            view: ScrollView::new(cx),
+        }
+    }
+
+    fn frame_loc_event(cx: &mut Cx, storage: &mut AppStorage, bm: &BuildManager) -> DebugPanelEvent {
+        if let Some((loc_message, offset)) = bm.debugger.frame_loc_message(cx, storage) {
+            DebugPanelEvent::SelectLocMessage {
+                loc_message: loc_message,
+                jump_to_offset: offset
+            }
+        }
+        else {
+            DebugPanelEvent::None
+        }
+    }
+
+    pub fn handle_debug_panel(&mut self, cx: &mut Cx, event: &mut Event, storage: &mut AppStorage, bm: &mut BuildManager) -> DebugPanelEvent {
+        let frames_len = bm.debugger.frames.len();
+        self.list.set_list_len(frames_len + bm.debugger.variables.len());
+
+        self.list.handle_list_scroll_bars(cx, event, &mut self.view);
+
+        if let Event::Signal(se) = event {
+            if let Some(statusses) = se.signals.get(&bm.signal) {
+                self.view.redraw_view_area(cx);
+                if statusses.contains(&BuildManager::status_debug_update()) && bm.debugger.jump_to_frame {
+                    bm.debugger.jump_to_frame = false;
+                    return Self::frame_loc_event(cx, storage, bm)
+                }
+            }
+        }
+
+        let le = self.list.handle_list_logic(cx, event, ListSelect::None, false, | cx, item_event, item, item_index | match item_event {
+            ListLogicEvent::Animate(ae) => {
+                item.animator.calc_area(cx, item.animator.area, ae.time);
+            },
+            ListLogicEvent::AnimEnded => {
+                item.animator.end();
+            },
+            ListLogicEvent::Select => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, true));
+            },
+            ListLogicEvent::Deselect => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, false));
+            },
+            ListLogicEvent::Cleanup => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Over => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Out => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            }
+        });
+
+        match le {
+            ListEvent::SelectSingle(select_index) => {
+                self.view.redraw_view_area(cx);
+                if select_index < frames_len {
+                    bm.debugger.select_frame(cx, storage, select_index);
+                    Self::frame_loc_event(cx, storage, bm)
+                }
+                else {
+                    let variable = &bm.debugger.variables[select_index - frames_len].variable;
+                    DebugPanelEvent::SelectMessages {
+                        items: format!("{}: {} = {}", variable.name, variable.type_name, variable.value)
+                    }
+                }
+            },
+            ListEvent::SelectDouble(select_index) => {
+                if select_index >= frames_len {
+                    bm.debugger.toggle_variable(storage, select_index - frames_len);
+                    self.view.redraw_view_area(cx);
+                }
+                DebugPanelEvent::None
+            },
+            ListEvent::SelectMultiple | ListEvent::None => {
+                DebugPanelEvent::None
+            }
+        }
+    }
+
+    pub fn draw_frame_item(&mut self, cx: &mut Cx, index: usize, frame: &HubStackFrame, selected: bool) {
+        let draw = &mut self.item_draw;
+        let list_item = &mut self.list.list_items[index];
+        list_item.animator.init(cx, | cx | LogItemDraw::get_default_anim(cx, index, false));
+        draw.item_bg.color = list_item.animator.last_color(cx, Quad::color());
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+
+        if selected {
+            draw.code_icon.draw_icon(cx, CodeIconType::Warning);
+            cx.turtle_align_y();
+        }
+        draw.text.color = draw.message_color.get(cx);
+        draw.text.draw_text(cx, &frame.name);
+        draw.text.color = draw.path_color.get(cx);
+        if let Some(path) = &frame.path {
+            draw.text.draw_text(cx, &format!(" - {}:{}", path, frame.line));
+        }
+
+        let bg_area = draw.item_bg.end_quad(cx, bg_inst);
+        list_item.animator.set_area(cx, bg_area);
+    }
+
+    pub fn draw_variable_item(&mut self, cx: &mut Cx, index: usize, item: &DebugVariableItem) {
+        let draw = &mut self.item_draw;
+        let list_item = &mut self.list.list_items[index];
+        list_item.animator.init(cx, | cx | LogItemDraw::get_default_anim(cx, index, false));
+        draw.item_bg.color = list_item.animator.last_color(cx, Quad::color());
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+
+        let fold = if item.variable.reference == 0 {" "} else if item.expanded {"-"} else {"+"};
+        draw.text.color = draw.path_color.get(cx);
+        draw.text.draw_text(cx, &format!("{}{} ", "  ".repeat(item.depth), fold));
+        draw.text.color = draw.message_color.get(cx);
+        draw.text.draw_text(cx, &item.variable.name);
+        draw.text.color = draw.path_color.get(cx);
+        draw.text.draw_text(cx, &format!(" = {}", item.variable.value));
+
+        let bg_area = draw.item_bg.end_quad(cx, bg_inst);
+        list_item.animator.set_area(cx, bg_area);
+    }
+
+    pub fn draw_status_line(&mut self, cx: &mut Cx, counter: usize, bm: &BuildManager) {
+        let draw = &mut self.item_draw;
+        let debugger = &bm.debugger;
+        draw.item_bg.color = if counter & 1 == 0 {Theme::color_bg_selected().get(cx)}else {Theme::color_bg_odd().get(cx)};
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+        draw.text.color = draw.path_color.get(cx);
+        if let Some(reason) = &debugger.stopped {
+            draw.text.draw_text(cx, &format!("Stopped on {}, continue or step from the Run menu", reason));
+        }
+        else if debugger.is_running() {
+            draw.code_icon.draw_icon(cx, CodeIconType::Wait);
+            cx.turtle_align_y();
+            draw.text.draw_text(cx, "Running");
+        }
+        else {
+            draw.text.draw_text(cx, "Not debugging, use Run > Start Debugging. Breakpoints go on with a double click in the gutter or F9");
+        }
+        draw.item_bg.end_quad(cx, bg_inst);
+    }
+
+    pub fn draw_debug_panel(&mut self, cx: &mut Cx, bm: &BuildManager) {
+        let debugger = &bm.debugger;
+        let frames_len = debugger.frames.len();
+
+        self.list.set_list_len(frames_len + debugger.variables.len());
+
+        self.item_draw.text.text_style = LogItemDraw::text_style_item().get(cx);
+
+        let row_height = LogItemDraw::layout_item().get(cx).walk.height.fixed();
+
+        if self.list.begin_list(cx, &mut self.view, false, row_height).is_err() {return}
+
+        let mut counter = 0;
+        for i in self.list.start_item..self.list.end_item {
+            if i < frames_len {
+                self.draw_frame_item(cx, i, &debugger.frames[i], i == debugger.frame_index);
+            }
+            else {
+                self.draw_variable_item(cx, i, &debugger.variables[i - frames_len]);
+            }
+            counter += 1;
+        }
+
+        self.list.walk_turtle_to_end(cx, row_height);
+
+        self.draw_status_line(cx, counter, bm);
+        counter += 1;
+
+        for _ in (self.list.end_item + 1)..self.list.end_fill {
+            self.item_draw.draw_filler(cx, counter);
+            counter += 1;
+        }
+
+        self.item_draw.shadow.draw_shadow_left(cx);
+        self.item_draw.shadow.draw_shadow_top(cx);
+
+        self.list.end_list(cx, &mut self.view);
+    }
+}
@@ -85,7 +85,7 @@ impl ItemDisplay {
     }
     
     pub fn update_message_text_buffer(text_buffer: &mut TextBuffer, loc_message: &LocMessage) {
-        let text = if let Some(rendered) = &loc_message.rendered {
+        let mut text = if let Some(rendered) = &loc_message.rendered {
             if let Some(explanation) = &loc_message.explanation {
                 format!("{}{}{}", loc_message.body, rendered, explanation)
             }
@@ -96,6 +96,9 @@ impl ItemDisplay {
         else {
             loc_message.body.clone()
         };
+        if let Some(fix) = loc_message.fixes.first() {
+            text.push_str(&format!("\nquick fix, ctrl/cmd + . on the marker in the editor: {}\n", fix.label));
+        }
         
         text_buffer.load_from_utf8(&text);
         
@@ -0,0 +1,158 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use crate::appstorage::*;
+
+// keeps the textbuffers of the editors in sync with the language server of their builder,
+// and holds on to the answers until the window picks them up
+#[derive(Clone)]
+pub struct LanguageClient {
+    // the paths that went out with an LspOpen
+    pub open_paths: Vec<String>,
+    // what each request was for, and the path of the editor it came from
+    pub requests: Vec<(HubUid, LanguageRequest, String)>,
+    pub hover: Option<String>,
+    pub jump_to: Option<LocMessage>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LanguageRequest {
+    Hover,
+    Definition,
+    References,
+    Completion
+}
+
+impl LanguageClient {
+    pub fn new() -> LanguageClient {
+        LanguageClient {
+            open_paths: Vec::new(),
+            requests: Vec::new(),
+            hover: None,
+            jump_to: None,
+        }
+    }
+
+    // builder/workspace/path splits into the builder and the path it knows the file by
+    fn split_path(path: &str) -> Option<(&str, &str)> {
+        let pos = path.find('/') ?;
+        Some((&path[..pos], &path[pos + 1..]))
+    }
+
+    fn send(storage: &mut AppStorage, builder: &str, msg: HubMsg) {
+        if let Some(hub_ui) = storage.hub_ui.as_mut() {
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(builder.to_string()),
+                msg: msg
+            });
+        }
+    }
+
+    // opens the file with the server the first time, after that it sends what changed since
+    pub fn sync(&mut self, cx: &mut Cx, storage: &mut AppStorage, path: &str) {
+        if !path.ends_with(".rs") || storage.hub_ui.is_none() {
+            return
+        }
+        let (builder, builder_path) = if let Some(split) = Self::split_path(path) {split} else {return};
+        let atb = storage.text_buffer_from_path(cx, path);
+        if !atb.text_buffer.is_loaded {
+            return
+        }
+        let msg = if !self.open_paths.iter().any( | p | p == path) {
+            self.open_paths.push(path.to_string());
+            atb.text_buffer.edit_log = Some(Vec::new());
+            HubMsg::LspOpen {uid: HubUid::zero(), path: builder_path.to_string(), text: atb.text_buffer.get_as_string()}
+        }
+        else {
+            let edits = if let Some(edit_log) = &mut atb.text_buffer.edit_log {std::mem::replace(edit_log, Vec::new())} else {return};
+            if edits.len() == 0 {
+                return
+            }
+            HubMsg::LspChange {uid: HubUid::zero(), path: builder_path.to_string(), edits: edits.iter().map( | edit | LocEdit {
+                start_line: edit.start.row + 1,
+                start_col: edit.start.col + 1,
+                end_line: edit.end.row + 1,
+                end_col: edit.end.col + 1,
+                replacement: edit.replacement.clone()
+            }).collect()}
+        };
+        Self::send(storage, builder, msg);
+    }
+
+    pub fn request(&mut self, cx: &mut Cx, storage: &mut AppStorage, path: &str, request: LanguageRequest, pos: TextPos) {
+        self.sync(cx, storage, path);
+        if !self.open_paths.iter().any( | p | p == path) {
+            return
+        }
+        let (builder, builder_path) = if let Some(split) = Self::split_path(path) {split} else {return};
+        let uid = storage.hub_ui.as_mut().unwrap().route_send.alloc_uid();
+        let (path_s, line, col) = (builder_path.to_string(), pos.row + 1, pos.col + 1);
+        let msg = match request {
+            LanguageRequest::Hover => HubMsg::LspHoverRequest {uid: uid, path: path_s, line: line, col: col},
+            LanguageRequest::Definition => HubMsg::LspDefinitionRequest {uid: uid, path: path_s, line: line, col: col},
+            LanguageRequest::References => HubMsg::LspReferencesRequest {uid: uid, path: path_s, line: line, col: col},
+            LanguageRequest::Completion => HubMsg::LspCompletionRequest {uid: uid, path: path_s, line: line, col: col},
+        };
+        self.requests.push((uid, request, path.to_string()));
+        Self::send(storage, builder, msg);
+    }
+
+    pub fn take_request(&mut self, uid: &HubUid) -> Option<(LanguageRequest, String)> {
+        let index = self.requests.iter().position( | (u, _, _) | u == uid) ?;
+        let (_, request, path) = self.requests.remove(index);
+        Some((request, path))
+    }
+
+    // the diagnostics of a file replace the messages its textbuffer had, they only carry where
+    // they start so they mark the token there
+    pub fn set_diagnostics(cx: &mut Cx, storage: &mut AppStorage, path: &str, items: &[HubLogItem]) {
+        let atb = storage.text_buffer_from_path(cx, &storage.remap_sync_path(path));
+        let text_buffer = &mut atb.text_buffer;
+        let mut messages = Vec::new();
+        for item in items {
+            let loc_message = if let Some(loc_message) = item.get_loc_message() {loc_message} else {continue};
+            let head = text_buffer.text_pos_to_offset(TextPos {row: loc_message.line.max(1) - 1, col: loc_message.col.max(1) - 1});
+            let tail = text_buffer.token_chunks.iter()
+                .find( | chunk | head >= chunk.offset && head < chunk.offset + chunk.len)
+                .map( | chunk | chunk.offset + chunk.len)
+                .unwrap_or(head + 1);
+            let level = match item {
+                HubLogItem::LocError(_) => TextBufferMessageLevel::Error,
+                HubLogItem::LocWarning(_) => TextBufferMessageLevel::Warning,
+                _ => TextBufferMessageLevel::Log
+            };
+            messages.push((TextCursor {head: head, tail: tail, max: 0}, TextBufferMessage {
+                level: level,
+                body: loc_message.body.clone(),
+                fixes: Vec::new()
+            }));
+        }
+        messages.sort_by( | a, b | a.0.head.cmp(&b.0.head));
+        let markers = &mut text_buffer.markers;
+        markers.mutation_id = text_buffer.mutation_id.max(1);
+        markers.message_cursors = messages.iter().map( | (cursor, _) | cursor.clone()).collect();
+        markers.message_bodies = messages.into_iter().map( | (_, message) | message).collect();
+        cx.send_signal(text_buffer.signal, TextBuffer::status_message_update());
+    }
+
+    pub fn set_completions(cx: &mut Cx, storage: &mut AppStorage, path: &str, items: &[HubCompletion]) {
+        let atb = storage.text_buffer_from_path(cx, path);
+        let markers = &mut atb.text_buffer.markers;
+        markers.completion_index = 0;
+        markers.completions = items.iter().map( | item | TextBufferCompletion {
+            label: item.label.clone(),
+            detail: item.detail.clone(),
+            insert_text: item.insert_text.clone()
+        }).collect();
+        cx.send_signal(atb.text_buffer.signal, TextBuffer::status_completion_update());
+    }
+
+    // where to jump for a location, with the offset of its line and column
+    pub fn loc_message_offset(cx: &mut Cx, storage: &mut AppStorage, loc_message: &LocMessage) -> (LocMessage, usize) {
+        let mut loc_message = loc_message.clone();
+        loc_message.path = storage.remap_sync_path(&loc_message.path);
+        let text_buffer = &storage.text_buffer_from_path(cx, &loc_message.path).text_buffer;
+        let offset = text_buffer.text_pos_to_offset(TextPos {row: loc_message.line.max(1) - 1, col: loc_message.col.max(1) - 1});
+        (loc_message, offset)
+    }
+}
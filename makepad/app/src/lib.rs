@@ -12,6 +12,14 @@ pub mod buildmanager;
 pub mod makepadstyle;
 pub mod searchindex;
 pub mod searchresults;
+pub mod testrunner;
+pub mod testexplorer;
+pub mod packagelist;
+pub mod debugger;
+pub mod debugpanel;
+pub mod languageclient;
+pub mod terminal;
+pub mod terminalpanel;
 
 pub mod codeicon;
 pub mod rusteditor;
@@ -4,12 +4,62 @@ use makepad_hub::*;
 use crate::appstorage::*;
 use crate::buildmanager::*;
 use crate::codeicon::*;
+use crate::terminalpanel::*;
 
 #[derive(Clone)]
 pub struct LogList {
     pub view: ScrollView,
     pub item_draw: LogItemDraw,
     pub list: ListLogic,
+    pub filter_input: TextInput,
+    pub filter: LogFilter,
+    // the rows of the list, a log item or a frame of an expanded backtrace, and how far into
+    // the log we looked. with nothing filtered or expanded the rows are the log items
+    pub rows: Vec<(usize, Option<usize>)>,
+    pub scanned: usize,
+    pub expanded: Vec<usize>,
+}
+
+// what is typed in the filter box of the log tab. with "error:" or "warning:" in front it keeps
+// that level and the ones worse than it, the rest has to be in the item somewhere, any case
+#[derive(Clone, Default, PartialEq)]
+pub struct LogFilter {
+    pub level: Option<HubLogLevel>,
+    pub text: String,
+}
+
+impl LogFilter {
+    pub fn parse(value: &str) -> LogFilter {
+        let value = value.trim();
+        let lower = value.to_lowercase();
+        for (prefix, level) in &[("error:", HubLogLevel::Error), ("warning:", HubLogLevel::Warning)] {
+            if lower.starts_with(prefix) {
+                return LogFilter {level: Some(*level), text: lower[prefix.len()..].trim().to_string()}
+            }
+        }
+        LogFilter {level: None, text: lower}
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.level.is_none() && self.text.len() == 0
+    }
+    
+    pub fn matches(&self, item: &HubLogItem) -> bool {
+        if let Some(level) = self.level {
+            if item.level() > level {
+                return false
+            }
+        }
+        if self.text.len() == 0 {
+            return true
+        }
+        if let Some(loc_message) = item.get_loc_message() {
+            if loc_message.path.to_lowercase().contains(&self.text) {
+                return true
+            }
+        }
+        item.get_body().to_lowercase().contains(&self.text)
+    }
 }
 
 #[derive(Clone)]
@@ -77,17 +127,68 @@ impl LogItemDraw {
         self.text.draw_text(cx, &format!("{}:{} - ", path, row));
     } 
     
+    // items of more than one line, like a backtrace or the notes under an error, show the first
+    // line and how many more there are. the whole of it shows when the item is selected
+    fn first_line(body: &str) -> (&str, usize) {
+        let mut lines = body.trim_end_matches('\n').split('\n');
+        let mut first = lines.next().unwrap_or("");
+        if first.len() > 500 {
+            let mut end = 500;
+            while !first.is_char_boundary(end) {
+                end -= 1;
+            }
+            first = &first[0..end];
+        }
+        (first, lines.count())
+    }
+    
+    pub fn draw_more_lines(&mut self, cx: &mut Cx, more: usize) {
+        if more > 0 {
+            self.text.color = self.path_color.get(cx);
+            self.text.draw_text(cx, &format!(" (+{} lines)", more));
+        }
+    }
+    
     pub fn draw_log_body(&mut self, cx: &mut Cx, body: &str) {
+        let (line, more) = Self::first_line(body);
         self.text.color = self.message_color.get(cx);
-        if body.len()>500 {
-            self.text.draw_text(cx, &body[0..500]);
+        self.text.draw_text(cx, line);
+        self.draw_more_lines(cx, more);
+    }
+    
+    // the colors of a span like the terminal has them, backgrounds are left out in the list
+    fn span_color(&self, cx: &Cx, style: &TerminalStyle) -> Color {
+        let fg = match style.fg {
+            TerminalColor::Indexed(index) if style.bold && index < 8 => TerminalColor::Indexed(index + 8),
+            fg => fg
+        };
+        TerminalPanel::terminal_color(cx, (fg, self.message_color))
+    }
+    
+    pub fn draw_log_styled(&mut self, cx: &mut Cx, text: &HubStyledText) {
+        let (line, more) = Self::first_line(&text.body);
+        let mut pos = 0;
+        for span in &text.spans {
+            if span.start >= line.len() {
+                break
+            }
+            if span.start > pos {
+                self.text.color = self.message_color.get(cx);
+                self.text.draw_text(cx, &line[pos..span.start]);
+            }
+            let end = span.end.min(line.len());
+            self.text.color = self.span_color(cx, &span.style);
+            self.text.draw_text(cx, &line[span.start..end]);
+            pos = end;
         }
-        else {
-            self.text.draw_text(cx, &body);
+        if pos < line.len() {
+            self.text.color = self.message_color.get(cx);
+            self.text.draw_text(cx, &line[pos..]);
         }
+        self.draw_more_lines(cx, more);
     }
     
-    pub fn draw_log_item(&mut self, cx: &mut Cx, index: usize, list_item: &mut ListItem, log_item: &HubLogItem) {
+    pub fn draw_log_item(&mut self, cx: &mut Cx, index: usize, list_item: &mut ListItem, log_item: &HubLogItem, expanded: bool) {
         
         list_item.animator.init(cx, | cx | Self::get_default_anim(cx, index, false));
         
@@ -131,6 +232,27 @@ impl LogItemDraw {
             },
             HubLogItem::Message(msg) => {
                 self.draw_log_body(cx, &msg);
+            },
+            HubLogItem::Styled {level, text} => {
+                match level {
+                    HubLogLevel::Error => {self.code_icon.draw_icon(cx, CodeIconType::Error);},
+                    HubLogLevel::Warning => {self.code_icon.draw_icon(cx, CodeIconType::Warning);},
+                    HubLogLevel::Message => ()
+                }
+                cx.turtle_align_y();
+                self.draw_log_styled(cx, text);
+            },
+            HubLogItem::Backtrace {panic, frames} => {
+                self.code_icon.draw_icon(cx, CodeIconType::Panic);
+                cx.turtle_align_y();
+                if panic.path.len() > 0 {
+                    self.draw_log_path(cx, &panic.path, panic.line);
+                }
+                self.draw_log_body(cx, &panic.body);
+                if frames.len() > 0 {
+                    self.text.color = self.path_color.get(cx);
+                    self.text.draw_text(cx, &format!(" [{}] {} frames", if expanded {"-"} else {"+"}, frames.len()));
+                }
             }
         }
         
@@ -138,6 +260,23 @@ impl LogItemDraw {
         list_item.animator.set_area(cx, bg_area);
     }
     
+    // a frame under an expanded backtrace, the ones of our own code stand out
+    pub fn draw_frame(&mut self, cx: &mut Cx, index: usize, list_item: &mut ListItem, frame: &HubBacktraceFrame) {
+        list_item.animator.init(cx, | cx | Self::get_default_anim(cx, index, false));
+        self.item_bg.color = list_item.animator.last_color(cx, Quad::color());
+        let bg_inst = self.item_bg.begin_quad(cx, Self::layout_item().get(cx));
+        
+        self.text.color = if frame.user_code {self.message_color.get(cx)} else {self.path_color.get(cx)};
+        self.text.draw_text(cx, &format!("        {}", frame.function));
+        if frame.file.len() > 0 {
+            self.text.color = self.path_color.get(cx);
+            self.text.draw_text(cx, &format!(" - {}:{}", frame.path.as_ref().unwrap_or(&frame.file), frame.line));
+        }
+        
+        let bg_area = self.item_bg.end_quad(cx, bg_inst);
+        list_item.animator.set_area(cx, bg_area);
+    }
+    
     pub fn draw_status_line(&mut self, cx: &mut Cx, counter: usize, bm: &BuildManager) {
         // draw status line
         self.item_bg.color = if counter & 1 == 0 {Theme::color_bg_selected().get(cx)}else {Theme::color_bg_odd().get(cx)};
@@ -211,8 +350,125 @@ impl LogList {
                 ..ListLogic::default()
             },
             view: ScrollView::new(cx),
+            filter_input: TextInput::new(cx, TextInputOptions {multiline: false, read_only: false, empty_message: "filter".to_string()}),
+            filter: LogFilter::default(),
+            rows: Vec::new(),
+            scanned: 0,
+            expanded: Vec::new(),
         }
     }
+    
+    fn rows_are_items(&self) -> bool {
+        self.filter.is_empty() && self.expanded.len() == 0
+    }
+    
+    // brings the rows up to date with the log, it only grows at the end unless cleared
+    pub fn update_rows(&mut self, bm: &BuildManager) {
+        if bm.log_items.len() < self.scanned {
+            self.rows.truncate(0);
+            self.expanded.truncate(0);
+            self.scanned = 0;
+        }
+        if self.rows_are_items() {
+            return
+        }
+        for i in self.scanned..bm.log_items.len() {
+            let log_item = &bm.log_items[i];
+            if !self.filter.matches(log_item) {
+                continue
+            }
+            self.rows.push((i, None));
+            if let HubLogItem::Backtrace {frames, ..} = log_item {
+                if self.expanded.contains(&i) {
+                    for frame in 0..frames.len() {
+                        self.rows.push((i, Some(frame)));
+                    }
+                }
+            }
+        }
+        self.scanned = bm.log_items.len();
+    }
+    
+    pub fn list_len(&self, bm: &BuildManager) -> usize {
+        if self.rows_are_items() {bm.log_items.len()} else {self.rows.len()}
+    }
+    
+    // the index in the log of a row in the list, and the frame when it is one
+    pub fn row(&self, row: usize) -> (usize, Option<usize>) {
+        if self.rows_are_items() {(row, None)} else {self.rows[row]}
+    }
+    
+    fn reset_rows(&mut self, cx: &mut Cx) {
+        self.rows.truncate(0);
+        self.scanned = 0;
+        self.list.list_items.truncate(0);
+        self.list.selection.truncate(0);
+        self.view.redraw_view_area(cx);
+    }
+    
+    pub fn set_filter(&mut self, cx: &mut Cx, value: &str) {
+        let filter = LogFilter::parse(value);
+        if filter == self.filter {
+            return
+        }
+        self.filter = filter;
+        self.reset_rows(cx);
+    }
+    
+    pub fn toggle_expanded(&mut self, cx: &mut Cx, index: usize) {
+        if let Some(pos) = self.expanded.iter().position( | i | *i == index) {
+            self.expanded.remove(pos);
+        }
+        else {
+            self.expanded.push(index);
+        }
+        self.reset_rows(cx);
+    }
+    
+    // a frame as text, for the frames that are not in a workspace and for copying
+    fn frame_text(frame: &HubBacktraceFrame) -> String {
+        if frame.file.len() == 0 {
+            return format!("{}\n", frame.function)
+        }
+        format!("{}\n    at {}:{}:{}\n", frame.function, frame.file, frame.line, frame.col)
+    }
+    
+    fn frame_loc_message(frame: &HubBacktraceFrame) -> Option<LocMessage> {
+        Some(LocMessage {
+            path: frame.path.clone() ?,
+            line: frame.line,
+            col: frame.col,
+            body: frame.function.clone(),
+            range: None,
+            rendered: None,
+            explanation: None,
+            fixes: Vec::new(),
+        })
+    }
+    
+    pub fn handle_filter_input(&mut self, cx: &mut Cx, event: &mut Event) -> bool {
+        match self.filter_input.handle_text_input(cx, event) {
+            TextEditorEvent::KeyFocus => {
+                return true
+            },
+            TextEditorEvent::Change => {
+                let value = self.filter_input.get_value();
+                self.set_filter(cx, &value);
+                return true
+            },
+            TextEditorEvent::Escape | TextEditorEvent::Search(_) => {
+                cx.revert_key_focus();
+            },
+            _ => ()
+        }
+        return false
+    }
+    
+    pub fn draw_log_list_tab(&mut self, cx: &mut Cx) {
+        cx.begin_style(Self::style_text_input());
+        self.filter_input.draw_text_input(cx);
+        cx.end_style();
+    }
 
     pub fn style_text_input() -> StyleId {uid!()}
     
@@ -230,7 +486,8 @@ impl LogList {
     
     pub fn handle_log_list(&mut self, cx: &mut Cx, event: &mut Event, storage: &mut AppStorage, bm: &mut BuildManager) -> LogListEvent {
         
-        self.list.set_list_len(bm.log_items.len());
+        self.update_rows(bm);
+        self.list.set_list_len(self.list_len(bm));
         
         if self.list.handle_list_scroll_bars(cx, event, &mut self.view){
             bm.tail_log_items = false;
@@ -305,45 +562,58 @@ impl LogList {
         match le {
             ListEvent::SelectSingle(select_index) => {
                 self.view.redraw_view_area(cx);
-                let log_item = &bm.log_items[select_index];
-                if let Some(loc_message) = log_item.get_loc_message() {
-                    if loc_message.path.len() == 0 {
-                        return LogListEvent::SelectLocMessage {
-                            loc_message: loc_message.clone(),
-                            jump_to_offset: 0,
-                        }
+                let (index, frame) = self.row(select_index);
+                let log_item = &bm.log_items[index];
+                let loc_message = match (log_item, frame) {
+                    (HubLogItem::Backtrace {frames, ..}, Some(frame)) => if let Some(loc_message) = Self::frame_loc_message(&frames[frame]) {
+                        loc_message
                     }
-                    
-                    let text_buffer = &storage.text_buffer_from_path(cx, &storage.remap_sync_path(&loc_message.path)).text_buffer;
-                    // check if we have a range:
-                    let offset = if let Some((head, tail)) = loc_message.range {
-                        if select_at_end {
-                            head
-                        }
-                        else {
-                            tail
-                        }
+                    else {
+                        return LogListEvent::SelectMessages {items: Self::frame_text(&frames[frame])}
+                    },
+                    _ => if let Some(loc_message) = log_item.get_loc_message() {
+                        loc_message.clone()
                     }
                     else {
-                        text_buffer.text_pos_to_offset(TextPos {row: loc_message.line.max(1) - 1, col: loc_message.col.max(1) - 1})
-                    };
-                    
-                    LogListEvent::SelectLocMessage {
-                        loc_message: loc_message.clone(),
-                        jump_to_offset: offset
+                        return LogListEvent::SelectMessages {items: log_item.get_body().clone()}
+                    }
+                };
+                if loc_message.path.len() == 0 {
+                    return LogListEvent::SelectLocMessage {
+                        loc_message: loc_message,
+                        jump_to_offset: 0,
                     }
                 }
-                else {
-                    LogListEvent::SelectMessages {
-                        items: log_item.get_body().clone(),
+                
+                let text_buffer = &storage.text_buffer_from_path(cx, &storage.remap_sync_path(&loc_message.path)).text_buffer;
+                // check if we have a range:
+                let offset = if let Some((head, tail)) = loc_message.range {
+                    if select_at_end {
+                        head
+                    }
+                    else {
+                        tail
                     }
                 }
+                else {
+                    text_buffer.text_pos_to_offset(TextPos {row: loc_message.line.max(1) - 1, col: loc_message.col.max(1) - 1})
+                };
+                
+                LogListEvent::SelectLocMessage {
+                    loc_message: loc_message,
+                    jump_to_offset: offset
+                }
             },
             ListEvent::SelectMultiple => {
                 self.view.redraw_view_area(cx);
                 let mut items = String::new();
                 for select in &self.list.selection {
-                    if let Some(loc_message) = bm.log_items[*select].get_loc_message() {
+                    let (index, frame) = self.row(*select);
+                    let log_item = &bm.log_items[index];
+                    if let (HubLogItem::Backtrace {frames, ..}, Some(frame)) = (log_item, frame) {
+                        items.push_str(&Self::frame_text(&frames[frame]));
+                    }
+                    else if let Some(loc_message) = log_item.get_loc_message() {
                         if let Some(rendered) = &loc_message.rendered {
                             items.push_str(rendered);
                             if items.len()>1000000 { // safety break
@@ -352,7 +622,7 @@ impl LogList {
                         }
                     }
                     else {
-                        items.push_str(bm.log_items[*select].get_body());
+                        items.push_str(log_item.get_body());
                         if items.len()>1000000 { // safety break
                             break;
                         }
@@ -363,7 +633,16 @@ impl LogList {
                     items: items,
                 }
             },
-            ListEvent::SelectDouble(_) | ListEvent::None => {
+            // a double click opens or closes the frames of a backtrace
+            ListEvent::SelectDouble(select_index) => {
+                if let (index, None) = self.row(select_index) {
+                    if let HubLogItem::Backtrace {..} = &bm.log_items[index] {
+                        self.toggle_expanded(cx, index);
+                    }
+                }
+                LogListEvent::None
+            },
+            ListEvent::None => {
                 LogListEvent::None
             }
         }
@@ -371,7 +650,8 @@ impl LogList {
     
     pub fn draw_log_list(&mut self, cx: &mut Cx, bm: &BuildManager) {
         
-        self.list.set_list_len(bm.log_items.len());
+        self.update_rows(bm);
+        self.list.set_list_len(self.list_len(bm));
         
         self.item_draw.text.text_style = LogItemDraw::text_style_item().get(cx);
         
@@ -381,7 +661,16 @@ impl LogList {
         
         let mut counter = 0;
         for i in self.list.start_item..self.list.end_item {
-            self.item_draw.draw_log_item(cx, i, &mut self.list.list_items[i], &bm.log_items[i]);
+            let (index, frame) = self.row(i);
+            match (&bm.log_items[index], frame) {
+                (HubLogItem::Backtrace {frames, ..}, Some(frame)) => {
+                    self.item_draw.draw_frame(cx, i, &mut self.list.list_items[i], &frames[frame]);
+                },
+                (log_item, _) => {
+                    let expanded = self.expanded.contains(&index);
+                    self.item_draw.draw_log_item(cx, i, &mut self.list.list_items[i], log_item, expanded);
+                }
+            }
             counter += 1;
         }
         
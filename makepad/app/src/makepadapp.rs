@@ -2,6 +2,7 @@
 use makepad_render::*;
 use makepad_widget::*;
 use makepad_microserde::*;
+use makepad_hub::*;
 use crate::appwindow::*;
 use crate::appstorage::*;
 use crate::filetree::*;
@@ -39,6 +40,19 @@ impl MakepadApp {
     pub fn command_toggle_block_comment() -> CommandId {uid!()}
     pub fn command_start_program() -> CommandId {uid!()}
     pub fn command_stop_program() -> CommandId {uid!()}
+    pub fn command_connect_discovered_builders() -> CommandId {uid!()}
+    pub fn command_sync_workspaces() -> CommandId {uid!()}
+    pub fn command_discover_tests() -> CommandId {uid!()}
+    pub fn command_run_all_tests() -> CommandId {uid!()}
+    pub fn command_rerun_failed_tests() -> CommandId {uid!()}
+    pub fn command_start_debugging() -> CommandId {uid!()}
+    pub fn command_stop_debugging() -> CommandId {uid!()}
+    pub fn command_debug_continue() -> CommandId {uid!()}
+    pub fn command_debug_pause() -> CommandId {uid!()}
+    pub fn command_debug_step_over() -> CommandId {uid!()}
+    pub fn command_debug_step_in() -> CommandId {uid!()}
+    pub fn command_debug_step_out() -> CommandId {uid!()}
+    pub fn command_new_terminal() -> CommandId {uid!()}
     pub fn command_bring_all_to_front() -> CommandId {uid!()}
     
     pub fn new(cx: &mut Cx) -> Self {
@@ -106,6 +120,23 @@ impl MakepadApp {
                 Menu::sub("Run", vec![
                     Menu::item("Start Program", Self::command_start_program()),
                     Menu::item("Stop Program", Self::command_stop_program()),
+                    Menu::line(),
+                    Menu::item("Connect Discovered Builders", Self::command_connect_discovered_builders()),
+                    Menu::item("Sync Workspaces", Self::command_sync_workspaces()),
+                    Menu::line(),
+                    Menu::item("Discover Tests", Self::command_discover_tests()),
+                    Menu::item("Run All Tests", Self::command_run_all_tests()),
+                    Menu::item("Rerun Failed Tests", Self::command_rerun_failed_tests()),
+                    Menu::line(),
+                    Menu::item("Start Debugging", Self::command_start_debugging()),
+                    Menu::item("Stop Debugging", Self::command_stop_debugging()),
+                    Menu::item("Continue", Self::command_debug_continue()),
+                    Menu::item("Pause", Self::command_debug_pause()),
+                    Menu::item("Step Over", Self::command_debug_step_over()),
+                    Menu::item("Step In", Self::command_debug_step_in()),
+                    Menu::item("Step Out", Self::command_debug_step_out()),
+                    Menu::line(),
+                    Menu::item("New Terminal", Self::command_new_terminal()),
                 ]),
                 Menu::sub("Window", vec![
                     Menu::item("Minimize", Cx::command_minimize()),
@@ -193,6 +224,26 @@ impl MakepadApp {
                                         title: "Log".to_string(),
                                         item: Panel::LogList
                                     },
+                                    DockTab {
+                                        closeable: false,
+                                        title: "Tests".to_string(),
+                                        item: Panel::TestExplorer
+                                    },
+                                    DockTab {
+                                        closeable: false,
+                                        title: "Packages".to_string(),
+                                        item: Panel::PackageList
+                                    },
+                                    DockTab {
+                                        closeable: false,
+                                        title: "Debug".to_string(),
+                                        item: Panel::DebugPanel
+                                    },
+                                    DockTab {
+                                        closeable: false,
+                                        title: "Terminal".to_string(),
+                                        item: Panel::Terminal
+                                    },
                                 ]
                             }),
                             last: Box::new(DockItem::TabControl {
@@ -229,6 +280,16 @@ impl MakepadApp {
         cx.redraw_child_area(Area::All);
     }
     
+    fn debug_command(command: CommandId) -> Option<HubDebugCommand> {
+        if command == Self::command_stop_debugging() {Some(HubDebugCommand::Stop)}
+        else if command == Self::command_debug_continue() {Some(HubDebugCommand::Continue)}
+        else if command == Self::command_debug_pause() {Some(HubDebugCommand::Pause)}
+        else if command == Self::command_debug_step_over() {Some(HubDebugCommand::StepOver)}
+        else if command == Self::command_debug_step_in() {Some(HubDebugCommand::StepIn)}
+        else if command == Self::command_debug_step_out() {Some(HubDebugCommand::StepOut)}
+        else {None}
+    }
+    
     pub fn reload_style(&mut self, cx: &mut Cx) {
         set_widget_style(cx, &self.storage.settings.style_options);
         set_makepad_style(cx, &self.storage.settings.style_options);
@@ -286,19 +347,71 @@ impl MakepadApp {
                         }
                     }
                 }
-                if let Some(_statusses) = se.signals.get(&self.storage.settings_changed) {
-                    if self.storage.settings_old.builders != self.storage.settings.builders {
-                        self.storage.reload_builders();
+                if let Some(_) = se.signals.get(&self.storage.hub_discovery_changed) {
+                    for discovered in self.storage.update_discovered_builders() {
+                        let msg = if discovered.same_key {
+                            format!("Found builder {} at {} with workspaces [{}], connect it with Run > Connect Discovered Builders", discovered.builder, discovered.addr.ip(), discovered.workspaces.join(", "))
+                        }
+                        else {
+                            format!("Found builder {} at {}, it has another key.ron ({}), give it ours to connect it", discovered.builder, discovered.addr.ip(), discovered.fingerprint)
+                        };
+                        self.build_manager.add_log_message(cx, msg);
                     }
-                    if self.storage.settings_old.style_options != self.storage.settings.style_options {
-                        self.reload_style(cx);
-                        cx.reset_font_atlas_and_redraw();
+                }
+                if let Some(statusses) = se.signals.get(&self.storage.settings_changed) {
+                    if statusses.contains(&AppStorage::status_settings_error()) {
+                        if let Some(loc_message) = self.storage.settings_error.take() {
+                            self.build_manager.add_log_loc_error(cx, loc_message);
+                        }
                     }
-                    if self.storage.settings_old.builds != self.storage.settings.builds {
-                        self.build_manager.restart_build(cx, &mut self.storage);
+                    if statusses.contains(&AppStorage::status_settings_changed()) {
+                        if self.storage.settings_old.builders != self.storage.settings.builders {
+                            self.storage.reload_builders();
+                        }
+                        if self.storage.settings_old.style_options != self.storage.settings.style_options {
+                            self.reload_style(cx);
+                            cx.reset_font_atlas_and_redraw();
+                        }
+                        if self.storage.settings_old.builds != self.storage.settings.builds {
+                            self.build_manager.restart_build(cx, &mut self.storage);
+                        }
                     }
                 }
             },
+            Event::Command(command) => {
+                if *command == Self::command_connect_discovered_builders() {
+                    let invited = self.storage.connect_discovered_builders(cx);
+                    let msg = if invited.len() == 0 {
+                        "No discovered builders to connect, they need our key.ron and the hub server on the network".to_string()
+                    }
+                    else {
+                        format!("Invited builders {} to connect", invited.join(", "))
+                    };
+                    self.build_manager.add_log_message(cx, msg);
+                }
+                else if *command == Self::command_sync_workspaces() {
+                    self.storage.start_sync();
+                }
+                else if *command == Self::command_discover_tests() {
+                    self.build_manager.test_runner.discover(&mut self.storage);
+                }
+                else if *command == Self::command_run_all_tests() {
+                    self.build_manager.test_runner.run_all(&mut self.storage);
+                }
+                else if *command == Self::command_rerun_failed_tests() {
+                    self.build_manager.test_runner.rerun_failed(&mut self.storage);
+                }
+                else if *command == Self::command_start_debugging() {
+                    self.build_manager.artifact_debug(cx, &mut self.storage);
+                }
+                else if let Some(debug_command) = Self::debug_command(*command) {
+                    self.build_manager.debugger.command(&mut self.storage, debug_command);
+                }
+                else if *command == Self::command_new_terminal() {
+                    self.build_manager.terminal.start(&mut self.storage);
+                    cx.send_signal(self.build_manager.signal, BuildManager::status_terminal_update());
+                }
+            },
             Event::ShaderRecompile(re) => {
                 self.build_manager.handle_shader_recompile_event(cx, re, &mut self.storage);
             },
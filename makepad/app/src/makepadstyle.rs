@@ -7,6 +7,7 @@ use crate::codeicon::*;
 use crate::searchresults::*;
 use crate::itemdisplay::*;
 use crate::livemacro::*;
+use crate::terminalpanel::*;
 
 pub fn set_makepad_style(cx: &mut Cx, opt:&StyleOptions) {
     CodeIcon::style(cx, opt);
@@ -18,4 +19,5 @@ pub fn set_makepad_style(cx: &mut Cx, opt:&StyleOptions) {
     ColorPicker::style(cx, opt);
     FloatSlider::style(cx, opt);
     LiveMacrosView::style(cx, opt);
+    TerminalPanel::style(cx, opt);
 }
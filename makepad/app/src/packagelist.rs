@@ -0,0 +1,180 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use makepad_microserde::*;
+use crate::appstorage::*;
+use crate::buildmanager::*;
+use crate::codeicon::*;
+use crate::loglist::*;
+
+// the packages of all builders, a row for each build profile they have. double clicking
+// one builds the package with it, or stops building it
+#[derive(Clone)]
+pub struct PackageList {
+    pub view: ScrollView,
+    pub item_draw: LogItemDraw,
+    pub list: ListLogic,
+}
+
+#[derive(Clone)]
+pub enum PackageListEvent {
+    SelectMessages {
+        items: String
+    },
+    None,
+}
+
+impl PackageList {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            item_draw: LogItemDraw::new(cx),
+            list: ListLogic::default(),
+            view: ScrollView::new(cx),
+        }
+    }
+
+    fn rows(storage: &AppStorage) -> Vec<(BuildTarget, &HubBuildProfile)> {
+        let mut rows = Vec::new();
+        for (builder, package) in &storage.packages {
+            for profile in &package.profiles {
+                rows.push((BuildTarget {
+                    builder: builder.clone(),
+                    workspace: package.project.clone(),
+                    package: package.package_name.clone(),
+                    config: profile.name.clone()
+                }, profile));
+            }
+        }
+        rows
+    }
+
+    pub fn handle_package_list(&mut self, cx: &mut Cx, event: &mut Event, storage: &mut AppStorage, bm: &mut BuildManager) -> PackageListEvent {
+
+        let rows: Vec<(BuildTarget, HubBuildProfile)> = Self::rows(storage).into_iter().map( | (bt, p) | (bt, p.clone())).collect();
+        self.list.set_list_len(rows.len());
+
+        self.list.handle_list_scroll_bars(cx, event, &mut self.view);
+
+        let le = self.list.handle_list_logic(cx, event, ListSelect::None, false, | cx, item_event, item, item_index | match item_event {
+            ListLogicEvent::Animate(ae) => {
+                item.animator.calc_area(cx, item.animator.area, ae.time);
+            },
+            ListLogicEvent::AnimEnded => {
+                item.animator.end();
+            },
+            ListLogicEvent::Select => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, true));
+            },
+            ListLogicEvent::Deselect => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, false));
+            },
+            ListLogicEvent::Cleanup => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Over => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Out => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            }
+        });
+
+        match le {
+            ListEvent::SelectSingle(select_index) => {
+                self.view.redraw_view_area(cx);
+                PackageListEvent::SelectMessages {
+                    items: rows[select_index].1.serialize_ron()
+                }
+            },
+            ListEvent::SelectDouble(select_index) => {
+                // a package is built with one profile at a time
+                let target = rows[select_index].0.clone();
+                let builds = &mut storage.settings.builds;
+                if let Some(pos) = builds.iter().position( | bt | *bt == target) {
+                    builds.remove(pos);
+                }
+                else {
+                    builds.retain( | bt | bt.builder != target.builder || bt.workspace != target.workspace || bt.package != target.package);
+                    builds.push(target);
+                }
+                storage.save_settings(cx);
+                bm.restart_build(cx, storage);
+                self.view.redraw_view_area(cx);
+                PackageListEvent::None
+            },
+            ListEvent::SelectMultiple | ListEvent::None => {
+                PackageListEvent::None
+            }
+        }
+    }
+
+    pub fn draw_package_item(&mut self, cx: &mut Cx, index: usize, target: &BuildTarget, profile: &HubBuildProfile, selected: bool) {
+        let draw = &mut self.item_draw;
+        let list_item = &mut self.list.list_items[index];
+        list_item.animator.init(cx, | cx | LogItemDraw::get_default_anim(cx, index, false));
+        draw.item_bg.color = list_item.animator.last_color(cx, Quad::color());
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+
+        if selected {
+            draw.code_icon.draw_icon(cx, CodeIconType::Ok);
+            cx.turtle_align_y();
+        }
+        draw.text.color = draw.path_color.get(cx);
+        draw.text.draw_text(cx, &format!("{}/{}/{} - ", target.builder, target.workspace, target.package));
+        draw.text.color = draw.message_color.get(cx);
+        draw.text.draw_text(cx, &target.config);
+        draw.text.color = draw.path_color.get(cx);
+        draw.text.draw_text(cx, &format!(" cargo {}", profile.cargo_args(&target.package).join(" ")));
+
+        let bg_area = draw.item_bg.end_quad(cx, bg_inst);
+        list_item.animator.set_area(cx, bg_area);
+    }
+
+    pub fn draw_status_line(&mut self, cx: &mut Cx, counter: usize, storage: &AppStorage) {
+        let draw = &mut self.item_draw;
+        draw.item_bg.color = if counter & 1 == 0 {Theme::color_bg_selected().get(cx)}else {Theme::color_bg_odd().get(cx)};
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+        draw.text.color = draw.path_color.get(cx);
+        if storage.packages.len() == 0 {
+            draw.text.draw_text(cx, "No packages, the builders have none or haven't answered yet");
+        }
+        else {
+            draw.text.draw_text(cx, "Double click a profile to build its package with it, the profiles are in the builders of makepad_settings.ron");
+        }
+        draw.item_bg.end_quad(cx, bg_inst);
+    }
+
+    pub fn draw_package_list(&mut self, cx: &mut Cx, storage: &AppStorage) {
+        let rows = Self::rows(storage);
+
+        self.list.set_list_len(rows.len());
+
+        self.item_draw.text.text_style = LogItemDraw::text_style_item().get(cx);
+
+        let row_height = LogItemDraw::layout_item().get(cx).walk.height.fixed();
+
+        if self.list.begin_list(cx, &mut self.view, false, row_height).is_err() {return}
+
+        let mut counter = 0;
+        for i in self.list.start_item..self.list.end_item {
+            let (target, profile) = &rows[i];
+            self.draw_package_item(cx, i, target, profile, storage.settings.builds.contains(target));
+            counter += 1;
+        }
+
+        self.list.walk_turtle_to_end(cx, row_height);
+
+        self.draw_status_line(cx, counter, storage);
+        counter += 1;
+
+        for _ in (self.list.end_item + 1)..self.list.end_fill {
+            self.item_draw.draw_filler(cx, counter);
+            counter += 1;
+        }
+
+        self.item_draw.shadow.draw_shadow_left(cx);
+        self.item_draw.shadow.draw_shadow_top(cx);
+
+        self.list.end_list(cx, &mut self.view);
+    }
+}
@@ -0,0 +1,167 @@
+use makepad_hub::*;
+use crate::appstorage::*;
+
+// the shell the terminal panel shows. it runs on a pty in the builder and outlives the app,
+// so when we connect again we attach to the one that is still there instead of starting one
+#[derive(Clone)]
+pub struct Terminal {
+    pub uid: Option<HubUid>,
+    pub builder: String,
+    pub screen: TerminalScreen,
+    // the list requests out to the builders, to find a terminal we can attach to
+    pub list_requests: Vec<(HubUid, String)>,
+    // the panel scrolls to the bottom when something new is written
+    pub tail: bool,
+    // the replay of an attach has the questions of programs in it that were answered before
+    pub replaying: bool,
+}
+
+impl Terminal {
+    pub fn new() -> Terminal {
+        let mut screen = TerminalScreen::new(80, 24);
+        screen.feed(b"Press Return to start a shell\r\n");
+        Terminal {
+            uid: None,
+            builder: String::new(),
+            screen: screen,
+            list_requests: Vec::new(),
+            tail: true,
+            replaying: false,
+        }
+    }
+
+    pub fn is_running_uid(&self, uid: &HubUid) -> bool {
+        self.uid == Some(*uid)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.uid.is_some()
+    }
+
+    fn send(storage: &mut AppStorage, builder: &str, msg: HubMsg) {
+        if let Some(hub_ui) = storage.hub_ui.as_mut() {
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(builder.to_string()),
+                msg: msg
+            });
+        }
+    }
+
+    // the main builder if there is one, and the first of its workspaces
+    fn default_workspace(storage: &AppStorage) -> Option<(String, String)> {
+        let mut builders: Vec<&String> = storage.settings.builders.keys().collect();
+        builders.sort_by_key( | builder | (*builder != "main", (*builder).clone()));
+        for builder in builders {
+            let mut workspaces: Vec<&String> = storage.settings.builders[builder].workspaces.keys().collect();
+            workspaces.sort();
+            if let Some(workspace) = workspaces.first() {
+                return Some((builder.clone(), workspace.to_string()))
+            }
+        }
+        None
+    }
+
+    // asks each builder for the terminals it has, the first one that answers gets attached
+    pub fn find_running(&mut self, storage: &mut AppStorage) {
+        if self.uid.is_some() {
+            return
+        }
+        let builders: Vec<String> = storage.settings.builders.keys().cloned().collect();
+        for builder in builders {
+            let uid = if let Some(hub_ui) = storage.hub_ui.as_mut() {hub_ui.route_send.alloc_uid()} else {return};
+            self.list_requests.push((uid, builder.clone()));
+            Self::send(storage, &builder, HubMsg::TerminalListRequest {uid: uid});
+        }
+    }
+
+    pub fn start(&mut self, storage: &mut AppStorage) {
+        self.kill(storage);
+        let (builder, workspace) = if let Some(ws) = Self::default_workspace(storage) {ws} else {
+            self.screen.feed(b"No builder with a workspace to start a shell in\r\n");
+            return
+        };
+        let uid = if let Some(hub_ui) = storage.hub_ui.as_mut() {hub_ui.route_send.alloc_uid()} else {return};
+        self.screen = TerminalScreen::new(self.screen.cols, self.screen.rows);
+        self.uid = Some(uid);
+        self.builder = builder.clone();
+        self.tail = true;
+        Self::send(storage, &builder, HubMsg::TerminalOpen {
+            uid: uid,
+            workspace: workspace,
+            command: Vec::new(),
+            cols: self.screen.cols,
+            rows: self.screen.rows
+        });
+    }
+
+    pub fn kill(&mut self, storage: &mut AppStorage) {
+        if let Some(uid) = self.uid.take() {
+            let builder = self.builder.clone();
+            Self::send(storage, &builder, HubMsg::TerminalKill {uid: uid});
+        }
+    }
+
+    pub fn input(&mut self, storage: &mut AppStorage, data: Vec<u8>) {
+        if let Some(uid) = self.uid {
+            self.tail = true;
+            let builder = self.builder.clone();
+            Self::send(storage, &builder, HubMsg::TerminalInput {uid: uid, data: data});
+        }
+    }
+
+    // the size of the panel in cells, the program on the pty hears about it with a SIGWINCH
+    pub fn resize(&mut self, storage: &mut AppStorage, cols: usize, rows: usize) {
+        if cols == self.screen.cols && rows == self.screen.rows {
+            return
+        }
+        self.screen.resize(cols, rows);
+        if let Some(uid) = self.uid {
+            let builder = self.builder.clone();
+            Self::send(storage, &builder, HubMsg::TerminalResize {uid: uid, cols: self.screen.cols, rows: self.screen.rows});
+        }
+    }
+
+    // returns true when the screen changed
+    pub fn handle_hub_msg(&mut self, storage: &mut AppStorage, htc: &FromHubMsg) -> bool {
+        match &htc.msg {
+            HubMsg::TerminalListResponse {uid, terminals} => {
+                let index = if let Some(index) = self.list_requests.iter().position( | (u, _) | u == uid) {index} else {return false};
+                let (_, builder) = self.list_requests.remove(index);
+                if self.uid.is_some() {
+                    return false
+                }
+                if let Some(terminal) = terminals.first() {
+                    self.list_requests.truncate(0);
+                    self.uid = Some(terminal.uid);
+                    self.builder = builder.clone();
+                    // the replay draws the screen again from the start, at our size
+                    self.screen = TerminalScreen::new(self.screen.cols, self.screen.rows);
+                    self.replaying = true;
+                    Self::send(storage, &builder, HubMsg::TerminalResize {uid: terminal.uid, cols: self.screen.cols, rows: self.screen.rows});
+                    Self::send(storage, &builder, HubMsg::TerminalAttach {uid: terminal.uid});
+                }
+                false
+            },
+            HubMsg::TerminalOutput {uid, data} => if self.is_running_uid(uid) {
+                self.screen.feed(data);
+                let responses = self.screen.take_responses();
+                if responses.len() > 0 && !self.replaying {
+                    let builder = self.builder.clone();
+                    Self::send(storage, &builder, HubMsg::TerminalInput {uid: *uid, data: responses});
+                }
+                self.replaying = false;
+                true
+            } else {false},
+            HubMsg::TerminalEnd {uid, code} => if self.is_running_uid(uid) {
+                self.uid = None;
+                let status = match code {
+                    Some(code) => format!("\r\n[process exited with code {}, press Return for a new shell]\r\n", code),
+                    None => "\r\n[process ended, press Return for a new shell]\r\n".to_string()
+                };
+                self.screen.feed(status.as_bytes());
+                true
+            } else {false},
+            _ => false
+        }
+    }
+}
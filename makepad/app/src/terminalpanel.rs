@@ -0,0 +1,337 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use crate::appstorage::*;
+use crate::buildmanager::*;
+
+// draws the screen of the terminal of the buildmanager, scrollback included, and sends it
+// the keys. it sizes the terminal to the panel, a drag selects text to copy
+#[derive(Clone)]
+pub struct TerminalPanel {
+    pub view: ScrollView,
+    pub bg: Quad,
+    pub cell_bg: Quad,
+    pub selection: Quad,
+    pub cursor: Quad,
+    pub text: Text,
+    pub shadow: ScrollShadow,
+    pub cell_size: Vec2,
+    pub max_scroll: f32,
+    // line and column of where the drag started and where it is now
+    pub select: Option<((usize, usize), (usize, usize))>,
+}
+
+impl TerminalPanel {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            view: ScrollView::new(cx),
+            bg: Quad::new(cx),
+            cell_bg: Quad::new(cx),
+            selection: Quad::new(cx),
+            cursor: Quad {
+                z: 1.0,
+                ..Quad::new(cx)
+            },
+            text: Text::new(cx),
+            shadow: ScrollShadow {z: 0.01, ..ScrollShadow::new(cx)},
+            cell_size: Vec2 {x: 7., y: 12.},
+            max_scroll: 0.,
+            select: None,
+        }
+    }
+
+    pub fn text_style_terminal() -> TextStyleId {uid!()}
+    pub fn color_bg() -> ColorId {uid!()}
+    pub fn color_text() -> ColorId {uid!()}
+    pub fn color_cursor() -> ColorId {uid!()}
+    pub fn color_selection() -> ColorId {uid!()}
+    pub fn padding() -> FloatId {uid!()}
+
+    pub fn style(cx: &mut Cx, opt: &StyleOptions) {
+        Self::text_style_terminal().set(cx, Theme::text_style_fixed().get(cx));
+        Self::color_bg().set(cx, Theme::color_bg_normal().get(cx));
+        Self::color_text().set(cx, Theme::color_text_focus().get(cx));
+        Self::color_cursor().set(cx, pick!(#b0b0b0).get(cx));
+        Self::color_selection().set(cx, pick!(#294e75).get(cx));
+        Self::padding().set(cx, 4. * opt.scale);
+    }
+
+    // the line of the screen and scrollback, and the column, under a point of the window
+    fn cell_at(&self, cx: &Cx, abs: Vec2) -> (usize, usize) {
+        let rect = self.view.view.get_rect(cx);
+        let scroll = self.view.get_scroll_pos(cx);
+        let padding = Self::padding().get(cx);
+        let line = ((abs.y - rect.y - padding + scroll.y) / self.cell_size.y).max(0.).floor() as usize;
+        let col = ((abs.x - rect.x - padding + scroll.x) / self.cell_size.x).max(0.).round() as usize;
+        (line, col)
+    }
+
+    fn selection_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (a, b) = self.select ?;
+        if a == b {
+            return None
+        }
+        Some(if a < b {(a, b)} else {(b, a)})
+    }
+
+    pub fn selected_text(&self, screen: &TerminalScreen) -> String {
+        let ((start_line, start_col), (end_line, end_col)) = if let Some(range) = self.selection_range() {range} else {return String::new()};
+        let mut lines = Vec::new();
+        for index in start_line..=end_line.min(screen.total_lines().max(1) - 1) {
+            let line = screen.line(index);
+            let from = if index == start_line {start_col.min(line.len())} else {0};
+            let to = if index == end_line {end_col.min(line.len())} else {line.len()};
+            lines.push(TerminalScreen::line_text(&line[from..to.max(from)]));
+        }
+        lines.join("\n")
+    }
+
+    // what a key sends to the program, the way xterm does it
+    fn key_bytes(ke: &KeyEvent) -> Option<Vec<u8>> {
+        if ke.modifiers.control && !ke.modifiers.alt {
+            if let Some(letter) = Self::key_letter(ke.key_code) {
+                return Some(vec![letter as u8 & 0x1f])
+            }
+        }
+        let bytes: &[u8] = match ke.key_code {
+            KeyCode::Return | KeyCode::NumpadEnter => b"\r",
+            KeyCode::Backspace => b"\x7f",
+            KeyCode::Tab => if ke.modifiers.shift {b"\x1b[Z"} else {b"\t"},
+            KeyCode::Escape => b"\x1b",
+            KeyCode::ArrowUp => b"\x1b[A",
+            KeyCode::ArrowDown => b"\x1b[B",
+            KeyCode::ArrowRight => b"\x1b[C",
+            KeyCode::ArrowLeft => b"\x1b[D",
+            KeyCode::Home => b"\x1b[H",
+            KeyCode::End => b"\x1b[F",
+            KeyCode::Insert => b"\x1b[2~",
+            KeyCode::Delete => b"\x1b[3~",
+            KeyCode::PageUp => b"\x1b[5~",
+            KeyCode::PageDown => b"\x1b[6~",
+            _ => return None
+        };
+        Some(bytes.to_vec())
+    }
+
+    fn key_letter(key_code: KeyCode) -> Option<char> {
+        Some(match key_code {
+            KeyCode::KeyA => 'a', KeyCode::KeyB => 'b', KeyCode::KeyC => 'c', KeyCode::KeyD => 'd',
+            KeyCode::KeyE => 'e', KeyCode::KeyF => 'f', KeyCode::KeyG => 'g', KeyCode::KeyH => 'h',
+            KeyCode::KeyI => 'i', KeyCode::KeyJ => 'j', KeyCode::KeyK => 'k', KeyCode::KeyL => 'l',
+            KeyCode::KeyM => 'm', KeyCode::KeyN => 'n', KeyCode::KeyO => 'o', KeyCode::KeyP => 'p',
+            KeyCode::KeyQ => 'q', KeyCode::KeyR => 'r', KeyCode::KeyS => 's', KeyCode::KeyT => 't',
+            KeyCode::KeyU => 'u', KeyCode::KeyV => 'v', KeyCode::KeyW => 'w', KeyCode::KeyX => 'x',
+            KeyCode::KeyY => 'y', KeyCode::KeyZ => 'z', KeyCode::LBracket => '[', KeyCode::Backslash => '\\',
+            KeyCode::RBracket => ']',
+            _ => return None
+        })
+    }
+
+    pub fn handle_terminal_panel(&mut self, cx: &mut Cx, event: &mut Event, storage: &mut AppStorage, bm: &mut BuildManager) {
+        if self.view.handle_scroll_view(cx, event) {
+            self.view.redraw_view_area(cx);
+            // scrolling up stops following the output, scrolling to the bottom follows it again
+            if let Event::FingerScroll(_) = event {
+                bm.terminal.tail = self.view.get_scroll_pos(cx).y >= self.max_scroll - 1.;
+            }
+        }
+
+        if let Event::Signal(se) = event {
+            if let Some(statusses) = se.signals.get(&bm.signal) {
+                if statusses.contains(&BuildManager::status_terminal_update()) {
+                    self.view.redraw_view_area(cx);
+                }
+            }
+        }
+
+        let view_area = self.view.get_view_area(cx);
+        match event.hits(cx, view_area, HitOpt::default()) {
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => {
+                self.view.redraw_view_area(cx);
+            },
+            Event::FingerHover(_) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Text);
+            },
+            Event::FingerDown(fe) => {
+                cx.set_key_focus(view_area);
+                cx.set_down_mouse_cursor(MouseCursor::Text);
+                let cell = self.cell_at(cx, fe.abs);
+                self.select = Some((cell, cell));
+                self.view.redraw_view_area(cx);
+            },
+            Event::FingerMove(fe) => {
+                let cell = self.cell_at(cx, fe.abs);
+                if let Some((start, _)) = self.select {
+                    self.select = Some((start, cell));
+                    self.view.redraw_view_area(cx);
+                }
+            },
+            Event::KeyDown(ke) => {
+                if !bm.terminal.is_running() {
+                    if ke.key_code == KeyCode::Return {
+                        bm.terminal.start(storage);
+                        self.view.redraw_view_area(cx);
+                    }
+                }
+                else if let Some(bytes) = Self::key_bytes(&ke) {
+                    self.select = None;
+                    bm.terminal.input(storage, bytes);
+                }
+            },
+            Event::TextInput(te) => if bm.terminal.is_running() {
+                self.select = None;
+                // a paste sends its newlines as the return key does
+                let input = if te.was_paste {te.input.replace("\r\n", "\r").replace('\n', "\r")} else {te.input.clone()};
+                bm.terminal.input(storage, input.into_bytes());
+            },
+            Event::TextCopy(_) => match event {
+                Event::TextCopy(req) => {
+                    req.response = Some(self.selected_text(&bm.terminal.screen));
+                },
+                _ => ()
+            },
+            _ => ()
+        }
+    }
+
+    pub fn terminal_color(cx: &Cx, (color, default): (TerminalColor, ColorId)) -> Color {
+        match color {
+            TerminalColor::Default => default.get(cx),
+            TerminalColor::Indexed(index) => {
+                let (r, g, b) = TerminalColor::rgb(index);
+                Color::rgb(r as i32, g as i32, b as i32)
+            },
+            TerminalColor::Rgb(r, g, b) => Color::rgb(r as i32, g as i32, b as i32)
+        }
+    }
+
+    // the text and background color of a cell with the color they default to, bold makes the
+    // first 8 colors bright like xterm does. there is no background to draw for the default one
+    fn cell_colors(style: &TerminalStyle) -> ((TerminalColor, ColorId), Option<(TerminalColor, ColorId)>) {
+        let fg = match style.fg {
+            TerminalColor::Indexed(index) if style.bold && index < 8 => TerminalColor::Indexed(index + 8),
+            fg => fg
+        };
+        if style.inverse {
+            ((style.bg, Self::color_bg()), Some((fg, Self::color_text())))
+        }
+        else if style.bg == TerminalColor::Default {
+            ((fg, Self::color_text()), None)
+        }
+        else {
+            ((fg, Self::color_text()), Some((style.bg, Self::color_bg())))
+        }
+    }
+
+    pub fn draw_terminal_panel(&mut self, cx: &mut Cx, storage: &mut AppStorage, bm: &mut BuildManager) {
+        if self.view.begin_view(cx, Layout::default()).is_err() {
+            return
+        }
+        self.text.text_style = Self::text_style_terminal().get(cx);
+        let base = self.text.get_monospace_base(cx);
+        self.cell_size = Vec2 {x: base.x * self.text.text_style.font_size, y: base.y * self.text.text_style.font_size};
+        let padding = Self::padding().get(cx);
+        let rect = cx.get_turtle_rect();
+        let origin = cx.get_turtle_origin();
+
+        // the program gets the size of the panel
+        if !rect.w.is_nan() && !rect.h.is_nan() {
+            let cols = ((rect.w - 2. * padding) / self.cell_size.x).floor().max(10.) as usize;
+            let rows = ((rect.h - 2. * padding) / self.cell_size.y).floor().max(2.) as usize;
+            bm.terminal.resize(storage, cols, rows);
+        }
+
+        let screen = &bm.terminal.screen;
+        let total_lines = screen.total_lines();
+        let content_h = total_lines as f32 * self.cell_size.y + 2. * padding;
+        self.max_scroll = (content_h - rect.h).max(0.);
+        let scroll = if bm.terminal.tail {Vec2 {x: 0., y: self.max_scroll}} else {self.view.get_scroll_pos(cx)};
+        let first = ((scroll.y - padding) / self.cell_size.y).max(0.).floor() as usize;
+        let last = (((scroll.y + rect.h) / self.cell_size.y).ceil() as usize + 1).min(total_lines);
+
+        self.bg.color = Self::color_bg().get(cx);
+        let inst = self.bg.draw_quad_rel(cx, Rect {x: 0., y: 0., w: rect.w, h: rect.h});
+        inst.set_do_scroll(cx, false, false);
+
+        let cell_size = self.cell_size;
+        let line_xy = | index: usize, col: usize | Vec2 {
+            x: origin.x + padding + col as f32 * cell_size.x,
+            y: origin.y + padding + index as f32 * cell_size.y
+        };
+
+        // backgrounds, in runs of the same color
+        for index in first..last {
+            let line = screen.line(index);
+            let mut col = 0;
+            while col < line.len() {
+                let (_, bg) = Self::cell_colors(&line[col].style);
+                let start = col;
+                while col < line.len() && Self::cell_colors(&line[col].style).1 == bg {
+                    col += 1;
+                }
+                if let Some(bg) = bg {
+                    let pos = line_xy(index, start);
+                    self.cell_bg.color = Self::terminal_color(cx, bg);
+                    self.cell_bg.draw_quad_abs(cx, Rect {x: pos.x, y: pos.y, w: (col - start) as f32 * self.cell_size.x, h: self.cell_size.y});
+                }
+            }
+        }
+
+        if let Some(((start_line, start_col), (end_line, end_col))) = self.selection_range() {
+            self.selection.color = Self::color_selection().get(cx);
+            for index in start_line.max(first)..=end_line.min(last.max(1) - 1) {
+                let from = if index == start_line {start_col} else {0};
+                let to = if index == end_line {end_col} else {screen.cols};
+                if to > from {
+                    let pos = line_xy(index, from);
+                    self.selection.draw_quad_abs(cx, Rect {x: pos.x, y: pos.y, w: (to - from) as f32 * self.cell_size.x, h: self.cell_size.y});
+                }
+            }
+        }
+
+        // the text, in runs of the same color
+        let mut aligned = self.text.begin_text(cx);
+        let mut chars = Vec::new();
+        for index in first..last {
+            let line = screen.line(index);
+            let mut col = 0;
+            while col < line.len() {
+                let (fg, _) = Self::cell_colors(&line[col].style);
+                let start = col;
+                chars.truncate(0);
+                while col < line.len() && Self::cell_colors(&line[col].style).0 == fg {
+                    chars.push(line[col].ch);
+                    col += 1;
+                }
+                if chars.iter().all( | ch | *ch == ' ') {
+                    continue
+                }
+                let pos = line_xy(index, start);
+                self.text.color = Self::terminal_color(cx, fg);
+                self.text.add_text(cx, pos.x, pos.y, 0, &mut aligned, &chars, | _, _, _, _ | {0.});
+            }
+        }
+        self.text.end_text(cx, &aligned);
+
+        if screen.cursor_visible && bm.terminal.is_running() {
+            cx.new_instance_draw_call(&self.cursor.shader, 0);
+            let pos = line_xy(screen.scrollback.len() + screen.cursor_row, screen.cursor_col);
+            let mut color = Self::color_cursor().get(cx);
+            if !cx.has_key_focus(self.view.get_view_area(cx)) {
+                color.a = 0.4;
+            }
+            self.cursor.color = color;
+            self.cursor.draw_quad_abs(cx, Rect {x: pos.x, y: pos.y, w: self.cell_size.x, h: self.cell_size.y});
+        }
+
+        cx.walk_turtle(Walk::wh(Width::Fix(screen.cols as f32 * self.cell_size.x + 2. * padding), Height::Fix(content_h)));
+
+        self.shadow.draw_shadow_left(cx);
+        self.shadow.draw_shadow_top(cx);
+
+        self.view.end_view(cx);
+        if bm.terminal.tail {
+            self.view.set_scroll_pos(cx, scroll);
+        }
+    }
+}
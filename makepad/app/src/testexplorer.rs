@@ -0,0 +1,193 @@
+use makepad_render::*;
+use makepad_widget::*;
+use makepad_hub::*;
+use crate::appstorage::*;
+use crate::buildmanager::*;
+use crate::codeicon::*;
+use crate::loglist::*;
+use crate::testrunner::*;
+
+#[derive(Clone)]
+pub struct TestExplorer {
+    pub view: ScrollView,
+    pub item_draw: LogItemDraw,
+    pub list: ListLogic,
+}
+
+#[derive(Clone)]
+pub enum TestExplorerEvent {
+    SelectLocMessage {
+        loc_message: LocMessage,
+        jump_to_offset: usize
+    },
+    SelectMessages {
+        items: String
+    },
+    None,
+}
+
+impl TestExplorer {
+    pub fn new(cx: &mut Cx) -> Self {
+        Self {
+            item_draw: LogItemDraw::new(cx),
+            list: ListLogic::default(),
+            view: ScrollView::new(cx),
+        }
+    }
+
+    pub fn handle_test_explorer(&mut self, cx: &mut Cx, event: &mut Event, storage: &mut AppStorage, bm: &mut BuildManager) -> TestExplorerEvent {
+
+        self.list.set_list_len(bm.test_runner.items.len());
+
+        self.list.handle_list_scroll_bars(cx, event, &mut self.view);
+
+        if let Event::Signal(se) = event {
+            if let Some(_) = se.signals.get(&bm.signal) {
+                self.view.redraw_view_area(cx);
+            }
+        }
+
+        let le = self.list.handle_list_logic(cx, event, ListSelect::None, false, | cx, item_event, item, item_index | match item_event {
+            ListLogicEvent::Animate(ae) => {
+                item.animator.calc_area(cx, item.animator.area, ae.time);
+            },
+            ListLogicEvent::AnimEnded => {
+                item.animator.end();
+            },
+            ListLogicEvent::Select => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, true));
+            },
+            ListLogicEvent::Deselect => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, false));
+            },
+            ListLogicEvent::Cleanup => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Over => {
+                item.animator.play_anim(cx, LogItemDraw::get_over_anim(cx, item_index, item.is_selected));
+            },
+            ListLogicEvent::Out => {
+                item.animator.play_anim(cx, LogItemDraw::get_default_anim(cx, item_index, item.is_selected));
+            }
+        });
+
+        match le {
+            ListEvent::SelectSingle(select_index) => {
+                self.view.redraw_view_area(cx);
+                let item = &bm.test_runner.items[select_index];
+                // a failed test jumps to where it panicked
+                if let Some(loc_message) = &item.loc {
+                    let text_buffer = &storage.text_buffer_from_path(cx, &storage.remap_sync_path(&loc_message.path)).text_buffer;
+                    let offset = text_buffer.text_pos_to_offset(TextPos {row: loc_message.line.max(1) - 1, col: loc_message.col.max(1) - 1});
+                    TestExplorerEvent::SelectLocMessage {
+                        loc_message: loc_message.clone(),
+                        jump_to_offset: offset
+                    }
+                }
+                else {
+                    TestExplorerEvent::SelectMessages {
+                        items: item.output.clone()
+                    }
+                }
+            },
+            ListEvent::SelectDouble(select_index) => {
+                bm.test_runner.run_tests(storage, &[select_index]);
+                self.view.redraw_view_area(cx);
+                TestExplorerEvent::None
+            },
+            ListEvent::SelectMultiple | ListEvent::None => {
+                TestExplorerEvent::None
+            }
+        }
+    }
+
+    pub fn draw_test_item(&mut self, cx: &mut Cx, index: usize, test_item: &TestItem) {
+        let draw = &mut self.item_draw;
+        let list_item = &mut self.list.list_items[index];
+        list_item.animator.init(cx, | cx | LogItemDraw::get_default_anim(cx, index, false));
+        draw.item_bg.color = list_item.animator.last_color(cx, Quad::color());
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+
+        let icon = match test_item.state {
+            TestState::NotRun => None,
+            TestState::Running => Some(CodeIconType::Wait),
+            TestState::Passed => Some(CodeIconType::Ok),
+            TestState::Failed => Some(CodeIconType::Error),
+            TestState::Ignored => Some(CodeIconType::Warning),
+        };
+        if let Some(icon) = icon {
+            draw.code_icon.draw_icon(cx, icon);
+            cx.turtle_align_y();
+        }
+        draw.text.color = draw.path_color.get(cx);
+        draw.text.draw_text(cx, &format!("{} - ", test_item.path()));
+        draw.text.color = draw.message_color.get(cx);
+        draw.text.draw_text(cx, &test_item.test.name);
+        if let Some(exec_time) = test_item.exec_time {
+            draw.text.color = draw.path_color.get(cx);
+            draw.text.draw_text(cx, &format!(" {:.2}s", exec_time));
+        }
+
+        let bg_area = draw.item_bg.end_quad(cx, bg_inst);
+        list_item.animator.set_area(cx, bg_area);
+    }
+
+    pub fn draw_status_line(&mut self, cx: &mut Cx, counter: usize, bm: &BuildManager) {
+        let draw = &mut self.item_draw;
+        let runner = &bm.test_runner;
+        draw.item_bg.color = if counter & 1 == 0 {Theme::color_bg_selected().get(cx)}else {Theme::color_bg_odd().get(cx)};
+        let bg_inst = draw.item_bg.begin_quad(cx, LogItemDraw::layout_item().get(cx));
+        draw.text.color = draw.path_color.get(cx);
+        if runner.is_running() {
+            draw.code_icon.draw_icon(cx, CodeIconType::Wait);
+            cx.turtle_align_y();
+            draw.text.draw_text(cx, &format!("Testing ({} running) ", runner.count(TestState::Running)));
+        }
+        else if runner.items.len() == 0 {
+            draw.text.draw_text(cx, "No tests, use Run > Discover Tests");
+        }
+        else {
+            draw.code_icon.draw_icon(cx, if runner.count(TestState::Failed) > 0 {CodeIconType::Error} else {CodeIconType::Ok});
+            cx.turtle_align_y();
+            draw.text.draw_text(cx, &format!(
+                "{} passed, {} failed, {} ignored - double click a test to run it again",
+                runner.count(TestState::Passed),
+                runner.count(TestState::Failed),
+                runner.count(TestState::Ignored)
+            ));
+        }
+        draw.item_bg.end_quad(cx, bg_inst);
+    }
+
+    pub fn draw_test_explorer(&mut self, cx: &mut Cx, bm: &BuildManager) {
+
+        self.list.set_list_len(bm.test_runner.items.len());
+
+        self.item_draw.text.text_style = LogItemDraw::text_style_item().get(cx);
+
+        let row_height = LogItemDraw::layout_item().get(cx).walk.height.fixed();
+
+        if self.list.begin_list(cx, &mut self.view, false, row_height).is_err() {return}
+
+        let mut counter = 0;
+        for i in self.list.start_item..self.list.end_item {
+            self.draw_test_item(cx, i, &bm.test_runner.items[i]);
+            counter += 1;
+        }
+
+        self.list.walk_turtle_to_end(cx, row_height);
+
+        self.draw_status_line(cx, counter, bm);
+        counter += 1;
+
+        for _ in (self.list.end_item + 1)..self.list.end_fill {
+            self.item_draw.draw_filler(cx, counter);
+            counter += 1;
+        }
+
+        self.item_draw.shadow.draw_shadow_left(cx);
+        self.item_draw.shadow.draw_shadow_top(cx);
+
+        self.list.end_list(cx, &mut self.view);
+    }
+}
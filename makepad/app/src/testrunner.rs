@@ -0,0 +1,202 @@
+use makepad_hub::*;
+use crate::appstorage::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestState {
+    NotRun,
+    Running,
+    Passed,
+    Failed,
+    Ignored
+}
+
+#[derive(Clone)]
+pub struct TestItem {
+    pub builder: String,
+    pub workspace: String,
+    pub test: HubTest,
+    pub state: TestState,
+    pub output: String,
+    pub exec_time: Option<f64>,
+    pub loc: Option<LocMessage>,
+}
+
+impl TestItem {
+    pub fn path(&self) -> String {
+        let target = match &self.test.target {
+            HubTestTarget::Lib => "lib".to_string(),
+            HubTestTarget::Bin(name) => format!("bin {}", name),
+            HubTestTarget::Test(name) => format!("test {}", name),
+            HubTestTarget::Example(name) => format!("example {}", name),
+            HubTestTarget::Doc => "doc".to_string(),
+        };
+        format!("{}/{}/{} ({})", self.builder, self.workspace, self.test.package, target)
+    }
+}
+
+#[derive(Clone)]
+pub struct TestRequest {
+    pub uid: HubUid,
+    pub builder: String,
+    pub workspace: String,
+    pub package: String,
+}
+
+// the tests of the packages we build, and how they did the last time they ran
+#[derive(Clone)]
+pub struct TestRunner {
+    pub items: Vec<TestItem>,
+    pub list_requests: Vec<TestRequest>,
+    pub run_requests: Vec<TestRequest>,
+}
+
+impl TestRunner {
+    pub fn new() -> TestRunner {
+        TestRunner {
+            items: Vec::new(),
+            list_requests: Vec::new(),
+            run_requests: Vec::new(),
+        }
+    }
+
+    pub fn is_running_uid(&self, uid: &HubUid) -> bool {
+        self.list_requests.iter().any( | r | r.uid == *uid) || self.run_requests.iter().any( | r | r.uid == *uid)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.list_requests.len() > 0 || self.run_requests.len() > 0
+    }
+
+    pub fn count(&self, state: TestState) -> usize {
+        self.items.iter().filter( | item | item.state == state).count()
+    }
+
+    fn packages(storage: &AppStorage) -> Vec<(String, String, String)> {
+        let mut packages = Vec::new();
+        for bt in &storage.settings.builds {
+            let package = (bt.builder.clone(), bt.workspace.clone(), bt.package.clone());
+            if !packages.contains(&package) {
+                packages.push(package);
+            }
+        }
+        packages
+    }
+
+    pub fn discover(&mut self, storage: &mut AppStorage) {
+        let packages = Self::packages(storage);
+        let hub_ui = storage.hub_ui.as_mut().unwrap();
+        self.items.truncate(0);
+        for (builder, workspace, package) in packages {
+            let uid = hub_ui.route_send.alloc_uid();
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(builder.clone()),
+                msg: HubMsg::TestListRequest {uid: uid, workspace: workspace.clone(), package: package.clone()}
+            });
+            self.list_requests.push(TestRequest {uid: uid, builder: builder, workspace: workspace, package: package});
+        }
+    }
+
+    pub fn run_all(&mut self, storage: &mut AppStorage) {
+        let packages = Self::packages(storage);
+        let hub_ui = storage.hub_ui.as_mut().unwrap();
+        for (builder, workspace, package) in packages {
+            let uid = hub_ui.route_send.alloc_uid();
+            for item in &mut self.items {
+                if item.builder == builder && item.workspace == workspace && item.test.package == package {
+                    item.state = TestState::Running;
+                }
+            }
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(builder.clone()),
+                msg: HubMsg::TestRun {uid: uid, workspace: workspace.clone(), package: package.clone(), tests: Vec::new()}
+            });
+            self.run_requests.push(TestRequest {uid: uid, builder: builder, workspace: workspace, package: package});
+        }
+    }
+
+    // runs the tests at these indices, one run per package
+    pub fn run_tests(&mut self, storage: &mut AppStorage, indices: &[usize]) {
+        let hub_ui = storage.hub_ui.as_mut().unwrap();
+        let mut runs: Vec<(TestRequest, Vec<HubTest>)> = Vec::new();
+        for index in indices {
+            let item = if let Some(item) = self.items.get_mut(*index) {item} else {continue};
+            item.state = TestState::Running;
+            if let Some((_, tests)) = runs.iter_mut().find( | (r, _) | r.builder == item.builder && r.workspace == item.workspace && r.package == item.test.package) {
+                tests.push(item.test.clone());
+            }
+            else {
+                runs.push((TestRequest {
+                    uid: hub_ui.route_send.alloc_uid(),
+                    builder: item.builder.clone(),
+                    workspace: item.workspace.clone(),
+                    package: item.test.package.clone()
+                }, vec![item.test.clone()]));
+            }
+        }
+        for (request, tests) in runs {
+            hub_ui.route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(request.builder.clone()),
+                msg: HubMsg::TestRun {uid: request.uid, workspace: request.workspace.clone(), package: request.package.clone(), tests: tests}
+            });
+            self.run_requests.push(request);
+        }
+    }
+
+    pub fn rerun_failed(&mut self, storage: &mut AppStorage) {
+        let failed: Vec<usize> = (0..self.items.len()).filter( | i | self.items[*i].state == TestState::Failed).collect();
+        self.run_tests(storage, &failed);
+    }
+
+    fn item_index(&mut self, builder: &str, workspace: &str, test: &HubTest) -> usize {
+        if let Some(index) = self.items.iter().position( | item | item.builder == builder && item.workspace == workspace && item.test == *test) {
+            return index
+        }
+        self.items.push(TestItem {
+            builder: builder.to_string(),
+            workspace: workspace.to_string(),
+            test: test.clone(),
+            state: TestState::NotRun,
+            output: String::new(),
+            exec_time: None,
+            loc: None
+        });
+        self.items.len() - 1
+    }
+
+    // returns a summary to log when a run ends
+    pub fn handle_hub_msg(&mut self, htc: &FromHubMsg) -> Option<String> {
+        match &htc.msg {
+            HubMsg::TestListResponse {uid, tests} => if let Some(pos) = self.list_requests.iter().position( | r | r.uid == *uid) {
+                let request = self.list_requests.remove(pos);
+                for test in tests {
+                    self.item_index(&request.builder, &request.workspace, test);
+                }
+            },
+            HubMsg::TestResult {uid, result} => if let Some(request) = self.run_requests.iter().find( | r | r.uid == *uid).cloned() {
+                let index = self.item_index(&request.builder, &request.workspace, &result.test);
+                let item = &mut self.items[index];
+                item.state = match result.outcome {
+                    HubTestOutcome::Passed => TestState::Passed,
+                    HubTestOutcome::Failed => TestState::Failed,
+                    HubTestOutcome::Ignored => TestState::Ignored,
+                };
+                item.output = result.output.clone();
+                item.exec_time = result.exec_time;
+                item.loc = result.loc.clone();
+            },
+            HubMsg::TestEnd {uid, passed, failed, ignored} => if let Some(pos) = self.run_requests.iter().position( | r | r.uid == *uid) {
+                let request = self.run_requests.remove(pos);
+                // tests that didn't report, because the build failed or a filter missed them
+                for item in &mut self.items {
+                    if item.state == TestState::Running && item.builder == request.builder && item.workspace == request.workspace && item.test.package == request.package
+                        && !self.run_requests.iter().any( | r | r.builder == item.builder && r.workspace == item.workspace && r.package == item.test.package) {
+                        item.state = TestState::NotRun;
+                    }
+                }
+                return Some(format!("Tests of {}/{}/{} done, {} passed, {} failed, {} ignored", request.builder, request.workspace, request.package, passed, failed, ignored))
+            },
+            _ => ()
+        }
+        None
+    }
+}
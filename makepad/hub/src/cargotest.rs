@@ -0,0 +1,238 @@
+use makepad_microserde::*;
+use crate::hubmsg::*;
+
+// reads the output of cargo test. the json of libtest says how each test went, and cargo test
+// is run once per test target, so a test can be found and run again by its target and name
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum HubTestTarget {
+    Lib,
+    Bin(String),
+    Test(String),
+    Example(String),
+    Doc
+}
+
+impl HubTestTarget {
+    pub fn cargo_args(&self) -> Vec<String> {
+        match self {
+            HubTestTarget::Lib => vec!["--lib".to_string()],
+            HubTestTarget::Bin(name) => vec!["--bin".to_string(), name.clone()],
+            HubTestTarget::Test(name) => vec!["--test".to_string(), name.clone()],
+            HubTestTarget::Example(name) => vec!["--example".to_string(), name.clone()],
+            HubTestTarget::Doc => vec!["--doc".to_string()],
+        }
+    }
+
+    // the targets of a package that cargo test runs, from cargo metadata --no-deps
+    pub fn from_cargo_metadata(json: &str, package: &str) -> Option<Vec<HubTestTarget>> {
+        let metadata: JsonValue = DeJson::deserialize_json(json).ok() ?;
+        let package = metadata.get("packages") ?.as_array() ?.iter().find( | p | p.get("name").and_then( | n | n.as_str()) == Some(package)) ?;
+        let mut targets = Vec::new();
+        for target in package.get("targets") ?.as_array() ? {
+            let name = target.get("name").and_then( | n | n.as_str()).unwrap_or("").to_string();
+            let kind = target.query("kind[0]").and_then( | k | k.as_str()).unwrap_or("");
+            let is_lib = kind.ends_with("lib") || kind == "proc-macro";
+            if target.get("test").and_then( | t | t.as_bool()).unwrap_or(kind != "example" && kind != "bench") {
+                match kind {
+                    "bin" => targets.push(HubTestTarget::Bin(name)),
+                    "test" => targets.push(HubTestTarget::Test(name)),
+                    "example" => targets.push(HubTestTarget::Example(name)),
+                    _ => if is_lib {
+                        targets.push(HubTestTarget::Lib)
+                    }
+                }
+            }
+            if is_lib && target.get("doctest").and_then( | t | t.as_bool()).unwrap_or(true) {
+                targets.push(HubTestTarget::Doc);
+            }
+        }
+        // the doc tests run last, like with cargo test
+        targets.sort_by_key( | t | *t == HubTestTarget::Doc);
+        Some(targets)
+    }
+
+    // the test binary in a line of cargo test --no-run --message-format=json, and the directory of
+    // its package that cargo would run it in
+    pub fn test_executable(line: &str) -> Option<(String, String)> {
+        let artifact: JsonValue = DeJson::deserialize_json(line).ok() ?;
+        if artifact.get("reason") ?.as_str() ? != "compiler-artifact" || !artifact.query("profile.test") ?.as_bool() ? {
+            return None
+        }
+        let executable = artifact.get("executable") ?.as_str() ?;
+        let package_dir = std::path::Path::new(artifact.get("manifest_path") ?.as_str() ?).parent() ?;
+        Some((executable.to_string(), package_dir.to_str() ?.to_string()))
+    }
+
+    // the lines cargo writes on stderr as it starts each test binary
+    pub fn is_cargo_line(line: &str) -> bool {
+        let line = line.trim();
+        line.starts_with("Running ") || line.starts_with("Doc-tests ")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubTest {
+    pub package: String,
+    pub target: HubTestTarget,
+    pub name: String
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum HubTestOutcome {
+    Passed,
+    Failed,
+    Ignored
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubTestResult {
+    pub test: HubTest,
+    pub outcome: HubTestOutcome,
+    pub output: String,
+    pub exec_time: Option<f64>,
+    pub loc: Option<LocMessage> // where a failed test panicked
+}
+
+// one line of libtest --format json
+#[derive(Clone, DeJson)]
+pub struct LibtestEvent {
+    #[rename = "type"]
+    ty: String,
+    event: String,
+    #[default]
+    name: String,
+    #[default]
+    stdout: Option<String>,
+    #[default]
+    message: Option<String>,
+    #[default]
+    exec_time: Option<f64>,
+    #[default]
+    test_count: Option<usize>,
+}
+
+pub enum CargoTestEvent {
+    Discovered(HubTest),
+    Begin {target: HubTestTarget, count: usize},
+    Result(HubTestResult),
+}
+
+pub struct CargoTestParser {
+    pub builder: String,
+    pub workspace: String,
+    pub abs_root_path: String,
+    pub package: String,
+    pub target: HubTestTarget,
+}
+
+impl CargoTestParser {
+    pub fn new(builder: &str, workspace: &str, abs_root_path: &str, package: &str, target: HubTestTarget) -> CargoTestParser {
+        CargoTestParser {
+            builder: builder.to_string(),
+            workspace: workspace.to_string(),
+            abs_root_path: abs_root_path.replace("\\", "/"),
+            package: package.to_string(),
+            target: target
+        }
+    }
+
+    // returns None for lines that aren't about tests, like the json cargo writes for builds
+    pub fn parse_line(&self, line: &str) -> Option<CargoTestEvent> {
+        let line = line.trim_end();
+        // the lines of cargo test -- --list
+        if line.ends_with(": test") {
+            return Some(CargoTestEvent::Discovered(self.test(&line[0..line.len() - ": test".len()])))
+        }
+        // doc tests are run by rustdoc through cargo, without the json format
+        if line.starts_with("running ") {
+            let count = line["running ".len()..].split(' ').next() ?.parse().ok() ?;
+            return Some(CargoTestEvent::Begin {target: self.target.clone(), count: count})
+        }
+        if line.starts_with("test ") {
+            let end = line.rfind(" ... ") ?;
+            let outcome = match &line[end + " ... ".len()..] {
+                "ok" => HubTestOutcome::Passed,
+                "FAILED" => HubTestOutcome::Failed,
+                "ignored" => HubTestOutcome::Ignored,
+                _ => return None
+            };
+            return Some(CargoTestEvent::Result(HubTestResult {
+                test: self.test(&line["test ".len()..end]),
+                outcome: outcome,
+                output: String::new(),
+                exec_time: None,
+                loc: None
+            }))
+        }
+        let event: LibtestEvent = DeJson::deserialize_json(line).ok() ?;
+        match (event.ty.as_ref(), event.event.as_ref()) {
+            ("suite", "started") => Some(CargoTestEvent::Begin {
+                target: self.target.clone(),
+                count: event.test_count.unwrap_or(0)
+            }),
+            ("test", "ok") | ("test", "failed") | ("test", "ignored") => {
+                let outcome = match event.event.as_ref() {
+                    "ok" => HubTestOutcome::Passed,
+                    "failed" => HubTestOutcome::Failed,
+                    _ => HubTestOutcome::Ignored
+                };
+                let mut output = event.stdout.clone().unwrap_or(String::new());
+                if let Some(message) = &event.message {
+                    output.push_str(message);
+                }
+                let loc = if outcome == HubTestOutcome::Failed {self.panic_loc(&output)}else {None};
+                Some(CargoTestEvent::Result(HubTestResult {
+                    test: self.test(&event.name),
+                    outcome: outcome,
+                    output: output,
+                    exec_time: event.exec_time,
+                    loc: loc
+                }))
+            },
+            _ => None
+        }
+    }
+
+    fn test(&self, name: &str) -> HubTest {
+        HubTest {
+            package: self.package.clone(),
+            target: self.target.clone(),
+            name: name.to_string()
+        }
+    }
+
+    // finds "panicked at src/lib.rs:10:5:" and the older "panicked at 'msg', src/lib.rs:10:5"
+    pub fn panic_loc(&self, output: &str) -> Option<LocMessage> {
+        let start = output.find("panicked at ") ? + "panicked at ".len();
+        let rest = &output[start..];
+        let (location, body) = if rest.starts_with('\'') {
+            let end = rest.find("', ") ?;
+            (rest[end + 3..].lines().next().unwrap_or(""), rest[1..end].to_string())
+        }
+        else {
+            let mut lines = rest.lines();
+            (lines.next().unwrap_or("").trim_end_matches(':'), lines.next().unwrap_or("").to_string())
+        };
+        let mut parts = location.rsplitn(3, ':');
+        let col = parts.next() ?.parse::<usize>().ok() ?;
+        let line = parts.next() ?.parse::<usize>().ok() ?;
+        let mut path = parts.next() ?.replace("\\", "/");
+        if path.starts_with(&self.abs_root_path) {
+            path = path[self.abs_root_path.len()..].trim_start_matches('/').to_string();
+        }
+        else if path.starts_with('/') || path.contains(":/") {
+            return None // somewhere outside the workspace, like the standard library
+        }
+        Some(LocMessage {
+            path: format!("{}/{}/{}", self.builder, self.workspace, path),
+            line: line,
+            col: col,
+            body: body,
+            range: None,
+            rendered: Some(output.to_string()),
+            explanation: None,
+            fixes: Vec::new()
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use makepad_microserde::*;
+use std::io::{Read, Write, BufRead};
+use std::net::TcpStream;
+use std::process::{Command, Child, Stdio};
+
+// a client for the debug adapter protocol, the json that debuggers like lldb-dap speak. every
+// message has a Content-Length header, requests get a response pointing back at their seq,
+// and events tell what the program does in between
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
+pub enum HubDebugAdapter {
+    // started by the builder, it talks over its stdin and stdout
+    Command {command: String, args: Vec<String>},
+    // an adapter that is already listening, like lldb-dap --connection listen://127.0.0.1:4711
+    Tcp(String)
+}
+
+impl HubDebugAdapter {
+    pub fn default_adapter() -> HubDebugAdapter {
+        HubDebugAdapter::Command {command: "lldb-dap".to_string(), args: Vec::new()}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum HubDebugCommand {
+    Continue,
+    Pause,
+    StepOver,
+    StepIn,
+    StepOut,
+    Stop
+}
+
+impl HubDebugCommand {
+    pub fn dap_command(&self) -> &'static str {
+        match self {
+            HubDebugCommand::Continue => "continue",
+            HubDebugCommand::Pause => "pause",
+            HubDebugCommand::StepOver => "next",
+            HubDebugCommand::StepIn => "stepIn",
+            HubDebugCommand::StepOut => "stepOut",
+            HubDebugCommand::Stop => "disconnect",
+        }
+    }
+}
+
+// paths are workspace/path like the ProgramRun ones, lines count from 1
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubBreakpoint {
+    pub path: String,
+    pub line: usize
+}
+
+// the path is builder/workspace/path like in a LocMessage, none when it is outside the workspaces
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubStackFrame {
+    pub id: u64,
+    pub name: String,
+    pub path: Option<String>,
+    pub line: usize,
+    pub col: usize
+}
+
+// a reference that isn't 0 has children, they come with a DebugVariablesRequest for it
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubVariable {
+    pub name: String,
+    pub value: String,
+    pub type_name: String,
+    pub reference: u64
+}
+
+impl HubVariable {
+    pub fn from_dap(body: &JsonValue) -> Vec<HubVariable> {
+        let mut variables = Vec::new();
+        if let Some(list) = body.get("variables").and_then( | v | v.as_array()) {
+            for var in list {
+                variables.push(HubVariable {
+                    name: var["name"].as_str().unwrap_or("").to_string(),
+                    value: var["value"].as_str().unwrap_or("").to_string(),
+                    type_name: var["type"].as_str().unwrap_or("").to_string(),
+                    reference: var["variablesReference"].as_u64().unwrap_or(0)
+                });
+            }
+        }
+        variables
+    }
+}
+
+// what a request was for, so its response can be handled when it comes in
+#[derive(Clone, Debug)]
+pub enum DapPending {
+    Initialize,
+    Launch,
+    Disconnect,
+    StackTrace {reason: String},
+    // stopped is there when the variables go out with a DebugStopped
+    Scopes {frame_id: u64, stopped: Option<(String, Vec<HubStackFrame>)>},
+    Variables {frame_id: u64, reference: u64, stopped: Option<(String, Vec<HubStackFrame>)>},
+    Other
+}
+
+pub struct DapConnection {
+    writer: Box<dyn Write + Send>,
+    child: Option<Child>,
+    seq: u64,
+    pending: Vec<(u64, DapPending)>,
+    pub thread_id: u64,
+}
+
+impl DapConnection {
+    // the reader goes to the thread that reads the messages
+    pub fn connect(adapter: &HubDebugAdapter, current_dir: &str) -> std::io::Result<(DapConnection, Box<dyn Read + Send>)> {
+        match adapter {
+            HubDebugAdapter::Command {command, args} => {
+                let mut child = Command::new(command)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .current_dir(current_dir)
+                    .spawn() ?;
+                let writer = child.stdin.take().unwrap();
+                let reader = child.stdout.take().unwrap();
+                Ok((DapConnection::new(Box::new(writer), Some(child)), Box::new(reader)))
+            },
+            HubDebugAdapter::Tcp(address) => {
+                let stream = TcpStream::connect(address) ?;
+                let reader = stream.try_clone() ?;
+                Ok((DapConnection::new(Box::new(stream), None), Box::new(reader)))
+            }
+        }
+    }
+
+    fn new(writer: Box<dyn Write + Send>, child: Option<Child>) -> DapConnection {
+        DapConnection {
+            writer: writer,
+            child: child,
+            seq: 0,
+            pending: Vec::new(),
+            thread_id: 0
+        }
+    }
+
+    pub fn request(&mut self, command: &str, arguments: JsonValue, pending: DapPending) -> std::io::Result<()> {
+        self.seq += 1;
+        self.pending.push((self.seq, pending));
+        write_dap_message(&mut self.writer, &json_object(vec![
+            ("seq", self.seq.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments)
+        ]))
+    }
+
+    pub fn take_pending(&mut self, request_seq: u64) -> Option<DapPending> {
+        let index = self.pending.iter().position( | (seq, _) | *seq == request_seq) ?;
+        Some(self.pending.remove(index).1)
+    }
+
+    pub fn kill(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+            self.child = None;
+        }
+    }
+}
+
+pub fn json_object(fields: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(fields.into_iter().map( | (key, value) | (key.to_string(), value)).collect())
+}
+
+pub fn write_dap_message<W: Write + ?Sized>(writer: &mut W, message: &JsonValue) -> std::io::Result<()> {
+    let body = message.serialize_json();
+    writer.write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes()) ?;
+    writer.flush()
+}
+
+// none when the stream closes or doesn't speak the protocol
+pub fn read_dap_message<R: BufRead>(reader: &mut R) -> Option<JsonValue> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok() ? == 0 {
+            return None
+        }
+        let line = line.trim();
+        if line.len() == 0 {
+            if content_length.is_some() {
+                break
+            }
+            continue
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = Some(value.trim().parse::<usize>().ok() ?);
+        }
+    }
+    let mut body = vec![0u8; content_length ?];
+    reader.read_exact(&mut body).ok() ?;
+    DeJson::deserialize_json(std::str::from_utf8(&body).ok() ?).ok()
+}
@@ -3,11 +3,13 @@ use std::sync::{mpsc, Arc, Mutex};
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::str;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
 use makepad_microserde::*;
+use crate::hubsync::*;
+use crate::websocket::*;
 
-#[derive(Debug, Clone, SerBin, DeBin, PartialEq, SerRon, DeRon)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged, PartialEq, SerRon, DeRon)]
 pub enum HttpServerConfig {
     Offline,
     Localhost(u16),
@@ -20,7 +22,18 @@ pub struct HttpServerShared {
     pub terminate: bool,
     pub watcher_id: u64,
     pub watch_pending: Vec<(u64, mpsc::Sender<String>)>,
+    pub websockets: Vec<(u64, mpsc::Sender<WebSocketMessage>)>,
     pub files_read: Vec<String>,
+    pub file_cache: HashMap<String, HttpCachedFile>,
+}
+
+// what we know of a file we served, it stays as long as the size and the time it was changed
+// do. the compressed versions are made the first time a client asks for them
+pub struct HttpCachedFile {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub etag: String,
+    pub encoded: Vec<(HttpEncoding, Arc<Vec<u8>>)>,
 }
 
 #[derive(Default)]
@@ -30,20 +43,353 @@ pub struct HttpServer {
     pub shared: Arc<Mutex<HttpServerShared>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    // the request line and the headers, None when the connection closed or it is not http
+    pub fn read<R: BufRead>(reader: &mut R) -> Option<HttpRequest> {
+        let mut line = String::new();
+        // there can be empty lines between requests
+        while line.trim().len() == 0 {
+            line.clear();
+            if reader.read_line(&mut line).ok() ? == 0 {
+                return None
+            }
+        }
+        let mut parts = line.trim().split(' ').filter( | part | part.len() > 0);
+        let method = parts.next() ?.to_string();
+        let path = parts.next() ?.to_string();
+        let version = parts.next().unwrap_or("HTTP/1.0").to_string();
+        if !version.starts_with("HTTP/") {
+            return None
+        }
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).ok() ? == 0 || headers.len() > 100 {
+                return None
+            }
+            let header = line.trim();
+            if header.len() == 0 {
+                break
+            }
+            let colon = header.find(':') ?;
+            headers.push((header[..colon].trim().to_string(), header[colon + 1..].trim().to_string()));
+        }
+        Some(HttpRequest {method: method, path: path, version: version, headers: headers})
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find( | (key, _) | key.eq_ignore_ascii_case(name)).map( | (_, value) | value.as_ref())
+    }
+
+    // http/1.1 keeps the connection unless it says otherwise, 1.0 only when it asks
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header("Connection").unwrap_or("").to_lowercase();
+        if self.version == "HTTP/1.0" {
+            connection.contains("keep-alive")
+        }
+        else {
+            !connection.contains("close")
+        }
+    }
+
+    // one we can't read is as good as too large
+    pub fn content_length(&self) -> u64 {
+        self.header("Content-Length").map( | len | len.parse().unwrap_or(std::u64::MAX)).unwrap_or(0)
+    }
+
+    // the workspace/path of the url, without the query and with its %xx decoded
+    pub fn url(&self) -> Option<String> {
+        let path = self.path.split( | c | c == '?' || c == '#').next().unwrap_or("");
+        if !path.starts_with('/') {
+            return None
+        }
+        let bytes = path[1..].as_bytes();
+        let mut url = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                url.push(u8::from_str_radix(str::from_utf8(&bytes[i + 1..i + 3]).ok() ?, 16).ok() ?);
+                i += 3;
+            }
+            else {
+                url.push(bytes[i]);
+                i += 1;
+            }
+        }
+        let mut url = String::from_utf8(url).ok() ?;
+        if url.ends_with("/") || url.len() == 0 {
+            url.push_str("index.html");
+        }
+        Some(url)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HttpEncoding {
+    Identity,
+    Gzip,
+    Brotli
+}
+
+impl HttpEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HttpEncoding::Identity => "identity",
+            HttpEncoding::Gzip => "gzip",
+            HttpEncoding::Brotli => "br"
+        }
+    }
+
+    // the best encoding the client takes, by its q values and brotli before gzip when they are the same
+    pub fn negotiate(accept_encoding: Option<&str>) -> HttpEncoding {
+        let (mut brotli, mut gzip, mut any) = (None, None, None);
+        for coding in accept_encoding.unwrap_or("").split(',') {
+            let mut params = coding.split(';');
+            let name = params.next().unwrap_or("").trim().to_lowercase();
+            let q = params.filter_map( | param | {
+                let param = param.trim();
+                if param.starts_with("q=") {param[2..].parse::<f32>().ok()} else {None}
+            }).next().unwrap_or(1.0);
+            match name.as_ref() {
+                "br" => brotli = Some(q),
+                "gzip" | "x-gzip" => gzip = Some(q),
+                "*" => any = Some(q),
+                _ => ()
+            }
+        }
+        let brotli = brotli.or(any).unwrap_or(0.0);
+        let gzip = gzip.or(any).unwrap_or(0.0);
+        if brotli > 0.0 && brotli >= gzip {
+            HttpEncoding::Brotli
+        }
+        else if gzip > 0.0 {
+            HttpEncoding::Gzip
+        }
+        else {
+            HttpEncoding::Identity
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HttpEncoding::Identity => data.to_vec(),
+            HttpEncoding::Gzip => gzip_bytes(data),
+            HttpEncoding::Brotli => {
+                let mut result = Vec::new();
+                {
+                    // quality 5 keeps a rebuilt wasm file quick to serve, 11 takes seconds
+                    let mut writer = brotli::CompressorWriter::new(&mut result, 4096, 5, 22);
+                    let _ = writer.write_all(data);
+                }
+                result
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HttpRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable
+}
+
+impl HttpRange {
+    // one range of a Range header, inclusive. more than one range or one we cant read gets the whole file
+    pub fn parse(range: &str, len: u64) -> HttpRange {
+        let range = range.trim();
+        if !range.starts_with("bytes=") || range.contains(',') {
+            return HttpRange::Full
+        }
+        let range = &range[6..];
+        let dash = if let Some(dash) = range.find('-') {dash} else {return HttpRange::Full};
+        let (start, end) = (range[..dash].trim(), range[dash + 1..].trim());
+        if start.len() == 0 { // the last n bytes
+            return match end.parse::<u64>() {
+                Ok(0) => HttpRange::Unsatisfiable,
+                Ok(_) if len == 0 => HttpRange::Unsatisfiable,
+                Ok(suffix) => HttpRange::Partial(len.saturating_sub(suffix), len - 1),
+                Err(_) => HttpRange::Full
+            }
+        }
+        let start = if let Ok(start) = start.parse::<u64>() {start} else {return HttpRange::Full};
+        let end = if end.len() == 0 {
+            len.saturating_sub(1)
+        }
+        else if let Ok(end) = end.parse::<u64>() {
+            if end < start {
+                return HttpRange::Full
+            }
+            end.min(len.saturating_sub(1))
+        }
+        else {
+            return HttpRange::Full
+        };
+        if start >= len {
+            return HttpRange::Unsatisfiable
+        }
+        HttpRange::Partial(start, end)
+    }
+}
+
+// Sun, 06 Nov 1994 08:49:37 GMT, the only date format we send or read
+pub fn http_date(time: SystemTime) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let secs = time.duration_since(UNIX_EPOCH).map( | d | d.as_secs()).unwrap_or(0);
+    let days = (secs / 86400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// the seconds since 1970 of a date like http_date makes
+pub fn parse_http_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let parts: Vec<&str> = date.split_whitespace().collect();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None
+    }
+    let day: i64 = parts[1].parse().ok() ?;
+    let month = MONTHS.iter().position( | month | *month == parts[2]) ? as i64 + 1;
+    let year: i64 = parts[3].parse().ok() ?;
+    let time: Vec<u64> = parts[4].split(':').filter_map( | part | part.parse().ok()).collect();
+    if time.len() != 3 {
+        return None
+    }
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None
+    }
+    Some(days as u64 * 86400 + time[0] * 3600 + time[1] * 60 + time[2])
+}
+
+// the days since 1970 and the date, the algorithms of http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 {z} else {z - 146096} / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    (yoe + era * 400 + if month <= 2 {1} else {0}, month, day)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = if year >= 0 {year} else {year - 399} / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 {month - 3} else {month + 9}) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// a gzip member around the deflate stream, the deflate crate only has it behind a feature
+pub fn gzip_bytes(data: &[u8]) -> Vec<u8> {
+    let mut result = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    result.extend_from_slice(&deflate::deflate_bytes(data));
+    result.extend_from_slice(&crc32(data).to_le_bytes());
+    result.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    result
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for i in 0..256 {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {0xedb88320 ^ (c >> 1)} else {c >> 1};
+        }
+        table[i] = c;
+    }
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc = table[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffffffff
+}
+
+// the largest request body we skip over, we only serve GET and HEAD
+const HTTP_MAX_BODY_SIZE: u64 = 64 * 1024;
+
+// the websocket server pings when it sent nothing for this long
+const WEBSOCKET_PING_SECS: u64 = 15;
+
+// the file of a workspace/path url
+fn workspace_file_path(url: &str, workspaces: &Arc<Mutex<HashMap<String, String>>>) -> Option<String> {
+    let file_pos = url.find('/') ?;
+    let (workspace, rest) = url.split_at(file_pos);
+    let workspaces = workspaces.lock().ok() ?;
+    workspaces.get(workspace).map( | abs_path | format!("{}{}", abs_path, rest))
+}
+
+// files that are never served, like the key of the hub
+fn is_private_file(url: &str) -> bool {
+    url.to_lowercase().ends_with("key.ron")
+}
+
+// a message of the live reload channel, like {"type":"file_change","path":"workspace/file"}
+fn live_reload_json(msg_type: &str, fields: Vec<(&str, JsonValue)>) -> String {
+    let mut msg = JsonValue::Object(Vec::new());
+    msg.insert("type", msg_type.into());
+    for (key, value) in fields {
+        msg.insert(key, value);
+    }
+    msg.serialize_json()
+}
+
+fn mime_type(url: &str) -> (&'static str, bool) {
+    let ext = url.rsplit('.').next().unwrap_or("").to_lowercase();
+    match ext.as_ref() {
+        "html" => ("text/html", true),
+        "wasm" => ("application/wasm", true),
+        "js" => ("text/javascript", true),
+        "json" => ("application/json", true),
+        "css" => ("text/css", true),
+        "svg" => ("image/svg+xml", true),
+        "ttf" => ("font/ttf", true),
+        "rs" | "ron" | "toml" | "txt" | "md" => ("text/plain", true),
+        "png" => ("image/png", false),
+        "jpg" | "jpeg" => ("image/jpeg", false),
+        "ico" => ("image/x-icon", false),
+        _ => ("application/octet-stream", false)
+    }
+}
+
 impl HttpServer {
     pub fn start_http_server(config: &HttpServerConfig, workspaces_arc: Arc<Mutex<HashMap<String, String>>>) -> Option<HttpServer> {
-        
+
         let listen_address = match config {
             HttpServerConfig::Offline => return None,
             HttpServerConfig::Localhost(port) => SocketAddr::from(([127, 0, 0, 1], *port)),
             HttpServerConfig::Network(port) => SocketAddr::from(([0, 0, 0, 0], *port)),
             HttpServerConfig::InterfaceV4(port, ip) => SocketAddr::from((*ip, *port)),
         };
-        
+
         let listener = if let Ok(listener) = TcpListener::bind(listen_address.clone()) {listener} else {println!("Cannot bind http server port"); return None};
+        // port 0 gets one from the os
+        let listen_address = listener.local_addr().unwrap_or(listen_address);
         let workspaces = Arc::clone(&workspaces_arc);
         let shared = Arc::new(Mutex::new(HttpServerShared::default()));
-        
+
         let listen_thread = {
             let shared = Arc::clone(&shared);
             std::thread::spawn(move || {
@@ -53,131 +399,325 @@ impl HttpServer {
                             return
                         }
                     }
-                    let mut tcp_stream = tcp_stream.expect("Incoming stream failure");
-                    let (tx_write, rx_write) = mpsc::channel::<String>();
-                    let mut reader = BufReader::new(tcp_stream.try_clone().expect("Cannot clone tcp stream"));
+                    let tcp_stream = if let Ok(tcp_stream) = tcp_stream {tcp_stream} else {continue};
                     let workspaces = Arc::clone(&workspaces);
                     let shared = Arc::clone(&shared);
                     let _read_thread = std::thread::spawn(move || {
-                        
-                        let mut line = String::new();
-                        reader.read_line(&mut line).expect("http read line fail");
-                        if !line.starts_with("GET /") || line.len() < 10 {
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                            return
-                        }
-                        
-                        let line = &line[5..];
-                        let space = line.find(' ').expect("http space fail");
-                        let mut url = line[0..space].to_string();
-                        if url.ends_with("/"){
-                            url.push_str("index.html");
-                        }
-                        let url_lc = url.clone();
-                        url_lc.to_lowercase();
-                        if url_lc.ends_with("/key.ron") || url.find("..").is_some() || url.starts_with("/") {
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                            return
-                        }
-                        if url_lc.starts_with("$watch") { // its a watcher wait for the finish
-                            let mut watcher_id = 0;
-                            if let Ok(mut shared) = shared.lock() {
-                                shared.watcher_id += 1;
-                                watcher_id = shared.watcher_id;
-                                shared.watch_pending.push((watcher_id, tx_write));
-                            };
-                            match rx_write.recv_timeout(Duration::from_secs(30)) {
-                                Ok(msg) => { // let the watcher know
-                                    write_bytes_to_tcp_stream_no_error(&mut tcp_stream, msg.as_bytes());
-                                    let _ = tcp_stream.shutdown(Shutdown::Both);
-                                },
-                                Err(_) => { // close gracefully
-                                    write_bytes_to_tcp_stream_no_error(&mut tcp_stream, "HTTP/1.1 201 Retry\r\n\r\n".as_bytes());
-                                    let _ = tcp_stream.shutdown(Shutdown::Both);
-                                }
-                            }
-
-                            if let Ok(mut shared) = shared.lock() {
-                                for i in 0..shared.watch_pending.len() {
-                                    let (id, _) = &shared.watch_pending[i];
-                                    if *id == watcher_id {
-                                        shared.watch_pending.remove(i);
-                                        break
-                                    }
-                                }
-                            };
-                            return
-                        }
-
-                        if url.ends_with("favicon.ico"){
-                            let header =  "HTTP/1.1 200 OK\r\nContent-Type: image/x-icon\r\nTransfer-encoding: identity\r\nContent-Length: 0\r\n: close\r\n\r\n";
-                            write_bytes_to_tcp_stream_no_error(&mut tcp_stream, header.as_bytes());
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                            return
-                        }
-
-                        let file_path = if let Some(file_pos) = url.find('/') {
-                            let (workspace, rest) = url.split_at(file_pos);
-                            let (_, rest) = rest.split_at(1);
-                            if let Ok(workspaces) = workspaces.lock() {
-                                if let Some(abs_path) = workspaces.get(workspace) {
-                                    Some(format!("{}/{}", abs_path, rest))
-                                }
-                                else {None}
-                            }
-                            else {None}
-                        }
-                        else {None};
-                        
-                        if file_path.is_none() {
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                            return
-                        }
-                        let file_path = file_path.unwrap();
-                        let file_path = if file_path.ends_with("/"){
-                            format!("{}/{}", file_path, "index.html")
-                        }
-                        else{
-                            file_path
-                        };
-
-                        if let Ok(mut shared) = shared.lock() {
-                            if shared.files_read.iter().find( | v | **v == url).is_none() {
-                                shared.files_read.push(url.to_string());
-                            }
-                        };
-                        
-                        if let Ok(data) = std::fs::read(&file_path) {
-                            let mime_type = if url.ends_with(".html") {"text/html"}
-                            else if url.ends_with(".wasm") {"application/wasm"}
-                            else if url.ends_with(".js") {"text/javascript"}
-                            else {"application/octet-stream"};
-                            
-                            // write the header
-                            let header = format!(
-                                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-encoding: identity\r\nTransfer-encoding: identity\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
-                                mime_type,
-                                data.len()
-                            );
-                            write_bytes_to_tcp_stream_no_error(&mut tcp_stream, header.as_bytes());
-                            write_bytes_to_tcp_stream_no_error(&mut tcp_stream, &data);
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                        }
-                        else { // 404
-                            write_bytes_to_tcp_stream_no_error(&mut tcp_stream, "HTTP/1.1 404 NotFound\r\n".as_bytes());
-                            let _ = tcp_stream.shutdown(Shutdown::Both);
-                        }
+                        Self::handle_connection(tcp_stream, workspaces, shared);
                     });
                 }
             })
         };
         Some(HttpServer {
             listen_thread: Some(listen_thread),
-            listen_address: Some(listen_address.clone()),
+            listen_address: Some(listen_address),
             shared: shared,
         })
     }
-    
+
+    // the requests of one connection, as long as the client keeps it open
+    fn handle_connection(mut tcp_stream: TcpStream, workspaces: Arc<Mutex<HashMap<String, String>>>, shared: Arc<Mutex<HttpServerShared>>) {
+        let _ = tcp_stream.set_read_timeout(Some(Duration::from_secs(30)));
+        let mut reader = if let Ok(stream) = tcp_stream.try_clone() {BufReader::new(stream)} else {return};
+        while let Some(request) = HttpRequest::read(&mut reader) {
+            // we have no use for a body, but the next request comes after it. the length is the clients word,
+            // so it is never an allocation and a large one closes the connection
+            let len = request.content_length();
+            if len > HTTP_MAX_BODY_SIZE {
+                write_response(&mut tcp_stream, "413 Payload Too Large", "", &[], false, false);
+                break
+            }
+            if std::io::copy(&mut (&mut reader).take(len), &mut std::io::sink()).ok() != Some(len) {
+                break
+            }
+            let keep_alive = request.keep_alive();
+            let head = request.method == "HEAD";
+            if request.method != "GET" && !head {
+                write_response(&mut tcp_stream, "405 Method Not Allowed", "Allow: GET, HEAD\r\n", &[], false, false);
+                break
+            }
+            let url = if let Some(url) = request.url() {url} else {
+                write_response(&mut tcp_stream, "400 Bad Request", "", &[], false, false);
+                break
+            };
+            if url.find("..").is_some() || url.starts_with("/") || url.contains('\\') {
+                write_response(&mut tcp_stream, "400 Bad Request", "", &[], false, false);
+                break
+            }
+            if url.starts_with("$websocket") {
+                Self::handle_websocket(&mut tcp_stream, &mut reader, &request, &workspaces, &shared);
+                break
+            }
+            if url.starts_with("$watch") { // its a watcher wait for the finish
+                Self::handle_watch(&mut tcp_stream, &shared);
+                break
+            }
+            if url.ends_with("favicon.ico") {
+                write_response(&mut tcp_stream, "200 OK", "Content-Type: image/x-icon\r\n", &[], head, keep_alive);
+            }
+            else {
+                Self::handle_file(&mut tcp_stream, &request, &url, &workspaces, &shared, head, keep_alive);
+            }
+            if !keep_alive {
+                break
+            }
+        }
+        let _ = tcp_stream.shutdown(Shutdown::Both);
+    }
+
+    fn handle_watch(tcp_stream: &mut TcpStream, shared: &Arc<Mutex<HttpServerShared>>) {
+        let (tx_write, rx_write) = mpsc::channel::<String>();
+        let mut watcher_id = 0;
+        if let Ok(mut shared) = shared.lock() {
+            shared.watcher_id += 1;
+            watcher_id = shared.watcher_id;
+            shared.watch_pending.push((watcher_id, tx_write));
+        };
+        match rx_write.recv_timeout(Duration::from_secs(30)) {
+            Ok(msg) => { // let the watcher know
+                write_bytes_to_tcp_stream_no_error(tcp_stream, msg.as_bytes());
+            },
+            Err(_) => { // close gracefully
+                write_bytes_to_tcp_stream_no_error(tcp_stream, "HTTP/1.1 201 Retry\r\n\r\n".as_bytes());
+            }
+        }
+        if let Ok(mut shared) = shared.lock() {
+            if let Some(pos) = shared.watch_pending.iter().position( | (id, _) | *id == watcher_id) {
+                shared.watch_pending.remove(pos);
+            }
+        };
+    }
+
+    fn handle_file(
+        tcp_stream: &mut TcpStream,
+        request: &HttpRequest,
+        url: &str,
+        workspaces: &Arc<Mutex<HashMap<String, String>>>,
+        shared: &Arc<Mutex<HttpServerShared>>,
+        head: bool,
+        keep_alive: bool
+    ) {
+        let not_found = | tcp_stream: &mut TcpStream | write_response(tcp_stream, "404 Not Found", "", &[], head, keep_alive);
+        if is_private_file(url) {
+            return not_found(tcp_stream)
+        }
+        let file_path = if let Some(file_path) = workspace_file_path(url, workspaces) {file_path} else {return not_found(tcp_stream)};
+        let metadata = match std::fs::metadata(&file_path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return not_found(tcp_stream)
+        };
+        let modified = metadata.modified().ok();
+
+        Self::add_file_read(shared, url);
+        let (etag, data) = if let Some(etag) = Self::file_etag(shared, &file_path, &metadata) {etag} else {return not_found(tcp_stream)};
+        let last_modified = modified.map( | modified | http_date(modified));
+        let (mime_type, compressible) = mime_type(url);
+        let mut headers = format!("Content-Type: {}\r\nETag: {}\r\nCache-Control: no-cache\r\nAccept-Ranges: bytes\r\n", mime_type, etag);
+        if let Some(last_modified) = &last_modified {
+            headers.push_str(&format!("Last-Modified: {}\r\n", last_modified));
+        }
+        if compressible {
+            headers.push_str("Vary: Accept-Encoding\r\n");
+        }
+
+        // If-None-Match wins from If-Modified-Since when both are there
+        let not_modified = if let Some(if_none_match) = request.header("If-None-Match") {
+            if_none_match.split(',').any( | tag | {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        }
+        else if let (Some(since), Some(modified)) = (request.header("If-Modified-Since").and_then(parse_http_date), modified) {
+            modified.duration_since(UNIX_EPOCH).map( | d | d.as_secs() <= since).unwrap_or(false)
+        }
+        else {
+            false
+        };
+        if not_modified {
+            return write_response(tcp_stream, "304 Not Modified", &headers, &[], true, keep_alive)
+        }
+
+        let data = if let Some(data) = data {data} else {
+            if let Ok(read) = std::fs::read(&file_path) {read} else {return not_found(tcp_stream)}
+        };
+
+        // a range is of the file itself, If-Range asks for it only when the file is still the one it has
+        let range = match (request.header("Range"), request.header("If-Range")) {
+            (Some(range), None) => HttpRange::parse(range, data.len() as u64),
+            (Some(range), Some(if_range)) if if_range == etag || Some(if_range) == last_modified.as_ref().map( | m | m.as_ref()) => HttpRange::parse(range, data.len() as u64),
+            _ => HttpRange::Full
+        };
+        match range {
+            HttpRange::Partial(start, end) => {
+                headers.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", start, end, data.len()));
+                return write_response(tcp_stream, "206 Partial Content", &headers, &data[start as usize..end as usize + 1], head, keep_alive)
+            },
+            HttpRange::Unsatisfiable => {
+                headers.push_str(&format!("Content-Range: bytes */{}\r\n", data.len()));
+                return write_response(tcp_stream, "416 Range Not Satisfiable", &headers, &[], head, keep_alive)
+            },
+            HttpRange::Full => ()
+        }
+
+        let encoding = if compressible {HttpEncoding::negotiate(request.header("Accept-Encoding"))} else {HttpEncoding::Identity};
+        if encoding == HttpEncoding::Identity {
+            return write_response(tcp_stream, "200 OK", &headers, &data, head, keep_alive)
+        }
+        let cached = if let Ok(shared) = shared.lock() {
+            shared.file_cache.get(&file_path).filter( | cached | cached.etag == etag).and_then( | cached | {
+                cached.encoded.iter().find( | (e, _) | *e == encoding).map( | (_, data) | Arc::clone(data))
+            })
+        } else {None};
+        let encoded = if let Some(encoded) = cached {encoded} else {
+            let encoded = Arc::new(encoding.encode(&data));
+            if let Ok(mut shared) = shared.lock() {
+                if let Some(cached) = shared.file_cache.get_mut(&file_path) {
+                    if cached.etag == etag {
+                        cached.encoded.push((encoding, Arc::clone(&encoded)));
+                    }
+                }
+            }
+            encoded
+        };
+        headers.push_str(&format!("Content-Encoding: {}\r\n", encoding.name()));
+        write_response(tcp_stream, "200 OK", &headers, &encoded, head, keep_alive)
+    }
+
+    fn add_file_read(shared: &Arc<Mutex<HttpServerShared>>, url: &str) {
+        if let Ok(mut shared) = shared.lock() {
+            if shared.files_read.iter().find( | v | **v == url).is_none() {
+                shared.files_read.push(url.to_string());
+            }
+        };
+    }
+
+    // the etag is the digest of the file, we only read it again when it changed. the data is there when we did
+    fn file_etag(shared: &Arc<Mutex<HttpServerShared>>, file_path: &str, metadata: &std::fs::Metadata) -> Option<(String, Option<Vec<u8>>)> {
+        let modified = metadata.modified().ok();
+        let cached_etag = if let Ok(shared) = shared.lock() {
+            shared.file_cache.get(file_path).filter( | cached | cached.modified == modified && cached.len == metadata.len()).map( | cached | cached.etag.clone())
+        } else {None};
+        if let Some(etag) = cached_etag {
+            return Some((etag, None))
+        }
+        let read = std::fs::read(file_path).ok() ?;
+        let digest = file_digest(&read);
+        let etag = format!("\"{:016x}{:016x}-{:x}\"", digest.buf[0], digest.buf[1], digest.buf[4]);
+        if let Ok(mut shared) = shared.lock() {
+            shared.file_cache.insert(file_path.to_string(), HttpCachedFile {
+                modified: modified,
+                len: read.len() as u64,
+                etag: etag.clone(),
+                encoded: Vec::new()
+            });
+        }
+        Some((etag, Some(read)))
+    }
+
+    // the live reload channel. we push what the long poll of $watch gets and the start and end of builds,
+    // and the page can ask if a file it has is still the one we have. a writer thread sends what comes
+    // in on the channel and pings when it is quiet, a client that doesn't answer for long is closed
+    fn handle_websocket(
+        tcp_stream: &mut TcpStream,
+        reader: &mut BufReader<TcpStream>,
+        request: &HttpRequest,
+        workspaces: &Arc<Mutex<HashMap<String, String>>>,
+        shared: &Arc<Mutex<HttpServerShared>>
+    ) {
+        let is_upgrade = request.header("Upgrade").map( | upgrade | upgrade.eq_ignore_ascii_case("websocket")).unwrap_or(false);
+        let key = if let Some(key) = request.header("Sec-WebSocket-Key").filter( | _ | is_upgrade && request.method == "GET") {key} else {
+            return write_response(tcp_stream, "400 Bad Request", "", &[], false, false)
+        };
+        if request.header("Sec-WebSocket-Version") != Some("13") {
+            return write_response(tcp_stream, "426 Upgrade Required", "Sec-WebSocket-Version: 13\r\n", &[], false, false)
+        }
+        write_bytes_to_tcp_stream_no_error(tcp_stream, format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket_accept_key(key)
+        ).as_bytes());
+
+        let (tx_write, rx_write) = mpsc::channel::<WebSocketMessage>();
+        let mut socket_id = 0;
+        if let Ok(mut shared) = shared.lock() {
+            shared.watcher_id += 1;
+            socket_id = shared.watcher_id;
+            shared.websockets.push((socket_id, tx_write.clone()));
+        };
+        let mut write_stream = if let Ok(stream) = tcp_stream.try_clone() {stream} else {return};
+        let write_thread = std::thread::spawn(move || {
+            loop {
+                let msg = match rx_write.recv_timeout(Duration::from_secs(WEBSOCKET_PING_SECS)) {
+                    Ok(msg) => msg,
+                    Err(mpsc::RecvTimeoutError::Timeout) => WebSocketMessage::Ping(Vec::new()),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return
+                };
+                if write_stream.write_all(&msg.to_frame(None)).is_err() {
+                    let _ = write_stream.shutdown(Shutdown::Both);
+                    return
+                }
+                if let WebSocketMessage::Close(_) = msg {
+                    return
+                }
+            }
+        });
+
+        let _ = tcp_stream.set_read_timeout(Some(Duration::from_secs(WEBSOCKET_PING_SECS * 3)));
+        let mut socket = WebSocketReader::new(reader, true);
+        loop {
+            match socket.read_message() {
+                Ok(WebSocketMessage::Ping(data)) => {
+                    let _ = tx_write.send(WebSocketMessage::Pong(data));
+                },
+                Ok(WebSocketMessage::Text(text)) => {
+                    if let Some(reply) = Self::websocket_request(&text, workspaces, shared) {
+                        let _ = tx_write.send(WebSocketMessage::Text(reply));
+                    }
+                },
+                Ok(WebSocketMessage::Close(code)) => {
+                    let _ = tx_write.send(WebSocketMessage::Close(code));
+                    break
+                },
+                Ok(_) => (),
+                Err(err) => {
+                    if err.kind() == std::io::ErrorKind::InvalidData {
+                        let _ = tx_write.send(WebSocketMessage::Close(Some(1002)));
+                    }
+                    break
+                }
+            }
+        }
+        if let Ok(mut shared) = shared.lock() {
+            if let Some(pos) = shared.websockets.iter().position( | (id, _) | *id == socket_id) {
+                shared.websockets.remove(pos);
+            }
+        };
+        drop(tx_write);
+        let _ = write_thread.join();
+    }
+
+    // {"type":"reload_request","path":"workspace/file","etag":"..."} gets a file_change when the file
+    // is not the one with that etag, or up_to_date. we tell it of the changes of the file from then on
+    fn websocket_request(text: &str, workspaces: &Arc<Mutex<HashMap<String, String>>>, shared: &Arc<Mutex<HttpServerShared>>) -> Option<String> {
+        let request: JsonValue = DeJson::deserialize_json(text).ok() ?;
+        if request.get("type").and_then( | t | t.as_str()) != Some("reload_request") {
+            return None
+        }
+        let path = request.get("path").and_then( | path | path.as_str()) ?;
+        let etag = request.get("etag").and_then( | etag | etag.as_str());
+        let path = path.trim_start_matches('/');
+        let file_path = workspace_file_path(path, workspaces).filter( | _ | !path.contains("..")) ?;
+        let metadata = std::fs::metadata(&file_path).ok().filter( | metadata | metadata.is_file() && !is_private_file(path));
+        let current = metadata.and_then( | metadata | Self::file_etag(shared, &file_path, &metadata));
+        let msg_type = match current {
+            None => "not_found",
+            Some((current, _)) => {
+                Self::add_file_read(shared, path);
+                if Some(current.as_ref()) == etag {"up_to_date"} else {"file_change"}
+            }
+        };
+        Some(live_reload_json(msg_type, vec![("path", path.into())]))
+    }
+
     pub fn send_json_message(&mut self, json_msg: &str) {
         if let Ok(shared) = self.shared.lock() {
             for (_, tx) in &shared.watch_pending {
@@ -189,27 +729,45 @@ impl HttpServer {
                 let _ = tx.send(msg);
             }
         }
+        self.send_websocket_message(json_msg);
     }
-    
+
+    // only the websockets get these, a long poll misses what happens while it asks again
+    pub fn send_websocket_message(&mut self, json_msg: &str) {
+        if let Ok(shared) = self.shared.lock() {
+            for (_, tx) in &shared.websockets {
+                let _ = tx.send(WebSocketMessage::Text(json_msg.to_string()));
+            }
+        }
+    }
+
     pub fn send_file_change(&mut self, path: &str) {
         if let Ok(shared) = self.shared.lock() {
             if shared.files_read.iter().find( | v | **v == path).is_none() {
                 return
             }
         }
-        self.send_json_message(&format!("{{\"type\":\"file_change\",\"path\":\"{}\"}}", path));
+        self.send_json_message(&live_reload_json("file_change", vec![("path", path.into())]));
     }
-    
+
     pub fn send_build_start(&mut self) {
-        //self.send_json_message(&format!("{{\"type\":\"build_start\"}}"));
+        self.send_websocket_message(&live_reload_json("build_start", vec![]));
+    }
+
+    pub fn send_build_end(&mut self, success: bool) {
+        self.send_websocket_message(&live_reload_json("build_end", vec![("success", success.into())]));
     }
-    
+
     pub fn terminate(&mut self) {
         if let Ok(mut shared) = self.shared.lock() {
             shared.terminate = true;
             for (_, tx) in &shared.watch_pending {
                 let _ = tx.send("HTTP/1.1 201 Retry\r\n\r\n".to_string());
             }
+            // going away
+            for (_, tx) in &shared.websockets {
+                let _ = tx.send(WebSocketMessage::Close(Some(1001)));
+            }
         }
         if let Some(listen_address) = self.listen_address {
             self.listen_address = None;
@@ -220,6 +778,21 @@ impl HttpServer {
     }
 }
 
+// a response with a Content-Length, a HEAD or a 304 gets the headers of it and no body
+fn write_response(tcp_stream: &mut TcpStream, status: &str, headers: &str, body: &[u8], head: bool, keep_alive: bool) {
+    let header = format!(
+        "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: {}\r\n\r\n",
+        status,
+        headers,
+        body.len(),
+        if keep_alive {"keep-alive"} else {"close"}
+    );
+    write_bytes_to_tcp_stream_no_error(tcp_stream, header.as_bytes());
+    if !head {
+        write_bytes_to_tcp_stream_no_error(tcp_stream, body);
+    }
+}
+
 fn write_bytes_to_tcp_stream_no_error(tcp_stream: &mut TcpStream, bytes: &[u8]) {
     let bytes_total = bytes.len();
     let mut bytes_left = bytes_total;
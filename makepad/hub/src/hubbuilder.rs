@@ -1,7 +1,13 @@
 use crate::process::*;
+use crate::pty::*;
 use crate::hubmsg::*;
 use crate::hubrouter::*;
 use crate::hubclient::*;
+use crate::hubdiscovery::*;
+use crate::hubsync::*;
+use crate::cargotest::*;
+use crate::dapclient::*;
+use crate::lspclient::*;
 use crate::httpserver::*;
 use crate::wasmstrip::*;
 
@@ -18,6 +24,12 @@ pub struct HubBuilder {
     pub route_send: HubRouteSend,
     pub http_server: Arc<Mutex<Option<HttpServer>>>,
     pub workspaces: Arc<Mutex<HashMap<String, String>>>,
+    pub build_profiles: Arc<Mutex<Vec<HubBuildProfile>>>,
+    pub debug_adapter: Arc<Mutex<HubDebugAdapter>>,
+    pub debug_sessions: Arc<Mutex<Vec<HubDebugSession>>>,
+    pub language_server: Arc<Mutex<HubLanguageServer>>,
+    pub lsp_servers: Arc<Mutex<Vec<HubLspServer>>>,
+    pub terminals: Arc<Mutex<Vec<HubTerminal>>>,
     pub builder: String, 
     pub abs_cwd_path: String,
     pub processes: Arc<Mutex<Vec<HubProcess>>>,
@@ -33,6 +45,27 @@ pub struct HubProcess {
     process: Process,
 }
 
+pub struct HubTerminal {
+    uid: HubUid,
+    workspace: String,
+    command: Vec<String>,
+    pty: Pty,
+    // the last of what it wrote, for a UI that attaches later
+    replay: Arc<Mutex<Vec<u8>>>,
+}
+
+pub struct HubDebugSession {
+    uid: HubUid,
+    connection: DapConnection,
+}
+
+// the language server of a workspace, none when it couldn't start
+pub struct HubLspServer {
+    uid: HubUid,
+    workspace: String,
+    connection: Option<LspConnection>,
+}
+
 pub enum HubWsError {
     Error(String),
     LocErrors(Vec<LocMessage>)
@@ -41,12 +74,20 @@ pub enum HubWsError {
 const INCLUDED_FILES: &[&'static str] = &[".json", ".toml", ".js", ".rs", ".txt", ".text", ".ron", ".html"];
 const EXCLUDED_FILES: &[&'static str] = &["key.ron", "makepad_state.ron"];
 const EXCLUDED_DIRS: &[&'static str] = &["target", ".git", ".github", "edit_repo"];
+// how long a language server gets to answer initialize
+const LSP_INITIALIZE_TIMEOUT_SECS: u64 = 20;
 
 impl HubBuilder {
     
     pub fn run_builder_direct<F>(builder: &str, hub_router: &mut HubRouter, event_handler: F) -> HubRouteSend
     where F: Fn(&mut HubBuilder, FromHubMsg) -> Result<(), HubWsError> + Clone + Send + 'static {
         let workspaces = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+        let build_profiles = Arc::new(Mutex::new(HubBuildProfile::defaults()));
+        let debug_adapter = Arc::new(Mutex::new(HubDebugAdapter::default_adapter()));
+        let debug_sessions = Arc::new(Mutex::new(Vec::<HubDebugSession>::new()));
+        let language_server = Arc::new(Mutex::new(HubLanguageServer::default_server()));
+        let lsp_servers = Arc::new(Mutex::new(Vec::<HubLspServer>::new()));
+        let terminals = Arc::new(Mutex::new(Vec::<HubTerminal>::new()));
         let http_server = Arc::new(Mutex::new(None));
         let abs_cwd_path = format!("{}", std::env::current_dir().unwrap().display());
         let processes = Arc::new(Mutex::new(Vec::<HubProcess>::new()));
@@ -74,6 +115,12 @@ impl HubBuilder {
                             route_send: route_send.clone(),
                             http_server: Arc::clone(&http_server),
                             workspaces: Arc::clone(&workspaces),
+                            build_profiles: Arc::clone(&build_profiles),
+                            debug_adapter: Arc::clone(&debug_adapter),
+                            debug_sessions: Arc::clone(&debug_sessions),
+                            language_server: Arc::clone(&language_server),
+                            lsp_servers: Arc::clone(&lsp_servers),
+                            terminals: Arc::clone(&terminals),
                             processes: Arc::clone(&processes),
                             builder: builder.to_string(),
                             abs_cwd_path: abs_cwd_path.clone(),
@@ -112,20 +159,40 @@ impl HubBuilder {
     where F: Fn(&mut HubBuilder, FromHubMsg) -> Result<(), HubWsError> + Clone + Send + 'static {
         
         let workspaces = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+        let build_profiles = Arc::new(Mutex::new(HubBuildProfile::defaults()));
+        let debug_adapter = Arc::new(Mutex::new(HubDebugAdapter::default_adapter()));
+        let debug_sessions = Arc::new(Mutex::new(Vec::<HubDebugSession>::new()));
+        let language_server = Arc::new(Mutex::new(HubLanguageServer::default_server()));
+        let lsp_servers = Arc::new(Mutex::new(Vec::<HubLspServer>::new()));
+        let terminals = Arc::new(Mutex::new(Vec::<HubTerminal>::new()));
         let http_server = Arc::new(Mutex::new(None));
         let abs_cwd_path = format!("{}", std::env::current_dir().unwrap().display());
         let processes = Arc::new(Mutex::new(Vec::<HubProcess>::new()));
         
+        let announcer = HubAnnouncer::new(&digest, HUB_ANNOUNCE_PORT);
+        let mut address = in_address;
+        
         loop {
             
-            hub_log.msg("Builder connecting to {:?}", &in_address);
+            hub_log.msg("Builder connecting to {:?}", &address);
 
-            let mut hub_client = if let Ok(hub_client) = HubClient::connect_to_server(digest.clone(), in_address, hub_log.clone()) {
+            let mut hub_client = if let Ok(hub_client) = HubClient::connect_to_server(digest.clone(), address, hub_log.clone()) {
                 hub_client
             }
             else {
-                println!("Builder cannot connect to to {:?}, retrying", in_address);
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                println!("Builder cannot connect to to {:?}, retrying", address);
+                // while we wait, let UIs on the LAN know we're here, one may invite us to its hub
+                if let Some(announcer) = &announcer {
+                    let workspaces = workspaces.lock().unwrap().keys().cloned().collect();
+                    announcer.announce(builder, workspaces, address.port());
+                    if let Some(invite_address) = announcer.wait_for_invite(std::time::Duration::from_millis(500)) {
+                        println!("Builder invited to {:?}", invite_address);
+                        address = invite_address;
+                    }
+                }
+                else {
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                }
                 continue;
             };
             
@@ -155,6 +222,12 @@ impl HubBuilder {
                         route_send: route_send.clone(),
                         http_server: Arc::clone(&http_server),
                         workspaces: Arc::clone(&workspaces),
+                        build_profiles: Arc::clone(&build_profiles),
+                        debug_adapter: Arc::clone(&debug_adapter),
+                        debug_sessions: Arc::clone(&debug_sessions),
+                        language_server: Arc::clone(&language_server),
+                        lsp_servers: Arc::clone(&lsp_servers),
+                        terminals: Arc::clone(&terminals),
                         processes: Arc::clone(&processes),
                         builder: builder.to_string(),
                         abs_cwd_path: abs_cwd_path.clone(),
@@ -262,6 +335,12 @@ impl HubBuilder {
         };
         
         let workspaces = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+        let build_profiles = Arc::new(Mutex::new(HubBuildProfile::defaults()));
+        let debug_adapter = Arc::new(Mutex::new(HubDebugAdapter::default_adapter()));
+        let debug_sessions = Arc::new(Mutex::new(Vec::<HubDebugSession>::new()));
+        let language_server = Arc::new(Mutex::new(HubLanguageServer::default_server()));
+        let lsp_servers = Arc::new(Mutex::new(Vec::<HubLspServer>::new()));
+        let terminals = Arc::new(Mutex::new(Vec::<HubTerminal>::new()));
         let http_server = Arc::new(Mutex::new(None));
         let processes = Arc::new(Mutex::new(Vec::<HubProcess>::new()));
         let abs_cwd_path = format!("{}", std::env::current_dir().unwrap().display());
@@ -285,6 +364,12 @@ impl HubBuilder {
             builder: "".to_string(),
             processes: Arc::clone(&processes),
             workspaces: Arc::clone(&workspaces),
+            build_profiles: Arc::clone(&build_profiles),
+            debug_adapter: Arc::clone(&debug_adapter),
+            debug_sessions: Arc::clone(&debug_sessions),
+            language_server: Arc::clone(&language_server),
+            lsp_servers: Arc::clone(&lsp_servers),
+            terminals: Arc::clone(&terminals),
             abs_cwd_path: abs_cwd_path.clone()
         };
         
@@ -367,6 +452,24 @@ impl HubBuilder {
     }
     
     pub fn set_config(&mut self, _uid: HubUid, config: HubBuilderConfig) -> Result<(), HubWsError> {
+        if let Ok(mut build_profiles) = self.build_profiles.lock() {
+            *build_profiles = config.build_profiles;
+        };
+        if let Ok(mut debug_adapter) = self.debug_adapter.lock() {
+            *debug_adapter = config.debug_adapter;
+        };
+        // the servers start again with the new workspaces when a document opens
+        if let Ok(mut language_server) = self.language_server.lock() {
+            *language_server = config.language_server;
+        };
+        if let Ok(mut lsp_servers) = self.lsp_servers.lock() {
+            for server in lsp_servers.iter_mut() {
+                if let Some(connection) = &mut server.connection {
+                    connection.kill();
+                }
+            }
+            lsp_servers.truncate(0);
+        };
         // if we have a http server. just shut it down
         if let Ok(mut workspaces) = self.workspaces.lock() {
             *workspaces = config.workspaces;
@@ -419,6 +522,32 @@ impl HubBuilder {
                 ws.file_write(htc.from, uid, &path, data);
                 Ok(())
             },
+            HubMsg::SyncSignatureRequest {uid, path, block_size} => {
+                ws.sync_signature_request(htc.from, uid, &path, block_size);
+                Ok(())
+            },
+            HubMsg::SyncDeltaRequest {uid, path, block_size, signatures} => {
+                ws.sync_delta_request(htc.from, uid, &path, block_size, &signatures);
+                Ok(())
+            },
+            HubMsg::SyncApplyRequest {uid, path, block_size, delta} => {
+                ws.sync_apply_request(htc.from, uid, &path, block_size, &delta);
+                Ok(())
+            },
+            HubMsg::SyncDeleteRequest {uid, path} => {
+                ws.sync_delete_request(htc.from, uid, &path);
+                Ok(())
+            },
+            HubMsg::SyncRenameRequest {uid, from, to} => {
+                ws.sync_rename_request(htc.from, uid, &from, &to);
+                Ok(())
+            },
+            HubMsg::TestListRequest {uid, workspace, package} => {
+                ws.cargo_test_list(htc.from, uid, &workspace, &package)
+            },
+            HubMsg::TestRun {uid, workspace, package, tests} => {
+                ws.cargo_test(uid, &workspace, &package, &tests)
+            },
             HubMsg::BuildKill {uid} => {
                 ws.process_kill(uid);
                 Ok(())
@@ -432,6 +561,64 @@ impl HubBuilder {
                 ws.program_run(uid, &path, &v) ?;
                 Ok(())
             },
+            HubMsg::DebugRun {uid, path, args, breakpoints} => {
+                ws.debug_run(uid, &path, &args, &breakpoints)
+            },
+            HubMsg::DebugSetBreakpoints {uid, path, lines} => {
+                ws.debug_set_breakpoints(uid, &path, &lines)
+            },
+            HubMsg::DebugCommand {uid, command} => {
+                ws.debug_command(uid, command);
+                Ok(())
+            },
+            HubMsg::DebugVariablesRequest {uid, frame_id, reference} => {
+                ws.debug_variables(uid, frame_id, reference);
+                Ok(())
+            },
+            HubMsg::LspOpen {uid, path, text} => {
+                ws.lsp_open(uid, &path, &text)
+            },
+            HubMsg::LspChange {uid, path, edits} => {
+                ws.lsp_change(uid, &path, &edits)
+            },
+            HubMsg::LspClose {uid, path} => {
+                ws.lsp_close(uid, &path)
+            },
+            HubMsg::LspHoverRequest {uid, path, line, col} => {
+                ws.lsp_request(uid, &path, line, col, "textDocument/hover", LspPending::Hover {uid: uid})
+            },
+            HubMsg::LspDefinitionRequest {uid, path, line, col} => {
+                ws.lsp_request(uid, &path, line, col, "textDocument/definition", LspPending::Definition {uid: uid})
+            },
+            HubMsg::LspReferencesRequest {uid, path, line, col} => {
+                ws.lsp_request(uid, &path, line, col, "textDocument/references", LspPending::References {uid: uid})
+            },
+            HubMsg::LspCompletionRequest {uid, path, line, col} => {
+                ws.lsp_request(uid, &path, line, col, "textDocument/completion", LspPending::Completion {uid: uid})
+            },
+            HubMsg::TerminalOpen {uid, workspace, command, cols, rows} => {
+                ws.terminal_open(uid, &workspace, &command, cols, rows)
+            },
+            HubMsg::TerminalInput {uid, data} => {
+                ws.terminal(uid, | terminal | terminal.pty.write(&data));
+                Ok(())
+            },
+            HubMsg::TerminalResize {uid, cols, rows} => {
+                ws.terminal(uid, | terminal | terminal.pty.resize(cols, rows));
+                Ok(())
+            },
+            HubMsg::TerminalKill {uid} => {
+                ws.terminal(uid, | terminal | {terminal.pty.kill(); Ok(())});
+                Ok(())
+            },
+            HubMsg::TerminalListRequest {uid} => {
+                ws.terminal_list(uid);
+                Ok(())
+            },
+            HubMsg::TerminalAttach {uid} => {
+                ws.terminal_attach(uid);
+                Ok(())
+            },
             _ => Ok(())
         }
     }
@@ -444,6 +631,7 @@ impl HubBuilder {
                 }
             }
         };
+        self.debug_command(uid, HubDebugCommand::Stop);
     }
     
     pub fn workspace_split_from_path(&mut self, uid: HubUid, path: &str) -> Result<(String, String, String), HubWsError> {
@@ -498,93 +686,201 @@ impl HubBuilder {
             msg: HubMsg::ProgramBegin {uid: uid}
         });
         
-        fn starts_with_digit(val: &str) -> bool {
-            if val.len() <= 1 {
-                return false;
-            }
-            let c1 = val.chars().next().unwrap();
-            c1 >= '0' && c1 <= '9'
-        }
-        
         let mut stderr: Vec<String> = Vec::new();
         
-        fn try_parse_stderr(uid: HubUid, builder: &str, workspace: &str, stderr: &Vec<String>, route_send: &HubRouteSend) {
+        fn try_parse_stderr(uid: HubUid, builder: &str, workspace: &str, abs_dir: &str, stderr: &Vec<String>, route_send: &HubRouteSend) {
+            
+            fn send_error(uid: HubUid, route_send: &HubRouteSend, text: &str) {
+                route_send.send(ToHubMsg {
+                    to: HubMsgTo::UI,
+                    msg: HubMsg::LogItem {
+                        uid: uid,
+                        item: HubLogItem::from_output(HubLogLevel::Error, text)
+                    }
+                });
+            }
             
             let mut tracing_panic = false;
             let mut panic_stack = Vec::new();
+            // indented lines go with the line before them, like the notes under an error
+            let mut group: Option<String> = None;
             for line in stderr {
-                let mut trimmed = line.trim_start().to_string();
-                trimmed.retain( | c | c != '\0');
+                let mut line = line.clone();
+                line.retain( | c | c != '\0');
+                let plain = if line.contains('\x1b') {HubStyledText::from_ansi(&line).body} else {line.clone()};
+                let trimmed = plain.trim_start().to_string();
                 if tracing_panic == false && (trimmed.starts_with("thread '") || trimmed.starts_with("0:")) { // this is how we recognise a stacktrace start..Very sturdy.
+                    if let Some(group) = group.take() {
+                        send_error(uid, route_send, &group);
+                    }
                     tracing_panic = true;
                     panic_stack.truncate(0);
                 }
                 if tracing_panic {
                     panic_stack.push(trimmed);
                 }
+                else if group.is_some() && plain.starts_with(char::is_whitespace) && plain.trim().len() > 0 {
+                    group.as_mut().unwrap().push_str(&line);
+                }
                 else {
-                    route_send.send(ToHubMsg {
-                        to: HubMsgTo::UI,
-                        msg: HubMsg::LogItem {
-                            uid: uid,
-                            item: HubLogItem::Error(trimmed.clone())
-                        }
-                    });
+                    if let Some(group) = group.take() {
+                        send_error(uid, route_send, &group);
+                    }
+                    group = Some(line.trim_start().to_string());
                 }
             }
+            if let Some(group) = group.take() {
+                send_error(uid, route_send, &group);
+            }
+            
+            if panic_stack.len() == 0 {
+                return
+            }
             
-            let mut path = None;
-            let mut line = 0;
-            let mut rendered = Vec::new();
-            if panic_stack.len()>0 {rendered.push(panic_stack[0].clone())};
-            let mut last_fn_name = String::new();
+            let mut message = Vec::new();
+            let mut location = None;
+            let mut frames: Vec<HubBacktraceFrame> = Vec::new();
             for panic_line in &panic_stack {
-                if panic_line.starts_with("at ")
-                    && !panic_line.starts_with("at /")
-                    && !panic_line.starts_with("at src/libstd/")
-                    && !panic_line.starts_with("at src/libcore/")
-                    && !panic_line.starts_with("at src/libpanic_unwind/") {
-                    if let Some(end) = panic_line.find(":") {
-                        let proc_path = format!("{}/{}/{}", builder, workspace, panic_line.get(3..end).unwrap().to_string());
-                        
-                        let proc_line = if let Ok(pl) = panic_line.get((end + 1)..(panic_line.len() - 1)).unwrap().parse::<usize>(){pl}else{0};
-                        
-                        rendered.push(format!("{}:{} - {}", proc_path, proc_line, last_fn_name));
-                        if path.is_none() {
-                            path = Some(proc_path);
-                            line = proc_line
-                        }
+                let text = panic_line.trim();
+                if text.len() == 0 || text.starts_with("note:") || text == "stack backtrace:" {
+                    continue
+                }
+                if let Some(function) = frame_function(text) {
+                    frames.push(HubBacktraceFrame::new(function));
+                }
+                else if text.starts_with("at ") && frames.len() > 0 {
+                    let (file, line, col) = split_location(&text[3..]);
+                    let frame = frames.last_mut().unwrap();
+                    if frame.file.len() == 0 {
+                        frame.path = workspace_path(builder, workspace, abs_dir, &file);
+                        frame.user_code = frame.path.is_some();
+                        frame.file = file;
+                        frame.line = line;
+                        frame.col = col;
                     }
                 }
-                else if starts_with_digit(panic_line) {
-                    if let Some(pos) = panic_line.find(" 0x") {
-                        last_fn_name = panic_line.get((pos + 15)..).unwrap().to_string();
+                else if frames.len() > 0 { // a function that got inlined into the frame before
+                    frames.push(HubBacktraceFrame::new(text.to_string()));
+                }
+                else {
+                    if location.is_none() {
+                        location = panic_location(text).map( | loc | split_location(loc));
                     }
+                    message.push(text.to_string());
                 }
             }
-            rendered.push("\n".to_string());
             
-            if panic_stack.len()<3 {
-                return;
+            // the first frame of our own code, or where it panicked when there is no stack
+            let top = frames.iter().find( | frame | frame.user_code).map( | frame | (frame.path.clone().unwrap(), frame.line, frame.col));
+            let top = top.or_else( | | {
+                let (file, line, col) = location.clone() ?;
+                Some((workspace_path(builder, workspace, abs_dir, &file) ?, line, col))
+            });
+            if top.is_none() && frames.len() == 0 {
+                send_error(uid, route_send, &panic_stack.join(""));
+                return
+            }
+            let (path, line, col) = top.unwrap_or((String::new(), 0, 0));
+            
+            let body = message.join(" ");
+            let mut rendered = vec![format!("{}\n", body)];
+            for frame in &frames {
+                if let Some(path) = &frame.path {
+                    rendered.push(format!("{}:{} - {}\n", path, frame.line, frame.function));
+                }
             }
             
             route_send.send(ToHubMsg {
                 to: HubMsgTo::UI,
                 msg: HubMsg::LogItem {
                     uid: uid,
-                    item: HubLogItem::LocPanic(LocMessage {
-                        path: if let Some(path) = path {path}else {"".to_string()},
-                        line: line,
-                        col: 1,
-                        range: None,
-                        body: rendered[0].clone(),
-                        rendered: Some(rendered.join("")),
-                        explanation: Some(panic_stack[1..].join("")),
-                    })
+                    item: HubLogItem::Backtrace {
+                        panic: LocMessage {
+                            path: path,
+                            line: line,
+                            col: col.max(1),
+                            range: None,
+                            body: body,
+                            rendered: Some(rendered.join("")),
+                            explanation: Some(panic_stack[1..].join("")),
+                            fixes: Vec::new(),
+                        },
+                        frames: frames
+                    }
                 }
             });
         }
         
+        // N: function, or N: 0x7f3a2b1c - function in a full backtrace. the hash rustc puts
+        // at the end of the symbol goes
+        fn frame_function(text: &str) -> Option<String> {
+            let colon = text.find(": ") ?;
+            if colon == 0 || !text[..colon].chars().all( | c | c.is_ascii_digit()) {
+                return None
+            }
+            let rest = text[colon + 2..].trim();
+            let mut function = if rest.starts_with("0x") {
+                rest.splitn(2, " - ").nth(1).unwrap_or("<unknown>")
+            } else {
+                rest
+            };
+            if let Some(pos) = function.rfind("::h") {
+                let hash = &function[pos + 3..];
+                if hash.len() == 16 && hash.chars().all( | c | c.is_ascii_hexdigit()) {
+                    function = &function[..pos];
+                }
+            }
+            Some(function.to_string())
+        }
+        
+        // file:line:col, or file:line in older backtraces
+        fn split_location(loc: &str) -> (String, usize, usize) {
+            let mut file = loc.trim();
+            let mut nums = Vec::new();
+            while nums.len() < 2 {
+                if let Some(pos) = file.rfind(':') {
+                    if let Ok(num) = file[pos + 1..].parse::<usize>() {
+                        nums.insert(0, num);
+                        file = &file[..pos];
+                        continue
+                    }
+                }
+                break
+            }
+            (file.to_string(), nums.get(0).cloned().unwrap_or(0), nums.get(1).cloned().unwrap_or(0))
+        }
+        
+        // thread 'main' panicked at src/main.rs:2:5: with the message on the next line, or
+        // thread 'main' panicked at 'message', src/main.rs:2:5 before rust 1.73
+        fn panic_location(text: &str) -> Option<&str> {
+            let at = text.find(" panicked at ") ? + 13;
+            let rest = text[at..].trim().trim_end_matches(':');
+            if rest.starts_with('\'') {
+                return rest.rsplit(", ").next()
+            }
+            Some(rest)
+        }
+        
+        // files of the workspace as builder/workspace path. the program runs in the workspace,
+        // so that is what ./ is. std has its files in /rustc/ or src/libstd in older versions
+        fn workspace_path(builder: &str, workspace: &str, abs_dir: &str, file: &str) -> Option<String> {
+            let file = file.replace("\\", "/");
+            let abs_dir = abs_dir.replace("\\", "/");
+            let rel = if file.starts_with("./") {
+                file[2..].to_string()
+            }
+            else if file.starts_with(&abs_dir) && file[abs_dir.len()..].starts_with('/') {
+                file[abs_dir.len() + 1..].to_string()
+            }
+            else if file.starts_with('/') || file.get(1..2) == Some(":") || file.starts_with("src/lib") || file.starts_with("library/") {
+                return None
+            }
+            else {
+                file
+            };
+            Some(format!("{}/{}/{}", builder, workspace, rel))
+        }
+        
         loop {
             let result = rx_line.recv_timeout(std::time::Duration::from_millis(100));
             match result {
@@ -595,7 +891,7 @@ impl HubBuilder {
                         }
                         else {
                             if stderr.len() > 0 {
-                                try_parse_stderr(uid, &builder, &workspace, &stderr, &route_mode);
+                                try_parse_stderr(uid, &builder, &workspace, &abs_dir, &stderr, &route_mode);
                                 stderr.truncate(0);
                             }
 
@@ -603,7 +899,7 @@ impl HubBuilder {
                                 to: HubMsgTo::UI,
                                 msg: HubMsg::LogItem {
                                     uid: uid,
-                                    item: HubLogItem::Message(line.clone())
+                                    item: HubLogItem::from_output(HubLogLevel::Message, &line)
                                 }
                             });
                         }
@@ -611,7 +907,7 @@ impl HubBuilder {
                 },
                 Err(err) => {
                     if stderr.len() > 0 {
-                        try_parse_stderr(uid, &builder, &workspace, &stderr, &route_mode);
+                        try_parse_stderr(uid, &builder, &workspace, &abs_dir, &stderr, &route_mode);
                         stderr.truncate(0);
                     }
                     if let RecvTimeoutError::Disconnected = err {
@@ -638,13 +934,734 @@ impl HubBuilder {
     }
     
     
+    // runs a program under the debug adapter of the builder config, until it ends or is stopped
+    pub fn debug_run(&mut self, uid: HubUid, path: &str, args: &[String], breakpoints: &[HubBreakpoint]) -> Result<(), HubWsError> {
+        let (abs_dir, _workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        let adapter = if let Ok(adapter) = self.debug_adapter.lock() {adapter.clone()} else {HubDebugAdapter::default_adapter()};
+        let (mut connection, reader) = match DapConnection::connect(&adapter, &abs_dir) {
+            Ok(connection) => connection,
+            Err(e) => return Err(
+                self.error(uid, format!("Builder {} cannot start debug adapter {:?} {:?}", self.builder, adapter, e))
+            )
+        };
+        let _ = connection.request("initialize", json_object(vec![
+            ("clientID", "makepad".into()),
+            ("adapterID", "makepad".into()),
+            ("linesStartAt1", true.into()),
+            ("columnsStartAt1", true.into()),
+            ("pathFormat", "path".into())
+        ]), DapPending::Initialize);
+        if let Ok(mut sessions) = self.debug_sessions.lock() {
+            sessions.push(HubDebugSession {
+                uid: uid,
+                connection: connection
+            });
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::ProgramBegin {uid: uid}
+        });
+        
+        let program = format!("{}/{}", abs_dir, sub_path);
+        let mut reader = std::io::BufReader::new(reader);
+        while let Some(message) = read_dap_message(&mut reader) {
+            if !self.debug_message(uid, &message, &program, &abs_dir, args, breakpoints) {
+                break
+            }
+        }
+        
+        if let Ok(mut sessions) = self.debug_sessions.lock() {
+            if let Some(index) = sessions.iter().position( | s | s.uid == uid) {
+                sessions[index].connection.kill();
+                sessions.remove(index);
+            }
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::ProgramEnd {uid: uid}
+        });
+        Ok(())
+    }
+    
+    // handles a message from the debug adapter, false ends the session
+    fn debug_message(&mut self, uid: HubUid, message: &JsonValue, program: &str, abs_dir: &str, args: &[String], breakpoints: &[HubBreakpoint]) -> bool {
+        let body = &message["body"];
+        match message["type"].as_str() {
+            Some("event") => match message["event"].as_str().unwrap_or("") {
+                "initialized" => {
+                    // the breakpoints go in per file, after that the program can start
+                    let mut paths: Vec<&str> = Vec::new();
+                    for breakpoint in breakpoints {
+                        if !paths.contains(&breakpoint.path.as_ref()) {
+                            paths.push(&breakpoint.path);
+                        }
+                    }
+                    for path in paths {
+                        let lines: Vec<usize> = breakpoints.iter().filter( | b | b.path == path).map( | b | b.line).collect();
+                        let _ = self.debug_set_breakpoints(uid, path, &lines);
+                    }
+                    self.debug_request(uid, "configurationDone", json_object(vec![]), DapPending::Other);
+                },
+                "stopped" => {
+                    let thread_id = body["threadId"].as_u64().unwrap_or(0);
+                    if let Ok(mut sessions) = self.debug_sessions.lock() {
+                        if let Some(session) = sessions.iter_mut().find( | s | s.uid == uid) {
+                            session.connection.thread_id = thread_id;
+                        }
+                    };
+                    self.debug_request(uid, "stackTrace", json_object(vec![
+                        ("threadId", thread_id.into()),
+                        ("levels", 64u64.into())
+                    ]), DapPending::StackTrace {reason: body["reason"].as_str().unwrap_or("").to_string()});
+                },
+                "continued" => {
+                    self.route_send.send(ToHubMsg {
+                        to: HubMsgTo::UI,
+                        msg: HubMsg::DebugContinued {uid: uid}
+                    });
+                },
+                "output" => {
+                    // what the program prints, the console output is the adapter talking
+                    let output = body["output"].as_str().unwrap_or("");
+                    for line in output.split_inclusive('\n') {
+                        let item = match body["category"].as_str() {
+                            Some("stdout") => HubLogItem::from_output(HubLogLevel::Message, line),
+                            Some("stderr") => HubLogItem::from_output(HubLogLevel::Error, line),
+                            _ => continue
+                        };
+                        self.route_send.send(ToHubMsg {
+                            to: HubMsgTo::UI,
+                            msg: HubMsg::LogItem {uid: uid, item: item}
+                        });
+                    }
+                },
+                "exited" => {
+                    let code = body["exitCode"].as_i64().unwrap_or(0);
+                    self.message(uid, format!("Program exited with code {}", code));
+                },
+                "terminated" => {
+                    // the session ends with the response
+                    if !self.debug_request(uid, "disconnect", json_object(vec![]), DapPending::Disconnect) {
+                        return false
+                    }
+                },
+                _ => ()
+            },
+            Some("response") => {
+                let pending = if let Ok(mut sessions) = self.debug_sessions.lock() {
+                    sessions.iter_mut().find( | s | s.uid == uid).and_then( | s | s.connection.take_pending(message["request_seq"].as_u64().unwrap_or(0)))
+                } else {None};
+                let pending = if let Some(pending) = pending {pending} else {return true};
+                let success = message["success"].as_bool().unwrap_or(false);
+                if !success {
+                    let command = message["command"].as_str().unwrap_or("");
+                    let error = message["message"].as_str().unwrap_or("");
+                    self.route_send.send(ToHubMsg {
+                        to: HubMsgTo::UI,
+                        msg: HubMsg::LogItem {uid: uid, item: HubLogItem::Error(format!("Debugger {} failed: {}\n", command, error))}
+                    });
+                }
+                match pending {
+                    DapPending::Initialize => {
+                        if !success {
+                            return false
+                        }
+                        self.debug_request(uid, "launch", json_object(vec![
+                            ("program", program.into()),
+                            ("args", JsonValue::Array(args.iter().map( | a | a.as_str().into()).collect())),
+                            ("cwd", abs_dir.into())
+                        ]), DapPending::Launch);
+                    },
+                    DapPending::Launch => if !success {
+                        return false
+                    },
+                    DapPending::Disconnect => {
+                        return false
+                    },
+                    DapPending::StackTrace {reason} => {
+                        let frames = self.debug_frames(body);
+                        if let Some(frame_id) = frames.first().map( | f | f.id) {
+                            self.debug_request(uid, "scopes", json_object(vec![("frameId", frame_id.into())]), DapPending::Scopes {
+                                frame_id: frame_id,
+                                stopped: Some((reason, frames))
+                            });
+                        }
+                        else {
+                            self.debug_variables_done(uid, 0, 0, Some((reason, frames)), Vec::new());
+                        }
+                    },
+                    DapPending::Scopes {frame_id, stopped} => {
+                        // the first scope has the locals
+                        let reference = body.query("scopes[0]").and_then( | s | s["variablesReference"].as_u64());
+                        if let Some(reference) = reference {
+                            self.debug_request(uid, "variables", json_object(vec![("variablesReference", reference.into())]), DapPending::Variables {
+                                frame_id: frame_id,
+                                reference: 0,
+                                stopped: stopped
+                            });
+                        }
+                        else {
+                            self.debug_variables_done(uid, frame_id, 0, stopped, Vec::new());
+                        }
+                    },
+                    DapPending::Variables {frame_id, reference, stopped} => {
+                        self.debug_variables_done(uid, frame_id, reference, stopped, HubVariable::from_dap(body));
+                    },
+                    DapPending::Other => ()
+                }
+            },
+            _ => ()
+        }
+        true
+    }
+    
+    fn debug_variables_done(&mut self, uid: HubUid, frame_id: u64, reference: u64, stopped: Option<(String, Vec<HubStackFrame>)>, variables: Vec<HubVariable>) {
+        let msg = if let Some((reason, frames)) = stopped {
+            let thread_id = if let Ok(sessions) = self.debug_sessions.lock() {
+                sessions.iter().find( | s | s.uid == uid).map( | s | s.connection.thread_id).unwrap_or(0)
+            } else {0};
+            HubMsg::DebugStopped {uid: uid, reason: reason, thread_id: thread_id, frames: frames, variables: variables}
+        }
+        else {
+            HubMsg::DebugVariablesResponse {uid: uid, frame_id: frame_id, reference: reference, variables: variables}
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: msg
+        });
+    }
+    
+    fn debug_frames(&self, body: &JsonValue) -> Vec<HubStackFrame> {
+        let mut frames = Vec::new();
+        if let Some(list) = body.get("stackFrames").and_then( | f | f.as_array()) {
+            for frame in list {
+                frames.push(HubStackFrame {
+                    id: frame["id"].as_u64().unwrap_or(0),
+                    name: frame["name"].as_str().unwrap_or("").to_string(),
+                    path: frame.query("source.path").and_then( | p | p.as_str()).and_then( | p | self.workspace_path_from_abs(p)),
+                    line: frame["line"].as_u64().unwrap_or(0) as usize,
+                    col: frame["column"].as_u64().unwrap_or(0) as usize
+                });
+            }
+        }
+        frames
+    }
+    
+    // the builder/workspace/path of a file in one of the workspaces
+    fn workspace_path_from_abs(&self, abs_path: &str) -> Option<String> {
+        let abs_path = abs_path.replace("\\", "/");
+        let workspaces = self.workspaces.lock().ok() ?;
+        for (name, abs_dir) in workspaces.iter() {
+            let abs_dir = abs_dir.replace("\\", "/");
+            if abs_path.starts_with(&abs_dir) && abs_path[abs_dir.len()..].starts_with('/') {
+                return Some(format!("{}/{}{}", self.builder, name, &abs_path[abs_dir.len()..]))
+            }
+        }
+        None
+    }
+    
+    // sends a request to the adapter of a debug session, false when there is no session
+    fn debug_request(&mut self, uid: HubUid, command: &str, arguments: JsonValue, pending: DapPending) -> bool {
+        if let Ok(mut sessions) = self.debug_sessions.lock() {
+            if let Some(session) = sessions.iter_mut().find( | s | s.uid == uid) {
+                if session.connection.request(command, arguments, pending).is_err() {
+                    session.connection.kill();
+                }
+                return true
+            }
+        }
+        false
+    }
+    
+    pub fn debug_set_breakpoints(&mut self, uid: HubUid, path: &str, lines: &[usize]) -> Result<(), HubWsError> {
+        let (abs_dir, _workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        let breakpoints = lines.iter().map( | line | json_object(vec![("line", (*line as u64).into())])).collect();
+        self.debug_request(uid, "setBreakpoints", json_object(vec![
+            ("source", json_object(vec![("path", format!("{}/{}", abs_dir, sub_path).into())])),
+            ("breakpoints", JsonValue::Array(breakpoints))
+        ]), DapPending::Other);
+        Ok(())
+    }
+    
+    pub fn debug_command(&mut self, uid: HubUid, command: HubDebugCommand) {
+        let thread_id = if let Ok(sessions) = self.debug_sessions.lock() {
+            sessions.iter().find( | s | s.uid == uid).map( | s | s.connection.thread_id).unwrap_or(0)
+        } else {0};
+        let (arguments, pending) = match command {
+            HubDebugCommand::Stop => (json_object(vec![("terminateDebuggee", true.into())]), DapPending::Disconnect),
+            _ => (json_object(vec![("threadId", thread_id.into())]), DapPending::Other)
+        };
+        self.debug_request(uid, command.dap_command(), arguments, pending);
+    }
+    
+    pub fn debug_variables(&mut self, uid: HubUid, frame_id: u64, reference: u64) {
+        if reference == 0 {
+            self.debug_request(uid, "scopes", json_object(vec![("frameId", frame_id.into())]), DapPending::Scopes {
+                frame_id: frame_id,
+                stopped: None
+            });
+        }
+        else {
+            self.debug_request(uid, "variables", json_object(vec![("variablesReference", reference.into())]), DapPending::Variables {
+                frame_id: frame_id,
+                reference: reference,
+                stopped: None
+            });
+        }
+    }
+    
+    // starts the language server of a workspace if it isn't running yet. it is initialized
+    // before this returns, so documents can go to it right away. a server that doesn't answer
+    // initialize in time is killed, and the next document that opens tries again
+    fn lsp_start(&mut self, uid: HubUid, workspace: &str, abs_dir: &str) -> Result<(), HubWsError> {
+        if let Ok(servers) = self.lsp_servers.lock() {
+            if servers.iter().any( | s | s.workspace == workspace) {
+                return Ok(())
+            }
+        };
+        let server = if let Ok(server) = self.language_server.lock() {server.clone()} else {HubLanguageServer::Off};
+        if server == HubLanguageServer::Off {
+            if let Ok(mut servers) = self.lsp_servers.lock() {
+                servers.push(HubLspServer {uid: uid, workspace: workspace.to_string(), connection: None});
+            };
+            return Ok(())
+        }
+        let started = LspConnection::connect(&server, abs_dir).and_then( | (mut connection, reader) | {
+            let root_uri = file_uri(abs_dir);
+            let id = connection.request("initialize", json_object(vec![
+                ("processId", (std::process::id() as u64).into()),
+                ("rootUri", root_uri.as_str().into()),
+                ("workspaceFolders", JsonValue::Array(vec![json_object(vec![("uri", root_uri.as_str().into()), ("name", workspace.into())])])),
+                ("capabilities", json_object(vec![
+                    ("general", json_object(vec![("positionEncodings", JsonValue::Array(vec!["utf-32".into()]))])),
+                    ("textDocument", json_object(vec![
+                        ("hover", json_object(vec![("contentFormat", JsonValue::Array(vec!["plaintext".into(), "markdown".into()]))])),
+                        ("completion", json_object(vec![("completionItem", json_object(vec![("snippetSupport", false.into())]))])),
+                        ("publishDiagnostics", json_object(vec![])),
+                        ("definition", json_object(vec![])),
+                        ("references", json_object(vec![]))
+                    ]))
+                ]))
+            ]), LspPending::Initialize) ?;
+            
+            // the reader thread hands us what it reads until the server answered initialize,
+            // and carries on with the rest once we put the connection in lsp_servers
+            let (tx_handshake, rx_handshake) = mpsc::channel();
+            let (tx_started, rx_started) = mpsc::channel();
+            let mut hub_builder = HubBuilder {
+                route_send: self.route_send.clone(),
+                http_server: Arc::clone(&self.http_server),
+                workspaces: Arc::clone(&self.workspaces),
+                build_profiles: Arc::clone(&self.build_profiles),
+                debug_adapter: Arc::clone(&self.debug_adapter),
+                debug_sessions: Arc::clone(&self.debug_sessions),
+                language_server: Arc::clone(&self.language_server),
+                lsp_servers: Arc::clone(&self.lsp_servers),
+                terminals: Arc::clone(&self.terminals),
+                processes: Arc::clone(&self.processes),
+                builder: self.builder.clone(),
+                abs_cwd_path: self.abs_cwd_path.clone(),
+            };
+            let workspace = workspace.to_string();
+            std::thread::spawn(move || {
+                let mut reader = std::io::BufReader::new(reader);
+                while let Some(message) = read_dap_message(&mut reader) {
+                    let initialized = message.get("method").is_none() && message["id"].as_u64() == Some(id);
+                    if tx_handshake.send(message).is_err() || initialized {
+                        break
+                    }
+                }
+                drop(tx_handshake);
+                if rx_started.recv().is_err() {
+                    return
+                }
+                while let Some(message) = read_dap_message(&mut reader) {
+                    hub_builder.lsp_message(&workspace, &message);
+                }
+                // it starts again with the next document that opens
+                if let Ok(mut servers) = hub_builder.lsp_servers.lock() {
+                    servers.retain( | s | s.uid != uid || s.workspace != workspace);
+                };
+            });
+            
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(LSP_INITIALIZE_TIMEOUT_SECS);
+            let handshake = ( | | {
+                loop {
+                    let message = match rx_handshake.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())) {
+                        Ok(message) => message,
+                        Err(RecvTimeoutError::Timeout) => return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "language server didn't answer initialize")),
+                        Err(RecvTimeoutError::Disconnected) => return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "language server exited"))
+                    };
+                    if message.get("method").is_none() && message["id"].as_u64() == Some(id) {
+                        connection.take_pending(id);
+                        connection.set_position_encoding(&message["result"]);
+                        break
+                    }
+                    if message.get("method").is_some() && message.get("id").is_some() {
+                        connection.respond_empty(&message) ?;
+                    }
+                }
+                connection.notify("initialized", json_object(vec![]))
+            })();
+            // killing it ends the reader thread too
+            if let Err(e) = handshake {
+                connection.kill();
+                return Err(e)
+            }
+            Ok((connection, tx_started))
+        });
+        match started {
+            Ok((connection, tx_started)) => {
+                if let Ok(mut servers) = self.lsp_servers.lock() {
+                    servers.push(HubLspServer {uid: uid, workspace: workspace.to_string(), connection: Some(connection)});
+                };
+                let _ = tx_started.send(());
+                Ok(())
+            },
+            // nothing is left in lsp_servers, so the next document that opens tries again
+            Err(e) => Err(
+                self.error(uid, format!("Builder {} cannot start language server {:?} {:?}", self.builder, server, e))
+            )
+        }
+    }
+    
+    // handles a message from the language server of a workspace
+    fn lsp_message(&mut self, workspace: &str, message: &JsonValue) {
+        let is_request = message.get("method").is_some() && message.get("id").is_some();
+        let (uid, pending) = if let Ok(mut servers) = self.lsp_servers.lock() {
+            let server = if let Some(server) = servers.iter_mut().find( | s | s.workspace == workspace && s.connection.is_some()) {server} else {return};
+            let connection = server.connection.as_mut().unwrap();
+            if is_request {
+                let _ = connection.respond_empty(message);
+                return
+            }
+            (server.uid, message["id"].as_u64().and_then( | id | connection.take_pending(id)))
+        } else {return};
+        
+        let result = &message["result"];
+        let msg = match pending {
+            Some(LspPending::Hover {uid}) => HubMsg::LspHoverResponse {uid: uid, text: lsp_hover_text(result)},
+            Some(LspPending::Definition {uid}) | Some(LspPending::References {uid}) => HubMsg::LspLocationsResponse {uid: uid, locations: self.lsp_locations(workspace, result)},
+            Some(LspPending::Completion {uid}) => HubMsg::LspCompletionResponse {uid: uid, items: HubCompletion::from_lsp(result)},
+            Some(LspPending::Initialize) => return,
+            None => match message["method"].as_str().unwrap_or("") {
+                "textDocument/publishDiagnostics" => {
+                    let params = &message["params"];
+                    let uri = params["uri"].as_str().unwrap_or("");
+                    let path = if let Some(path) = uri_to_path(uri).and_then( | p | self.workspace_path_from_abs(&p)) {path} else {return};
+                    let mut items = Vec::new();
+                    for diagnostic in params["diagnostics"].as_array().unwrap_or(&[]) {
+                        let (line, col) = self.lsp_range_start(workspace, uri, &diagnostic["range"]);
+                        let loc_message = LocMessage {
+                            path: path.clone(),
+                            line: line,
+                            col: col,
+                            range: None,
+                            body: diagnostic["message"].as_str().unwrap_or("").to_string(),
+                            rendered: None,
+                            explanation: None,
+                            fixes: Vec::new()
+                        };
+                        items.push(match diagnostic["severity"].as_u64() {
+                            Some(1) => HubLogItem::LocError(loc_message),
+                            Some(2) => HubLogItem::LocWarning(loc_message),
+                            _ => HubLogItem::LocMessage(loc_message)
+                        });
+                    }
+                    HubMsg::LspDiagnostics {uid: uid, path: path, items: items}
+                },
+                "window/showMessage" => {
+                    let text = message.query("params.message").and_then( | m | m.as_str()).unwrap_or("").to_string();
+                    HubMsg::LogItem {uid: uid, item: match message.query("params.type").and_then( | t | t.as_u64()) {
+                        Some(1) => HubLogItem::Error(text),
+                        Some(2) => HubLogItem::Warning(text),
+                        _ => HubLogItem::Message(text)
+                    }}
+                },
+                _ => return
+            }
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: msg
+        });
+    }
+    
+    // the start of a range in a message of the server, in the columns of the encoding it uses
+    fn lsp_range_start(&self, workspace: &str, uri: &str, range: &JsonValue) -> (usize, usize) {
+        if let Ok(servers) = self.lsp_servers.lock() {
+            if let Some(connection) = servers.iter().find( | s | s.workspace == workspace).and_then( | s | s.connection.as_ref()) {
+                return connection.range_start(uri, range)
+            }
+        }
+        lsp_range_start(range)
+    }
+    
+    // a Location, a list of them or a list of LocationLinks. the ones outside the workspaces are left out
+    fn lsp_locations(&self, workspace: &str, result: &JsonValue) -> Vec<LocMessage> {
+        let list: Vec<&JsonValue> = if let Some(list) = result.as_array() {list.iter().collect()} else if result.get("uri").is_some() {vec![result]} else {Vec::new()};
+        let mut locations = Vec::new();
+        for location in list {
+            let (uri, range) = if let Some(uri) = location["targetUri"].as_str() {
+                (uri, &location["targetSelectionRange"])
+            }
+            else {
+                (location["uri"].as_str().unwrap_or(""), &location["range"])
+            };
+            let abs_path = if let Some(abs_path) = uri_to_path(uri) {abs_path} else {continue};
+            let path = if let Some(path) = self.workspace_path_from_abs(&abs_path) {path} else {continue};
+            let (line, col) = self.lsp_range_start(workspace, uri, range);
+            // the line it is on, so a list of references reads like code
+            let body = fs::read_to_string(&abs_path).ok()
+                .and_then( | text | text.lines().nth(line - 1).map( | l | l.trim().to_string()))
+                .unwrap_or(String::new());
+            locations.push(LocMessage {
+                path: path,
+                line: line,
+                col: col,
+                range: None,
+                body: body,
+                rendered: None,
+                explanation: None,
+                fixes: Vec::new()
+            });
+        }
+        locations
+    }
+    
+    // runs f with the server of a workspace, false when it has none running
+    fn lsp_connection<F>(&mut self, workspace: &str, f: F) -> bool
+    where F: FnOnce(&mut LspConnection) -> std::io::Result<()> {
+        if let Ok(mut servers) = self.lsp_servers.lock() {
+            if let Some(connection) = servers.iter_mut().find( | s | s.workspace == workspace).and_then( | s | s.connection.as_mut()) {
+                if f(connection).is_err() {
+                    connection.kill();
+                }
+                return true
+            }
+        }
+        false
+    }
+    
+    pub fn lsp_open(&mut self, uid: HubUid, path: &str, text: &str) -> Result<(), HubWsError> {
+        let (abs_dir, workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        self.lsp_start(uid, &workspace, &abs_dir) ?;
+        let uri = file_uri(&format!("{}/{}", abs_dir, sub_path));
+        let language_id = match sub_path.rsplit('.').next() {
+            Some("rs") => "rust",
+            Some("js") => "javascript",
+            Some("toml") => "toml",
+            _ => "plaintext"
+        };
+        self.lsp_connection(&workspace, | connection | {
+            // open already, the whole text replaces it
+            if let Some(version) = connection.next_version(&uri) {
+                if let Some(document) = connection.documents.iter_mut().find( | d | d.uri == uri) {
+                    document.text = text.to_string();
+                }
+                return connection.notify("textDocument/didChange", json_object(vec![
+                    ("textDocument", json_object(vec![("uri", uri.as_str().into()), ("version", version.into())])),
+                    ("contentChanges", JsonValue::Array(vec![json_object(vec![("text", text.into())])]))
+                ]))
+            }
+            connection.documents.push(LspDocument {uri: uri.clone(), version: 1, text: text.to_string()});
+            connection.notify("textDocument/didOpen", json_object(vec![
+                ("textDocument", json_object(vec![
+                    ("uri", uri.as_str().into()),
+                    ("languageId", language_id.into()),
+                    ("version", 1u64.into()),
+                    ("text", text.into())
+                ]))
+            ]))
+        });
+        Ok(())
+    }
+    
+    pub fn lsp_change(&mut self, uid: HubUid, path: &str, edits: &[LocEdit]) -> Result<(), HubWsError> {
+        let (abs_dir, workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        let uri = file_uri(&format!("{}/{}", abs_dir, sub_path));
+        self.lsp_connection(&workspace, | connection | {
+            if let Some(version) = connection.next_version(&uri) {
+                let changes = edits.iter().map( | edit | connection.change(&uri, edit)).collect();
+                return connection.notify("textDocument/didChange", json_object(vec![
+                    ("textDocument", json_object(vec![("uri", uri.as_str().into()), ("version", version.into())])),
+                    ("contentChanges", JsonValue::Array(changes))
+                ]))
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+    
+    pub fn lsp_close(&mut self, uid: HubUid, path: &str) -> Result<(), HubWsError> {
+        let (abs_dir, workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        let uri = file_uri(&format!("{}/{}", abs_dir, sub_path));
+        self.lsp_connection(&workspace, | connection | {
+            connection.documents.retain( | d | d.uri != uri);
+            connection.notify("textDocument/didClose", json_object(vec![
+                ("textDocument", json_object(vec![("uri", uri.as_str().into())]))
+            ]))
+        });
+        Ok(())
+    }
+    
+    pub fn lsp_request(&mut self, uid: HubUid, path: &str, line: usize, col: usize, method: &str, pending: LspPending) -> Result<(), HubWsError> {
+        let (abs_dir, workspace, sub_path) = self.workspace_split_from_path(uid, path) ?;
+        let uri = file_uri(&format!("{}/{}", abs_dir, sub_path));
+        self.lsp_connection(&workspace, | connection | {
+            let mut params = json_object(vec![
+                ("textDocument", json_object(vec![("uri", uri.as_str().into())])),
+                ("position", connection.position(&uri, line, col))
+            ]);
+            if let LspPending::References {..} = pending {
+                params.insert("context", json_object(vec![("includeDeclaration", true.into())]));
+            }
+            connection.request(method, params, pending).map( | _ | ())
+        });
+        Ok(())
+    }
+    
+    // a shell on a pty, its output goes to every UI until it exits
+    pub fn terminal_open(&mut self, uid: HubUid, workspace: &str, command: &[String], cols: usize, rows: usize) -> Result<(), HubWsError> {
+        let abs_dir = self.get_workspace_abs(uid, workspace) ?;
+        let command = if command.len() == 0 {
+            vec![std::env::var("SHELL").unwrap_or("/bin/sh".to_string())]
+        }
+        else {
+            command.to_vec()
+        };
+        let env = [("TERM", "xterm-256color"), ("COLORTERM", "truecolor")];
+        let (pty, mut reader) = match Pty::spawn(&command[0], &command[1..], &abs_dir, &env, cols, rows) {
+            Ok(pty) => pty,
+            Err(e) => return Err(
+                self.error(uid, format!("Builder {} cannot start terminal {:?} in {} {:?}", self.builder, command, abs_dir, e))
+            )
+        };
+        let replay = Arc::new(Mutex::new(Vec::new()));
+        if let Ok(mut terminals) = self.terminals.lock() {
+            terminals.push(HubTerminal {
+                uid: uid,
+                workspace: workspace.to_string(),
+                command: command,
+                pty: pty,
+                replay: Arc::clone(&replay)
+            });
+        }
+        let route_send = self.route_send.clone();
+        let terminals = Arc::clone(&self.terminals);
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    // linux says EIO once the last process on the pty is gone
+                    Err(_) => break
+                };
+                // sent under the lock, so an attach never gets a piece twice or not at all
+                if let Ok(mut replay) = replay.lock() {
+                    replay.extend_from_slice(&buf[0..n]);
+                    if replay.len() > 256 * 1024 {
+                        let excess = replay.len() - 256 * 1024;
+                        replay.drain(0..excess);
+                    }
+                    route_send.send(ToHubMsg {
+                        to: HubMsgTo::UI,
+                        msg: HubMsg::TerminalOutput {uid: uid, data: buf[0..n].to_vec()}
+                    });
+                }
+            }
+            let terminal = if let Ok(mut terminals) = terminals.lock() {
+                terminals.iter().position( | t | t.uid == uid).map( | index | terminals.remove(index))
+            } else {None};
+            let code = terminal.and_then( | mut t | t.pty.wait());
+            route_send.send(ToHubMsg {
+                to: HubMsgTo::UI,
+                msg: HubMsg::TerminalEnd {uid: uid, code: code}
+            });
+        });
+        Ok(())
+    }
+    
+    // runs f with a running terminal, a terminal that is gone is no error
+    fn terminal<F>(&mut self, uid: HubUid, f: F)
+    where F: FnOnce(&mut HubTerminal) -> std::io::Result<()> {
+        if let Ok(mut terminals) = self.terminals.lock() {
+            if let Some(terminal) = terminals.iter_mut().find( | t | t.uid == uid) {
+                let _ = f(terminal);
+            }
+        }
+    }
+    
+    pub fn terminal_list(&mut self, uid: HubUid) {
+        let terminals = if let Ok(terminals) = self.terminals.lock() {
+            terminals.iter().map( | t | HubTerminalInfo {uid: t.uid, workspace: t.workspace.clone(), command: t.command.clone()}).collect()
+        } else {Vec::new()};
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::TerminalListResponse {uid: uid, terminals: terminals}
+        });
+    }
+    
+    pub fn terminal_attach(&mut self, uid: HubUid) {
+        let replay = if let Ok(terminals) = self.terminals.lock() {
+            if let Some(terminal) = terminals.iter().find( | t | t.uid == uid) {Arc::clone(&terminal.replay)} else {return}
+        } else {return};
+        if let Ok(replay) = replay.lock() {
+            self.route_send.send(ToHubMsg {
+                to: HubMsgTo::UI,
+                msg: HubMsg::TerminalOutput {uid: uid, data: replay.clone()}
+            });
+        };
+    }
+    
     pub fn cannot_find_build(&mut self, uid: HubUid, package: &str, target: &str) -> Result<(), HubWsError> {
         Err(
             self.error(uid, format!("Builder {} Cannot find package {} and target {}", self.builder, package, target))
         )
     }
     
+    // the build profiles for a package, the first one of a name wins
+    pub fn package_build_profiles(&self, package: &str) -> Vec<HubBuildProfile> {
+        let mut profiles: Vec<HubBuildProfile> = Vec::new();
+        if let Ok(build_profiles) = self.build_profiles.lock() {
+            for profile in build_profiles.iter() {
+                if profile.matches_package(package) && !profiles.iter().any( | p | p.name == profile.name) {
+                    profiles.push(profile.clone());
+                }
+            }
+        };
+        profiles
+    }
+    
+    pub fn build_profile(&mut self, uid: HubUid, workspace: &str, package: &str, config: &str) -> Result<(), HubWsError> {
+        let profile = match self.package_build_profiles(package).into_iter().find( | p | p.name == config) {
+            Some(profile) => profile,
+            None => return self.cannot_find_build(uid, package, config)
+        };
+        let args = profile.cargo_args(package);
+        let args: Vec<&str> = args.iter().map( | a | a.as_ref()).collect();
+        let env: Vec<(&str, &str)> = profile.env.iter().map( | (k, v) | (k.as_ref(), v.as_ref())).collect();
+        let build_result = self.cargo(uid, workspace, &args, &env) ?;
+        for post_build in &profile.post_build {
+            match post_build {
+                HubPostBuild::WasmStripDebug => if let BuildResult::Wasm {path} = &build_result {
+                    self.wasm_strip_debug(uid, path) ?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
     pub fn cargo(&mut self, uid: HubUid, workspace: &str, args: &[&str], env: &[(&str, &str)]) -> Result<BuildResult, HubWsError> {
+        self.cargo_with_lines(uid, workspace, args, env, | _, _ | false)
+    }
+    
+    // cargo, with the lines that aren't compiler messages going to on_line first. it returns
+    // true for the lines it handled, the rest are logged as usual
+    pub fn cargo_with_lines<F>(&mut self, uid: HubUid, workspace: &str, args: &[&str], env: &[(&str, &str)], mut on_line: F) -> Result<BuildResult, HubWsError>
+    where F: FnMut(bool, &str) -> bool {
         
         if let Ok(mut http_server) = self.http_server.lock() {
             if let Some(http_server) = &mut *http_server {
@@ -655,7 +1672,9 @@ impl HubBuilder {
         let abs_root_path = self.get_workspace_abs(uid, workspace) ?;
 
         let mut extargs = args.to_vec();
-        extargs.push("--message-format=json");
+        // before a --, as what comes after it is for the test binaries
+        let pos = extargs.iter().position( | arg | *arg == "--").unwrap_or(extargs.len());
+        extargs.insert(pos, "--message-format=json");
         let mut process = Process::start("cargo", &extargs, &abs_root_path, env).expect("Cannot start process");
 
         let route_send = self.route_send.clone();
@@ -680,6 +1699,9 @@ impl HubBuilder {
         let mut build_result = BuildResult::NoOutput;
         while let Ok(line) = rx_line.recv() {
             if let Some((is_stderr, line)) = line {
+                if on_line(is_stderr, &line) {
+                    continue
+                }
                 if is_stderr && line != "\n"
                     && !line.contains("Finished")
                     && !line.contains("Blocking")
@@ -689,16 +1711,26 @@ impl HubBuilder {
                         to: HubMsgTo::UI,
                         msg: HubMsg::LogItem {
                             uid: uid,
-                            item: HubLogItem::Error(line.clone())
+                            item: HubLogItem::from_output(HubLogLevel::Error, &line)
                         }
                     });
                 }
+                // the json messages are on stdout, stderr only has the log lines above
+                if is_stderr {
+                    continue
+                }
                 
                 let mut parsed: Result<RustcCompilerMessage, DeJsonErr> = DeJson::deserialize_json(&line);
                 match &mut parsed {
                     Err(_) => (),
                     Ok(parsed) => {
                         if let Some(message) = &mut parsed.message { //.spans;
+                            // json-diagnostic-rendered-ansi colors the rendered text, we show it plain
+                            if let Some(rendered) = &mut message.rendered {
+                                if rendered.contains('\x1b') {
+                                    *rendered = HubStyledText::from_ansi(rendered).body;
+                                }
+                            }
                             let spans = &message.spans;
                             for i in 0..spans.len() {
                                 let span = spans[i].clone();
@@ -714,6 +1746,7 @@ impl HubBuilder {
                                 }
                                 msg = msg.replace("\n", "");
                                 // lets try to pull path out of rendered, this fixes some rust bugs
+                                let fixes = message.loc_fixes(&span.file_name);
                                 let mut path = span.file_name;
                                 let line = span.line_start as usize;
                                 let col = span.column_start as usize;
@@ -737,6 +1770,7 @@ impl HubBuilder {
                                     body: msg,
                                     rendered: message.rendered.clone(),
                                     explanation: if let Some(code) = &message.code {code.explanation.clone()}else {None},
+                                    fixes: fixes,
                                 };
                                 let item = match message.level.as_ref() {
                                     "error" => {
@@ -810,6 +1844,12 @@ impl HubBuilder {
             }
         });
         
+        if let Ok(mut http_server) = self.http_server.lock() {
+            if let Some(http_server) = &mut *http_server {
+                http_server.send_build_end(if let BuildResult::Error = build_result {false} else {true});
+            }
+        };
+        
         // remove process from process list
         if let Ok(mut processes) = self.processes.lock() {
             if let Some(index) = processes.iter().position( | p | p.uid == uid) {
@@ -823,6 +1863,216 @@ impl HubBuilder {
         return Ok(build_result);
     }
     
+    // runs a test binary that cargo built in the directory of its package, like cargo test does
+    pub fn run_test_executable<F>(&mut self, uid: HubUid, executable: &str, package_dir: &str, args: &[&str], mut on_line: F) -> Result<(), HubWsError>
+    where F: FnMut(&str) -> bool {
+        let env = [("RUSTC_BOOTSTRAP", "1"), ("CARGO_MANIFEST_DIR", package_dir)];
+        let mut process = match Process::start(executable, args, package_dir, &env) {
+            Ok(process) => process,
+            Err(e) => return Err(self.error(uid, format!("Cannot run test executable {} {:?}", executable, e)))
+        };
+        let rx_line = process.rx_line.take().unwrap();
+        if let Ok(mut processes) = self.processes.lock() {
+            processes.push(HubProcess {
+                uid: uid,
+                process: process,
+            });
+        };
+        while let Ok(Some((is_stderr, line))) = rx_line.recv() {
+            if is_stderr {
+                self.route_send.send(ToHubMsg {
+                    to: HubMsgTo::UI,
+                    msg: HubMsg::LogItem {uid: uid, item: HubLogItem::from_output(HubLogLevel::Error, &line)}
+                });
+            }
+            else {
+                on_line(&line);
+            }
+        }
+        if let Ok(mut processes) = self.processes.lock() {
+            if let Some(index) = processes.iter().position( | p | p.uid == uid) {
+                processes[index].process.wait();
+                processes.remove(index);
+            }
+        };
+        Ok(())
+    }
+    
+    // cargo test is run once per target, as a test name only means something within its test binary
+    pub fn cargo_test_targets(&mut self, uid: HubUid, workspace: &str, package: &str) -> Result<Vec<HubTestTarget>, HubWsError> {
+        let abs_root_path = self.get_workspace_abs(uid, workspace) ?;
+        let output = match std::process::Command::new("cargo").args(&["metadata", "--no-deps", "--format-version", "1"]).current_dir(&abs_root_path).output() {
+            Ok(output) => output,
+            Err(e) => return Err(self.error(uid, format!("Cannot run cargo metadata {:?}", e)))
+        };
+        match HubTestTarget::from_cargo_metadata(&String::from_utf8_lossy(&output.stdout), package) {
+            Some(targets) => Ok(targets),
+            None => Err(self.error(uid, format!("Cannot find package {} in {}", package, workspace)))
+        }
+    }
+    
+    pub fn cargo_test_list(&mut self, from: HubAddr, uid: HubUid, workspace: &str, package: &str) -> Result<(), HubWsError> {
+        let abs_root_path = self.get_workspace_abs(uid, workspace) ?;
+        let mut tests = Vec::new();
+        // the response is always sent, so the ui isn't left waiting
+        let (targets, mut result) = match self.cargo_test_targets(uid, workspace, package) {
+            Ok(targets) => (targets, Ok(())),
+            Err(err) => (Vec::new(), Err(err))
+        };
+        for target in targets {
+            let parser = CargoTestParser::new(&self.builder, workspace, &abs_root_path, package, target.clone());
+            let mut args = vec!["test".to_string(), "-p".to_string(), package.to_string()];
+            args.extend(target.cargo_args());
+            args.extend(["--", "--list"].iter().map( | a | a.to_string()));
+            let args: Vec<&str> = args.iter().map( | a | a.as_ref()).collect();
+            let list = self.cargo_with_lines(uid, workspace, &args, &[], | is_stderr, line | {
+                if is_stderr {
+                    return HubTestTarget::is_cargo_line(line)
+                }
+                match parser.parse_line(line) {
+                    Some(CargoTestEvent::Discovered(test)) => {tests.push(test); true},
+                    _ => false
+                }
+            });
+            if let Err(err) = list {
+                result = Err(err);
+                break;
+            }
+        }
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::Client(from),
+            msg: HubMsg::TestListResponse {
+                uid: uid,
+                tests: tests
+            }
+        });
+        result
+    }
+    
+    // runs the tests, or all tests of the package when there are none
+    pub fn cargo_test(&mut self, uid: HubUid, workspace: &str, package: &str, tests: &[HubTest]) -> Result<(), HubWsError> {
+        let abs_root_path = self.get_workspace_abs(uid, workspace) ?;
+        let mut groups: Vec<(HubTestTarget, Vec<String>)> = Vec::new();
+        let mut result = Ok(());
+        if tests.len() == 0 {
+            match self.cargo_test_targets(uid, workspace, package) {
+                Ok(targets) => groups.extend(targets.into_iter().map( | target | (target, Vec::new()))),
+                Err(err) => result = Err(err)
+            }
+        }
+        for test in tests {
+            if let Some(group) = groups.iter_mut().find( | (target, _) | *target == test.target) {
+                group.1.push(test.name.clone());
+            }
+            else {
+                groups.push((test.target.clone(), vec![test.name.clone()]));
+            }
+        }
+        
+        let route_send = self.route_send.clone();
+        let (mut passed, mut failed, mut ignored) = (0, 0, 0);
+        for (target, names) in groups {
+            let parser = CargoTestParser::new(&self.builder, workspace, &abs_root_path, package, target.clone());
+            let mut on_test_line = | line: &str | {
+                match parser.parse_line(line) {
+                    Some(CargoTestEvent::Begin {target, count}) => {
+                        route_send.send(ToHubMsg {
+                            to: HubMsgTo::UI,
+                            msg: HubMsg::TestBegin {uid: uid, target: target, count: count}
+                        });
+                        true
+                    },
+                    Some(CargoTestEvent::Result(test_result)) => {
+                        match test_result.outcome {
+                            HubTestOutcome::Passed => passed += 1,
+                            HubTestOutcome::Failed => failed += 1,
+                            HubTestOutcome::Ignored => ignored += 1,
+                        }
+                        route_send.send(ToHubMsg {
+                            to: HubMsgTo::UI,
+                            msg: HubMsg::TestResult {uid: uid, result: test_result}
+                        });
+                        true
+                    },
+                    Some(CargoTestEvent::Discovered(_)) => true,
+                    // the rest of the libtest json, the json of cargo builds goes on to cargo_with_lines
+                    None if line.starts_with("{") => line.starts_with("{ \"type\""),
+                    // the failures and the summary of doc tests
+                    None => {
+                        if line.trim().len() > 0 {
+                            route_send.send(ToHubMsg {
+                                to: HubMsgTo::UI,
+                                msg: HubMsg::LogItem {uid: uid, item: HubLogItem::Message(line.trim_end().to_string())}
+                            });
+                        }
+                        true
+                    }
+                }
+            };
+            let mut args = vec!["test".to_string(), "-p".to_string(), package.to_string()];
+            args.extend(target.cargo_args());
+            let mut names_args = Vec::new();
+            if names.len() > 0 {
+                names_args.push("--exact".to_string());
+                names_args.extend(names);
+            }
+            
+            // rustdoc builds and runs doc tests itself so they go through cargo, with the plain output
+            if target == HubTestTarget::Doc {
+                args.push("--".to_string());
+                args.extend(names_args);
+                let args: Vec<&str> = args.iter().map( | a | a.as_ref()).collect();
+                let run = self.cargo_with_lines(uid, workspace, &args, &[], | is_stderr, line | {
+                    if is_stderr {
+                        return HubTestTarget::is_cargo_line(line)
+                    }
+                    on_test_line(line)
+                });
+                if let Err(err) = run {
+                    result = Err(err);
+                }
+                continue
+            }
+            
+            args.push("--no-run".to_string());
+            let args: Vec<&str> = args.iter().map( | a | a.as_ref()).collect();
+            let mut executables = Vec::new();
+            let build = self.cargo_with_lines(uid, workspace, &args, &[], | is_stderr, line | {
+                if is_stderr {
+                    return line.trim().starts_with("Executable ")
+                }
+                if let Some(executable) = HubTestTarget::test_executable(line) {
+                    executables.push(executable);
+                }
+                false
+            });
+            // a target that doesn't build doesn't stop the others
+            if let Err(err) = build {
+                result = Err(err);
+                continue
+            }
+            // the json format of libtest is still unstable, RUSTC_BOOTSTRAP lets a stable toolchain use it.
+            // only the test binary gets it, so it doesn't change how cargo builds anything
+            let mut test_args = vec!["-Z", "unstable-options", "--format", "json"];
+            test_args.extend(names_args.iter().map( | a | a.as_str()));
+            for (executable, package_dir) in executables {
+                if let Err(err) = self.run_test_executable(uid, &executable, &package_dir, &test_args, &mut on_test_line) {
+                    result = Err(err);
+                }
+            }
+        }
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::TestEnd {
+                uid: uid,
+                passed: passed,
+                failed: failed,
+                ignored: ignored
+            }
+        });
+        result
+    }
+    
     pub fn packages_response(&mut self, from: HubAddr, uid: HubUid, packages: Vec<HubPackage>) {
         
         self.route_send.send(ToHubMsg {
@@ -908,7 +2158,7 @@ impl HubBuilder {
                     Ok(v) => v
                 };
 
-                if let Some(Toml::Array(members)) = toml.get("workspace.members") {
+                if let Some(Toml::Array(members)) = toml.get_path("workspace.members") {
                     for member in members {
                         if let Toml::Str(member) = member {
                             let file_path = format!("{}/{}/Cargo.toml", abs_path, member);
@@ -927,7 +2177,7 @@ impl HubBuilder {
                                 },
                                 Ok(v) => v
                             };
-                            if let Some(Toml::Str(name)) = toml.get("package.name") {
+                            if let Some(Toml::Str(name)) = toml.get_path("package.name") {
                                 packages.push((workspace.clone(), name.clone()));
                             }
                         }
@@ -997,6 +2247,129 @@ impl HubBuilder {
         }
     }
     
+    // the absolute path for a path in a sync message, with the same checks file_read and file_write do
+    fn sync_abs_path(&mut self, uid: HubUid, path: &str) -> Option<String> {
+        let (abs_dir, _workspace, sub_path) = self.workspace_split_from_path(uid, path).ok() ?;
+        if sub_path.contains("..") || sub_path.ends_with("key.ron") {
+            self.error(uid, format!("sync won't touch {}, ignoring", path));
+            return None
+        }
+        Some(format!("{}/{}", abs_dir, sub_path))
+    }
+    
+    // a block size of 0 would never advance through the file, a huge one allocates per block
+    fn sync_block_size_ok(&mut self, uid: HubUid, path: &str, block_size: u32) -> bool {
+        if block_size == 0 || block_size > SYNC_MAX_BLOCK_SIZE {
+            self.error(uid, format!("sync of {} with block size {} refused", path, block_size));
+            return false
+        }
+        true
+    }
+    
+    fn sync_file_response(&mut self, from: HubAddr, uid: HubUid, path: &str, done: bool) {
+        if done {
+            if let Ok(mut http_server) = self.http_server.lock() {
+                if let Some(http_server) = &mut *http_server {
+                    http_server.send_file_change(path);
+                }
+            };
+        }
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::Client(from),
+            msg: HubMsg::SyncFileResponse {
+                uid: uid,
+                path: path.to_string(),
+                done: done
+            }
+        });
+    }
+    
+    pub fn sync_signature_request(&mut self, from: HubAddr, uid: HubUid, path: &str, block_size: u32) {
+        let signatures = if self.sync_block_size_ok(uid, path, block_size) {
+            self.sync_abs_path(uid, path)
+                .and_then( | abs_path | fs::read(abs_path).ok())
+                .map( | data | sync_signatures(&data, block_size))
+        }
+        else {
+            None
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::Client(from),
+            msg: HubMsg::SyncSignatureResponse {
+                uid: uid,
+                path: path.to_string(),
+                signatures: signatures
+            }
+        });
+    }
+    
+    pub fn sync_delta_request(&mut self, from: HubAddr, uid: HubUid, path: &str, block_size: u32, signatures: &[SyncBlockSig]) {
+        let delta = if self.sync_block_size_ok(uid, path, block_size) {
+            self.sync_abs_path(uid, path)
+                .and_then( | abs_path | fs::read(abs_path).ok())
+                .map( | data | sync_delta(signatures, block_size, &data))
+        }
+        else {
+            None
+        };
+        self.route_send.send(ToHubMsg {
+            to: HubMsgTo::Client(from),
+            msg: HubMsg::SyncDeltaResponse {
+                uid: uid,
+                path: path.to_string(),
+                delta: delta
+            }
+        });
+    }
+    
+    pub fn sync_apply_request(&mut self, from: HubAddr, uid: HubUid, path: &str, block_size: u32, delta: &SyncDelta) {
+        if !self.sync_block_size_ok(uid, path, block_size) {
+            self.sync_file_response(from, uid, path, false);
+            return
+        }
+        let done = if let Some(abs_path) = self.sync_abs_path(uid, path) {
+            let old = fs::read(&abs_path).unwrap_or(Vec::new());
+            // a delta against another version of the file than it was made for fails the digest check
+            if let Some(data) = sync_delta_apply(&old, block_size, delta) {
+                if let Some(parent) = std::path::Path::new(&abs_path).parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                fs::write(&abs_path, &data).is_ok()
+            }
+            else {
+                self.error(uid, format!("sync delta for {} doesn't apply", path));
+                false
+            }
+        }
+        else {
+            false
+        };
+        self.sync_file_response(from, uid, path, done);
+    }
+    
+    pub fn sync_delete_request(&mut self, from: HubAddr, uid: HubUid, path: &str) {
+        let done = if let Some(abs_path) = self.sync_abs_path(uid, path) {
+            fs::remove_file(&abs_path).is_ok()
+        }
+        else {
+            false
+        };
+        self.sync_file_response(from, uid, path, done);
+    }
+    
+    pub fn sync_rename_request(&mut self, from: HubAddr, uid: HubUid, from_path: &str, to_path: &str) {
+        let done = if let (Some(abs_from), Some(abs_to)) = (self.sync_abs_path(uid, from_path), self.sync_abs_path(uid, to_path)) {
+            if let Some(parent) = std::path::Path::new(&abs_to).parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            fs::rename(&abs_from, &abs_to).is_ok()
+        }
+        else {
+            false
+        };
+        self.sync_file_response(from, uid, to_path, done);
+    }
+    
     pub fn workspace_file_tree(&mut self, create_digest: bool, ext_inc: &[&str], file_ex: &[&str], dir_ex: &[&str]) -> BuilderFileTreeNode {
         fn digest_folder(create_digest: bool, name: &str, folder: &Vec<BuilderFileTreeNode>) -> Option<Box<Digest>> {
             if !create_digest {
@@ -1044,11 +2417,14 @@ impl HubBuilder {
                                         continue
                                     }
                                     if ext_inc.iter().find(|ext| name.ends_with(*ext)).is_some(){
-                                        if create_digest {
-                                            
+                                        let digest = if create_digest {
+                                            fs::read(format!("{}/{}", path, name)).ok().map( | data | Box::new(file_digest(&data)))
                                         }
+                                        else {
+                                            None
+                                        };
                                         ret.push(BuilderFileTreeNode::File {
-                                            digest: None,
+                                            digest: digest,
                                             name: name
                                         });
                                     }
@@ -1112,8 +2488,9 @@ fn de_relativize_path(path: &str) -> String {
 }
 
 
-// rust compiler output json structs
+// rust compiler output json structs, cargo and rustc add keys over time so the ones we don't use are skipped
 #[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcTarget {
     kind: Vec<String>,
     crate_types: Vec<String>,
@@ -1124,13 +2501,7 @@ pub struct RustcTarget {
 }
 
 #[derive(Clone, DeJson, Default)]
-pub struct RustcText {
-    text: String,
-    highlight_start: u32,
-    highlight_end: u32
-}
-
-#[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcSpan {
     file_name: String,
     byte_start: u32,
@@ -1140,28 +2511,20 @@ pub struct RustcSpan {
     column_start: u32,
     column_end: u32,
     is_primary: bool,
-    text: Vec<RustcText>,
     label: Option<String>,
     suggested_replacement: Option<String>,
-    suggestion_applicability: Option<String>,
-    expansion: Option<Box<RustcExpansion>>,
-    level: Option<String>
-}
-
-#[derive(Clone, DeJson, Default)]
-pub struct RustcExpansion {
-    span: Option<RustcSpan>,
-    macro_decl_name: String,
-    def_site_span: Option<RustcSpan>
+    suggestion_applicability: Option<String>
 }
 
 #[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcCode {
     code: String,
     explanation: Option<String>
 }
 
 #[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcMessage {
     message: String,
     code: Option<RustcCode>,
@@ -1171,7 +2534,48 @@ pub struct RustcMessage {
     rendered: Option<String>
 }
 
+impl RustcMessage {
+    // the suggestions of the children that can be applied without looking, like rustfix does
+    fn loc_fixes(&self, file_name: &str) -> Vec<LocFix> {
+        let mut fixes = Vec::new();
+        for child in &self.children {
+            let mut edits = Vec::new();
+            let mut label = child.message.clone();
+            let mut other_file = false;
+            for span in &child.spans {
+                if let Some(replacement) = &span.suggested_replacement {
+                    if span.suggestion_applicability.as_ref().map( | a | a.as_ref()) != Some("MachineApplicable") {
+                        continue
+                    }
+                    if span.file_name != file_name {
+                        other_file = true;
+                        continue
+                    }
+                    // the label says what the replacement does, like "help: remove this `mut`"
+                    if let Some(span_label) = &span.label {
+                        if edits.len() == 0 && span_label.len() > 0 && *span_label != label {
+                            label = format!("{}: {}", label, span_label);
+                        }
+                    }
+                    edits.push(LocEdit {
+                        start_line: span.line_start as usize,
+                        start_col: span.column_start as usize,
+                        end_line: span.line_end as usize,
+                        end_col: span.column_end as usize,
+                        replacement: replacement.clone()
+                    });
+                }
+            }
+            if edits.len() > 0 && !other_file {
+                fixes.push(LocFix {label: label, edits: edits});
+            }
+        }
+        fixes
+    }
+}
+
 #[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcProfile {
     opt_level: String,
     debuginfo: Option<u32>,
@@ -1181,6 +2585,7 @@ pub struct RustcProfile {
 }
 
 #[derive(Clone, DeJson, Default)]
+#[ignore_unknown]
 pub struct RustcCompilerMessage {
     reason: String,
     package_id: String,
@@ -1,5 +1,6 @@
 use crate::hubmsg::*;
 use crate::hubrouter::*;
+use crate::hubcrypto::*;
 use makepad_microserde::*;
 
 use std::net::{TcpStream, SocketAddr, Shutdown};
@@ -47,14 +48,13 @@ pub fn read_exact_bytes_from_tcp_stream(tcp_stream: &mut TcpStream, bytes: &mut
     Ok(())
 }
 
-pub fn read_block_from_tcp_stream(tcp_stream: &mut TcpStream, mut check_digest: Digest) -> HubResult<Vec<u8>> {
-    let mut dwd_read = DigestWithData::default();
+// a block is its length and the sealed data, the length is authenticated with it
+pub fn read_block_from_tcp_stream(tcp_stream: &mut TcpStream, cipher: &mut HubCipher) -> HubResult<Vec<u8>> {
+    let mut header = [0u8; 8];
+    read_exact_bytes_from_tcp_stream(tcp_stream, &mut header) ?;
     
-    let dwd_u8 = unsafe {std::mem::transmute::<&mut DigestWithData, &mut [u8; 26 * 8]>(&mut dwd_read)};
-    read_exact_bytes_from_tcp_stream(tcp_stream, dwd_u8) ?;
-    
-    let bytes_total = dwd_read.data as usize;
-    if bytes_total > 250 * 1024 * 1024 {
+    let bytes_total = u64::from_le_bytes(header) as usize;
+    if bytes_total > HUB_MAX_BLOCK_SIZE + 16 {
         return Err(HubError::new("read_block_from_tcp_stream: bytes_total more than 250mb"))
     }
     
@@ -62,16 +62,7 @@ pub fn read_block_from_tcp_stream(tcp_stream: &mut TcpStream, mut check_digest:
     msg_buf.resize(bytes_total, 0);
     read_exact_bytes_from_tcp_stream(tcp_stream, &mut msg_buf) ?;
     
-    check_digest.digest_buffer(&msg_buf);
-    
-    if check_digest != dwd_read.digest {
-        return Err(HubError::new("read_block_from_tcp_stream: block digest check failed"))
-    }
-    
-    //let mut dec = snap::Decoder::new();
-    let decompressed = msg_buf;//dec.decompress_vec(&msg_buf).expect_msg("read_block_from_tcp_stream: cannot decompress_vec");
-    
-    return Ok(decompressed);
+    cipher.open(&header, &msg_buf)
 }
 
 pub fn write_exact_bytes_to_tcp_stream(tcp_stream: &mut TcpStream, bytes: &[u8]) -> HubResult<()> {
@@ -88,26 +79,16 @@ pub fn write_exact_bytes_to_tcp_stream(tcp_stream: &mut TcpStream, bytes: &[u8])
     Ok(())
 }
 
-pub fn write_block_to_tcp_stream(tcp_stream: &mut TcpStream, msg_buf: &[u8], digest: Digest) -> HubResult<()> {
-    let bytes_total = msg_buf.len();
-    
-    if bytes_total > 250 * 1024 * 1024 {
-        return Err(HubError::new("read_block_from_tcp_stream: bytes_total more than 250mb"))
+pub fn write_block_to_tcp_stream(tcp_stream: &mut TcpStream, msg_buf: &[u8], cipher: &mut HubCipher) -> HubResult<()> {
+    if msg_buf.len() > HUB_MAX_BLOCK_SIZE {
+        return Err(HubError::new("write_block_to_tcp_stream: bytes_total more than 250mb"))
     }
     
-    //let mut enc = snap::Encoder::new();
-    let compressed = msg_buf;//enc.compress_vec(msg_buf).expect_msg("read_block_from_tcp_stream: cannot compress msgbuf") ?;
+    let header = ((msg_buf.len() + 16) as u64).to_le_bytes();
+    let sealed = cipher.seal(&header, msg_buf) ?;
     
-    let mut dwd_write = DigestWithData{
-        digest:digest,
-        data: compressed.len() as u64
-    };
-    
-    dwd_write.digest.digest_buffer(&compressed);
-    
-    let dwd_u8 = unsafe {std::mem::transmute::<&DigestWithData, &[u8; 26 * 8]>(&dwd_write)};
-    write_exact_bytes_to_tcp_stream(tcp_stream, dwd_u8) ?;
-    write_exact_bytes_to_tcp_stream(tcp_stream, &compressed) ?;
+    write_exact_bytes_to_tcp_stream(tcp_stream, &header) ?;
+    write_exact_bytes_to_tcp_stream(tcp_stream, &sealed) ?;
     Ok(())
 }
 
@@ -122,12 +103,6 @@ pub struct HubClient {
     pub tx_write: mpsc::Sender<ToHubMsg>
 }
 
-#[derive(Default, Eq, PartialEq, Debug, Clone, SerBin, DeBin)]
-pub struct DigestWithData{
-    pub digest:Digest,
-    pub data: u64
-}
-
 impl HubClient {
     pub fn connect_to_server(digest: Digest, server_address: SocketAddr, hub_log: HubLog) -> HubResult<HubClient> {
         
@@ -145,6 +120,8 @@ impl HubClient {
         
         let own_addr = HubAddr::from_socket_addr(tcp_stream.local_addr().expect("Cannot get client local address"));
         
+        let HubSession {read: mut read_cipher, write: mut write_cipher} = hub_handshake_client(&mut tcp_stream, &digest) ?;
+        
         let (tx_read, rx_read) = mpsc::channel::<FromHubMsg>();
         let (tx_write, rx_write) = mpsc::channel::<ToHubMsg>();
         let tx_read_copy = tx_read.clone();
@@ -152,14 +129,16 @@ impl HubClient {
         
         let read_thread = {
             let mut tcp_stream = tcp_stream.try_clone().expect_msg("connect_to_hub: cannot clone socket") ?;
-            let digest = digest.clone();
             let server_hubaddr = server_hubaddr.clone();
             let hub_log = hub_log.clone();
             std::thread::spawn(move || {
                 loop {
-                    match read_block_from_tcp_stream(&mut tcp_stream, digest.clone()) {
-                        Ok(msg_buf) => {
-                            let htc_msg: FromHubMsg = DeBin::deserialize_bin(&msg_buf).expect("Cannot parse binary");
+                    // a message we can't read is from an incompatible hub, and closes the connection
+                    let msg = read_block_from_tcp_stream(&mut tcp_stream, &mut read_cipher).and_then( | msg_buf | {
+                        FromHubMsg::deserialize_bin_tagged(&msg_buf).map_err( | err | HubError::new(&format!("Cannot parse message: {:?}", err)))
+                    });
+                    match msg {
+                        Ok(htc_msg) => {
                             hub_log.msg("HubClient received", &htc_msg);
                             tx_read.send(htc_msg).expect("tx_read.send fails - should never happen");
                         },
@@ -182,7 +161,6 @@ impl HubClient {
         };
         
         let write_thread = {
-            let digest = digest.clone();
             let tx_read = tx_read_copy.clone();
             let server_hubaddr = server_hubaddr.clone();
             let hub_log = hub_log.clone();
@@ -195,9 +173,8 @@ impl HubClient {
                         },
                         _ => ()
                     }
-                    let mut msg_buf = Vec::new();
-                    cth_msg.ser_bin(&mut msg_buf);
-                    if let Err(e) = write_block_to_tcp_stream(&mut tcp_stream, &msg_buf, digest.clone()) {
+                    let msg_buf = cth_msg.serialize_bin_tagged();
+                    if let Err(e) = write_block_to_tcp_stream(&mut tcp_stream, &msg_buf, &mut write_cipher) {
                         // disconnect the socket and send shutdown
                         let _ = tcp_stream.shutdown(Shutdown::Both);
                         let _ = tx_read.send(FromHubMsg {
@@ -250,7 +227,7 @@ impl HubClient {
 }
 
 
-#[derive(Eq, PartialEq, Debug, Clone, SerBin, DeBin, SerRon, DeRon)]
+#[derive(Eq, PartialEq, Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
 pub struct Digest {
     pub buf: [u64; 25]
 }
@@ -261,14 +238,17 @@ impl Default for Digest {
 
 impl Digest {
     
-    pub fn generate() -> Digest {
+    // a random key, for key.ron
+    pub fn generate() -> Result<Digest, HubError> {
+        let mut bytes = [0u8; 25 * 8];
+        random_bytes(&mut bytes) ?;
         let mut result = Digest::default();
-        for _ in 0..25 {
-            //result.buf[i] ^= time::precise_time_ns();
-            std::thread::sleep(std::time::Duration::from_millis(1));
-            result.digest_cycle();
+        for i in 0..25 {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[i * 8..i * 8 + 8]);
+            result.buf[i] = u64::from_le_bytes(word);
         }
-        result
+        Ok(result)
     }
     
     pub fn digest_cycle(&mut self){
@@ -298,7 +278,7 @@ impl Digest {
     
 }
 
-// digest function to hash tcp data to enable error checking and multiple servers on one network, found various
+// the keccak permutation, for the SHA3 hubcrypto derives its session keys with and for file digests, found various
 // similar versions of this on crates.io and github (as MIT). Not sure which one to attribute it to. Thanks whoever wrote this :)
 
 const RHO: [u32; 24] = [1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,];
@@ -0,0 +1,401 @@
+use crate::hubmsg::*;
+use crate::hubclient::*;
+
+use std::net::TcpStream;
+use std::io::Read;
+
+// hub connections are encrypted and authenticated with ChaCha20-Poly1305 (RFC 8439).
+// both sides know the key in key.ron. a connection starts with a handshake where each side
+// sends a random value, and the session keys are derived from the shared key and both values,
+// so every connection has its own keys and nothing from an older connection can be replayed.
+// each direction has its own key and counts its blocks, the count is the nonce. a block that is
+// replayed, reordered, dropped or changed fails to decrypt and closes the connection
+
+type HubResult<T> = Result<T, HubError>;
+
+const HUB_HELLO: [u8; 8] = *b"mkpdhub1";
+const HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+const TAG_SIZE: usize = 16;
+
+// the largest block read or written, 250mb
+pub const HUB_MAX_BLOCK_SIZE: usize = 250 * 1024 * 1024;
+
+// one direction of a connection
+pub struct HubCipher {
+    key: [u8; 32],
+    counter: u64
+}
+
+// the ciphers of a connection after the handshake
+pub struct HubSession {
+    pub read: HubCipher,
+    pub write: HubCipher
+}
+
+impl HubCipher {
+    pub fn new(key: &[u8; 32]) -> HubCipher {
+        HubCipher {key: *key, counter: 0}
+    }
+    
+    fn next_nonce(&mut self) -> HubResult<[u8; 12]> {
+        if self.counter == std::u64::MAX {
+            return Err(HubError::new("HubCipher: nonce exhausted"))
+        }
+        let mut nonce = [0u8; 12];
+        nonce[4..12].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        Ok(nonce)
+    }
+    
+    // encrypts data, returns the ciphertext followed by the tag
+    pub fn seal(&mut self, aad: &[u8], data: &[u8]) -> HubResult<Vec<u8>> {
+        let nonce = self.next_nonce() ?;
+        Ok(chacha20_poly1305_seal(&self.key, &nonce, aad, data))
+    }
+    
+    // checks and decrypts what seal returned on the other side
+    pub fn open(&mut self, aad: &[u8], sealed: &[u8]) -> HubResult<Vec<u8>> {
+        let nonce = self.next_nonce() ?;
+        chacha20_poly1305_open(&self.key, &nonce, aad, sealed).ok_or_else( || {
+            HubError::new("HubCipher: block authentication failed, wrong key.ron or tampered data")
+        })
+    }
+}
+
+pub fn chacha20_poly1305_seal(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], data: &[u8]) -> Vec<u8> {
+    let (key, nonce) = (key_words(key), nonce_words(nonce));
+    let mut out = data.to_vec();
+    chacha20_xor(&key, 1, &nonce, &mut out);
+    let tag = aead_tag(&key, &nonce, aad, &out);
+    out.extend_from_slice(&tag);
+    out
+}
+
+pub fn chacha20_poly1305_open(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < TAG_SIZE {
+        return None
+    }
+    let (key, nonce) = (key_words(key), nonce_words(nonce));
+    let (data, tag) = sealed.split_at(sealed.len() - TAG_SIZE);
+    let expected = aead_tag(&key, &nonce, aad, data);
+    // compare in constant time
+    let mut diff = 0;
+    for i in 0..TAG_SIZE {
+        diff |= expected[i] ^ tag[i];
+    }
+    if diff != 0 {
+        return None
+    }
+    let mut out = data.to_vec();
+    chacha20_xor(&key, 1, &nonce, &mut out);
+    Some(out)
+}
+
+fn key_words(key: &[u8; 32]) -> [u32; 8] {
+    let mut k = [0u32; 8];
+    for i in 0..8 {
+        k[i] = le_u32(&key[i * 4..]);
+    }
+    k
+}
+
+fn nonce_words(nonce: &[u8; 12]) -> [u32; 3] {
+    [le_u32(&nonce[0..]), le_u32(&nonce[4..]), le_u32(&nonce[8..])]
+}
+
+fn key_bytes(key: &Digest) -> Vec<u8> {
+    let mut out = Vec::with_capacity(25 * 8);
+    for word in key.buf.iter() {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+fn session_keys(key: &Digest, client_random: &[u8], server_random: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let key = key_bytes(key);
+    (
+        sha3_256(&[b"makepad hub client to server", &key, client_random, server_random]),
+        sha3_256(&[b"makepad hub server to client", &key, client_random, server_random])
+    )
+}
+
+fn read_hello(tcp_stream: &mut TcpStream) -> HubResult<[u8; 32]> {
+    let mut hello = [0u8; 8 + 32];
+    read_exact_bytes_from_tcp_stream(tcp_stream, &mut hello) ?;
+    if hello[0..8] != HUB_HELLO {
+        return Err(HubError::new("hub handshake: peer does not speak this hub protocol"))
+    }
+    let mut random = [0u8; 32];
+    random.copy_from_slice(&hello[8..]);
+    Ok(random)
+}
+
+fn write_hello(tcp_stream: &mut TcpStream) -> HubResult<[u8; 32]> {
+    let mut random = [0u8; 32];
+    random_bytes(&mut random) ?;
+    let mut hello = HUB_HELLO.to_vec();
+    hello.extend_from_slice(&random);
+    write_exact_bytes_to_tcp_stream(tcp_stream, &hello) ?;
+    Ok(random)
+}
+
+// each side proves it has the key by sending a block the other can decrypt. until then the
+// peer isn't trusted with more than that block, so any other length is refused before reading it
+fn read_confirm(tcp_stream: &mut TcpStream, cipher: &mut HubCipher, expected: &[u8]) -> HubResult<()> {
+    let mut header = [0u8; 8];
+    read_exact_bytes_from_tcp_stream(tcp_stream, &mut header) ?;
+    if u64::from_le_bytes(header) != (expected.len() + TAG_SIZE) as u64 {
+        return Err(HubError::new("hub handshake: confirmation has the wrong length"))
+    }
+    let mut sealed = vec![0u8; expected.len() + TAG_SIZE];
+    read_exact_bytes_from_tcp_stream(tcp_stream, &mut sealed) ?;
+    let msg = cipher.open(&header, &sealed) ?;
+    if msg != expected {
+        return Err(HubError::new("hub handshake: unexpected confirmation"))
+    }
+    Ok(())
+}
+
+fn with_handshake_timeout<F>(tcp_stream: &mut TcpStream, f: F) -> HubResult<HubSession>
+where F: FnOnce(&mut TcpStream) -> HubResult<HubSession> {
+    let timeout = std::time::Duration::from_secs(HANDSHAKE_TIMEOUT_SECS);
+    let _ = tcp_stream.set_read_timeout(Some(timeout));
+    let result = f(tcp_stream);
+    let _ = tcp_stream.set_read_timeout(None);
+    result
+}
+
+pub fn hub_handshake_client(tcp_stream: &mut TcpStream, key: &Digest) -> HubResult<HubSession> {
+    with_handshake_timeout(tcp_stream, | tcp_stream | {
+        let client_random = write_hello(tcp_stream) ?;
+        let server_random = read_hello(tcp_stream) ?;
+        let (c2s, s2c) = session_keys(key, &client_random, &server_random);
+        let mut session = HubSession {read: HubCipher::new(&s2c), write: HubCipher::new(&c2s)};
+        write_block_to_tcp_stream(tcp_stream, b"client", &mut session.write) ?;
+        read_confirm(tcp_stream, &mut session.read, b"server") ?;
+        Ok(session)
+    })
+}
+
+// the server only sends data under the session keys once the client proved it has the key
+pub fn hub_handshake_server(tcp_stream: &mut TcpStream, key: &Digest) -> HubResult<HubSession> {
+    with_handshake_timeout(tcp_stream, | tcp_stream | {
+        let client_random = read_hello(tcp_stream) ?;
+        let server_random = write_hello(tcp_stream) ?;
+        let (c2s, s2c) = session_keys(key, &client_random, &server_random);
+        let mut session = HubSession {read: HubCipher::new(&c2s), write: HubCipher::new(&s2c)};
+        read_confirm(tcp_stream, &mut session.read, b"client") ?;
+        write_block_to_tcp_stream(tcp_stream, b"server", &mut session.write) ?;
+        Ok(session)
+    })
+}
+
+// names a key.ron without giving it away, builders announce it so a UI can tell which
+// ones it shares a key with
+pub fn key_fingerprint(key: &Digest) -> String {
+    let hash = sha3_256(&[b"makepad hub fingerprint", &key_bytes(key)]);
+    hash[0..8].iter().map( | b | format!("{:02x}", b)).collect()
+}
+
+// random bytes from the OS. there is no fallback, a key or a handshake made of bytes
+// that can be guessed is worse than not connecting at all
+#[cfg(unix)]
+pub fn random_bytes(out: &mut [u8]) -> HubResult<()> {
+    let mut file = std::fs::File::open("/dev/urandom").map_err( | e | HubError::new(&format!("random_bytes: cannot open /dev/urandom {:?}", e))) ?;
+    file.read_exact(out).map_err( | e | HubError::new(&format!("random_bytes: cannot read /dev/urandom {:?}", e)))
+}
+
+#[cfg(windows)]
+pub fn random_bytes(out: &mut [u8]) -> HubResult<()> {
+    #[link(name = "advapi32")]
+    extern "system" {
+        // RtlGenRandom
+        fn SystemFunction036(buffer: *mut u8, len: u32) -> u8;
+    }
+    for chunk in out.chunks_mut(std::u32::MAX as usize) {
+        if unsafe {SystemFunction036(chunk.as_mut_ptr(), chunk.len() as u32)} == 0 {
+            return Err(HubError::new("random_bytes: RtlGenRandom failed"))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn random_bytes(_out: &mut [u8]) -> HubResult<()> {
+    Err(HubError::new("random_bytes: no source of random bytes on this platform"))
+}
+
+// SHA3-256 of the concatenated parts, on the keccak permutation of Digest
+pub fn sha3_256(parts: &[&[u8]]) -> [u8; 32] {
+    const RATE: usize = 136;
+    let mut state = Digest::default();
+    let mut pos = 0;
+    for part in parts {
+        for byte in part.iter() {
+            state.buf[pos / 8] ^= (*byte as u64) << (8 * (pos % 8));
+            pos += 1;
+            if pos == RATE {
+                state.digest_cycle();
+                pos = 0;
+            }
+        }
+    }
+    state.buf[pos / 8] ^= 0x06 << (8 * (pos % 8));
+    state.buf[(RATE - 1) / 8] ^= 0x80 << (8 * ((RATE - 1) % 8));
+    state.digest_cycle();
+    let mut out = [0u8; 32];
+    for i in 0..32 {
+        out[i] = (state.buf[i / 8] >> (8 * (i % 8))) as u8;
+    }
+    out
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+}
+
+fn chacha20_block(key: &[u32; 8], counter: u32, nonce: &[u32; 3]) -> [u8; 64] {
+    let init = [
+        0x61707865, 0x3320646e, 0x79622d32, 0x6b206574,
+        key[0], key[1], key[2], key[3], key[4], key[5], key[6], key[7],
+        counter, nonce[0], nonce[1], nonce[2]
+    ];
+    let mut x = init;
+    fn quarter(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(16);
+        x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(12);
+        x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(8);
+        x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(7);
+    }
+    for _ in 0..10 {
+        quarter(&mut x, 0, 4, 8, 12);
+        quarter(&mut x, 1, 5, 9, 13);
+        quarter(&mut x, 2, 6, 10, 14);
+        quarter(&mut x, 3, 7, 11, 15);
+        quarter(&mut x, 0, 5, 10, 15);
+        quarter(&mut x, 1, 6, 11, 12);
+        quarter(&mut x, 2, 7, 8, 13);
+        quarter(&mut x, 3, 4, 9, 14);
+    }
+    let mut out = [0u8; 64];
+    for i in 0..16 {
+        out[i * 4..i * 4 + 4].copy_from_slice(&x[i].wrapping_add(init[i]).to_le_bytes());
+    }
+    out
+}
+
+fn chacha20_xor(key: &[u32; 8], counter: u32, nonce: &[u32; 3], data: &mut [u8]) {
+    for (index, chunk) in data.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(index as u32), nonce);
+        for (byte, k) in chunk.iter_mut().zip(block.iter()) {
+            *byte ^= k;
+        }
+    }
+}
+
+// poly1305 in 26 bit limbs. the aead pads everything to 16 bytes, so it only sees full blocks
+struct Poly1305 {
+    r: [u32; 5],
+    s: [u32; 4],
+    h: [u32; 5]
+}
+
+impl Poly1305 {
+    fn new(key: &[u8]) -> Poly1305 {
+        Poly1305 {
+            r: [
+                le_u32(&key[0..]) & 0x3ffffff,
+                (le_u32(&key[3..]) >> 2) & 0x3ffff03,
+                (le_u32(&key[6..]) >> 4) & 0x3ffc0ff,
+                (le_u32(&key[9..]) >> 6) & 0x3f03fff,
+                (le_u32(&key[12..]) >> 8) & 0x00fffff
+            ],
+            s: [le_u32(&key[16..]), le_u32(&key[20..]), le_u32(&key[24..]), le_u32(&key[28..])],
+            h: [0; 5]
+        }
+    }
+
+    fn block(&mut self, m: &[u8; 16]) {
+        let r = self.r;
+        let (s1, s2, s3, s4) = (r[1] as u64 * 5, r[2] as u64 * 5, r[3] as u64 * 5, r[4] as u64 * 5);
+        let h0 = (self.h[0] + (le_u32(&m[0..]) & 0x3ffffff)) as u64;
+        let h1 = (self.h[1] + ((le_u32(&m[3..]) >> 2) & 0x3ffffff)) as u64;
+        let h2 = (self.h[2] + ((le_u32(&m[6..]) >> 4) & 0x3ffffff)) as u64;
+        let h3 = (self.h[3] + ((le_u32(&m[9..]) >> 6) & 0x3ffffff)) as u64;
+        let h4 = (self.h[4] + ((le_u32(&m[12..]) >> 8) | (1 << 24))) as u64;
+        let (r0, r1, r2, r3, r4) = (r[0] as u64, r[1] as u64, r[2] as u64, r[3] as u64, r[4] as u64);
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        let mut h0 = (d0 & 0x3ffffff) as u32 + (d4 >> 26) as u32 * 5;
+        let h1 = (d1 & 0x3ffffff) as u32 + (h0 >> 26);
+        h0 &= 0x3ffffff;
+        self.h = [h0, h1, (d2 & 0x3ffffff) as u32, (d3 & 0x3ffffff) as u32, (d4 & 0x3ffffff) as u32];
+    }
+
+    // data padded with zeros to a multiple of 16
+    fn padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut m = [0u8; 16];
+            m[0..chunk.len()].copy_from_slice(chunk);
+            self.block(&m);
+        }
+    }
+
+    fn finish(self) -> [u8; 16] {
+        let mut h = self.h;
+        // carry fully
+        for i in 1..5 {
+            h[i] += h[i - 1] >> 26;
+            h[i - 1] &= 0x3ffffff;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= 0x3ffffff;
+        h[1] += h[0] >> 26;
+        h[0] &= 0x3ffffff;
+        // h - p, used when h >= p
+        let mut g = [0u32; 5];
+        g[0] = h[0] + 5;
+        for i in 1..5 {
+            g[i] = h[i] + (g[i - 1] >> 26);
+            g[i - 1] &= 0x3ffffff;
+        }
+        g[4] = g[4].wrapping_sub(1 << 26);
+        let mask = (g[4] >> 31).wrapping_sub(1);
+        for i in 0..5 {
+            h[i] = (h[i] & !mask) | (g[i] & mask);
+        }
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8)
+        ];
+        let mut out = [0u8; 16];
+        let mut carry = 0u64;
+        for i in 0..4 {
+            let f = words[i] as u64 + self.s[i] as u64 + carry;
+            out[i * 4..i * 4 + 4].copy_from_slice(&(f as u32).to_le_bytes());
+            carry = f >> 32;
+        }
+        out
+    }
+}
+
+fn aead_tag(key: &[u32; 8], nonce: &[u32; 3], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+    let poly_key = chacha20_block(key, 0, nonce);
+    let mut poly = Poly1305::new(&poly_key[0..32]);
+    poly.padded(aad);
+    poly.padded(ciphertext);
+    let mut lengths = [0u8; 16];
+    lengths[0..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+    lengths[8..16].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly.block(&lengths);
+    poly.finish()
+}
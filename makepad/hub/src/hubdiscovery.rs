@@ -0,0 +1,221 @@
+use std::net::{UdpSocket, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+
+use crate::hubclient::*;
+use crate::hubcrypto::*;
+use makepad_microserde::*;
+
+// builders that aren't connected announce themselves on the LAN, so a UI can list them and
+// invite them to its hub without editing makepad_settings.ron for every machine
+
+const HUB_DISCOVERY_MAGIC: [u8; 8] = *b"mkpddsc1";
+const HUB_DISCOVERY_MAX_PACKET: usize = 8192;
+
+// a builder that hasn't announced for this long is connected elsewhere, or gone
+pub const HUB_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+#[version = 1]
+pub enum HubDiscoveryMsg {
+    Announce {
+        builder: String,
+        workspaces: Vec<String>,
+        port: u16, // the hub port the builder tries to connect to
+        fingerprint: String
+    },
+    Invite {
+        hub_port: u16,
+        fingerprint: String
+    }
+}
+
+impl HubDiscoveryMsg {
+    fn to_packet(&self) -> Vec<u8> {
+        let mut packet = HUB_DISCOVERY_MAGIC.to_vec();
+        packet.extend_from_slice(&self.serialize_bin_tagged());
+        packet
+    }
+
+    fn from_packet(packet: &[u8]) -> Option<HubDiscoveryMsg> {
+        if packet.len() < 8 || packet[0..8] != HUB_DISCOVERY_MAGIC {
+            return None
+        }
+        HubDiscoveryMsg::deserialize_bin_tagged(&packet[8..]).ok()
+    }
+}
+
+// the builder side, announces and waits for an invite
+pub struct HubAnnouncer {
+    pub socket: UdpSocket,
+    pub targets: Vec<SocketAddr>,
+    pub fingerprint: String
+}
+
+impl HubAnnouncer {
+    pub fn new(key: &Digest, announce_port: u16) -> Option<HubAnnouncer> {
+        let socket = if let Ok(socket) = UdpSocket::bind("0.0.0.0:0") {socket}else {println!("HubAnnouncer cannot bind socket"); return None};
+        let _ = socket.set_broadcast(true);
+        Some(HubAnnouncer {
+            socket: socket,
+            // the broadcast doesn't reach a UI on this machine everywhere, so it gets its own
+            targets: vec![
+                SocketAddr::from(([255, 255, 255, 255], announce_port)),
+                SocketAddr::from(([127, 0, 0, 1], announce_port)),
+            ],
+            fingerprint: key_fingerprint(key)
+        })
+    }
+
+    pub fn announce(&self, builder: &str, workspaces: Vec<String>, port: u16) {
+        let packet = HubDiscoveryMsg::Announce {
+            builder: builder.to_string(),
+            workspaces: workspaces,
+            port: port,
+            fingerprint: self.fingerprint.clone()
+        }.to_packet();
+        for target in &self.targets {
+            let _ = self.socket.send_to(&packet, target);
+        }
+    }
+
+    // the address of the hub we got invited to, if a UI with our key invites us within timeout
+    pub fn wait_for_invite(&self, timeout: Duration) -> Option<SocketAddr> {
+        let end = Instant::now() + timeout;
+        let mut packet = [0u8; HUB_DISCOVERY_MAX_PACKET];
+        loop {
+            let now = Instant::now();
+            if now >= end {
+                return None
+            }
+            let _ = self.socket.set_read_timeout(Some(end - now));
+            let (len, from) = if let Ok(recv) = self.socket.recv_from(&mut packet) {recv}else {return None};
+            if let Some(HubDiscoveryMsg::Invite {hub_port, fingerprint}) = HubDiscoveryMsg::from_packet(&packet[0..len]) {
+                if fingerprint == self.fingerprint {
+                    return Some(SocketAddr::new(from.ip(), hub_port))
+                }
+                println!("HubAnnouncer ignoring invite from {:?}, it has another key.ron", from);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HubDiscoveredBuilder {
+    pub builder: String,
+    pub workspaces: Vec<String>,
+    pub port: u16,
+    pub fingerprint: String,
+    pub same_key: bool, // only these can connect to our hub
+    pub addr: SocketAddr, // where the announce came from, and where invites go
+    pub last_seen: Instant
+}
+
+#[derive(Default)]
+pub struct HubDiscoveryShared {
+    pub terminate: bool,
+    pub builders: HashMap<String, HubDiscoveredBuilder>
+}
+
+// the UI side, listens for announcing builders
+pub struct HubDiscovery {
+    pub socket: UdpSocket,
+    pub fingerprint: String,
+    pub listen_address: SocketAddr,
+    pub shared: Arc<Mutex<HubDiscoveryShared>>,
+    pub listen_thread: Option<std::thread::JoinHandle<()>>
+}
+
+impl HubDiscovery {
+    // on_change is called from the listen thread when a builder shows up, changes or goes away
+    pub fn start_hub_discovery<F>(key: &Digest, listen_address: SocketAddr, on_change: F) -> Option<HubDiscovery>
+    where F: Fn() + Send + 'static {
+        let socket = if let Ok(socket) = UdpSocket::bind(listen_address) {socket}else {println!("start_hub_discovery cannot bind {:?}", listen_address); return None};
+        let listen_address = socket.local_addr().expect("Cannot get local address");
+        let fingerprint = key_fingerprint(key);
+        let shared = Arc::new(Mutex::new(HubDiscoveryShared::default()));
+
+        let listen_thread = {
+            let socket = socket.try_clone().expect("Cannot clone socket");
+            let shared = Arc::clone(&shared);
+            let fingerprint = fingerprint.clone();
+            let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+            std::thread::spawn(move || {
+                let mut packet = [0u8; HUB_DISCOVERY_MAX_PACKET];
+                loop {
+                    let recv = socket.recv_from(&mut packet);
+                    let mut shared = shared.lock().unwrap();
+                    if shared.terminate {
+                        return
+                    }
+                    let mut changed = false;
+                    if let Ok((len, from)) = recv {
+                        if let Some(HubDiscoveryMsg::Announce {builder, workspaces, port, fingerprint: their_fingerprint}) = HubDiscoveryMsg::from_packet(&packet[0..len]) {
+                            let discovered = HubDiscoveredBuilder {
+                                same_key: their_fingerprint == fingerprint,
+                                builder: builder.clone(),
+                                workspaces: workspaces,
+                                port: port,
+                                fingerprint: their_fingerprint,
+                                addr: from,
+                                last_seen: Instant::now()
+                            };
+                            changed = if let Some(old) = shared.builders.get(&builder) {
+                                old.addr != discovered.addr || old.workspaces != discovered.workspaces || old.fingerprint != discovered.fingerprint
+                            }
+                            else {
+                                true
+                            };
+                            shared.builders.insert(builder, discovered);
+                        }
+                    }
+                    let count = shared.builders.len();
+                    shared.builders.retain( | _, b | b.last_seen.elapsed() < HUB_DISCOVERY_TIMEOUT);
+                    if changed || count != shared.builders.len() {
+                        drop(shared);
+                        on_change();
+                    }
+                }
+            })
+        };
+
+        Some(HubDiscovery {
+            socket: socket,
+            fingerprint: fingerprint,
+            listen_address: listen_address,
+            shared: shared,
+            listen_thread: Some(listen_thread)
+        })
+    }
+
+    // sorted by name
+    pub fn builders(&self) -> Vec<HubDiscoveredBuilder> {
+        let shared = self.shared.lock().unwrap();
+        let mut builders: Vec<HubDiscoveredBuilder> = shared.builders.values().cloned().collect();
+        builders.sort_by( | a, b | a.builder.cmp(&b.builder));
+        builders
+    }
+
+    // asks a discovered builder to connect to our hub on hub_port
+    pub fn invite(&self, builder: &str, hub_port: u16) -> bool {
+        let addr = if let Some(discovered) = self.shared.lock().unwrap().builders.get(builder) {
+            discovered.addr
+        }
+        else {
+            return false
+        };
+        let packet = HubDiscoveryMsg::Invite {
+            hub_port: hub_port,
+            fingerprint: self.fingerprint.clone()
+        }.to_packet();
+        self.socket.send_to(&packet, addr).is_ok()
+    }
+
+    pub fn terminate(&mut self) {
+        self.shared.lock().unwrap().terminate = true;
+        if let Some(listen_thread) = self.listen_thread.take() {
+            let _ = listen_thread.join();
+        }
+    }
+}
@@ -4,8 +4,17 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use crate::httpserver::*;
 use crate::hubclient::*;
+use crate::hubsync::*;
+use crate::cargotest::*;
+use crate::dapclient::*;
+use crate::lspclient::*;
+use crate::terminal::*;
 
-#[derive(Clone, Debug, SerBin, DeBin)]
+// sent between hubs in the tagged bin format, so that builders and UIs of different versions
+// can talk. variants and fields get ids from their position: add new ones at the end or give
+// them an #[id], and bump the version when older programs can't read the messages anymore
+#[derive(Clone, Debug, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+#[version = 1]
 pub enum HubMsg {
     ConnectBuilder(String),
     ConnectClone(String),
@@ -131,6 +140,273 @@ pub enum HubMsg {
         path: String,
         done: bool
     },
+    
+    // syncing a workspace to another builder, see HubSync
+    SyncSignatureRequest {
+        uid: HubUid,
+        path: String,
+        block_size: u32
+    },
+    
+    SyncSignatureResponse {
+        uid: HubUid,
+        path: String,
+        signatures: Option<Vec<SyncBlockSig>>
+    },
+    
+    SyncDeltaRequest {
+        uid: HubUid,
+        path: String,
+        block_size: u32,
+        signatures: Vec<SyncBlockSig>
+    },
+    
+    SyncDeltaResponse {
+        uid: HubUid,
+        path: String,
+        delta: Option<SyncDelta>
+    },
+    
+    SyncApplyRequest {
+        uid: HubUid,
+        path: String,
+        block_size: u32,
+        delta: SyncDelta
+    },
+    
+    SyncDeleteRequest {
+        uid: HubUid,
+        path: String
+    },
+    
+    SyncRenameRequest {
+        uid: HubUid,
+        from: String,
+        to: String
+    },
+    
+    SyncFileResponse {
+        uid: HubUid,
+        path: String,
+        done: bool
+    },
+    
+    SyncProgress {
+        uid: HubUid,
+        done: usize,
+        total: usize,
+        path: String
+    },
+    
+    SyncConflict {
+        uid: HubUid,
+        path: String
+    },
+    
+    SyncEnd {
+        uid: HubUid,
+        result: SyncResult
+    },
+    
+    // cargo test, see cargotest.rs
+    TestListRequest {
+        uid: HubUid,
+        workspace: String,
+        package: String
+    },
+    
+    TestListResponse {
+        uid: HubUid,
+        tests: Vec<HubTest>
+    },
+    
+    TestRun {
+        uid: HubUid,
+        workspace: String,
+        package: String,
+        tests: Vec<HubTest> // all of them when empty
+    },
+    
+    TestBegin {
+        uid: HubUid,
+        target: HubTestTarget,
+        count: usize
+    },
+    
+    TestResult {
+        uid: HubUid,
+        result: HubTestResult
+    },
+    
+    TestEnd {
+        uid: HubUid,
+        passed: usize,
+        failed: usize,
+        ignored: usize
+    },
+    
+    // a program run under a debug adapter, see dapclient.rs. it begins and ends like
+    // ProgramRun does, and ProgramKill stops it
+    DebugRun {
+        uid: HubUid,
+        path: String,
+        args: Vec<String>,
+        breakpoints: Vec<HubBreakpoint>
+    },
+    
+    DebugSetBreakpoints {
+        uid: HubUid,
+        path: String,
+        lines: Vec<usize>
+    },
+    
+    DebugCommand {
+        uid: HubUid,
+        command: HubDebugCommand
+    },
+    
+    DebugVariablesRequest {
+        uid: HubUid,
+        frame_id: u64,
+        reference: u64 // the locals of the frame when 0
+    },
+    
+    DebugVariablesResponse {
+        uid: HubUid,
+        frame_id: u64,
+        reference: u64,
+        variables: Vec<HubVariable>
+    },
+    
+    DebugStopped {
+        uid: HubUid,
+        reason: String,
+        thread_id: u64,
+        frames: Vec<HubStackFrame>,
+        variables: Vec<HubVariable> // the locals of the top frame
+    },
+    
+    DebugContinued {
+        uid: HubUid
+    },
+    
+    // the language server of a workspace, see lspclient.rs. documents are synced with edits
+    // in the order they happened, the uid of the first LspOpen of a workspace gets its
+    // diagnostics. lines and cols count from 1
+    LspOpen {
+        uid: HubUid,
+        path: String,
+        text: String
+    },
+    
+    LspChange {
+        uid: HubUid,
+        path: String,
+        edits: Vec<LocEdit>
+    },
+    
+    LspClose {
+        uid: HubUid,
+        path: String
+    },
+    
+    LspDiagnostics {
+        uid: HubUid,
+        path: String,
+        items: Vec<HubLogItem>
+    },
+    
+    LspHoverRequest {
+        uid: HubUid,
+        path: String,
+        line: usize,
+        col: usize
+    },
+    
+    LspHoverResponse {
+        uid: HubUid,
+        text: String
+    },
+    
+    LspDefinitionRequest {
+        uid: HubUid,
+        path: String,
+        line: usize,
+        col: usize
+    },
+    
+    LspReferencesRequest {
+        uid: HubUid,
+        path: String,
+        line: usize,
+        col: usize
+    },
+    
+    LspLocationsResponse {
+        uid: HubUid,
+        locations: Vec<LocMessage>
+    },
+    
+    LspCompletionRequest {
+        uid: HubUid,
+        path: String,
+        line: usize,
+        col: usize
+    },
+    
+    LspCompletionResponse {
+        uid: HubUid,
+        items: Vec<HubCompletion>
+    },
+    
+    // a shell on a pty in a workspace, the uid names the terminal from then on. an empty
+    // command starts the shell of the user
+    TerminalOpen {
+        uid: HubUid,
+        workspace: String,
+        command: Vec<String>,
+        cols: usize,
+        rows: usize
+    },
+    
+    TerminalInput {
+        uid: HubUid,
+        data: Vec<u8>
+    },
+    
+    TerminalResize {
+        uid: HubUid,
+        cols: usize,
+        rows: usize
+    },
+    
+    TerminalOutput {
+        uid: HubUid,
+        data: Vec<u8>
+    },
+    
+    TerminalKill {
+        uid: HubUid
+    },
+    
+    TerminalEnd {
+        uid: HubUid,
+        code: Option<i32>
+    },
+    
+    // terminals keep running without a UI, these find them again and replay what they wrote
+    TerminalListRequest {
+        uid: HubUid
+    },
+    
+    TerminalListResponse {
+        uid: HubUid,
+        terminals: Vec<HubTerminalInfo>
+    },
+    
+    TerminalAttach {
+        uid: HubUid
+    },
 }
 
 impl HubMsg{
@@ -138,12 +414,38 @@ impl HubMsg{
         match self{
             HubMsg::BuilderConfig{..}=>true,
             HubMsg::FileWriteRequest{..}=>true,
+            HubMsg::SyncApplyRequest{..}=>true,
+            HubMsg::SyncDeleteRequest{..}=>true,
+            HubMsg::SyncRenameRequest{..}=>true,
+            // the adapter has to get these in order, they only write a request
+            HubMsg::DebugSetBreakpoints{..}=>true,
+            HubMsg::DebugCommand{..}=>true,
+            HubMsg::DebugVariablesRequest{..}=>true,
+            // a document has to be in sync before anything is asked about it
+            HubMsg::LspOpen{..}=>true,
+            HubMsg::LspChange{..}=>true,
+            HubMsg::LspClose{..}=>true,
+            HubMsg::LspHoverRequest{..}=>true,
+            HubMsg::LspDefinitionRequest{..}=>true,
+            HubMsg::LspReferencesRequest{..}=>true,
+            HubMsg::LspCompletionRequest{..}=>true,
+            // keystrokes have to reach the pty in order
+            HubMsg::TerminalOpen{..}=>true,
+            HubMsg::TerminalInput{..}=>true,
+            HubMsg::TerminalResize{..}=>true,
             _=>false
         }
     }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, SerBin, DeBin, SerRon, DeRon)]
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubTerminalInfo {
+    pub uid: HubUid,
+    pub workspace: String,
+    pub command: Vec<String>
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
 pub enum BuilderFileTreeNode {
     File {name: String, digest:Option<Box<Digest>>},
     Folder {name: String, digest:Option<Box<Digest>>, folder: Vec<BuilderFileTreeNode>}
@@ -183,7 +485,7 @@ impl PartialOrd for BuilderFileTreeNode {
 }
 
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub enum BuildResult {
     Executable {path: String},
     Wasm {path: String},
@@ -192,32 +494,136 @@ pub enum BuildResult {
     Error,
 }
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct HubPackage {
     pub project: String,
     pub package_name: String,
     pub configs: Vec<String>,
+    // the build profiles behind the configs
+    #[default]
+    pub profiles: Vec<HubBuildProfile>,
 }
 
 impl HubPackage {
-    pub fn new(project: &str, package_name: &str, targets: &[&str]) -> HubPackage {
+    pub fn new(project: &str, package_name: &str, profiles: Vec<HubBuildProfile>) -> HubPackage {
         HubPackage {
             project: project.to_string(),
             package_name: package_name.to_string(),
-            configs: targets.iter().map( | v | v.to_string()).collect()
+            configs: profiles.iter().map( | p | p.name.clone()).collect(),
+            profiles: profiles
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
+pub enum HubPostBuild {
+    WasmStripDebug
+}
+
+// how a builder builds a package, the configs of the Build message are their names
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
+pub struct HubBuildProfile {
+    pub name: String,
+    // the packages it is for, a * at the start or end matches anything there. empty is all of them
+    #[default]
+    pub packages: Vec<String>,
+    // the cargo subcommand, like build, check or clippy
+    pub command: String,
+    #[default]
+    pub args: Vec<String>,
+    #[default]
+    pub features: Vec<String>,
+    #[default]
+    pub target: Option<String>,
+    #[default]
+    pub env: HashMap<String, String>,
+    #[default]
+    pub post_build: Vec<HubPostBuild>,
+}
+
+impl HubBuildProfile {
+    pub fn new(name: &str, command: &str, args: &[&str]) -> HubBuildProfile {
+        HubBuildProfile {
+            name: name.to_string(),
+            packages: Vec::new(),
+            command: command.to_string(),
+            args: args.iter().map( | a | a.to_string()).collect(),
+            features: Vec::new(),
+            target: None,
+            env: HashMap::new(),
+            post_build: Vec::new()
+        }
+    }
+    
+    // check, debug, release and small, with the packages ending in wasm built for the web
+    pub fn defaults() -> Vec<HubBuildProfile> {
+        let small = | mut profile: HubBuildProfile | {
+            profile.env.insert("RUSTFLAGS".to_string(), "-C opt-level=z -C panic=abort -C codegen-units=1".to_string());
+            profile
+        };
+        let wasm = | mut profile: HubBuildProfile | {
+            profile.packages = vec!["*wasm".to_string()];
+            profile.target = Some("wasm32-unknown-unknown".to_string());
+            profile
+        };
+        let mut wasm_small = wasm(small(HubBuildProfile::new("small", "build", &["--release"])));
+        wasm_small.post_build.push(HubPostBuild::WasmStripDebug);
+        vec![
+            wasm(HubBuildProfile::new("check", "check", &[])),
+            wasm(HubBuildProfile::new("debug", "build", &[])),
+            wasm(HubBuildProfile::new("release", "build", &["--release"])),
+            wasm_small,
+            wasm(HubBuildProfile::new("clippy", "clippy", &[])),
+            HubBuildProfile::new("check", "check", &[]),
+            HubBuildProfile::new("debug", "build", &[]),
+            HubBuildProfile::new("release", "build", &["--release"]),
+            small(HubBuildProfile::new("small", "build", &["--release"])),
+            HubBuildProfile::new("clippy", "clippy", &[]),
+        ]
+    }
+    
+    pub fn matches_package(&self, package: &str) -> bool {
+        self.packages.len() == 0 || self.packages.iter().any( | pattern | {
+            if pattern.starts_with('*') {
+                package.ends_with(&pattern[1..])
+            }
+            else if pattern.ends_with('*') {
+                package.starts_with(&pattern[0..pattern.len() - 1])
+            }
+            else {
+                package == pattern
+            }
+        })
+    }
+    
+    pub fn cargo_args(&self, package: &str) -> Vec<String> {
+        let mut args = vec![self.command.clone(), "-p".to_string(), package.to_string()];
+        args.extend(self.args.iter().cloned());
+        if self.features.len() > 0 {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if let Some(target) = &self.target {
+            args.push(format!("--target={}", target));
+        }
+        args
+    }
+}
 
-#[derive(Debug, Clone, SerBin, DeBin, PartialEq, SerRon, DeRon)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged, PartialEq, SerRon, DeRon)]
 pub struct HubBuilderConfig {
     pub http_server: HttpServerConfig,
     pub workspaces: HashMap<String, String>,
+    #[default(HubBuildProfile::defaults())]
+    pub build_profiles: Vec<HubBuildProfile>,
+    #[default(HubDebugAdapter::default_adapter())]
+    pub debug_adapter: HubDebugAdapter,
+    #[default(HubLanguageServer::default_server())]
+    pub language_server: HubLanguageServer,
 }
 
 
-#[derive(Debug, Clone, PartialEq, SerBin, DeBin)]
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct LocMessage {
     pub path: String,
     pub line: usize,
@@ -226,9 +632,28 @@ pub struct LocMessage {
     pub range: Option<(usize, usize)>,
     pub rendered: Option<String>,
     pub explanation: Option<String>,
+    // suggestions rustc or clippy are sure of, they only edit the file of the message
+    #[default]
+    pub fixes: Vec<LocFix>,
+}
+
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct LocFix {
+    pub label: String,
+    pub edits: Vec<LocEdit>,
 }
 
-#[derive(Debug, Clone, PartialEq, SerBin, DeBin)]
+// replaces the text from start to end, the lines and cols count from 1 like the LocMessage ones
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct LocEdit {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub enum HubLogItem {
     LocPanic(LocMessage),
     LocError(LocMessage),
@@ -236,10 +661,139 @@ pub enum HubLogItem {
     LocMessage(LocMessage),
     Error(String),
     Warning(String),
-    Message(String)
+    Message(String),
+    // output that had ansi colors in it, cargo --color=always or a program that colors its log
+    Styled {level: HubLogLevel, text: HubStyledText},
+    // a panic of a program with its stack, the panic is at the first frame in the workspace
+    Backtrace {panic: LocMessage, frames: Vec<HubBacktraceFrame>}
+}
+
+// a frame of a rust backtrace. path is the builder/workspace path of the file when it is in the
+// workspace of the program, the files of std and of dependencies only have what was printed
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubBacktraceFrame {
+    pub function: String,
+    pub file: String,
+    pub path: Option<String>,
+    pub line: usize,
+    pub col: usize,
+    pub user_code: bool,
+}
+
+impl HubBacktraceFrame {
+    pub fn new(function: String) -> HubBacktraceFrame {
+        HubBacktraceFrame {function: function, file: String::new(), path: None, line: 0, col: 0, user_code: false}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum HubLogLevel {
+    Error,
+    Warning,
+    Message
+}
+
+// text without its escape sequences, and the styles the sequences gave parts of it
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubStyledText {
+    pub body: String,
+    pub spans: Vec<HubStyledSpan>,
+}
+
+// a byte range of the body, text outside of the spans has the default style
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubStyledSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TerminalStyle,
+}
+
+impl HubStyledText {
+    // keeps the SGR sequences as spans and drops the other escape sequences, like the
+    // cursor movement of progress bars or the OSC 8 hyperlinks of cargo
+    pub fn from_ansi(text: &str) -> HubStyledText {
+        let mut body = String::new();
+        let mut spans: Vec<HubStyledSpan> = Vec::new();
+        let mut style = TerminalStyle::default();
+        let mut span_start = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' => match chars.next() {
+                    Some('[') => {
+                        let mut params = Vec::new();
+                        let mut param = None;
+                        while let Some(ch) = chars.next() {
+                            match ch {
+                                '0'..='9' => param = Some(param.unwrap_or(0usize).saturating_mul(10).saturating_add(ch as usize - '0' as usize)),
+                                ';' | ':' => params.push(param.take().unwrap_or(0)),
+                                '\x40'..='\x7e' => {
+                                    if let Some(param) = param.take() {
+                                        params.push(param);
+                                    }
+                                    if ch == 'm' {
+                                        let mut next = style;
+                                        next.apply_sgr(&params);
+                                        if next != style {
+                                            if body.len() > span_start && style != TerminalStyle::default() {
+                                                spans.push(HubStyledSpan {start: span_start, end: body.len(), style: style});
+                                            }
+                                            span_start = body.len();
+                                            style = next;
+                                        }
+                                    }
+                                    break
+                                },
+                                _ => ()
+                            }
+                        }
+                    },
+                    Some(']') => {
+                        // up to a BEL or an ESC \
+                        while let Some(ch) = chars.next() {
+                            if ch == '\x07' || (ch == '\x1b' && chars.peek() == Some(&'\\')) {
+                                if ch == '\x1b' {
+                                    chars.next();
+                                }
+                                break
+                            }
+                        }
+                    },
+                    _ => ()
+                },
+                '\r' => (),
+                _ => body.push(ch)
+            }
+        }
+        if body.len() > span_start && style != TerminalStyle::default() {
+            spans.push(HubStyledSpan {start: span_start, end: body.len(), style: style});
+        }
+        HubStyledText {body: body, spans: spans}
+    }
 }
 
 impl HubLogItem {
+    // a line of output, styled when it has escape sequences
+    pub fn from_output(level: HubLogLevel, line: &str) -> HubLogItem {
+        if line.contains('\x1b') {
+            return HubLogItem::Styled {level: level, text: HubStyledText::from_ansi(line)}
+        }
+        match level {
+            HubLogLevel::Error => HubLogItem::Error(line.to_string()),
+            HubLogLevel::Warning => HubLogItem::Warning(line.to_string()),
+            HubLogLevel::Message => HubLogItem::Message(line.to_string())
+        }
+    }
+
+    pub fn level(&self) -> HubLogLevel {
+        match self {
+            HubLogItem::LocPanic(_) | HubLogItem::LocError(_) | HubLogItem::Error(_) | HubLogItem::Backtrace {..} => HubLogLevel::Error,
+            HubLogItem::LocWarning(_) | HubLogItem::Warning(_) => HubLogLevel::Warning,
+            HubLogItem::LocMessage(_) | HubLogItem::Message(_) => HubLogLevel::Message,
+            HubLogItem::Styled {level, ..} => *level
+        }
+    }
+
     pub fn get_loc_message(&self) -> Option<&LocMessage> {
         match self {
             HubLogItem::LocPanic(msg) => Some(msg),
@@ -248,7 +802,9 @@ impl HubLogItem {
             HubLogItem::LocMessage(msg) => Some(msg),
             HubLogItem::Error(_) => None,
             HubLogItem::Warning(_) => None,
-            HubLogItem::Message(_) => None
+            HubLogItem::Message(_) => None,
+            HubLogItem::Styled {..} => None,
+            HubLogItem::Backtrace {panic, ..} => Some(panic)
         }
     }
     pub fn get_body(&self) -> &String {
@@ -259,24 +815,26 @@ impl HubLogItem {
             HubLogItem::LocMessage(msg) => &msg.body,
             HubLogItem::Error(body) => body,
             HubLogItem::Warning(body) => body,
-            HubLogItem::Message(body) => body
+            HubLogItem::Message(body) => body,
+            HubLogItem::Styled {text, ..} => &text.body,
+            HubLogItem::Backtrace {panic, ..} => &panic.body
         }
     }
 }
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct HubCargoArtifact {
     pub package_id: String,
     pub fresh: bool,
 }
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct HubCargoCheck {
     pub target: String,
     pub args: String,
 }
 
-#[derive(PartialEq, Copy, Debug, Clone, SerBin, DeBin)]
+#[derive(PartialEq, Copy, Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub enum HubAddr {
     None,
     Local {uid: u64},
@@ -308,7 +866,7 @@ impl HubAddr {
     }
 }
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub enum HubMsgTo {
     Client(HubAddr),
     Builder(String),
@@ -317,7 +875,7 @@ pub enum HubMsgTo {
     Hub
 }
 
-#[derive(PartialEq, Copy, Debug, Clone, SerBin, DeBin)]
+#[derive(PartialEq, Copy, Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct HubUid {
     pub addr: HubAddr,
     pub id: u64
@@ -329,19 +887,19 @@ impl HubUid {
     }
 }
 
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct ToHubMsg {
     pub to: HubMsgTo,
     pub msg: HubMsg
 }
 
-#[derive(Clone, Debug, SerBin, DeBin)]
+#[derive(Clone, Debug, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct FromHubMsg {
     pub from: HubAddr,
     pub msg: HubMsg
 }
 
-#[derive(Clone, Debug, SerBin, DeBin)]
+#[derive(Clone, Debug, SerBin, DeBin, SerBinTagged, DeBinTagged)]
 pub struct HubError {
     pub msg: String
 }
@@ -128,6 +128,7 @@ pub struct HubRouter{
     pub tx_pump: mpsc::Sender<(HubAddr, ToHubMsg)>,
     pub routes: Arc<Mutex<Vec<HubRoute>>>,
     pub router_thread: Option<std::thread::JoinHandle<()>>,
+    pub hub_log: HubLog,
 }
 
 impl HubRouter{
@@ -307,7 +308,8 @@ impl HubRouter{
             tx_pump: tx_pump,
             router_thread: Some(router_thread),
             local_uid: 1,
-            routes: routes
+            routes: routes,
+            hub_log: hub_log
         };
     }
 }
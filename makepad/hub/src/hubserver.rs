@@ -4,6 +4,7 @@ use std::sync::{mpsc, Arc, Mutex};
 use crate::hubmsg::*;
 use crate::hubclient::*;
 use crate::hubrouter::*;
+use crate::hubcrypto::*;
 use makepad_microserde::*;
 
 #[derive(Debug, Clone, SerBin, DeBin, SerRon, DeRon, PartialEq)]
@@ -46,105 +47,31 @@ impl HubServer {
         }));
         
         let listen_thread = {
-            //let hub_log = hub_log.clone();
+            let hub_log = hub_router.hub_log.clone();
             let routes = Arc::clone(&routes);
             let shared = Arc::clone(&shared);
             let digest = digest.clone();
             std::thread::spawn(move || {
                 for tcp_stream in listener.incoming() {
                     let tcp_stream = tcp_stream.expect("Incoming stream failure");
-                    let peer_addr = HubAddr::from_socket_addr(tcp_stream.peer_addr().expect("No peer address"));
-                    
-                    if let Ok(mut shared) = shared.lock() {
+                    if let Ok(shared) = shared.lock() {
                         if shared.terminate {
-                            for (_, tcp_stream) in &mut shared.connections {
+                            for (_, tcp_stream) in &shared.connections {
                                 let _ = tcp_stream.shutdown(Shutdown::Both);
                             }
                             // lets disconnect all our connections
                             return
                         }
-                        let tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
-                        shared.connections.push((peer_addr, tcp_stream));
                     }
-                    
-                    let (tx_write, rx_write) = mpsc::channel::<FromHubMsg>();
-                    let tx_write_copy = tx_write.clone();
-                    // clone our transmit-to-pump
-                    let _read_thread = {
-                        let tx_pump = tx_pump.clone();
-                        let digest = digest.clone();
-                        let peer_addr = peer_addr.clone();
-                        let mut tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
-                        //let hub_log = hub_log.clone();
-                        std::thread::spawn(move || {
-                            loop {
-                                match read_block_from_tcp_stream(&mut tcp_stream, digest.clone()) {
-                                    Ok(msg_buf) => {
-                                        let cth_msg: ToHubMsg = DeBin::deserialize_bin(&msg_buf).expect("Can't parse binary");
-                                        tx_pump.send((peer_addr.clone(), cth_msg)).expect("tx_pump.send fails - should never happen");
-                                    }
-                                    Err(e) => {
-                                        let _ = tcp_stream.shutdown(Shutdown::Both);
-                                        let _ = tx_pump.send((peer_addr.clone(), ToHubMsg {
-                                            to: HubMsgTo::Hub,
-                                            msg: HubMsg::ConnectionError(e.clone())
-                                        })).expect("tx_pump.send fails - should never happen");
-                                        // lets break rx write
-                                        let _ = tx_write_copy.send(FromHubMsg {
-                                            from: peer_addr.clone(),
-                                            msg: HubMsg::ConnectionError(e)
-                                        });
-                                        return
-                                    }
-                                }
-                            }
-                        })
-                    };
-                    let _write_thread = {
-                        let digest = digest.clone();
-                        let peer_addr = peer_addr.clone();
-                        let tx_pump = tx_pump.clone();
-                        let shared = Arc::clone(&shared);
-                        let mut tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
-                        //let hub_log = hub_log.clone();
-                        std::thread::spawn(move || {
-                            while let Ok(htc_msg) = rx_write.recv() {
-                                match &htc_msg.msg {
-                                    HubMsg::ConnectionError(_) => { // we are closed by the read loop
-                                        let _ = tcp_stream.shutdown(Shutdown::Both);
-                                        break
-                                    },
-                                    _ => ()
-                                }
-                                let mut msg_buf = Vec::new(); 
-                                htc_msg.ser_bin(&mut msg_buf);
-                                
-                                if let Err(e) = write_block_to_tcp_stream(&mut tcp_stream, &msg_buf, digest.clone()) {
-                                    // disconnect the socket and send shutdown
-                                    let _ = tcp_stream.shutdown(Shutdown::Both);
-                                    tx_pump.send((peer_addr.clone(), ToHubMsg {
-                                        to: HubMsgTo::Hub,
-                                        msg: HubMsg::ConnectionError(e)
-                                    })).expect("tx_pump.send fails - should never happen");
-                                }
-                            }
-                            // remove tx_write from our shared pool
-                            if let Ok(mut shared) = shared.lock() {
-                                while let Some(position) = shared.connections.iter().position( | (addr, _) | *addr == peer_addr) {
-                                    shared.connections.remove(position);
-                                }
-                            }
-                        })
-                    };
-                    
-                    if let Ok(mut routes) = routes.lock() {
-                        routes.push(HubRoute {
-                            route_type: HubRouteType::Unknown,
-                            peer_addr: peer_addr.clone(),
-                            tcp_stream: Some(tcp_stream),
-                            tx_write: tx_write
-                        })
-                    };
+                    // the handshake runs on its own thread so a slow peer can't hold up the others
+                    let tx_pump = tx_pump.clone();
+                    let routes = Arc::clone(&routes);
+                    let shared = Arc::clone(&shared);
+                    let digest = digest.clone();
+                    let hub_log = hub_log.clone();
+                    std::thread::spawn(move || {
+                        Self::start_connection(tcp_stream, &digest, tx_pump, routes, shared, &hub_log)
+                    });
                 }
             })
         };
@@ -159,6 +86,114 @@ impl HubServer {
         return Some(hub_server);
     }
     
+    fn start_connection(
+        mut tcp_stream: TcpStream,
+        digest: &Digest,
+        tx_pump: mpsc::Sender<(HubAddr, ToHubMsg)>,
+        routes: Arc<Mutex<Vec<HubRoute>>>,
+        shared: Arc<Mutex<HubServerShared>>,
+        hub_log: &HubLog
+    ) {
+        let peer_addr = if let Ok(addr) = tcp_stream.peer_addr() {HubAddr::from_socket_addr(addr)} else {return};
+        let HubSession {read: mut read_cipher, write: mut write_cipher} = match hub_handshake_server(&mut tcp_stream, digest) {
+            Ok(session) => session,
+            Err(e) => {
+                // a peer without our key.ron never gets a route
+                hub_log.log(&format!("Hub connection from {:?} refused: {}", peer_addr, e.msg));
+                let _ = tcp_stream.shutdown(Shutdown::Both);
+                return
+            }
+        };
+        
+        if let Ok(mut shared) = shared.lock() {
+            if shared.terminate {
+                let _ = tcp_stream.shutdown(Shutdown::Both);
+                return
+            }
+            let tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
+            shared.connections.push((peer_addr, tcp_stream));
+        }
+        
+        let (tx_write, rx_write) = mpsc::channel::<FromHubMsg>();
+        let tx_write_copy = tx_write.clone();
+        // clone our transmit-to-pump
+        let _read_thread = {
+            let tx_pump = tx_pump.clone();
+            let peer_addr = peer_addr.clone();
+            let mut tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
+            //let hub_log = hub_log.clone();
+            std::thread::spawn(move || {
+                loop {
+                    // a message we can't read is an incompatible peer, and closes the connection
+                    let msg = read_block_from_tcp_stream(&mut tcp_stream, &mut read_cipher).and_then( | msg_buf | {
+                        ToHubMsg::deserialize_bin_tagged(&msg_buf).map_err( | err | HubError::new(&format!("Cannot parse message: {:?}", err)))
+                    });
+                    match msg {
+                        Ok(cth_msg) => {
+                            tx_pump.send((peer_addr.clone(), cth_msg)).expect("tx_pump.send fails - should never happen");
+                        }
+                        Err(e) => {
+                            let _ = tcp_stream.shutdown(Shutdown::Both);
+                            let _ = tx_pump.send((peer_addr.clone(), ToHubMsg {
+                                to: HubMsgTo::Hub,
+                                msg: HubMsg::ConnectionError(e.clone())
+                            })).expect("tx_pump.send fails - should never happen");
+                            // lets break rx write
+                            let _ = tx_write_copy.send(FromHubMsg {
+                                from: peer_addr.clone(),
+                                msg: HubMsg::ConnectionError(e)
+                            });
+                            return
+                        }
+                    }
+                }
+            })
+        };
+        let _write_thread = {
+            let peer_addr = peer_addr.clone();
+            let tx_pump = tx_pump.clone();
+            let shared = Arc::clone(&shared);
+            let mut tcp_stream = tcp_stream.try_clone().expect("Cannot clone tcp stream");
+            //let hub_log = hub_log.clone();
+            std::thread::spawn(move || {
+                while let Ok(htc_msg) = rx_write.recv() {
+                    match &htc_msg.msg {
+                        HubMsg::ConnectionError(_) => { // we are closed by the read loop
+                            let _ = tcp_stream.shutdown(Shutdown::Both);
+                            break
+                        },
+                        _ => ()
+                    }
+                    let msg_buf = htc_msg.serialize_bin_tagged();
+                    
+                    if let Err(e) = write_block_to_tcp_stream(&mut tcp_stream, &msg_buf, &mut write_cipher) {
+                        // disconnect the socket and send shutdown
+                        let _ = tcp_stream.shutdown(Shutdown::Both);
+                        tx_pump.send((peer_addr.clone(), ToHubMsg {
+                            to: HubMsgTo::Hub,
+                            msg: HubMsg::ConnectionError(e)
+                        })).expect("tx_pump.send fails - should never happen");
+                    }
+                }
+                // remove tx_write from our shared pool
+                if let Ok(mut shared) = shared.lock() {
+                    while let Some(position) = shared.connections.iter().position( | (addr, _) | *addr == peer_addr) {
+                        shared.connections.remove(position);
+                    }
+                }
+            })
+        };
+        
+        if let Ok(mut routes) = routes.lock() {
+            routes.push(HubRoute {
+                route_type: HubRouteType::Unknown,
+                peer_addr: peer_addr.clone(),
+                tcp_stream: Some(tcp_stream),
+                tx_write: tx_write
+            })
+        };
+    }
+    
     pub fn terminate(&mut self) {
         if let Ok(mut shared) = self.shared.lock() {
             shared.terminate = true;
@@ -0,0 +1,530 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::hubmsg::*;
+use crate::hubclient::*;
+use crate::hubrouter::*;
+use crate::hubcrypto::*;
+use makepad_microserde::*;
+
+// keeps a mirror workspace on another builder in sync with a source workspace. HubSync runs
+// on the UI side: it asks both builders for digest trees, plans what changed, and moves the
+// changed files over as rsync style block deltas
+
+// files smaller than this are sent whole, there is no point in asking for signatures
+pub const SYNC_BLOCK_SIZE: u32 = 2048;
+const SYNC_DELTA_MIN_SIZE: u64 = SYNC_BLOCK_SIZE as u64 * 4;
+// block sizes come from the other side, builders refuse anything outside 1..=this
+pub const SYNC_MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+// the files of a workspace by path, relative to the workspace
+pub type SyncDigests = BTreeMap<String, SyncFile>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncFile {
+    pub digest: Digest,
+    pub size: u64
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct SyncBlockSig {
+    pub weak: u32,
+    pub strong: u64
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum SyncDeltaOp {
+    Copy {block: u32, count: u32},
+    Data(Vec<u8>)
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct SyncDelta {
+    pub digest: Digest, // of the whole new file, checked after applying
+    pub ops: Vec<SyncDeltaOp>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct SyncResult {
+    pub written: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+    pub conflicts: usize,
+    pub failed: usize,
+    pub bytes_sent: u64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyncAction {
+    Write(String),
+    Delete(String),
+    Rename {from: String, to: String},
+    Conflict(String)
+}
+
+// file digests are SHA3-256 with the file size after it, kept in a Digest so they fit BuilderFileTreeNode
+pub fn file_digest(data: &[u8]) -> Digest {
+    let hash = sha3_256(&[data]);
+    let mut digest = Digest::default();
+    for i in 0..4 {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&hash[i * 8..i * 8 + 8]);
+        digest.buf[i] = u64::from_le_bytes(word);
+    }
+    digest.buf[4] = data.len() as u64;
+    digest
+}
+
+// the files of a workspace in a tree from BuilderFileTreeRequest with create_digest
+pub fn sync_digests_from_tree(tree: &BuilderFileTreeNode, workspace: &str) -> Option<SyncDigests> {
+    fn recur(nodes: &Vec<BuilderFileTreeNode>, base: &str, out: &mut SyncDigests) {
+        for node in nodes {
+            match node {
+                BuilderFileTreeNode::File {name, digest} => if let Some(digest) = digest {
+                    out.insert(format!("{}{}", base, name), SyncFile {digest: *digest.clone(), size: digest.buf[4]});
+                },
+                BuilderFileTreeNode::Folder {name, folder, ..} => {
+                    recur(folder, &format!("{}{}/", base, name), out);
+                }
+            }
+        }
+    }
+    if let BuilderFileTreeNode::Folder {folder, ..} = tree {
+        for node in folder {
+            if let BuilderFileTreeNode::Folder {name, folder, ..} = node {
+                if name == workspace {
+                    let mut out = SyncDigests::new();
+                    recur(folder, "", &mut out);
+                    return Some(out)
+                }
+            }
+        }
+    }
+    None
+}
+
+// what it takes to make target look like source. base is what both looked like after the last
+// sync: a target file changed since then is a conflict and left alone, and only files that
+// were synced before get deleted. without a base nothing is deleted or called a conflict
+pub fn sync_plan(source: &SyncDigests, target: &SyncDigests, base: Option<&SyncDigests>) -> Vec<SyncAction> {
+    let mut writes = Vec::new();
+    let mut deletes = Vec::new();
+    let mut actions = Vec::new();
+
+    let changed_on_target = | path: &str, file: &SyncFile | {
+        if let Some(base) = base {
+            base.get(path).map( | b | b.digest != file.digest).unwrap_or(true)
+        }
+        else {
+            false
+        }
+    };
+
+    for (path, file) in source {
+        match target.get(path) {
+            Some(target_file) if target_file.digest == file.digest => (),
+            Some(target_file) => if changed_on_target(path, target_file) {
+                actions.push(SyncAction::Conflict(path.clone()))
+            }
+            else {
+                writes.push(path.clone())
+            },
+            None => writes.push(path.clone())
+        }
+    }
+    if let Some(base) = base {
+        for (path, file) in target {
+            if source.contains_key(path) {
+                continue;
+            }
+            if base.contains_key(path) {
+                if changed_on_target(path, file) {
+                    actions.push(SyncAction::Conflict(path.clone()))
+                }
+                else {
+                    deletes.push(path.clone())
+                }
+            }
+        }
+    }
+    // a file that is new on target and one to delete with the same content is a rename
+    for write in writes {
+        let source_digest = &source[&write].digest;
+        if !target.contains_key(&write) {
+            if let Some(pos) = deletes.iter().position( | d | target[d].digest == *source_digest) {
+                actions.push(SyncAction::Rename {from: deletes.remove(pos), to: write});
+                continue;
+            }
+        }
+        actions.push(SyncAction::Write(write));
+    }
+    for delete in deletes {
+        actions.push(SyncAction::Delete(delete));
+    }
+    actions
+}
+
+// the rsync rolling checksum
+fn weak_checksum(block: &[u8]) -> (u32, u32) {
+    let mut a: u32 = 0;
+    let mut b: u32 = 0;
+    let len = block.len() as u32;
+    for (i, byte) in block.iter().enumerate() {
+        a = a.wrapping_add(*byte as u32);
+        b = b.wrapping_add((len - i as u32).wrapping_mul(*byte as u32));
+    }
+    (a & 0xffff, b & 0xffff)
+}
+
+fn strong_checksum(block: &[u8]) -> u64 {
+    let hash = sha3_256(&[block]);
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&hash[0..8]);
+    u64::from_le_bytes(word)
+}
+
+// the signatures of the whole blocks of the file on the receiving end
+pub fn sync_signatures(data: &[u8], block_size: u32) -> Vec<SyncBlockSig> {
+    data.chunks_exact(block_size as usize).map( | block | {
+        let (a, b) = weak_checksum(block);
+        SyncBlockSig {weak: a | (b << 16), strong: strong_checksum(block)}
+    }).collect()
+}
+
+// the new data, as blocks the receiver already has and the bytes it doesn't
+pub fn sync_delta(signatures: &[SyncBlockSig], block_size: u32, data: &[u8]) -> SyncDelta {
+    let block_size = block_size as usize;
+    let mut ops = Vec::new();
+    let mut literal_start = 0;
+
+    fn push_copy(ops: &mut Vec<SyncDeltaOp>, block: u32) {
+        if let Some(SyncDeltaOp::Copy {block: start, count}) = ops.last_mut() {
+            if *start + *count == block {
+                *count += 1;
+                return
+            }
+        }
+        ops.push(SyncDeltaOp::Copy {block: block, count: 1});
+    }
+
+    if signatures.len() > 0 && data.len() >= block_size {
+        let mut by_weak = HashMap::new();
+        for (index, sig) in signatures.iter().enumerate() {
+            by_weak.entry(sig.weak).or_insert_with(Vec::new).push(index as u32);
+        }
+        let mut pos = 0;
+        let (mut a, mut b) = weak_checksum(&data[0..block_size]);
+        loop {
+            let mut matched = None;
+            if let Some(blocks) = by_weak.get(&(a | (b << 16))) {
+                let strong = strong_checksum(&data[pos..pos + block_size]);
+                matched = blocks.iter().find( | block | signatures[**block as usize].strong == strong).cloned();
+            }
+            if let Some(block) = matched {
+                if literal_start < pos {
+                    ops.push(SyncDeltaOp::Data(data[literal_start..pos].to_vec()));
+                }
+                push_copy(&mut ops, block);
+                pos += block_size;
+                literal_start = pos;
+                if pos + block_size > data.len() {
+                    break;
+                }
+                let sums = weak_checksum(&data[pos..pos + block_size]);
+                a = sums.0;
+                b = sums.1;
+            }
+            else {
+                if pos + block_size >= data.len() {
+                    break;
+                }
+                // roll the window one byte
+                let out = data[pos] as u32;
+                let inn = data[pos + block_size] as u32;
+                a = a.wrapping_sub(out).wrapping_add(inn) & 0xffff;
+                b = b.wrapping_sub((block_size as u32).wrapping_mul(out)).wrapping_add(a) & 0xffff;
+                pos += 1;
+            }
+        }
+    }
+    if literal_start < data.len() {
+        ops.push(SyncDeltaOp::Data(data[literal_start..].to_vec()));
+    }
+    SyncDelta {digest: file_digest(data), ops: ops}
+}
+
+pub fn sync_delta_apply(old: &[u8], block_size: u32, delta: &SyncDelta) -> Option<Vec<u8>> {
+    let block_size = block_size as usize;
+    let mut out = Vec::new();
+    for op in &delta.ops {
+        match op {
+            SyncDeltaOp::Copy {block, count} => {
+                let start = *block as usize * block_size;
+                let end = start + *count as usize * block_size;
+                if end > old.len() {
+                    return None
+                }
+                out.extend_from_slice(&old[start..end]);
+            },
+            SyncDeltaOp::Data(data) => out.extend_from_slice(data)
+        }
+    }
+    if file_digest(&out) != delta.digest {
+        return None
+    }
+    Some(out)
+}
+
+impl SyncDelta {
+    pub fn data_len(&self) -> u64 {
+        self.ops.iter().map( | op | if let SyncDeltaOp::Data(data) = op {data.len() as u64}else {0}).sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum HubSyncState {
+    Trees,
+    Action,
+    Done
+}
+
+// syncs source ("builder/workspace") to target, one file at a time
+pub struct HubSync {
+    pub uid: HubUid,
+    pub source: String,
+    pub target: String,
+    pub base: Option<SyncDigests>,
+    pub result: SyncResult,
+    state: HubSyncState,
+    source_digests: Option<SyncDigests>,
+    target_digests: Option<SyncDigests>,
+    actions: Vec<SyncAction>,
+    current: Option<SyncAction>,
+    total: usize,
+    failed: Vec<String>
+}
+
+// splits builder/workspace, or workspace/path
+fn split_builder(path: &str) -> (String, String) {
+    if let Some(pos) = path.find('/') {
+        (path[0..pos].to_string(), path[pos + 1..].to_string())
+    }
+    else {
+        (path.to_string(), String::new())
+    }
+}
+
+impl HubSync {
+    pub fn new(uid: HubUid, source: &str, target: &str, base: Option<SyncDigests>) -> HubSync {
+        HubSync {
+            uid: uid,
+            source: source.to_string(),
+            target: target.to_string(),
+            base: base,
+            result: SyncResult::default(),
+            state: HubSyncState::Trees,
+            source_digests: None,
+            target_digests: None,
+            actions: Vec::new(),
+            current: None,
+            total: 0,
+            failed: Vec::new()
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == HubSyncState::Done
+    }
+
+    pub fn start(&mut self, route_send: &HubRouteSend) {
+        for path in &[&self.source, &self.target] {
+            route_send.send(ToHubMsg {
+                to: HubMsgTo::Builder(split_builder(path).0),
+                msg: HubMsg::BuilderFileTreeRequest {uid: self.uid, create_digest: true}
+            });
+        }
+    }
+
+    // what the workspaces look like now, to pass as base to the next sync
+    pub fn synced_digests(&self) -> Option<SyncDigests> {
+        if !self.is_done() {
+            return None
+        }
+        let (source, target) = (self.source_digests.as_ref() ?, self.target_digests.as_ref() ?);
+        let mut synced = source.clone();
+        // what didn't make it over stays as it was, so it shows up again next time
+        for path in &self.failed {
+            match self.base.as_ref().and_then( | base | base.get(path)).or(target.get(path)) {
+                Some(file) => {synced.insert(path.clone(), file.clone());},
+                None => {synced.remove(path);}
+            }
+        }
+        Some(synced)
+    }
+
+    // returns true if the message was for this sync
+    pub fn handle_hub_msg(&mut self, route_send: &HubRouteSend, htc: &FromHubMsg) -> bool {
+        match &htc.msg {
+            HubMsg::BuilderFileTreeResponse {uid, tree} if *uid == self.uid && self.state == HubSyncState::Trees => {
+                let name = if let BuilderFileTreeNode::Folder {name, ..} = tree {name.clone()}else {return true};
+                for (path, digests) in &mut [(&self.source, &mut self.source_digests), (&self.target, &mut self.target_digests)] {
+                    let (builder, workspace) = split_builder(path);
+                    if builder == name && digests.is_none() {
+                        **digests = Some(sync_digests_from_tree(tree, &workspace).unwrap_or(SyncDigests::new()));
+                        break;
+                    }
+                }
+                if let (Some(source), Some(target)) = (&self.source_digests, &self.target_digests) {
+                    self.actions = sync_plan(source, target, self.base.as_ref());
+                    self.actions.reverse();
+                    self.total = self.actions.len();
+                    self.state = HubSyncState::Action;
+                    self.next_action(route_send);
+                }
+                true
+            },
+            HubMsg::SyncSignatureResponse {uid, path, signatures} if *uid == self.uid => {
+                let (builder, workspace) = split_builder(&self.source);
+                route_send.send(ToHubMsg {
+                    to: HubMsgTo::Builder(builder),
+                    msg: HubMsg::SyncDeltaRequest {
+                        uid: self.uid,
+                        path: format!("{}/{}", workspace, split_builder(path).1),
+                        block_size: SYNC_BLOCK_SIZE,
+                        signatures: signatures.clone().unwrap_or(Vec::new())
+                    }
+                });
+                true
+            },
+            HubMsg::SyncDeltaResponse {uid, path, delta} if *uid == self.uid => {
+                if let Some(delta) = delta {
+                    self.result.bytes_sent += delta.data_len();
+                    let (builder, workspace) = split_builder(&self.target);
+                    route_send.send(ToHubMsg {
+                        to: HubMsgTo::Builder(builder),
+                        msg: HubMsg::SyncApplyRequest {
+                            uid: self.uid,
+                            path: format!("{}/{}", workspace, split_builder(path).1),
+                            block_size: SYNC_BLOCK_SIZE,
+                            delta: delta.clone()
+                        }
+                    });
+                }
+                else {
+                    self.action_done(route_send, false);
+                }
+                true
+            },
+            HubMsg::SyncFileResponse {uid, done, ..} if *uid == self.uid => {
+                self.action_done(route_send, *done);
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn send_progress(&self, route_send: &HubRouteSend, path: &str) {
+        route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::SyncProgress {
+                uid: self.uid,
+                done: self.total - self.actions.len(),
+                total: self.total,
+                path: path.to_string()
+            }
+        });
+    }
+
+    fn action_done(&mut self, route_send: &HubRouteSend, done: bool) {
+        let path = match self.current.take() {
+            Some(SyncAction::Write(path)) => {
+                if done {self.result.written += 1}else {self.failed.push(path.clone())}
+                path
+            },
+            Some(SyncAction::Delete(path)) => {
+                if done {self.result.deleted += 1}else {self.failed.push(path.clone())}
+                path
+            },
+            Some(SyncAction::Rename {from, to}) => {
+                if done {self.result.renamed += 1}else {self.failed.push(from); self.failed.push(to.clone())}
+                to
+            },
+            _ => return
+        };
+        if !done {
+            self.result.failed += 1;
+        }
+        self.send_progress(route_send, &path);
+        self.next_action(route_send);
+    }
+
+    fn next_action(&mut self, route_send: &HubRouteSend) {
+        let (target_builder, target_workspace) = split_builder(&self.target);
+        while let Some(action) = self.actions.pop() {
+            if let SyncAction::Conflict(_) = &action {}else {
+                self.current = Some(action.clone());
+            }
+            match action {
+                SyncAction::Conflict(path) => {
+                    self.result.conflicts += 1;
+                    self.failed.push(path.clone());
+                    route_send.send(ToHubMsg {
+                        to: HubMsgTo::UI,
+                        msg: HubMsg::SyncConflict {uid: self.uid, path: format!("{}/{}", self.target, path)}
+                    });
+                    self.send_progress(route_send, &path);
+                    continue;
+                },
+                SyncAction::Write(path) => {
+                    let (source_builder, source_workspace) = split_builder(&self.source);
+                    let target_size = self.target_digests.as_ref().and_then( | t | t.get(&path)).map( | f | f.size);
+                    // only ask for signatures when there is a big enough file to have blocks of
+                    if target_size.map( | size | size >= SYNC_DELTA_MIN_SIZE).unwrap_or(false) {
+                        route_send.send(ToHubMsg {
+                            to: HubMsgTo::Builder(target_builder),
+                            msg: HubMsg::SyncSignatureRequest {
+                                uid: self.uid,
+                                path: format!("{}/{}", target_workspace, path),
+                                block_size: SYNC_BLOCK_SIZE
+                            }
+                        });
+                    }
+                    else {
+                        route_send.send(ToHubMsg {
+                            to: HubMsgTo::Builder(source_builder),
+                            msg: HubMsg::SyncDeltaRequest {
+                                uid: self.uid,
+                                path: format!("{}/{}", source_workspace, path),
+                                block_size: SYNC_BLOCK_SIZE,
+                                signatures: Vec::new()
+                            }
+                        });
+                    }
+                },
+                SyncAction::Delete(path) => {
+                    route_send.send(ToHubMsg {
+                        to: HubMsgTo::Builder(target_builder),
+                        msg: HubMsg::SyncDeleteRequest {
+                            uid: self.uid,
+                            path: format!("{}/{}", target_workspace, path)
+                        }
+                    });
+                },
+                SyncAction::Rename {from, to} => {
+                    route_send.send(ToHubMsg {
+                        to: HubMsgTo::Builder(target_builder),
+                        msg: HubMsg::SyncRenameRequest {
+                            uid: self.uid,
+                            from: format!("{}/{}", target_workspace, from),
+                            to: format!("{}/{}", target_workspace, to)
+                        }
+                    });
+                }
+            }
+            return
+        }
+        self.state = HubSyncState::Done;
+        route_send.send(ToHubMsg {
+            to: HubMsgTo::UI,
+            msg: HubMsg::SyncEnd {uid: self.uid, result: self.result.clone()}
+        });
+    }
+}
@@ -7,9 +7,21 @@ pub use crate::hubbuilder::*;
 mod process;
 pub use crate::process::*;
 
+mod pty;
+pub use crate::pty::*;
+
+mod terminal;
+pub use crate::terminal::*;
+
 mod hubclient;
 pub use crate::hubclient::*;
 
+mod hubcrypto;
+pub use crate::hubcrypto::*;
+
+mod hubdiscovery;
+pub use crate::hubdiscovery::*;
+
 mod hubserver;
 pub use crate::hubserver::*;
 
@@ -19,8 +31,23 @@ pub use crate::hubrouter::*;
 mod hubmsg;
 pub use crate::hubmsg::*;
 
+mod hubsync;
+pub use crate::hubsync::*;
+
+mod cargotest;
+pub use crate::cargotest::*;
+
+mod dapclient;
+pub use crate::dapclient::*;
+
+mod lspclient;
+pub use crate::lspclient::*;
+
 mod httpserver;
 pub use crate::httpserver::*;
 
+mod websocket;
+pub use crate::websocket::*;
+
 mod wasmstrip;
 pub use crate::wasmstrip::*;
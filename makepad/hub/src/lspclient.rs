@@ -0,0 +1,333 @@
+use makepad_microserde::*;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Child, Stdio};
+use crate::dapclient::*;
+use crate::hubmsg::*;
+
+// a client for the language server protocol, the json-rpc that rust-analyzer speaks. the base
+// protocol frames messages with a Content-Length header like the debug adapter protocol does,
+// requests get a response with their id, notifications get nothing back
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged, SerRon, DeRon)]
+pub enum HubLanguageServer {
+    Off,
+    // started by the builder for each workspace, it talks over its stdin and stdout
+    Command {command: String, args: Vec<String>},
+    // a server that is already listening, mostly for tests
+    Tcp(String)
+}
+
+impl HubLanguageServer {
+    pub fn default_server() -> HubLanguageServer {
+        HubLanguageServer::Command {command: "rust-analyzer".to_string(), args: Vec::new()}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct HubCompletion {
+    pub label: String,
+    pub detail: String,
+    pub insert_text: String
+}
+
+impl HubCompletion {
+    pub fn from_lsp(result: &JsonValue) -> Vec<HubCompletion> {
+        // either a list of items, or a CompletionList with them
+        let items = result.as_array().or_else( | | result.get("items").and_then( | i | i.as_array()));
+        let mut completions = Vec::new();
+        for item in items.unwrap_or(&[]) {
+            let label = item["label"].as_str().unwrap_or("").to_string();
+            let insert_text = item.query("textEdit.newText").and_then( | t | t.as_str())
+                .or(item["insertText"].as_str())
+                .unwrap_or(&label).to_string();
+            completions.push(HubCompletion {
+                detail: item["detail"].as_str().unwrap_or("").to_string(),
+                insert_text: insert_text,
+                label: label,
+            });
+        }
+        completions
+    }
+}
+
+// the text of a hover result, that is markup content, a marked string or a list of those
+pub fn lsp_hover_text(result: &JsonValue) -> String {
+    fn marked_string(value: &JsonValue) -> String {
+        if let Some(s) = value.as_str() {
+            return s.to_string()
+        }
+        value["value"].as_str().unwrap_or("").to_string()
+    }
+    let contents = &result["contents"];
+    if let Some(list) = contents.as_array() {
+        return list.iter().map(marked_string).collect::<Vec<_>>().join("\n\n")
+    }
+    marked_string(contents)
+}
+
+// what a request was for, and the uid of the UI request it answers
+#[derive(Clone, Debug)]
+pub enum LspPending {
+    Initialize,
+    Hover {uid: HubUid},
+    Definition {uid: HubUid},
+    References {uid: HubUid},
+    Completion {uid: HubUid},
+}
+
+// a document that is open on the server, as it is after our last change
+pub struct LspDocument {
+    pub uri: String,
+    pub version: u64,
+    pub text: String
+}
+
+pub struct LspConnection {
+    writer: Box<dyn Write + Send>,
+    child: Option<Child>,
+    // kept to shut the connection down, the writer and the reader are clones of it
+    stream: Option<TcpStream>,
+    id: u64,
+    pending: Vec<(u64, LspPending)>,
+    pub documents: Vec<LspDocument>,
+    // columns go to the server in utf-16 code units, unless it picked utf-32 in its initialize result
+    pub utf16: bool,
+}
+
+impl LspConnection {
+    // the reader goes to the thread that reads the messages
+    pub fn connect(server: &HubLanguageServer, current_dir: &str) -> std::io::Result<(LspConnection, Box<dyn Read + Send>)> {
+        match server {
+            HubLanguageServer::Off => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "language server is off")),
+            HubLanguageServer::Command {command, args} => {
+                let mut child = Command::new(command)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .current_dir(current_dir)
+                    .spawn() ?;
+                let writer = child.stdin.take().unwrap();
+                let reader = child.stdout.take().unwrap();
+                Ok((LspConnection::new(Box::new(writer), Some(child), None), Box::new(reader)))
+            },
+            HubLanguageServer::Tcp(address) => {
+                let stream = TcpStream::connect(address) ?;
+                let reader = stream.try_clone() ?;
+                let writer = stream.try_clone() ?;
+                Ok((LspConnection::new(Box::new(writer), None, Some(stream)), Box::new(reader)))
+            }
+        }
+    }
+
+    fn new(writer: Box<dyn Write + Send>, child: Option<Child>, stream: Option<TcpStream>) -> LspConnection {
+        LspConnection {
+            writer: writer,
+            child: child,
+            stream: stream,
+            id: 0,
+            pending: Vec::new(),
+            documents: Vec::new(),
+            utf16: true
+        }
+    }
+
+    pub fn request(&mut self, method: &str, params: JsonValue, pending: LspPending) -> std::io::Result<u64> {
+        self.id += 1;
+        self.pending.push((self.id, pending));
+        write_dap_message(&mut self.writer, &json_object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", self.id.into()),
+            ("method", method.into()),
+            ("params", params)
+        ])) ?;
+        Ok(self.id)
+    }
+
+    pub fn notify(&mut self, method: &str, params: JsonValue) -> std::io::Result<()> {
+        write_dap_message(&mut self.writer, &json_object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params)
+        ]))
+    }
+
+    // answers a request of the server, we support none of them so they all get an empty result
+    pub fn respond_empty(&mut self, request: &JsonValue) -> std::io::Result<()> {
+        let result = if request["method"].as_str() == Some("workspace/configuration") {
+            let items = request.query("params.items").and_then( | i | i.as_array()).map( | i | i.len()).unwrap_or(0);
+            JsonValue::Array(vec![JsonValue::Null; items])
+        }
+        else {
+            JsonValue::Null
+        };
+        write_dap_message(&mut self.writer, &json_object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", request["id"].clone()),
+            ("result", result)
+        ]))
+    }
+
+    pub fn take_pending(&mut self, id: u64) -> Option<LspPending> {
+        let index = self.pending.iter().position( | (i, _) | *i == id) ?;
+        Some(self.pending.remove(index).1)
+    }
+
+    // the version the next change of a document gets, none when it isn't open
+    pub fn next_version(&mut self, uri: &str) -> Option<u64> {
+        let document = self.documents.iter_mut().find( | d | d.uri == uri) ?;
+        document.version += 1;
+        Some(document.version)
+    }
+    
+    // the positionEncoding the server picked from the ones we offered, utf-16 when it picked none
+    pub fn set_position_encoding(&mut self, initialize_result: &JsonValue) {
+        self.utf16 = initialize_result.query("capabilities.positionEncoding").and_then( | e | e.as_str()) != Some("utf-32");
+    }
+    
+    // a line of a document, of the text we sent when it is open and from disk when it isn't
+    fn line_text(&self, uri: &str, line: usize) -> Option<String> {
+        if let Some(document) = self.documents.iter().find( | d | d.uri == uri) {
+            return document.text.lines().nth(line.max(1) - 1).map( | l | l.to_string())
+        }
+        let text = std::fs::read_to_string(uri_to_path(uri) ?).ok() ?;
+        text.lines().nth(line.max(1) - 1).map( | l | l.to_string())
+    }
+    
+    // our line and column in characters as a position the server understands
+    pub fn position(&self, uri: &str, line: usize, col: usize) -> JsonValue {
+        if self.utf16 {
+            if let Some(text) = self.line_text(uri, line) {
+                return lsp_position(line, col_to_utf16(&text, col))
+            }
+        }
+        lsp_position(line, col)
+    }
+    
+    // and the start of a range of the server back in our line and column
+    pub fn range_start(&self, uri: &str, range: &JsonValue) -> (usize, usize) {
+        let (line, col) = lsp_range_start(range);
+        if self.utf16 {
+            if let Some(text) = self.line_text(uri, line) {
+                return (line, utf16_to_col(&text, col))
+            }
+        }
+        (line, col)
+    }
+    
+    // the change as we send it, the text of the document is kept up to date so the
+    // positions of the next one are counted in the text the server has
+    pub fn change(&mut self, uri: &str, edit: &LocEdit) -> JsonValue {
+        let change = json_object(vec![
+            ("range", json_object(vec![
+                ("start", self.position(uri, edit.start_line, edit.start_col)),
+                ("end", self.position(uri, edit.end_line, edit.end_col))
+            ])),
+            ("text", edit.replacement.as_str().into())
+        ]);
+        if let Some(document) = self.documents.iter_mut().find( | d | d.uri == uri) {
+            let start = text_offset(&document.text, edit.start_line, edit.start_col);
+            let end = text_offset(&document.text, edit.end_line, edit.end_col).max(start);
+            document.text.replace_range(start..end, &edit.replacement);
+        }
+        change
+    }
+
+    pub fn kill(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+            self.child = None;
+        }
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+pub fn file_uri(abs_path: &str) -> String {
+    let path = abs_path.replace("\\", "/");
+    let mut uri = if path.starts_with('/') {"file://".to_string()} else {"file:///".to_string()};
+    for c in path.chars() {
+        match c {
+            ' ' => uri.push_str("%20"),
+            '%' => uri.push_str("%25"),
+            '#' => uri.push_str("%23"),
+            '?' => uri.push_str("%3F"),
+            _ => uri.push(c)
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://") ?;
+    let mut bytes = Vec::new();
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next() ?, iter.next() ?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok() ?, 16).ok() ?);
+        }
+        else {
+            bytes.push(b);
+        }
+    }
+    let path = String::from_utf8(bytes).ok() ?;
+    // file:///C:/x on windows
+    if path.len() > 2 && path.as_bytes()[2] == b':' {
+        return Some(path[1..].to_string())
+    }
+    Some(path)
+}
+
+// lsp counts lines and columns from 0, we count them from 1
+pub fn lsp_position(line: usize, col: usize) -> JsonValue {
+    json_object(vec![
+        ("line", (line.max(1) as u64 - 1).into()),
+        ("character", (col.max(1) as u64 - 1).into())
+    ])
+}
+
+pub fn lsp_range_start(range: &JsonValue) -> (usize, usize) {
+    (
+        range.query("start.line").and_then( | l | l.as_u64()).unwrap_or(0) as usize + 1,
+        range.query("start.character").and_then( | c | c.as_u64()).unwrap_or(0) as usize + 1
+    )
+}
+
+// a column in characters to one in utf-16 code units, both counted from 1
+pub fn col_to_utf16(line: &str, col: usize) -> usize {
+    let chars = col.max(1) - 1;
+    let units: usize = line.chars().take(chars).map( | c | c.len_utf16()).sum();
+    units + chars.saturating_sub(line.chars().count()) + 1
+}
+
+pub fn utf16_to_col(line: &str, col: usize) -> usize {
+    let mut units = col.max(1) - 1;
+    let mut chars = 0;
+    for c in line.chars() {
+        if units < c.len_utf16() {
+            // inside a surrogate pair is on its character
+            return chars + 1
+        }
+        units -= c.len_utf16();
+        chars += 1;
+    }
+    chars + units + 1
+}
+
+// the byte offset of a line and column in characters, clamped to the end of the line
+fn text_offset(text: &str, line: usize, col: usize) -> usize {
+    let mut offset = 0;
+    for _ in 1..line.max(1) {
+        match text[offset..].find('\n') {
+            Some(index) => offset += index + 1,
+            None => return text.len()
+        }
+    }
+    let rest = &text[offset..];
+    let line_len = rest.find('\n').unwrap_or(rest.len());
+    offset + rest[..line_len].char_indices().nth(col.max(1) - 1).map( | (index, _) | index).unwrap_or(line_len)
+}
@@ -14,7 +14,7 @@ impl Process {
     
     pub fn start(cmd: &str, args: &[&str], current_dir: &str, env: &[(&str, &str)]) -> Result<Process, std::io::Error> {
         fn create_process(cmd: &str, args: &[&str], current_dir: &str, env: &[(&str, &str)]) -> Result<Child, std::io::Error> {
-            let mut cbuild = if cmd.find("/").is_some() {
+            let mut cbuild = if cmd.find("/").is_some() && !std::path::Path::new(cmd).is_absolute() {
                 Command::new(&format!("{}/{}", current_dir, cmd))
             }
             else {
@@ -52,7 +52,8 @@ impl Process {
                     }
                     storage.resize(offset + n_bytes_read, 0u8);
                     let mut start = 0;
-                    for (index, ch) in storage.iter().enumerate() {
+                    // everything before offset is part of an unfinished line, so only scan the new bytes
+                    for (index, ch) in storage.iter().enumerate().skip(offset) {
                         if *ch == '\n' as u8 {
                             // emit a line
                             if let Ok(line) = str::from_utf8(&storage[start..(index + 1)]) {
@@ -79,7 +80,8 @@ impl Process {
                     }
                     storage.resize(offset + n_bytes_read, 0u8);
                     let mut start = 0;
-                    for (index, ch) in storage.iter().enumerate() {
+                    // everything before offset is part of an unfinished line, so only scan the new bytes
+                    for (index, ch) in storage.iter().enumerate().skip(offset) {
                         if *ch == '\n' as u8 {
                             // emit a line
                             if let Ok(line) = str::from_utf8(&storage[start..(index + 1)]) {
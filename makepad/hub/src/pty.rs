@@ -0,0 +1,128 @@
+use std::io::{Read, Write};
+use std::process::{Command, Child};
+
+// a process on a pseudo terminal, so shells and anything with a line editor or colors
+// behave like they do in a terminal. it is what process_forkpty in examples/old wanted to be,
+// but it opens the pty itself and lets std::process do the fork
+pub struct Pty {
+    master: std::fs::File,
+    child: Child,
+}
+
+impl Pty {
+    // the reader goes to the thread that reads the output
+    #[cfg(unix)]
+    pub fn spawn(cmd: &str, args: &[String], current_dir: &str, env: &[(&str, &str)], cols: usize, rows: usize) -> std::io::Result<(Pty, Box<dyn Read + Send>)> {
+        use std::os::unix::io::{AsRawFd, FromRawFd};
+        use std::os::unix::process::CommandExt;
+        use std::process::Stdio;
+
+        let master = unsafe {libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY)};
+        if master < 0 {
+            return Err(std::io::Error::last_os_error())
+        }
+        let master = unsafe {std::fs::File::from_raw_fd(master)};
+        // posix_openpt can't take O_CLOEXEC everywhere, without it every other child we spawn
+        // keeps the master open and the shell never sees its terminal hang up
+        if unsafe {libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC)} < 0 {
+            return Err(std::io::Error::last_os_error())
+        }
+        let slave_path = Self::slave_path(&master) ?;
+        let slave = std::fs::OpenOptions::new().read(true).write(true).open(&slave_path) ?;
+        Self::set_size(&master, cols, rows) ?;
+
+        let child = {
+            let mut command = Command::new(cmd);
+            command.args(args)
+                .stdin(Stdio::from(slave.try_clone() ?))
+                .stdout(Stdio::from(slave.try_clone() ?))
+                .stderr(Stdio::from(slave))
+                .current_dir(current_dir);
+            for (key, value) in env {
+                command.env(key, value);
+            }
+            unsafe {
+                // a session of its own with the pty as its terminal, so ctrl-c and job control work
+                command.pre_exec( || {
+                    if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                        return Err(std::io::Error::last_os_error())
+                    }
+                    Ok(())
+                });
+            }
+            // the command holds on to the slave, dropping it here gives us eof when the child exits
+            command.spawn() ?
+        };
+        let reader = master.try_clone() ?;
+        Ok((Pty {master: master, child: child}, Box::new(reader)))
+    }
+
+    #[cfg(not(unix))]
+    pub fn spawn(_cmd: &str, _args: &[String], _current_dir: &str, _env: &[(&str, &str)], _cols: usize, _rows: usize) -> std::io::Result<(Pty, Box<dyn Read + Send>)> {
+        Err(std::io::Error::new(std::io::ErrorKind::Other, "terminals need a unix builder"))
+    }
+
+    #[cfg(unix)]
+    fn slave_path(master: &std::fs::File) -> std::io::Result<String> {
+        use std::os::unix::io::AsRawFd;
+        let fd = master.as_raw_fd();
+        unsafe {
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(std::io::Error::last_os_error())
+            }
+            #[cfg(target_os = "linux")] {
+                let mut name = [0 as libc::c_char; 128];
+                if libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) != 0 {
+                    return Err(std::io::Error::last_os_error())
+                }
+                Ok(std::ffi::CStr::from_ptr(name.as_ptr()).to_string_lossy().to_string())
+            }
+            #[cfg(not(target_os = "linux"))] {
+                let name = libc::ptsname(fd);
+                if name.is_null() {
+                    return Err(std::io::Error::last_os_error())
+                }
+                Ok(std::ffi::CStr::from_ptr(name).to_string_lossy().to_string())
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn set_size(master: &std::fs::File, cols: usize, rows: usize) -> std::io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        let size = libc::winsize {
+            ws_row: rows.max(1) as u16,
+            ws_col: cols.max(1) as u16,
+            ws_xpixel: 0,
+            ws_ypixel: 0
+        };
+        if unsafe {libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ as _, &size)} != 0 {
+            return Err(std::io::Error::last_os_error())
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn set_size(_master: &std::fs::File, _cols: usize, _rows: usize) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.master.write_all(data) ?;
+        self.master.flush()
+    }
+
+    // the program gets a SIGWINCH and asks for the new size
+    pub fn resize(&mut self, cols: usize, rows: usize) -> std::io::Result<()> {
+        Self::set_size(&self.master, cols, rows)
+    }
+
+    pub fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    pub fn wait(&mut self) -> Option<i32> {
+        self.child.wait().ok().and_then( | status | status.code())
+    }
+}
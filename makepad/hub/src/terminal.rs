@@ -0,0 +1,604 @@
+// the screen of a terminal and the parser for what programs write to it, the vt100 and xterm
+// escape sequences shells and tools like vim, htop or cargo use. it lives in the hub next to the
+// pty, the terminal panel of the app draws it
+
+use makepad_microserde::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub enum TerminalColor {
+    Default,
+    // the 16 ansi colors, the 6x6x6 cube and the grays
+    Indexed(u8),
+    Rgb(u8, u8, u8)
+}
+
+impl TerminalColor {
+    pub fn rgb(index: u8) -> (u8, u8, u8) {
+        const ANSI: [(u8, u8, u8); 16] = [
+            (0, 0, 0), (205, 49, 49), (13, 188, 121), (229, 229, 16),
+            (36, 114, 200), (188, 63, 188), (17, 168, 205), (229, 229, 229),
+            (102, 102, 102), (241, 76, 76), (35, 209, 139), (245, 245, 67),
+            (59, 142, 234), (214, 112, 214), (41, 184, 219), (255, 255, 255),
+        ];
+        match index {
+            0..=15 => ANSI[index as usize],
+            16..=231 => {
+                let i = index - 16;
+                let level = | v: u8 | if v == 0 {0} else {55 + v * 40};
+                (level(i / 36), level((i / 6) % 6), level(i % 6))
+            },
+            _ => {
+                let gray = 8 + (index - 232) * 10;
+                (gray, gray, gray)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, SerBin, DeBin, SerBinTagged, DeBinTagged)]
+pub struct TerminalStyle {
+    pub fg: TerminalColor,
+    pub bg: TerminalColor,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl TerminalStyle {
+    // the parameters of an SGR sequence, ESC [ ... m, the one that sets colors and attributes
+    pub fn apply_sgr(&mut self, params: &[usize]) {
+        if params.len() == 0 {
+            *self = TerminalStyle::default();
+            return
+        }
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = TerminalStyle::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                p @ 30..=37 => self.fg = TerminalColor::Indexed((p - 30) as u8),
+                39 => self.fg = TerminalColor::Default,
+                p @ 40..=47 => self.bg = TerminalColor::Indexed((p - 40) as u8),
+                49 => self.bg = TerminalColor::Default,
+                p @ 90..=97 => self.fg = TerminalColor::Indexed((p - 90 + 8) as u8),
+                p @ 100..=107 => self.bg = TerminalColor::Indexed((p - 100 + 8) as u8),
+                p @ 38 | p @ 48 => {
+                    // 5;n for the 256 colors, 2;r;g;b for true color
+                    let color = match params.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            params.get(i).map( | n | TerminalColor::Indexed(*n as u8))
+                        },
+                        Some(2) => {
+                            i += 4;
+                            if i < params.len() {
+                                Some(TerminalColor::Rgb(params[i - 2] as u8, params[i - 1] as u8, params[i] as u8))
+                            } else {None}
+                        },
+                        _ => None
+                    };
+                    if let Some(color) = color {
+                        if p == 38 {self.fg = color} else {self.bg = color}
+                    }
+                },
+                _ => ()
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Default for TerminalStyle {
+    fn default() -> TerminalStyle {
+        TerminalStyle {
+            fg: TerminalColor::Default,
+            bg: TerminalColor::Default,
+            bold: false,
+            italic: false,
+            underline: false,
+            inverse: false
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerminalCell {
+    pub ch: char,
+    pub style: TerminalStyle
+}
+
+impl TerminalCell {
+    // erasing keeps the background color that is set, like xterm does
+    fn blank(style: &TerminalStyle) -> TerminalCell {
+        TerminalCell {ch: ' ', style: TerminalStyle {bg: style.bg, ..TerminalStyle::default()}}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParseState {
+    Ground,
+    Escape,
+    Charset,
+    Csi,
+    Osc,
+    OscEscape
+}
+
+#[derive(Clone)]
+pub struct TerminalScreen {
+    pub cols: usize,
+    pub rows: usize,
+    // the rows on screen, and what scrolled off the top of it
+    pub lines: Vec<Vec<TerminalCell>>,
+    pub scrollback: Vec<Vec<TerminalCell>>,
+    pub max_scrollback: usize,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    pub cursor_visible: bool,
+    pub title: String,
+    pub style: TerminalStyle,
+    // the cursor sits on the last column until the next character wraps it
+    wrap_pending: bool,
+    auto_wrap: bool,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    saved_cursor: (usize, usize, TerminalStyle),
+    // the main screen while the alternate one of full screen programs is up
+    main_screen: Option<(Vec<Vec<TerminalCell>>, usize, usize)>,
+    state: ParseState,
+    params: Vec<usize>,
+    param: Option<usize>,
+    private: Option<char>,
+    osc: String,
+    utf8: Vec<u8>,
+    // what the terminal answers, like the cursor position for a status report
+    responses: Vec<u8>,
+}
+
+impl TerminalScreen {
+    pub fn new(cols: usize, rows: usize) -> TerminalScreen {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        TerminalScreen {
+            cols: cols,
+            rows: rows,
+            lines: vec![vec![TerminalCell::blank(&TerminalStyle::default()); cols]; rows],
+            scrollback: Vec::new(),
+            max_scrollback: 10000,
+            cursor_row: 0,
+            cursor_col: 0,
+            cursor_visible: true,
+            title: String::new(),
+            style: TerminalStyle::default(),
+            wrap_pending: false,
+            auto_wrap: true,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            saved_cursor: (0, 0, TerminalStyle::default()),
+            main_screen: None,
+            state: ParseState::Ground,
+            params: Vec::new(),
+            param: None,
+            private: None,
+            osc: String::new(),
+            utf8: Vec::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    // scrollback and screen together, the way the panel scrolls through them
+    pub fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.lines.len()
+    }
+
+    pub fn line(&self, index: usize) -> &[TerminalCell] {
+        if index < self.scrollback.len() {
+            &self.scrollback[index]
+        }
+        else {
+            &self.lines[index - self.scrollback.len()]
+        }
+    }
+
+    pub fn line_text(line: &[TerminalCell]) -> String {
+        let text: String = line.iter().map( | cell | cell.ch).collect();
+        text.trim_end().to_string()
+    }
+
+    pub fn is_alternate_screen(&self) -> bool {
+        self.main_screen.is_some()
+    }
+
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::replace(&mut self.responses, Vec::new())
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        for byte in data {
+            if self.utf8.len() == 0 && *byte < 0x80 {
+                self.process(*byte as char);
+                continue
+            }
+            // a character can be split over two reads
+            self.utf8.push(*byte);
+            let expect = match self.utf8[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1
+            };
+            if self.utf8.len() >= expect {
+                let ch = std::str::from_utf8(&self.utf8).ok().and_then( | s | s.chars().next()).unwrap_or('\u{fffd}');
+                self.utf8.truncate(0);
+                self.process(ch);
+            }
+        }
+    }
+
+    fn process(&mut self, ch: char) {
+        match self.state {
+            ParseState::Ground => match ch {
+                '\x1b' => self.state = ParseState::Escape,
+                '\x08' => {
+                    self.cursor_col = self.cursor_col.saturating_sub(1);
+                    self.wrap_pending = false;
+                },
+                '\t' => {
+                    self.cursor_col = ((self.cursor_col / 8 + 1) * 8).min(self.cols - 1);
+                    self.wrap_pending = false;
+                },
+                '\n' | '\x0b' | '\x0c' => self.line_feed(),
+                '\r' => {
+                    self.cursor_col = 0;
+                    self.wrap_pending = false;
+                },
+                _ if ch < ' ' || ch == '\x7f' => (),
+                _ => self.print(ch)
+            },
+            ParseState::Escape => {
+                self.state = ParseState::Ground;
+                match ch {
+                    '[' => {
+                        self.state = ParseState::Csi;
+                        self.params.truncate(0);
+                        self.param = None;
+                        self.private = None;
+                    },
+                    ']' => {
+                        self.state = ParseState::Osc;
+                        self.osc.truncate(0);
+                    },
+                    '(' | ')' | '*' | '+' => self.state = ParseState::Charset,
+                    '7' => self.save_cursor(),
+                    '8' => self.restore_cursor(),
+                    'D' => self.line_feed(),
+                    'E' => {
+                        self.cursor_col = 0;
+                        self.line_feed();
+                    },
+                    'M' => self.reverse_index(),
+                    'c' => {
+                        let (cols, rows) = (self.cols, self.rows);
+                        *self = TerminalScreen {scrollback: std::mem::replace(&mut self.scrollback, Vec::new()), ..TerminalScreen::new(cols, rows)};
+                    },
+                    _ => ()
+                }
+            },
+            ParseState::Charset => self.state = ParseState::Ground,
+            ParseState::Csi => match ch {
+                '0'..='9' => {
+                    self.param = Some(self.param.unwrap_or(0).saturating_mul(10).saturating_add(ch as usize - '0' as usize));
+                },
+                ';' | ':' => {
+                    self.params.push(self.param.take().unwrap_or(0));
+                },
+                '?' | '>' | '=' | '<' => self.private = Some(ch),
+                '\x1b' => self.state = ParseState::Escape,
+                '\x40'..='\x7e' => {
+                    if let Some(param) = self.param.take() {
+                        self.params.push(param);
+                    }
+                    self.state = ParseState::Ground;
+                    self.csi_dispatch(ch);
+                },
+                _ => ()
+            },
+            ParseState::Osc => match ch {
+                '\x07' => self.osc_dispatch(),
+                '\x1b' => self.state = ParseState::OscEscape,
+                _ => if self.osc.len() < 4096 {
+                    self.osc.push(ch)
+                }
+            },
+            ParseState::OscEscape => self.osc_dispatch()
+        }
+    }
+
+    fn osc_dispatch(&mut self) {
+        self.state = ParseState::Ground;
+        // 0 and 2 set the title, the rest we don't do
+        if let Some(title) = self.osc.strip_prefix("0;").or(self.osc.strip_prefix("2;")) {
+            self.title = title.to_string();
+        }
+    }
+
+    // a parameter, where 0 or nothing means the default
+    fn param(&self, index: usize, default: usize) -> usize {
+        match self.params.get(index) {
+            Some(0) | None => default,
+            Some(value) => *value
+        }
+    }
+
+    fn csi_dispatch(&mut self, ch: char) {
+        if self.private == Some('?') {
+            let on = ch == 'h';
+            if ch == 'h' || ch == 'l' {
+                for mode in self.params.clone() {
+                    match mode {
+                        7 => self.auto_wrap = on,
+                        25 => self.cursor_visible = on,
+                        47 | 1047 | 1049 => self.set_alternate_screen(on, mode == 1049),
+                        _ => ()
+                    }
+                }
+            }
+            return
+        }
+        if self.private.is_some() {
+            return
+        }
+        self.wrap_pending = false;
+        let n = self.param(0, 1);
+        match ch {
+            'A' => self.cursor_row = self.cursor_row.saturating_sub(n),
+            'B' | 'e' => self.cursor_row = self.cursor_row.saturating_add(n).min(self.rows - 1),
+            'C' | 'a' => self.cursor_col = self.cursor_col.saturating_add(n).min(self.cols - 1),
+            'D' => self.cursor_col = self.cursor_col.saturating_sub(n),
+            'E' => {
+                self.cursor_row = self.cursor_row.saturating_add(n).min(self.rows - 1);
+                self.cursor_col = 0;
+            },
+            'F' => {
+                self.cursor_row = self.cursor_row.saturating_sub(n);
+                self.cursor_col = 0;
+            },
+            'G' | '`' => self.cursor_col = (n - 1).min(self.cols - 1),
+            'd' => self.cursor_row = (n - 1).min(self.rows - 1),
+            'H' | 'f' => {
+                self.cursor_row = (n - 1).min(self.rows - 1);
+                self.cursor_col = (self.param(1, 1) - 1).min(self.cols - 1);
+            },
+            'J' => match self.params.get(0).cloned().unwrap_or(0) {
+                0 => {
+                    self.erase_line_range(self.cursor_row, self.cursor_col, self.cols);
+                    for row in self.cursor_row + 1..self.rows {
+                        self.erase_line_range(row, 0, self.cols);
+                    }
+                },
+                1 => {
+                    for row in 0..self.cursor_row {
+                        self.erase_line_range(row, 0, self.cols);
+                    }
+                    self.erase_line_range(self.cursor_row, 0, self.cursor_col + 1);
+                },
+                2 => for row in 0..self.rows {
+                    self.erase_line_range(row, 0, self.cols);
+                },
+                3 => self.scrollback.truncate(0),
+                _ => ()
+            },
+            'K' => match self.params.get(0).cloned().unwrap_or(0) {
+                0 => self.erase_line_range(self.cursor_row, self.cursor_col, self.cols),
+                1 => self.erase_line_range(self.cursor_row, 0, self.cursor_col + 1),
+                2 => self.erase_line_range(self.cursor_row, 0, self.cols),
+                _ => ()
+            },
+            'X' => self.erase_line_range(self.cursor_row, self.cursor_col, self.cursor_col.saturating_add(n)),
+            '@' => {
+                let blank = TerminalCell::blank(&self.style);
+                let line = &mut self.lines[self.cursor_row];
+                for _ in 0..n.min(self.cols - self.cursor_col) {
+                    line.insert(self.cursor_col, blank);
+                    line.pop();
+                }
+            },
+            'P' => {
+                let blank = TerminalCell::blank(&self.style);
+                let line = &mut self.lines[self.cursor_row];
+                for _ in 0..n.min(self.cols - self.cursor_col) {
+                    line.remove(self.cursor_col);
+                    line.push(blank);
+                }
+            },
+            'L' => if self.cursor_row >= self.scroll_top && self.cursor_row <= self.scroll_bottom {
+                self.scroll_region_down(self.cursor_row, n);
+            },
+            'M' => if self.cursor_row >= self.scroll_top && self.cursor_row <= self.scroll_bottom {
+                self.scroll_region_up(self.cursor_row, n);
+            },
+            'S' => self.scroll_region_up(self.scroll_top, n),
+            'T' => self.scroll_region_down(self.scroll_top, n),
+            'm' => self.style.apply_sgr(&self.params),
+            'r' => {
+                let top = self.param(0, 1) - 1;
+                let bottom = self.param(1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.cursor_row = 0;
+                    self.cursor_col = 0;
+                }
+            },
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            'n' => match self.params.get(0) {
+                Some(5) => self.responses.extend_from_slice(b"\x1b[0n"),
+                Some(6) => {
+                    let report = format!("\x1b[{};{}R", self.cursor_row + 1, self.cursor_col + 1);
+                    self.responses.extend_from_slice(report.as_bytes());
+                },
+                _ => ()
+            },
+            'c' => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+            _ => ()
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.wrap_pending {
+            self.wrap_pending = false;
+            self.cursor_col = 0;
+            self.line_feed();
+        }
+        self.lines[self.cursor_row][self.cursor_col] = TerminalCell {ch: ch, style: self.style};
+        if self.cursor_col + 1 < self.cols {
+            self.cursor_col += 1;
+        }
+        else if self.auto_wrap {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_bottom {
+            self.scroll_region_up(self.scroll_top, 1);
+        }
+        else if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_top {
+            self.scroll_region_down(self.scroll_top, 1);
+        }
+        else if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+        }
+    }
+
+    // moves the lines from top to the bottom of the scroll region up, lines that leave the
+    // top of the main screen go to the scrollback
+    fn scroll_region_up(&mut self, top: usize, n: usize) {
+        let blank = TerminalCell::blank(&self.style);
+        for _ in 0..n.min(self.scroll_bottom + 1 - top) {
+            let line = self.lines.remove(top);
+            self.lines.insert(self.scroll_bottom, vec![blank; self.cols]);
+            if top == 0 && self.main_screen.is_none() {
+                self.scrollback.push(line);
+            }
+        }
+        if self.scrollback.len() > self.max_scrollback {
+            let excess = self.scrollback.len() - self.max_scrollback;
+            self.scrollback.drain(0..excess);
+        }
+    }
+
+    fn scroll_region_down(&mut self, top: usize, n: usize) {
+        let blank = TerminalCell::blank(&self.style);
+        for _ in 0..n.min(self.scroll_bottom + 1 - top) {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(top, vec![blank; self.cols]);
+        }
+    }
+
+    fn erase_line_range(&mut self, row: usize, start: usize, end: usize) {
+        let blank = TerminalCell::blank(&self.style);
+        let line = &mut self.lines[row];
+        for cell in &mut line[start.min(self.cols)..end.min(self.cols)] {
+            *cell = blank;
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = (self.cursor_row, self.cursor_col, self.style);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (row, col, style) = self.saved_cursor;
+        self.cursor_row = row.min(self.rows - 1);
+        self.cursor_col = col.min(self.cols - 1);
+        self.style = style;
+        self.wrap_pending = false;
+    }
+
+    fn set_alternate_screen(&mut self, on: bool, save_cursor: bool) {
+        if on && self.main_screen.is_none() {
+            if save_cursor {
+                self.save_cursor();
+            }
+            let blank = vec![vec![TerminalCell::blank(&TerminalStyle::default()); self.cols]; self.rows];
+            let main = std::mem::replace(&mut self.lines, blank);
+            self.main_screen = Some((main, self.cursor_row, self.cursor_col));
+        }
+        else if !on {
+            if let Some((main, row, col)) = self.main_screen.take() {
+                self.lines = main;
+                self.cursor_row = row;
+                self.cursor_col = col;
+                if save_cursor {
+                    self.restore_cursor();
+                }
+            }
+        }
+    }
+
+    // lines that no longer fit go to the scrollback as long as the cursor is below them
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        if cols == self.cols && rows == self.rows {
+            return
+        }
+        let fit = | lines: &mut Vec<Vec<TerminalCell>>, cursor_row: &mut usize, scrollback: Option<&mut Vec<Vec<TerminalCell>>> | {
+            let mut scrollback = scrollback;
+            while lines.len() > rows {
+                if *cursor_row > 0 {
+                    let line = lines.remove(0);
+                    if let Some(scrollback) = &mut scrollback {
+                        scrollback.push(line);
+                    }
+                    *cursor_row -= 1;
+                }
+                else {
+                    lines.pop();
+                }
+            }
+            while lines.len() < rows {
+                lines.push(Vec::new());
+            }
+            for line in lines.iter_mut() {
+                line.resize(cols, TerminalCell::blank(&TerminalStyle::default()));
+            }
+        };
+        if let Some((main, row, col)) = &mut self.main_screen {
+            fit(main, row, Some(&mut self.scrollback));
+            *col = (*col).min(cols - 1);
+            fit(&mut self.lines, &mut self.cursor_row, None);
+        }
+        else {
+            fit(&mut self.lines, &mut self.cursor_row, Some(&mut self.scrollback));
+        }
+        for line in self.scrollback.iter_mut() {
+            line.resize(cols, TerminalCell::blank(&TerminalStyle::default()));
+        }
+        self.cols = cols;
+        self.rows = rows;
+        self.cursor_row = self.cursor_row.min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols - 1);
+        self.wrap_pending = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+    }
+}
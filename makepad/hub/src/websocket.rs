@@ -0,0 +1,228 @@
+use std::io::prelude::*;
+use std::io;
+
+// websockets as in RFC 6455, the http server upgrades a connection to one for the live reload channel.
+// a client masks the frames it sends and a server doesn't, a message can come in fragments with
+// control frames in between
+
+// the key the accept key is made of, from the rfc
+pub const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// the largest message we read, a live reload channel only sends small json
+pub const WEBSOCKET_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebSocketMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<u16>)
+}
+
+impl WebSocketMessage {
+    // one frame with all of the message, a client gives a mask
+    pub fn to_frame(&self, mask: Option<[u8; 4]>) -> Vec<u8> {
+        let close_data;
+        let (opcode, data): (u8, &[u8]) = match self {
+            WebSocketMessage::Text(text) => (1, text.as_bytes()),
+            WebSocketMessage::Binary(data) => (2, data),
+            WebSocketMessage::Close(code) => {
+                close_data = code.map( | code | code.to_be_bytes().to_vec()).unwrap_or(Vec::new());
+                (8, &close_data)
+            },
+            WebSocketMessage::Ping(data) => (9, data),
+            WebSocketMessage::Pong(data) => (10, data),
+        };
+        let mut frame = vec![0x80 | opcode];
+        let mask_bit = if mask.is_some() {0x80} else {0};
+        if data.len() < 126 {
+            frame.push(mask_bit | data.len() as u8);
+        }
+        else if data.len() <= 0xffff {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        }
+        else {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(data.len() as u64).to_be_bytes());
+        }
+        if let Some(mask) = mask {
+            frame.extend_from_slice(&mask);
+            frame.extend(data.iter().enumerate().map( | (i, byte) | byte ^ mask[i & 3]));
+        }
+        else {
+            frame.extend_from_slice(data);
+        }
+        frame
+    }
+}
+
+// reads the messages of one side of a connection, the server reads masked frames and the client unmasked ones
+pub struct WebSocketReader<R> {
+    pub reader: R,
+    pub masked: bool,
+    // the opcode and the data of a message that came in fragments so far
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: Read> WebSocketReader<R> {
+    pub fn new(reader: R, masked: bool) -> WebSocketReader<R> {
+        WebSocketReader {
+            reader: reader,
+            masked: masked,
+            partial: None
+        }
+    }
+
+    // the next message, a control frame in between the fragments of a message comes first.
+    // a frame that breaks the protocol is an InvalidData error, the connection is closed with 1002 then
+    pub fn read_message(&mut self) -> io::Result<WebSocketMessage> {
+        loop {
+            let mut head = [0u8; 2];
+            self.reader.read_exact(&mut head) ?;
+            let fin = head[0] & 0x80 != 0;
+            let opcode = head[0] & 0x0f;
+            if head[0] & 0x70 != 0 {
+                return Err(protocol_error("reserved bits are set"))
+            }
+            if (head[1] & 0x80 != 0) != self.masked {
+                return Err(protocol_error(if self.masked {"frame is not masked"} else {"frame is masked"}))
+            }
+            let len = match head[1] & 0x7f {
+                126 => {
+                    let mut len = [0u8; 2];
+                    self.reader.read_exact(&mut len) ?;
+                    u16::from_be_bytes(len) as u64
+                },
+                127 => {
+                    let mut len = [0u8; 8];
+                    self.reader.read_exact(&mut len) ?;
+                    u64::from_be_bytes(len)
+                },
+                len => len as u64
+            };
+            let is_control = opcode & 0x8 != 0;
+            if is_control && (!fin || len > 125) {
+                return Err(protocol_error("control frame is fragmented or too long"))
+            }
+            let partial_len = self.partial.as_ref().map( | (_, data) | data.len()).unwrap_or(0);
+            if len as usize > WEBSOCKET_MAX_MESSAGE_SIZE - partial_len {
+                return Err(protocol_error("message is too long"))
+            }
+            let mut mask = [0u8; 4];
+            if self.masked {
+                self.reader.read_exact(&mut mask) ?;
+            }
+            let mut data = vec![0u8; len as usize];
+            self.reader.read_exact(&mut data) ?;
+            if self.masked {
+                for (i, byte) in data.iter_mut().enumerate() {
+                    *byte ^= mask[i & 3];
+                }
+            }
+            let (opcode, data) = match opcode {
+                0 => { // continuation
+                    let (opcode, mut partial) = if let Some(partial) = self.partial.take() {partial} else {
+                        return Err(protocol_error("continuation without a message"))
+                    };
+                    partial.extend_from_slice(&data);
+                    if !fin {
+                        self.partial = Some((opcode, partial));
+                        continue
+                    }
+                    (opcode, partial)
+                },
+                1 | 2 => {
+                    if self.partial.is_some() {
+                        return Err(protocol_error("message in between fragments"))
+                    }
+                    if !fin {
+                        self.partial = Some((opcode, data));
+                        continue
+                    }
+                    (opcode, data)
+                },
+                8 | 9 | 10 => (opcode, data),
+                _ => return Err(protocol_error("unknown opcode"))
+            };
+            return match opcode {
+                1 => match String::from_utf8(data) {
+                    Ok(text) => Ok(WebSocketMessage::Text(text)),
+                    Err(_) => Err(protocol_error("text is not utf8"))
+                },
+                2 => Ok(WebSocketMessage::Binary(data)),
+                8 => Ok(WebSocketMessage::Close(if data.len() >= 2 {Some(u16::from_be_bytes([data[0], data[1]]))} else {None})),
+                9 => Ok(WebSocketMessage::Ping(data)),
+                _ => Ok(WebSocketMessage::Pong(data)),
+            }
+        }
+    }
+}
+
+fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// the Sec-WebSocket-Accept of a Sec-WebSocket-Key
+pub fn websocket_accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{}{}", key.trim(), WEBSOCKET_GUID).as_bytes()))
+}
+
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([chunk[i * 4], chunk[i * 4 + 1], chunk[i * 4 + 2], chunk[i * 4 + 3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+        for i in 0..80 {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6)
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(w[i]);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+    let mut digest = [0u8; 20];
+    for i in 0..5 {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&h[i].to_be_bytes());
+    }
+    digest
+}
+
+pub fn base64_encode(data: &[u8]) -> String {
+    const CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(CHARS[(n >> 18) as usize & 63] as char);
+        out.push(CHARS[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {CHARS[(n >> 6) as usize & 63] as char} else {'='});
+        out.push(if chunk.len() > 2 {CHARS[n as usize & 63] as char} else {'='});
+    }
+    out
+}
@@ -0,0 +1,115 @@
+// the program runs are shell scripts
+#![cfg(unix)]
+
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::os::unix::fs::PermissionsExt;
+
+// runs a script that writes the stderr of a panicking program, and returns what was logged
+fn run_script(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, dir: &std::path::Path, name: &str, stderr: &str) -> Vec<HubLogItem> {
+    let script = dir.join(name);
+    std::fs::write(&script, format!("#!/bin/sh\ncat >&2 <<'EOF'\n{}EOF\n", stderr)).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::ProgramRun {uid: uid, path: format!("fixture/./{}", name), args: Vec::new()}
+    });
+    let mut items = Vec::new();
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            match htc.msg {
+                HubMsg::LogItem {uid: item_uid, item} if item_uid == uid => items.push(item),
+                HubMsg::ProgramEnd {uid: end_uid} if end_uid == uid => return items,
+                _ => ()
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(20)).expect("no message from builder");
+    }
+}
+
+fn frame(function: &str, file: &str, path: Option<&str>, line: usize, col: usize) -> HubBacktraceFrame {
+    HubBacktraceFrame {
+        function: function.to_string(),
+        file: file.to_string(),
+        path: path.map( | path | path.to_string()),
+        line: line,
+        col: col,
+        user_code: path.is_some()
+    }
+}
+
+#[test]
+fn panics_become_backtraces() {
+    let dir = std::env::temp_dir().join(format!("makepad_backtrace_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), format!("{}", dir.display()));
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {
+            http_server: HttpServerConfig::Offline,
+            workspaces: workspaces,
+            build_profiles: HubBuildProfile::defaults(),
+            debug_adapter: HubDebugAdapter::default_adapter(),
+            language_server: HubLanguageServer::Off
+        }}
+    });
+
+    // a full backtrace, with std, a dependency, a function inlined into our own and one without a file
+    let items = run_script(&mut hub_ui, &rx, &dir, "full.sh", &format!(concat!(
+        "thread 'main' panicked at src/main.rs:2:5:\n",
+        "boom\n",
+        "stack backtrace:\n",
+        "   0:     0x55d0c6b2d3f5 - std::panicking::begin_panic::h0123456789abcdef\n",
+        "                               at /rustc/90b35a6/library/std/src/panicking.rs:652:5\n",
+        "   1:     0x55d0c6b2d3f6 - dep::helper::hfedcba9876543210\n",
+        "                               at /home/u/.cargo/registry/src/dep-1.0/src/lib.rs:10:9\n",
+        "   2:     0x55d0c6b2d3f7 - app::inner\n",
+        "                               at ./src/lib.rs:7:13\n",
+        "      app::main\n",
+        "                               at {}/src/main.rs:2:5\n",
+        "   3:     0x55d0c6b2d3f8 - __libc_start_main\n",
+        "note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.\n",
+    ), dir.display()));
+    assert_eq!(items.len(), 1);
+    if let HubLogItem::Backtrace {panic, frames} = &items[0] {
+        assert_eq!(panic.body, "thread 'main' panicked at src/main.rs:2:5: boom");
+        assert_eq!((panic.path.as_ref(), panic.line, panic.col), ("main/fixture/src/lib.rs", 7, 13));
+        assert_eq!(frames, &vec![
+            frame("std::panicking::begin_panic", "/rustc/90b35a6/library/std/src/panicking.rs", None, 652, 5),
+            frame("dep::helper", "/home/u/.cargo/registry/src/dep-1.0/src/lib.rs", None, 10, 9),
+            frame("app::inner", "./src/lib.rs", Some("main/fixture/src/lib.rs"), 7, 13),
+            frame("app::main", &format!("{}/src/main.rs", dir.display()), Some("main/fixture/src/main.rs"), 2, 5),
+            frame("__libc_start_main", "", None, 0, 0),
+        ]);
+        assert_eq!(panic.rendered.as_ref().unwrap(), &format!(
+            "{}\nmain/fixture/src/lib.rs:7 - app::inner\nmain/fixture/src/main.rs:2 - app::main\n",
+            panic.body
+        ));
+    }
+    else {
+        panic!("not a backtrace {:?}", items[0]);
+    }
+
+    // without a stack it is still at the place it panicked, also in the format before rust 1.73
+    let items = run_script(&mut hub_ui, &rx, &dir, "short.sh", concat!(
+        "thread 'main' panicked at 'oops, bad', src/main.rs:4:1\n",
+        "note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n",
+    ));
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].level(), HubLogLevel::Error);
+    let panic = items[0].get_loc_message().unwrap();
+    assert_eq!((panic.path.as_ref(), panic.line, panic.col), ("main/fixture/src/main.rs", 4, 1));
+    assert_eq!(items[0].get_body(), "thread 'main' panicked at 'oops, bad', src/main.rs:4:1");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,54 @@
+use makepad_hub::*;
+use makepad_microserde::*;
+
+#[test]
+fn default_profiles() {
+    let profiles = HubBuildProfile::defaults();
+    let for_package = | package: &str | -> Vec<HubBuildProfile> {
+        // the first profile with a name wins, like the builder picks them
+        let mut out: Vec<HubBuildProfile> = Vec::new();
+        for profile in &profiles {
+            if profile.matches_package(package) && !out.iter().any( | p | p.name == profile.name) {
+                out.push(profile.clone());
+            }
+        }
+        out
+    };
+    let wasm = for_package("makepad_wasm");
+    assert_eq!(wasm.iter().map( | p | p.name.as_str()).collect::<Vec<_>>(), vec!["check", "debug", "release", "small", "clippy"]);
+    assert_eq!(wasm[3].cargo_args("makepad_wasm"), vec!["build", "-p", "makepad_wasm", "--release", "--target=wasm32-unknown-unknown"]);
+    assert_eq!(wasm[3].post_build, vec![HubPostBuild::WasmStripDebug]);
+    
+    let native = for_package("makepad");
+    assert_eq!(native[2].cargo_args("makepad"), vec!["build", "-p", "makepad", "--release"]);
+    assert_eq!(native[3].env.get("RUSTFLAGS").map( | s | s.as_str()), Some("-C opt-level=z -C panic=abort -C codegen-units=1"));
+    assert!(native[3].post_build.is_empty());
+}
+
+#[test]
+fn profile_from_settings() {
+    let profile: HubBuildProfile = DeRon::deserialize_ron(r#"(
+        name: "arm",
+        packages: ["makepad*"],
+        command: "build",
+        features: ["mtl", "xr"],
+        target: "aarch64-unknown-linux-gnu",
+    )"#).unwrap();
+    assert!(profile.matches_package("makepad_hub"));
+    assert!(!profile.matches_package("hello_world"));
+    assert_eq!(profile.cargo_args("makepad"), vec!["build", "-p", "makepad", "--features", "mtl,xr", "--target=aarch64-unknown-linux-gnu"]);
+    
+    // settings written before there were profiles get the defaults
+    let mut config = HubBuilderConfig {
+        http_server: HttpServerConfig::Offline,
+        workspaces: Default::default(),
+        build_profiles: vec![profile],
+        debug_adapter: HubDebugAdapter::default_adapter(),
+        language_server: HubLanguageServer::default_server()
+    };
+    let ron = config.serialize_ron();
+    assert_eq!(HubBuilderConfig::deserialize_ron(&ron).unwrap(), config);
+    config.build_profiles = HubBuildProfile::defaults();
+    let old = ron.replace(&ron[ron.find("build_profiles").unwrap()..ron.rfind(')').unwrap()], "");
+    assert_eq!(HubBuilderConfig::deserialize_ron(&old).unwrap(), config);
+}
@@ -0,0 +1,179 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn targets_from_cargo_metadata() {
+    let json = r#"{"packages":[{"name":"other","targets":[]},{"name":"makepad-hub","version":"0.1.0","targets":[
+        {"kind":["lib"],"crate_types":["lib"],"name":"makepad_hub","doctest":true,"test":true},
+        {"kind":["bin"],"crate_types":["bin"],"name":"tool","doctest":false,"test":true},
+        {"kind":["test"],"crate_types":["bin"],"name":"transport","doctest":false,"test":true},
+        {"kind":["example"],"crate_types":["bin"],"name":"demo","doctest":false,"test":false},
+        {"kind":["bench"],"crate_types":["bin"],"name":"speed","doctest":false,"test":false}
+    ]}],"workspace_root":"/home/me/makepad"}"#;
+    assert_eq!(HubTestTarget::from_cargo_metadata(json, "makepad-hub"), Some(vec![
+        HubTestTarget::Lib,
+        HubTestTarget::Bin("tool".to_string()),
+        HubTestTarget::Test("transport".to_string()),
+        HubTestTarget::Doc,
+    ]));
+    assert_eq!(HubTestTarget::from_cargo_metadata(json, "missing"), None);
+    assert!(HubTestTarget::is_cargo_line("     Running tests/transport.rs (target/debug/deps/transport-1234)"));
+    assert!(HubTestTarget::is_cargo_line("   Doc-tests makepad_hub"));
+    assert!(!HubTestTarget::is_cargo_line("   Compiling makepad-hub v0.1.0"));
+}
+
+#[test]
+fn libtest_json() {
+    let parser = CargoTestParser::new("main", "makepad", "/home/me/makepad", "makepad-hub", HubTestTarget::Test("sync".to_string()));
+    match parser.parse_line(r#"{ "type": "suite", "event": "started", "test_count": 2 }"#) {
+        Some(CargoTestEvent::Begin {target, count}) => {
+            assert_eq!(target, HubTestTarget::Test("sync".to_string()));
+            assert_eq!(count, 2);
+        },
+        _ => panic!("no begin")
+    }
+    assert!(parser.parse_line(r#"{ "type": "test", "event": "started", "name": "plans" }"#).is_none());
+    match parser.parse_line(r#"{ "type": "test", "name": "plans", "event": "ok", "exec_time": 0.25 }"#) {
+        Some(CargoTestEvent::Result(result)) => {
+            assert_eq!(result.test, HubTest {package: "makepad-hub".to_string(), target: HubTestTarget::Test("sync".to_string()), name: "plans".to_string()});
+            assert_eq!(result.outcome, HubTestOutcome::Passed);
+            assert_eq!(result.exec_time, Some(0.25));
+        },
+        _ => panic!("no result")
+    }
+    let failed = r#"{ "type": "test", "name": "block_deltas", "event": "failed", "stdout": "\nthread 'block_deltas' panicked at makepad/hub/tests/sync.rs:31:5:\nsent 9000 bytes\nnote: run with `RUST_BACKTRACE=1`\n" }"#;
+    match parser.parse_line(failed) {
+        Some(CargoTestEvent::Result(result)) => {
+            assert_eq!(result.outcome, HubTestOutcome::Failed);
+            let loc = result.loc.unwrap();
+            assert_eq!(loc.path, "main/makepad/makepad/hub/tests/sync.rs");
+            assert_eq!((loc.line, loc.col), (31, 5));
+            assert_eq!(loc.body, "sent 9000 bytes");
+        },
+        _ => panic!("no result")
+    }
+    // cargo's own json isn't for us
+    assert!(parser.parse_line(r#"{"reason":"build-finished","success":true}"#).is_none());
+    assert!(matches!(parser.parse_line("tests::plans: test"), Some(CargoTestEvent::Discovered(test)) if test.name == "tests::plans"));
+
+    // the test binaries of cargo test --no-run, but not the libraries they use
+    let artifact = r#"{"reason":"compiler-artifact","package_id":"sync 0.1.0","manifest_path":"/home/me/makepad/hub/Cargo.toml","target":{"name":"sync"},"profile":{"test":true},"filenames":["/home/me/makepad/target/debug/deps/sync-1234"],"executable":"/home/me/makepad/target/debug/deps/sync-1234","fresh":false}"#;
+    assert_eq!(HubTestTarget::test_executable(artifact), Some(("/home/me/makepad/target/debug/deps/sync-1234".to_string(), "/home/me/makepad/hub".to_string())));
+    assert_eq!(HubTestTarget::test_executable(&artifact.replace("\"test\":true", "\"test\":false")), None);
+}
+
+#[test]
+fn doc_test_lines() {
+    let parser = CargoTestParser::new("main", "makepad", "/home/me/makepad", "makepad-hub", HubTestTarget::Doc);
+    assert!(matches!(parser.parse_line("running 1 test"), Some(CargoTestEvent::Begin {count: 1, ..})));
+    match parser.parse_line("test src/lib.rs - documented (line 1) ... FAILED") {
+        Some(CargoTestEvent::Result(result)) => {
+            assert_eq!(result.test.name, "src/lib.rs - documented (line 1)");
+            assert_eq!(result.outcome, HubTestOutcome::Failed);
+        },
+        _ => panic!("no result")
+    }
+    assert!(parser.parse_line("test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out").is_none());
+}
+
+#[test]
+fn old_panic_messages() {
+    let parser = CargoTestParser::new("main", "makepad", "/home/me/makepad", "makepad-hub", HubTestTarget::Lib);
+    let loc = parser.panic_loc("thread 'a' panicked at 'assertion failed: x', /home/me/makepad/src/lib.rs:3:9\n").unwrap();
+    assert_eq!(loc.path, "main/makepad/src/lib.rs");
+    assert_eq!((loc.line, loc.col), (3, 9));
+    assert_eq!(loc.body, "assertion failed: x");
+    // outside the workspace there is nothing to jump to
+    assert!(parser.panic_loc("thread 'a' panicked at /rustc/abc/library/core/src/option.rs:3:9:\n").is_none());
+}
+
+fn recv_until<F>(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, mut f: F) where F: FnMut(HubMsg) -> bool {
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            if f(htc.msg) {
+                return
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(120)).expect("no message from builder");
+    }
+}
+
+#[test]
+fn run_tests_on_builder() {
+    let dir = std::env::temp_dir().join(format!("makepad_cargotest_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("tests")).unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[workspace]\n").unwrap();
+    std::fs::write(dir.join("src/lib.rs"), concat!(
+        "#[test]\nfn passes() {}\n",
+        "#[test]\nfn fails() {\n    assert_eq!(1, 2);\n}\n",
+        "#[test]\n#[ignore]\nfn ignored() {}\n",
+        "/// ```\n/// assert_eq!(fixture::documented(), 1);\n/// ```\npub fn documented() -> u32 {1}\n"
+    )).unwrap();
+    std::fs::write(dir.join("tests/it.rs"), "#[test]\nfn passes() {}\n").unwrap();
+
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    let route_send = HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), format!("{}", dir.display()));
+    route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {http_server: HttpServerConfig::Offline, workspaces: workspaces, build_profiles: HubBuildProfile::defaults(), debug_adapter: HubDebugAdapter::default_adapter(), language_server: HubLanguageServer::Off}}
+    });
+
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::TestListRequest {uid: uid, workspace: "fixture".to_string(), package: "fixture".to_string()}
+    });
+    let mut tests = Vec::new();
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TestListResponse {tests: list, ..} = msg {tests = list; true}else {false});
+    let mut names: Vec<String> = tests.iter().map( | t | format!("{:?} {}", t.target, t.name)).collect();
+    names.sort();
+    assert_eq!(names, vec!["Doc src/lib.rs - documented (line 10)", "Lib fails", "Lib ignored", "Lib passes", "Test(\"it\") passes"]);
+
+    // all of them
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::TestRun {uid: uid, workspace: "fixture".to_string(), package: "fixture".to_string(), tests: Vec::new()}
+    });
+    let mut results = Vec::new();
+    let mut end = None;
+    recv_until(&mut hub_ui, &rx, | msg | match msg {
+        HubMsg::TestResult {result, ..} => {results.push(result); false},
+        HubMsg::TestEnd {passed, failed, ignored, ..} => {end = Some((passed, failed, ignored)); true},
+        _ => false
+    });
+    assert_eq!(end, Some((3, 1, 1)));
+    assert!(results.iter().any( | r | r.test.target == HubTestTarget::Doc && r.outcome == HubTestOutcome::Passed));
+    let failed = results.iter().find( | r | r.outcome == HubTestOutcome::Failed).unwrap();
+    assert_eq!(failed.test.name, "fails");
+    let loc = failed.loc.as_ref().unwrap();
+    assert_eq!(loc.path, "main/fixture/src/lib.rs");
+    assert_eq!(loc.line, 5);
+
+    // and just the one in tests/it.rs, which shares its name with one in the lib
+    let it = tests.iter().find( | t | t.target == HubTestTarget::Test("it".to_string())).unwrap().clone();
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::TestRun {uid: uid, workspace: "fixture".to_string(), package: "fixture".to_string(), tests: vec![it.clone()]}
+    });
+    let mut results = Vec::new();
+    recv_until(&mut hub_ui, &rx, | msg | match msg {
+        HubMsg::TestResult {result, ..} => {results.push(result); false},
+        HubMsg::TestEnd {..} => true,
+        _ => false
+    });
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].test, it);
+    assert_eq!(results[0].outcome, HubTestOutcome::Passed);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,192 @@
+use makepad_hub::*;
+use makepad_microserde::*;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn recv_until<F>(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, mut f: F) where F: FnMut(HubMsg) -> bool {
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            if f(htc.msg) {
+                return
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(20)).expect("no message from builder");
+    }
+}
+
+fn send(stream: &mut TcpStream, seq: &mut u64, mut message: JsonValue) {
+    *seq += 1;
+    message.insert("seq", (*seq).into());
+    write_dap_message(stream, &message).unwrap();
+}
+
+fn event(name: &str, body: JsonValue) -> JsonValue {
+    json_object(vec![("type", "event".into()), ("event", name.into()), ("body", body)])
+}
+
+fn variable(name: &str, value: &str, reference: u64) -> JsonValue {
+    json_object(vec![("name", name.into()), ("value", value.into()), ("type", "i32".into()), ("variablesReference", reference.into())])
+}
+
+// stands in for lldb-dap: a program stopped at a breakpoint in main, that steps once and then runs to the end
+fn mock_adapter(listener: TcpListener, main_rs: String, requests: mpsc::Sender<JsonValue>) {
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    let mut seq = 0;
+    while let Some(request) = read_dap_message(&mut reader) {
+        let command = request["command"].as_str().unwrap().to_string();
+        let args = &request["arguments"];
+        let body = match command.as_ref() {
+            "scopes" => json_object(vec![("scopes", JsonValue::Array(vec![
+                json_object(vec![("name", "Locals".into()), ("variablesReference", (100 + args["frameId"].as_u64().unwrap()).into())])
+            ]))]),
+            "stackTrace" => json_object(vec![("stackFrames", JsonValue::Array(vec![
+                json_object(vec![("id", 1u64.into()), ("name", "fixture::main".into()), ("source", json_object(vec![("path", main_rs.as_str().into())])), ("line", 3u64.into()), ("column", 5u64.into())]),
+                json_object(vec![("id", 2u64.into()), ("name", "std::rt::lang_start".into()), ("source", json_object(vec![("path", "/rustc/abc/library/std/src/rt.rs".into())])), ("line", 1u64.into()), ("column", 1u64.into())]),
+            ]))]),
+            "variables" => json_object(vec![("variables", JsonValue::Array(match args["variablesReference"].as_u64().unwrap() {
+                101 => vec![variable("x", "5", 0), variable("v", "size=2", 200)],
+                102 => vec![variable("argc", "1", 0)],
+                _ => vec![variable("[0]", "1", 0), variable("[1]", "2", 0)],
+            }))]),
+            _ => json_object(vec![])
+        };
+        send(&mut stream, &mut seq, json_object(vec![
+            ("type", "response".into()),
+            ("request_seq", request["seq"].clone()),
+            ("command", command.as_str().into()),
+            ("success", true.into()),
+            ("body", body)
+        ]));
+        match command.as_ref() {
+            "launch" => send(&mut stream, &mut seq, event("initialized", json_object(vec![]))),
+            "configurationDone" => send(&mut stream, &mut seq, event("stopped", json_object(vec![("reason", "breakpoint".into()), ("threadId", 7u64.into())]))),
+            "next" => send(&mut stream, &mut seq, event("stopped", json_object(vec![("reason", "step".into()), ("threadId", 7u64.into())]))),
+            "continue" => {
+                send(&mut stream, &mut seq, event("continued", json_object(vec![("threadId", 7u64.into())])));
+                send(&mut stream, &mut seq, event("output", json_object(vec![("category", "stdout".into()), ("output", "hello\nworld\n".into())])));
+                send(&mut stream, &mut seq, event("output", json_object(vec![("category", "console".into()), ("output", "Process 1 exited\n".into())])));
+                send(&mut stream, &mut seq, event("exited", json_object(vec![("exitCode", 0u64.into())])));
+                send(&mut stream, &mut seq, event("terminated", json_object(vec![])));
+            },
+            _ => ()
+        }
+        let done = command == "disconnect";
+        requests.send(request).unwrap();
+        if done {
+            return
+        }
+    }
+}
+
+#[test]
+fn debug_with_mock_adapter() {
+    let dir = std::env::temp_dir().join(format!("makepad_debugger_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let abs_dir = format!("{}", dir.display());
+    let main_rs = format!("{}/src/main.rs", abs_dir);
+    
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("{}", listener.local_addr().unwrap());
+    let (tx_requests, rx_requests) = mpsc::channel();
+    let mock = {
+        let main_rs = main_rs.clone();
+        std::thread::spawn(move || mock_adapter(listener, main_rs, tx_requests))
+    };
+    
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), abs_dir.clone());
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {
+            http_server: HttpServerConfig::Offline,
+            workspaces: workspaces,
+            build_profiles: HubBuildProfile::defaults(),
+            debug_adapter: HubDebugAdapter::Tcp(address),
+            language_server: HubLanguageServer::Off
+        }}
+    });
+    
+    let to = HubMsgTo::Builder("main".to_string());
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::DebugRun {
+        uid: uid,
+        path: "fixture/target/debug/fixture".to_string(),
+        args: vec!["a".to_string()],
+        breakpoints: vec![HubBreakpoint {path: "fixture/src/main.rs".to_string(), line: 3}]
+    }});
+    
+    // it stops at the breakpoint, with the locals of main
+    let mut stopped = None;
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::DebugStopped {reason, thread_id, frames, variables, ..} = msg {
+        stopped = Some((reason, thread_id, frames, variables));
+        true
+    } else {false});
+    let (reason, thread_id, frames, variables) = stopped.unwrap();
+    assert_eq!((reason.as_ref(), thread_id), ("breakpoint", 7));
+    assert_eq!(frames[0], HubStackFrame {id: 1, name: "fixture::main".to_string(), path: Some("main/fixture/src/main.rs".to_string()), line: 3, col: 5});
+    assert_eq!(frames[1].path, None);
+    assert_eq!(variables.iter().map( | v | (v.name.as_ref(), v.reference)).collect::<Vec<_>>(), vec![("x", 0), ("v", 200)]);
+    
+    // the children of a variable, and the locals of another frame
+    for (frame_id, reference) in &[(1, 200), (2, 0)] {
+        hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::DebugVariablesRequest {uid: uid, frame_id: *frame_id, reference: *reference}});
+        let mut response = None;
+        recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::DebugVariablesResponse {frame_id, reference, variables, ..} = msg {
+            response = Some((frame_id, reference, variables));
+            true
+        } else {false});
+        let (got_frame_id, got_reference, variables) = response.unwrap();
+        assert_eq!((got_frame_id, got_reference), (*frame_id, *reference));
+        let names: Vec<String> = variables.iter().map( | v | v.name.clone()).collect();
+        assert_eq!(names, if *reference == 200 {vec!["[0]", "[1]"]} else {vec!["argc"]});
+    }
+    
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::DebugSetBreakpoints {uid: uid, path: "fixture/src/main.rs".to_string(), lines: vec![3, 4]}});
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::DebugCommand {uid: uid, command: HubDebugCommand::StepOver}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::DebugStopped {reason, ..} = msg {
+        assert_eq!(reason, "step");
+        true
+    } else {false});
+    
+    // and runs to the end, with what it printed in the log
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::DebugCommand {uid: uid, command: HubDebugCommand::Continue}});
+    let mut log = Vec::new();
+    let mut continued = false;
+    recv_until(&mut hub_ui, &rx, | msg | match msg {
+        HubMsg::DebugContinued {..} => {continued = true; false},
+        HubMsg::LogItem {item, ..} => {log.push(item.get_body().clone()); false},
+        HubMsg::ProgramEnd {uid: end_uid} => end_uid == uid,
+        _ => false
+    });
+    assert!(continued);
+    assert_eq!(log, vec!["hello\n", "world\n", "Program exited with code 0"]);
+    
+    mock.join().unwrap();
+    let requests: Vec<JsonValue> = rx_requests.try_iter().collect();
+    let commands: Vec<&str> = requests.iter().map( | r | r["command"].as_str().unwrap()).collect();
+    assert_eq!(commands, vec![
+        "initialize", "launch", "setBreakpoints", "configurationDone", "stackTrace", "scopes", "variables",
+        "variables", "scopes", "variables", "setBreakpoints", "next", "stackTrace", "scopes", "variables", "continue", "disconnect"
+    ]);
+    assert_eq!(requests[1]["arguments"]["program"].as_str(), Some(format!("{}/target/debug/fixture", abs_dir).as_ref()));
+    assert_eq!(requests[1]["arguments"].query("args[0]").and_then( | a | a.as_str()), Some("a"));
+    for (index, lines) in &[(2, vec![3]), (10, vec![3, 4])] {
+        let arguments = &requests[*index]["arguments"];
+        assert_eq!(arguments.query("source.path").and_then( | p | p.as_str()), Some(main_rs.as_ref()));
+        let got: Vec<u64> = arguments.query_all("breakpoints[*].line").iter().map( | l | l.as_u64().unwrap()).collect();
+        assert_eq!(got, lines.iter().map( | l | *l as u64).collect::<Vec<_>>());
+    }
+    assert_eq!(requests[11]["arguments"]["threadId"].as_u64(), Some(7));
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,91 @@
+use makepad_hub::*;
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn key(seed: u64) -> Digest {
+    let mut key = Digest::default();
+    key.buf[0] = seed;
+    key.digest_cycle();
+    key
+}
+
+#[test]
+fn fingerprints() {
+    assert_eq!(key_fingerprint(&key(1)), key_fingerprint(&key(1)));
+    assert_ne!(key_fingerprint(&key(1)), key_fingerprint(&key(2)));
+    assert_eq!(key_fingerprint(&key(1)).len(), 16);
+}
+
+// the builder side runs in a child process, this test executable started again with the
+// announce port in MAKEPAD_TEST_ANNOUNCE_PORT. it announces until it is invited, connects to
+// the hub and prints where it connected to
+fn run_builder(announce_port: u16) {
+    let announcer = HubAnnouncer::new(&key(1), announce_port).unwrap();
+    let mut invite = None;
+    for _ in 0..60 {
+        announcer.announce("windows", vec!["makepad".to_string()], 7243);
+        invite = announcer.wait_for_invite(Duration::from_millis(500));
+        if invite.is_some() {
+            break
+        }
+    }
+    let address = invite.expect("no invite");
+    let builder = HubClient::connect_to_server(key(1), address, HubLog::None).unwrap();
+    builder.tx_write.send(ToHubMsg {to: HubMsgTo::All, msg: HubMsg::ConnectBuilder("windows".to_string())}).unwrap();
+    let msg = builder.rx_read.as_ref().unwrap().recv_timeout(Duration::from_secs(10)).expect("no message from hub").msg;
+    assert!(matches!(msg, HubMsg::ConnectBuilder(ws) if ws == "windows"));
+    println!("builder connected to {}", address);
+}
+
+#[test]
+fn announce_and_invite() {
+    if let Ok(port) = std::env::var("MAKEPAD_TEST_ANNOUNCE_PORT") {
+        return run_builder(port.parse().unwrap())
+    }
+    let hub_router = HubRouter::start_hub_router(HubLog::None);
+    let mut hub_server = HubServer::start_hub_server(key(1), &HubServerConfig::Localhost(0), &hub_router).unwrap();
+    let hub_port = hub_server.listen_address.unwrap().port();
+
+    let (tx_change, rx_change) = mpsc::channel();
+    let mut discovery = HubDiscovery::start_hub_discovery(&key(1), SocketAddr::from(([127, 0, 0, 1], 0)), move || {
+        let _ = tx_change.send(());
+    }).unwrap();
+    let announce_port = discovery.listen_address.port();
+
+    // a builder with our key in another process, and one with another key here
+    let child = Command::new(std::env::current_exe().unwrap())
+        .args(["announce_and_invite", "--exact", "--nocapture", "--test-threads=1"])
+        .env("MAKEPAD_TEST_ANNOUNCE_PORT", format!("{}", announce_port))
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stranger = HubAnnouncer::new(&key(2), announce_port).unwrap();
+    stranger.announce("linux", vec![], 7243);
+    while discovery.builders().len() < 2 {
+        rx_change.recv_timeout(Duration::from_secs(10)).expect("builders not discovered");
+    }
+
+    let builders = discovery.builders();
+    assert_eq!(builders[0].builder, "linux");
+    assert!(!builders[0].same_key);
+    assert_eq!(builders[1].builder, "windows");
+    assert!(builders[1].same_key);
+    assert_eq!(builders[1].workspaces, vec!["makepad".to_string()]);
+    assert_eq!(builders[1].port, 7243);
+
+    // the stranger ignores our invite, the builder with our key gets our hub address
+    assert!(discovery.invite("linux", hub_port));
+    assert!(stranger.wait_for_invite(Duration::from_millis(200)).is_none());
+    assert!(discovery.invite("windows", hub_port));
+    assert!(!discovery.invite("macos", hub_port));
+
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "builder failed {}", stdout);
+    assert!(stdout.contains(&format!("builder connected to 127.0.0.1:{}", hub_port)), "{}", stdout);
+
+    discovery.terminate();
+    hub_server.terminate();
+}
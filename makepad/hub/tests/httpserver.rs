@@ -0,0 +1,217 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+struct Response {
+    status: u32,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find( | (key, _) | key.eq_ignore_ascii_case(name)).map( | (_, value) | value.as_ref())
+    }
+}
+
+// sends a request on a connection and reads one response, a HEAD has no body after the headers
+fn request(reader: &mut BufReader<TcpStream>, head: bool, request: &str) -> Response {
+    reader.get_mut().write_all(request.as_bytes()).unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let status = line.split(' ').nth(1).expect("no status").parse().unwrap();
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        let header = line.trim();
+        if header.len() == 0 {
+            break
+        }
+        let colon = header.find(':').unwrap();
+        headers.push((header[..colon].to_string(), header[colon + 1..].trim().to_string()));
+    }
+    let mut response = Response {status: status, headers: headers, body: Vec::new()};
+    if !head && status != 304 {
+        let len: usize = response.header("Content-Length").unwrap().parse().unwrap();
+        response.body = vec![0; len];
+        reader.read_exact(&mut response.body).unwrap();
+    }
+    response
+}
+
+fn start_server(name: &str) -> (HttpServer, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("makepad_http_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let mut workspaces = HashMap::new();
+    workspaces.insert("site".to_string(), format!("{}", dir.display()));
+    let server = HttpServer::start_http_server(&HttpServerConfig::Localhost(0), Arc::new(Mutex::new(workspaces))).unwrap();
+    (server, dir)
+}
+
+fn connect(server: &HttpServer) -> BufReader<TcpStream> {
+    BufReader::new(TcpStream::connect(server.listen_address.unwrap()).unwrap())
+}
+
+#[test]
+fn request_parsing_and_negotiation() {
+    let raw = "\r\nGET /site/a%20b.html?x=1 HTTP/1.1\r\nHost: localhost\r\naccept-encoding: gzip, br\r\n\r\n";
+    let request = HttpRequest::read(&mut BufReader::new(raw.as_bytes())).unwrap();
+    assert_eq!((request.method.as_ref(), request.url().unwrap().as_ref()), ("GET", "site/a b.html"));
+    assert_eq!(request.header("Accept-Encoding"), Some("gzip, br"));
+    assert!(request.keep_alive());
+    let request = HttpRequest::read(&mut BufReader::new("GET /site/ HTTP/1.0\r\n\r\n".as_bytes())).unwrap();
+    assert_eq!(request.url().unwrap(), "site/index.html");
+    assert!(!request.keep_alive());
+    assert_eq!(HttpRequest::read(&mut BufReader::new("GET /".as_bytes())), None);
+
+    assert_eq!(HttpEncoding::negotiate(Some("gzip, deflate, br")), HttpEncoding::Brotli);
+    assert_eq!(HttpEncoding::negotiate(Some("br;q=0.5, gzip")), HttpEncoding::Gzip);
+    assert_eq!(HttpEncoding::negotiate(Some("*;q=0.2, br;q=0")), HttpEncoding::Gzip);
+    assert_eq!(HttpEncoding::negotiate(Some("deflate, identity")), HttpEncoding::Identity);
+    assert_eq!(HttpEncoding::negotiate(None), HttpEncoding::Identity);
+
+    assert_eq!(HttpRange::parse("bytes=2-5", 10), HttpRange::Partial(2, 5));
+    assert_eq!(HttpRange::parse("bytes=7-", 10), HttpRange::Partial(7, 9));
+    assert_eq!(HttpRange::parse("bytes=-3", 10), HttpRange::Partial(7, 9));
+    assert_eq!(HttpRange::parse("bytes=5-100", 10), HttpRange::Partial(5, 9));
+    assert_eq!(HttpRange::parse("bytes=10-", 10), HttpRange::Unsatisfiable);
+    assert_eq!(HttpRange::parse("bytes=0-1,4-5", 10), HttpRange::Full);
+    assert_eq!(HttpRange::parse("lines=1-2", 10), HttpRange::Full);
+
+    let date = std::time::UNIX_EPOCH + std::time::Duration::from_secs(784111777);
+    assert_eq!(http_date(date), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+    assert_eq!(http_date(std::time::UNIX_EPOCH + std::time::Duration::from_secs(951782400)), "Tue, 29 Feb 2000 00:00:00 GMT");
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+}
+
+#[test]
+fn keep_alive_and_conditional_get() {
+    let (mut server, dir) = start_server("conditional");
+    std::fs::write(dir.join("index.html"), "<html>hello</html>").unwrap();
+    let mut conn = connect(&server);
+
+    let first = request(&mut conn, false, "GET /site/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!((first.status, first.body.as_ref()), (200, "<html>hello</html>".as_bytes()));
+    assert_eq!(first.header("Content-Type"), Some("text/html"));
+    let etag = first.header("ETag").unwrap().to_string();
+    let last_modified = first.header("Last-Modified").unwrap().to_string();
+
+    // the same connection, it stays open for the next requests
+    let cached = request(&mut conn, false, &format!("GET /site/index.html HTTP/1.1\r\nIf-None-Match: \"other\", {}\r\n\r\n", etag));
+    assert_eq!((cached.status, cached.header("ETag")), (304, Some(etag.as_ref())));
+    let cached = request(&mut conn, false, &format!("GET /site/index.html HTTP/1.1\r\nIf-Modified-Since: {}\r\n\r\n", last_modified));
+    assert_eq!(cached.status, 304);
+    let head = request(&mut conn, true, "HEAD /site/index.html HTTP/1.1\r\n\r\n");
+    assert_eq!((head.status, head.header("Content-Length")), (200, Some("18")));
+
+    // the digest changes with the content, not with the time
+    std::fs::write(dir.join("index.html"), "<html>changed</html>").unwrap();
+    let changed = request(&mut conn, false, &format!("GET /site/index.html HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n", etag));
+    assert_eq!((changed.status, changed.body.as_ref()), (200, "<html>changed</html>".as_bytes()));
+    assert_ne!(changed.header("ETag"), Some(etag.as_ref()));
+
+    assert_eq!(request(&mut conn, false, "GET /site/missing.html HTTP/1.1\r\n\r\n").status, 404);
+    std::fs::write(dir.join("key.ron"), "secret").unwrap();
+    assert_eq!(request(&mut conn, false, "GET /site/key.ron HTTP/1.1\r\n\r\n").status, 404);
+    let closed = request(&mut conn, false, "GET /site/index.html HTTP/1.1\r\nConnection: close\r\n\r\n");
+    assert_eq!((closed.status, closed.header("Connection")), (200, Some("close")));
+    let mut rest = Vec::new();
+    conn.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest.len(), 0);
+
+    let mut conn = connect(&server);
+    assert_eq!(request(&mut conn, false, "GET /site/../secret HTTP/1.1\r\n\r\n").status, 400);
+
+    server.terminate();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn request_bodies() {
+    let (mut server, dir) = start_server("bodies");
+    std::fs::write(dir.join("index.html"), "<html>hello</html>").unwrap();
+
+    // a small body is skipped, the request after it on the same connection still works
+    let mut conn = connect(&server);
+    let first = request(&mut conn, false, "GET /site/ HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello");
+    assert_eq!(first.status, 200);
+    assert_eq!(request(&mut conn, false, "GET /site/ HTTP/1.1\r\n\r\n").status, 200);
+
+    // a length we will never read is refused without allocating it, and the server lives on
+    let mut conn = connect(&server);
+    let large = request(&mut conn, false, "GET /site/ HTTP/1.1\r\nContent-Length: 900000000000000\r\n\r\n");
+    assert_eq!((large.status, large.header("Connection")), (413, Some("close")));
+    let mut rest = Vec::new();
+    conn.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest.len(), 0);
+    let mut conn = connect(&server);
+    assert_eq!(request(&mut conn, false, "GET /site/ HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n").status, 413);
+    let mut conn = connect(&server);
+    assert_eq!(request(&mut conn, false, "GET /site/ HTTP/1.1\r\n\r\n").status, 200);
+
+    server.terminate();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn byte_ranges() {
+    let (mut server, dir) = start_server("ranges");
+    let data: Vec<u8> = (0..100u8).collect();
+    std::fs::write(dir.join("sub/data.bin"), &data).unwrap();
+    let mut conn = connect(&server);
+
+    let part = request(&mut conn, false, "GET /site/sub/data.bin HTTP/1.1\r\nRange: bytes=10-19\r\n\r\n");
+    assert_eq!((part.status, part.header("Content-Range")), (206, Some("bytes 10-19/100")));
+    assert_eq!(part.body, &data[10..20]);
+    let etag = part.header("ETag").unwrap().to_string();
+    let tail = request(&mut conn, false, &format!("GET /site/sub/data.bin HTTP/1.1\r\nRange: bytes=-5\r\nIf-Range: {}\r\n\r\n", etag));
+    assert_eq!((tail.status, tail.body.as_ref()), (206, &data[95..]));
+
+    // If-Range of a file that changed since gets all of it
+    let full = request(&mut conn, false, "GET /site/sub/data.bin HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: \"old\"\r\n\r\n");
+    assert_eq!((full.status, full.body.len()), (200, 100));
+    let outside = request(&mut conn, false, "GET /site/sub/data.bin HTTP/1.1\r\nRange: bytes=100-\r\n\r\n");
+    assert_eq!((outside.status, outside.header("Content-Range")), (416, Some("bytes */100")));
+
+    server.terminate();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn compression() {
+    let (mut server, dir) = start_server("compression");
+    let text = "fn main() {println!(\"hello\");}\n".repeat(200);
+    std::fs::write(dir.join("main.js"), &text).unwrap();
+    std::fs::write(dir.join("image.png"), &text).unwrap();
+    let mut conn = connect(&server);
+
+    let br = request(&mut conn, false, "GET /site/main.js HTTP/1.1\r\nAccept-Encoding: gzip, deflate, br\r\n\r\n");
+    assert_eq!((br.status, br.header("Content-Encoding"), br.header("Vary")), (200, Some("br"), Some("Accept-Encoding")));
+    assert!(br.body.len() < text.len() / 10);
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(&br.body[..], 4096).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, text.as_bytes());
+
+    let gzip = request(&mut conn, false, "GET /site/main.js HTTP/1.1\r\nAccept-Encoding: br;q=0.1, gzip\r\n\r\n");
+    assert_eq!(gzip.header("Content-Encoding"), Some("gzip"));
+    assert_eq!(&gzip.body[0..3], &[0x1f, 0x8b, 8]);
+    let trailer = &gzip.body[gzip.body.len() - 8..];
+    assert_eq!(&trailer[0..4], &crc32(text.as_bytes()).to_le_bytes());
+    assert_eq!(&trailer[4..8], &(text.len() as u32).to_le_bytes());
+
+    // the etag is of the file, whatever encoding it is sent in
+    assert_eq!(br.header("ETag"), gzip.header("ETag"));
+    let plain = request(&mut conn, false, "GET /site/main.js HTTP/1.1\r\nAccept-Encoding: identity\r\n\r\n");
+    assert_eq!((plain.header("Content-Encoding"), plain.body.as_ref()), (None, text.as_bytes()));
+    let image = request(&mut conn, false, "GET /site/image.png HTTP/1.1\r\nAccept-Encoding: br\r\n\r\n");
+    assert_eq!((image.header("Content-Encoding"), image.body.len()), (None, text.len()));
+
+    server.terminate();
+    let _ = std::fs::remove_dir_all(&dir);
+}
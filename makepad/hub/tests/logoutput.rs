@@ -0,0 +1,96 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn span(start: usize, end: usize, f: fn(&mut TerminalStyle)) -> HubStyledSpan {
+    let mut style = TerminalStyle::default();
+    f(&mut style);
+    HubStyledSpan {start: start, end: end, style: style}
+}
+
+#[test]
+fn ansi_to_spans() {
+    let text = HubStyledText::from_ansi("\x1b[1m\x1b[31merror\x1b[0m\x1b[1m: unused\x1b[0m `x`\r\n");
+    assert_eq!(text.body, "error: unused `x`\n");
+    assert_eq!(text.spans, vec![
+        span(0, 5, | s | {s.bold = true; s.fg = TerminalColor::Indexed(1)}),
+        span(5, 13, | s | s.bold = true),
+    ]);
+
+    // the colors carry on until they are reset, other sequences and hyperlinks are dropped
+    let text = HubStyledText::from_ansi("\x1b[38;5;208mé\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x07\x1b[2K done");
+    assert_eq!(text.body, "éa.rs done");
+    assert_eq!(text.spans, vec![span(0, 11, | s | s.fg = TerminalColor::Indexed(208))]);
+
+    let plain = HubLogItem::from_output(HubLogLevel::Warning, "plain\n");
+    assert_eq!(plain, HubLogItem::Warning("plain\n".to_string()));
+    let styled = HubLogItem::from_output(HubLogLevel::Error, "\x1b[32mok\x1b[39m\n");
+    assert_eq!((styled.level(), styled.get_body().as_ref()), (HubLogLevel::Error, "ok\n"));
+    assert!(HubLogLevel::Error < HubLogLevel::Warning && HubLogLevel::Warning < HubLogLevel::Message);
+}
+
+#[cfg(unix)]
+#[test]
+fn program_output_is_styled_and_grouped() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("makepad_logoutput_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    let script = dir.join("bin/run.sh");
+    std::fs::write(&script, concat!(
+        "#!/bin/sh\n",
+        "printf '\\033[32mok\\033[0m plain\\n'\n",
+        "printf 'error: \\033[1mbad\\033[0m\\n  note: more\\n  help: try\\nsecond\\n' >&2\n",
+    )).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), format!("{}", dir.display()));
+    let to = HubMsgTo::Builder("main".to_string());
+    hub_ui.route_send.send(ToHubMsg {
+        to: to.clone(),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {
+            http_server: HttpServerConfig::Offline,
+            workspaces: workspaces,
+            build_profiles: HubBuildProfile::defaults(),
+            debug_adapter: HubDebugAdapter::default_adapter(),
+            language_server: HubLanguageServer::Off
+        }}
+    });
+
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::ProgramRun {uid: uid, path: "fixture/bin/run.sh".to_string(), args: Vec::new()}});
+    let mut items = Vec::new();
+    'outer: loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            match htc.msg {
+                HubMsg::LogItem {uid: item_uid, item} if item_uid == uid => items.push(item),
+                HubMsg::ProgramEnd {uid: end_uid} if end_uid == uid => break 'outer,
+                _ => ()
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(20)).expect("no message from builder");
+    }
+
+    // stdout and stderr come in on their own threads, so only the order within each is fixed
+    let messages: Vec<&HubLogItem> = items.iter().filter( | item | item.level() == HubLogLevel::Message).collect();
+    assert_eq!(messages, vec![&HubLogItem::Styled {
+        level: HubLogLevel::Message,
+        text: HubStyledText {body: "ok plain\n".to_string(), spans: vec![span(0, 2, | s | s.fg = TerminalColor::Indexed(2))]}
+    }]);
+    let errors: Vec<&HubLogItem> = items.iter().filter( | item | item.level() == HubLogLevel::Error).collect();
+    assert_eq!(errors, vec![
+        &HubLogItem::Styled {
+            level: HubLogLevel::Error,
+            text: HubStyledText {body: "error: bad\n  note: more\n  help: try\n".to_string(), spans: vec![span(7, 10, | s | s.bold = true)]}
+        },
+        &HubLogItem::Error("second\n".to_string())
+    ]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,287 @@
+use makepad_hub::*;
+use makepad_microserde::*;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn recv_until<F>(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, mut f: F) where F: FnMut(HubMsg) -> bool {
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            if f(htc.msg) {
+                return
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(20)).expect("no message from builder");
+    }
+}
+
+fn send(stream: &mut TcpStream, mut message: JsonValue) {
+    message.insert("jsonrpc", "2.0".into());
+    write_dap_message(stream, &message).unwrap();
+}
+
+fn range(line: u64, character: u64) -> JsonValue {
+    let position = json_object(vec![("line", line.into()), ("character", character.into())]);
+    json_object(vec![("start", position.clone()), ("end", position)])
+}
+
+// stands in for rust-analyzer: it asks for its configuration while initializing, publishes a
+// diagnostic on every change and answers the requests with fixed results. x on the third line
+// is at character 21 in utf-16 and 20 in utf-32, the crab before it takes two utf-16 units
+fn mock_server(listener: TcpListener, main_uri: String, encoding: Option<&str>, messages: mpsc::Sender<JsonValue>) {
+    let x_character = if encoding == Some("utf-32") {20} else {21};
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    while let Some(message) = read_dap_message(&mut reader) {
+        let method = message["method"].as_str().unwrap_or("").to_string();
+        let result = match method.as_ref() {
+            "initialize" => {
+                send(&mut stream, json_object(vec![
+                    ("id", 1000u64.into()),
+                    ("method", "workspace/configuration".into()),
+                    ("params", json_object(vec![("items", JsonValue::Array(vec![json_object(vec![("section", "rust-analyzer".into())])]))]))
+                ]));
+                let capabilities = if let Some(encoding) = encoding {vec![("positionEncoding", encoding.into())]} else {vec![]};
+                Some(json_object(vec![("capabilities", json_object(capabilities))]))
+            },
+            "textDocument/hover" => Some(json_object(vec![
+                ("contents", json_object(vec![("kind", "markdown".into()), ("value", "let x: i32".into())]))
+            ])),
+            "textDocument/definition" => Some(JsonValue::Array(vec![json_object(vec![
+                ("targetUri", main_uri.as_str().into()),
+                ("targetRange", range(1, 0)),
+                ("targetSelectionRange", range(1, 8))
+            ])])),
+            "textDocument/references" => Some(JsonValue::Array(vec![
+                json_object(vec![("uri", main_uri.as_str().into()), ("range", range(1, 8))]),
+                json_object(vec![("uri", "file:///rustc/abc/library/core/src/lib.rs".into()), ("range", range(0, 0))]),
+                json_object(vec![("uri", main_uri.as_str().into()), ("range", range(2, x_character))]),
+            ])),
+            "textDocument/completion" => Some(json_object(vec![("isIncomplete", false.into()), ("items", JsonValue::Array(vec![
+                json_object(vec![("label", "println!(…)".into()), ("detail", "macro_rules! println".into()), ("insertText", "println!".into())]),
+                json_object(vec![("label", "x".into()), ("detail", "i32".into())]),
+            ]))])),
+            "shutdown" => Some(JsonValue::Null),
+            _ => None
+        };
+        if let Some(result) = result {
+            send(&mut stream, json_object(vec![("id", message["id"].clone()), ("result", result)]));
+        }
+        if method == "textDocument/didChange" {
+            send(&mut stream, json_object(vec![
+                ("method", "textDocument/publishDiagnostics".into()),
+                ("params", json_object(vec![
+                    ("uri", main_uri.as_str().into()),
+                    ("diagnostics", JsonValue::Array(vec![
+                        json_object(vec![("range", range(2, 4)), ("severity", 1u64.into()), ("message", "cannot find value `y`".into())]),
+                        json_object(vec![("range", range(1, 8)), ("severity", 2u64.into()), ("message", "unused variable: `x`".into())]),
+                    ]))
+                ]))
+            ]));
+        }
+        let done = method == "textDocument/didClose";
+        messages.send(message).unwrap();
+        if done {
+            return
+        }
+    }
+}
+
+fn start_builder(abs_dir: &str, address: String) -> (HubRouter, HubUI, mpsc::Receiver<()>) {
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), abs_dir.to_string());
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {
+            http_server: HttpServerConfig::Offline,
+            workspaces: workspaces,
+            build_profiles: HubBuildProfile::defaults(),
+            debug_adapter: HubDebugAdapter::default_adapter(),
+            language_server: HubLanguageServer::Tcp(address)
+        }}
+    });
+    (hub_router, hub_ui, rx)
+}
+
+#[test]
+fn lsp_with_mock_server() {
+    lsp_with_encoding(None);
+}
+
+#[test]
+fn lsp_with_utf32_server() {
+    lsp_with_encoding(Some("utf-32"));
+}
+
+fn lsp_with_encoding(encoding: Option<&'static str>) {
+    let x_character = if encoding == Some("utf-32") {20} else {21};
+    let dir = std::env::temp_dir().join(format!("makepad_lsp_{}_{}", encoding.unwrap_or("utf-16"), std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let abs_dir = format!("{}", dir.display());
+    let main_rs = format!("{}/src/main.rs", abs_dir);
+    let text = "fn main() {\n    let x = 5;\n    println!(\"🦀{}\", x);\n}\n";
+    std::fs::write(&main_rs, text).unwrap();
+    let main_uri = file_uri(&main_rs);
+    assert_eq!(uri_to_path(&main_uri), Some(main_rs.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("{}", listener.local_addr().unwrap());
+    let (tx_messages, rx_messages) = mpsc::channel();
+    let mock = {
+        let main_uri = main_uri.clone();
+        std::thread::spawn(move || mock_server(listener, main_uri, encoding, tx_messages))
+    };
+
+    let (_hub_router, mut hub_ui, rx) = start_builder(&abs_dir, address);
+
+    let to = HubMsgTo::Builder("main".to_string());
+    let path = "fixture/src/main.rs".to_string();
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspOpen {uid: uid, path: path.clone(), text: text.to_string()}});
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspChange {uid: uid, path: path.clone(), edits: vec![
+        LocEdit {start_line: 3, start_col: 21, end_line: 3, end_col: 22, replacement: "y".to_string()}
+    ]}});
+
+    // the diagnostics of the change come back as log items of the open
+    let mut diagnostics = None;
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::LspDiagnostics {uid: got_uid, path, items} = msg {
+        diagnostics = Some((got_uid, path, items));
+        true
+    } else {false});
+    let (got_uid, got_path, items) = diagnostics.unwrap();
+    assert_eq!((got_uid, got_path.as_ref()), (uid, "main/fixture/src/main.rs"));
+    match &items[..] {
+        [HubLogItem::LocError(error), HubLogItem::LocWarning(warning)] => {
+            assert_eq!((error.line, error.col, error.body.as_ref()), (3, 5, "cannot find value `y`"));
+            assert_eq!((warning.line, warning.col), (2, 9));
+        },
+        _ => panic!("unexpected diagnostics {:?}", items)
+    }
+
+    let hover_uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspHoverRequest {uid: hover_uid, path: path.clone(), line: 2, col: 9}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::LspHoverResponse {uid, text} = msg {
+        assert_eq!((uid, text.as_ref()), (hover_uid, "let x: i32"));
+        true
+    } else {false});
+
+    // definition and references point into the workspace, with the line they are on
+    for references in &[false, true] {
+        let request_uid = hub_ui.route_send.alloc_uid();
+        hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: if *references {
+            HubMsg::LspReferencesRequest {uid: request_uid, path: path.clone(), line: 3, col: 21}
+        } else {
+            HubMsg::LspDefinitionRequest {uid: request_uid, path: path.clone(), line: 3, col: 21}
+        }});
+        let mut locations = None;
+        recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::LspLocationsResponse {uid, locations: got} = msg {
+            assert_eq!(uid, request_uid);
+            locations = Some(got);
+            true
+        } else {false});
+        let got: Vec<(String, usize, usize, String)> = locations.unwrap().into_iter().map( | l | (l.path, l.line, l.col, l.body)).collect();
+        let mut expect = vec![("main/fixture/src/main.rs".to_string(), 2, 9, "let x = 5;".to_string())];
+        if *references {
+            expect.push(("main/fixture/src/main.rs".to_string(), 3, 21, "println!(\"🦀{}\", x);".to_string()));
+        }
+        assert_eq!(got, expect);
+    }
+
+    let completion_uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspCompletionRequest {uid: completion_uid, path: path.clone(), line: 3, col: 5}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::LspCompletionResponse {uid, items} = msg {
+        assert_eq!(uid, completion_uid);
+        assert_eq!(items, vec![
+            HubCompletion {label: "println!(…)".to_string(), detail: "macro_rules! println".to_string(), insert_text: "println!".to_string()},
+            HubCompletion {label: "x".to_string(), detail: "i32".to_string(), insert_text: "x".to_string()},
+        ]);
+        true
+    } else {false});
+
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspClose {uid: uid, path: path.clone()}});
+
+    mock.join().unwrap();
+    let messages: Vec<JsonValue> = rx_messages.try_iter().collect();
+    let methods: Vec<&str> = messages.iter().map( | m | m["method"].as_str().unwrap_or("response")).collect();
+    assert_eq!(methods, vec![
+        "initialize", "response", "initialized", "textDocument/didOpen", "textDocument/didChange",
+        "textDocument/hover", "textDocument/definition", "textDocument/references", "textDocument/completion", "textDocument/didClose"
+    ]);
+    assert_eq!(messages[0].query("params.rootUri").and_then( | u | u.as_str()), Some(file_uri(&abs_dir).as_ref()));
+    assert_eq!(messages[0].query("params.capabilities.general.positionEncodings"), Some(&JsonValue::Array(vec!["utf-32".into()])));
+    // the configuration request got a null for each item
+    assert_eq!(messages[1]["id"].as_u64(), Some(1000));
+    assert_eq!(messages[1]["result"], JsonValue::Array(vec![JsonValue::Null]));
+    let open = &messages[3]["params"]["textDocument"];
+    assert_eq!((open["languageId"].as_str(), open["version"].as_u64(), open["text"].as_str()), (Some("rust"), Some(1), Some(text)));
+    let change = &messages[4]["params"];
+    assert_eq!(change.query("textDocument.version").and_then( | v | v.as_u64()), Some(2));
+    assert_eq!(change["contentChanges"], JsonValue::Array(vec![json_object(vec![
+        ("range", json_object(vec![
+            ("start", json_object(vec![("line", 2u64.into()), ("character", x_character.into())])),
+            ("end", json_object(vec![("line", 2u64.into()), ("character", (x_character + 1).into())]))
+        ])),
+        ("text", "y".into())
+    ])]));
+    assert_eq!(messages[5].query("params.position.character").and_then( | c | c.as_u64()), Some(8));
+    assert_eq!(messages[6].query("params.position.character").and_then( | c | c.as_u64()), Some(x_character));
+    assert_eq!(messages[7].query("params.context.includeDeclaration"), Some(&JsonValue::Bool(true)));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// a server that goes away while initializing doesn't stop the next open from starting one
+#[test]
+fn lsp_start_retries() {
+    let dir = std::env::temp_dir().join(format!("makepad_lsp_retry_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let abs_dir = format!("{}", dir.display());
+    let main_uri = file_uri(&format!("{}/src/main.rs", abs_dir));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("{}", listener.local_addr().unwrap());
+    let (tx_messages, rx_messages) = mpsc::channel();
+    let mock = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        assert!(read_dap_message(&mut BufReader::new(stream)).is_some());
+        mock_server(listener, main_uri, None, tx_messages)
+    });
+    let (_hub_router, mut hub_ui, rx) = start_builder(&abs_dir, address);
+
+    let to = HubMsgTo::Builder("main".to_string());
+    let path = "fixture/src/main.rs".to_string();
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspOpen {uid: uid, path: path.clone(), text: "fn main() {}".to_string()}});
+    recv_until(&mut hub_ui, &rx, | msg | match msg {
+        HubMsg::LogItem {item: HubLogItem::Error(msg), ..} => msg.contains("cannot start language server"),
+        _ => false
+    });
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspOpen {uid: uid, path: path.clone(), text: "fn main() {}".to_string()}});
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::LspClose {uid: uid, path: path.clone()}});
+
+    let first = rx_messages.recv_timeout(Duration::from_secs(20)).expect("the second open didn't start a server");
+    assert_eq!(first["method"].as_str(), Some("initialize"));
+    mock.join().unwrap();
+    let methods: Vec<String> = std::iter::once(first).chain(rx_messages.try_iter()).map( | m | m["method"].as_str().unwrap_or("response").to_string()).collect();
+    assert_eq!(methods, vec!["initialize", "response", "initialized", "textDocument/didOpen", "textDocument/didClose"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn utf16_columns() {
+    let line = "a🦀é b";
+    assert_eq!((col_to_utf16(line, 1), col_to_utf16(line, 3), col_to_utf16(line, 4), col_to_utf16(line, 8)), (1, 4, 5, 9));
+    assert_eq!((utf16_to_col(line, 1), utf16_to_col(line, 4), utf16_to_col(line, 5), utf16_to_col(line, 9)), (1, 3, 4, 8));
+    // the middle of the surrogate pair is the crab
+    assert_eq!(utf16_to_col(line, 3), 2);
+}
@@ -0,0 +1,77 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn recv_until<F>(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, mut f: F) where F: FnMut(HubMsg) -> bool {
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            if f(htc.msg) {
+                return
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(120)).expect("no message from builder");
+    }
+}
+
+fn build_messages(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, config: &str) -> Vec<LocMessage> {
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::Build {uid: uid, workspace: "fixture".to_string(), package: "fixture".to_string(), config: config.to_string()}
+    });
+    let mut messages = Vec::new();
+    recv_until(hub_ui, rx, | msg | match msg {
+        HubMsg::LogItem {item, ..} => {
+            if let Some(loc) = item.get_loc_message() {
+                messages.push(loc.clone());
+            }
+            false
+        },
+        HubMsg::CargoEnd {..} => true,
+        _ => false
+    });
+    messages
+}
+
+#[test]
+fn fixes_from_check_and_clippy() {
+    let dir = std::env::temp_dir().join(format!("makepad_quickfix_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[workspace]\n").unwrap();
+    std::fs::write(dir.join("src/lib.rs"), concat!(
+        "pub fn one() -> usize {\n    let mut x = 1;\n    x\n}\n",
+        "pub fn empty(v: &Vec<u8>) -> bool {\n    v.len() == 0\n}\n"
+    )).unwrap();
+
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | match htc.msg {
+        HubMsg::Build {uid, workspace, package, config} => ws.build_profile(uid, &workspace, &package, &config),
+        _ => ws.default(htc)
+    });
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), format!("{}", dir.display()));
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("main".to_string()),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {http_server: HttpServerConfig::Offline, workspaces: workspaces, build_profiles: HubBuildProfile::defaults(), debug_adapter: HubDebugAdapter::default_adapter(), language_server: HubLanguageServer::Off}}
+    });
+
+    // rustc wants the mut gone
+    let messages = build_messages(&mut hub_ui, &rx, "check");
+    let unused_mut = messages.iter().find( | m | m.body.contains("does not need to be mutable")).expect("no unused mut warning");
+    assert_eq!(unused_mut.path, "main/fixture/src/lib.rs");
+    assert_eq!(unused_mut.fixes.len(), 1);
+    assert!(unused_mut.fixes[0].label.contains("mut"), "{}", unused_mut.fixes[0].label);
+    assert_eq!(unused_mut.fixes[0].edits, vec![LocEdit {start_line: 2, start_col: 9, end_line: 2, end_col: 13, replacement: "".to_string()}]);
+
+    // and clippy has lints on top, with fixes in the same form
+    let messages = build_messages(&mut hub_ui, &rx, "clippy");
+    let len_zero = messages.iter().find( | m | m.body.contains("is_empty")).expect("no len_zero lint");
+    assert_eq!(len_zero.fixes.len(), 1);
+    assert_eq!(len_zero.fixes[0].edits, vec![LocEdit {start_line: 6, start_col: 5, end_line: 6, end_col: 17, replacement: "v.is_empty()".to_string()}]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,218 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// deterministic bytes that don't repeat in blocks
+fn noise(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (0..len).map( | _ | {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as u8
+    }).collect()
+}
+
+fn file(data: &[u8]) -> SyncFile {
+    SyncFile {digest: file_digest(data), size: data.len() as u64}
+}
+
+#[test]
+fn block_deltas() {
+    let old = noise(1, 100_000);
+    let mut new = old.clone();
+    // an insert shifts everything after it, a change and a shorter tail
+    new.splice(10_000..10_000, b"inserted".iter().cloned());
+    new[50_000] ^= 0xff;
+    new.truncate(90_000);
+
+    let signatures = sync_signatures(&old, SYNC_BLOCK_SIZE);
+    assert_eq!(signatures.len(), 100_000 / SYNC_BLOCK_SIZE as usize);
+    let delta = sync_delta(&signatures, SYNC_BLOCK_SIZE, &new);
+    assert!(delta.data_len() < 3 * SYNC_BLOCK_SIZE as u64 + 8, "sent {} bytes", delta.data_len());
+    assert_eq!(sync_delta_apply(&old, SYNC_BLOCK_SIZE, &delta).unwrap(), new);
+
+    // applied to another version of the file it fails the digest check
+    let mut other = old.clone();
+    other[0] ^= 1;
+    assert!(sync_delta_apply(&other, SYNC_BLOCK_SIZE, &delta).is_none());
+
+    // without signatures it is the whole file
+    let delta = sync_delta(&[], SYNC_BLOCK_SIZE, &new);
+    assert_eq!(delta.data_len(), new.len() as u64);
+    assert_eq!(sync_delta_apply(&[], SYNC_BLOCK_SIZE, &delta).unwrap(), new);
+}
+
+#[test]
+fn plans() {
+    let mut source = SyncDigests::new();
+    source.insert("same.rs".to_string(), file(b"same"));
+    source.insert("changed.rs".to_string(), file(b"changed on source"));
+    source.insert("moved/to.rs".to_string(), file(b"moved"));
+    source.insert("both.rs".to_string(), file(b"source edit"));
+
+    let mut base = SyncDigests::new();
+    base.insert("same.rs".to_string(), file(b"same"));
+    base.insert("changed.rs".to_string(), file(b"changed"));
+    base.insert("from.rs".to_string(), file(b"moved"));
+    base.insert("deleted.rs".to_string(), file(b"deleted"));
+    base.insert("both.rs".to_string(), file(b"both"));
+
+    let mut target = base.clone();
+    target.insert("both.rs".to_string(), file(b"target edit"));
+    target.insert("target_only.rs".to_string(), file(b"new on target"));
+
+    let mut plan = sync_plan(&source, &target, Some(&base));
+    plan.sort_by_key( | a | format!("{:?}", a));
+    assert_eq!(plan, vec![
+        SyncAction::Conflict("both.rs".to_string()),
+        SyncAction::Delete("deleted.rs".to_string()),
+        SyncAction::Rename {from: "from.rs".to_string(), to: "moved/to.rs".to_string()},
+        SyncAction::Write("changed.rs".to_string()),
+    ]);
+
+    // the first sync has nothing to go on, so source wins and nothing is deleted
+    let mut plan = sync_plan(&source, &target, None);
+    plan.sort_by_key( | a | format!("{:?}", a));
+    assert_eq!(plan, vec![
+        SyncAction::Write("both.rs".to_string()),
+        SyncAction::Write("changed.rs".to_string()),
+        SyncAction::Write("moved/to.rs".to_string()),
+    ]);
+}
+
+struct Fixture {
+    dir: std::path::PathBuf
+}
+
+impl Fixture {
+    fn new(name: &str) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("makepad_sync_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("linux/src")).unwrap();
+        std::fs::create_dir_all(dir.join("windows")).unwrap();
+        Fixture {dir: dir}
+    }
+    fn write(&self, path: &str, data: &[u8]) {
+        let path = self.dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.dir.join(path)).ok()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn start_builders(fixture: &Fixture) -> (HubRouter, HubUI, mpsc::Receiver<()>) {
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    for builder in &["linux", "windows"] {
+        let route_send = HubBuilder::run_builder_direct(builder, &mut hub_router, | ws, htc | ws.default(htc));
+        let mut workspaces = HashMap::new();
+        workspaces.insert("makepad".to_string(), format!("{}", fixture.dir.join(builder).display()));
+        route_send.send(ToHubMsg {
+            to: HubMsgTo::Builder(builder.to_string()),
+            msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {http_server: HttpServerConfig::Offline, workspaces: workspaces, build_profiles: HubBuildProfile::defaults(), debug_adapter: HubDebugAdapter::default_adapter(), language_server: HubLanguageServer::Off}}
+        });
+    }
+    (hub_router, hub_ui, rx)
+}
+
+fn run_sync(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, base: Option<SyncDigests>) -> (SyncResult, Vec<HubMsg>, Option<SyncDigests>) {
+    let uid = hub_ui.route_send.alloc_uid();
+    let mut sync = HubSync::new(uid, "linux/makepad", "windows/makepad", base);
+    sync.start(&hub_ui.route_send);
+    let mut reports = Vec::new();
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            sync.handle_hub_msg(&hub_ui.route_send, &htc);
+            match htc.msg {
+                HubMsg::SyncEnd {result, ..} => return (result, reports, sync.synced_digests()),
+                HubMsg::SyncProgress {..} | HubMsg::SyncConflict {..} => reports.push(htc.msg),
+                _ => ()
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(10)).expect("sync stalled");
+    }
+}
+
+#[test]
+fn sync_between_builders() {
+    let fixture = Fixture::new("builders");
+    let big = noise(2, 50_000);
+    fixture.write("linux/src/lib.rs", b"pub fn main(){}");
+    fixture.write("linux/src/big.rs", &big);
+    fixture.write("linux/Cargo.toml", b"[package]");
+    fixture.write("windows/Cargo.toml", b"[package] old");
+
+    let (_hub_router, mut hub_ui, rx) = start_builders(&fixture);
+
+    let (result, reports, base) = run_sync(&mut hub_ui, &rx, None);
+    assert_eq!(result.written, 3);
+    assert_eq!(reports.len(), 3);
+    assert!(matches!(&reports[2], HubMsg::SyncProgress {done: 3, total: 3, ..}));
+    assert_eq!(fixture.read("windows/src/big.rs").unwrap(), big);
+    assert_eq!(fixture.read("windows/Cargo.toml").unwrap(), b"[package]");
+
+    // an edit to a big file only sends what changed, a rename moves the file, and
+    // an edit on the mirror is reported and left alone
+    let mut edited = big.clone();
+    edited[25_000] ^= 0xff;
+    fixture.write("linux/src/big.rs", &edited);
+    std::fs::rename(fixture.dir.join("linux/src/lib.rs"), fixture.dir.join("linux/src/main.rs")).unwrap();
+    fixture.write("windows/Cargo.toml", b"[package] edited on windows");
+    fixture.write("linux/Cargo.toml", b"[package] edited on linux");
+
+    let (result, reports, _) = run_sync(&mut hub_ui, &rx, base);
+    assert_eq!((result.written, result.renamed, result.conflicts, result.failed), (1, 1, 1, 0));
+    // the changed block, and the tail that isn't a whole block
+    assert!(result.bytes_sent < 2 * SYNC_BLOCK_SIZE as u64, "sent {} bytes", result.bytes_sent);
+    assert!(reports.iter().any( | msg | matches!(msg, HubMsg::SyncConflict {path, ..} if path == "windows/makepad/Cargo.toml")));
+    assert_eq!(fixture.read("windows/src/big.rs").unwrap(), edited);
+    assert_eq!(fixture.read("windows/src/main.rs").unwrap(), b"pub fn main(){}");
+    assert!(fixture.read("windows/src/lib.rs").is_none());
+    assert_eq!(fixture.read("windows/Cargo.toml").unwrap(), b"[package] edited on windows");
+}
+
+#[test]
+fn zero_block_size_is_refused() {
+    let fixture = Fixture::new("zero_block");
+    fixture.write("linux/src/lib.rs", b"pub fn main(){}");
+    let (_hub_router, mut hub_ui, rx) = start_builders(&fixture);
+
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("linux".to_string()),
+        msg: HubMsg::SyncSignatureRequest {uid: uid, path: "makepad/src/lib.rs".to_string(), block_size: 0}
+    });
+    hub_ui.route_send.send(ToHubMsg {
+        to: HubMsgTo::Builder("linux".to_string()),
+        msg: HubMsg::SyncDeltaRequest {uid: uid, path: "makepad/src/lib.rs".to_string(), block_size: 0, signatures: vec![SyncBlockSig {weak: 0, strong: 0}]}
+    });
+    let (mut signatures, mut delta, mut errors) = (None, None, 0);
+    while signatures.is_none() || delta.is_none() {
+        for htc in hub_ui.get_messages().unwrap() {
+            match htc.msg {
+                HubMsg::SyncSignatureResponse {uid: msg_uid, signatures: sigs, ..} if msg_uid == uid => signatures = Some(sigs),
+                HubMsg::SyncDeltaResponse {uid: msg_uid, delta: d, ..} if msg_uid == uid => delta = Some(d),
+                HubMsg::LogItem {uid: msg_uid, item: HubLogItem::Error(msg)} if msg_uid == uid => {
+                    assert!(msg.contains("block size 0"), "{}", msg);
+                    errors += 1;
+                },
+                _ => ()
+            }
+        }
+        if signatures.is_none() || delta.is_none() {
+            rx.recv_timeout(Duration::from_secs(10)).expect("builder didn't answer");
+        }
+    }
+    assert_eq!(signatures, Some(None));
+    assert_eq!(delta, Some(None));
+    assert_eq!(errors, 2);
+}
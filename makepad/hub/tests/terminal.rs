@@ -0,0 +1,190 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn screen_text(screen: &TerminalScreen) -> Vec<String> {
+    screen.lines.iter().map( | line | TerminalScreen::line_text(line)).collect()
+}
+
+#[test]
+fn print_wrap_and_scrollback() {
+    let mut screen = TerminalScreen::new(5, 3);
+    screen.feed(b"one\r\ntwo\r\nthree\r\nfour and more");
+    assert_eq!(screen_text(&screen), vec!["four", "and m", "ore"]);
+    let scrollback: Vec<String> = screen.scrollback.iter().map( | line | TerminalScreen::line_text(line)).collect();
+    assert_eq!(scrollback, vec!["one", "two", "three"]);
+    assert_eq!((screen.cursor_row, screen.cursor_col), (2, 3));
+    assert_eq!(screen.total_lines(), 6);
+    assert_eq!(TerminalScreen::line_text(screen.line(2)), "three");
+
+    // a character split over two reads
+    let mut screen = TerminalScreen::new(10, 2);
+    let bytes = "é→".as_bytes();
+    screen.feed(&bytes[0..1]);
+    screen.feed(&bytes[1..3]);
+    screen.feed(&bytes[3..]);
+    assert_eq!(screen_text(&screen)[0], "é→");
+}
+
+#[test]
+fn cursor_movement_and_erase() {
+    let mut screen = TerminalScreen::new(10, 4);
+    screen.feed(b"abcdefghij\x1b[2;3Hxy\x1b[1;5H\x1b[K\x1b[3;1H123\x1b[2D\x1b[1P");
+    assert_eq!(screen_text(&screen), vec!["abcd", "  xy", "13", ""]);
+    screen.feed(b"\x1b[2J\x1b[H\tX\x1b[4;10HZ");
+    assert_eq!(screen_text(&screen), vec!["        X", "", "", "         Z"]);
+    // the cursor stays on the last column until the next character
+    assert_eq!((screen.cursor_row, screen.cursor_col), (3, 9));
+    screen.feed(b"\x1b[6n\x1b[c");
+    assert_eq!(screen.take_responses(), b"\x1b[4;10R\x1b[?1;2c".to_vec());
+    assert_eq!(screen.take_responses(), Vec::<u8>::new());
+    // parameters saturate, moving by them stops at the edge
+    screen.feed(b"\x1b[H\x1b[2JA\x1b[99999999999999999999C\x1b[99999999999999999999BB\x1b[H\x1b[99999999999999999999X");
+    assert_eq!(screen_text(&screen), vec!["", "", "", "         B"]);
+    screen.feed(b"\x1b[99999999999999999999E");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (3, 0));
+}
+
+#[test]
+fn colors_and_title() {
+    let mut screen = TerminalScreen::new(20, 2);
+    screen.feed(b"\x1b]0;cargo build\x07\x1b[1;31mE\x1b[0m \x1b[38;5;208mo\x1b[48;2;10;20;30mr\x1b[7;94mi\x1b[39;49;27m");
+    assert_eq!(screen.title, "cargo build");
+    let line = &screen.lines[0];
+    assert_eq!(line[0].style.fg, TerminalColor::Indexed(1));
+    assert!(line[0].style.bold);
+    assert_eq!(line[1].style, TerminalStyle::default());
+    assert_eq!(line[2].style.fg, TerminalColor::Indexed(208));
+    assert_eq!(line[3].style.bg, TerminalColor::Rgb(10, 20, 30));
+    assert_eq!((line[4].style.fg, line[4].style.inverse), (TerminalColor::Indexed(12), true));
+    assert_eq!(screen.style.fg, TerminalColor::Default);
+    assert_eq!(TerminalColor::rgb(196), (255, 0, 0));
+    assert_eq!(TerminalColor::rgb(232), (8, 8, 8));
+}
+
+#[test]
+fn scroll_region_and_alternate_screen() {
+    let mut screen = TerminalScreen::new(4, 4);
+    screen.feed(b"a\r\nb\r\nc\r\nd\x1b[2;3r\x1b[3;1H\n\nX");
+    // only rows 2 and 3 scroll, and nothing goes to the scrollback
+    assert_eq!(screen_text(&screen), vec!["a", "", "X", "d"]);
+    assert_eq!(screen.scrollback.len(), 0);
+    screen.feed(b"\x1b[r\x1b[1;1H\x1b[2L");
+    assert_eq!(screen_text(&screen), vec!["", "", "a", ""]);
+
+    let mut screen = TerminalScreen::new(6, 2);
+    screen.feed(b"shell$");
+    screen.feed(b"\x1b[?1049h\x1b[Hvim\x1b[?25l");
+    assert!(screen.is_alternate_screen());
+    assert!(!screen.cursor_visible);
+    assert_eq!(screen_text(&screen), vec!["vim", ""]);
+    screen.feed(b"\x1b[?1049l\x1b[?25h");
+    assert_eq!(screen_text(&screen), vec!["shell$", ""]);
+    assert_eq!((screen.cursor_row, screen.cursor_col), (0, 5));
+}
+
+#[test]
+fn resize_keeps_the_cursor_line() {
+    let mut screen = TerminalScreen::new(8, 4);
+    screen.feed(b"1\r\n2\r\n3\r\n$ ls");
+    screen.resize(4, 2);
+    assert_eq!(screen_text(&screen), vec!["3", "$ ls"]);
+    assert_eq!(screen.scrollback.len(), 2);
+    assert_eq!((screen.cursor_row, screen.cursor_col), (1, 3));
+    screen.resize(6, 3);
+    assert_eq!(screen_text(&screen), vec!["3", "$ ls", ""]);
+    assert_eq!(screen.lines[2].len(), 6);
+}
+
+fn recv_until<F>(hub_ui: &mut HubUI, rx: &mpsc::Receiver<()>, mut f: F) where F: FnMut(HubMsg) -> bool {
+    loop {
+        for htc in hub_ui.get_messages().unwrap() {
+            if f(htc.msg) {
+                return
+            }
+        }
+        rx.recv_timeout(Duration::from_secs(20)).expect("no message from builder");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn shell_on_a_pty() {
+    let dir = std::env::temp_dir().join(format!("makepad_terminal_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut hub_router = HubRouter::start_hub_router(HubLog::None);
+    let (tx, rx) = mpsc::channel();
+    let mut hub_ui = HubUI::start_hub_ui_direct(&mut hub_router, move || {let _ = tx.send(());});
+    HubBuilder::run_builder_direct("main", &mut hub_router, | ws, htc | ws.default(htc));
+    let mut workspaces = HashMap::new();
+    workspaces.insert("fixture".to_string(), format!("{}", dir.display()));
+    let to = HubMsgTo::Builder("main".to_string());
+    hub_ui.route_send.send(ToHubMsg {
+        to: to.clone(),
+        msg: HubMsg::BuilderConfig {uid: HubUid::zero(), config: HubBuilderConfig {
+            http_server: HttpServerConfig::Offline,
+            workspaces: workspaces,
+            build_profiles: HubBuildProfile::defaults(),
+            debug_adapter: HubDebugAdapter::default_adapter(),
+            language_server: HubLanguageServer::Off
+        }}
+    });
+
+    let uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalOpen {
+        uid: uid,
+        workspace: "fixture".to_string(),
+        command: vec!["/bin/sh".to_string()],
+        cols: 80,
+        rows: 24
+    }});
+    let mut screen = TerminalScreen::new(80, 24);
+    let mut output = String::new();
+    let feed = | screen: &mut TerminalScreen, output: &mut String, data: &[u8] | {
+        screen.feed(data);
+        output.push_str(&String::from_utf8_lossy(data));
+    };
+
+    // it runs in the workspace on a terminal of the size we asked for, and tells us a new size
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalInput {uid: uid, data: b"pwd; stty size; echo $((6*7))done\n".to_vec()}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TerminalOutput {uid: out_uid, data} = msg {
+        assert_eq!(out_uid, uid);
+        feed(&mut screen, &mut output, &data);
+        output.contains("42done")
+    } else {false});
+    assert!(output.contains(&format!("{}", dir.display())));
+    assert!(output.contains("24 80"));
+
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalResize {uid: uid, cols: 100, rows: 30}});
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalInput {uid: uid, data: b"stty size\n".to_vec()}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TerminalOutput {data, ..} = msg {
+        feed(&mut screen, &mut output, &data);
+        output.contains("30 100")
+    } else {false});
+
+    // it is still there for a UI that comes later, with what it wrote so far
+    let list_uid = hub_ui.route_send.alloc_uid();
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalListRequest {uid: list_uid}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TerminalListResponse {uid, terminals} = msg {
+        assert_eq!(uid, list_uid);
+        assert_eq!(terminals.len(), 1);
+        assert_eq!(terminals[0].command, vec!["/bin/sh".to_string()]);
+        true
+    } else {false});
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalAttach {uid: uid}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TerminalOutput {data, ..} = msg {
+        String::from_utf8_lossy(&data).contains("42done")
+    } else {false});
+
+    hub_ui.route_send.send(ToHubMsg {to: to.clone(), msg: HubMsg::TerminalInput {uid: uid, data: b"exit 3\n".to_vec()}});
+    recv_until(&mut hub_ui, &rx, | msg | if let HubMsg::TerminalEnd {uid: end_uid, code} = msg {
+        assert_eq!((end_uid, code), (uid, Some(3)));
+        true
+    } else {false});
+    assert!(screen_text(&screen).iter().any( | line | line == "42done"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
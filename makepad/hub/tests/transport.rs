@@ -0,0 +1,151 @@
+use makepad_hub::*;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len() / 2).map( | i | u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap()).collect()
+}
+
+fn key(seed: u64) -> Digest {
+    let mut key = Digest::default();
+    key.buf[0] = seed;
+    key.digest_cycle();
+    key
+}
+
+#[test]
+fn aead_rfc8439() {
+    // the AEAD example of RFC 8439 section 2.8.2
+    let mut key = [0u8; 32];
+    for i in 0..32 {
+        key[i] = 0x80 + i as u8;
+    }
+    let nonce = [7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+    let aad = from_hex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let sealed = chacha20_poly1305_seal(&key, &nonce, &aad, plaintext);
+    assert_eq!(sealed, from_hex(concat!(
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b",
+        "1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+        "3ff4def08e4b7a9de576d26586cec64b6116",
+        "1ae10b594f09e26a7e902ecbd0600691"
+    )));
+    assert_eq!(chacha20_poly1305_open(&key, &nonce, &aad, &sealed).unwrap(), &plaintext[..]);
+
+    let mut tampered = sealed.clone();
+    tampered[3] ^= 1;
+    assert!(chacha20_poly1305_open(&key, &nonce, &aad, &tampered).is_none());
+    assert!(chacha20_poly1305_open(&key, &nonce, b"other", &sealed).is_none());
+}
+
+#[test]
+fn sha3() {
+    assert_eq!(sha3_256(&[]).to_vec(), from_hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"));
+    assert_eq!(sha3_256(&[b"a", b"bc"]).to_vec(), from_hex("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"));
+    // longer than one block
+    assert_eq!(sha3_256(&[&[b'a'; 200]]).to_vec(), from_hex("cce34485baf2bf2aca99b94833892a4f52896d3d153f7b840cc4f9fe695f1387"));
+}
+
+#[test]
+fn replayed_blocks_fail() {
+    let mut write = HubCipher::new(&[1; 32]);
+    let mut read = HubCipher::new(&[1; 32]);
+    let first = write.seal(b"", b"first").unwrap();
+    let second = write.seal(b"", b"second").unwrap();
+    assert_eq!(read.open(b"", &first).unwrap(), b"first");
+    // the counter moved on, so the same block can't be read again
+    assert!(read.open(b"", &first).is_err());
+
+    // and blocks can't be read out of order
+    let mut read = HubCipher::new(&[1; 32]);
+    assert!(read.open(b"", &second).is_err());
+}
+
+// runs the server side of a handshake on a thread, and returns what it read after it
+fn serve_one(listener: TcpListener, key: Digest) -> std::thread::JoinHandle<Result<Vec<Vec<u8>>, HubError>> {
+    std::thread::spawn(move || {
+        let (mut tcp_stream, _) = listener.accept().unwrap();
+        let mut session = hub_handshake_server(&mut tcp_stream, &key) ?;
+        let mut blocks = Vec::new();
+        while let Ok(block) = read_block_from_tcp_stream(&mut tcp_stream, &mut session.read) {
+            write_block_to_tcp_stream(&mut tcp_stream, &block, &mut session.write) ?;
+            blocks.push(block);
+        }
+        Ok(blocks)
+    })
+}
+
+#[test]
+fn handshake() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = serve_one(listener, key(1));
+
+    let mut tcp_stream = TcpStream::connect(addr).unwrap();
+    let mut session = hub_handshake_client(&mut tcp_stream, &key(1)).unwrap();
+    for block in &[&b"source file"[..], b"", &[7u8; 100000]] {
+        write_block_to_tcp_stream(&mut tcp_stream, block, &mut session.write).unwrap();
+        assert_eq!(&read_block_from_tcp_stream(&mut tcp_stream, &mut session.read).unwrap()[..], *block);
+    }
+    drop(tcp_stream);
+    assert_eq!(server.join().unwrap().unwrap().len(), 3);
+
+    // a client with another key.ron is refused in the handshake
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = serve_one(listener, key(1));
+    let mut tcp_stream = TcpStream::connect(addr).unwrap();
+    assert!(hub_handshake_client(&mut tcp_stream, &key(2)).is_err());
+    assert!(server.join().unwrap().is_err());
+
+    // as is a peer that doesn't speak the protocol
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = serve_one(listener, key(1));
+    let mut tcp_stream = TcpStream::connect(addr).unwrap();
+    write_exact_bytes_to_tcp_stream(&mut tcp_stream, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n..........").unwrap();
+    assert!(server.join().unwrap().is_err());
+
+    // and one that announces a block bigger than the confirmation is refused without waiting for it
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = serve_one(listener, key(1));
+    let mut tcp_stream = TcpStream::connect(addr).unwrap();
+    let start = std::time::Instant::now();
+    write_exact_bytes_to_tcp_stream(&mut tcp_stream, &[&b"mkpdhub1"[..], &[0u8; 32], &(1u64 << 20).to_le_bytes()].concat()).unwrap();
+    assert!(server.join().unwrap().is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+fn recv(client: &HubClient) -> HubMsg {
+    client.rx_read.as_ref().unwrap().recv_timeout(Duration::from_secs(10)).expect("no message from hub").msg
+}
+
+#[test]
+fn hub_server_and_clients() {
+    let hub_router = HubRouter::start_hub_router(HubLog::None);
+    let mut hub_server = HubServer::start_hub_server(key(1), &HubServerConfig::Localhost(0), &hub_router).unwrap();
+    let addr = hub_server.listen_address.unwrap();
+
+    let builder = HubClient::connect_to_server(key(1), addr, HubLog::None).unwrap();
+    builder.tx_write.send(ToHubMsg {to: HubMsgTo::All, msg: HubMsg::ConnectBuilder("main".to_string())}).unwrap();
+    assert!(matches!(recv(&builder), HubMsg::ConnectBuilder(ws) if ws == "main"));
+
+    let mut ui = HubClient::connect_to_server(key(1), addr, HubLog::None).unwrap();
+    ui.tx_write.send(ToHubMsg {to: HubMsgTo::All, msg: HubMsg::ConnectUI}).unwrap();
+    assert!(matches!(recv(&ui), HubMsg::ConnectUI));
+    assert!(matches!(recv(&builder), HubMsg::ConnectUI));
+
+    let uid = ui.alloc_uid();
+    ui.tx_write.send(ToHubMsg {to: HubMsgTo::Hub, msg: HubMsg::ListBuildersRequest {uid: uid}}).unwrap();
+    match recv(&ui) {
+        HubMsg::ListBuildersResponse {uid: response_uid, builders} => {
+            assert_eq!(response_uid, uid);
+            assert_eq!(builders, vec!["main".to_string()]);
+        }
+        msg => panic!("unexpected {:?}", msg)
+    }
+
+    assert!(HubClient::connect_to_server(key(2), addr, HubLog::None).is_err());
+    hub_server.terminate();
+}
@@ -0,0 +1,139 @@
+use makepad_hub::*;
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+fn start_server(name: &str) -> (HttpServer, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("makepad_websocket_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut workspaces = HashMap::new();
+    workspaces.insert("site".to_string(), format!("{}", dir.display()));
+    let server = HttpServer::start_http_server(&HttpServerConfig::Localhost(0), Arc::new(Mutex::new(workspaces))).unwrap();
+    (server, dir)
+}
+
+// the status line and the headers of a response
+fn read_head(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().len() == 0 {
+            return lines
+        }
+        lines.push(line.trim().to_string());
+    }
+}
+
+fn etag_of(server: &HttpServer, path: &str) -> String {
+    let mut reader = BufReader::new(TcpStream::connect(server.listen_address.unwrap()).unwrap());
+    reader.get_mut().write_all(format!("GET /{} HTTP/1.1\r\nConnection: close\r\n\r\n", path).as_bytes()).unwrap();
+    let head = read_head(&mut reader);
+    head.iter().find( | line | line.starts_with("ETag:")).unwrap()[5..].trim().to_string()
+}
+
+fn send(socket: &mut WebSocketReader<BufReader<TcpStream>>, msg: WebSocketMessage) {
+    socket.reader.get_mut().write_all(&msg.to_frame(Some(MASK))).unwrap();
+}
+
+#[test]
+fn frames_and_accept_key() {
+    // the example of the rfc
+    assert_eq!(websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    let digest: String = sha1(b"abc").iter().map( | b | format!("{:02x}", b)).collect();
+    assert_eq!(digest, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!((base64_encode(b"f"), base64_encode(b"fo"), base64_encode(b"foo")), ("Zg==".to_string(), "Zm8=".to_string(), "Zm9v".to_string()));
+
+    // the lengths of 7 bits, 16 and 64, masked and not
+    for len in [5, 126, 70000].iter() {
+        let text = "x".repeat(*len);
+        let masked = WebSocketMessage::Text(text.clone()).to_frame(Some(MASK));
+        assert_eq!(WebSocketReader::new(&masked[..], true).read_message().unwrap(), WebSocketMessage::Text(text.clone()));
+        let unmasked = WebSocketMessage::Text(text.clone()).to_frame(None);
+        assert_eq!(WebSocketReader::new(&unmasked[..], false).read_message().unwrap(), WebSocketMessage::Text(text.clone()));
+        // a server only takes masked frames
+        assert_eq!(WebSocketReader::new(&unmasked[..], true).read_message().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // a message in fragments, with a ping in between
+    let mut frames = vec![0x01, 0x83];
+    frames.extend_from_slice(&MASK);
+    frames.extend(b"Hel".iter().enumerate().map( | (i, b) | b ^ MASK[i & 3]));
+    frames.extend(WebSocketMessage::Ping(b"hi".to_vec()).to_frame(Some(MASK)));
+    frames.extend_from_slice(&[0x80, 0x82]);
+    frames.extend_from_slice(&MASK);
+    frames.extend(b"lo".iter().enumerate().map( | (i, b) | b ^ MASK[i & 3]));
+    frames.extend(WebSocketMessage::Close(Some(1000)).to_frame(Some(MASK)));
+    let mut reader = WebSocketReader::new(&frames[..], true);
+    assert_eq!(reader.read_message().unwrap(), WebSocketMessage::Ping(b"hi".to_vec()));
+    assert_eq!(reader.read_message().unwrap(), WebSocketMessage::Text("Hello".to_string()));
+    assert_eq!(reader.read_message().unwrap(), WebSocketMessage::Close(Some(1000)));
+
+    // a continuation without a start, and a fragmented ping
+    assert!(WebSocketReader::new(&[0x80u8, 0x00][..], false).read_message().is_err());
+    assert!(WebSocketReader::new(&[0x09u8, 0x00][..], false).read_message().is_err());
+}
+
+#[test]
+fn live_reload_channel() {
+    let (mut server, dir) = start_server("reload");
+    std::fs::write(dir.join("app.wasm"), "first").unwrap();
+    let etag = etag_of(&server, "site/app.wasm");
+
+    let mut reader = BufReader::new(TcpStream::connect(server.listen_address.unwrap()).unwrap());
+    reader.get_mut().write_all(b"GET /$websocket HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n").unwrap();
+    let head = read_head(&mut reader);
+    assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(head.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()));
+    let mut socket = WebSocketReader::new(reader, false);
+
+    send(&mut socket, WebSocketMessage::Ping(b"alive".to_vec()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Pong(b"alive".to_vec()));
+
+    // the page asks if its wasm file is still the one we have
+    send(&mut socket, WebSocketMessage::Text(format!("{{\"type\":\"reload_request\",\"path\":\"site/app.wasm\",\"etag\":{:?}}}", etag)));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"up_to_date\",\"path\":\"site/app.wasm\"}".to_string()));
+    std::fs::write(dir.join("app.wasm"), "second build").unwrap();
+    send(&mut socket, WebSocketMessage::Text(format!("{{\"type\":\"reload_request\",\"path\":\"site/app.wasm\",\"etag\":{:?}}}", etag)));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"file_change\",\"path\":\"site/app.wasm\"}".to_string()));
+    send(&mut socket, WebSocketMessage::Text("{\"type\":\"reload_request\",\"path\":\"site/missing.wasm\"}".to_string()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"not_found\",\"path\":\"site/missing.wasm\"}".to_string()));
+
+    // paths are escaped in the json, and the key of the hub is never there
+    std::fs::write(dir.join("we\"ird.wasm"), "odd").unwrap();
+    send(&mut socket, WebSocketMessage::Text("{\"type\":\"reload_request\",\"path\":\"site/we\\\"ird.wasm\"}".to_string()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"file_change\",\"path\":\"site/we\\\"ird.wasm\"}".to_string()));
+    std::fs::write(dir.join("key.ron"), "secret").unwrap();
+    send(&mut socket, WebSocketMessage::Text("{\"type\":\"reload_request\",\"path\":\"site/key.ron\"}".to_string()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"not_found\",\"path\":\"site/key.ron\"}".to_string()));
+
+    // what the builder tells the server goes to the page
+    server.send_build_start();
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"build_start\"}".to_string()));
+    server.send_file_change("site/app.wasm");
+    server.send_file_change("site/never_read.js");
+    server.send_file_change("site/we\"ird.wasm");
+    server.send_build_end(true);
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"file_change\",\"path\":\"site/app.wasm\"}".to_string()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"file_change\",\"path\":\"site/we\\\"ird.wasm\"}".to_string()));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Text("{\"type\":\"build_end\",\"success\":true}".to_string()));
+
+    // a close is answered with a close
+    send(&mut socket, WebSocketMessage::Close(Some(1000)));
+    assert_eq!(socket.read_message().unwrap(), WebSocketMessage::Close(Some(1000)));
+
+    // a version we don't speak
+    let mut reader = BufReader::new(TcpStream::connect(server.listen_address.unwrap()).unwrap());
+    reader.get_mut().write_all(b"GET /$websocket HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 8\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n").unwrap();
+    let head = read_head(&mut reader);
+    assert_eq!(head[0], "HTTP/1.1 426 Upgrade Required");
+    assert!(head.contains(&"Sec-WebSocket-Version: 13".to_string()));
+
+    server.terminate();
+    let _ = std::fs::remove_dir_all(&dir);
+}
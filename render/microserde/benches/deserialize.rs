@@ -0,0 +1,119 @@
+// compares owned and borrowed deserialization, and the line reader against splitting a
+// buffer, on generated cargo messages. run with cargo bench -p makepad-microserde
+use makepad_microserde::*;
+use std::borrow::Cow;
+use std::time::Instant;
+
+#[derive(SerJson, DeJson, SerRon, DeRon, SerBin, DeBin)]
+struct Span {
+    file_name: String,
+    line_start: u32,
+    column_start: u32,
+    is_primary: bool,
+    label: Option<String>,
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, SerBin, DeBin)]
+struct Message {
+    reason: String,
+    package_id: String,
+    level: String,
+    message: String,
+    spans: Vec<Span>,
+    rendered: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(DeJson, DeRon, DeBin)]
+struct SpanRef<'a> {
+    file_name: &'a str,
+    line_start: u32,
+    column_start: u32,
+    is_primary: bool,
+    label: Option<&'a str>,
+}
+
+#[allow(dead_code)]
+#[derive(DeJson, DeRon, DeBin)]
+struct MessageRef<'a> {
+    reason: &'a str,
+    package_id: &'a str,
+    level: &'a str,
+    // messages can contain escapes, so they fall back to a copy
+    message: Cow<'a, str>,
+    spans: Vec<SpanRef<'a>>,
+    rendered: Option<Cow<'a, str>>,
+}
+
+fn messages(count: usize) -> Vec<Message> {
+    (0..count).map( | index | Message {
+        reason: "compiler-message".to_string(),
+        package_id: format!("makepad-render 0.1.0 (path+file:///home/user/makepad/render/{})", index % 7),
+        level: if index % 3 == 0 {"error"} else {"warning"}.to_string(),
+        message: format!("unused variable: `value_{}`", index),
+        spans: (0..3).map( | span | Span {
+            file_name: format!("render/src/module_{}/file_{}.rs", index % 13, span),
+            line_start: (index * 7 + span) as u32,
+            column_start: 9,
+            is_primary: span == 0,
+            label: if span == 0 {Some("help: consider prefixing with an underscore".to_string())} else {None},
+        }).collect(),
+        rendered: if index % 4 == 0 {Some(format!("warning: unused variable\n  --> render/src/lib.rs:{}:9\n", index))} else {None},
+    }).collect()
+}
+
+fn bench<F: FnMut() -> usize>(name: &str, bytes: usize, mut f: F) {
+    // warm up, then take the best of a few runs
+    let mut check = f();
+    let mut best = std::f64::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        check += f();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!("{:<36} {:>8.2} ms {:>8.1} MB/s  ({})", name, best * 1000.0, bytes as f64 / best / 1_000_000.0, check);
+}
+
+fn main() {
+    let messages = messages(20000);
+
+    // one document per line, like cargo --message-format=json
+    let mut ndjson = String::new();
+    for message in &messages {
+        ndjson.push_str(&message.serialize_json().replace('\n', ""));
+        ndjson.push('\n');
+    }
+    bench("json lines, owned", ndjson.len(), || {
+        ndjson.lines().map( | line | Message::deserialize_json(line).unwrap().spans.len()).sum()
+    });
+    bench("json lines, borrowed", ndjson.len(), || {
+        ndjson.lines().map( | line | MessageRef::deserialize_json(line).unwrap().spans.len()).sum()
+    });
+    bench("json line reader, borrowed", ndjson.len(), || {
+        let mut reader = JsonLineReader::new(ndjson.as_bytes());
+        let mut count = 0;
+        while let Some(message) = reader.read_next::<MessageRef>() {
+            count += message.unwrap().spans.len();
+        }
+        count
+    });
+    bench("json line reader, owned", ndjson.len(), || {
+        JsonLineReader::new(ndjson.as_bytes()).documents::<Message>().map( | m | m.unwrap().spans.len()).sum()
+    });
+
+    let ron = messages.serialize_ron();
+    bench("ron, owned", ron.len(), || {
+        Vec::<Message>::deserialize_ron(&ron).unwrap().len()
+    });
+    bench("ron, borrowed", ron.len(), || {
+        Vec::<MessageRef>::deserialize_ron(&ron).unwrap().len()
+    });
+
+    let bin = messages.serialize_bin();
+    bench("bin, owned", bin.len(), || {
+        Vec::<Message>::deserialize_bin(&bin).unwrap().len()
+    });
+    bench("bin, borrowed", bin.len(), || {
+        Vec::<MessageRef>::deserialize_bin(&bin).unwrap().len()
+    });
+}
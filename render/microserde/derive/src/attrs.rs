@@ -0,0 +1,190 @@
+use proc_macro::{TokenStream};
+use crate::macro_lib::*;
+
+// the serde attributes on fields and containers
+//
+// #[rename = "name"]   use another key for a field or variant in JSON, RON and TOML
+// #[default]           a missing field deserializes to Default::default()
+// #[default = 5]       a missing field deserializes to a literal
+// #[default = "expr"]  or to an expression in a string, as rustc only allows literals after =
+// #[default(expr)]     or to an expression
+// #[skip]              the field is not serialized and deserializes to its default
+// #[flatten]           the fields of a struct valued field are inlined in the parent
+//
+// and on containers, for JSON, RON and TOML
+// #[default]           on a struct, missing fields come from the Default of the struct
+// #[ignore_unknown]    keys that aren't fields are skipped instead of being an error
+//
+// and on enums, for JSON, RON and TOML
+// #[tag = "type"]                      internally tagged {"type":"Variant", ...fields}
+// #[tag = "t"] #[content = "c"]       adjacently tagged {"t":"Variant","c":...}
+// #[untagged]                          just the content, the first variant that parses wins
+
+pub enum EnumRepr {
+    External,
+    Internal(String),
+    Adjacent(String, String),
+    Untagged
+}
+
+pub fn enum_repr(attrs: &[Attribute]) -> Result<EnumRepr, TokenStream> {
+    let tag = find_attr(attrs, "tag");
+    let content = find_attr(attrs, "content");
+    let untagged = find_attr(attrs, "untagged").is_some();
+    match (tag, content, untagged) {
+        (None, None, false) => Ok(EnumRepr::External),
+        (None, None, true) => Ok(EnumRepr::Untagged),
+        (Some(tag), None, false) => match tag.string() {
+            Some(tag) => Ok(EnumRepr::Internal(tag)),
+            None => Err(error("#[tag] needs a string, as in #[tag = \"type\"]"))
+        },
+        (Some(tag), Some(content), false) => match (tag.string(), content.string()) {
+            (Some(tag), Some(content)) => Ok(EnumRepr::Adjacent(tag, content)),
+            _ => Err(error("#[tag] and #[content] need a string, as in #[tag = \"t\"] #[content = \"c\"]"))
+        },
+        (None, Some(_), _) => Err(error("#[content] needs a #[tag]")),
+        (Some(_), _, true) => Err(error("#[untagged] can't be combined with #[tag]")),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ContainerAttrs {
+    pub default: bool,
+    pub ignore_unknown: bool
+}
+
+pub fn container_attrs(attrs: &[Attribute], is_struct: bool) -> Result<ContainerAttrs, TokenStream> {
+    let default = find_attr(attrs, "default");
+    if let Some(default) = default {
+        if !is_struct {
+            return Err(error("#[default] on a container is only supported on structs"))
+        }
+        if default.value.is_some() || default.args.is_some() {
+            return Err(error("#[default] on a struct takes no value, missing fields come from its Default"))
+        }
+    }
+    Ok(ContainerAttrs {
+        default: default.is_some(),
+        ignore_unknown: find_attr(attrs, "ignore_unknown").is_some()
+    })
+}
+
+// the key a variant is written as
+pub fn variant_key(variant: &str, attrs: &[Attribute]) -> String {
+    find_attr(attrs, "rename").and_then( | attr | attr.string()).unwrap_or_else( || variant.to_string())
+}
+
+// the fields of a struct or variant, with their names bound to _name
+pub fn bind_fields(tb: &mut TokenBuilder, fields: &[StructField]) {
+    tb.add("{");
+    for field in fields {
+        if !field.is_skip() {
+            tb.ident(&field.name).add(":").ident(&field.binding()).add(",");
+        }
+    }
+    tb.add(".. }");
+}
+
+// at most one field can be flattened, it gets all keys its parent doesn't know
+pub fn check_fields(fields: &[StructField]) -> Result<(), TokenStream> {
+    if fields.iter().filter( | field | field.is_flatten()).count() > 1 {
+        return Err(error("Only one #[flatten] field per struct is supported"))
+    }
+    for field in fields {
+        if field.is_flatten() && (field.is_skip() || field.attr("rename").is_some()) {
+            return Err(error("#[flatten] can't be combined with #[skip] or #[rename]"))
+        }
+        if let Some(attr) = field.attr("default") {
+            if let Some(expr) = attr.string() {
+                if expr.parse::<TokenStream>().is_err() {
+                    return Err(error(&format!("Cannot parse #[default] expression \"{}\"", expr)))
+                }
+            }
+        }
+        if let Some(attr) = field.attr("rename") {
+            if attr.string().is_none() {
+                return Err(error("#[rename] needs a string, as in #[rename = \"name\"]"))
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn check_variants(variants: &[EnumVariant], repr: &EnumRepr) -> Result<(), TokenStream> {
+    for variant in variants {
+        match &variant.fields {
+            VariantFields::Named(fields) => check_fields(fields) ?,
+            VariantFields::Tuple(types) => if let EnumRepr::Internal(_) = repr {
+                if types.len() != 1 {
+                    return Err(error("Internally tagged enums only support tuple variants with one field"))
+                }
+            },
+            VariantFields::Unit => ()
+        }
+    }
+    Ok(())
+}
+
+impl EnumVariant {
+    pub fn key(&self) -> String {
+        variant_key(&self.name, &self.attrs)
+    }
+}
+
+impl StructField {
+    pub fn key(&self) -> String {
+        variant_key(&self.name, &self.attrs)
+    }
+
+    pub fn binding(&self) -> String {
+        format!("_{}", self.name)
+    }
+
+    pub fn is_skip(&self) -> bool {
+        self.attr("skip").is_some()
+    }
+
+    pub fn is_flatten(&self) -> bool {
+        self.attr("flatten").is_some()
+    }
+
+    // the value a missing or skipped field gets, None if it is required
+    pub fn default_value(&self) -> Option<TokenStream> {
+        if let Some(attr) = self.attr("default") {
+            let expr = if let Some(expr) = attr.string() {
+                Some(expr.parse().unwrap())
+            }
+            else {
+                attr.value.clone().or_else( || attr.args.clone())
+            };
+            if let Some(expr) = expr {
+                let mut tb = TokenBuilder::new();
+                tb.add("(").stream(Some(expr)).add(")");
+                return Some(tb.end())
+            }
+        }
+        else if !self.is_skip() {
+            return None
+        }
+        let mut tb = TokenBuilder::new();
+        tb.add("std :: default :: Default :: default ( )");
+        Some(tb.end())
+    }
+}
+
+// the value of a field that isn't in the input, for the text formats.
+// a struct with #[default] has its Default in _default
+pub fn missing_field(tb: &mut TokenBuilder, field: &StructField, container: ContainerAttrs) {
+    if container.default && field.attr("default").is_none() {
+        tb.add("_default .").ident(&field.name);
+    }
+    else if let Some(value) = field.default_value() {
+        tb.stream(Some(value));
+    }
+    else if field.is_option() {
+        tb.add("None");
+    }
+    else {
+        tb.add("return std :: result :: Result :: Err ( s . err_nf (").string(&field.key()).add(") )");
+    }
+}
@@ -1,12 +1,47 @@
 use proc_macro::{TokenStream};
 use crate::macro_lib::*;
+use crate::attrs::*;
+
+// bin is positional, so renames and enum representations don't apply. Skipped fields are
+// left out, and a field with a default that is past the end of the data gets its default,
+// so fields can be appended to a struct without breaking older data.
+// fields are serialized by reference like enum bindings, so a &[u8] field gets its length prefix
+fn de_bin_field(tb: &mut TokenBuilder, field: &StructField) {
+    tb.ident(&field.name).add(":");
+    if field.is_skip() {
+        tb.stream(field.default_value());
+    }
+    else if let Some(value) = field.default_value() {
+        tb.add("if * o < d . len ( ) {");
+        de_bin_value(tb, &field.name);
+        tb.add("} else {").stream(Some(value)).add("}");
+    }
+    else {
+        de_bin_value(tb, &field.name);
+    }
+    tb.add(",");
+}
+
+// a field value, with the field name on the path of its errors
+fn de_bin_value(tb: &mut TokenBuilder, name: &str) {
+    tb.add("DeBin :: de_bin ( o , d ) . map_err ( | e | e . at_key (").string(name).add(") ) ?");
+}
+
+fn de_bin_tuple(tb: &mut TokenBuilder, len: usize) {
+    tb.add("(");
+    for index in 0..len {
+        tb.add("DeBin :: de_bin ( o , d ) . map_err ( | e | e . at_index (").unsuf_usize(index).add(") ) ? ,");
+    }
+    tb.add(")");
+}
 
 pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
 
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
     
-    parser.eat_ident("pub");
+    parser.eat_attributes();
+    parser.eat_visibility();
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
@@ -19,12 +54,14 @@ pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
 
             if let Some(types) = types{
                 for i in 0..types.len(){
-                     tb.add("self .").unsuf_usize(i).add(". ser_bin ( s ) ;");
+                     tb.add("( & self .").unsuf_usize(i).add(") . ser_bin ( s ) ;");
                 }
             }
             else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                for (field,_ty) in fields{
-                    tb.add("self .").ident(&field).add(". ser_bin ( s ) ;");
+                for field in fields{
+                    if !field.is_skip(){
+                        tb.add("( & self .").ident(&field.name).add(") . ser_bin ( s ) ;");
+                    }
                 }
             }
             else{
@@ -44,15 +81,17 @@ pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
             tb.add("{ fn ser_bin ( & self , s : & mut Vec < u8 > ) {");
             tb.add("match self {");
             
-            if !parser.open_brace(){
-                return parser.unexpected()
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
             }
-            let mut index = 0;
-            while !parser.eat_eot(){
-                // parse ident
-                if let Some(variant) = parser.eat_any_ident(){
-                    if let Some(types) = parser.eat_all_types(){
-                        tb.add("Self ::").ident(&variant).add("(");
+            else{
+                return parser.unexpected()
+            };
+            for (index, variant) in variants.iter().enumerate(){
+                let index = index as u16;
+                match &variant.fields{
+                    VariantFields::Tuple(types) => {
+                        tb.add("Self ::").ident(&variant.name).add("(");
                         for i in 0..types.len(){
                             tb.ident(&format!("n{}", i)).add(",");
                         }
@@ -62,29 +101,21 @@ pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
                         }
                         tb.add("}");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for (field, _ty) in fields.iter(){
-                            tb.ident(field).add(",");
-                        }
-                        tb.add("} => {").suf_u16(index).add(". ser_bin ( s ) ;");
-                        for (field, _ty) in fields{
-                            tb.ident(&field).add(". ser_bin ( s ) ;");
+                    VariantFields::Named(fields) => {
+                        tb.add("Self ::").ident(&variant.name);
+                        bind_fields(&mut tb, fields);
+                        tb.add("=> {").suf_u16(index).add(". ser_bin ( s ) ;");
+                        for field in fields{
+                            if !field.is_skip(){
+                                tb.ident(&field.binding()).add(". ser_bin ( s ) ;");
+                            }
                         }
                         tb.add("}");
                     }
-                    else if parser.is_punct(',') || parser.is_eot(){ // bare variant
-                        tb.add("Self ::").ident(&variant).add("=> {");
+                    VariantFields::Unit => {
+                        tb.add("Self ::").ident(&variant.name).add("=> {");
                         tb.suf_u16(index).add(". ser_bin ( s ) ; }");
                     }
-                    else{
-                        return parser.unexpected();
-                    }
-                    index += 1;
-                    parser.eat_punct(',');
-                }
-                else{
-                    return parser.unexpected()
                 }
             }
             tb.add("} } } ;");
@@ -98,30 +129,28 @@ pub fn derive_de_bin_impl(input: TokenStream) -> TokenStream {
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
     
-    parser.eat_ident("pub");
+    parser.eat_attributes();
+    parser.eat_visibility();
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
             let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("DeBin"));
+            let where_clause = parser.eat_where_clause(Some("DeBin < 'de >"));
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_bin ( o : & mut usize , d : & [ u8 ] )");
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeBin < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn de_bin ( o : & mut usize , d : & 'de [ u8 ] )");
             tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeBinErr > { ");
             tb.add("std :: result :: Result :: Ok ( Self");
 
             if let Some(types) = types{
-                tb.add("(");
-                for _ in 0..types.len(){
-                     tb.add("DeBin :: de_bin ( o , d ) ?");
-                }
-                tb.add(")");
+                de_bin_tuple(&mut tb, types.len());
             }
             else if let Some(fields) = parser.eat_all_struct_fields(){ 
                 tb.add("{");
-                for (field,_ty) in fields{
-                    tb.ident(&field).add(": DeBin :: de_bin ( o , d ) ? ,");
+                for field in &fields{
+                    de_bin_field(&mut tb, field);
                 }
                 tb.add("}");
             }
@@ -135,55 +164,42 @@ pub fn derive_de_bin_impl(input: TokenStream) -> TokenStream {
     else if parser.eat_ident("enum"){
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("DeBin"));
+            let where_clause = parser.eat_where_clause(Some("DeBin < 'de >"));
             
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_bin ( o : & mut usize , d : & [ u8 ] )");
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeBin < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn de_bin ( o : & mut usize , d : & 'de [ u8 ] )");
             tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeBinErr > {");
             tb.add("let id : u16 = DeBin :: de_bin ( o , d ) ? ;");
             tb.add("match id {");
             
-            if !parser.open_brace(){
-                return parser.unexpected()
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
             }
-            let mut index = 0;
-            while !parser.eat_eot(){
-                // parse ident
-                if let Some(variant) = parser.eat_any_ident(){
-                    tb.suf_u16(index as u16).add("=> {");
-                    tb.add("std :: result :: Result :: Ok ( Self ::");
-                    if let Some(types) = parser.eat_all_types(){
-                        tb.ident(&variant).add("(");
-                        for _ in 0..types.len(){
-                            tb.add("DeBin :: de_bin ( o , d ) ? ,");
-                        }
-                        tb.add(")");
+            else{
+                return parser.unexpected()
+            };
+            for (index, variant) in variants.iter().enumerate(){
+                tb.suf_u16(index as u16).add("=> {");
+                tb.add("std :: result :: Result :: Ok ( Self ::").ident(&variant.name);
+                match &variant.fields{
+                    VariantFields::Tuple(types) => {
+                        de_bin_tuple(&mut tb, types.len());
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.ident(&variant).add("{");
-                        for (field, _ty) in fields.iter(){
-                            tb.ident(field).add(": DeBin :: de_bin ( o , d ) ? ,");
+                    VariantFields::Named(fields) => {
+                        tb.add("{");
+                        for field in fields{
+                            de_bin_field(&mut tb, field);
                         }
                         tb.add("}");
                     }
-                    else if parser.is_punct(',') || parser.is_eot(){ // bare variant
-                        tb.ident(&variant);
-                    }
-                    else{
-                        return parser.unexpected();
-                    }
-                    
-                    tb.add(") }");
-                    index += 1;
-                    parser.eat_punct(',');
-                }
-                else{
-                    return parser.unexpected()
+                    VariantFields::Unit => ()
                 }
+                tb.add(") }");
             } 
-            tb.add("_ => std :: result :: Result :: Err ( makepad_microserde :: DeBinErr { o : * o , l :");
-            tb.unsuf_usize(1).add(", s : d . len ( ) } )");
+            tb.add("_ => std :: result :: Result :: Err ( makepad_microserde :: DeBinErr :: new ( * o ,");
+            tb.unsuf_usize(1).add(", d . len ( ) ) )");
             tb.add("} } } ;");
             return tb.end();
         }
@@ -0,0 +1,376 @@
+use proc_macro::{TokenStream};
+use crate::macro_lib::*;
+use crate::attrs::*;
+
+// the tagged bin format writes fields with ids, so renames and enum representations don't
+// apply and #[flatten] fields are written as a nested message:
+//
+// #[id = 3]                 the id of a field or variant, by default one more than the one before,
+//                           starting at 1. give new fields new ids and don't reuse the ones of removed fields
+// #[version = 2]            on a struct or enum, the version written to the data
+// #[min_version = 1]        the oldest version this type can read, and that can read it
+
+// the id of every field, or variant
+fn ids(attrs: &[&[Attribute]]) -> Result<Vec<u32>, TokenStream> {
+    let mut ids = Vec::new();
+    for attrs in attrs {
+        let id = match find_attr(attrs, "id") {
+            Some(attr) => match attr.int() {
+                Some(id) if id > 0 && id <= std::u32::MAX as u64 >> 3 => id as u32,
+                _ => return Err(error("#[id] needs a number from 1, as in #[id = 3]"))
+            },
+            None => ids.last().map_or(1, | id | id + 1)
+        };
+        if ids.contains(&id) {
+            return Err(error(&format!("Id {} is used twice", id)))
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+fn field_ids(fields: &[StructField]) -> Result<Vec<u32>, TokenStream> {
+    ids(&fields.iter().map( | field | &field.attrs[..]).collect::<Vec<_ >> ())
+}
+
+// the (version, min_version) of a struct or enum
+fn version(attrs: &[Attribute]) -> Result<(u32, u32), TokenStream> {
+    let version = match find_attr(attrs, "version") {
+        Some(attr) => match attr.int() {
+            Some(version) if version <= std::u32::MAX as u64 => version as u32,
+            _ => return Err(error("#[version] needs a number, as in #[version = 2]"))
+        },
+        None => 0
+    };
+    let min_version = match find_attr(attrs, "min_version") {
+        Some(attr) => match attr.int() {
+            Some(min_version) if min_version <= version as u64 => min_version as u32,
+            _ => return Err(error("#[min_version] needs a number up to the #[version], as in #[min_version = 1]"))
+        },
+        None => 0
+    };
+    Ok((version, min_version))
+}
+
+fn ser_version(tb: &mut TokenBuilder, version: (u32, u32)) {
+    if version.0 != 0 {
+        tb.add("makepad_microserde :: bin_tagged_version (").unsuf_usize(version.0 as usize).add(",");
+        tb.unsuf_usize(version.1 as usize).add(", s ) ;");
+    }
+}
+
+// writes a message of fields, bound to the given names
+fn ser_message(tb: &mut TokenBuilder, bindings: &[String], ids: &[u32]) {
+    tb.add("let start = makepad_microserde :: bin_tagged_begin ( s ) ;");
+    for (binding, id) in bindings.iter().zip(ids) {
+        tb.add("(").ident(binding).add(") . ser_bin_tagged_field (").unsuf_usize(*id as usize).add(", s ) ;");
+    }
+    tb.add("makepad_microserde :: bin_tagged_end ( start , s ) ;");
+}
+
+// the value of a field that isn't in the data
+fn de_missing(tb: &mut TokenBuilder, name: &str, field: &str, id: u32, default: Option<TokenStream>) {
+    if let Some(value) = default {
+        tb.stream(Some(value));
+    }
+    else {
+        tb.add("match DeBinTagged :: de_bin_tagged_missing ( ) { Some ( v ) => v , None =>");
+        tb.add("return std :: result :: Result :: Err ( makepad_microserde :: DeBinTaggedErr :: missing ( * o ,");
+        tb.string(name).add(",").string(field).add(",").unsuf_usize(id as usize).add(") ) }");
+    }
+}
+
+// reads the fields of a message that ends at end into _0, _1, .. skipping the ones without
+// an id, and checks the version of the data when one is given. the version comes first,
+// so a newer message isn't misread before it is rejected
+fn de_message(tb: &mut TokenBuilder, ids: &[Option<u32>], version: Option<(&str, (u32, u32))>) {
+    for (i, id) in ids.iter().enumerate() {
+        if id.is_some() {
+            tb.add("let mut").ident(&format!("_{}", i)).add("= None ;");
+        }
+    }
+    if version.is_some() {
+        tb.add("let mut _versioned = false ;");
+    }
+    tb.add("while * o < end {");
+    tb.add("let ( id , wire ) = makepad_microserde :: bin_tagged_read_key ( o , d ) ? ;");
+    tb.add("match id {");
+    if let Some((name, version)) = version {
+        de_version_arm(tb, name, version);
+    }
+    for (i, id) in ids.iter().enumerate() {
+        let id = if let Some(id) = id {*id} else {continue};
+        tb.unsuf_usize(id as usize).add("=>").ident(&format!("_{}", i));
+        tb.add("= Some ( DeBinTagged :: de_bin_tagged_field ( o , d , wire ) ? ) ,");
+    }
+    tb.add("_ => makepad_microserde :: bin_tagged_skip ( o , d , wire ) ? ,");
+    tb.add("} }");
+    tb.add("makepad_microserde :: bin_tagged_check_end ( o , end ) ? ;");
+}
+
+fn de_version_arm(tb: &mut TokenBuilder, name: &str, version: (u32, u32)) {
+    tb.add("0 => { makepad_microserde :: bin_tagged_read_version ( o , d , wire ,").string(name).add(",");
+    tb.unsuf_usize(version.0 as usize).add(",").unsuf_usize(version.1 as usize).add(") ? ; _versioned = true ; }");
+}
+
+// data without a version is version 0
+fn de_check_version(tb: &mut TokenBuilder, name: &str, version: (u32, u32)) {
+    tb.add("if ! _versioned { makepad_microserde :: bin_tagged_check_version ( * o ,").string(name).add(", ( 0 , 0 ) ,");
+    tb.unsuf_usize(version.0 as usize).add(",").unsuf_usize(version.1 as usize).add(") ? ; }");
+}
+
+// the value of the fields read by de_message
+fn de_struct_fields(tb: &mut TokenBuilder, name: &str, fields: &[StructField], ids: &[u32]) {
+    tb.add("{");
+    for (i, field) in fields.iter().enumerate() {
+        tb.ident(&field.name).add(":");
+        if field.is_skip() {
+            tb.stream(field.default_value());
+        }
+        else {
+            tb.add("match").ident(&format!("_{}", i)).add("{ Some ( v ) => v , None =>");
+            de_missing(tb, name, &field.name, ids[i], field.default_value());
+            tb.add("}");
+        }
+        tb.add(",");
+    }
+    tb.add("}");
+}
+
+fn de_tuple_fields(tb: &mut TokenBuilder, name: &str, ids: &[u32]) {
+    tb.add("(");
+    for (i, id) in ids.iter().enumerate() {
+        tb.add("match").ident(&format!("_{}", i)).add("{ Some ( v ) => v , None =>");
+        de_missing(tb, name, &i.to_string(), *id, None);
+        tb.add("} ,");
+    }
+    tb.add(")");
+}
+
+pub fn derive_ser_bin_tagged_impl(input: TokenStream) -> TokenStream {
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
+    let version = match version(&attrs) {
+        Ok(version) => version,
+        Err(err) => return err
+    };
+    if parser.eat_ident("struct") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some("SerBinTagged"));
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("SerBinTagged for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ const WIRE : u8 = makepad_microserde :: BIN_WIRE_LEN ;");
+            tb.add("fn ser_bin_tagged ( & self , s : & mut Vec < u8 > ) {");
+            tb.add("let start = makepad_microserde :: bin_tagged_begin ( s ) ;");
+            ser_version(&mut tb, version);
+            if let Some(types) = types {
+                for i in 0..types.len() {
+                    tb.add("( & self .").unsuf_usize(i).add(") . ser_bin_tagged_field (").unsuf_usize(i + 1).add(", s ) ;");
+                }
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields() {
+                let ids = match field_ids(&fields) {
+                    Ok(ids) => ids,
+                    Err(err) => return err
+                };
+                for (field, id) in fields.iter().zip(ids) {
+                    if !field.is_skip() {
+                        tb.add("( & self .").ident(&field.name).add(") . ser_bin_tagged_field (").unsuf_usize(id as usize).add(", s ) ;");
+                    }
+                }
+            }
+            else {
+                return parser.unexpected()
+            }
+            tb.add("makepad_microserde :: bin_tagged_end ( start , s ) ;");
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some("SerBinTagged"));
+            let variants = if let Some(variants) = parser.eat_all_enum_variants() {
+                variants
+            }
+            else {
+                return parser.unexpected()
+            };
+            let variant_ids = match ids(&variants.iter().map( | variant | &variant.attrs[..]).collect::<Vec<_ >> ()) {
+                Ok(ids) => ids,
+                Err(err) => return err
+            };
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("SerBinTagged for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ const WIRE : u8 = makepad_microserde :: BIN_WIRE_LEN ;");
+            tb.add("fn ser_bin_tagged ( & self , s : & mut Vec < u8 > ) {");
+            tb.add("let outer = makepad_microserde :: bin_tagged_begin ( s ) ;");
+            ser_version(&mut tb, version);
+            tb.add("match self {");
+            for (variant, variant_id) in variants.iter().zip(variant_ids) {
+                tb.add("Self ::").ident(&variant.name);
+                let (bindings, ids) = match &variant.fields {
+                    VariantFields::Tuple(types) => {
+                        let bindings: Vec<String> = (0..types.len()).map( | i | format!("n{}", i)).collect();
+                        tb.add("(");
+                        for binding in &bindings {
+                            tb.ident(binding).add(",");
+                        }
+                        tb.add(")");
+                        (bindings, (1..=types.len() as u32).collect())
+                    }
+                    VariantFields::Named(fields) => {
+                        let ids = match field_ids(fields) {
+                            Ok(ids) => ids,
+                            Err(err) => return err
+                        };
+                        bind_fields(&mut tb, fields);
+                        let fields: Vec<(&StructField, u32)> = fields.iter().zip(ids).filter( | (field, _) | !field.is_skip()).collect();
+                        (fields.iter().map( | (field, _) | field.binding()).collect(), fields.iter().map( | (_, id) | *id).collect())
+                    }
+                    VariantFields::Unit => (Vec::new(), Vec::new())
+                };
+                tb.add("=> { makepad_microserde :: bin_tagged_key (").unsuf_usize(variant_id as usize);
+                tb.add(", makepad_microserde :: BIN_WIRE_LEN , s ) ;");
+                ser_message(&mut tb, &bindings, &ids);
+                tb.add("}");
+            }
+            tb.add("}");
+            tb.add("makepad_microserde :: bin_tagged_end ( outer , s ) ;");
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
+
+pub fn derive_de_bin_tagged_impl(input: TokenStream) -> TokenStream {
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
+    let version = match version(&attrs) {
+        Ok(version) => version,
+        Err(err) => return err
+    };
+    if parser.eat_ident("struct") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some("DeBinTagged < 'de >"));
+
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeBinTagged < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ const WIRE : u8 = makepad_microserde :: BIN_WIRE_LEN ;");
+            tb.add("fn de_bin_tagged ( o : & mut usize , d : & 'de [ u8 ] )");
+            tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeBinTaggedErr > {");
+            tb.add("let end = makepad_microserde :: bin_tagged_read_len ( o , d ) ? ;");
+
+            if let Some(types) = types {
+                let ids: Vec<u32> = (1..=types.len() as u32).collect();
+                de_message(&mut tb, &ids.iter().map( | id | Some(*id)).collect::<Vec<_ >> (), Some((&name, version)));
+                de_check_version(&mut tb, &name, version);
+                tb.add("std :: result :: Result :: Ok ( Self");
+                de_tuple_fields(&mut tb, &name, &ids);
+                tb.add(")");
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields() {
+                let ids = match field_ids(&fields) {
+                    Ok(ids) => ids,
+                    Err(err) => return err
+                };
+                // skipped fields are read past like unknown ones
+                let read_ids: Vec<Option<u32>> = fields.iter().zip(&ids).map( | (field, id) | if field.is_skip() {None} else {Some(*id)}).collect();
+                de_message(&mut tb, &read_ids, Some((&name, version)));
+                de_check_version(&mut tb, &name, version);
+                tb.add("std :: result :: Result :: Ok ( Self");
+                de_struct_fields(&mut tb, &name, &fields, &ids);
+                tb.add(")");
+            }
+            else {
+                return parser.unexpected()
+            }
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some("DeBinTagged < 'de >"));
+            let variants = if let Some(variants) = parser.eat_all_enum_variants() {
+                variants
+            }
+            else {
+                return parser.unexpected()
+            };
+            let variant_ids = match ids(&variants.iter().map( | variant | &variant.attrs[..]).collect::<Vec<_ >> ()) {
+                Ok(ids) => ids,
+                Err(err) => return err
+            };
+
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeBinTagged < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ const WIRE : u8 = makepad_microserde :: BIN_WIRE_LEN ;");
+            tb.add("fn de_bin_tagged ( o : & mut usize , d : & 'de [ u8 ] )");
+            tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeBinTaggedErr > {");
+            tb.add("let end = makepad_microserde :: bin_tagged_read_len ( o , d ) ? ;");
+            tb.add("let mut result = None ;");
+            tb.add("let mut _versioned = false ;");
+            tb.add("while * o < end {");
+            tb.add("let ( id , wire ) = makepad_microserde :: bin_tagged_read_key ( o , d ) ? ;");
+            tb.add("match id {");
+            de_version_arm(&mut tb, &name, version);
+            for (variant, variant_id) in variants.iter().zip(variant_ids) {
+                tb.unsuf_usize(variant_id as usize).add("=> {");
+                tb.add("let end = makepad_microserde :: bin_tagged_read_variant ( o , d , wire ) ? ;");
+                match &variant.fields {
+                    VariantFields::Tuple(types) => {
+                        let ids: Vec<u32> = (1..=types.len() as u32).collect();
+                        de_message(&mut tb, &ids.iter().map( | id | Some(*id)).collect::<Vec<_ >> (), None);
+                        tb.add("result = Some ( Self ::").ident(&variant.name);
+                        de_tuple_fields(&mut tb, &format!("{}::{}", name, variant.name), &ids);
+                        tb.add(") ;");
+                    }
+                    VariantFields::Named(fields) => {
+                        let ids = match field_ids(fields) {
+                            Ok(ids) => ids,
+                            Err(err) => return err
+                        };
+                        let read_ids: Vec<Option<u32>> = fields.iter().zip(&ids).map( | (field, id) | if field.is_skip() {None} else {Some(*id)}).collect();
+                        de_message(&mut tb, &read_ids, None);
+                        tb.add("result = Some ( Self ::").ident(&variant.name);
+                        de_struct_fields(&mut tb, &format!("{}::{}", name, variant.name), fields, &ids);
+                        tb.add(") ;");
+                    }
+                    VariantFields::Unit => {
+                        de_message(&mut tb, &[], None);
+                        tb.add("result = Some ( Self ::").ident(&variant.name).add(") ;");
+                    }
+                }
+                tb.add("}");
+            }
+            tb.add("_ => return std :: result :: Result :: Err ( makepad_microserde :: DeBinTaggedErr :: unknown_variant ( * o ,");
+            tb.string(&name).add(", id ) ) ,");
+            tb.add("} }");
+            tb.add("makepad_microserde :: bin_tagged_check_end ( o , end ) ? ;");
+            de_check_version(&mut tb, &name, version);
+            tb.add("match result { Some ( r ) => std :: result :: Result :: Ok ( r ) ,");
+            tb.add("None => std :: result :: Result :: Err ( makepad_microserde :: DeBinTaggedErr :: no_variant ( * o ,").string(&name).add(") ) }");
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
@@ -0,0 +1,335 @@
+use proc_macro::{TokenStream};
+use crate::macro_lib::*;
+use crate::attrs::*;
+
+// MessagePack and CBOR have the same data model, so their derives differ only in names.
+// structs are maps of their field keys, with #[rename], #[skip] and #[default] like JSON.
+// enums are always externally tagged and #[flatten] fields are written as a nested map
+
+pub struct PackFormat {
+    pub ser: &'static str,
+    pub de: &'static str,
+    // the name in the method and helper names, as in ser_msgpack and msgpack_read_map
+    pub name: &'static str,
+    pub err: &'static str
+}
+
+pub const MSGPACK: PackFormat = PackFormat {ser: "SerMsgPack", de: "DeMsgPack", name: "msgpack", err: "DeMsgPackErr"};
+pub const CBOR: PackFormat = PackFormat {ser: "SerCbor", de: "DeCbor", name: "cbor", err: "DeCborErr"};
+
+impl PackFormat {
+    fn ser_fn(&self) -> String {format!("ser_{}", self.name)}
+    fn de_fn(&self) -> String {format!("de_{}", self.name)}
+    fn helper(&self, tb: &mut TokenBuilder, name: &str) {
+        tb.add("makepad_microserde ::").ident(&format!("{}_{}", self.name, name));
+    }
+    fn err(&self, tb: &mut TokenBuilder) {
+        tb.add("makepad_microserde ::").ident(self.err);
+    }
+    fn de_value(&self, tb: &mut TokenBuilder) {
+        tb.ident(self.de).add("::").ident(&self.de_fn()).add("( o , d ) ?");
+    }
+}
+
+// writes the fields bound by bind_fields as a map
+fn ser_fields(f: &PackFormat, tb: &mut TokenBuilder, fields: &[StructField]) {
+    let fields: Vec<&StructField> = fields.iter().filter( | field | !field.is_skip()).collect();
+    f.helper(tb, "map_len");
+    tb.add("(").unsuf_usize(fields.len()).add(", s ) ;");
+    for field in fields {
+        tb.string(&field.key()).add(".").ident(&f.ser_fn()).add("( s ) ;");
+        tb.ident(&field.binding()).add(".").ident(&f.ser_fn()).add("( s ) ;");
+    }
+}
+
+// writes the tuple fields n0, n1, .. as an array, or a single one as is
+fn ser_tuple(f: &PackFormat, tb: &mut TokenBuilder, len: usize) {
+    if len != 1 {
+        f.helper(tb, "array_len");
+        tb.add("(").unsuf_usize(len).add(", s ) ;");
+    }
+    for i in 0..len {
+        tb.ident(&format!("n{}", i)).add(".").ident(&f.ser_fn()).add("( s ) ;");
+    }
+}
+
+// reads a map into the fields of a struct or variant, evaluates to the value
+fn de_fields(f: &PackFormat, tb: &mut TokenBuilder, name: &str, variant: Option<&str>, fields: &[StructField]) {
+    tb.add("{");
+    for field in fields {
+        if !field.is_skip() {
+            tb.add("let mut").ident(&field.binding()).add("= None ;");
+        }
+    }
+    tb.add("let mut len =");
+    f.helper(tb, "read_map");
+    tb.add("( o , d ) ? ; while");
+    f.helper(tb, "more");
+    tb.add("( o , d , & mut len ) ? {");
+    tb.add("let key =");
+    f.helper(tb, "read_str");
+    tb.add("( o , d ) ? ; match & * key {");
+    for field in fields {
+        if !field.is_skip() {
+            tb.string(&field.key()).add("=>").ident(&field.binding()).add("= Some (");
+            f.de_value(tb);
+            tb.add(") ,");
+        }
+    }
+    tb.add("_ =>");
+    f.helper(tb, "skip");
+    tb.add("( o , d ) ? , } }");
+
+    tb.add("Self");
+    if let Some(variant) = variant {
+        tb.add("::").ident(variant);
+    }
+    tb.add("{");
+    for field in fields {
+        tb.ident(&field.name).add(":");
+        if field.is_skip() {
+            tb.stream(field.default_value());
+        }
+        else {
+            tb.add("match").ident(&field.binding()).add("{ Some ( v ) => v , None =>");
+            if let Some(value) = field.default_value() {
+                tb.stream(Some(value));
+            }
+            else if field.is_option() {
+                tb.add("None");
+            }
+            else {
+                tb.add("return std :: result :: Result :: Err (");
+                f.err(tb);
+                tb.add(":: missing ( * o ,").string(name).add(",").string(&field.key()).add(") )");
+            }
+            tb.add("}");
+        }
+        tb.add(",");
+    }
+    tb.add("} }");
+}
+
+// reads an array, or a single value, into the fields of a tuple struct or variant
+fn de_tuple(f: &PackFormat, tb: &mut TokenBuilder, variant: Option<&str>, len: usize) {
+    tb.add("{");
+    if len != 1 {
+        tb.add("let mut len =");
+        f.helper(tb, "read_array");
+        tb.add("( o , d ) ? ;");
+    }
+    tb.add("let r = Self");
+    if let Some(variant) = variant {
+        tb.add("::").ident(variant);
+    }
+    tb.add("(");
+    for _ in 0..len {
+        if len != 1 {
+            tb.add("{");
+            f.helper(tb, "next");
+            tb.add("( o , d , & mut len ) ? ;");
+            f.de_value(tb);
+            tb.add("}");
+        }
+        else {
+            f.de_value(tb);
+        }
+        tb.add(",");
+    }
+    tb.add(") ;");
+    if len != 1 {
+        f.helper(tb, "finish");
+        tb.add("( o , d , & mut len ) ? ;");
+    }
+    tb.add("r }");
+}
+
+fn derive_ser_pack(f: &PackFormat, input: TokenStream) -> TokenStream {
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    parser.eat_attributes();
+    parser.eat_visibility();
+    if parser.eat_ident("struct") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some(f.ser));
+
+            tb.add("impl").stream(generic.clone());
+            tb.ident(f.ser).add("for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn").ident(&f.ser_fn()).add("( & self , s : & mut Vec < u8 > ) {");
+            if let Some(types) = types {
+                tb.add("let Self (");
+                for i in 0..types.len() {
+                    tb.ident(&format!("n{}", i)).add(",");
+                }
+                tb.add(") = self ;");
+                ser_tuple(f, &mut tb, types.len());
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields() {
+                tb.add("let Self");
+                bind_fields(&mut tb, &fields);
+                tb.add("= self ;");
+                ser_fields(f, &mut tb, &fields);
+            }
+            else {
+                return parser.unexpected()
+            }
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some(f.ser));
+            let variants = if let Some(variants) = parser.eat_all_enum_variants() {
+                variants
+            }
+            else {
+                return parser.unexpected()
+            };
+
+            tb.add("impl").stream(generic.clone());
+            tb.ident(f.ser).add("for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn").ident(&f.ser_fn()).add("( & self , s : & mut Vec < u8 > ) {");
+            tb.add("match self {");
+            for variant in &variants {
+                tb.add("Self ::").ident(&variant.name);
+                match &variant.fields {
+                    VariantFields::Tuple(types) => {
+                        tb.add("(");
+                        for i in 0..types.len() {
+                            tb.ident(&format!("n{}", i)).add(",");
+                        }
+                        tb.add(")");
+                    }
+                    VariantFields::Named(fields) => bind_fields(&mut tb, fields),
+                    VariantFields::Unit => ()
+                }
+                tb.add("=> {");
+                if let VariantFields::Unit = &variant.fields {
+                    tb.string(&variant.key()).add(".").ident(&f.ser_fn()).add("( s ) ;");
+                }
+                else {
+                    f.helper(&mut tb, "map_len");
+                    tb.add("( 1 , s ) ;");
+                    tb.string(&variant.key()).add(".").ident(&f.ser_fn()).add("( s ) ;");
+                    match &variant.fields {
+                        VariantFields::Tuple(types) => ser_tuple(f, &mut tb, types.len()),
+                        VariantFields::Named(fields) => ser_fields(f, &mut tb, fields),
+                        VariantFields::Unit => ()
+                    }
+                }
+                tb.add("}");
+            }
+            tb.add("} } } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
+
+fn derive_de_pack(f: &PackFormat, input: TokenStream) -> TokenStream {
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    parser.eat_attributes();
+    parser.eat_visibility();
+    let de_trait = format!("{} < 'de >", f.de);
+    if parser.eat_ident("struct") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some(&de_trait));
+
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add(&de_trait).add("for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn").ident(&f.de_fn()).add("( o : & mut usize , d : & 'de [ u8 ] )");
+            tb.add("-> std :: result :: Result < Self ,");
+            f.err(&mut tb);
+            tb.add("> { std :: result :: Result :: Ok (");
+            if let Some(types) = types {
+                de_tuple(f, &mut tb, None, types.len());
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields() {
+                if let Err(err) = check_fields(&fields) {
+                    return err
+                }
+                de_fields(f, &mut tb, &name, None, &fields);
+            }
+            else {
+                return parser.unexpected()
+            }
+            tb.add(") } } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum") {
+        if let Some(name) = parser.eat_any_ident() {
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some(&de_trait));
+            let variants = if let Some(variants) = parser.eat_all_enum_variants() {
+                variants
+            }
+            else {
+                return parser.unexpected()
+            };
+
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add(&de_trait).add("for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn").ident(&f.de_fn()).add("( o : & mut usize , d : & 'de [ u8 ] )");
+            tb.add("-> std :: result :: Result < Self ,");
+            f.err(&mut tb);
+            tb.add("> { let ( variant , value ) =");
+            f.helper(&mut tb, "read_variant");
+            tb.add("( o , d ) ? ; std :: result :: Result :: Ok ( match ( & * variant , value ) {");
+            for variant in &variants {
+                tb.add("(").string(&variant.key());
+                match &variant.fields {
+                    VariantFields::Unit => {
+                        // a unit variant written as a map has a value, like null
+                        tb.add(", value ) => { if value {");
+                        f.helper(&mut tb, "skip");
+                        tb.add("( o , d ) ? ; } Self ::").ident(&variant.name).add("}");
+                    }
+                    VariantFields::Tuple(types) => {
+                        tb.add(", true ) =>");
+                        de_tuple(f, &mut tb, Some(&variant.name), types.len());
+                    }
+                    VariantFields::Named(fields) => {
+                        if let Err(err) = check_fields(fields) {
+                            return err
+                        }
+                        tb.add(", true ) =>");
+                        de_fields(f, &mut tb, &format!("{}::{}", name, variant.name), Some(&variant.name), fields);
+                    }
+                }
+            }
+            tb.add("_ => return std :: result :: Result :: Err (");
+            f.err(&mut tb);
+            tb.add(":: unknown_variant ( * o ,").string(&name).add(", & variant ) ) , } ) } } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
+
+pub fn derive_ser_msgpack_impl(input: TokenStream) -> TokenStream {
+    derive_ser_pack(&MSGPACK, input)
+}
+
+pub fn derive_de_msgpack_impl(input: TokenStream) -> TokenStream {
+    derive_de_pack(&MSGPACK, input)
+}
+
+pub fn derive_ser_cbor_impl(input: TokenStream) -> TokenStream {
+    derive_ser_pack(&CBOR, input)
+}
+
+pub fn derive_de_cbor_impl(input: TokenStream) -> TokenStream {
+    derive_de_pack(&CBOR, input)
+}
@@ -1,16 +1,199 @@
- 
 use proc_macro::{TokenStream};
 use crate::macro_lib::*;
+use crate::attrs::*;
+
+// writes the fields bound by bind_fields, of a struct at depth d
+fn ser_ron_fields(tb: &mut TokenBuilder, d: &str, fields: &[StructField]) {
+    for field in fields {
+        if field.is_skip() {
+            continue
+        }
+        if field.is_flatten() {
+            tb.add("s . ser_flatten (").add(d).add(",").ident(&field.binding()).add(") ;");
+        }
+        else if field.is_option() {
+            tb.add("if let Some ( t ) =").ident(&field.binding()).add("{");
+            tb.add("s . field (").add(d).add("+ 1 ,").string(&field.key()).add(") ;");
+            tb.add("t . ser_ron (").add(d).add("+ 1 , s ) ; s . conl ( ) ; } ;");
+        }
+        else {
+            tb.add("s . field (").add(d).add("+ 1 ,").string(&field.key()).add(") ;");
+            tb.ident(&field.binding()).add(". ser_ron (").add(d).add("+ 1 , s ) ; s . conl ( ) ;");
+        }
+    }
+}
+
+// writes the tuple fields n0, n1, .. as a tuple, or a single one as is
+fn ser_ron_tuple(tb: &mut TokenBuilder, d: &str, len: usize, inline_single: bool) {
+    if inline_single && len == 1 {
+        tb.add("n0 . ser_ron (").add(d).add(", s ) ;");
+        return
+    }
+    tb.add("s . out . push (").chr('(').add(") ;");
+    for i in 0..len {
+        tb.ident(&format!("n{}", i)).add(". ser_ron (").add(d).add(", s ) ;");
+        if i != len - 1 {
+            tb.add("s . out . push_str (").string(", ").add(") ;");
+        }
+    }
+    tb.add("s . out . push (").chr(')').add(") ;");
+}
+
+fn ser_ron_tag(tb: &mut TokenBuilder, tag: &str, key: &str) {
+    tb.add("s . field ( d + 1 ,").string(tag).add(") ;");
+    tb.add("s . label (").string(key).add(") ; s . conl ( ) ;");
+}
+
+fn add_ctor(tb: &mut TokenBuilder, variant: Option<&str>) {
+    tb.add("Self");
+    if let Some(variant) = variant {
+        tb.add("::").ident(variant);
+    }
+}
+
+// reads a struct into the fields of a struct or variant, evaluates to the value
+fn de_ron_fields(tb: &mut TokenBuilder, variant: Option<&str>, fields: &[StructField], container: ContainerAttrs) {
+    let flatten = fields.iter().find( | field | field.is_flatten());
+    tb.add("s . paren_open ( i ) ? ;");
+    for field in fields {
+        if !field.is_skip() && !field.is_flatten() {
+            tb.add("let mut").ident(&field.binding()).add("= None ;");
+        }
+    }
+    if let Some(field) = flatten {
+        tb.add("let mut").ident(&field.binding()).add("= makepad_microserde :: SerRonState :: default ( ) ;");
+        tb.ident(&field.binding()).add(". st_pre ( ) ;");
+    }
+    tb.add("while let Some ( _ ) = s . next_ident ( ) {");
+    tb.add("match s . identbuf . as_ref ( ) {");
+    for field in fields {
+        if field.is_flatten() {
+            continue
+        }
+        tb.string(&field.key()).add("=> { s . next_colon ( i ) ? ;");
+        if field.is_skip() {
+            tb.add("s . skip_value ( i ) ? ; } ,");
+        }
+        else {
+            tb.ident(&field.binding()).add("= Some ( DeRon :: de_ron ( s , i ) . map_err ( | e | e . at_key (").string(&field.key()).add(") ) ? ) ; } ,");
+        }
+    }
+    if let Some(field) = flatten {
+        tb.add("_ => { s . capture_key ( & mut").ident(&field.binding()).add(") ; s . next_colon ( i ) ? ;");
+        tb.add("s . capture_value ( & mut").ident(&field.binding()).add(", i ) ? ;");
+        tb.ident(&field.binding()).add(". conl ( ) ; }");
+    }
+    else if container.ignore_unknown {
+        tb.add("_ => { s . next_colon ( i ) ? ; s . skip_value ( i ) ? ; }");
+    }
+    else {
+        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) )");
+    }
+    tb.add("} ; s . eat_comma_paren ( i ) ? ;");
+    tb.add("} ; s . paren_close ( i ) ? ;");
+    if let Some(field) = flatten {
+        tb.ident(&field.binding()).add(". out . push (").chr(')').add(") ;");
+    }
+    if container.default {
+        tb.add("let _default : Self = std :: default :: Default :: default ( ) ;");
+    }
+
+    add_ctor(tb, variant);
+    tb.add("{");
+    for field in fields {
+        tb.ident(&field.name).add(":");
+        if field.is_skip() {
+            missing_field(tb, field, container);
+        }
+        else if field.is_flatten() {
+            tb.add("s . de_captured ( &").ident(&field.binding()).add(". out , | s , i | DeRon :: de_ron ( s , i ) ) ?");
+        }
+        else {
+            tb.add("if let Some ( t ) =").ident(&field.binding()).add("{ t } else {");
+            missing_field(tb, field, container);
+            tb.add("}");
+        }
+        tb.add(",");
+    }
+    tb.add("}");
+}
+
+// reads a tuple into the fields of a tuple struct or variant, or a single one as is
+fn de_ron_tuple(tb: &mut TokenBuilder, variant: Option<&str>, len: usize, inline_single: bool) {
+    if inline_single && len == 1 {
+        add_ctor(tb, variant);
+        tb.add("( DeRon :: de_ron ( s , i ) ? )");
+        return
+    }
+    tb.add("s . paren_open ( i ) ? ;");
+    tb.add("let r =");
+    add_ctor(tb, variant);
+    tb.add("(");
+    for index in 0..len {
+        tb.add("{ let r = DeRon :: de_ron ( s , i ) . map_err ( | e | e . at_index (").unsuf_usize(index).add(") ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
+    }
+    tb.add(") ;");
+    tb.add("s . paren_close ( i ) ? ; r");
+}
+
+// reads the content of a variant that was already identified by its tag
+fn de_ron_content(tb: &mut TokenBuilder, variant: &EnumVariant, inline_single: bool, container: ContainerAttrs) {
+    match &variant.fields {
+        VariantFields::Tuple(types) => de_ron_tuple(tb, Some(&variant.name), types.len(), inline_single),
+        VariantFields::Named(fields) => de_ron_fields(tb, Some(&variant.name), fields, container),
+        VariantFields::Unit => {
+            tb.add("s . paren_open ( i ) ? ; s . paren_close ( i ) ? ; Self ::").ident(&variant.name);
+        }
+    }
+}
+
+// reads the tag of an internally or adjacently tagged enum into _tag, and the rest into _c
+fn de_ron_tag(tb: &mut TokenBuilder, tag: &str, content: Option<&str>) {
+    tb.add("s . paren_open ( i ) ? ;");
+    tb.add("let mut _tag = None ;");
+    if content.is_some() {
+        tb.add("let mut _c = None ;");
+    }
+    else {
+        tb.add("let mut _c = makepad_microserde :: SerRonState :: default ( ) ; _c . st_pre ( ) ;");
+    }
+    tb.add("while let Some ( _ ) = s . next_ident ( ) {");
+    tb.add("if s . identbuf ==").string(tag).add("{");
+    tb.add("s . next_colon ( i ) ? ; _tag = Some ( s . as_string ( ) ? ) ; s . next_tok ( i ) ? ;");
+    tb.add("}");
+    if let Some(content) = content {
+        tb.add("else if s . identbuf ==").string(content).add("{");
+        tb.add("s . next_colon ( i ) ? ;");
+        tb.add("let mut c = makepad_microserde :: SerRonState :: default ( ) ;");
+        tb.add("s . capture_value ( & mut c , i ) ? ; _c = Some ( c . out ) ;");
+        tb.add("}");
+        tb.add("else { return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) ) }");
+    }
+    else {
+        tb.add("else {");
+        tb.add("s . capture_key ( & mut _c ) ; s . next_colon ( i ) ? ;");
+        tb.add("s . capture_value ( & mut _c , i ) ? ; _c . conl ( ) ;");
+        tb.add("}");
+    }
+    tb.add("s . eat_comma_paren ( i ) ? ;");
+    tb.add("} s . paren_close ( i ) ? ;");
+    if content.is_none() {
+        tb.add("_c . out . push (").chr(')').add(") ;");
+    }
+    tb.add("let _tag : String = if let Some ( t ) = _tag { t } else {");
+    tb.add("return std :: result :: Result :: Err ( s . err_nf (").string(tag).add(") ) } ;");
+}
 
 pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
 
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
-    
-    parser.eat_ident("pub");
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
-            
+
             let generic = parser.eat_generic();
             let types = parser.eat_all_types();
             let where_clause = parser.eat_where_clause(Some("SerRon"));
@@ -18,7 +201,7 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
             tb.add("impl").stream(generic.clone());
             tb.add("SerRon for").ident(&name).stream(generic).stream(where_clause);
             tb.add("{ fn ser_ron ( & self , d : usize , s : & mut makepad_microserde :: SerRonState ) {");
-            
+
             if let Some(types) = types{
                 tb.add("s . out . push (").chr('(').add(") ;");
                 for i in 0..types.len(){
@@ -29,20 +212,15 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
                 }
                 tb.add("s . out . push (").chr(')').add(") ;");
             }
-            else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                tb.add("s . st_pre ( ) ;");
-                // named struct
-                for (field,ty) in fields{
-                    if ty.into_iter().next().unwrap().to_string() == "Option"{
-                        tb.add("if let Some ( t ) = ").add("& self .").ident(&field).add("{");
-                        tb.add("s . field ( d + 1 ,").string(&field).add(") ;");
-                        tb.add("t . ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
-                    }
-                    else{
-                        tb.add("s . field ( d + 1 ,").string(&field).add(" ) ;");
-                        tb.add("self .").ident(&field).add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
-                    }
+            else if let Some(fields) = parser.eat_all_struct_fields(){
+                if let Err(err) = check_fields(&fields){
+                    return err
                 }
+                tb.add("let Self");
+                bind_fields(&mut tb, &fields);
+                tb.add("= self ;");
+                tb.add("s . st_pre ( ) ;");
+                ser_ron_fields(&mut tb, "d", &fields);
                 tb.add("s . st_post ( d ) ;");
             }
             else{
@@ -56,72 +234,108 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
             let where_clause = parser.eat_where_clause(Some("SerRon"));
+            let repr = match enum_repr(&attrs){
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
+            }
+            else{
+                return parser.unexpected()
+            };
+            if let Err(err) = check_variants(&variants, &repr){
+                return err
+            }
 
             tb.add("impl").stream(generic.clone());
             tb.add("SerRon for").ident(&name).stream(generic).stream(where_clause);
             tb.add("{ fn ser_ron ( & self , d : usize , s : & mut makepad_microserde :: SerRonState ) {");
             tb.add("match self {");
-            
-            if !parser.open_brace(){
-                return parser.unexpected()
-            }
 
-            while !parser.eat_eot(){
-                // parse ident
-                if let Some(variant) = parser.eat_any_ident(){
-                    if let Some(types) = parser.eat_all_types(){
-                        
-                        tb.add("Self ::").ident(&variant).add("(");
+            for variant in &variants{
+                let key = variant.key();
+                match &variant.fields{
+                    VariantFields::Tuple(types) => {
+                        tb.add("Self ::").ident(&variant.name).add("(");
                         for i in 0..types.len(){
                             tb.ident(&format!("n{}", i)).add(",");
                         }
                         tb.add(") => {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
-                        tb.add("s . out . push (").chr('(').add(") ;");
-                        
-                        for i in 0..types.len(){
-                            tb.ident(&format!("n{}", i)).add(". ser_ron ( d , s ) ;");
-                            if i != types.len() - 1{
-                                tb.add("s . out . push_str (").string(", ").add(") ;");
+                        match &repr{
+                            EnumRepr::External => {
+                                tb.add("s . out . push_str (").string(&key).add(") ;");
+                                ser_ron_tuple(&mut tb, "d", types.len(), false);
+                            }
+                            EnumRepr::Internal(tag) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_tag(&mut tb, tag, &key);
+                                tb.add("s . ser_flatten ( d , n0 ) ;");
+                                tb.add("s . st_post ( d ) ;");
+                            }
+                            EnumRepr::Adjacent(tag, content) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_tag(&mut tb, tag, &key);
+                                tb.add("s . field ( d + 1 ,").string(content).add(") ;");
+                                ser_ron_tuple(&mut tb, "d + 1", types.len(), true);
+                                tb.add("s . conl ( ) ; s . st_post ( d ) ;");
+                            }
+                            EnumRepr::Untagged => {
+                                ser_ron_tuple(&mut tb, "d", types.len(), true);
                             }
                         }
-                        tb.add("s . out . push (").chr(')').add(") ;");
                         tb.add("}");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for (field, _ty) in fields.iter(){
-                            tb.ident(field).add(",");
-                        }
-                        tb.add("} => {");
-                        
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
-                        tb.add("s . st_pre ( ) ;");
-                        
-                        for (field, ty) in fields{
-                            if ty.into_iter().next().unwrap().to_string() == "Option"{
-                                tb.add("if ").ident(&field).add(". is_some ( ) {");
-                                tb.add("s . field ( d + 1 ,").string(&field).add(") ;");
-                                tb.ident(&field).add(" . ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
+                    VariantFields::Named(fields) => {
+                        tb.add("Self ::").ident(&variant.name);
+                        bind_fields(&mut tb, fields);
+                        tb.add("=> {");
+                        match &repr{
+                            EnumRepr::External => {
+                                tb.add("s . out . push_str (").string(&key).add(") ;");
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_fields(&mut tb, "d", fields);
+                                tb.add("s . st_post ( d ) ;");
+                            }
+                            EnumRepr::Internal(tag) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_tag(&mut tb, tag, &key);
+                                ser_ron_fields(&mut tb, "d", fields);
+                                tb.add("s . st_post ( d ) ;");
                             }
-                            else{
-                                tb.add("s . field ( d + 1 ,").string(&field).add(" ) ;");
-                                tb.ident(&field).add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
+                            EnumRepr::Adjacent(tag, content) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_tag(&mut tb, tag, &key);
+                                tb.add("s . field ( d + 1 ,").string(content).add(") ;");
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_fields(&mut tb, "d + 1", fields);
+                                tb.add("s . st_post ( d + 1 ) ; s . conl ( ) ; s . st_post ( d ) ;");
+                            }
+                            EnumRepr::Untagged => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_fields(&mut tb, "d", fields);
+                                tb.add("s . st_post ( d ) ;");
                             }
                         }
-                        tb.add("s . st_post ( d ) ; }");
-                    }
-                    else if parser.is_punct(',') || parser.is_eot(){ // bare variant
-                        tb.add("Self ::").ident(&variant).add("=> {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ; }");
+                        tb.add("}");
                     }
-                    else{
-                        return parser.unexpected();
+                    VariantFields::Unit => {
+                        tb.add("Self ::").ident(&variant.name).add("=> {");
+                        match &repr{
+                            EnumRepr::External => {
+                                tb.add("s . out . push_str (").string(&key).add(") ;");
+                            }
+                            EnumRepr::Internal(tag) | EnumRepr::Adjacent(tag, _) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_ron_tag(&mut tb, tag, &key);
+                                tb.add("s . st_post ( d ) ;");
+                            }
+                            EnumRepr::Untagged => {
+                                tb.add("s . out . push_str (").string("()").add(") ;");
+                            }
+                        }
+                        tb.add("}");
                     }
-                    parser.eat_punct(',');
-                }
-                else{
-                    return parser.unexpected()
                 }
             }
             tb.add("}");
@@ -135,143 +349,148 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
 pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
-    parser.eat_ident("pub");
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
             let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("DeRon"));
+            let where_clause = parser.eat_where_clause(Some("DeRon < 'de >"));
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeRon for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_ron ( s : &  mut makepad_microserde :: DeRonState , i : & mut std :: str :: Chars )");
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeRon < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn de_ron ( s : &  mut makepad_microserde :: DeRonState , i : & mut std :: str :: Chars < 'de > )");
             tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeRonErr > { ");
 
             if let Some(types) = types{
-                tb.add("s . paren_open ( i ) ? ;");
-                tb.add("let r = Self");
-                tb.add("(");
-                for _ in 0..types.len(){
-                     tb.add("{ let r = DeRon :: de_ron ( s , i ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
-                }
-                tb.add(") ;");
-                tb.add("s . paren_close ( i ) ? ;");
-                tb.add("std :: result :: Result :: Ok ( r ) ;");
+                tb.add("std :: result :: Result :: Ok ( {");
+                de_ron_tuple(&mut tb, None, types.len(), false);
+                tb.add("} )");
             }
-            else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                tb.add("s . paren_open ( i ) ? ;");
-                for (field,_ty) in &fields{
-                    tb.add("let mut").ident(&format!("_{}",field)).add("= None ;");
-                }
-                tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                tb.add("match s . identbuf . as_ref ( ) {");
-                for (field,_ty) in &fields{
-                    tb.string(&field).add("=> { s . next_colon ( i ) ? ;");
-                    tb.ident(&format!("_{}",field)).add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                }
-                tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) )");
-                tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                tb.add("} ; s . paren_close ( i ) ? ;");
-                
-                tb.add("std :: result :: Result :: Ok ( Self {");
-                for (field,ty) in fields{
-                    tb.ident(&field).add(":");
-                    if ty.into_iter().next().unwrap().to_string() == "Option"{
-                        tb.add("if let Some ( t ) =").ident(&format!("_{}",field));
-                        tb.add("{ t } else { None } ,");
-                    }
-                    else{
-                        tb.add("if let Some ( t ) =").ident(&format!("_{}",field));
-                        tb.add("{ t } else { return Err ( s . err_nf (");
-                        tb.string(&field).add(") ) } ,");
-                    }
+            else if let Some(fields) = parser.eat_all_struct_fields(){
+                if let Err(err) = check_fields(&fields){
+                    return err
                 }
+                let container = match container_attrs(&attrs, true){
+                    Ok(container) => container,
+                    Err(err) => return err
+                };
+                tb.add("std :: result :: Result :: Ok ( {");
+                de_ron_fields(&mut tb, None, &fields, container);
                 tb.add("} )");
             }
             else{
                 return parser.unexpected()
             }
-            tb.add("} } ;"); 
+            tb.add("} } ;");
             return tb.end();
         }
     }
     else if parser.eat_ident("enum"){
-         
+
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("DeRon"));
-
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeRon for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_ron ( s : & mut makepad_microserde :: DeRonState , i : & mut std :: str :: Chars )");
-            tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeRonErr > { ");
-            tb.add("s . ident ( i ) ? ;");
-            tb.add("std :: result :: Result :: Ok ( match s . identbuf . as_ref ( ) {");
-            
-            if !parser.open_brace(){
+            let where_clause = parser.eat_where_clause(Some("DeRon < 'de >"));
+            let repr = match enum_repr(&attrs){
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
+            }
+            else{
                 return parser.unexpected()
+            };
+            if let Err(err) = check_variants(&variants, &repr){
+                return err
             }
-            while !parser.eat_eot(){
-                // parse ident
-                if let Some(variant) = parser.eat_any_ident(){
-                    tb.string(&variant).add("=> {");
-                    if let Some(types) = parser.eat_all_types(){
-                        
-                        tb.add("s . paren_open ( i ) ? ;");
-                        tb.add("let r = Self ::").ident(&variant).add("(");
-                        for _ in 0..types.len(){
-                            tb.add("{ let r = DeRon :: de_ron ( s , i ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
+            let container = match container_attrs(&attrs, false){
+                Ok(container) => container,
+                Err(err) => return err
+            };
+
+            let (impl_generic, where_clause) = de_lifetime_generics(generic.clone(), where_clause);
+            tb.add("impl").stream(Some(impl_generic));
+            tb.add("DeRon < 'de > for").ident(&name).stream(generic).stream(Some(where_clause));
+            tb.add("{ fn de_ron ( s : & mut makepad_microserde :: DeRonState , i : & mut std :: str :: Chars < 'de > )");
+            tb.add("-> std :: result :: Result < Self , makepad_microserde :: DeRonErr > { ");
+
+            match &repr{
+                EnumRepr::External => {
+                    tb.add("s . ident ( i ) ? ;");
+                    tb.add("std :: result :: Result :: Ok ( match s . identbuf . as_ref ( ) {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=> {");
+                        match &variant.fields{
+                            VariantFields::Unit => {
+                                tb.add("Self ::").ident(&variant.name);
+                            }
+                            _ => de_ron_content(&mut tb, variant, false, container)
                         }
-                        tb.add(") ;");
-                        tb.add("s . paren_close ( i ) ? ; r");
+                        tb.add("}");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("s . paren_open ( i ) ? ;");
-                        for (field,_ty) in &fields{
-                            tb.add("let mut").ident(&format!("_{}",field)).add("= None ;");
-                        }
-                        tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                        tb.add("match s . identbuf . as_ref ( ) {");
-                        for (field,_ty) in &fields{
-                            tb.string(&field).add("=> { s . next_colon ( i ) ? ;");
-                            tb.ident(&format!("_{}",field)).add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                        }
-                        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-                        tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                        tb.add("} ; s . paren_close ( i ) ? ;");
-                        
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for (field,ty) in fields{
-                            tb.ident(&field).add(":");
-                            if ty.into_iter().next().unwrap().to_string() == "Option"{
-                                tb.add("if let Some ( t ) =").ident(&format!("_{}",field));
-                                tb.add("{ t } else { None } ,");
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & s . identbuf ) )");
+                    tb.add("} )");
+                }
+                EnumRepr::Internal(tag) => {
+                    de_ron_tag(&mut tb, tag, None);
+                    tb.add("std :: result :: Result :: Ok ( match _tag . as_ref ( ) {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=>");
+                        match &variant.fields{
+                            VariantFields::Tuple(_) => {
+                                tb.add("Self ::").ident(&variant.name);
+                                tb.add("( s . de_captured ( & _c . out , | s , i | DeRon :: de_ron ( s , i ) ) ? ) ,");
                             }
-                            else{
-                                tb.add("if let Some ( t ) =").ident(&format!("_{}",field));
-                                tb.add("{ t } else { return Err ( s . err_nf (");
-                                tb.string(&field).add(") ) } ,");
+                            VariantFields::Named(fields) => {
+                                tb.add("s . de_captured ( & _c . out , | s , i | std :: result :: Result :: Ok ( {");
+                                de_ron_fields(&mut tb, Some(&variant.name), fields, container);
+                                tb.add("} ) ) ? ,");
+                            }
+                            VariantFields::Unit => {
+                                tb.add("Self ::").ident(&variant.name).add(",");
                             }
                         }
-                        tb.add("}");
-                    }
-                    else if parser.is_punct(',') || parser.is_eot(){ // bare variant
-                        tb.add("Self ::").ident(&variant);
                     }
-                    else{
-                        return parser.unexpected();
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & _tag ) )");
+                    tb.add("} )");
+                }
+                EnumRepr::Adjacent(tag, content) => {
+                    de_ron_tag(&mut tb, tag, Some(content));
+                    tb.add("std :: result :: Result :: Ok ( match _tag . as_ref ( ) {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=>");
+                        match &variant.fields{
+                            VariantFields::Unit => {
+                                tb.add("Self ::").ident(&variant.name).add(",");
+                            }
+                            _ => {
+                                tb.add("{ let _c : String = if let Some ( c ) = _c { c } else {");
+                                tb.add("return std :: result :: Result :: Err ( s . err_nf (").string(content).add(") ) } ;");
+                                tb.add("s . de_captured ( & _c , | s , i | std :: result :: Result :: Ok ( {");
+                                de_ron_content(&mut tb, variant, true, container);
+                                tb.add("} ) ) ? } ,");
+                            }
+                        }
                     }
-                    
-                    tb.add("}");
-                    parser.eat_punct(',');
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & _tag ) )");
+                    tb.add("} )");
                 }
-                else{
-                    return parser.unexpected()
+                EnumRepr::Untagged => {
+                    tb.add("let mut _c = makepad_microserde :: SerRonState :: default ( ) ;");
+                    tb.add("s . capture_value ( & mut _c , i ) ? ;");
+                    for variant in &variants{
+                        tb.add("if let Ok ( r ) = s . de_captured ( & _c . out , | s , i | std :: result :: Result :: Ok ( {");
+                        de_ron_content(&mut tb, variant, true, container);
+                        tb.add("} ) ) { return std :: result :: Result :: Ok ( r ) }");
+                    }
+                    tb.add("std :: result :: Result :: Err ( s . err_untagged (").string(&name).add(") )");
                 }
-            } 
-            tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & s . identbuf ) )");
-            tb.add("} ) } }");
-           return tb.end();
+            }
+            tb.add("} }");
+            return tb.end();
         }
     }
     return parser.unexpected()
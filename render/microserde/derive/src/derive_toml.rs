@@ -0,0 +1,412 @@
+use proc_macro::{TokenStream};
+use crate::macro_lib::*;
+use crate::attrs::*;
+
+// inserts the fields bound by bind_fields into the table t
+fn ser_toml_fields(tb: &mut TokenBuilder, fields: &[StructField]) {
+    for field in fields {
+        if field.is_skip() {
+            continue
+        }
+        if field.is_flatten() {
+            tb.add("t . flatten (").ident(&field.binding()).add(". ser_toml ( ) ) ;");
+        }
+        else if field.is_option() {
+            tb.add("if let Some ( v ) =").ident(&field.binding()).add("{");
+            tb.add("t . insert (").string(&field.key()).add(". to_string ( ) , v . ser_toml ( ) ) ; }");
+        }
+        else {
+            tb.add("t . insert (").string(&field.key()).add(". to_string ( ) ,");
+            tb.ident(&field.binding()).add(". ser_toml ( ) ) ;");
+        }
+    }
+}
+
+fn ser_toml_table(tb: &mut TokenBuilder, fields: &[StructField]) {
+    tb.add("{ let mut t = makepad_microserde :: TomlTable :: default ( ) ;");
+    ser_toml_fields(tb, fields);
+    tb.add("makepad_microserde :: Toml :: Table ( t ) }");
+}
+
+// the tuple fields n0, n1, .. as an array, or a single one as is
+fn ser_toml_tuple(tb: &mut TokenBuilder, len: usize, inline_single: bool) {
+    if inline_single && len == 1 {
+        tb.add("n0 . ser_toml ( )");
+        return
+    }
+    tb.add("makepad_microserde :: Toml :: Array ( vec ! [");
+    for i in 0..len {
+        tb.ident(&format!("n{}", i)).add(". ser_toml ( ) ,");
+    }
+    tb.add("] )");
+}
+
+fn ser_toml_tag(tb: &mut TokenBuilder, tag: &str, key: &str) {
+    tb.add("t . insert (").string(tag).add(". to_string ( ) ,");
+    tb.add("makepad_microserde :: Toml :: Str (").string(key).add(". to_string ( ) ) ) ;");
+}
+
+fn add_ctor(tb: &mut TokenBuilder, variant: Option<&str>) {
+    tb.add("Self");
+    if let Some(variant) = variant {
+        tb.add("::").ident(variant);
+    }
+}
+
+// reads the table in the Toml value input into the fields of a struct or variant,
+// the keys in known are allowed next to the fields
+fn de_toml_fields(tb: &mut TokenBuilder, variant: Option<&str>, fields: &[StructField], input: &str, known: Option<&str>, container: ContainerAttrs) {
+    let flatten = fields.iter().find( | field | field.is_flatten());
+    tb.add("{ let t = s . table (").add(input).add(") ? ;");
+    if flatten.is_some() {
+        tb.add("let mut _rest = makepad_microserde :: TomlTable :: default ( ) ;");
+    }
+    tb.add("for ( k , _v ) in t . iter ( ) { match k . as_str ( ) {");
+    let mut keys: Vec<String> = fields.iter().filter( | field | !field.is_flatten()).map( | field | field.key()).collect();
+    keys.extend(known.map( | key | key.to_string()));
+    for key in keys {
+        tb.string(&key).add("=> ( ) ,");
+    }
+    if flatten.is_some() {
+        tb.add("_ => { _rest . insert ( k . clone ( ) , _v . clone ( ) ) ; }");
+    }
+    else if container.ignore_unknown {
+        tb.add("_ => ( )");
+    }
+    else {
+        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( k ) )");
+    }
+    tb.add("} }");
+    if container.default {
+        tb.add("let _default : Self = std :: default :: Default :: default ( ) ;");
+    }
+    add_ctor(tb, variant);
+    tb.add("{");
+    for field in fields {
+        tb.ident(&field.name).add(":");
+        if field.is_skip() {
+            missing_field(tb, field, container);
+        }
+        else if field.is_flatten() {
+            tb.add("DeToml :: de_toml ( s , & makepad_microserde :: Toml :: Table ( _rest ) ) ?");
+        }
+        else {
+            tb.add("match t . get (").string(&field.key()).add(") {");
+            tb.add("Some ( v ) => s . de_field (").string(&field.key()).add(", v ) ? ,");
+            tb.add("None => {");
+            missing_field(tb, field, container);
+            tb.add("} }");
+        }
+        tb.add(",");
+    }
+    tb.add("} }");
+}
+
+// reads an array in input into the fields of a tuple struct or variant, or a single one as is
+fn de_toml_tuple(tb: &mut TokenBuilder, variant: Option<&str>, len: usize, input: &str, inline_single: bool) {
+    if inline_single && len == 1 {
+        add_ctor(tb, variant);
+        tb.add("( DeToml :: de_toml ( s ,").add(input).add(") ? )");
+        return
+    }
+    tb.add("{ let a = s . array_n (").add(input).add(",").unsuf_usize(len).add(") ? ;");
+    add_ctor(tb, variant);
+    tb.add("(");
+    for i in 0..len {
+        tb.add("s . de_field (").string(&i.to_string()).add(", & a [").unsuf_usize(i).add("] ) ? ,");
+    }
+    tb.add(") }");
+}
+
+pub fn derive_ser_toml_impl(input: TokenStream) -> TokenStream {
+
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
+    if parser.eat_ident("struct"){
+        if let Some(name) = parser.eat_any_ident(){
+
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some("SerToml"));
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("SerToml for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn ser_toml ( & self ) -> makepad_microserde :: Toml {");
+
+            if let Some(types) = types{
+                tb.add("makepad_microserde :: Toml :: Array ( vec ! [");
+                for i in 0..types.len(){
+                    tb.add("self .").unsuf_usize(i).add(". ser_toml ( ) ,");
+                }
+                tb.add("] )");
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields(){
+                if let Err(err) = check_fields(&fields){
+                    return err
+                }
+                tb.add("let Self");
+                bind_fields(&mut tb, &fields);
+                tb.add("= self ;");
+                ser_toml_table(&mut tb, &fields);
+            }
+            else{
+                return parser.unexpected()
+            }
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum"){
+        if let Some(name) = parser.eat_any_ident(){
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some("SerToml"));
+            let repr = match enum_repr(&attrs){
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
+            }
+            else{
+                return parser.unexpected()
+            };
+            if let Err(err) = check_variants(&variants, &repr){
+                return err
+            }
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("SerToml for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn ser_toml ( & self ) -> makepad_microserde :: Toml {");
+            tb.add("match self {");
+
+            for variant in &variants{
+                let key = variant.key();
+                tb.add("Self ::").ident(&variant.name);
+                match &variant.fields{
+                    VariantFields::Tuple(types) => {
+                        tb.add("(");
+                        for i in 0..types.len(){
+                            tb.ident(&format!("n{}", i)).add(",");
+                        }
+                        tb.add(")");
+                    }
+                    VariantFields::Named(fields) => bind_fields(&mut tb, fields),
+                    VariantFields::Unit => ()
+                }
+                tb.add("=> {");
+                if let EnumRepr::Untagged = repr{
+                    match &variant.fields{
+                        VariantFields::Tuple(types) => ser_toml_tuple(&mut tb, types.len(), true),
+                        VariantFields::Named(fields) => ser_toml_table(&mut tb, fields),
+                        VariantFields::Unit => {
+                            tb.add("makepad_microserde :: Toml :: InlineTable ( makepad_microserde :: TomlTable :: default ( ) )");
+                        }
+                    }
+                    tb.add("}");
+                    continue
+                }
+                tb.add("let mut t = makepad_microserde :: TomlTable :: default ( ) ;");
+                match &repr{
+                    EnumRepr::External => {
+                        tb.add("t . insert (").string(&key).add(". to_string ( ) ,");
+                        match &variant.fields{
+                            VariantFields::Tuple(types) => ser_toml_tuple(&mut tb, types.len(), false),
+                            VariantFields::Named(fields) => ser_toml_table(&mut tb, fields),
+                            VariantFields::Unit => {
+                                tb.add("makepad_microserde :: Toml :: Array ( vec ! [ ] )");
+                            }
+                        }
+                        tb.add(") ;");
+                    }
+                    EnumRepr::Internal(tag) => {
+                        ser_toml_tag(&mut tb, tag, &key);
+                        match &variant.fields{
+                            VariantFields::Tuple(_) => {
+                                tb.add("t . flatten ( n0 . ser_toml ( ) ) ;");
+                            }
+                            VariantFields::Named(fields) => ser_toml_fields(&mut tb, fields),
+                            VariantFields::Unit => ()
+                        }
+                    }
+                    EnumRepr::Adjacent(tag, content) => {
+                        ser_toml_tag(&mut tb, tag, &key);
+                        match &variant.fields{
+                            VariantFields::Tuple(types) => {
+                                tb.add("t . insert (").string(content).add(". to_string ( ) ,");
+                                ser_toml_tuple(&mut tb, types.len(), true);
+                                tb.add(") ;");
+                            }
+                            VariantFields::Named(fields) => {
+                                tb.add("t . insert (").string(content).add(". to_string ( ) ,");
+                                ser_toml_table(&mut tb, fields);
+                                tb.add(") ;");
+                            }
+                            VariantFields::Unit => ()
+                        }
+                    }
+                    EnumRepr::Untagged => ()
+                }
+                tb.add("makepad_microserde :: Toml :: Table ( t ) }");
+            }
+            tb.add("}");
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
+
+pub fn derive_de_toml_impl(input: TokenStream) -> TokenStream {
+    let mut parser = TokenParser::new(input);
+    let mut tb = TokenBuilder::new();
+
+    let attrs = parser.eat_attributes();
+    parser.eat_visibility();
+    if parser.eat_ident("struct"){
+        if let Some(name) = parser.eat_any_ident(){
+            let generic = parser.eat_generic();
+            let types = parser.eat_all_types();
+            let where_clause = parser.eat_where_clause(Some("DeToml"));
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("DeToml for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn de_toml ( s : & mut makepad_microserde :: DeTomlState , t : & makepad_microserde :: Toml )");
+            tb.add("-> std :: result :: Result < Self , makepad_microserde :: TomlErr > {");
+
+            if let Some(types) = types{
+                tb.add("std :: result :: Result :: Ok (");
+                de_toml_tuple(&mut tb, None, types.len(), "t", false);
+                tb.add(")");
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields(){
+                if let Err(err) = check_fields(&fields){
+                    return err
+                }
+                let container = match container_attrs(&attrs, true){
+                    Ok(container) => container,
+                    Err(err) => return err
+                };
+                tb.add("std :: result :: Result :: Ok (");
+                de_toml_fields(&mut tb, None, &fields, "t", None, container);
+                tb.add(")");
+            }
+            else{
+                return parser.unexpected()
+            }
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    else if parser.eat_ident("enum"){
+
+        if let Some(name) = parser.eat_any_ident(){
+            let generic = parser.eat_generic();
+            let where_clause = parser.eat_where_clause(Some("DeToml"));
+            let repr = match enum_repr(&attrs){
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = if let Some(variants) = parser.eat_all_enum_variants(){
+                variants
+            }
+            else{
+                return parser.unexpected()
+            };
+            if let Err(err) = check_variants(&variants, &repr){
+                return err
+            }
+            let container = match container_attrs(&attrs, false){
+                Ok(container) => container,
+                Err(err) => return err
+            };
+
+            tb.add("impl").stream(generic.clone());
+            tb.add("DeToml for").ident(&name).stream(generic).stream(where_clause);
+            tb.add("{ fn de_toml ( s : & mut makepad_microserde :: DeTomlState , t : & makepad_microserde :: Toml )");
+            tb.add("-> std :: result :: Result < Self , makepad_microserde :: TomlErr > {");
+
+            match &repr{
+                EnumRepr::External => {
+                    tb.add("let ( k , v ) = s . variant ( t ) ? ;");
+                    tb.add("std :: result :: Result :: Ok ( match k {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=>");
+                        match &variant.fields{
+                            VariantFields::Tuple(types) => de_toml_tuple(&mut tb, Some(&variant.name), types.len(), "v", false),
+                            VariantFields::Named(fields) => de_toml_fields(&mut tb, Some(&variant.name), fields, "v", None, container),
+                            VariantFields::Unit => {
+                                tb.add("{ s . array_n ( v , 0 ) ? ; Self ::").ident(&variant.name).add("}");
+                            }
+                        }
+                        tb.add(",");
+                    }
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( k ) ) } )");
+                }
+                EnumRepr::Internal(tag) => {
+                    tb.add("let k = s . tag ( t ,").string(tag).add(") ? ;");
+                    tb.add("std :: result :: Result :: Ok ( match k {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=>");
+                        match &variant.fields{
+                            VariantFields::Tuple(_) => {
+                                tb.add("{ let mut r = s . table ( t ) ? . clone ( ) ; r . remove (").string(tag).add(") ;");
+                                tb.add("Self ::").ident(&variant.name);
+                                tb.add("( DeToml :: de_toml ( s , & makepad_microserde :: Toml :: Table ( r ) ) ? ) }");
+                            }
+                            VariantFields::Named(fields) => de_toml_fields(&mut tb, Some(&variant.name), fields, "t", Some(tag), container),
+                            VariantFields::Unit => {
+                                tb.add("Self ::").ident(&variant.name);
+                            }
+                        }
+                        tb.add(",");
+                    }
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( k ) ) } )");
+                }
+                EnumRepr::Adjacent(tag, content) => {
+                    tb.add("let k = s . tag ( t ,").string(tag).add(") ? ;");
+                    tb.add("std :: result :: Result :: Ok ( match k {");
+                    for variant in &variants{
+                        tb.string(&variant.key()).add("=>");
+                        match &variant.fields{
+                            VariantFields::Tuple(types) => {
+                                tb.add("{ let c = s . content ( t ,").string(content).add(") ? ;");
+                                de_toml_tuple(&mut tb, Some(&variant.name), types.len(), "c", true);
+                                tb.add("}");
+                            }
+                            VariantFields::Named(fields) => {
+                                tb.add("{ let c = s . content ( t ,").string(content).add(") ? ;");
+                                de_toml_fields(&mut tb, Some(&variant.name), fields, "c", None, container);
+                                tb.add("}");
+                            }
+                            VariantFields::Unit => {
+                                tb.add("Self ::").ident(&variant.name);
+                            }
+                        }
+                        tb.add(",");
+                    }
+                    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( k ) ) } )");
+                }
+                EnumRepr::Untagged => {
+                    for variant in &variants{
+                        tb.add("if let Some ( r ) = s . de_try ( | s | std :: result :: Result :: Ok (");
+                        match &variant.fields{
+                            VariantFields::Tuple(types) => de_toml_tuple(&mut tb, Some(&variant.name), types.len(), "t", true),
+                            VariantFields::Named(fields) => de_toml_fields(&mut tb, Some(&variant.name), fields, "t", None, container),
+                            VariantFields::Unit => {
+                                tb.add("{ s . unit ( t ) ? ; Self ::").ident(&variant.name).add("}");
+                            }
+                        }
+                        tb.add(") ) { return std :: result :: Result :: Ok ( r ) }");
+                    }
+                    tb.add("std :: result :: Result :: Err ( s . err_untagged (").string(&name).add(") )");
+                }
+            }
+            tb.add("} } ;");
+            return tb.end();
+        }
+    }
+    return parser.unexpected()
+}
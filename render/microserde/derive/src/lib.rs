@@ -2,44 +2,93 @@ extern crate proc_macro;
 use proc_macro::{TokenStream};
 
 mod macro_lib; 
+mod attrs;
 
 mod derive_bin;
 use crate::derive_bin::*;
 
+mod derive_bin_tagged;
+use crate::derive_bin_tagged::*;
+
+mod derive_pack;
+use crate::derive_pack::*;
+
 mod derive_ron;
 use crate::derive_ron::*;
 
 mod derive_json;
 use crate::derive_json::*;
 
-#[proc_macro_derive(SerBin)]
+mod derive_toml;
+use crate::derive_toml::*;
+
+#[proc_macro_derive(SerBin, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_ser_bin(input: TokenStream) -> TokenStream {
     derive_ser_bin_impl(input)
 }
 
-#[proc_macro_derive(DeBin)]
+#[proc_macro_derive(DeBin, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_de_bin(input: TokenStream) -> TokenStream {
     derive_de_bin_impl(input)
 }
 
-#[proc_macro_derive(SerJson)]
+#[proc_macro_derive(SerBinTagged, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown, id, version, min_version))]
+pub fn derive_ser_bin_tagged(input: TokenStream) -> TokenStream {
+    derive_ser_bin_tagged_impl(input)
+}
+
+#[proc_macro_derive(DeBinTagged, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown, id, version, min_version))]
+pub fn derive_de_bin_tagged(input: TokenStream) -> TokenStream {
+    derive_de_bin_tagged_impl(input)
+}
+
+#[proc_macro_derive(SerMsgPack, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_ser_msgpack(input: TokenStream) -> TokenStream {
+    derive_ser_msgpack_impl(input)
+}
+
+#[proc_macro_derive(DeMsgPack, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_de_msgpack(input: TokenStream) -> TokenStream {
+    derive_de_msgpack_impl(input)
+}
+
+#[proc_macro_derive(SerCbor, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_ser_cbor(input: TokenStream) -> TokenStream {
+    derive_ser_cbor_impl(input)
+}
+
+#[proc_macro_derive(DeCbor, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_de_cbor(input: TokenStream) -> TokenStream {
+    derive_de_cbor_impl(input)
+}
+
+#[proc_macro_derive(SerJson, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_ser_json(input: TokenStream) -> TokenStream {
     derive_ser_json_impl(input)
 }
 
-#[proc_macro_derive(DeJson)]
+#[proc_macro_derive(DeJson, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_de_json(input: TokenStream) -> TokenStream {
     derive_de_json_impl(input)
 }
 
 
-#[proc_macro_derive(SerRon)]
+#[proc_macro_derive(SerRon, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_ser_ron(input: TokenStream) -> TokenStream {
     derive_ser_ron_impl(input)
 }
 
-#[proc_macro_derive(DeRon)]
+#[proc_macro_derive(DeRon, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
 pub fn derive_de_ron(input: TokenStream) -> TokenStream {
     derive_de_ron_impl(input)
 }
 
+#[proc_macro_derive(SerToml, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_ser_toml(input: TokenStream) -> TokenStream {
+    derive_ser_toml_impl(input)
+}
+
+#[proc_macro_derive(DeToml, attributes(rename, default, skip, flatten, tag, content, untagged, ignore_unknown))]
+pub fn derive_de_toml(input: TokenStream) -> TokenStream {
+    derive_de_toml_impl(input)
+}
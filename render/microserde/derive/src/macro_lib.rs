@@ -65,13 +65,17 @@ impl TokenBuilder {
                 "}" => self.pop_group(Delimiter::Brace),
                 ")" => self.pop_group(Delimiter::Parenthesis),
                 "]" => self.pop_group(Delimiter::Bracket),
-                "?" | ";" | "&" | "^" | ":" | "::" | "," | "!" | "." | "<<" | ">>" |
+                "?" | ";" | "&" | "^" | ":" | "::" | "," | "!" | "." | ".." | "|" | "<<" | ">>" |
                 "->" | "=>" | "<" | ">" | "<=" | ">=" | "=" | "==" | "!=" |
                 "+" | "+=" | "-" | "-=" | "*" | "*=" | "/" | "/=" => self.punct(part),
                 _ => {
                     if part.len() == 0{
                         continue
                     }
+                    if part.starts_with('\''){
+                        self.lifetime(&part[1..]);
+                        continue
+                    }
                     match part.chars().next().unwrap(){
                         '0'..='9'=>{
                             self.unsuf_usize(part.parse().expect(&format!("Can't parse number \"{}\"", what))) 
@@ -88,6 +92,11 @@ impl TokenBuilder {
         self.extend(TokenTree::from(Ident::new(id, Span::call_site())))
     }
     
+    pub fn lifetime(&mut self, name: &str) -> &mut Self {
+        self.extend(TokenTree::from(Punct::new('\'', Spacing::Joint)));
+        self.ident(name)
+    }
+    
     pub fn ident_with_span(&mut self, id: &str, span: Span) -> &mut Self {
         self.extend(TokenTree::from(Ident::new(id, span)))
     }
@@ -172,6 +181,70 @@ impl<It> Iterator for Iter<It> where It: Iterator {
     }
 }
 
+#[derive(Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub value: Option<TokenStream>, // #[name = value]
+    pub args: Option<TokenStream> // #[name(args)]
+}
+
+impl Attribute {
+    // the value of #[name = "value"]
+    pub fn string(&self) -> Option<String> {
+        let value = self.value.as_ref() ?;
+        let mut iter = value.clone().into_iter();
+        if let (Some(TokenTree::Literal(lit)), None) = (iter.next(), iter.next()) {
+            let lit = lit.to_string();
+            if lit.len() >= 2 && lit.starts_with('"') && lit.ends_with('"') {
+                return Some(lit[1..lit.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\"))
+            }
+        }
+        None
+    }
+    
+    // the value of #[name = 5]
+    pub fn int(&self) -> Option<u64> {
+        let value = self.value.as_ref() ?;
+        let mut iter = value.clone().into_iter();
+        if let (Some(TokenTree::Literal(lit)), None) = (iter.next(), iter.next()) {
+            return lit.to_string().replace('_', "").parse().ok()
+        }
+        None
+    }
+}
+
+pub fn find_attr<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attrs.iter().find( | attr | attr.name == name)
+}
+
+pub struct StructField {
+    pub name: String,
+    pub ty: TokenStream,
+    pub attrs: Vec<Attribute>
+}
+
+impl StructField {
+    pub fn is_option(&self) -> bool {
+        self.ty.clone().into_iter().next().unwrap().to_string() == "Option"
+    }
+    
+    pub fn attr(&self, name: &str) -> Option<&Attribute> {
+        find_attr(&self.attrs, name)
+    }
+}
+
+pub enum VariantFields {
+    Unit,
+    Tuple(Vec<TokenStream>),
+    Named(Vec<StructField>)
+}
+
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: VariantFields,
+    pub attrs: Vec<Attribute>
+}
+
 pub struct TokenParser {
     iter_stack: Vec<IntoIter>,
     current: Option<TokenTree>
@@ -297,7 +370,8 @@ impl TokenParser {
     pub fn is_punct(&mut self, what: char) -> bool {
         // check if our punct is multichar.
         if let Some(TokenTree::Punct(current)) = &self.current {
-            if current.as_char() == what && (what == '>' || current.spacing() == Spacing::Alone){
+            // > closes nested generics, and < & and ' are joined with lifetimes as in <'a> and &'a T
+            if current.as_char() == what && (what == '>' || what == '<' || what == '&' || what == '\'' || current.spacing() == Spacing::Alone){
                 return true
             }
             else {
@@ -353,7 +427,7 @@ impl TokenParser {
                             if self.eat_punct(',') { // next one
                                 if let Some(add_where) = add_where {
                                     tb.add("+");
-                                    tb.ident(add_where);
+                                    tb.add(add_where);
                                 }
                                 tb.add(",");
                                 break
@@ -361,7 +435,7 @@ impl TokenParser {
                             if self.is_brace() || self.is_punct(';') { // upnext is a brace.. we're done
                                 if let Some(add_where) = add_where {
                                     tb.add("+");
-                                    tb.ident(add_where);
+                                    tb.add(add_where);
                                 }
                                 return Some(tb.end())
                             }
@@ -379,26 +453,67 @@ impl TokenParser {
         return None
     }
     
-    pub fn eat_struct_field(&mut self) -> Option<(String, TokenStream)> {
+    pub fn eat_visibility(&mut self) {
+        if self.eat_ident("pub") && self.is_paren() {
+            self.advance();
+        }
+    }
+    
+    // eats any number of #[name], #[name = value] and #[name(args)]
+    pub fn eat_attributes(&mut self) -> Vec<Attribute> {
+        let mut attrs = Vec::new();
+        while self.is_punct('#') {
+            self.advance();
+            if !self.open_bracket() {
+                break
+            }
+            if let Some(name) = self.eat_any_ident() {
+                let mut value = None;
+                let mut args = None;
+                if self.eat_punct('=') {
+                    let mut tb = TokenBuilder::new();
+                    while !self.is_eot() {
+                        if let Some(current) = &self.current {
+                            tb.extend(current.clone());
+                        }
+                        self.advance();
+                    }
+                    value = Some(tb.end());
+                }
+                else if let Some(TokenTree::Group(group)) = &self.current {
+                    args = Some(group.stream());
+                    self.advance();
+                }
+                attrs.push(Attribute {name, value, args});
+            }
+            while !self.eat_eot() {
+                self.advance();
+            }
+        }
+        attrs
+    }
+    
+    pub fn eat_struct_field(&mut self) -> Option<StructField> {
         // letsparse an ident
-        self.eat_ident("pub");
-        if let Some(field) = self.eat_any_ident() {
+        let attrs = self.eat_attributes();
+        self.eat_visibility();
+        if let Some(name) = self.eat_any_ident() {
             if self.eat_punct(':') {
                 if let Some(ty) = self.eat_type() {
-                    return Some((field, ty))
+                    return Some(StructField {name, ty, attrs})
                 }
             }
         }
         return None
     }
     
-    pub fn eat_all_struct_fields(&mut self, )->Option<Vec<(String, TokenStream)>>{
+    pub fn eat_all_struct_fields(&mut self, )->Option<Vec<StructField>>{
         
         if self.open_brace(){
             let mut fields = Vec::new();
             while !self.eat_eot(){
-                if let Some((field, ty)) = self.eat_struct_field(){
-                    fields.push((field, ty));
+                if let Some(field) = self.eat_struct_field(){
+                    fields.push(field);
                     self.eat_punct(',');
                 }
                 else{
@@ -411,6 +526,32 @@ impl TokenParser {
     }
     
 
+    pub fn eat_all_enum_variants(&mut self) -> Option<Vec<EnumVariant>> {
+        if self.open_brace() {
+            let mut variants = Vec::new();
+            while !self.eat_eot() {
+                let attrs = self.eat_attributes();
+                let name = self.eat_any_ident() ?;
+                let fields = if let Some(types) = self.eat_all_types() {
+                    VariantFields::Tuple(types)
+                }
+                else if let Some(fields) = self.eat_all_struct_fields() {
+                    VariantFields::Named(fields)
+                }
+                else if self.is_punct(',') || self.is_eot() {
+                    VariantFields::Unit
+                }
+                else {
+                    return None
+                };
+                variants.push(EnumVariant {name, fields, attrs});
+                self.eat_punct(',');
+            }
+            return Some(variants)
+        }
+        return None
+    }
+    
     pub fn eat_generic(&mut self) -> Option<TokenStream> {
         let mut tb = TokenBuilder::new();
         // if we have a <, keep running and keep a < stack
@@ -448,6 +589,8 @@ impl TokenParser {
         if self.open_paren(){
             let mut ret = Vec::new();
             while !self.eat_eot(){
+                self.eat_attributes();
+                self.eat_visibility();
                 if let Some(tt) = self.eat_type(){
                     ret.push(tt);
                     self.eat_punct(',');
@@ -476,6 +619,17 @@ impl TokenParser {
             tb.add("]");
             return Some(tb.end())
         }
+        else if self.eat_punct('&') { // reference type, with an optional lifetime
+            tb.add("&");
+            if self.eat_punct('\'') {
+                tb.lifetime(&self.eat_any_ident()?);
+            }
+            if self.eat_ident("mut") {
+                tb.add("mut");
+            }
+            tb.stream(Some(self.eat_type()?));
+            return Some(tb.end())
+        }
         else if let Some(ty) = self.eat_any_ident() {
             tb.ident(&ty);
             tb.stream(self.eat_generic());
@@ -485,3 +639,47 @@ impl TokenParser {
         return None
     }
 }
+
+// the generics and where clause of a deserialize impl that can borrow from its input:
+// 'de goes in front of the generics of the type and outlives all of its lifetimes
+pub fn de_lifetime_generics(generic: Option<TokenStream>, where_clause: Option<TokenStream>) -> (TokenStream, TokenStream) {
+    let mut lifetimes: Vec<String> = Vec::new();
+    let mut tb = TokenBuilder::new();
+    tb.add("< 'de");
+    if let Some(generic) = generic {
+        let tokens: Vec<TokenTree> = generic.into_iter().collect();
+        if tokens.len() > 2 {
+            tb.add(",");
+            let mut after_quote = false;
+            for tt in &tokens[1..tokens.len() - 1] {
+                if let TokenTree::Ident(ident) = tt {
+                    if after_quote && !lifetimes.contains(&ident.to_string()) {
+                        lifetimes.push(ident.to_string());
+                    }
+                }
+                after_quote = if let TokenTree::Punct(punct) = tt {punct.as_char() == '\''} else {false};
+                tb.extend(tt.clone());
+            }
+        }
+    }
+    tb.add(">");
+    let mut wb = TokenBuilder::new();
+    let mut need_comma = false;
+    if let Some(where_clause) = where_clause {
+        for tt in where_clause.into_iter() {
+            need_comma = if let TokenTree::Punct(punct) = &tt {punct.as_char() != ','} else {true};
+            wb.extend(tt);
+        }
+    }
+    else if lifetimes.len() > 0 {
+        wb.add("where");
+    }
+    for lifetime in &lifetimes {
+        if need_comma {
+            wb.add(",");
+        }
+        wb.add("'de :").lifetime(lifetime);
+        need_comma = true;
+    }
+    (tb.end(), wb.end())
+}
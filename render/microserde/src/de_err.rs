@@ -0,0 +1,58 @@
+// the parts of DeRonErr and DeJsonErr that say where in the data an error is
+
+// prefixes a path with a field name, errors collect their path on the way out of nested values
+pub(crate) fn path_push_key(path: &mut String, key: &str) {
+    if path.len() == 0 {
+        *path = key.to_string();
+    }
+    else if path.starts_with('[') {
+        *path = format!("{}{}", key, path);
+    }
+    else {
+        *path = format!("{}.{}", key, path);
+    }
+}
+
+pub(crate) fn path_push_index(path: &mut String, index: usize) {
+    if path.len() == 0 || path.starts_with('[') {
+        *path = format!("[{}]{}", index, path);
+    }
+    else {
+        *path = format!("[{}].{}", index, path);
+    }
+}
+
+// the line of input an error is on, with line and col 0 based
+pub(crate) fn source_line(input: &str, line: usize) -> Option<String> {
+    input.lines().nth(line).map( | l | l.trim_end_matches('\r').to_string())
+}
+
+// writes an error as
+// Ron Deserialize error: Expected ..., at a.b[1], line:3 col:9
+//   3 |     b: [1, x]
+//     |            ^
+pub(crate) fn fmt_de_err(
+    f: &mut std::fmt::Formatter<'_>,
+    what: &str,
+    msg: &str,
+    path: &str,
+    line: usize,
+    col: usize,
+    source_line: &Option<String>
+) -> std::fmt::Result {
+    write!(f, "{} Deserialize error: {}", what, msg) ?;
+    if path.len() != 0 {
+        write!(f, ", at {}", path) ?;
+    }
+    write!(f, ", line:{} col:{}", line + 1, col + 1) ?;
+    if let Some(source_line) = source_line {
+        let num = format!("{}", line + 1);
+        write!(f, "\n {} | {}\n {} | ", num, source_line, " ".repeat(num.len())) ?;
+        // tabs are kept so the caret lines up with the source line
+        for c in source_line.chars().take(col) {
+            write!(f, "{}", if c == '\t' {'\t'} else {' '}) ?;
+        }
+        write!(f, "^") ?;
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::io::{BufRead, BufReader, Read};
+use std::marker::PhantomData;
+use crate::serde_json::*;
+
+// reads newline delimited JSON, like the output of cargo --message-format=json, one document
+// at a time from any Read. the line buffer is reused, so a document can borrow strings from it
+// until the next one is read
+pub struct JsonLineReader<R: Read> {
+    reader: BufReader<R>,
+    line: String,
+    // lines read so far, for the line numbers of errors
+    line_count: usize
+}
+
+impl<R: Read> JsonLineReader<R> {
+    pub fn new(read: R) -> Self {
+        JsonLineReader {
+            reader: BufReader::new(read),
+            line: String::new(),
+            line_count: 0
+        }
+    }
+
+    // the next document, or None at the end of the input. empty lines are skipped
+    pub fn read_next<'a, T: DeJson<'a>>(&'a mut self) -> Option<Result<T, DeJsonErr>> {
+        loop {
+            self.line.truncate(0);
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(err) => return Some(Err(DeJsonErr::new(format!("Cannot read input {}", err), self.line_count, 0)))
+            }
+            self.line_count += 1;
+            if self.line.trim().len() != 0 {
+                break
+            }
+        }
+        let line = self.line_count - 1;
+        Some(T::deserialize_json(&self.line).map_err( | mut err | {err.line += line; err}))
+    }
+
+    // the text of the last line read
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    // an iterator over owned documents
+    pub fn documents<T>(self) -> JsonLines<R, T> where T: for<'de> DeJson<'de> {
+        JsonLines {reader: self, _doc: PhantomData}
+    }
+}
+
+pub struct JsonLines<R: Read, T> {
+    reader: JsonLineReader<R>,
+    _doc: PhantomData<T>
+}
+
+impl<R: Read, T> Iterator for JsonLines<R, T> where T: for<'de> DeJson<'de> {
+    type Item = Result<T, DeJsonErr>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_next()
+    }
+}
@@ -0,0 +1,237 @@
+use std::str::Chars;
+use crate::serde_json::*;
+use crate::value_path::*;
+
+// a JSON document of any shape, for messages that don't need a full schema
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Str(String),
+    Array(Vec<JsonValue>),
+    // keys stay in document order
+    Object(Vec<(String, JsonValue)>),
+}
+
+static JSON_NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+    pub fn is_null(&self) -> bool {
+        if let JsonValue::Null = self {true} else {false}
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let JsonValue::Bool(v) = self {Some(*v)} else {None}
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::U64(v) => Some(*v),
+            JsonValue::I64(v) if *v >= 0 => Some(*v as u64),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::I64(v) => Some(*v),
+            JsonValue::U64(v) if *v <= std::i64::MAX as u64 => Some(*v as i64),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::F64(v) => Some(*v),
+            JsonValue::U64(v) => Some(*v as f64),
+            JsonValue::I64(v) => Some(*v as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let JsonValue::Str(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        if let JsonValue::Array(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        if let JsonValue::Object(v) = self {Some(v)} else {None}
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object() ?.iter().find( | (k, _) | k == key).map( | (_, v) | v)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        if let JsonValue::Object(entries) = self {
+            return entries.iter_mut().find( | (k, _) | k == key).map( | (_, v) | v)
+        }
+        None
+    }
+
+    // sets a key of an object, replacing the value of an existing one
+    pub fn insert(&mut self, key: &str, value: JsonValue) {
+        if let Some(old) = self.get_mut(key) {
+            *old = value;
+        }
+        else if let JsonValue::Object(entries) = self {
+            entries.push((key.to_string(), value));
+        }
+    }
+
+    // the first value at a path like "message.spans[0].file_name", see value_path.rs
+    pub fn query(&self, path: &str) -> Option<&JsonValue> {
+        self.query_path(path).into_iter().next()
+    }
+
+    // every value at a path with wildcards, like "children[*].message"
+    pub fn query_all(&self, path: &str) -> Vec<&JsonValue> {
+        self.query_path(path)
+    }
+
+    // converts any SerJson type into a value tree
+    pub fn from_type<T: SerJson + ?Sized>(value: &T) -> Result<JsonValue, DeJsonErr> {
+        JsonValue::deserialize_json(&value.serialize_json())
+    }
+
+    // converts the value tree into any DeJson type
+    pub fn to_type<T: for<'de> DeJson<'de>>(&self) -> Result<T, DeJsonErr> {
+        T::deserialize_json(&self.serialize_json())
+    }
+}
+
+impl PathValue for JsonValue {
+    fn child_key(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn child_index(&self, index: usize) -> Option<&Self> {
+        self.as_array() ?.get(index)
+    }
+
+    fn children(&self) -> Vec<&Self> {
+        match self {
+            JsonValue::Array(items) => items.iter().collect(),
+            JsonValue::Object(entries) => entries.iter().map( | (_, v) | v).collect(),
+            _ => Vec::new()
+        }
+    }
+}
+
+// missing keys and indices give Null, like in javascript
+impl std::ops::Index<&str> for JsonValue {
+    type Output = JsonValue;
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&JSON_NULL)
+    }
+}
+
+impl std::ops::Index<usize> for JsonValue {
+    type Output = JsonValue;
+    fn index(&self, index: usize) -> &JsonValue {
+        self.as_array().and_then( | items | items.get(index)).unwrap_or(&JSON_NULL)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(v: bool) -> Self {JsonValue::Bool(v)}
+}
+
+impl From<u64> for JsonValue {
+    fn from(v: u64) -> Self {JsonValue::U64(v)}
+}
+
+impl From<i64> for JsonValue {
+    fn from(v: i64) -> Self {JsonValue::I64(v)}
+}
+
+impl From<f64> for JsonValue {
+    fn from(v: f64) -> Self {JsonValue::F64(v)}
+}
+
+impl From<&str> for JsonValue {
+    fn from(v: &str) -> Self {JsonValue::Str(v.to_string())}
+}
+
+impl From<String> for JsonValue {
+    fn from(v: String) -> Self {JsonValue::Str(v)}
+}
+
+impl From<Vec<JsonValue>> for JsonValue {
+    fn from(v: Vec<JsonValue>) -> Self {JsonValue::Array(v)}
+}
+
+impl SerJson for JsonValue {
+    // objects are written without the trailing comma of the derives, so other tools can read them
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        match self {
+            JsonValue::Null => s.out.push_str("null"),
+            JsonValue::Bool(v) => v.ser_json(d, s),
+            JsonValue::U64(v) => v.ser_json(d, s),
+            JsonValue::I64(v) => v.ser_json(d, s),
+            JsonValue::F64(v) => {
+                if v.is_finite() {
+                    // keep the . so it reads back as a float
+                    let num = v.to_string();
+                    s.out.push_str(&num);
+                    if !num.contains('.') {
+                        s.out.push_str(".0");
+                    }
+                }
+                else {
+                    s.out.push_str("null");
+                }
+            }
+            JsonValue::Str(v) => v.ser_json(d, s),
+            JsonValue::Array(items) => items.ser_json(d, s),
+            JsonValue::Object(entries) => {
+                s.st_pre();
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index != 0 {
+                        s.out.push(',');
+                    }
+                    s.indent(d + 1);
+                    key.ser_json(d + 1, s);
+                    s.out.push(':');
+                    value.ser_json(d + 1, s);
+                }
+                s.st_post(d);
+            }
+        }
+    }
+}
+
+impl<'de> DeJson<'de> for JsonValue {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Self, DeJsonErr> {
+        let value = match s.tok {
+            DeJsonTok::Null => JsonValue::Null,
+            DeJsonTok::Bool(v) => JsonValue::Bool(v),
+            DeJsonTok::U64(v) => JsonValue::U64(v),
+            DeJsonTok::I64(v) => JsonValue::I64(v),
+            DeJsonTok::F64(v) => JsonValue::F64(v),
+            DeJsonTok::Str => JsonValue::Str(s.as_string() ?),
+            DeJsonTok::BlockOpen => return Ok(JsonValue::Array(DeJson::de_json(s, i) ?)),
+            DeJsonTok::CurlyOpen => {
+                let mut entries = Vec::new();
+                s.curly_open(i) ?;
+                while s.tok != DeJsonTok::CurlyClose {
+                    let key = String::de_json(s, i) ?;
+                    s.colon(i) ?;
+                    entries.push((key, DeJson::de_json(s, i) ?));
+                    s.eat_comma_curly(i) ?;
+                }
+                s.curly_close(i) ?;
+                return Ok(JsonValue::Object(entries))
+            }
+            _ => return Err(s.err_token("value"))
+        };
+        s.next_tok(i) ?;
+        Ok(value)
+    }
+}
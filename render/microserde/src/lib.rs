@@ -3,6 +3,17 @@ pub use makepad_microserde_derive::*;
 mod serde_bin;
 pub use crate::serde_bin::*;
 
+mod serde_bin_tagged;
+pub use crate::serde_bin_tagged::*;
+
+mod serde_msgpack;
+pub use crate::serde_msgpack::*;
+
+mod serde_cbor;
+pub use crate::serde_cbor::*;
+
+mod de_err;
+
 mod serde_json;
 pub use crate::serde_json::*;
 
@@ -10,4 +21,19 @@ mod serde_ron;
 pub use crate::serde_ron::*;
 
 mod toml;
-pub use crate::toml::*;
\ No newline at end of file
+pub use crate::toml::*;
+
+mod serde_toml;
+pub use crate::serde_toml::*;
+
+mod value_path;
+pub use crate::value_path::*;
+
+mod json_value;
+pub use crate::json_value::*;
+
+mod ron_value;
+pub use crate::ron_value::*;
+
+mod json_lines;
+pub use crate::json_lines::*;
@@ -0,0 +1,302 @@
+use std::str::Chars;
+use crate::serde_ron::*;
+use crate::value_path::*;
+
+// a RON document of any shape
+#[derive(Clone, Debug, PartialEq)]
+pub enum RonValue {
+    // ()
+    Unit,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Char(char),
+    Str(String),
+    // a bare identifier, as in unit enum variants and None
+    Ident(String),
+    // [a, b]
+    List(Vec<RonValue>),
+    // {key: value}
+    Map(Vec<(RonValue, RonValue)>),
+    // (a, b) or Name(a, b), which includes Some(a)
+    Tuple(Option<String>, Vec<RonValue>),
+    // (field: a) or Name(field: a)
+    Struct(Option<String>, Vec<(String, RonValue)>),
+}
+
+static RON_UNIT: RonValue = RonValue::Unit;
+
+impl RonValue {
+    pub fn is_unit(&self) -> bool {
+        if let RonValue::Unit = self {true} else {false}
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let RonValue::Bool(v) = self {Some(*v)} else {None}
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            RonValue::U64(v) => Some(*v),
+            RonValue::I64(v) if *v >= 0 => Some(*v as u64),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            RonValue::I64(v) => Some(*v),
+            RonValue::U64(v) if *v <= std::i64::MAX as u64 => Some(*v as i64),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            RonValue::F64(v) => Some(*v),
+            RonValue::U64(v) => Some(*v as f64),
+            RonValue::I64(v) => Some(*v as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        if let RonValue::Char(v) = self {Some(*v)} else {None}
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let RonValue::Str(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_ident(&self) -> Option<&str> {
+        if let RonValue::Ident(v) = self {Some(v)} else {None}
+    }
+
+    // the items of a list or a tuple
+    pub fn as_list(&self) -> Option<&[RonValue]> {
+        match self {
+            RonValue::List(v) | RonValue::Tuple(_, v) => Some(v),
+            _ => None
+        }
+    }
+
+    // the name of a named tuple or struct, or of an ident
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            RonValue::Tuple(Some(name), _) | RonValue::Struct(Some(name), _) | RonValue::Ident(name) => Some(name),
+            _ => None
+        }
+    }
+
+    // a struct field, or a map entry with a string or ident key
+    pub fn get(&self, key: &str) -> Option<&RonValue> {
+        match self {
+            RonValue::Struct(_, fields) => fields.iter().find( | (k, _) | k == key).map( | (_, v) | v),
+            RonValue::Map(entries) => entries.iter().find( | (k, _) | match k {
+                RonValue::Str(k) | RonValue::Ident(k) => k == key,
+                _ => false
+            }).map( | (_, v) | v),
+            _ => None
+        }
+    }
+
+    // the first value at a path like "window.size[0]", see value_path.rs
+    pub fn query(&self, path: &str) -> Option<&RonValue> {
+        self.query_path(path).into_iter().next()
+    }
+
+    // every value at a path with wildcards, like "windows[*].title"
+    pub fn query_all(&self, path: &str) -> Vec<&RonValue> {
+        self.query_path(path)
+    }
+
+    // converts any SerRon type into a value tree
+    pub fn from_type<T: SerRon + ?Sized>(value: &T) -> Result<RonValue, DeRonErr> {
+        RonValue::deserialize_ron(&value.serialize_ron())
+    }
+
+    // converts the value tree into any DeRon type
+    pub fn to_type<T: for<'de> DeRon<'de>>(&self) -> Result<T, DeRonErr> {
+        T::deserialize_ron(&self.serialize_ron())
+    }
+}
+
+impl PathValue for RonValue {
+    fn child_key(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn child_index(&self, index: usize) -> Option<&Self> {
+        self.as_list() ?.get(index)
+    }
+
+    fn children(&self) -> Vec<&Self> {
+        match self {
+            RonValue::List(items) | RonValue::Tuple(_, items) => items.iter().collect(),
+            RonValue::Map(entries) => entries.iter().map( | (_, v) | v).collect(),
+            RonValue::Struct(_, fields) => fields.iter().map( | (_, v) | v).collect(),
+            _ => Vec::new()
+        }
+    }
+}
+
+impl std::ops::Index<&str> for RonValue {
+    type Output = RonValue;
+    fn index(&self, key: &str) -> &RonValue {
+        self.get(key).unwrap_or(&RON_UNIT)
+    }
+}
+
+impl std::ops::Index<usize> for RonValue {
+    type Output = RonValue;
+    fn index(&self, index: usize) -> &RonValue {
+        self.as_list().and_then( | items | items.get(index)).unwrap_or(&RON_UNIT)
+    }
+}
+
+impl SerRon for RonValue {
+    fn ser_ron(&self, d: usize, s: &mut SerRonState) {
+        match self {
+            RonValue::Unit => s.out.push_str("()"),
+            RonValue::Bool(v) => v.ser_ron(d, s),
+            RonValue::U64(v) => v.ser_ron(d, s),
+            RonValue::I64(v) => v.ser_ron(d, s),
+            RonValue::F64(v) => {
+                // keep the . so it reads back as a float
+                let num = v.to_string();
+                s.out.push_str(&num);
+                if !num.contains('.') {
+                    s.out.push_str(".0");
+                }
+            }
+            RonValue::Char(c) => {
+                s.out.push('\'');
+                if *c == '\'' || *c == '\\' {
+                    s.out.push('\\');
+                }
+                s.out.push(*c);
+                s.out.push('\'');
+            }
+            RonValue::Str(v) => v.ser_ron(d, s),
+            RonValue::Ident(v) => s.out.push_str(v),
+            RonValue::List(items) => items.ser_ron(d, s),
+            RonValue::Map(entries) => {
+                s.out.push_str("{\n");
+                for (key, value) in entries {
+                    s.indent(d + 1);
+                    key.ser_ron(d + 1, s);
+                    s.out.push(':');
+                    value.ser_ron(d + 1, s);
+                    s.conl();
+                }
+                s.indent(d);
+                s.out.push('}');
+            }
+            RonValue::Tuple(name, items) => {
+                if let Some(name) = name {
+                    s.out.push_str(name);
+                }
+                s.out.push('(');
+                for (index, item) in items.iter().enumerate() {
+                    if index != 0 {
+                        s.out.push_str(", ");
+                    }
+                    item.ser_ron(d, s);
+                }
+                s.out.push(')');
+            }
+            RonValue::Struct(name, fields) => {
+                if let Some(name) = name {
+                    s.out.push_str(name);
+                }
+                s.st_pre();
+                for (key, value) in fields {
+                    s.field(d + 1, key);
+                    value.ser_ron(d + 1, s);
+                    s.conl();
+                }
+                s.st_post(d);
+            }
+        }
+    }
+}
+
+impl<'de> DeRon<'de> for RonValue {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Self, DeRonErr> {
+        let value = match s.tok {
+            DeRonTok::Bool(v) => RonValue::Bool(v),
+            DeRonTok::U64(v) => RonValue::U64(v),
+            DeRonTok::I64(v) => RonValue::I64(v),
+            DeRonTok::F64(v) => RonValue::F64(v),
+            DeRonTok::Char(v) => RonValue::Char(v),
+            DeRonTok::Str => RonValue::Str(s.as_string() ?),
+            DeRonTok::Ident => {
+                let name = s.identbuf.clone();
+                s.next_tok(i) ?;
+                return de_ron_named(s, i, Some(name))
+            }
+            DeRonTok::ParenOpen => return de_ron_named(s, i, None),
+            DeRonTok::BlockOpen => return Ok(RonValue::List(DeRon::de_ron(s, i) ?)),
+            DeRonTok::CurlyOpen => {
+                let mut entries = Vec::new();
+                s.curly_open(i) ?;
+                while s.tok != DeRonTok::CurlyClose {
+                    let key = DeRon::de_ron(s, i) ?;
+                    s.colon(i) ?;
+                    entries.push((key, DeRon::de_ron(s, i) ?));
+                    s.eat_comma_curly(i) ?;
+                }
+                s.curly_close(i) ?;
+                return Ok(RonValue::Map(entries))
+            }
+            _ => return Err(s.err_token("value"))
+        };
+        s.next_tok(i) ?;
+        Ok(value)
+    }
+}
+
+// reads what follows an optional name: a tuple, a struct, or nothing for a bare ident.
+// a struct is told apart from a tuple by its first field: an ident followed by a :
+fn de_ron_named(s: &mut DeRonState, i: &mut Chars, name: Option<String>) -> Result<RonValue, DeRonErr> {
+    if s.tok != DeRonTok::ParenOpen {
+        return match name {
+            Some(name) => Ok(RonValue::Ident(name)),
+            None => Err(s.err_token("("))
+        }
+    }
+    s.paren_open(i) ?;
+    if s.tok == DeRonTok::ParenClose {
+        s.paren_close(i) ?;
+        return Ok(if name.is_some() {RonValue::Tuple(name, Vec::new())} else {RonValue::Unit})
+    }
+    let mut items = Vec::new();
+    if s.tok == DeRonTok::Ident {
+        let ident = s.identbuf.clone();
+        s.next_tok(i) ?;
+        if s.tok == DeRonTok::Colon {
+            let mut fields = Vec::new();
+            s.colon(i) ?;
+            fields.push((ident, DeRon::de_ron(s, i) ?));
+            s.eat_comma_paren(i) ?;
+            while s.tok != DeRonTok::ParenClose {
+                let key = s.identbuf.clone();
+                s.ident(i) ?;
+                s.colon(i) ?;
+                fields.push((key, DeRon::de_ron(s, i) ?));
+                s.eat_comma_paren(i) ?;
+            }
+            s.paren_close(i) ?;
+            return Ok(RonValue::Struct(name, fields))
+        }
+        items.push(de_ron_named(s, i, Some(ident)) ?);
+        s.eat_comma_paren(i) ?;
+    }
+    while s.tok != DeRonTok::ParenClose {
+        items.push(DeRon::de_ron(s, i) ?);
+        s.eat_comma_paren(i) ?;
+    }
+    s.paren_close(i) ?;
+    Ok(RonValue::Tuple(name, items))
+}
@@ -1,5 +1,7 @@
+use std::borrow::Cow;
 use std::collections::{HashMap};
 use std::hash::Hash;
+use crate::de_err::*;
 
 pub trait SerBin {
     fn serialize_bin(&self)->Vec<u8>{
@@ -11,24 +13,47 @@ pub trait SerBin {
     fn ser_bin(&self, s: &mut Vec<u8>);
 }
 
-pub trait DeBin:Sized {
-    fn deserialize_bin(d:&[u8])->Result<Self, DeBinErr>{
+// 'de is the lifetime of the input, &'de [u8] and &'de str borrow from it
+pub trait DeBin<'de>:Sized {
+    fn deserialize_bin(d:&'de [u8])->Result<Self, DeBinErr>{
         DeBin::de_bin(&mut 0, d)
     }
 
-    fn de_bin(o:&mut usize, d:&[u8]) -> Result<Self, DeBinErr>;
+    fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<Self, DeBinErr>;
 }
 
 
 pub struct DeBinErr{
     pub o:usize,
     pub l: usize,
-    pub s: usize
+    pub s: usize,
+    // the field path of the value that failed, like builders[1].name
+    pub path: String
+}
+
+impl DeBinErr {
+    pub fn new(o: usize, l: usize, s: usize) -> Self {
+        DeBinErr{o, l, s, path: String::new()}
+    }
+    
+    pub fn at_key(mut self, key: &str) -> Self {
+        path_push_key(&mut self.path, key);
+        self
+    }
+    
+    pub fn at_index(mut self, index: usize) -> Self {
+        path_push_index(&mut self.path, index);
+        self
+    }
 }
 
 impl std::fmt::Debug for DeBinErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Bin deserialize error at:{} wanted:{} bytes but max size is {}", self.o, self.l, self.s)
+        write!(f, "Bin deserialize error at:{} wanted:{} bytes but max size is {}", self.o, self.l, self.s) ?;
+        if self.path.len() != 0 {
+            write!(f, " in {}", self.path) ?;
+        }
+        Ok(())
     }
 }
 
@@ -41,16 +66,16 @@ macro_rules! impl_ser_de_bin_for {
             }
         }
         
-        impl DeBin for $ty {
-            fn de_bin(o:&mut usize, d:&[u8]) -> Result<$ty, DeBinErr> {
+        impl<'de> DeBin<'de> for $ty {
+            fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<$ty, DeBinErr> {
                 let l = std::mem::size_of::<$ty>();
                 if *o + l > d.len(){
-                    return Err(DeBinErr{o:*o, l:l, s:d.len()})
+                    return Err(DeBinErr::new(*o, l, d.len()))
                 } 
-                let mut m = [0 as $ty];
-                unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize) as *const $ty, m.as_mut_ptr() as *mut $ty, 1)}
+                // the data has no alignment
+                let m = unsafe {std::ptr::read_unaligned(d.as_ptr().add(*o) as *const $ty)};
                 *o += l;
-                Ok(m[0])
+                Ok(m)
             }
         }
     };
@@ -73,23 +98,22 @@ impl SerBin for usize {
     }
 }
 
-impl DeBin for usize {
-    fn de_bin(o:&mut usize, d:&[u8]) -> Result<usize, DeBinErr> {
+impl<'de> DeBin<'de> for usize {
+    fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<usize, DeBinErr> {
         let l = std::mem::size_of::<u64>();
         if *o + l > d.len(){
-            return Err(DeBinErr{o:*o, l:l, s:d.len()})
+            return Err(DeBinErr::new(*o, l, d.len()))
         } 
-        let mut m = [0 as u64];
-        unsafe {std::ptr::copy_nonoverlapping(d.as_ptr().offset(*o as isize) as *const u64, m.as_mut_ptr() as *mut u64, 1)}
+        let m = unsafe {std::ptr::read_unaligned(d.as_ptr().add(*o) as *const u64)};
         *o += l;
-        Ok(m[0] as usize)
+        Ok(m as usize)
     }
 }
 
-impl DeBin for u8 {
-    fn de_bin(o:&mut usize, d:&[u8]) -> Result<u8,DeBinErr> {
+impl<'de> DeBin<'de> for u8 {
+    fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<u8,DeBinErr> {
         if *o + 1 > d.len(){
-            return Err(DeBinErr{o:*o, l:1, s:d.len()})
+            return Err(DeBinErr::new(*o, 1, d.len()))
         } 
         let m = d[*o];
         *o += 1;
@@ -109,10 +133,10 @@ impl SerBin for bool {
     }
 }
 
-impl DeBin for bool {
-    fn de_bin(o:&mut usize, d:&[u8]) -> Result<bool, DeBinErr> {
+impl<'de> DeBin<'de> for bool {
+    fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<bool, DeBinErr> {
         if *o + 1 > d.len(){
-            return Err(DeBinErr{o:*o, l:1, s:d.len()})
+            return Err(DeBinErr::new(*o, 1, d.len()))
         } 
         let m = d[*o];
         *o += 1;
@@ -120,7 +144,7 @@ impl DeBin for bool {
     }
 }
 
-impl SerBin for String {
+impl SerBin for str {
     fn ser_bin(&self, s: &mut Vec<u8>) {
         let len = self.len();
         len.ser_bin(s);
@@ -128,13 +152,66 @@ impl SerBin for String {
     }
 }
 
-impl DeBin for String {
-    fn de_bin(o:&mut usize, d:&[u8])->Result<String, DeBinErr> {
+impl SerBin for String {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        self.as_str().ser_bin(s)
+    }
+}
+
+impl<'a> SerBin for &'a str {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        (**self).ser_bin(s)
+    }
+}
+
+impl<'de> DeBin<'de> for String {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<String, DeBinErr> {
+        let len:usize = DeBin::de_bin(o,d)?;
+        if *o + len > d.len(){
+            return Err(DeBinErr::new(*o, 1, d.len()))
+        } 
+        let r = std::str::from_utf8(&d[*o..(*o+len)]).map_err(|_| DeBinErr::new(*o, len, d.len()))?.to_string();
+        *o += len;
+        Ok(r)
+    }
+}
+
+impl<'de> DeBin<'de> for &'de str {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<&'de str, DeBinErr> {
+        let bytes: &'de [u8] = DeBin::de_bin(o,d)?;
+        std::str::from_utf8(bytes).map_err(|_| DeBinErr::new(*o - bytes.len(), bytes.len(), d.len()))
+    }
+}
+
+impl<'a> SerBin for Cow<'a, str> {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        self.as_ref().ser_bin(s)
+    }
+}
+
+// bin strings have no escapes, so this always borrows
+impl<'de> DeBin<'de> for Cow<'de, str> {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<Cow<'de, str>, DeBinErr> {
+        Ok(Cow::Borrowed(DeBin::de_bin(o,d)?))
+    }
+}
+
+// written like a Vec<u8>, a length and the bytes
+impl<'a> SerBin for &'a [u8] {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        let len = self.len();
+        len.ser_bin(s);
+        s.extend_from_slice(self);
+    }
+}
+
+impl<'de> DeBin<'de> for &'de [u8] {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<&'de [u8], DeBinErr> {
         let len:usize = DeBin::de_bin(o,d)?;
         if *o + len > d.len(){
-            return Err(DeBinErr{o:*o, l:1, s:d.len()})
+            return Err(DeBinErr::new(*o, len, d.len()))
         } 
-        let r = std::str::from_utf8(&d[*o..(*o+len)]).unwrap().to_string();
+        let r = &d[*o..(*o+len)];
         *o += len;
         Ok(r)
     }
@@ -150,12 +227,12 @@ impl<T> SerBin for Vec<T> where T: SerBin {
     }
 }
 
-impl<T> DeBin for Vec<T> where T:DeBin{
-    fn de_bin(o:&mut usize, d:&[u8])->Result<Vec<T>, DeBinErr> {
+impl<'de, T> DeBin<'de> for Vec<T> where T: DeBin<'de>{
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<Vec<T>, DeBinErr> {
         let len:usize = DeBin::de_bin(o,d)?;
         let mut out = Vec::new();
         for _ in 0..len{
-            out.push(DeBin::de_bin(o,d)?)
+            out.push(DeBin::de_bin(o,d).map_err(|e| e.at_index(out.len()))?)
         }
         Ok(out)
     }
@@ -173,10 +250,10 @@ impl<T> SerBin for Option<T> where T: SerBin {
     }
 }
 
-impl<T> DeBin for Option<T> where T:DeBin{
-    fn de_bin(o:&mut usize, d:&[u8])->Result<Option<T>, DeBinErr> {
+impl<'de, T> DeBin<'de> for Option<T> where T: DeBin<'de>{
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<Option<T>, DeBinErr> {
         if *o + 1 > d.len(){
-            return Err(DeBinErr{o:*o, l:1, s:d.len()})
+            return Err(DeBinErr::new(*o, 1, d.len()))
         } 
         let m = d[*o];
         *o += 1;
@@ -198,9 +275,9 @@ impl<T> SerBin for [T] where T: SerBin {
 }
 
 
-unsafe fn de_bin_array_impl_inner<T>(top: *mut T, count: usize, o:&mut usize, d:&[u8]) -> Result<(), DeBinErr> where T:DeBin{
+unsafe fn de_bin_array_impl_inner<'de, T>(top: *mut T, count: usize, o:&mut usize, d:&'de [u8]) -> Result<(), DeBinErr> where T: DeBin<'de>{
     for c in 0..count {
-        top.add(c).write(DeBin::de_bin(o, d) ?);
+        top.add(c).write(DeBin::de_bin(o, d).map_err(|e| e.at_index(c)) ?);
     }
     Ok(())
 }
@@ -208,8 +285,8 @@ unsafe fn de_bin_array_impl_inner<T>(top: *mut T, count: usize, o:&mut usize, d:
 macro_rules!de_bin_array_impl {
     ( $($count:expr),*) => {
         $(
-        impl<T> DeBin for [T; $count] where T: DeBin {
-            fn de_bin(o:&mut usize, d:&[u8]) -> Result<Self,
+        impl<'de, T> DeBin<'de> for [T; $count] where T: DeBin<'de> {
+            fn de_bin(o:&mut usize, d:&'de [u8]) -> Result<Self,
             DeBinErr> {
                 unsafe{
                     let mut to = std::mem::MaybeUninit::<[T; $count]>::uninit();
@@ -232,8 +309,8 @@ impl<A,B> SerBin for (A,B) where A: SerBin, B:SerBin {
     }
 }
 
-impl<A,B> DeBin for (A,B) where A:DeBin, B:DeBin{
-    fn de_bin(o:&mut usize, d:&[u8])->Result<(A,B), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
+impl<'de, A,B> DeBin<'de> for (A,B) where A: DeBin<'de>, B: DeBin<'de>{
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<(A,B), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
 }
 
 impl<A,B,C> SerBin for (A,B,C) where A: SerBin, B:SerBin, C:SerBin {
@@ -244,8 +321,8 @@ impl<A,B,C> SerBin for (A,B,C) where A: SerBin, B:SerBin, C:SerBin {
     } 
 }
 
-impl<A,B,C> DeBin for (A,B,C) where A:DeBin, B:DeBin, C:DeBin{
-    fn de_bin(o:&mut usize, d:&[u8])->Result<(A,B,C), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
+impl<'de, A,B,C> DeBin<'de> for (A,B,C) where A: DeBin<'de>, B: DeBin<'de>, C: DeBin<'de>{
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<(A,B,C), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
 }
 
 impl<A,B,C,D> SerBin for (A,B,C,D) where A: SerBin, B:SerBin, C:SerBin, D:SerBin {
@@ -257,8 +334,8 @@ impl<A,B,C,D> SerBin for (A,B,C,D) where A: SerBin, B:SerBin, C:SerBin, D:SerBin
     }
 }
 
-impl<A,B,C,D> DeBin for (A,B,C,D) where A:DeBin, B:DeBin, C:DeBin, D:DeBin{
-    fn de_bin(o:&mut usize, d:&[u8])->Result<(A,B,C,D), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
+impl<'de, A,B,C,D> DeBin<'de> for (A,B,C,D) where A: DeBin<'de>, B: DeBin<'de>, C: DeBin<'de>, D: DeBin<'de>{
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<(A,B,C,D), DeBinErr> {Ok((DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?,DeBin::de_bin(o,d)?))}
 }
 
 impl<K, V> SerBin for HashMap<K, V> where K: SerBin,
@@ -273,9 +350,9 @@ V: SerBin {
     }
 }
 
-impl<K, V> DeBin for HashMap<K, V> where K: DeBin + Eq + Hash,
-V: DeBin {
-    fn de_bin(o:&mut usize, d:&[u8])->Result<Self, DeBinErr>{
+impl<'de, K, V> DeBin<'de> for HashMap<K, V> where K: DeBin<'de> + Eq + Hash,
+V: DeBin<'de> {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<Self, DeBinErr>{
         let len:usize = DeBin::de_bin(o,d)?;
         let mut h = HashMap::new();
         for _ in 0..len{
@@ -294,8 +371,8 @@ impl<T> SerBin for Box<T> where T: SerBin {
     }
 }
 
-impl<T> DeBin for Box<T> where T: DeBin {
-    fn de_bin(o:&mut usize, d:&[u8])->Result<Box<T>, DeBinErr> {
+impl<'de, T> DeBin<'de> for Box<T> where T: DeBin<'de> {
+    fn de_bin(o:&mut usize, d:&'de [u8])->Result<Box<T>, DeBinErr> {
         Ok(Box::new(DeBin::de_bin(o,d)?))
     }
 }
\ No newline at end of file
@@ -0,0 +1,582 @@
+use std::borrow::Cow;
+use std::collections::{HashMap};
+use std::convert::TryInto;
+use std::hash::Hash;
+
+// a self describing variant of the bin format, for data that is read by other versions of the
+// program. structs are messages of fields, each with a key holding its id and wire type, so a
+// reader skips the fields it doesn't know and fills in the ones that aren't there:
+//
+// key       varint, id << 3 | wire type
+// varint    LEB128, signed integers are zigzagged
+// fixed32   f32, little endian
+// fixed64   f64, little endian
+// len       a varint length and that many bytes: strings, bytes, structs, enums, lists, maps, tuples
+//
+// an enum is a message with one field whose id is the variant, holding the variant fields as
+// a message. field 0 of a struct or enum is the version written by #[version], as a message
+// of the version and the oldest version that can read it
+
+pub const BIN_WIRE_VARINT: u8 = 0;
+pub const BIN_WIRE_FIXED64: u8 = 1;
+pub const BIN_WIRE_LEN: u8 = 2;
+pub const BIN_WIRE_FIXED32: u8 = 5;
+
+pub trait SerBinTagged {
+    // the wire type in the key of a field with this value
+    const WIRE: u8;
+
+    fn serialize_bin_tagged(&self) -> Vec<u8> {
+        let mut s = Vec::new();
+        self.ser_bin_tagged(&mut s);
+        s
+    }
+
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>);
+
+    // writes the key and the value, Option leaves out None
+    fn ser_bin_tagged_field(&self, id: u32, s: &mut Vec<u8>) {
+        bin_tagged_key(id, Self::WIRE, s);
+        self.ser_bin_tagged(s);
+    }
+
+    // writes the items of a list, u8 writes them as raw bytes
+    fn ser_bin_tagged_items(items: &[Self], s: &mut Vec<u8>) where Self: Sized {
+        for item in items {
+            item.ser_bin_tagged(s);
+        }
+    }
+}
+
+pub trait DeBinTagged<'de>: Sized {
+    const WIRE: u8;
+
+    fn deserialize_bin_tagged(d: &'de [u8]) -> Result<Self, DeBinTaggedErr> {
+        let mut o = 0;
+        let value = DeBinTagged::de_bin_tagged(&mut o, d) ?;
+        if o != d.len() {
+            return Err(DeBinTaggedErr {o, msg: format!("{} bytes of trailing data", d.len() - o)})
+        }
+        Ok(value)
+    }
+
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Self, DeBinTaggedErr>;
+
+    // reads a field, the wire type of its key has to match
+    fn de_bin_tagged_field(o: &mut usize, d: &'de [u8], wire: u8) -> Result<Self, DeBinTaggedErr> {
+        if wire != Self::WIRE {
+            return Err(DeBinTaggedErr {o: *o, msg: format!("Wire type {} expected {}, the type of the field changed", wire, Self::WIRE)})
+        }
+        DeBinTagged::de_bin_tagged(o, d)
+    }
+
+    // the value of a field that isn't in the data, None makes it an error
+    fn de_bin_tagged_missing() -> Option<Self> {
+        None
+    }
+
+    // reads the items of a list up to end
+    fn de_bin_tagged_items(o: &mut usize, d: &'de [u8], end: usize) -> Result<Vec<Self>, DeBinTaggedErr> {
+        let mut out = Vec::new();
+        while *o < end {
+            out.push(DeBinTagged::de_bin_tagged(o, d) ?);
+        }
+        Ok(out)
+    }
+}
+
+pub struct DeBinTaggedErr {
+    pub o: usize,
+    pub msg: String
+}
+
+impl DeBinTaggedErr {
+    pub fn missing(o: usize, ty: &str, field: &str, id: u32) -> DeBinTaggedErr {
+        DeBinTaggedErr {o, msg: format!("Field {}.{} (id {}) not found", ty, field, id)}
+    }
+
+    pub fn unknown_variant(o: usize, ty: &str, id: u32) -> DeBinTaggedErr {
+        DeBinTaggedErr {o, msg: format!("Enum {} has no variant with id {}", ty, id)}
+    }
+
+    pub fn no_variant(o: usize, ty: &str) -> DeBinTaggedErr {
+        DeBinTaggedErr {o, msg: format!("Enum {} without a variant", ty)}
+    }
+}
+
+impl std::fmt::Debug for DeBinTaggedErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bin deserialize error at:{} {}", self.o, self.msg)
+    }
+}
+
+fn err_eof(o: usize, d: &[u8]) -> DeBinTaggedErr {
+    DeBinTaggedErr {o, msg: format!("Unexpected end of data, size is {}", d.len())}
+}
+
+pub fn bin_tagged_varint(mut v: u64, s: &mut Vec<u8>) {
+    while v >= 0x80 {
+        s.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    s.push(v as u8);
+}
+
+pub fn bin_tagged_read_varint(o: &mut usize, d: &[u8]) -> Result<u64, DeBinTaggedErr> {
+    let mut v = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *d.get(*o).ok_or_else( || err_eof(*o, d)) ?;
+        if shift == 63 && byte > 1 {
+            return Err(DeBinTaggedErr {o: *o, msg: "Varint overflows 64 bits".to_string()})
+        }
+        *o += 1;
+        v |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(v)
+        }
+        shift += 7;
+    }
+}
+
+pub fn bin_tagged_key(id: u32, wire: u8, s: &mut Vec<u8>) {
+    bin_tagged_varint(((id as u64) << 3) | wire as u64, s);
+}
+
+pub fn bin_tagged_read_key(o: &mut usize, d: &[u8]) -> Result<(u32, u8), DeBinTaggedErr> {
+    let key = bin_tagged_read_varint(o, d) ?;
+    if key >> 3 > std::u32::MAX as u64 {
+        return Err(DeBinTaggedErr {o: *o, msg: format!("Field id {} out of range", key >> 3)})
+    }
+    Ok(((key >> 3) as u32, (key & 7) as u8))
+}
+
+// a length prefixed value is written after the fact: begin, write it, then end prefixes the length
+pub fn bin_tagged_begin(s: &mut Vec<u8>) -> usize {
+    s.len()
+}
+
+pub fn bin_tagged_end(start: usize, s: &mut Vec<u8>) {
+    let mut len = Vec::new();
+    bin_tagged_varint((s.len() - start) as u64, &mut len);
+    s.splice(start..start, len);
+}
+
+// reads the length of a length prefixed value, and returns where it ends
+pub fn bin_tagged_read_len(o: &mut usize, d: &[u8]) -> Result<usize, DeBinTaggedErr> {
+    let len = bin_tagged_read_varint(o, d) ?;
+    if len > (d.len() - *o) as u64 {
+        return Err(DeBinTaggedErr {o: *o, msg: format!("Length {} past the end of the data, size is {}", len, d.len())})
+    }
+    Ok(*o + len as usize)
+}
+
+pub fn bin_tagged_check_end(o: &mut usize, end: usize) -> Result<(), DeBinTaggedErr> {
+    if *o != end {
+        return Err(DeBinTaggedErr {o: *o, msg: format!("Value ends past its length at {}", end)})
+    }
+    Ok(())
+}
+
+// reads the message of an enum variant field
+pub fn bin_tagged_read_variant(o: &mut usize, d: &[u8], wire: u8) -> Result<usize, DeBinTaggedErr> {
+    if wire != BIN_WIRE_LEN {
+        return Err(DeBinTaggedErr {o: *o, msg: format!("Enum variant with wire type {}", wire)})
+    }
+    bin_tagged_read_len(o, d)
+}
+
+pub fn bin_tagged_skip(o: &mut usize, d: &[u8], wire: u8) -> Result<(), DeBinTaggedErr> {
+    let end = match wire {
+        BIN_WIRE_VARINT => return bin_tagged_read_varint(o, d).map( | _ | ()),
+        BIN_WIRE_FIXED64 => *o + 8,
+        BIN_WIRE_FIXED32 => *o + 4,
+        BIN_WIRE_LEN => bin_tagged_read_len(o, d) ?,
+        _ => return Err(DeBinTaggedErr {o: *o, msg: format!("Cannot skip a field with wire type {}", wire)})
+    };
+    if end > d.len() {
+        return Err(err_eof(*o, d))
+    }
+    *o = end;
+    Ok(())
+}
+
+pub fn bin_tagged_version(version: u32, min_version: u32, s: &mut Vec<u8>) {
+    bin_tagged_key(0, BIN_WIRE_LEN, s);
+    let start = bin_tagged_begin(s);
+    bin_tagged_varint(version as u64, s);
+    bin_tagged_varint(min_version as u64, s);
+    bin_tagged_end(start, s);
+}
+
+// reads field 0, the (version, min_version) of the data, and checks it against the reader
+pub fn bin_tagged_read_version(o: &mut usize, d: &[u8], wire: u8, ty: &str, version: u32, min_version: u32) -> Result<(), DeBinTaggedErr> {
+    let data: (u32, u32) = DeBinTagged::de_bin_tagged_field(o, d, wire) ?;
+    bin_tagged_check_version(*o, ty, data, version, min_version)
+}
+
+// data without a version is version 0. a reader accepts data from its min_version on,
+// and data can only be read from its min_version on
+pub fn bin_tagged_check_version(o: usize, ty: &str, data: (u32, u32), version: u32, min_version: u32) -> Result<(), DeBinTaggedErr> {
+    if data.0 < min_version {
+        return Err(DeBinTaggedErr {o, msg: format!("{} data is version {}, this program reads versions {} to {}", ty, data.0, min_version, version)})
+    }
+    if data.1 > version {
+        return Err(DeBinTaggedErr {o, msg: format!("{} data is version {} and needs at least version {}, this program is version {}", ty, data.0, data.1, version)})
+    }
+    Ok(())
+}
+
+macro_rules! impl_ser_de_bin_tagged_unsigned {
+    ( $ ty: ident) => {
+        impl SerBinTagged for $ ty {
+            const WIRE: u8 = BIN_WIRE_VARINT;
+            fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+                bin_tagged_varint(*self as u64, s);
+            }
+        }
+
+        impl<'de> DeBinTagged<'de> for $ ty {
+            const WIRE: u8 = BIN_WIRE_VARINT;
+            fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result< $ ty, DeBinTaggedErr> {
+                let v = bin_tagged_read_varint(o, d) ?;
+                v.try_into().map_err( | _ | DeBinTaggedErr {o: *o, msg: format!("Value {} out of range for {}", v, stringify!( $ ty))})
+            }
+        }
+    }
+}
+
+macro_rules! impl_ser_de_bin_tagged_signed {
+    ( $ ty: ident) => {
+        impl SerBinTagged for $ ty {
+            const WIRE: u8 = BIN_WIRE_VARINT;
+            fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+                let v = *self as i64;
+                bin_tagged_varint(((v << 1) ^ (v >> 63)) as u64, s);
+            }
+        }
+
+        impl<'de> DeBinTagged<'de> for $ ty {
+            const WIRE: u8 = BIN_WIRE_VARINT;
+            fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result< $ ty, DeBinTaggedErr> {
+                let z = bin_tagged_read_varint(o, d) ?;
+                let v = ((z >> 1) as i64) ^ -((z & 1) as i64);
+                v.try_into().map_err( | _ | DeBinTaggedErr {o: *o, msg: format!("Value {} out of range for {}", v, stringify!( $ ty))})
+            }
+        }
+    }
+}
+
+macro_rules! impl_ser_de_bin_tagged_float {
+    ( $ ty: ident, $ wire: ident) => {
+        impl SerBinTagged for $ ty {
+            const WIRE: u8 = $ wire;
+            fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+                s.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl<'de> DeBinTagged<'de> for $ ty {
+            const WIRE: u8 = $ wire;
+            fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result< $ ty, DeBinTaggedErr> {
+                let l = std::mem::size_of::< $ ty>();
+                let bytes = d.get(*o..*o + l).ok_or_else( || err_eof(*o, d)) ?;
+                *o += l;
+                Ok( $ ty::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    }
+}
+
+impl_ser_de_bin_tagged_unsigned!(usize);
+impl_ser_de_bin_tagged_unsigned!(u64);
+impl_ser_de_bin_tagged_unsigned!(u32);
+impl_ser_de_bin_tagged_unsigned!(u16);
+
+impl SerBinTagged for u8 {
+    const WIRE: u8 = BIN_WIRE_VARINT;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        bin_tagged_varint(*self as u64, s);
+    }
+
+    fn ser_bin_tagged_items(items: &[u8], s: &mut Vec<u8>) {
+        s.extend_from_slice(items);
+    }
+}
+
+impl<'de> DeBinTagged<'de> for u8 {
+    const WIRE: u8 = BIN_WIRE_VARINT;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<u8, DeBinTaggedErr> {
+        let v = bin_tagged_read_varint(o, d) ?;
+        v.try_into().map_err( | _ | DeBinTaggedErr {o: *o, msg: format!("Value {} out of range for u8", v)})
+    }
+
+    fn de_bin_tagged_items(o: &mut usize, d: &'de [u8], end: usize) -> Result<Vec<u8>, DeBinTaggedErr> {
+        let r = d[*o..end].to_vec();
+        *o = end;
+        Ok(r)
+    }
+}
+impl_ser_de_bin_tagged_signed!(i64);
+impl_ser_de_bin_tagged_signed!(i32);
+impl_ser_de_bin_tagged_signed!(i16);
+impl_ser_de_bin_tagged_signed!(i8);
+impl_ser_de_bin_tagged_float!(f64, BIN_WIRE_FIXED64);
+impl_ser_de_bin_tagged_float!(f32, BIN_WIRE_FIXED32);
+
+impl SerBinTagged for bool {
+    const WIRE: u8 = BIN_WIRE_VARINT;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        s.push(if *self {1} else {0});
+    }
+}
+
+impl<'de> DeBinTagged<'de> for bool {
+    const WIRE: u8 = BIN_WIRE_VARINT;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<bool, DeBinTaggedErr> {
+        Ok(bin_tagged_read_varint(o, d) ? != 0)
+    }
+}
+
+impl SerBinTagged for str {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        bin_tagged_varint(self.len() as u64, s);
+        s.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl SerBinTagged for String {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        self.as_str().ser_bin_tagged(s)
+    }
+}
+
+impl<'a> SerBinTagged for &'a str {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        (**self).ser_bin_tagged(s)
+    }
+}
+
+impl<'a> SerBinTagged for Cow<'a, str> {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        self.as_ref().ser_bin_tagged(s)
+    }
+}
+
+impl<'de> DeBinTagged<'de> for &'de str {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<&'de str, DeBinTaggedErr> {
+        let start = *o;
+        let bytes: &'de [u8] = DeBinTagged::de_bin_tagged(o, d) ?;
+        std::str::from_utf8(bytes).map_err( | _ | DeBinTaggedErr {o: start, msg: "String is not utf8".to_string()})
+    }
+}
+
+impl<'de> DeBinTagged<'de> for String {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<String, DeBinTaggedErr> {
+        Ok(<&str>::de_bin_tagged(o, d) ?.to_string())
+    }
+}
+
+impl<'de> DeBinTagged<'de> for Cow<'de, str> {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Cow<'de, str>, DeBinTaggedErr> {
+        Ok(Cow::Borrowed(DeBinTagged::de_bin_tagged(o, d) ?))
+    }
+}
+
+// raw bytes, like a Vec<u8>
+impl<'a> SerBinTagged for &'a [u8] {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        bin_tagged_varint(self.len() as u64, s);
+        s.extend_from_slice(self);
+    }
+}
+
+impl<'de> DeBinTagged<'de> for &'de [u8] {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<&'de [u8], DeBinTaggedErr> {
+        let end = bin_tagged_read_len(o, d) ?;
+        let r = &d[*o..end];
+        *o = end;
+        Ok(r)
+    }
+}
+
+// as a field None is left out, in a list it is an empty value
+impl<T> SerBinTagged for Option<T> where T: SerBinTagged {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        let start = bin_tagged_begin(s);
+        if let Some(v) = self {
+            v.ser_bin_tagged(s);
+        }
+        bin_tagged_end(start, s);
+    }
+
+    fn ser_bin_tagged_field(&self, id: u32, s: &mut Vec<u8>) {
+        if let Some(v) = self {
+            v.ser_bin_tagged_field(id, s);
+        }
+    }
+}
+
+impl<'de, T> DeBinTagged<'de> for Option<T> where T: DeBinTagged<'de> {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Option<T>, DeBinTaggedErr> {
+        let end = bin_tagged_read_len(o, d) ?;
+        if *o == end {
+            return Ok(None)
+        }
+        let v = DeBinTagged::de_bin_tagged(o, d) ?;
+        bin_tagged_check_end(o, end) ?;
+        Ok(Some(v))
+    }
+
+    fn de_bin_tagged_field(o: &mut usize, d: &'de [u8], wire: u8) -> Result<Option<T>, DeBinTaggedErr> {
+        Ok(Some(DeBinTagged::de_bin_tagged_field(o, d, wire) ?))
+    }
+
+    fn de_bin_tagged_missing() -> Option<Option<T>> {
+        Some(None)
+    }
+}
+
+impl<T> SerBinTagged for [T] where T: SerBinTagged {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        let start = bin_tagged_begin(s);
+        T::ser_bin_tagged_items(self, s);
+        bin_tagged_end(start, s);
+    }
+}
+
+impl<T> SerBinTagged for Vec<T> where T: SerBinTagged {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        self.as_slice().ser_bin_tagged(s)
+    }
+}
+
+impl<'de, T> DeBinTagged<'de> for Vec<T> where T: DeBinTagged<'de> {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Vec<T>, DeBinTaggedErr> {
+        let end = bin_tagged_read_len(o, d) ?;
+        let out = T::de_bin_tagged_items(o, d, end) ?;
+        bin_tagged_check_end(o, end) ?;
+        Ok(out)
+    }
+}
+
+macro_rules!bin_tagged_array_impl {
+    ( $($count:expr),*) => {
+        $(
+        impl<T> SerBinTagged for [T; $count] where T: SerBinTagged {
+            const WIRE: u8 = BIN_WIRE_LEN;
+            fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+                self[..].ser_bin_tagged(s)
+            }
+        }
+
+        impl<'de, T> DeBinTagged<'de> for [T; $count] where T: DeBinTagged<'de> {
+            const WIRE: u8 = BIN_WIRE_LEN;
+            fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Self, DeBinTaggedErr> {
+                let start = *o;
+                let items: Vec<T> = DeBinTagged::de_bin_tagged(o, d) ?;
+                let len = items.len();
+                items.try_into().map_err( | _ | DeBinTaggedErr {o: start, msg: format!("Array of {} items expected {}", len, $count)})
+            }
+        }
+        )*
+    }
+}
+
+bin_tagged_array_impl!(2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32);
+
+macro_rules!impl_ser_de_bin_tagged_tuple {
+    ( $($name:ident $index:tt),*) => {
+        impl<$($name),*> SerBinTagged for ($($name),*) where $($name: SerBinTagged),* {
+            const WIRE: u8 = BIN_WIRE_LEN;
+            fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+                let start = bin_tagged_begin(s);
+                $(self.$index.ser_bin_tagged(s);)*
+                bin_tagged_end(start, s);
+            }
+        }
+
+        impl<'de, $($name),*> DeBinTagged<'de> for ($($name),*) where $($name: DeBinTagged<'de>),* {
+            const WIRE: u8 = BIN_WIRE_LEN;
+            fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Self, DeBinTaggedErr> {
+                let end = bin_tagged_read_len(o, d) ?;
+                let r = ($({let v: $name = DeBinTagged::de_bin_tagged(o, d) ?; v}),*);
+                bin_tagged_check_end(o, end) ?;
+                Ok(r)
+            }
+        }
+    }
+}
+
+impl_ser_de_bin_tagged_tuple!(A 0, B 1);
+impl_ser_de_bin_tagged_tuple!(A 0, B 1, C 2);
+impl_ser_de_bin_tagged_tuple!(A 0, B 1, C 2, D 3);
+
+impl<K, V> SerBinTagged for HashMap<K, V> where K: SerBinTagged,
+V: SerBinTagged {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        let start = bin_tagged_begin(s);
+        for (k, v) in self {
+            k.ser_bin_tagged(s);
+            v.ser_bin_tagged(s);
+        }
+        bin_tagged_end(start, s);
+    }
+}
+
+impl<'de, K, V> DeBinTagged<'de> for HashMap<K, V> where K: DeBinTagged<'de> + Eq + Hash,
+V: DeBinTagged<'de> {
+    const WIRE: u8 = BIN_WIRE_LEN;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Self, DeBinTaggedErr> {
+        let end = bin_tagged_read_len(o, d) ?;
+        let mut h = HashMap::new();
+        while *o < end {
+            let k = DeBinTagged::de_bin_tagged(o, d) ?;
+            let v = DeBinTagged::de_bin_tagged(o, d) ?;
+            h.insert(k, v);
+        }
+        bin_tagged_check_end(o, end) ?;
+        Ok(h)
+    }
+}
+
+impl<T> SerBinTagged for Box<T> where T: SerBinTagged {
+    const WIRE: u8 = T::WIRE;
+    fn ser_bin_tagged(&self, s: &mut Vec<u8>) {
+        (**self).ser_bin_tagged(s)
+    }
+
+    fn ser_bin_tagged_field(&self, id: u32, s: &mut Vec<u8>) {
+        (**self).ser_bin_tagged_field(id, s)
+    }
+}
+
+impl<'de, T> DeBinTagged<'de> for Box<T> where T: DeBinTagged<'de> {
+    const WIRE: u8 = T::WIRE;
+    fn de_bin_tagged(o: &mut usize, d: &'de [u8]) -> Result<Box<T>, DeBinTaggedErr> {
+        Ok(Box::new(DeBinTagged::de_bin_tagged(o, d) ?))
+    }
+
+    fn de_bin_tagged_field(o: &mut usize, d: &'de [u8], wire: u8) -> Result<Box<T>, DeBinTaggedErr> {
+        Ok(Box::new(DeBinTagged::de_bin_tagged_field(o, d, wire) ?))
+    }
+
+    fn de_bin_tagged_missing() -> Option<Box<T>> {
+        T::de_bin_tagged_missing().map(Box::new)
+    }
+}
@@ -0,0 +1,629 @@
+use std::borrow::Cow;
+use std::collections::{HashMap};
+use std::convert::TryInto;
+use std::hash::Hash;
+
+// CBOR (RFC 8949), with the same layout as MessagePack: structs are maps keyed by their field
+// names, a unit variant is its name and other variants a map of the name to the fields.
+// writers use definite lengths, readers also take indefinite lengths and skip tags
+
+pub trait SerCbor {
+    fn serialize_cbor(&self) -> Vec<u8> {
+        let mut s = Vec::new();
+        self.ser_cbor(&mut s);
+        s
+    }
+
+    fn ser_cbor(&self, s: &mut Vec<u8>);
+}
+
+// 'de is the lifetime of the input, &'de [u8] and &'de str borrow from it
+pub trait DeCbor<'de>: Sized {
+    fn deserialize_cbor(d: &'de [u8]) -> Result<Self, DeCborErr> {
+        DeCbor::de_cbor(&mut 0, d)
+    }
+
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Self, DeCborErr>;
+}
+
+pub struct DeCborErr {
+    pub o: usize,
+    pub msg: String
+}
+
+impl DeCborErr {
+    pub fn missing(o: usize, ty: &str, field: &str) -> DeCborErr {
+        DeCborErr {o, msg: format!("Field {}.{} not found", ty, field)}
+    }
+
+    pub fn unknown_variant(o: usize, ty: &str, variant: &str) -> DeCborErr {
+        DeCborErr {o, msg: format!("Enum {} has no variant {}", ty, variant)}
+    }
+
+    fn unexpected(o: usize, expected: &str, d: &[u8]) -> DeCborErr {
+        match d.get(o) {
+            Some(byte) => DeCborErr {o, msg: format!("Expected {} found {}", expected, cbor_type_name(*byte))},
+            None => DeCborErr {o, msg: format!("Expected {} found the end of the data", expected)}
+        }
+    }
+}
+
+impl std::fmt::Debug for DeCborErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CBOR deserialize error at:{} {}", self.o, self.msg)
+    }
+}
+
+const CBOR_UINT: u8 = 0;
+const CBOR_NINT: u8 = 1;
+const CBOR_BYTES: u8 = 2;
+const CBOR_TEXT: u8 = 3;
+const CBOR_ARRAY: u8 = 4;
+const CBOR_MAP: u8 = 5;
+const CBOR_TAG: u8 = 6;
+const CBOR_SIMPLE: u8 = 7;
+
+const CBOR_FALSE: u8 = 0xf4;
+const CBOR_TRUE: u8 = 0xf5;
+const CBOR_NULL: u8 = 0xf6;
+const CBOR_UNDEFINED: u8 = 0xf7;
+const CBOR_F16: u8 = 0xf9;
+const CBOR_F32: u8 = 0xfa;
+const CBOR_F64: u8 = 0xfb;
+const CBOR_BREAK: u8 = 0xff;
+
+fn cbor_type_name(byte: u8) -> &'static str {
+    match byte >> 5 {
+        CBOR_UINT | CBOR_NINT => "an integer",
+        CBOR_BYTES => "bytes",
+        CBOR_TEXT => "a string",
+        CBOR_ARRAY => "an array",
+        CBOR_MAP => "a map",
+        CBOR_TAG => "a tag",
+        _ => match byte {
+            CBOR_FALSE | CBOR_TRUE => "a bool",
+            CBOR_NULL => "null",
+            CBOR_UNDEFINED => "undefined",
+            CBOR_F16 | CBOR_F32 | CBOR_F64 => "a float",
+            CBOR_BREAK => "the end of a list",
+            _ => "a simple value"
+        }
+    }
+}
+
+fn cbor_take<'de>(o: &mut usize, d: &'de [u8], len: usize) -> Result<&'de [u8], DeCborErr> {
+    if *o > d.len() || len > d.len() - *o {
+        return Err(DeCborErr {o: *o, msg: format!("Unexpected end of data, wanted {} bytes but size is {}", len, d.len())})
+    }
+    let r = &d[*o..*o + len];
+    *o += len;
+    Ok(r)
+}
+
+fn cbor_uint(o: &mut usize, d: &[u8], len: usize) -> Result<u64, DeCborErr> {
+    Ok(cbor_take(o, d, len) ?.iter().fold(0u64, | v, b | (v << 8) | *b as u64))
+}
+
+// the first byte of an item and the number after it, the smallest that holds it
+pub fn cbor_head(major: u8, v: u64, s: &mut Vec<u8>) {
+    let major = major << 5;
+    if v < 24 {
+        s.push(major | v as u8);
+    }
+    else if v <= 0xff {
+        s.push(major | 24);
+        s.push(v as u8);
+    }
+    else if v <= 0xffff {
+        s.push(major | 25);
+        s.extend_from_slice(&(v as u16).to_be_bytes());
+    }
+    else if v <= 0xffff_ffff {
+        s.push(major | 26);
+        s.extend_from_slice(&(v as u32).to_be_bytes());
+    }
+    else {
+        s.push(major | 27);
+        s.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+// reads the major type and the number of an item, None for an indefinite length
+fn cbor_read_head(o: &mut usize, d: &[u8]) -> Result<(u8, Option<u64>), DeCborErr> {
+    let start = *o;
+    let byte = cbor_take(o, d, 1) ?[0];
+    let v = match byte & 0x1f {
+        info @ 0..=23 => Some(info as u64),
+        24 => Some(cbor_uint(o, d, 1) ?),
+        25 => Some(cbor_uint(o, d, 2) ?),
+        26 => Some(cbor_uint(o, d, 4) ?),
+        27 => Some(cbor_uint(o, d, 8) ?),
+        31 if byte >> 5 >= CBOR_BYTES && byte >> 5 <= CBOR_MAP => None,
+        // break is a simple value that ends an indefinite length
+        31 if byte == CBOR_BREAK => None,
+        _ => return Err(DeCborErr {o: start, msg: format!("Invalid item 0x{:02x}", byte)})
+    };
+    Ok((byte >> 5, v))
+}
+
+// tags, like the one on a date, are read past to the item they tag
+fn cbor_untag(o: &mut usize, d: &[u8]) -> Result<(), DeCborErr> {
+    while let Some(byte) = d.get(*o) {
+        if byte >> 5 != CBOR_TAG {
+            break
+        }
+        cbor_read_head(o, d) ?;
+    }
+    Ok(())
+}
+
+fn cbor_peek(o: &mut usize, d: &[u8]) -> Result<Option<u8>, DeCborErr> {
+    cbor_untag(o, d) ?;
+    Ok(d.get(*o).cloned())
+}
+
+fn cbor_read_len(o: &mut usize, d: &[u8], major: u8, what: &str) -> Result<Option<usize>, DeCborErr> {
+    cbor_untag(o, d) ?;
+    let start = *o;
+    match cbor_read_head(o, d) ? {
+        (m, len) if m == major => Ok(len.map( | len | len as usize)),
+        _ => Err(DeCborErr::unexpected(start, what, d))
+    }
+}
+
+pub fn cbor_array_len(len: usize, s: &mut Vec<u8>) {
+    cbor_head(CBOR_ARRAY, len as u64, s);
+}
+
+pub fn cbor_map_len(len: usize, s: &mut Vec<u8>) {
+    cbor_head(CBOR_MAP, len as u64, s);
+}
+
+pub fn cbor_nil(s: &mut Vec<u8>) {
+    s.push(CBOR_NULL);
+}
+
+pub fn cbor_read_array(o: &mut usize, d: &[u8]) -> Result<Option<usize>, DeCborErr> {
+    cbor_read_len(o, d, CBOR_ARRAY, "an array")
+}
+
+pub fn cbor_read_map(o: &mut usize, d: &[u8]) -> Result<Option<usize>, DeCborErr> {
+    cbor_read_len(o, d, CBOR_MAP, "a map")
+}
+
+// true while an array or map has items left, counting them down or reading up to the break
+pub fn cbor_more(o: &mut usize, d: &[u8], len: &mut Option<usize>) -> Result<bool, DeCborErr> {
+    match len {
+        Some(0) => Ok(false),
+        Some(len) => {
+            *len -= 1;
+            Ok(true)
+        }
+        None => match d.get(*o) {
+            Some(&CBOR_BREAK) => {
+                *o += 1;
+                *len = Some(0);
+                Ok(false)
+            }
+            Some(_) => Ok(true),
+            None => Err(DeCborErr::unexpected(*o, "the end of a list", d))
+        }
+    }
+}
+
+// the next item of an array that has to be there
+pub fn cbor_next(o: &mut usize, d: &[u8], len: &mut Option<usize>) -> Result<(), DeCborErr> {
+    if !cbor_more(o, d, len) ? {
+        return Err(DeCborErr {o: *o, msg: "Array has fewer items than expected".to_string()})
+    }
+    Ok(())
+}
+
+// the end of an array that can't have more items
+pub fn cbor_finish(o: &mut usize, d: &[u8], len: &mut Option<usize>) -> Result<(), DeCborErr> {
+    if cbor_more(o, d, len) ? {
+        return Err(DeCborErr {o: *o, msg: "Array has more items than expected".to_string()})
+    }
+    Ok(())
+}
+
+// reads a definite length string or bytes, or joins the chunks of an indefinite one
+fn cbor_read_chunks<'de>(o: &mut usize, d: &'de [u8], major: u8, what: &str) -> Result<Cow<'de, [u8]>, DeCborErr> {
+    match cbor_read_len(o, d, major, what) ? {
+        Some(len) => Ok(Cow::Borrowed(cbor_take(o, d, len) ?)),
+        None => {
+            let mut out = Vec::new();
+            let mut len = None;
+            while cbor_more(o, d, &mut len) ? {
+                let start = *o;
+                match cbor_read_head(o, d) ? {
+                    (m, Some(chunk)) if m == major => out.extend_from_slice(cbor_take(o, d, chunk as usize) ?),
+                    _ => return Err(DeCborErr::unexpected(start, what, d))
+                }
+            }
+            Ok(Cow::Owned(out))
+        }
+    }
+}
+
+pub fn cbor_read_str<'de>(o: &mut usize, d: &'de [u8]) -> Result<Cow<'de, str>, DeCborErr> {
+    let start = *o;
+    let r = match cbor_read_chunks(o, d, CBOR_TEXT, "a string") ? {
+        Cow::Borrowed(bytes) => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+        Cow::Owned(bytes) => String::from_utf8(bytes).ok().map(Cow::Owned)
+    };
+    r.ok_or_else( || DeCborErr {o: start, msg: "String is not UTF-8".to_string()})
+}
+
+// reads the name of an enum variant, and if it has a value after it
+pub fn cbor_read_variant<'de>(o: &mut usize, d: &'de [u8]) -> Result<(Cow<'de, str>, bool), DeCborErr> {
+    match cbor_peek(o, d) ? {
+        Some(byte) if byte == (CBOR_MAP << 5) | 1 => {
+            *o += 1;
+            Ok((cbor_read_str(o, d) ?, true))
+        }
+        Some(byte) if byte >> 5 == CBOR_TEXT => Ok((cbor_read_str(o, d) ?, false)),
+        _ => Err(DeCborErr::unexpected(*o, "an enum variant", d))
+    }
+}
+
+// reads past a value, like the fields a struct doesn't know
+pub fn cbor_skip(o: &mut usize, d: &[u8]) -> Result<(), DeCborErr> {
+    cbor_untag(o, d) ?;
+    let start = *o;
+    match cbor_read_head(o, d) ? {
+        (CBOR_BYTES, _) | (CBOR_TEXT, _) => {
+            *o = start;
+            let major = d[start] >> 5;
+            cbor_read_chunks(o, d, major, "a string") ?;
+        }
+        (CBOR_ARRAY, len) => {
+            let mut len = len.map( | len | len as usize);
+            while cbor_more(o, d, &mut len) ? {
+                cbor_skip(o, d) ?;
+            }
+        }
+        (CBOR_MAP, len) => {
+            let mut len = len.map( | len | len as usize * 2);
+            while cbor_more(o, d, &mut len) ? {
+                cbor_skip(o, d) ?;
+            }
+        }
+        (CBOR_SIMPLE, None) => return Err(DeCborErr::unexpected(start, "a value", d)),
+        _ => ()
+    }
+    Ok(())
+}
+
+// reads any integer
+pub fn cbor_read_int(o: &mut usize, d: &[u8]) -> Result<i128, DeCborErr> {
+    cbor_untag(o, d) ?;
+    let start = *o;
+    match cbor_read_head(o, d) ? {
+        (CBOR_UINT, Some(v)) => Ok(v as i128),
+        (CBOR_NINT, Some(v)) => Ok(-1 - v as i128),
+        _ => Err(DeCborErr::unexpected(start, "an integer", d))
+    }
+}
+
+// half precision floats are only read
+fn cbor_f16(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 {-1.0} else {1.0};
+    let exp = ((bits >> 10) & 0x1f) as i32;
+    let frac = (bits & 0x3ff) as f32;
+    sign * match exp {
+        0 => frac * 2f32.powi(-24),
+        31 => if frac == 0.0 {std::f32::INFINITY} else {std::f32::NAN},
+        _ => (1.0 + frac / 1024.0) * 2f32.powi(exp - 15)
+    }
+}
+
+macro_rules! impl_ser_de_cbor_uint {
+    ( $ ty: ident) => {
+        impl SerCbor for $ ty {
+            fn ser_cbor(&self, s: &mut Vec<u8>) {
+                cbor_head(CBOR_UINT, *self as u64, s);
+            }
+        }
+        impl_de_cbor_int!( $ ty);
+    }
+}
+
+macro_rules! impl_ser_de_cbor_int {
+    ( $ ty: ident) => {
+        impl SerCbor for $ ty {
+            fn ser_cbor(&self, s: &mut Vec<u8>) {
+                if *self >= 0 {
+                    cbor_head(CBOR_UINT, *self as u64, s);
+                }
+                else {
+                    cbor_head(CBOR_NINT, !(*self as i64) as u64, s);
+                }
+            }
+        }
+        impl_de_cbor_int!( $ ty);
+    }
+}
+
+macro_rules! impl_de_cbor_int {
+    ( $ ty: ident) => {
+        impl<'de> DeCbor<'de> for $ ty {
+            fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result< $ ty, DeCborErr> {
+                let start = *o;
+                let v = cbor_read_int(o, d) ?;
+                v.try_into().map_err( | _ | DeCborErr {o: start, msg: format!("Value {} out of range for {}", v, stringify!( $ ty))})
+            }
+        }
+    }
+}
+
+impl_ser_de_cbor_uint!(u8);
+impl_ser_de_cbor_uint!(u16);
+impl_ser_de_cbor_uint!(u32);
+impl_ser_de_cbor_uint!(u64);
+impl_ser_de_cbor_uint!(usize);
+impl_ser_de_cbor_int!(i8);
+impl_ser_de_cbor_int!(i16);
+impl_ser_de_cbor_int!(i32);
+impl_ser_de_cbor_int!(i64);
+
+impl SerCbor for f32 {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        s.push(CBOR_F32);
+        s.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl SerCbor for f64 {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        s.push(CBOR_F64);
+        s.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+// floats read any size, and integers
+impl<'de> DeCbor<'de> for f64 {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<f64, DeCborErr> {
+        match cbor_peek(o, d) ? {
+            Some(CBOR_F16) => {
+                *o += 1;
+                Ok(cbor_f16(cbor_uint(o, d, 2) ? as u16) as f64)
+            }
+            Some(CBOR_F32) => {
+                *o += 1;
+                Ok(f32::from_bits(cbor_uint(o, d, 4) ? as u32) as f64)
+            }
+            Some(CBOR_F64) => {
+                *o += 1;
+                Ok(f64::from_bits(cbor_uint(o, d, 8) ?))
+            }
+            Some(byte) if byte >> 5 == CBOR_UINT || byte >> 5 == CBOR_NINT => Ok(cbor_read_int(o, d) ? as f64),
+            _ => Err(DeCborErr::unexpected(*o, "a float", d))
+        }
+    }
+}
+
+impl<'de> DeCbor<'de> for f32 {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<f32, DeCborErr> {
+        Ok(f64::de_cbor(o, d) ? as f32)
+    }
+}
+
+impl SerCbor for bool {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        s.push(if *self {CBOR_TRUE} else {CBOR_FALSE});
+    }
+}
+
+impl<'de> DeCbor<'de> for bool {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<bool, DeCborErr> {
+        match cbor_peek(o, d) ? {
+            Some(CBOR_FALSE) => {*o += 1; Ok(false)}
+            Some(CBOR_TRUE) => {*o += 1; Ok(true)}
+            _ => Err(DeCborErr::unexpected(*o, "a bool", d))
+        }
+    }
+}
+
+impl SerCbor for str {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        cbor_head(CBOR_TEXT, self.len() as u64, s);
+        s.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl SerCbor for String {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        self.as_str().ser_cbor(s)
+    }
+}
+
+impl<'a> SerCbor for &'a str {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        (**self).ser_cbor(s)
+    }
+}
+
+impl<'a> SerCbor for Cow<'a, str> {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        self.as_ref().ser_cbor(s)
+    }
+}
+
+// a &str can't hold a string written in chunks
+impl<'de> DeCbor<'de> for &'de str {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<&'de str, DeCborErr> {
+        let start = *o;
+        match cbor_read_str(o, d) ? {
+            Cow::Borrowed(s) => Ok(s),
+            Cow::Owned(_) => Err(DeCborErr {o: start, msg: "Cannot borrow a string of indefinite length".to_string()})
+        }
+    }
+}
+
+impl<'de> DeCbor<'de> for String {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<String, DeCborErr> {
+        Ok(cbor_read_str(o, d) ?.into_owned())
+    }
+}
+
+impl<'de> DeCbor<'de> for Cow<'de, str> {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Cow<'de, str>, DeCborErr> {
+        cbor_read_str(o, d)
+    }
+}
+
+// bytes are written as a byte string, where a Vec<u8> is an array of integers
+impl<'a> SerCbor for &'a [u8] {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        cbor_head(CBOR_BYTES, self.len() as u64, s);
+        s.extend_from_slice(self);
+    }
+}
+
+impl<'de> DeCbor<'de> for &'de [u8] {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<&'de [u8], DeCborErr> {
+        let start = *o;
+        match cbor_read_chunks(o, d, CBOR_BYTES, "bytes") ? {
+            Cow::Borrowed(bytes) => Ok(bytes),
+            Cow::Owned(_) => Err(DeCborErr {o: start, msg: "Cannot borrow bytes of indefinite length".to_string()})
+        }
+    }
+}
+
+impl<T> SerCbor for Option<T> where T: SerCbor {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        if let Some(v) = self {
+            v.ser_cbor(s);
+        }
+        else {
+            cbor_nil(s);
+        }
+    }
+}
+
+// null and undefined are both None
+impl<'de, T> DeCbor<'de> for Option<T> where T: DeCbor<'de> {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Option<T>, DeCborErr> {
+        match cbor_peek(o, d) ? {
+            Some(CBOR_NULL) | Some(CBOR_UNDEFINED) => {
+                *o += 1;
+                Ok(None)
+            }
+            _ => Ok(Some(DeCbor::de_cbor(o, d) ?))
+        }
+    }
+}
+
+impl<T> SerCbor for [T] where T: SerCbor {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        cbor_array_len(self.len(), s);
+        for item in self {
+            item.ser_cbor(s);
+        }
+    }
+}
+
+impl<T> SerCbor for Vec<T> where T: SerCbor {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        self.as_slice().ser_cbor(s)
+    }
+}
+
+impl<'de, T> DeCbor<'de> for Vec<T> where T: DeCbor<'de> {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Vec<T>, DeCborErr> {
+        let mut len = cbor_read_array(o, d) ?;
+        let mut out = Vec::new();
+        while cbor_more(o, d, &mut len) ? {
+            out.push(DeCbor::de_cbor(o, d) ?);
+        }
+        Ok(out)
+    }
+}
+
+macro_rules!cbor_array_impl {
+    ( $($count:expr),*) => {
+        $(
+        impl<T> SerCbor for [T; $count] where T: SerCbor {
+            fn ser_cbor(&self, s: &mut Vec<u8>) {
+                self[..].ser_cbor(s)
+            }
+        }
+
+        impl<'de, T> DeCbor<'de> for [T; $count] where T: DeCbor<'de> {
+            fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Self, DeCborErr> {
+                let start = *o;
+                let items: Vec<T> = DeCbor::de_cbor(o, d) ?;
+                let len = items.len();
+                items.try_into().map_err( | _ | DeCborErr {o: start, msg: format!("Array of {} items expected {}", len, $count)})
+            }
+        }
+        )*
+    }
+}
+
+cbor_array_impl!(2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32);
+
+macro_rules!impl_ser_de_cbor_tuple {
+    ( $ len: expr, $ ( $ name: ident: $ index: tt), *) => {
+        impl< $ ( $ name), *> SerCbor for ( $ ( $ name), *) where $ ( $ name: SerCbor), * {
+            fn ser_cbor(&self, s: &mut Vec<u8>) {
+                cbor_array_len( $ len, s);
+                $ (self. $ index.ser_cbor(s);) *
+            }
+        }
+
+        impl<'de, $ ( $ name), *> DeCbor<'de> for ( $ ( $ name), *) where $ ( $ name: DeCbor<'de>), * {
+            fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Self, DeCborErr> {
+                let mut len = cbor_read_array(o, d) ?;
+                let r = ( $ ({
+                    cbor_next(o, d, &mut len) ?;
+                    < $ name as DeCbor>::de_cbor(o, d) ?
+                }), *);
+                cbor_finish(o, d, &mut len) ?;
+                Ok(r)
+            }
+        }
+    }
+}
+
+impl_ser_de_cbor_tuple!(2, A: 0, B: 1);
+impl_ser_de_cbor_tuple!(3, A: 0, B: 1, C: 2);
+impl_ser_de_cbor_tuple!(4, A: 0, B: 1, C: 2, D: 3);
+
+impl<K, V> SerCbor for HashMap<K, V> where K: SerCbor,
+V: SerCbor {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        cbor_map_len(self.len(), s);
+        for (k, v) in self {
+            k.ser_cbor(s);
+            v.ser_cbor(s);
+        }
+    }
+}
+
+impl<'de, K, V> DeCbor<'de> for HashMap<K, V> where K: DeCbor<'de> + Eq + Hash,
+V: DeCbor<'de> {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Self, DeCborErr> {
+        let mut len = cbor_read_map(o, d) ?;
+        let mut h = HashMap::new();
+        while cbor_more(o, d, &mut len) ? {
+            let k = DeCbor::de_cbor(o, d) ?;
+            let v = DeCbor::de_cbor(o, d) ?;
+            h.insert(k, v);
+        }
+        Ok(h)
+    }
+}
+
+impl<T> SerCbor for Box<T> where T: SerCbor {
+    fn ser_cbor(&self, s: &mut Vec<u8>) {
+        (**self).ser_cbor(s)
+    }
+}
+
+impl<'de, T> DeCbor<'de> for Box<T> where T: DeCbor<'de> {
+    fn de_cbor(o: &mut usize, d: &'de [u8]) -> Result<Box<T>, DeCborErr> {
+        Ok(Box::new(DeCbor::de_cbor(o, d) ?))
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::{HashMap};
 use std::hash::Hash;
+use std::borrow::Cow;
 use std::str::Chars;
+use crate::de_err::*;
 
+#[derive(Default)]
 pub struct SerJsonState {
     pub out: String
 }
@@ -40,6 +43,23 @@ impl SerJsonState {
         self.out.push('}');
     }
     
+    // writes the entries of an object valued `value` into the object being written,
+    // for #[flatten] fields and the newtype variants of internally tagged enums
+    pub fn ser_flatten<T>(&mut self, d: usize, value: &T) where T: SerJson + ?Sized {
+        let mut inner = SerJsonState::default();
+        value.ser_json(d, &mut inner);
+        let body = inner.out.trim();
+        if body.len() >= 2 && body.starts_with('{') && body.ends_with('}') {
+            let body = body[1..body.len() - 1].trim_end();
+            if body.len() > 0 {
+                self.out.push_str(body);
+                if !body.ends_with(',') {
+                    self.conl();
+                }
+            }
+        }
+    }
+    
 }
 
 pub trait SerJson {
@@ -55,18 +75,18 @@ pub trait SerJson {
     fn ser_json(&self, d: usize, s: &mut SerJsonState);
 }
 
-pub trait DeJson: Sized {
+// 'de is the lifetime of the input, types like &'de str borrow from it
+pub trait DeJson<'de>: Sized {
     
-    fn deserialize_json(input: &str) -> Result<Self,
+    fn deserialize_json(input: &'de str) -> Result<Self,
     DeJsonErr> {
         let mut state = DeJsonState::default();
         let mut chars = input.chars();
         state.next(&mut chars);
-        state.next_tok(&mut chars) ?;
-        DeJson::de_json(&mut state, &mut chars)
+        state.next_tok(&mut chars).and_then( | _ | DeJson::de_json(&mut state, &mut chars)).map_err( | err | err.with_source(input))
     }
     
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self,
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Self,
     DeJsonErr>;
 }
 
@@ -102,18 +122,60 @@ pub struct DeJsonState {
     pub numbuf:String,
     pub identbuf:String,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // where the current token starts, errors are reported there
+    pub tok_line: usize,
+    pub tok_col: usize,
+    // where the last string token is in the input, for borrowing it
+    str_start: usize,
+    str_len: usize,
+    str_input_end: usize,
+    str_escaped: bool
 }
 
 pub struct DeJsonErr{
     pub msg:String,
+    // the field path of the value that failed, like builders.main.workspaces or items[2].name
+    pub path:String,
+    pub expected:Option<String>,
+    pub found:Option<String>,
     pub line:usize,
-    pub col:usize
+    pub col:usize,
+    // the line of input the error is on, filled in by deserialize_json
+    pub source_line:Option<String>
+}
+
+impl DeJsonErr {
+    pub fn new(msg: String, line: usize, col: usize) -> Self {
+        DeJsonErr{msg, path: String::new(), expected: None, found: None, line, col, source_line: None}
+    }
+    
+    // called on the way out of a field, so the path is built from the inside out
+    pub fn at_key(mut self, key: &str) -> Self {
+        path_push_key(&mut self.path, key);
+        self
+    }
+    
+    pub fn at_index(mut self, index: usize) -> Self {
+        path_push_index(&mut self.path, index);
+        self
+    }
+    
+    pub fn with_source(mut self, input: &str) -> Self {
+        self.source_line = source_line(input, self.line);
+        self
+    }
 }
 
 impl std::fmt::Debug for DeJsonErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Json Deserialize error: {}, line:{} col:{}", self.msg, self.line+1, self.col+1)
+        fmt_de_err(f, "Json", &self.msg, &self.path, self.line, self.col, &self.source_line)
+    }
+}
+
+impl std::fmt::Display for DeJsonErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
     }
 }
 
@@ -134,32 +196,74 @@ impl DeJsonState {
         }
     }
     
+    fn err(&self, msg: String) -> DeJsonErr {
+        DeJsonErr::new(msg, self.tok_line, self.tok_col)
+    }
+    
     pub fn err_exp(&self, name: &str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Unexpected key {}", name), line:self.line, col:self.col}
+        self.err(format!("Unexpected key {}", name))
     }
     
     pub fn err_nf(&self, name: &str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Key not found {}", name), line:self.line, col:self.col}
+        self.err(format!("Key not found {}", name))
     }
 
     pub fn err_enum(&self, name: &str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Enum not defined {}", name), line:self.line, col:self.col}
+        self.err(format!("Enum not defined {}", name))
     }
 
+    pub fn err_untagged(&self, name: &str) -> DeJsonErr {
+        self.err(format!("Data did not match any variant of {}", name))
+    }
+    
     pub fn err_token(&self, what:&str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Unexpected token {:?} expected {} ", self.tok, what), line:self.line, col:self.col}
+        let found = self.tok_desc();
+        let mut err = self.err(format!("Expected {} found {}", what, found));
+        err.expected = Some(what.to_string());
+        err.found = Some(found);
+        err
     }
-
+    
     pub fn err_range(&self, what:&str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Value out of range {} ", what), line:self.line, col:self.col}
+        self.err(format!("Value out of range {} ", what))
     }
-
+    
     pub fn err_type(&self, what:&str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Token wrong type {} ", what), line:self.line, col:self.col}
+        self.err(format!("Token wrong type {} ", what))
     }
-
+    
     pub fn err_parse(&self, what:&str) -> DeJsonErr {
-        DeJsonErr{msg:format!("Cannot parse {} ", what), line:self.line, col:self.col}
+        self.err(format!("Cannot parse {} ", what))
+    }
+    
+    // the current token as it reads in an error message
+    pub fn tok_desc(&self) -> String {
+        match &self.tok {
+            DeJsonTok::Str => format!("string {:?}", self.strbuf),
+            DeJsonTok::Char(c) => format!("char {:?}", c),
+            DeJsonTok::U64(_) | DeJsonTok::I64(_) | DeJsonTok::F64(_) => format!("number {}", self.numbuf),
+            DeJsonTok::Bool(v) => format!("{}", v),
+            DeJsonTok::BareIdent => format!("identifier {}", self.identbuf),
+            DeJsonTok::Null => "null".to_string(),
+            DeJsonTok::Colon => "':'".to_string(),
+            DeJsonTok::CurlyOpen => "'{'".to_string(),
+            DeJsonTok::CurlyClose => "'}'".to_string(),
+            DeJsonTok::BlockOpen => "'['".to_string(),
+            DeJsonTok::BlockClose => "']'".to_string(),
+            DeJsonTok::Comma => "','".to_string(),
+            DeJsonTok::Bof => "start of input".to_string(),
+            DeJsonTok::Eof => "end of input".to_string(),
+        }
+    }
+    
+    // the current token as a map key, for the path of errors in its value
+    pub fn tok_key(&self) -> String {
+        match &self.tok {
+            DeJsonTok::Str => self.strbuf.clone(),
+            DeJsonTok::BareIdent => self.identbuf.clone(),
+            DeJsonTok::U64(_) | DeJsonTok::I64(_) | DeJsonTok::F64(_) => self.numbuf.clone(),
+            tok => format!("{:?}", tok)
+        }
     }
     
     pub fn eat_comma_block(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
@@ -314,6 +418,24 @@ impl DeJsonState {
         Err(self.err_token("boolean"))
     }
     
+    // the current string token as a slice of the input, which only works without escapes
+    pub fn as_borrowed_str<'de>(&mut self, i: &Chars<'de>) -> Result<&'de str, DeJsonErr> {
+        if self.tok != DeJsonTok::Str {
+            return Err(self.err_token("string"))
+        }
+        let rest = i.as_str();
+        if self.str_input_end != rest.as_ptr() as usize + rest.len() || self.str_start + self.str_len > rest.as_ptr() as usize {
+            return Err(self.err_type("borrowed string, not read from this input"))
+        }
+        if self.str_escaped {
+            return Err(self.err_type("borrowed string, it contains escapes"))
+        }
+        // the string lies before the position of i in the same input
+        unsafe {
+            Ok(std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.str_start as *const u8, self.str_len)))
+        }
+    }
+    
     pub fn as_string(&mut self) -> Result<String, DeJsonErr> {
         if let DeJsonTok::Str = &mut self.tok {
             let mut val = String::new();
@@ -323,10 +445,65 @@ impl DeJsonState {
         Err(self.err_token("string"))
     }
     
+    // writes the key in strbuf to out, as "key":
+    pub fn capture_key(&mut self, out: &mut SerJsonState) {
+        self.strbuf.ser_json(0, out);
+        out.out.push(':');
+    }
+    
+    // consumes one value and writes it to out, so it can be deserialized later with de_captured
+    pub fn capture_value(&mut self, out: &mut SerJsonState, i: &mut Chars) -> Result<(), DeJsonErr> {
+        let mut depth = 0;
+        loop {
+            match self.tok {
+                DeJsonTok::CurlyOpen => {depth += 1; out.out.push('{')},
+                DeJsonTok::BlockOpen => {depth += 1; out.out.push('[')},
+                DeJsonTok::CurlyClose | DeJsonTok::BlockClose if depth == 0 => {
+                    return Err(self.err_token("value"))
+                },
+                DeJsonTok::CurlyClose => {depth -= 1; out.out.push('}')},
+                DeJsonTok::BlockClose => {depth -= 1; out.out.push(']')},
+                DeJsonTok::Colon if depth > 0 => out.out.push(':'),
+                DeJsonTok::Comma if depth > 0 => out.out.push(','),
+                DeJsonTok::Str => self.strbuf.ser_json(0, out),
+                DeJsonTok::U64(_) | DeJsonTok::I64(_) | DeJsonTok::F64(_) => out.out.push_str(&self.numbuf),
+                DeJsonTok::Bool(value) => value.ser_json(0, out),
+                DeJsonTok::Null => out.out.push_str("null"),
+                _ => return Err(self.err_token("value"))
+            }
+            self.next_tok(i) ?;
+            if depth == 0 {
+                return Ok(())
+            }
+        }
+    }
+    
+    pub fn skip_value(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
+        self.capture_value(&mut SerJsonState::default(), i)
+    }
+    
+    // deserializes captured input with f, errors are reported at the current position.
+    // the input is a copy, so what f reads cannot borrow from it
+    pub fn de_captured<T, F>(&self, input: &str, f: F) -> Result<T, DeJsonErr>
+    where F: FnOnce(&mut DeJsonState, &mut Chars) -> Result<T, DeJsonErr> {
+        let mut state = DeJsonState::default();
+        let mut chars = input.chars();
+        state.next(&mut chars);
+        let result = state.next_tok(&mut chars).and_then( | _ | f(&mut state, &mut chars));
+        match result {
+            Ok(_) if state.tok != DeJsonTok::Eof => Err(self.err_parse("captured value, trailing data")),
+            Ok(value) => Ok(value),
+            Err(err) => Err(DeJsonErr {line: self.tok_line, col: self.tok_col, ..err})
+        }
+    }
+    
     pub fn next_tok(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
         while self.cur == '\n' || self.cur == '\r' || self.cur == '\t' || self.cur == ' ' {
             self.next(i);
         }
+        // col is one past cur, except at the end of the input
+        self.tok_line = self.line;
+        self.tok_col = if self.cur == '\0' {self.col} else {self.col.saturating_sub(1)};
         if self.cur == '\0' {
             self.tok = DeJsonTok::Eof;
             return Ok(())
@@ -431,13 +608,20 @@ impl DeJsonState {
                     return Ok(())
                 }
                 self.tok = DeJsonTok::BareIdent;
-                return Err(self.err_token(&format!("Got ##{}## needed true, false, null", self.identbuf)));
+                return Err(self.err_token("true, false or null"));
             }
             '"' => {
                 self.strbuf.truncate(0);
+                self.str_escaped = false;
+                let start = i.as_str();
                 self.next(i);
                 while self.cur != '"' {
                     if self.cur == '\\' {
+                        if !self.str_escaped {
+                            // from the first escape on the string is built char by char
+                            self.str_escaped = true;
+                            self.strbuf.push_str(&start[..start.len() - i.as_str().len() - 1]);
+                        }
                         self.next(i);
                         match self.cur{
                             'n'=>self.strbuf.push('\n'),
@@ -455,16 +639,25 @@ impl DeJsonState {
                         if self.cur == '\0' {
                             return Err(self.err_parse("string"));
                         }
-                        self.strbuf.push(self.cur);
+                        if self.str_escaped {
+                            self.strbuf.push(self.cur);
+                        }
                         self.next(i);
                     }
                 }
+                let len = start.len() - i.as_str().len() - 1;
+                if !self.str_escaped {
+                    self.strbuf.push_str(&start[..len]);
+                }
+                self.str_start = start.as_ptr() as usize;
+                self.str_len = len;
+                self.str_input_end = start.as_ptr() as usize + start.len();
                 self.next(i);
                 self.tok = DeJsonTok::Str;
                 return Ok(())
             },
             _ => {
-                return Err(self.err_token("tokenizer"));
+                return Err(self.err_parse(&format!("character {:?}", self.cur)));
             }
         }
     }
@@ -478,8 +671,8 @@ macro_rules!impl_ser_de_json_unsigned {
             }
         }
         
-        impl DeJson for $ ty {
-            fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeJson<'de> for $ ty {
+            fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeJsonErr> {
                 let val = s.u64_range( $ max as u64) ?;
                 s.next_tok(i) ?;
@@ -497,8 +690,8 @@ macro_rules!impl_ser_de_json_signed {
             }
         }
         
-        impl DeJson for $ ty {
-            fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeJson<'de> for $ ty {
+            fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeJsonErr> {
                 //s.is_prefix(p, i) ?;
                 let val = s.i64_range( $ min as i64, $ max as i64) ?;
@@ -517,8 +710,8 @@ macro_rules!impl_ser_de_json_float {
             }
         }
         
-        impl DeJson for $ ty {
-            fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeJson<'de> for $ ty {
+            fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeJsonErr> {
                 //s.is_prefix(p, i) ?;
                 let val = s.as_f64() ?;
@@ -547,13 +740,13 @@ impl<T> SerJson for Option<T> where T: SerJson {
             v.ser_json(d, s);
         }
         else {
-            s.out.push_str("None");
+            s.out.push_str("null");
         }
     }
 }
 
-impl<T> DeJson for Option<T> where T: DeJson{
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self,
+impl<'de, T> DeJson<'de> for Option<T> where T: DeJson<'de>{
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Self,
     DeJsonErr> {
         if let DeJsonTok::Null = s.tok {
             s.next_tok(i) ?;
@@ -574,15 +767,15 @@ impl SerJson for bool {
     }
 }
 
-impl DeJson for bool {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<bool, DeJsonErr> {
+impl<'de> DeJson<'de> for bool {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<bool, DeJsonErr> {
         let val = s.as_bool() ?;
         s.next_tok(i) ?;
         return Ok(val);
     }
 }
 
-impl SerJson for String {
+impl SerJson for str {
     fn ser_json(&self, _d: usize, s: &mut SerJsonState) {
         s.out.push('"');
         for c in self.chars() {
@@ -600,14 +793,52 @@ impl SerJson for String {
     }
 }
 
-impl DeJson for String {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<String, DeJsonErr> {
+impl<'de> DeJson<'de> for String {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<String, DeJsonErr> {
         let val = s.as_string() ?;
         s.next_tok(i) ?;
         return Ok(val);
     }
 }
 
+impl SerJson for String {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        self.as_str().ser_json(d, s)
+    }
+}
+
+impl<'a> SerJson for &'a str {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        (**self).ser_json(d, s)
+    }
+}
+
+// fails on strings with escapes, use Cow to fall back to a copy
+impl<'de> DeJson<'de> for &'de str {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<&'de str, DeJsonErr> {
+        let val = s.as_borrowed_str(i) ?;
+        s.next_tok(i) ?;
+        return Ok(val);
+    }
+}
+
+impl<'a> SerJson for Cow<'a, str> {
+    fn ser_json(&self, d: usize, s: &mut SerJsonState) {
+        self.as_ref().ser_json(d, s)
+    }
+}
+
+impl<'de> DeJson<'de> for Cow<'de, str> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Cow<'de, str>, DeJsonErr> {
+        let val = match s.as_borrowed_str(i) {
+            Ok(val) => Cow::Borrowed(val),
+            Err(_) => Cow::Owned(s.as_string() ?)
+        };
+        s.next_tok(i) ?;
+        return Ok(val);
+    }
+}
+
 impl<T> SerJson for Vec<T> where T: SerJson {
     fn ser_json(&self, d: usize, s: &mut SerJsonState) {
         s.out.push('[');
@@ -625,13 +856,14 @@ impl<T> SerJson for Vec<T> where T: SerJson {
     }
 }
 
-impl<T> DeJson for Vec<T> where T: DeJson {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Vec<T>, DeJsonErr> {
+impl<'de, T> DeJson<'de> for Vec<T> where T: DeJson<'de> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Vec<T>, DeJsonErr> {
         let mut out = Vec::new();
         s.block_open(i) ?;
         
         while s.tok != DeJsonTok::BlockClose {
-            out.push(DeJson::de_json(s, i) ?);
+            let index = out.len();
+            out.push(DeJson::de_json(s, i).map_err( | err | err.at_index(index)) ?);
             s.eat_comma_block(i) ?;
         }
         s.block_close(i) ?;
@@ -653,10 +885,10 @@ impl<T> SerJson for [T] where T: SerJson {
     }
 }
 
-unsafe fn de_json_array_impl_inner<T>(top: *mut T, count: usize, s: &mut DeJsonState, i: &mut Chars) -> Result<(), DeJsonErr> where T:DeJson{
+unsafe fn de_json_array_impl_inner<'de, T>(top: *mut T, count: usize, s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<(), DeJsonErr> where T: DeJson<'de>{
     s.block_open(i) ?;
     for c in 0..count {
-        top.add(c).write(DeJson::de_json(s, i) ?);
+        top.add(c).write(DeJson::de_json(s, i).map_err( | err | err.at_index(c)) ?);
         s.eat_comma_block(i) ?;
     }
     s.block_close(i) ?;
@@ -666,8 +898,8 @@ unsafe fn de_json_array_impl_inner<T>(top: *mut T, count: usize, s: &mut DeJsonS
 macro_rules!de_json_array_impl {
     ( $($count:expr),*) => {
         $(
-        impl<T> DeJson for [T; $count] where T: DeJson {
-            fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self,
+        impl<'de, T> DeJson<'de> for [T; $count] where T: DeJson<'de> {
+            fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Self,
             DeJsonErr> {
                 unsafe{
                     let mut to = std::mem::MaybeUninit::<[T; $count]>::uninit();
@@ -683,8 +915,8 @@ macro_rules!de_json_array_impl {
 
 de_json_array_impl!(2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32);
 
-fn de_json_comma_block<T>(s: &mut DeJsonState, i: &mut Chars) -> Result<T, DeJsonErr> where T: DeJson {
-    let t = DeJson::de_json(s, i);
+fn de_json_comma_block<'de, T>(s: &mut DeJsonState, i: &mut Chars<'de>, index: usize) -> Result<T, DeJsonErr> where T: DeJson<'de> {
+    let t = DeJson::de_json(s, i).map_err( | err | err.at_index(index));
     s.eat_comma_block(i) ?;
     t
 }
@@ -700,11 +932,11 @@ B: SerJson {
     }
 }
 
-impl<A, B> DeJson for (A, B) where A: DeJson,
-B: DeJson {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<(A, B), DeJsonErr> {
+impl<'de, A, B> DeJson<'de> for (A, B) where A: DeJson<'de>,
+B: DeJson<'de> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<(A, B), DeJsonErr> {
         s.block_open(i) ?;
-        let r = (de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?);
+        let r = (de_json_comma_block(s, i, 0) ?, de_json_comma_block(s, i, 1) ?);
         s.block_close(i) ?;
         Ok(r)
     }
@@ -724,12 +956,12 @@ C: SerJson {
     }
 }
 
-impl<A, B, C> DeJson for (A, B, C) where A: DeJson,
-B: DeJson,
-C: DeJson {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<(A, B, C), DeJsonErr> {
+impl<'de, A, B, C> DeJson<'de> for (A, B, C) where A: DeJson<'de>,
+B: DeJson<'de>,
+C: DeJson<'de> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<(A, B, C), DeJsonErr> {
         s.block_open(i) ?;
-        let r = (de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?);
+        let r = (de_json_comma_block(s, i, 0) ?, de_json_comma_block(s, i, 1) ?, de_json_comma_block(s, i, 2) ?);
         s.block_close(i) ?;
         Ok(r)
     }
@@ -752,13 +984,13 @@ D: SerJson {
     }
 }
 
-impl<A, B, C, D> DeJson for (A, B, C, D) where A: DeJson,
-B: DeJson,
-C: DeJson,
-D: DeJson {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<(A, B, C, D), DeJsonErr> {
+impl<'de, A, B, C, D> DeJson<'de> for (A, B, C, D) where A: DeJson<'de>,
+B: DeJson<'de>,
+C: DeJson<'de>,
+D: DeJson<'de> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<(A, B, C, D), DeJsonErr> {
         s.block_open(i) ?;
-        let r = (de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?, de_json_comma_block(s, i) ?);
+        let r = (de_json_comma_block(s, i, 0) ?, de_json_comma_block(s, i, 1) ?, de_json_comma_block(s, i, 2) ?, de_json_comma_block(s, i, 3) ?);
         s.block_close(i) ?;
         Ok(r)
     }
@@ -768,33 +1000,31 @@ impl<K, V> SerJson for HashMap<K, V> where K: SerJson,
 V: SerJson {
     fn ser_json(&self, d: usize, s: &mut SerJsonState) {
         s.out.push('{');
-        let last = self.len() - 1;
-        let mut index = 0;
-        for (k, v) in self {
+        for (index, (k, v)) in self.iter().enumerate() {
+            if index != 0{
+                s.conl();
+            }
             s.indent(d + 1);
             k.ser_json(d + 1, s);
             s.out.push(':');
             v.ser_json(d + 1, s);
-            if index != last{
-                s.conl();
-            }
-            index += 1;
         }
         s.indent(d);
         s.out.push('}');
     }
 }
 
-impl<K, V> DeJson for HashMap<K, V> where K: DeJson + Eq + Hash,
-V: DeJson  {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self,
+impl<'de, K, V> DeJson<'de> for HashMap<K, V> where K: DeJson<'de> + Eq + Hash,
+V: DeJson<'de>  {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Self,
     DeJsonErr> {
         let mut h = HashMap::new();
         s.curly_open(i) ?;
         while s.tok != DeJsonTok::CurlyClose {
-            let k = DeJson::de_json(s, i) ?;
+            let key = s.tok_key();
+            let k = DeJson::de_json(s, i).map_err( | err | err.at_key(&key)) ?;
             s.colon(i) ?;
-            let v = DeJson::de_json(s, i) ?;
+            let v = DeJson::de_json(s, i).map_err( | err | err.at_key(&key)) ?;
             s.eat_comma_curly(i) ?;
             h.insert(k, v);
         }
@@ -809,8 +1039,8 @@ impl<T> SerJson for Box<T> where T: SerJson {
     }
 }
 
-impl<T> DeJson for Box<T> where T: DeJson {
-    fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Box<T>, DeJsonErr> {
+impl<'de, T> DeJson<'de> for Box<T> where T: DeJson<'de> {
+    fn de_json(s: &mut DeJsonState, i: &mut Chars<'de>) -> Result<Box<T>, DeJsonErr> {
         Ok(Box::new(DeJson::de_json(s, i) ?))
     }
 }
\ No newline at end of file
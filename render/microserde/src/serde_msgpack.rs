@@ -0,0 +1,578 @@
+use std::borrow::Cow;
+use std::collections::{HashMap};
+use std::convert::TryInto;
+use std::hash::Hash;
+
+// MessagePack, for data shared with tools in other languages. structs are maps keyed by their
+// field names and enums are externally tagged: a unit variant is its name as a string, other
+// variants a map of the name to the fields. a tuple struct or variant is an array, or just
+// the value when it has one field. readers accept any size of integer that fits the type
+
+pub trait SerMsgPack {
+    fn serialize_msgpack(&self) -> Vec<u8> {
+        let mut s = Vec::new();
+        self.ser_msgpack(&mut s);
+        s
+    }
+
+    fn ser_msgpack(&self, s: &mut Vec<u8>);
+}
+
+// 'de is the lifetime of the input, &'de [u8] and &'de str borrow from it
+pub trait DeMsgPack<'de>: Sized {
+    fn deserialize_msgpack(d: &'de [u8]) -> Result<Self, DeMsgPackErr> {
+        DeMsgPack::de_msgpack(&mut 0, d)
+    }
+
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Self, DeMsgPackErr>;
+}
+
+pub struct DeMsgPackErr {
+    pub o: usize,
+    pub msg: String
+}
+
+impl DeMsgPackErr {
+    pub fn missing(o: usize, ty: &str, field: &str) -> DeMsgPackErr {
+        DeMsgPackErr {o, msg: format!("Field {}.{} not found", ty, field)}
+    }
+
+    pub fn unknown_variant(o: usize, ty: &str, variant: &str) -> DeMsgPackErr {
+        DeMsgPackErr {o, msg: format!("Enum {} has no variant {}", ty, variant)}
+    }
+
+    fn unexpected(o: usize, expected: &str, d: &[u8]) -> DeMsgPackErr {
+        match d.get(o) {
+            Some(byte) => DeMsgPackErr {o, msg: format!("Expected {} found {}", expected, msgpack_type_name(*byte))},
+            None => DeMsgPackErr {o, msg: format!("Expected {} found the end of the data", expected)}
+        }
+    }
+}
+
+impl std::fmt::Debug for DeMsgPackErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MessagePack deserialize error at:{} {}", self.o, self.msg)
+    }
+}
+
+fn msgpack_type_name(byte: u8) -> &'static str {
+    match byte {
+        0x00..=0x7f | 0xcc..=0xd3 | 0xe0..=0xff => "an integer",
+        0x80..=0x8f | 0xde | 0xdf => "a map",
+        0x90..=0x9f | 0xdc | 0xdd => "an array",
+        0xa0..=0xbf | 0xd9..=0xdb => "a string",
+        0xc0 => "nil",
+        0xc2 | 0xc3 => "a bool",
+        0xc4..=0xc6 => "bytes",
+        0xca | 0xcb => "a float",
+        0xc7..=0xc9 | 0xd4..=0xd8 => "an extension",
+        _ => "an unused marker"
+    }
+}
+
+fn msgpack_take<'de>(o: &mut usize, d: &'de [u8], len: usize) -> Result<&'de [u8], DeMsgPackErr> {
+    if *o > d.len() || len > d.len() - *o {
+        return Err(DeMsgPackErr {o: *o, msg: format!("Unexpected end of data, wanted {} bytes but size is {}", len, d.len())})
+    }
+    let r = &d[*o..*o + len];
+    *o += len;
+    Ok(r)
+}
+
+fn msgpack_byte(o: &mut usize, d: &[u8]) -> Result<u8, DeMsgPackErr> {
+    Ok(msgpack_take(o, d, 1) ?[0])
+}
+
+// a big endian unsigned integer of 1, 2, 4 or 8 bytes
+fn msgpack_uint(o: &mut usize, d: &[u8], len: usize) -> Result<u64, DeMsgPackErr> {
+    Ok(msgpack_take(o, d, len) ?.iter().fold(0u64, | v, b | (v << 8) | *b as u64))
+}
+
+// the smallest of a fix marker holding the length, a 1, 2 or 4 byte length
+fn msgpack_write_len(len: usize, fix: Option<(u8, usize)>, marker8: Option<u8>, marker16: u8, s: &mut Vec<u8>) {
+    if let Some((fix, _)) = fix.filter( | (_, fix_max) | len <= *fix_max) {
+        s.push(fix | len as u8);
+    }
+    else if let (Some(marker8), true) = (marker8, len <= 0xff) {
+        s.push(marker8);
+        s.push(len as u8);
+    }
+    else if len <= 0xffff {
+        s.push(marker16);
+        s.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+    else {
+        s.push(marker16 + 1);
+        s.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+pub fn msgpack_array_len(len: usize, s: &mut Vec<u8>) {
+    msgpack_write_len(len, Some((0x90, 15)), None, 0xdc, s);
+}
+
+pub fn msgpack_map_len(len: usize, s: &mut Vec<u8>) {
+    msgpack_write_len(len, Some((0x80, 15)), None, 0xde, s);
+}
+
+pub fn msgpack_nil(s: &mut Vec<u8>) {
+    s.push(0xc0);
+}
+
+pub fn msgpack_uint_value(v: u64, s: &mut Vec<u8>) {
+    if v < 0x80 {
+        s.push(v as u8);
+    }
+    else if v <= 0xff {
+        s.push(0xcc);
+        s.push(v as u8);
+    }
+    else if v <= 0xffff {
+        s.push(0xcd);
+        s.extend_from_slice(&(v as u16).to_be_bytes());
+    }
+    else if v <= 0xffff_ffff {
+        s.push(0xce);
+        s.extend_from_slice(&(v as u32).to_be_bytes());
+    }
+    else {
+        s.push(0xcf);
+        s.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+pub fn msgpack_int_value(v: i64, s: &mut Vec<u8>) {
+    if v >= 0 {
+        msgpack_uint_value(v as u64, s)
+    }
+    else if v >= -32 {
+        s.push(v as u8);
+    }
+    else if v >= std::i8::MIN as i64 {
+        s.push(0xd0);
+        s.push(v as u8);
+    }
+    else if v >= std::i16::MIN as i64 {
+        s.push(0xd1);
+        s.extend_from_slice(&(v as i16).to_be_bytes());
+    }
+    else if v >= std::i32::MIN as i64 {
+        s.push(0xd2);
+        s.extend_from_slice(&(v as i32).to_be_bytes());
+    }
+    else {
+        s.push(0xd3);
+        s.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+// reads any integer
+pub fn msgpack_read_int(o: &mut usize, d: &[u8]) -> Result<i128, DeMsgPackErr> {
+    let start = *o;
+    let byte = msgpack_byte(o, d) ?;
+    Ok(match byte {
+        0x00..=0x7f => byte as i128,
+        0xe0..=0xff => byte as i8 as i128,
+        0xcc => msgpack_uint(o, d, 1) ? as i128,
+        0xcd => msgpack_uint(o, d, 2) ? as i128,
+        0xce => msgpack_uint(o, d, 4) ? as i128,
+        0xcf => msgpack_uint(o, d, 8) ? as i128,
+        0xd0 => msgpack_uint(o, d, 1) ? as u8 as i8 as i128,
+        0xd1 => msgpack_uint(o, d, 2) ? as u16 as i16 as i128,
+        0xd2 => msgpack_uint(o, d, 4) ? as u32 as i32 as i128,
+        0xd3 => msgpack_uint(o, d, 8) ? as i64 as i128,
+        _ => return Err(DeMsgPackErr::unexpected(start, "an integer", d))
+    })
+}
+
+fn msgpack_read_len(o: &mut usize, d: &[u8], what: &str, fix: u8, marker8: Option<u8>, marker16: u8) -> Result<usize, DeMsgPackErr> {
+    let start = *o;
+    let byte = msgpack_byte(o, d) ?;
+    let fix_mask = if fix == 0xa0 {0xe0} else {0xf0};
+    if byte & fix_mask == fix {
+        return Ok((byte & !fix_mask) as usize)
+    }
+    if Some(byte) == marker8 {
+        return Ok(msgpack_uint(o, d, 1) ? as usize)
+    }
+    if byte == marker16 {
+        return Ok(msgpack_uint(o, d, 2) ? as usize)
+    }
+    if byte == marker16 + 1 {
+        return Ok(msgpack_uint(o, d, 4) ? as usize)
+    }
+    Err(DeMsgPackErr::unexpected(start, what, d))
+}
+
+pub fn msgpack_read_array(o: &mut usize, d: &[u8]) -> Result<usize, DeMsgPackErr> {
+    msgpack_read_len(o, d, "an array", 0x90, None, 0xdc)
+}
+
+pub fn msgpack_read_map(o: &mut usize, d: &[u8]) -> Result<usize, DeMsgPackErr> {
+    msgpack_read_len(o, d, "a map", 0x80, None, 0xde)
+}
+
+// true while an array or map has items left, counting them down
+pub fn msgpack_more(_o: &mut usize, _d: &[u8], len: &mut usize) -> Result<bool, DeMsgPackErr> {
+    if *len == 0 {
+        return Ok(false)
+    }
+    *len -= 1;
+    Ok(true)
+}
+
+// the next item of an array that has to be there
+pub fn msgpack_next(o: &mut usize, d: &[u8], len: &mut usize) -> Result<(), DeMsgPackErr> {
+    if !msgpack_more(o, d, len) ? {
+        return Err(DeMsgPackErr {o: *o, msg: "Array has fewer items than expected".to_string()})
+    }
+    Ok(())
+}
+
+// the end of an array that can't have more items
+pub fn msgpack_finish(o: &mut usize, _d: &[u8], len: &mut usize) -> Result<(), DeMsgPackErr> {
+    if *len != 0 {
+        return Err(DeMsgPackErr {o: *o, msg: format!("Array has {} more items than expected", len)})
+    }
+    Ok(())
+}
+
+pub fn msgpack_read_str<'de>(o: &mut usize, d: &'de [u8]) -> Result<&'de str, DeMsgPackErr> {
+    let len = msgpack_read_len(o, d, "a string", 0xa0, Some(0xd9), 0xda) ?;
+    let start = *o;
+    let bytes = msgpack_take(o, d, len) ?;
+    std::str::from_utf8(bytes).map_err( | _ | DeMsgPackErr {o: start, msg: "String is not UTF-8".to_string()})
+}
+
+// reads the name of an enum variant, and if it has a value after it
+pub fn msgpack_read_variant<'de>(o: &mut usize, d: &'de [u8]) -> Result<(&'de str, bool), DeMsgPackErr> {
+    match d.get(*o) {
+        Some(0x81) => {
+            *o += 1;
+            Ok((msgpack_read_str(o, d) ?, true))
+        }
+        Some(0xa0..=0xbf) | Some(0xd9..=0xdb) => Ok((msgpack_read_str(o, d) ?, false)),
+        _ => Err(DeMsgPackErr::unexpected(*o, "an enum variant", d))
+    }
+}
+
+// reads past a value, like the fields a struct doesn't know
+pub fn msgpack_skip(o: &mut usize, d: &[u8]) -> Result<(), DeMsgPackErr> {
+    let start = *o;
+    let byte = msgpack_byte(o, d) ?;
+    let len = match byte {
+        0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => 0,
+        0x80..=0x8f | 0xde | 0xdf => {
+            *o = start;
+            for _ in 0..msgpack_read_map(o, d) ? * 2 {
+                msgpack_skip(o, d) ?;
+            }
+            return Ok(())
+        }
+        0x90..=0x9f | 0xdc | 0xdd => {
+            *o = start;
+            for _ in 0..msgpack_read_array(o, d) ? {
+                msgpack_skip(o, d) ?;
+            }
+            return Ok(())
+        }
+        0xa0..=0xbf => (byte & 0x1f) as usize,
+        0xc4 | 0xd9 => msgpack_uint(o, d, 1) ? as usize,
+        0xc5 | 0xda => msgpack_uint(o, d, 2) ? as usize,
+        0xc6 | 0xdb => msgpack_uint(o, d, 4) ? as usize,
+        0xc7 => msgpack_uint(o, d, 1) ? as usize + 1,
+        0xc8 => msgpack_uint(o, d, 2) ? as usize + 1,
+        0xc9 => msgpack_uint(o, d, 4) ? as usize + 1,
+        0xca => 4,
+        0xcb => 8,
+        0xcc | 0xd0 => 1,
+        0xcd | 0xd1 => 2,
+        0xce | 0xd2 => 4,
+        0xcf | 0xd3 => 8,
+        0xd4..=0xd8 => 1 + (1 << (byte - 0xd4)),
+        _ => return Err(DeMsgPackErr::unexpected(start, "a value", d))
+    };
+    msgpack_take(o, d, len) ?;
+    Ok(())
+}
+
+macro_rules! impl_ser_de_msgpack_int {
+    ( $ ty: ident, $ write: ident, $ as_ty: ident) => {
+        impl SerMsgPack for $ ty {
+            fn ser_msgpack(&self, s: &mut Vec<u8>) {
+                $ write(*self as $ as_ty, s);
+            }
+        }
+
+        impl<'de> DeMsgPack<'de> for $ ty {
+            fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result< $ ty, DeMsgPackErr> {
+                let start = *o;
+                let v = msgpack_read_int(o, d) ?;
+                v.try_into().map_err( | _ | DeMsgPackErr {o: start, msg: format!("Value {} out of range for {}", v, stringify!( $ ty))})
+            }
+        }
+    }
+}
+
+impl_ser_de_msgpack_int!(u8, msgpack_uint_value, u64);
+impl_ser_de_msgpack_int!(u16, msgpack_uint_value, u64);
+impl_ser_de_msgpack_int!(u32, msgpack_uint_value, u64);
+impl_ser_de_msgpack_int!(u64, msgpack_uint_value, u64);
+impl_ser_de_msgpack_int!(usize, msgpack_uint_value, u64);
+impl_ser_de_msgpack_int!(i8, msgpack_int_value, i64);
+impl_ser_de_msgpack_int!(i16, msgpack_int_value, i64);
+impl_ser_de_msgpack_int!(i32, msgpack_int_value, i64);
+impl_ser_de_msgpack_int!(i64, msgpack_int_value, i64);
+
+impl SerMsgPack for f32 {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        s.push(0xca);
+        s.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl SerMsgPack for f64 {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        s.push(0xcb);
+        s.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+// floats read either size, and integers
+impl<'de> DeMsgPack<'de> for f64 {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<f64, DeMsgPackErr> {
+        match d.get(*o) {
+            Some(0xca) => {
+                *o += 1;
+                Ok(f32::from_bits(msgpack_uint(o, d, 4) ? as u32) as f64)
+            }
+            Some(0xcb) => {
+                *o += 1;
+                Ok(f64::from_bits(msgpack_uint(o, d, 8) ?))
+            }
+            Some(0x00..=0x7f) | Some(0xcc..=0xd3) | Some(0xe0..=0xff) => Ok(msgpack_read_int(o, d) ? as f64),
+            _ => Err(DeMsgPackErr::unexpected(*o, "a float", d))
+        }
+    }
+}
+
+impl<'de> DeMsgPack<'de> for f32 {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<f32, DeMsgPackErr> {
+        Ok(f64::de_msgpack(o, d) ? as f32)
+    }
+}
+
+impl SerMsgPack for bool {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        s.push(if *self {0xc3} else {0xc2});
+    }
+}
+
+impl<'de> DeMsgPack<'de> for bool {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<bool, DeMsgPackErr> {
+        match d.get(*o) {
+            Some(0xc2) => {*o += 1; Ok(false)}
+            Some(0xc3) => {*o += 1; Ok(true)}
+            _ => Err(DeMsgPackErr::unexpected(*o, "a bool", d))
+        }
+    }
+}
+
+impl SerMsgPack for str {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        msgpack_write_len(self.len(), Some((0xa0, 31)), Some(0xd9), 0xda, s);
+        s.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl SerMsgPack for String {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        self.as_str().ser_msgpack(s)
+    }
+}
+
+impl<'a> SerMsgPack for &'a str {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        (**self).ser_msgpack(s)
+    }
+}
+
+impl<'a> SerMsgPack for Cow<'a, str> {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        self.as_ref().ser_msgpack(s)
+    }
+}
+
+impl<'de> DeMsgPack<'de> for &'de str {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<&'de str, DeMsgPackErr> {
+        msgpack_read_str(o, d)
+    }
+}
+
+impl<'de> DeMsgPack<'de> for String {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<String, DeMsgPackErr> {
+        Ok(msgpack_read_str(o, d) ?.to_string())
+    }
+}
+
+// MessagePack strings have no escapes, so this always borrows
+impl<'de> DeMsgPack<'de> for Cow<'de, str> {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Cow<'de, str>, DeMsgPackErr> {
+        Ok(Cow::Borrowed(msgpack_read_str(o, d) ?))
+    }
+}
+
+// bytes are written as bin, where a Vec<u8> is an array of integers
+impl<'a> SerMsgPack for &'a [u8] {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        msgpack_write_len(self.len(), None, Some(0xc4), 0xc5, s);
+        s.extend_from_slice(self);
+    }
+}
+
+impl<'de> DeMsgPack<'de> for &'de [u8] {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<&'de [u8], DeMsgPackErr> {
+        let start = *o;
+        let len = match msgpack_byte(o, d) ? {
+            0xc4 => msgpack_uint(o, d, 1) ?,
+            0xc5 => msgpack_uint(o, d, 2) ?,
+            0xc6 => msgpack_uint(o, d, 4) ?,
+            _ => return Err(DeMsgPackErr::unexpected(start, "bytes", d))
+        };
+        msgpack_take(o, d, len as usize)
+    }
+}
+
+impl<T> SerMsgPack for Option<T> where T: SerMsgPack {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        if let Some(v) = self {
+            v.ser_msgpack(s);
+        }
+        else {
+            msgpack_nil(s);
+        }
+    }
+}
+
+impl<'de, T> DeMsgPack<'de> for Option<T> where T: DeMsgPack<'de> {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Option<T>, DeMsgPackErr> {
+        if d.get(*o) == Some(&0xc0) {
+            *o += 1;
+            return Ok(None)
+        }
+        Ok(Some(DeMsgPack::de_msgpack(o, d) ?))
+    }
+}
+
+impl<T> SerMsgPack for [T] where T: SerMsgPack {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        msgpack_array_len(self.len(), s);
+        for item in self {
+            item.ser_msgpack(s);
+        }
+    }
+}
+
+impl<T> SerMsgPack for Vec<T> where T: SerMsgPack {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        self.as_slice().ser_msgpack(s)
+    }
+}
+
+impl<'de, T> DeMsgPack<'de> for Vec<T> where T: DeMsgPack<'de> {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Vec<T>, DeMsgPackErr> {
+        let mut len = msgpack_read_array(o, d) ?;
+        let mut out = Vec::new();
+        while msgpack_more(o, d, &mut len) ? {
+            out.push(DeMsgPack::de_msgpack(o, d) ?);
+        }
+        Ok(out)
+    }
+}
+
+macro_rules!msgpack_array_impl {
+    ( $($count:expr),*) => {
+        $(
+        impl<T> SerMsgPack for [T; $count] where T: SerMsgPack {
+            fn ser_msgpack(&self, s: &mut Vec<u8>) {
+                self[..].ser_msgpack(s)
+            }
+        }
+
+        impl<'de, T> DeMsgPack<'de> for [T; $count] where T: DeMsgPack<'de> {
+            fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Self, DeMsgPackErr> {
+                let start = *o;
+                let items: Vec<T> = DeMsgPack::de_msgpack(o, d) ?;
+                let len = items.len();
+                items.try_into().map_err( | _ | DeMsgPackErr {o: start, msg: format!("Array of {} items expected {}", len, $count)})
+            }
+        }
+        )*
+    }
+}
+
+msgpack_array_impl!(2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32);
+
+macro_rules!impl_ser_de_msgpack_tuple {
+    ( $ len: expr, $ ( $ name: ident: $ index: tt), *) => {
+        impl< $ ( $ name), *> SerMsgPack for ( $ ( $ name), *) where $ ( $ name: SerMsgPack), * {
+            fn ser_msgpack(&self, s: &mut Vec<u8>) {
+                msgpack_array_len( $ len, s);
+                $ (self. $ index.ser_msgpack(s);) *
+            }
+        }
+
+        impl<'de, $ ( $ name), *> DeMsgPack<'de> for ( $ ( $ name), *) where $ ( $ name: DeMsgPack<'de>), * {
+            fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Self, DeMsgPackErr> {
+                let mut len = msgpack_read_array(o, d) ?;
+                let r = ( $ ({
+                    msgpack_next(o, d, &mut len) ?;
+                    < $ name as DeMsgPack>::de_msgpack(o, d) ?
+                }), *);
+                msgpack_finish(o, d, &mut len) ?;
+                Ok(r)
+            }
+        }
+    }
+}
+
+impl_ser_de_msgpack_tuple!(2, A: 0, B: 1);
+impl_ser_de_msgpack_tuple!(3, A: 0, B: 1, C: 2);
+impl_ser_de_msgpack_tuple!(4, A: 0, B: 1, C: 2, D: 3);
+
+impl<K, V> SerMsgPack for HashMap<K, V> where K: SerMsgPack,
+V: SerMsgPack {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        msgpack_map_len(self.len(), s);
+        for (k, v) in self {
+            k.ser_msgpack(s);
+            v.ser_msgpack(s);
+        }
+    }
+}
+
+impl<'de, K, V> DeMsgPack<'de> for HashMap<K, V> where K: DeMsgPack<'de> + Eq + Hash,
+V: DeMsgPack<'de> {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Self, DeMsgPackErr> {
+        let mut len = msgpack_read_map(o, d) ?;
+        let mut h = HashMap::new();
+        while msgpack_more(o, d, &mut len) ? {
+            let k = DeMsgPack::de_msgpack(o, d) ?;
+            let v = DeMsgPack::de_msgpack(o, d) ?;
+            h.insert(k, v);
+        }
+        Ok(h)
+    }
+}
+
+impl<T> SerMsgPack for Box<T> where T: SerMsgPack {
+    fn ser_msgpack(&self, s: &mut Vec<u8>) {
+        (**self).ser_msgpack(s)
+    }
+}
+
+impl<'de, T> DeMsgPack<'de> for Box<T> where T: DeMsgPack<'de> {
+    fn de_msgpack(o: &mut usize, d: &'de [u8]) -> Result<Box<T>, DeMsgPackErr> {
+        Ok(Box::new(DeMsgPack::de_msgpack(o, d) ?))
+    }
+}
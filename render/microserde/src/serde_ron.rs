@@ -1,7 +1,10 @@
 use std::collections::{HashMap};
 use std::hash::Hash;
+use std::borrow::Cow;
 use std::str::Chars;
+use crate::de_err::*;
 
+#[derive(Default)]
 pub struct SerRonState {
     pub out: String
 }
@@ -19,6 +22,12 @@ impl SerRonState {
         self.out.push(':');
     }
     
+    pub fn label(&mut self, label: &str) {
+        self.out.push('"');
+        self.out.push_str(label);
+        self.out.push('"');
+    }
+    
     pub fn conl(&mut self) {
         self.out.push_str(",\n")
     }
@@ -32,6 +41,26 @@ impl SerRonState {
         self.out.push(')');
     }
     
+    // writes the fields of a struct valued `value` into the struct being written,
+    // for #[flatten] fields and the newtype variants of internally tagged enums
+    pub fn ser_flatten<T>(&mut self, d: usize, value: &T) where T: SerRon + ?Sized {
+        let mut inner = SerRonState::default();
+        value.ser_ron(d, &mut inner);
+        let body = inner.out.trim();
+        if body.len() >= 2 && body.starts_with('(') && body.ends_with(')') {
+            let body = body[1..body.len() - 1].trim_start_matches('\n').trim_end();
+            if body.len() > 0 {
+                self.out.push_str(body);
+                if body.ends_with(',') {
+                    self.out.push('\n');
+                }
+                else {
+                    self.conl();
+                }
+            }
+        }
+    }
+    
 }
 
 pub trait SerRon {
@@ -47,18 +76,18 @@ pub trait SerRon {
     fn ser_ron(&self, d: usize, s: &mut SerRonState);
 }
 
-pub trait DeRon: Sized {
+// 'de is the lifetime of the input, types like &'de str borrow from it
+pub trait DeRon<'de>: Sized {
     
-    fn deserialize_ron(input: &str) -> Result<Self,
+    fn deserialize_ron(input: &'de str) -> Result<Self,
     DeRonErr> {
         let mut state = DeRonState::default();
         let mut chars = input.chars();
         state.next(&mut chars);
-        state.next_tok(&mut chars) ?;
-        DeRon::de_ron(&mut state, &mut chars)
+        state.next_tok(&mut chars).and_then( | _ | DeRon::de_ron(&mut state, &mut chars)).map_err( | err | err.with_source(input))
     }
     
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Self,
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Self,
     DeRonErr>;
 }
 
@@ -95,18 +124,60 @@ pub struct DeRonState {
     pub numbuf: String,
     pub identbuf: String,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // where the current token starts, errors are reported there
+    pub tok_line: usize,
+    pub tok_col: usize,
+    // where the last string token is in the input, for borrowing it
+    str_start: usize,
+    str_len: usize,
+    str_input_end: usize,
+    str_escaped: bool
 }
 
 pub struct DeRonErr {
     pub msg: String,
+    // the field path of the value that failed, like builders.main.workspaces or items[2].name
+    pub path: String,
+    pub expected: Option<String>,
+    pub found: Option<String>,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // the line of input the error is on, filled in by deserialize_ron
+    pub source_line: Option<String>
+}
+
+impl DeRonErr {
+    pub fn new(msg: String, line: usize, col: usize) -> Self {
+        DeRonErr {msg, path: String::new(), expected: None, found: None, line, col, source_line: None}
+    }
+    
+    // called on the way out of a field, so the path is built from the inside out
+    pub fn at_key(mut self, key: &str) -> Self {
+        path_push_key(&mut self.path, key);
+        self
+    }
+    
+    pub fn at_index(mut self, index: usize) -> Self {
+        path_push_index(&mut self.path, index);
+        self
+    }
+    
+    pub fn with_source(mut self, input: &str) -> Self {
+        self.source_line = source_line(input, self.line);
+        self
+    }
 }
 
 impl std::fmt::Debug for DeRonErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Ron Deserialize error: {}, line:{} col:{}", self.msg, self.line + 1, self.col + 1)
+        fmt_de_err(f, "Ron", &self.msg, &self.path, self.line, self.col, &self.source_line)
+    }
+}
+
+impl std::fmt::Display for DeRonErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
     }
 }
 
@@ -119,7 +190,7 @@ impl DeRonState {
                 self.col = 0;
             }
             else {
-                self.col = 0;
+                self.col += 1;
             }
         }
         else {
@@ -127,32 +198,75 @@ impl DeRonState {
         }
     }
     
+    fn err(&self, msg: String) -> DeRonErr {
+        DeRonErr::new(msg, self.tok_line, self.tok_col)
+    }
+    
     pub fn err_exp(&self, name: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Unexpected key {}", name), line: self.line, col: self.col}
+        self.err(format!("Unexpected key {}", name))
     }
     
     pub fn err_nf(&self, name: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Key not found {}", name), line: self.line, col: self.col}
+        self.err(format!("Key not found {}", name))
     }
     
     pub fn err_enum(&self, name: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Enum not defined {}", name), line: self.line, col: self.col}
+        self.err(format!("Enum not defined {}", name))
+    }
+    
+    pub fn err_untagged(&self, name: &str) -> DeRonErr {
+        self.err(format!("Data did not match any variant of {}", name))
     }
     
     pub fn err_token(&self, what: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Unexpected token {:?} expected {} ", self.tok, what), line: self.line, col: self.col}
+        let found = self.tok_desc();
+        let mut err = self.err(format!("Expected {} found {}", what, found));
+        err.expected = Some(what.to_string());
+        err.found = Some(found);
+        err
     }
     
     pub fn err_range(&self, what: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Value out of range {} ", what), line: self.line, col: self.col}
+        self.err(format!("Value out of range {} ", what))
     }
     
     pub fn err_type(&self, what: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Token wrong type {} ", what), line: self.line, col: self.col}
+        self.err(format!("Token wrong type {} ", what))
     }
     
     pub fn err_parse(&self, what: &str) -> DeRonErr {
-        DeRonErr {msg: format!("Cannot parse {} ", what), line: self.line, col: self.col}
+        self.err(format!("Cannot parse {} ", what))
+    }
+    
+    // the current token as it reads in an error message
+    pub fn tok_desc(&self) -> String {
+        match &self.tok {
+            DeRonTok::Ident => format!("identifier {}", self.identbuf),
+            DeRonTok::Str => format!("string {:?}", self.strbuf),
+            DeRonTok::U64(_) | DeRonTok::I64(_) | DeRonTok::F64(_) => format!("number {}", self.numbuf),
+            DeRonTok::Bool(v) => format!("{}", v),
+            DeRonTok::Char(c) => format!("char {:?}", c),
+            DeRonTok::Colon => "':'".to_string(),
+            DeRonTok::CurlyOpen => "'{'".to_string(),
+            DeRonTok::CurlyClose => "'}'".to_string(),
+            DeRonTok::ParenOpen => "'('".to_string(),
+            DeRonTok::ParenClose => "')'".to_string(),
+            DeRonTok::BlockOpen => "'['".to_string(),
+            DeRonTok::BlockClose => "']'".to_string(),
+            DeRonTok::Comma => "','".to_string(),
+            DeRonTok::Bof => "start of input".to_string(),
+            DeRonTok::Eof => "end of input".to_string(),
+        }
+    }
+    
+    // the current token as a map key, for the path of errors in its value
+    pub fn tok_key(&self) -> String {
+        match &self.tok {
+            DeRonTok::Ident => self.identbuf.clone(),
+            DeRonTok::Str => self.strbuf.clone(),
+            DeRonTok::U64(_) | DeRonTok::I64(_) | DeRonTok::F64(_) => self.numbuf.clone(),
+            tok => format!("{:?}", tok)
+        }
     }
     
     pub fn eat_comma_paren(&mut self, i: &mut Chars) -> Result<(), DeRonErr> {
@@ -340,6 +454,24 @@ impl DeRonState {
         Err(self.err_token("boolean"))
     }
     
+    // the current string token as a slice of the input, which only works without escapes
+    pub fn as_borrowed_str<'de>(&mut self, i: &Chars<'de>) -> Result<&'de str, DeRonErr> {
+        if self.tok != DeRonTok::Str {
+            return Err(self.err_token("string"))
+        }
+        let rest = i.as_str();
+        if self.str_input_end != rest.as_ptr() as usize + rest.len() || self.str_start + self.str_len > rest.as_ptr() as usize {
+            return Err(self.err_type("borrowed string, not read from this input"))
+        }
+        if self.str_escaped {
+            return Err(self.err_type("borrowed string, it contains escapes"))
+        }
+        // the string lies before the position of i in the same input
+        unsafe {
+            Ok(std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.str_start as *const u8, self.str_len)))
+        }
+    }
+    
     pub fn as_string(&mut self) -> Result<String, DeRonErr> {
         if let DeRonTok::Str = &mut self.tok {
             let mut val = String::new();
@@ -349,11 +481,81 @@ impl DeRonState {
         Err(self.err_token("string"))
     }
     
+    // writes the key in identbuf to out, as key:
+    pub fn capture_key(&mut self, out: &mut SerRonState) {
+        out.out.push_str(&self.identbuf);
+        out.out.push(':');
+    }
+    
+    // consumes one value and writes it to out, so it can be deserialized later with de_captured
+    pub fn capture_value(&mut self, out: &mut SerRonState, i: &mut Chars) -> Result<(), DeRonErr> {
+        let mut depth = 0;
+        loop {
+            let mut is_ident = false;
+            match self.tok {
+                DeRonTok::ParenOpen => {depth += 1; out.out.push('(')},
+                DeRonTok::BlockOpen => {depth += 1; out.out.push('[')},
+                DeRonTok::CurlyOpen => {depth += 1; out.out.push('{')},
+                DeRonTok::ParenClose | DeRonTok::BlockClose | DeRonTok::CurlyClose if depth == 0 => {
+                    return Err(self.err_token("value"))
+                },
+                DeRonTok::ParenClose => {depth -= 1; out.out.push(')')},
+                DeRonTok::BlockClose => {depth -= 1; out.out.push(']')},
+                DeRonTok::CurlyClose => {depth -= 1; out.out.push('}')},
+                DeRonTok::Colon if depth > 0 => out.out.push(':'),
+                DeRonTok::Comma if depth > 0 => out.out.push(','),
+                DeRonTok::Ident => {
+                    is_ident = true;
+                    out.out.push_str(&self.identbuf)
+                },
+                DeRonTok::Str => self.strbuf.ser_ron(0, out),
+                DeRonTok::Char(c) => {
+                    out.out.push('\'');
+                    if c == '\'' || c == '\\' {
+                        out.out.push('\\');
+                    }
+                    out.out.push(c);
+                    out.out.push('\'');
+                },
+                DeRonTok::U64(_) | DeRonTok::I64(_) | DeRonTok::F64(_) => out.out.push_str(&self.numbuf),
+                DeRonTok::Bool(value) => value.ser_ron(0, out),
+                _ => return Err(self.err_token("value"))
+            }
+            self.next_tok(i) ?;
+            // an ident followed by ( is a struct or enum variant
+            if depth == 0 && !(is_ident && self.tok == DeRonTok::ParenOpen) {
+                return Ok(())
+            }
+        }
+    }
+    
+    pub fn skip_value(&mut self, i: &mut Chars) -> Result<(), DeRonErr> {
+        self.capture_value(&mut SerRonState::default(), i)
+    }
+    
+    // deserializes captured input with f, errors are reported at the current position.
+    // the input is a copy, so what f reads cannot borrow from it
+    pub fn de_captured<T, F>(&self, input: &str, f: F) -> Result<T, DeRonErr>
+    where F: FnOnce(&mut DeRonState, &mut Chars) -> Result<T, DeRonErr> {
+        let mut state = DeRonState::default();
+        let mut chars = input.chars();
+        state.next(&mut chars);
+        let result = state.next_tok(&mut chars).and_then( | _ | f(&mut state, &mut chars));
+        match result {
+            Ok(_) if state.tok != DeRonTok::Eof => Err(self.err_parse("captured value, trailing data")),
+            Ok(value) => Ok(value),
+            Err(err) => Err(DeRonErr {line: self.tok_line, col: self.tok_col, ..err})
+        }
+    }
+    
     pub fn next_tok(&mut self, i: &mut Chars) -> Result<(), DeRonErr> {
         loop {
             while self.cur == '\n' || self.cur == '\r' || self.cur == '\t' || self.cur == ' ' {
                 self.next(i);
             }
+            // col is one past cur, except at the end of the input
+            self.tok_line = self.line;
+            self.tok_col = if self.cur == '\0' {self.col} else {self.col.saturating_sub(1)};
             match self.cur {
                 '\0' => {
                     self.tok = DeRonTok::Eof;
@@ -504,12 +706,20 @@ impl DeRonState {
                     }
                     self.next(i);
                     self.tok = DeRonTok::Char(chr);
+                    return Ok(())
                 },
                 '"' => {
                     self.strbuf.truncate(0);
+                    self.str_escaped = false;
+                    let start = i.as_str();
                     self.next(i);
                     while self.cur != '"' {
                         if self.cur == '\\' {
+                            if !self.str_escaped {
+                                // from the first escape on the string is built char by char
+                                self.str_escaped = true;
+                                self.strbuf.push_str(&start[..start.len() - i.as_str().len() - 1]);
+                            }
                             self.next(i);
                             match self.cur {
                                 'n' => self.strbuf.push('\n'),
@@ -527,16 +737,25 @@ impl DeRonState {
                             if self.cur == '\0' {
                                 return Err(self.err_parse("string"));
                             }
-                            self.strbuf.push(self.cur);
+                            if self.str_escaped {
+                                self.strbuf.push(self.cur);
+                            }
                             self.next(i);
                         }
                     }
+                    let len = start.len() - i.as_str().len() - 1;
+                    if !self.str_escaped {
+                        self.strbuf.push_str(&start[..len]);
+                    }
+                    self.str_start = start.as_ptr() as usize;
+                    self.str_len = len;
+                    self.str_input_end = start.as_ptr() as usize + start.len();
                     self.next(i);
                     self.tok = DeRonTok::Str;
                     return Ok(())
                 },
                 _ => {
-                    return Err(self.err_token("tokenizer"));
+                    return Err(self.err_parse(&format!("character {:?}", self.cur)));
                 }
             }
         }
@@ -551,8 +770,8 @@ macro_rules!impl_ser_de_ron_unsigned {
             }
         }
         
-        impl DeRon for $ ty {
-            fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeRon<'de> for $ ty {
+            fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeRonErr> {
                 //s.is_prefix(p, i) ?;
                 let val = s.u64_range( $ max as u64) ?;
@@ -571,8 +790,8 @@ macro_rules!impl_ser_de_ron_signed {
             }
         }
         
-        impl DeRon for $ ty {
-            fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeRon<'de> for $ ty {
+            fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeRonErr> {
                 //s.is_prefix(p, i) ?;
                 let val = s.i64_range( $ min as i64, $ max as i64) ?;
@@ -591,8 +810,8 @@ macro_rules!impl_ser_de_ron_float {
             }
         }
         
-        impl DeRon for $ ty {
-            fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result< $ ty,
+        impl<'de> DeRon<'de> for $ ty {
+            fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result< $ ty,
             DeRonErr> {
                 //s.is_prefix(p, i) ?;
                 let val = s.as_f64() ?;
@@ -626,8 +845,8 @@ impl<T> SerRon for Option<T> where T: SerRon {
     }
 }
 
-impl<T> DeRon for Option<T> where T: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Self,
+impl<'de, T> DeRon<'de> for Option<T> where T: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Self,
     DeRonErr> {
         if let DeRonTok::Ident = &s.tok {
             if s.identbuf == "None" {
@@ -650,15 +869,15 @@ impl SerRon for bool {
     }
 }
 
-impl DeRon for bool {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<bool, DeRonErr> {
+impl<'de> DeRon<'de> for bool {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<bool, DeRonErr> {
         let val = s.as_bool() ?;
         s.next_tok(i) ?;
         return Ok(val);
     }
 }
 
-impl SerRon for String {
+impl SerRon for str {
     fn ser_ron(&self, _d: usize, s: &mut SerRonState) {
         s.out.push('"');
         for c in self.chars() {
@@ -676,14 +895,52 @@ impl SerRon for String {
     }
 }
 
-impl DeRon for String {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<String, DeRonErr> {
+impl<'de> DeRon<'de> for String {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<String, DeRonErr> {
         let val = s.as_string() ?;
         s.next_tok(i) ?;
         return Ok(val);
     }
 }
 
+impl SerRon for String {
+    fn ser_ron(&self, d: usize, s: &mut SerRonState) {
+        self.as_str().ser_ron(d, s)
+    }
+}
+
+impl<'a> SerRon for &'a str {
+    fn ser_ron(&self, d: usize, s: &mut SerRonState) {
+        (**self).ser_ron(d, s)
+    }
+}
+
+// fails on strings with escapes, use Cow to fall back to a copy
+impl<'de> DeRon<'de> for &'de str {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<&'de str, DeRonErr> {
+        let val = s.as_borrowed_str(i) ?;
+        s.next_tok(i) ?;
+        return Ok(val);
+    }
+}
+
+impl<'a> SerRon for Cow<'a, str> {
+    fn ser_ron(&self, d: usize, s: &mut SerRonState) {
+        self.as_ref().ser_ron(d, s)
+    }
+}
+
+impl<'de> DeRon<'de> for Cow<'de, str> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Cow<'de, str>, DeRonErr> {
+        let val = match s.as_borrowed_str(i) {
+            Ok(val) => Cow::Borrowed(val),
+            Err(_) => Cow::Owned(s.as_string() ?)
+        };
+        s.next_tok(i) ?;
+        return Ok(val);
+    }
+}
+
 impl<T> SerRon for Vec<T> where T: SerRon {
     fn ser_ron(&self, d: usize, s: &mut SerRonState) {
         s.out.push_str("[\n");
@@ -697,13 +954,14 @@ impl<T> SerRon for Vec<T> where T: SerRon {
     }
 }
 
-impl<T> DeRon for Vec<T> where T: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Vec<T>, DeRonErr> {
+impl<'de, T> DeRon<'de> for Vec<T> where T: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Vec<T>, DeRonErr> {
         let mut out = Vec::new();
         s.block_open(i) ?;
         
         while s.tok != DeRonTok::BlockClose {
-            out.push(DeRon::de_ron(s, i) ?);
+            let index = out.len();
+            out.push(DeRon::de_ron(s, i).map_err( | err | err.at_index(index)) ?);
             s.eat_comma_block(i) ?;
         }
         s.block_close(i) ?;
@@ -725,10 +983,10 @@ impl<T> SerRon for [T] where T: SerRon {
     }
 }
 
-unsafe fn de_ron_array_impl_inner<T>(top: *mut T, count: usize, s: &mut DeRonState, i: &mut Chars) -> Result<(), DeRonErr> where T: DeRon {
+unsafe fn de_ron_array_impl_inner<'de, T>(top: *mut T, count: usize, s: &mut DeRonState, i: &mut Chars<'de>) -> Result<(), DeRonErr> where T: DeRon<'de> {
     s.paren_open(i) ?;
     for c in 0..count {
-        top.add(c).write(DeRon::de_ron(s, i) ?);
+        top.add(c).write(DeRon::de_ron(s, i).map_err( | err | err.at_index(c)) ?);
         s.eat_comma_paren(i) ?;
     }
     s.paren_close(i) ?;
@@ -738,8 +996,8 @@ unsafe fn de_ron_array_impl_inner<T>(top: *mut T, count: usize, s: &mut DeRonSta
 macro_rules!de_ron_array_impl {
     ( $ ( $ count: expr), *) => {
         $ (
-            impl<T> DeRon for [T; $ count] where T: DeRon {
-                fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Self,
+            impl<'de, T> DeRon<'de> for [T; $ count] where T: DeRon<'de> {
+                fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Self,
                 DeRonErr> {
                     unsafe {
                         let mut to = std::mem::MaybeUninit::<[T; $ count]>::uninit();
@@ -755,8 +1013,8 @@ macro_rules!de_ron_array_impl {
 
 de_ron_array_impl!(2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32);
 
-fn de_ron_comma_paren<T>(s: &mut DeRonState, i: &mut Chars) -> Result<T, DeRonErr> where T: DeRon {
-    let t = DeRon::de_ron(s, i);
+fn de_ron_comma_paren<'de, T>(s: &mut DeRonState, i: &mut Chars<'de>, index: usize) -> Result<T, DeRonErr> where T: DeRon<'de> {
+    let t = DeRon::de_ron(s, i).map_err( | err | err.at_index(index));
     s.eat_comma_paren(i) ?;
     t
 }
@@ -772,11 +1030,11 @@ B: SerRon {
     }
 }
 
-impl<A, B> DeRon for (A, B) where A: DeRon,
-B: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<(A, B), DeRonErr> {
+impl<'de, A, B> DeRon<'de> for (A, B) where A: DeRon<'de>,
+B: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<(A, B), DeRonErr> {
         s.paren_open(i) ?;
-        let r = (de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?);
+        let r = (de_ron_comma_paren(s, i, 0) ?, de_ron_comma_paren(s, i, 1) ?);
         s.paren_close(i) ?;
         Ok(r)
     }
@@ -796,12 +1054,12 @@ C: SerRon {
     }
 }
 
-impl<A, B, C> DeRon for (A, B, C) where A: DeRon,
-B: DeRon,
-C: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<(A, B, C), DeRonErr> {
+impl<'de, A, B, C> DeRon<'de> for (A, B, C) where A: DeRon<'de>,
+B: DeRon<'de>,
+C: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<(A, B, C), DeRonErr> {
         s.paren_open(i) ?;
-        let r = (de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?);
+        let r = (de_ron_comma_paren(s, i, 0) ?, de_ron_comma_paren(s, i, 1) ?, de_ron_comma_paren(s, i, 2) ?);
         s.paren_close(i) ?;
         Ok(r)
     }
@@ -824,13 +1082,13 @@ D: SerRon {
     }
 }
 
-impl<A, B, C, D> DeRon for (A, B, C, D) where A: DeRon,
-B: DeRon,
-C: DeRon,
-D: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<(A, B, C, D), DeRonErr> {
+impl<'de, A, B, C, D> DeRon<'de> for (A, B, C, D) where A: DeRon<'de>,
+B: DeRon<'de>,
+C: DeRon<'de>,
+D: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<(A, B, C, D), DeRonErr> {
         s.paren_open(i) ?;
-        let r = (de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?, de_ron_comma_paren(s, i) ?);
+        let r = (de_ron_comma_paren(s, i, 0) ?, de_ron_comma_paren(s, i, 1) ?, de_ron_comma_paren(s, i, 2) ?, de_ron_comma_paren(s, i, 3) ?);
         s.paren_close(i) ?;
         Ok(r)
     }
@@ -852,16 +1110,17 @@ V: SerRon {
     }
 }
 
-impl<K, V> DeRon for HashMap<K, V> where K: DeRon + Eq + Hash,
-V: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Self,
+impl<'de, K, V> DeRon<'de> for HashMap<K, V> where K: DeRon<'de> + Eq + Hash,
+V: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Self,
     DeRonErr> {
         let mut h = HashMap::new();
         s.curly_open(i) ?;
         while s.tok != DeRonTok::CurlyClose {
-            let k = DeRon::de_ron(s, i) ?;
+            let key = s.tok_key();
+            let k = DeRon::de_ron(s, i).map_err( | err | err.at_key(&key)) ?;
             s.colon(i) ?;
-            let v = DeRon::de_ron(s, i) ?;
+            let v = DeRon::de_ron(s, i).map_err( | err | err.at_key(&key)) ?;
             s.eat_comma_curly(i) ?;
             h.insert(k, v);
         }
@@ -876,8 +1135,8 @@ impl<T> SerRon for Box<T> where T: SerRon {
     }
 }
 
-impl<T> DeRon for Box<T> where T: DeRon {
-    fn de_ron(s: &mut DeRonState, i: &mut Chars) -> Result<Box<T>, DeRonErr> {
+impl<'de, T> DeRon<'de> for Box<T> where T: DeRon<'de> {
+    fn de_ron(s: &mut DeRonState, i: &mut Chars<'de>) -> Result<Box<T>, DeRonErr> {
         Ok(Box::new(DeRon::de_ron(s, i) ?))
     }
 }
\ No newline at end of file
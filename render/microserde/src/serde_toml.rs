@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use crate::toml::*;
+
+// TOML has to order values before tables, so it doesn't stream like JSON and RON:
+// values are turned into a Toml tree first, which is then written out
+
+pub trait SerToml {
+
+    fn serialize_toml(&self) -> String {
+        match self.ser_toml() {
+            Toml::Table(table) | Toml::InlineTable(table) => table.to_toml_string(),
+            value => value.to_string()
+        }
+    }
+
+    fn ser_toml(&self) -> Toml;
+}
+
+pub trait DeToml: Sized {
+
+    fn deserialize_toml(input: &str) -> Result<Self, TomlErr> {
+        let table = TomlParser::parse(input) ?;
+        let mut state = DeTomlState::default();
+        DeToml::de_toml(&mut state, &Toml::Table(table))
+    }
+
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Self, TomlErr>;
+}
+
+// keeps the key path of the value being read, for the error messages
+#[derive(Default)]
+pub struct DeTomlState {
+    pub path: Vec<String>
+}
+
+impl DeTomlState {
+    fn err(&self, msg: String) -> TomlErr {
+        let msg = if self.path.is_empty() {msg} else {format!("{} in {}", msg, self.path.join("."))};
+        TomlErr {msg, line: 0, col: 0}
+    }
+
+    pub fn err_exp(&self, name: &str) -> TomlErr {
+        self.err(format!("Unexpected key {}", name))
+    }
+
+    pub fn err_nf(&self, name: &str) -> TomlErr {
+        self.err(format!("Key not found {}", name))
+    }
+
+    pub fn err_enum(&self, name: &str) -> TomlErr {
+        self.err(format!("Enum not defined {}", name))
+    }
+
+    pub fn err_untagged(&self, name: &str) -> TomlErr {
+        self.err(format!("Data did not match any variant of {}", name))
+    }
+
+    pub fn err_range(&self, what: &str) -> TomlErr {
+        self.err(format!("Value out of range {}", what))
+    }
+
+    pub fn err_type(&self, what: &str, t: &Toml) -> TomlErr {
+        self.err(format!("Value wrong type {} expected {}", t.type_name(), what))
+    }
+
+    pub fn err_len(&self, len: usize, t: &[Toml]) -> TomlErr {
+        self.err(format!("Array has {} items expected {}", t.len(), len))
+    }
+
+    // reads the value of a key, with the key on the path
+    pub fn de_field<T: DeToml>(&mut self, key: &str, t: &Toml) -> Result<T, TomlErr> {
+        self.path.push(key.to_string());
+        let r = DeToml::de_toml(self, t);
+        self.path.pop();
+        r
+    }
+
+    // for untagged enums, tries to read a variant and gives None when it doesn't fit
+    pub fn de_try<T, F>(&mut self, f: F) -> Option<T> where F: FnOnce(&mut DeTomlState) -> Result<T, TomlErr> {
+        let depth = self.path.len();
+        let r = f(self).ok();
+        self.path.truncate(depth);
+        r
+    }
+
+    pub fn table<'a>(&self, t: &'a Toml) -> Result<&'a TomlTable, TomlErr> {
+        t.as_table().ok_or_else( || self.err_type("table", t))
+    }
+
+    pub fn array<'a>(&self, t: &'a Toml) -> Result<&'a [Toml], TomlErr> {
+        t.as_array().ok_or_else( || self.err_type("array", t))
+    }
+
+    pub fn array_n<'a>(&self, t: &'a Toml, len: usize) -> Result<&'a [Toml], TomlErr> {
+        let items = self.array(t) ?;
+        if items.len() != len {
+            return Err(self.err_len(len, items))
+        }
+        Ok(items)
+    }
+
+    pub fn string<'a>(&self, t: &'a Toml) -> Result<&'a str, TomlErr> {
+        t.as_str().ok_or_else( || self.err_type("string", t))
+    }
+
+    // the single key and value of an externally tagged enum
+    pub fn variant<'a>(&self, t: &'a Toml) -> Result<(&'a str, &'a Toml), TomlErr> {
+        let table = self.table(t) ?;
+        match table.entries.first() {
+            Some((key, value)) if table.len() == 1 => Ok((key, value)),
+            _ => Err(self.err_type("table with one variant", t))
+        }
+    }
+
+    // the tag of an internally or adjacently tagged enum
+    pub fn tag<'a>(&self, t: &'a Toml, tag: &str) -> Result<&'a str, TomlErr> {
+        match self.table(t) ?.get(tag) {
+            Some(value) => self.string(value),
+            None => Err(self.err_nf(tag))
+        }
+    }
+
+    pub fn content<'a>(&self, t: &'a Toml, content: &str) -> Result<&'a Toml, TomlErr> {
+        self.table(t) ?.get(content).ok_or_else( || self.err_nf(content))
+    }
+
+    // unit variants of untagged enums are written as {}
+    pub fn unit(&self, t: &Toml) -> Result<(), TomlErr> {
+        if !self.table(t) ?.is_empty() {
+            return Err(self.err_type("empty table", t))
+        }
+        Ok(())
+    }
+}
+
+macro_rules!impl_ser_de_toml_int {
+    ( $ ty: ident) => {
+        impl SerToml for $ ty {
+            fn ser_toml(&self) -> Toml {
+                Toml::Int(*self as i64)
+            }
+        }
+
+        impl DeToml for $ ty {
+            fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result< $ ty, TomlErr> {
+                let val = t.as_i64().ok_or_else( || s.err_type("integer", t)) ?;
+                if val < $ ty::MIN as i64 || val as i128 > $ ty::MAX as i128 {
+                    return Err(s.err_range(&format!("{} for {}", val, stringify!( $ ty))))
+                }
+                Ok(val as $ ty)
+            }
+        }
+    }
+}
+
+// TOML integers are 64 bit signed, so u64 and usize only go up to i64::MAX
+impl_ser_de_toml_int!(usize);
+impl_ser_de_toml_int!(u64);
+impl_ser_de_toml_int!(u32);
+impl_ser_de_toml_int!(u16);
+impl_ser_de_toml_int!(u8);
+impl_ser_de_toml_int!(i64);
+impl_ser_de_toml_int!(i32);
+impl_ser_de_toml_int!(i16);
+impl_ser_de_toml_int!(i8);
+
+macro_rules!impl_ser_de_toml_float {
+    ( $ ty: ident) => {
+        impl SerToml for $ ty {
+            fn ser_toml(&self) -> Toml {
+                Toml::Float(*self as f64)
+            }
+        }
+
+        impl DeToml for $ ty {
+            fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result< $ ty, TomlErr> {
+                Ok(t.as_f64().ok_or_else( || s.err_type("float", t)) ? as $ ty)
+            }
+        }
+    }
+}
+
+impl_ser_de_toml_float!(f64);
+impl_ser_de_toml_float!(f32);
+
+impl<T> DeToml for Option<T> where T: DeToml {
+    // a key that is there is Some, missing keys are handled by the derive
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Self, TomlErr> {
+        Ok(Some(DeToml::de_toml(s, t) ?))
+    }
+}
+
+impl SerToml for bool {
+    fn ser_toml(&self) -> Toml {
+        Toml::Bool(*self)
+    }
+}
+
+impl DeToml for bool {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<bool, TomlErr> {
+        t.as_bool().ok_or_else( || s.err_type("boolean", t))
+    }
+}
+
+impl SerToml for String {
+    fn ser_toml(&self) -> Toml {
+        Toml::Str(self.clone())
+    }
+}
+
+impl DeToml for String {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<String, TomlErr> {
+        Ok(s.string(t) ?.to_string())
+    }
+}
+
+impl SerToml for TomlDateTime {
+    fn ser_toml(&self) -> Toml {
+        Toml::Date(*self)
+    }
+}
+
+impl DeToml for TomlDateTime {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<TomlDateTime, TomlErr> {
+        t.as_date().cloned().ok_or_else( || s.err_type("datetime", t))
+    }
+}
+
+impl SerToml for Toml {
+    fn ser_toml(&self) -> Toml {
+        self.clone()
+    }
+}
+
+impl DeToml for Toml {
+    fn de_toml(_s: &mut DeTomlState, t: &Toml) -> Result<Toml, TomlErr> {
+        Ok(t.clone())
+    }
+}
+
+impl<T> SerToml for [T] where T: SerToml {
+    // a list of tables is written as an array of tables
+    fn ser_toml(&self) -> Toml {
+        let items: Vec<Toml> = self.iter().map( | item | item.ser_toml()).collect();
+        if items.len() > 0 && items.iter().all( | item | if let Toml::Table(_) = item {true} else {false}) {
+            return Toml::ArrayOfTables(items.into_iter().map( | item | match item {
+                Toml::Table(table) => table,
+                _ => unreachable!()
+            }).collect())
+        }
+        Toml::Array(items)
+    }
+}
+
+impl<T> SerToml for Vec<T> where T: SerToml {
+    fn ser_toml(&self) -> Toml {
+        self.as_slice().ser_toml()
+    }
+}
+
+impl<T> DeToml for Vec<T> where T: DeToml {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Vec<T>, TomlErr> {
+        let mut out = Vec::new();
+        match t {
+            Toml::Array(items) => for (index, item) in items.iter().enumerate() {
+                out.push(s.de_field(&index.to_string(), item) ?);
+            },
+            Toml::ArrayOfTables(tables) => for (index, table) in tables.iter().enumerate() {
+                out.push(s.de_field(&index.to_string(), &Toml::Table(table.clone())) ?);
+            },
+            _ => return Err(s.err_type("array", t))
+        }
+        Ok(out)
+    }
+}
+
+macro_rules!impl_ser_de_toml_array {
+    ( $($count:expr),*) => {
+        $(
+        impl<T> SerToml for [T; $count] where T: SerToml {
+            fn ser_toml(&self) -> Toml {
+                self[..].ser_toml()
+            }
+        }
+
+        impl<T> DeToml for [T; $count] where T: DeToml {
+            fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Self, TomlErr> {
+                let items: Vec<T> = DeToml::de_toml(s, t) ?;
+                let len = items.len();
+                std::convert::TryInto::try_into(items).map_err( | _ | s.err(format!("Array has {} items expected {}", len, $count)))
+            }
+        }
+        )*
+    }
+}
+
+impl_ser_de_toml_array!(2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32);
+
+macro_rules!impl_ser_de_toml_tuple {
+    ( $ len: expr, $($name:ident $index:tt),*) => {
+        impl<$($name),*> SerToml for ($($name,)*) where $($name: SerToml),* {
+            fn ser_toml(&self) -> Toml {
+                Toml::Array(vec![$(self.$index.ser_toml()),*])
+            }
+        }
+
+        impl<$($name),*> DeToml for ($($name,)*) where $($name: DeToml),* {
+            fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Self, TomlErr> {
+                let items = s.array_n(t, $ len) ?;
+                Ok(($(s.de_field(stringify!($index), &items[$index]) ?,)*))
+            }
+        }
+    }
+}
+
+impl_ser_de_toml_tuple!(2, A 0, B 1);
+impl_ser_de_toml_tuple!(3, A 0, B 1, C 2);
+impl_ser_de_toml_tuple!(4, A 0, B 1, C 2, D 3);
+
+impl<V, H> SerToml for HashMap<String, V, H> where V: SerToml, H: BuildHasher {
+    fn ser_toml(&self) -> Toml {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+        let mut table = TomlTable::default();
+        for key in keys {
+            table.insert(key.clone(), self[key].ser_toml());
+        }
+        Toml::Table(table)
+    }
+}
+
+impl<V, H> DeToml for HashMap<String, V, H> where V: DeToml, H: BuildHasher + Default {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Self, TomlErr> {
+        let mut h = HashMap::default();
+        for (key, value) in s.table(t) ?.iter() {
+            h.insert(key.clone(), s.de_field(key, value) ?);
+        }
+        Ok(h)
+    }
+}
+
+impl<T> SerToml for Box<T> where T: SerToml {
+    fn ser_toml(&self) -> Toml {
+        (**self).ser_toml()
+    }
+}
+
+impl<T> DeToml for Box<T> where T: DeToml {
+    fn de_toml(s: &mut DeTomlState, t: &Toml) -> Result<Box<T>, TomlErr> {
+        Ok(Box::new(DeToml::de_toml(s, t) ?))
+    }
+}
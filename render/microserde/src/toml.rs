@@ -1,137 +1,676 @@
-use std::collections::{HashMap};
+use std::collections::{HashSet};
 use std::str::Chars;
 
 #[derive(Default)]
 pub struct TomlParser {
     pub cur: char,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // identity paths of tables defined with a [header] and of tables created by dotted keys,
+    // elements of arrays of tables are in the path as \0index
+    defined: HashSet<Vec<String>>,
+    dotted: HashSet<Vec<String>>,
 }
 
-#[derive(PartialEq, Debug)]
-pub enum TomlTok {
-    Ident(String),
-    Str(String),
-    U64(u64),
-    I64(i64),
-    F64(f64),
-    Bool(bool),
-    Nan(bool),
-    Inf(bool),
-    Date(String),
-    Equals,
-    BlockOpen,
-    BlockClose,
-    Comma,
-    Bof,
-    Eof
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TomlDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8
 }
 
-pub enum Toml{
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TomlTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TomlOffset {
+    Z,
+    Minutes(i16)
+}
+
+// one type for the four TOML kinds: an offset datetime has all three parts,
+// a local datetime has no offset, a local date only a date and a local time only a time
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TomlDateTime {
+    pub date: Option<TomlDate>,
+    pub time: Option<TomlTime>,
+    pub offset: Option<TomlOffset>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Toml {
     Str(String),
     Bool(bool),
-    Num(f64),
-    Date(String),
+    Int(i64),
+    Float(f64),
+    Date(TomlDateTime),
     Array(Vec<Toml>),
+    Table(TomlTable),
+    InlineTable(TomlTable),
+    ArrayOfTables(Vec<TomlTable>),
+}
+
+// a table keeps its keys in definition order
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct TomlTable {
+    pub entries: Vec<(String, Toml)>
 }
 
-pub struct TomlErr{
-    pub msg:String,
-    pub line:usize,
-    pub col:usize
+pub struct TomlErr {
+    pub msg: String,
+    pub line: usize,
+    pub col: usize
 }
 
 impl std::fmt::Debug for TomlErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Toml error: {}, line:{} col:{}", self.msg, self.line+1, self.col+1)
+        write!(f, "Toml error: {}, line:{} col:{}", self.msg, self.line + 1, self.col + 1)
     }
 }
 
-impl TomlParser {
-    pub fn to_val(&mut self, tok:TomlTok, i: &mut Chars)->Result<Toml, TomlErr>{
-        match tok{
-            TomlTok::BlockOpen=>{
-                let mut vals = Vec::new();
-                loop{
-                    let tok = self.next_tok(i)?;
-                    if tok == TomlTok::BlockClose || tok == TomlTok::Eof{
-                        break
-                    }
-                    if tok != TomlTok::Comma{
-                        vals.push(self.to_val(tok, i)?);
-                    }
+impl TomlTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, Toml)> {
+        self.entries.iter()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Toml> {
+        self.entries.iter().find( | (k, _) | k == key).map( | (_, v) | v)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Toml> {
+        self.entries.iter_mut().find( | (k, _) | k == key).map( | (_, v) | v)
+    }
+
+    // replaces the value of an existing key in place
+    pub fn insert(&mut self, key: String, value: Toml) -> Option<Toml> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(std::mem::replace(old, value))
+        }
+        self.entries.push((key, value));
+        None
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Toml> {
+        let index = self.entries.iter().position( | (k, _) | k == key) ?;
+        Some(self.entries.remove(index).1)
+    }
+
+    // looks up a path of bare keys like "workspace.members"
+    pub fn get_path(&self, path: &str) -> Option<&Toml> {
+        let mut parts = path.split('.');
+        let mut value = self.get(parts.next() ?) ?;
+        for part in parts {
+            value = value.get(part) ?;
+        }
+        Some(value)
+    }
+
+    // moves the entries of a table value into this table, for #[flatten]
+    pub fn flatten(&mut self, value: Toml) {
+        if let Toml::Table(table) | Toml::InlineTable(table) = value {
+            for (key, value) in table.entries {
+                self.insert(key, value);
+            }
+        }
+    }
+
+    // writes the table as a TOML document
+    pub fn to_toml_string(&self) -> String {
+        let mut out = String::new();
+        write_table(&mut out, &mut Vec::new(), self);
+        out
+    }
+}
+
+impl Toml {
+    pub fn as_str(&self) -> Option<&str> {
+        if let Toml::Str(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Toml::Bool(v) = self {Some(*v)} else {None}
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        if let Toml::Int(v) = self {Some(*v)} else {None}
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Toml::Float(v) => Some(*v),
+            Toml::Int(v) => Some(*v as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_date(&self) -> Option<&TomlDateTime> {
+        if let Toml::Date(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_array(&self) -> Option<&[Toml]> {
+        if let Toml::Array(v) = self {Some(v)} else {None}
+    }
+
+    pub fn as_table(&self) -> Option<&TomlTable> {
+        match self {
+            Toml::Table(v) | Toml::InlineTable(v) => Some(v),
+            _ => None
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Toml> {
+        self.as_table() ?.get(key)
+    }
+
+    pub fn get_path(&self, path: &str) -> Option<&Toml> {
+        self.as_table() ?.get_path(path)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Toml::Str(_) => "string",
+            Toml::Bool(_) => "boolean",
+            Toml::Int(_) => "integer",
+            Toml::Float(_) => "float",
+            Toml::Date(_) => "datetime",
+            Toml::Array(_) => "array",
+            Toml::Table(_) | Toml::InlineTable(_) => "table",
+            Toml::ArrayOfTables(_) => "array of tables",
+        }
+    }
+}
+
+impl std::fmt::Display for TomlDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(date) = &self.date {
+            write!(f, "{:04}-{:02}-{:02}", date.year, date.month, date.day) ?;
+            if self.time.is_some() {
+                write!(f, "T") ?;
+            }
+        }
+        if let Some(time) = &self.time {
+            write!(f, "{:02}:{:02}:{:02}", time.hour, time.minute, time.second) ?;
+            if time.nanosecond != 0 {
+                let frac = format!("{:09}", time.nanosecond);
+                write!(f, ".{}", frac.trim_end_matches('0')) ?;
+            }
+        }
+        match self.offset {
+            Some(TomlOffset::Z) => write!(f, "Z"),
+            Some(TomlOffset::Minutes(minutes)) => {
+                let sign = if minutes < 0 {'-'} else {'+'};
+                write!(f, "{}{:02}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+            }
+            None => Ok(())
+        }
+    }
+}
+
+impl std::fmt::Display for Toml {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        write_value(&mut out, self);
+        write!(f, "{}", out)
+    }
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c >= '0' && c <= '9' || c == '_' || c == '-'
+}
+
+fn is_control(c: char) -> bool {
+    c < ' ' && c != '\t' || c == '\u{7f}'
+}
+
+fn write_key(out: &mut String, key: &str) {
+    if key.len() > 0 && key.chars().all(is_bare_key_char) {
+        out.push_str(key);
+    }
+    else {
+        write_str(out, key);
+    }
+}
+
+fn write_str(out: &mut String, val: &str) {
+    out.push('"');
+    for c in val.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            c if is_control(c) => out.push_str(&format!("\\u{:04X}", c as u32)),
+            _ => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+// writes a value in its inline form, tables become inline tables
+fn write_value(out: &mut String, value: &Toml) {
+    match value {
+        Toml::Str(v) => write_str(out, v),
+        Toml::Bool(v) => out.push_str(if *v {"true"} else {"false"}),
+        Toml::Int(v) => out.push_str(&v.to_string()),
+        Toml::Float(v) => {
+            if v.is_nan() {
+                out.push_str("nan")
+            }
+            else if v.is_infinite() {
+                out.push_str(if *v < 0.0 {"-inf"} else {"inf"})
+            }
+            else { // debug formatting always has a . or an exponent
+                out.push_str(&format!("{:?}", v))
+            }
+        }
+        Toml::Date(v) => out.push_str(&v.to_string()),
+        Toml::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index != 0 {
+                    out.push_str(", ");
                 }
-                Ok(Toml::Array(vals))
-            },
-            TomlTok::Str(v)=>Ok(Toml::Str(v)),
-            TomlTok::U64(v)=>Ok(Toml::Num(v as f64)),
-            TomlTok::I64(v)=>Ok(Toml::Num(v as f64)),
-            TomlTok::F64(v)=>Ok(Toml::Num(v as f64)),
-            TomlTok::Bool(v)=>Ok(Toml::Bool(v)),
-            TomlTok::Nan(v)=>Ok(Toml::Num(if v{-std::f64::NAN}else{std::f64::NAN})),
-            TomlTok::Inf(v)=>Ok(Toml::Num(if v{-std::f64::INFINITY}else{std::f64::INFINITY})),
-            TomlTok::Date(v)=>Ok(Toml::Date(v)),
-            _=>Err(self.err_token(tok))
-        }
-    }
-    
-    pub fn parse_key_value(&mut self, local_scope:&String, key:String, i: &mut Chars, out:&mut HashMap<String, Toml>)->Result<(), TomlErr>{
-        let tok = self.next_tok(i)?;
-        if tok != TomlTok::Equals{
-            return Err(self.err_token(tok));
-        }
-        let tok = self.next_tok(i)?;
-        let val = self.to_val(tok, i)?;
-        let key = if local_scope.len()>0{
-            format!("{}.{}", local_scope, key)
-        }
-        else{
-            key
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Toml::Table(table) | Toml::InlineTable(table) => write_inline_table(out, table),
+        Toml::ArrayOfTables(tables) => {
+            out.push('[');
+            for (index, table) in tables.iter().enumerate() {
+                if index != 0 {
+                    out.push_str(", ");
+                }
+                write_inline_table(out, table);
+            }
+            out.push(']');
+        }
+    }
+}
+
+fn write_inline_table(out: &mut String, table: &TomlTable) {
+    if table.is_empty() {
+        out.push_str("{}");
+        return
+    }
+    out.push_str("{ ");
+    for (index, (key, value)) in table.iter().enumerate() {
+        if index != 0 {
+            out.push_str(", ");
+        }
+        write_key(out, key);
+        out.push_str(" = ");
+        write_value(out, value);
+    }
+    out.push_str(" }");
+}
+
+fn write_header(out: &mut String, path: &[String], array: bool) {
+    if out.len() > 0 {
+        out.push('\n');
+    }
+    out.push_str(if array {"[["} else {"["});
+    for (index, key) in path.iter().enumerate() {
+        if index != 0 {
+            out.push('.');
+        }
+        write_key(out, key);
+    }
+    out.push_str(if array {"]]\n"} else {"]\n"});
+}
+
+// writes the values of a table first and then its tables under [headers], since a value
+// after a header would end up in that table
+fn write_table(out: &mut String, path: &mut Vec<String>, table: &TomlTable) {
+    let is_section = | value: &Toml | match value {
+        Toml::Table(_) | Toml::ArrayOfTables(_) => true,
+        _ => false
+    };
+    for (key, value) in table.iter() {
+        if !is_section(value) {
+            write_key(out, key);
+            out.push_str(" = ");
+            write_value(out, value);
+            out.push('\n');
+        }
+    }
+    for (key, value) in table.iter() {
+        path.push(key.clone());
+        match value {
+            Toml::Table(table) => {
+                // a table with only tables in it is implied by their headers
+                if table.is_empty() || !table.iter().all( | (_, v) | is_section(v)) {
+                    write_header(out, path, false);
+                }
+                write_table(out, path, table);
+            }
+            Toml::ArrayOfTables(tables) => for table in tables {
+                write_header(out, path, true);
+                write_table(out, path, table);
+            }
+            _ => ()
+        }
+        path.pop();
+    }
+}
+
+// removes underscores, which have to sit between two digits
+fn strip_underscores(val: &str, radix: u32) -> Option<String> {
+    let mut out = String::new();
+    let mut last_digit = false;
+    for c in val.chars() {
+        if c == '_' {
+            if !last_digit {
+                return None
+            }
+            last_digit = false;
+        }
+        else if c.is_digit(radix) {
+            out.push(c);
+            last_digit = true;
+        }
+        else {
+            return None
+        }
+    }
+    if !last_digit {
+        return None
+    }
+    Some(out)
+}
+
+// a decimal without leading zeros
+fn strip_decimal(val: &str) -> Option<String> {
+    let digits = strip_underscores(val, 10) ?;
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None
+    }
+    Some(digits)
+}
+
+fn parse_number(word: &str) -> Option<Toml> {
+    let (neg, body) = if let Some(body) = word.strip_prefix('-') {
+        (true, body)
+    }
+    else {
+        (false, word.strip_prefix('+').unwrap_or(word))
+    };
+    for (prefix, radix) in &[("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = body.strip_prefix(prefix) {
+            if body.len() != word.len() { // no sign allowed
+                return None
+            }
+            return i64::from_str_radix(&strip_underscores(digits, *radix) ?, *radix).ok().map(Toml::Int)
+        }
+    }
+    let mut num = String::new();
+    if neg {
+        num.push('-');
+    }
+    if body.contains( | c | c == '.' || c == 'e' || c == 'E') {
+        let (mantissa, exponent) = match body.find( | c | c == 'e' || c == 'E') {
+            Some(pos) => (&body[..pos], Some(&body[pos + 1..])),
+            None => (body, None)
         };
-        out.insert(key, val);
-        Ok(())
+        let (int_part, frac_part) = match mantissa.find('.') {
+            Some(pos) => (&mantissa[..pos], Some(&mantissa[pos + 1..])),
+            None => (mantissa, None)
+        };
+        num.push_str(&strip_decimal(int_part) ?);
+        if let Some(frac_part) = frac_part {
+            num.push('.');
+            num.push_str(&strip_underscores(frac_part, 10) ?);
+        }
+        if let Some(exponent) = exponent {
+            num.push('e');
+            let exponent = if let Some(exponent) = exponent.strip_prefix('-') {
+                num.push('-');
+                exponent
+            }
+            else {
+                exponent.strip_prefix('+').unwrap_or(exponent)
+            };
+            num.push_str(&strip_underscores(exponent, 10) ?);
+        }
+        return num.parse().ok().map(Toml::Float)
+    }
+    num.push_str(&strip_decimal(body) ?);
+    num.parse().ok().map(Toml::Int)
+}
+
+fn parse_digits(val: &[u8]) -> Option<u32> {
+    if val.is_empty() || !val.iter().all( | c | c.is_ascii_digit()) {
+        return None
     }
-    
-    pub fn parse(data:&str)->Result<HashMap<String, Toml>, TomlErr>{
+    std::str::from_utf8(val).ok() ?.parse().ok()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 => if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {29} else {28},
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// parses 1979-05-27T07:32:00.999-07:00 and its local datetime, date and time parts
+fn parse_datetime(word: &str) -> Option<TomlDateTime> {
+    let mut rest = word.as_bytes();
+    let mut dt = TomlDateTime {date: None, time: None, offset: None};
+    if rest.len() >= 10 && rest[4] == b'-' && rest[7] == b'-' {
+        let year = parse_digits(&rest[0..4]) ?;
+        let month = parse_digits(&rest[5..7]) ?;
+        let day = parse_digits(&rest[8..10]) ?;
+        if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) {
+            return None
+        }
+        dt.date = Some(TomlDate {year: year as u16, month: month as u8, day: day as u8});
+        rest = &rest[10..];
+        if rest.is_empty() {
+            return Some(dt)
+        }
+        if rest[0] != b'T' && rest[0] != b't' {
+            return None
+        }
+        rest = &rest[1..];
+    }
+    if rest.len() < 8 || rest[2] != b':' || rest[5] != b':' {
+        return None
+    }
+    let hour = parse_digits(&rest[0..2]) ?;
+    let minute = parse_digits(&rest[3..5]) ?;
+    let second = parse_digits(&rest[6..8]) ?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None
+    }
+    rest = &rest[8..];
+    let mut nanosecond = 0;
+    if rest.first() == Some(&b'.') {
+        let digits = rest[1..].iter().take_while( | c | c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None
+        }
+        // precision beyond nanoseconds is truncated
+        for index in 0..9 {
+            nanosecond = nanosecond * 10 + if index < digits {(rest[1 + index] - b'0') as u32} else {0};
+        }
+        rest = &rest[1 + digits..];
+    }
+    dt.time = Some(TomlTime {hour: hour as u8, minute: minute as u8, second: second as u8, nanosecond});
+    if rest.is_empty() {
+        return Some(dt)
+    }
+    if dt.date.is_none() { // a local time has no offset
+        return None
+    }
+    dt.offset = Some(match rest {
+        [b'Z'] | [b'z'] => TomlOffset::Z,
+        [sign, h1, h2, b':', m1, m2] if *sign == b'+' || *sign == b'-' => {
+            let hours = parse_digits(&[*h1, *h2]) ?;
+            let minutes = parse_digits(&[*m1, *m2]) ?;
+            if hours > 23 || minutes > 59 {
+                return None
+            }
+            let minutes = (hours * 60 + minutes) as i16;
+            TomlOffset::Minutes(if *sign == b'-' {-minutes} else {minutes})
+        }
+        _ => return None
+    });
+    Some(dt)
+}
+
+impl TomlParser {
+    pub fn parse(data: &str) -> Result<TomlTable, TomlErr> {
         let i = &mut data.chars();
         let mut t = TomlParser::default();
         t.next(i);
-        let mut out = HashMap::new();
-        let mut local_scope = String::new();
-        loop{
-            let tok = t.next_tok(i)?;
-            match tok{
-                TomlTok::Eof=>{ // at eof. 
-                    return Ok(out);
-                },
-                TomlTok::BlockOpen=>{ // its a scope
-                    // we should expect an ident or a string
-                    let tok = t.next_tok(i)?;
-                    match tok{
-                        TomlTok::Str(key)=>{ // a key
-                            local_scope = key;
-                        },
-                        TomlTok::Ident(key)=>{ // also a key
-                            local_scope = key;
-                        },
-                        _=>return Err(t.err_token(tok))
+        let mut root = TomlTable::default();
+        let mut current = Vec::new(); // the keys of the table from the last [header]
+        loop {
+            t.skip_ws(i);
+            match t.cur {
+                '\0' => return Ok(root),
+                '#' | '\n' | '\r' => t.expect_eol(i) ?,
+                '[' => {
+                    t.next(i);
+                    let is_array = t.cur == '[';
+                    if is_array {
+                        t.next(i);
+                    }
+                    let keys = t.parse_key(i) ?;
+                    if t.cur != ']' {
+                        return Err(t.err_parse("table header, expected ]"))
+                    }
+                    t.next(i);
+                    if is_array {
+                        if t.cur != ']' {
+                            return Err(t.err_parse("array of tables header, expected ]]"))
+                        }
+                        t.next(i);
                     }
-                    let tok = t.next_tok(i)?;
-                    if tok != TomlTok::BlockClose{
-                        return Err(t.err_token(tok))
+                    t.expect_eol(i) ?;
+                    t.open_table(&mut root, &keys, is_array) ?;
+                    current = keys;
+                },
+                _ => {
+                    let keys = t.parse_key(i) ?;
+                    if t.cur != '=' {
+                        return Err(t.err_parse("key value, expected ="))
                     }
+                    t.next(i);
+                    t.skip_ws(i);
+                    let value = t.parse_value(i) ?;
+                    t.expect_eol(i) ?;
+                    t.insert_key_value(&mut root, &current, &keys, value) ?;
+                }
+            }
+        }
+    }
+
+    // walks keys from table, creating tables that don't exist yet and going into the last element
+    // of arrays of tables. ident gets the identity path of the table it ends up in
+    fn walk<'a>(&self, mut table: &'a mut TomlTable, keys: &[String], ident: &mut Vec<String>) -> Result<&'a mut TomlTable, TomlErr> {
+        for key in keys {
+            if !table.contains_key(key) {
+                table.insert(key.clone(), Toml::Table(TomlTable::default()));
+            }
+            ident.push(key.clone());
+            table = match table.get_mut(key).unwrap() {
+                Toml::Table(table) => table,
+                Toml::ArrayOfTables(tables) => {
+                    ident.push(format!("\0{}", tables.len() - 1));
+                    tables.last_mut().unwrap()
                 },
-                TomlTok::Str(key)=>{ // a key
-                    t.parse_key_value(&local_scope, key, i, &mut out)?;
+                Toml::InlineTable(_) => return Err(self.err_parse(&format!("inline table {} cannot be extended", key))),
+                _ => return Err(self.err_parse(&format!("key {} is not a table", key)))
+            };
+        }
+        Ok(table)
+    }
+
+    fn open_table(&mut self, root: &mut TomlTable, keys: &[String], is_array: bool) -> Result<(), TomlErr> {
+        let mut ident = Vec::new();
+        let (last, parent_keys) = keys.split_last().unwrap();
+        let parent = self.walk(root, parent_keys, &mut ident) ?;
+        ident.push(last.clone());
+        if is_array {
+            match parent.get_mut(last) {
+                None => {
+                    parent.insert(last.clone(), Toml::ArrayOfTables(vec![TomlTable::default()]));
+                    ident.push("\00".to_string());
                 },
-                TomlTok::Ident(key)=>{ // also a key
-                    t.parse_key_value(&local_scope, key, i, &mut out)?;
+                Some(Toml::ArrayOfTables(tables)) => {
+                    tables.push(TomlTable::default());
+                    ident.push(format!("\0{}", tables.len() - 1));
                 },
-                _=>return Err(t.err_token(tok))
+                Some(_) => return Err(self.err_parse(&format!("{} is not an array of tables", last)))
             }
         }
+        else {
+            match parent.get(last) {
+                None => {
+                    parent.insert(last.clone(), Toml::Table(TomlTable::default()));
+                },
+                Some(Toml::Table(_)) => if self.defined.contains(&ident) || self.dotted.contains(&ident) {
+                    return Err(self.err_parse(&format!("table {} is defined twice", last)))
+                },
+                Some(_) => return Err(self.err_parse(&format!("key {} is already defined", last)))
+            }
+        }
+        self.defined.insert(ident);
+        Ok(())
     }
-    
+
+    fn insert_key_value(&mut self, root: &mut TomlTable, current: &[String], keys: &[String], value: Toml) -> Result<(), TomlErr> {
+        let mut ident = Vec::new();
+        let mut table = self.walk(root, current, &mut ident) ?;
+        let (last, dotted_keys) = keys.split_last().unwrap();
+        for key in dotted_keys {
+            ident.push(key.clone());
+            match table.get(key) {
+                None => {
+                    table.insert(key.clone(), Toml::Table(TomlTable::default()));
+                    self.dotted.insert(ident.clone());
+                },
+                Some(Toml::Table(_)) if self.dotted.contains(&ident) => (),
+                Some(_) => return Err(self.err_parse(&format!("{} cannot be extended with dotted keys", key)))
+            }
+            table = match table.get_mut(key) {
+                Some(Toml::Table(table)) => table,
+                _ => unreachable!()
+            };
+        }
+        if table.contains_key(last) {
+            return Err(self.err_parse(&format!("duplicate key {}", last)))
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
     pub fn next(&mut self, i: &mut Chars) {
         if let Some(c) = i.next() {
             self.cur = c;
@@ -140,199 +679,316 @@ impl TomlParser {
                 self.col = 0;
             }
             else {
-                self.col = 0;
+                self.col += 1;
             }
         }
         else {
             self.cur = '\0';
         }
     }
-    
-    pub fn err_token(&self, tok:TomlTok) -> TomlErr {
-        TomlErr{msg:format!("Unexpected token {:?} ", tok), line:self.line, col:self.col}
+
+    pub fn err_parse(&self, what: &str) -> TomlErr {
+        TomlErr {msg: format!("Cannot parse toml {} ", what), line: self.line, col: self.col}
     }
-    
-    pub fn err_parse(&self, what:&str) -> TomlErr {
-        TomlErr{msg:format!("Cannot parse toml {} ", what), line:self.line, col:self.col}
+
+    fn skip_ws(&mut self, i: &mut Chars) {
+        while self.cur == ' ' || self.cur == '\t' {
+            self.next(i);
+        }
     }
-    
-    pub fn next_tok(&mut self, i: &mut Chars) -> Result<TomlTok, TomlErr> {
-        while self.cur == '\n' || self.cur == '\r' || self.cur == '\t' || self.cur == ' ' {
+
+    fn skip_comment(&mut self, i: &mut Chars) -> Result<(), TomlErr> {
+        if self.cur == '#' {
+            while self.cur != '\n' && self.cur != '\0' {
+                if is_control(self.cur) && self.cur != '\r' {
+                    return Err(self.err_parse("comment, control character"))
+                }
+                self.next(i);
+            }
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self, i: &mut Chars) -> Result<(), TomlErr> {
+        if self.cur == '\r' {
             self.next(i);
+            if self.cur != '\n' {
+                return Err(self.err_parse("newline, \\r without \\n"))
+            }
         }
-        loop{
-            if self.cur == '\0' {
-                return Ok(TomlTok::Eof)
+        self.next(i);
+        Ok(())
+    }
+
+    // the rest of a line can only be whitespace and a comment
+    fn expect_eol(&mut self, i: &mut Chars) -> Result<(), TomlErr> {
+        self.skip_ws(i);
+        self.skip_comment(i) ?;
+        match self.cur {
+            '\0' => Ok(()),
+            '\n' | '\r' => self.newline(i),
+            _ => Err(self.err_parse("line, expected newline"))
+        }
+    }
+
+    // whitespace, comments and newlines, as allowed in arrays
+    fn skip_ws_nl(&mut self, i: &mut Chars) -> Result<(), TomlErr> {
+        loop {
+            self.skip_ws(i);
+            self.skip_comment(i) ?;
+            if self.cur != '\n' && self.cur != '\r' {
+                return Ok(())
             }
-            match self.cur {
-                ',' => {
-                    self.next(i);
-                    return Ok(TomlTok::Comma)
-                }
-                '[' => {
-                    self.next(i);
-                    return Ok(TomlTok::BlockOpen)
-                }
-                ']' => {
+            self.newline(i) ?;
+        }
+    }
+
+    // a dotted key like a."b.c".'d', ends after the whitespace following the last part
+    fn parse_key(&mut self, i: &mut Chars) -> Result<Vec<String>, TomlErr> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_ws(i);
+            if self.cur == '"' || self.cur == '\'' {
+                keys.push(self.parse_string(i, false) ?);
+            }
+            else if is_bare_key_char(self.cur) {
+                let mut key = String::new();
+                while is_bare_key_char(self.cur) {
+                    key.push(self.cur);
                     self.next(i);
-                    return Ok(TomlTok::BlockClose)
                 }
-                '=' => {
+                keys.push(key);
+            }
+            else {
+                return Err(self.err_parse("key"))
+            }
+            self.skip_ws(i);
+            if self.cur != '.' {
+                return Ok(keys)
+            }
+            self.next(i);
+        }
+    }
+
+    // basic "strings" with escapes, 'literal strings', and their """multiline""" forms
+    fn parse_string(&mut self, i: &mut Chars, multiline_ok: bool) -> Result<String, TomlErr> {
+        let quote = self.cur;
+        self.next(i);
+        let mut multiline = false;
+        if self.cur == quote {
+            self.next(i);
+            if self.cur != quote {
+                return Ok(String::new())
+            }
+            if !multiline_ok {
+                return Err(self.err_parse("key, multiline strings are not allowed"))
+            }
+            self.next(i);
+            multiline = true;
+            // a newline right after the opening quotes is not part of the string
+            if self.cur == '\n' || self.cur == '\r' {
+                self.newline(i) ?;
+            }
+        }
+        let mut val = String::new();
+        loop {
+            match self.cur {
+                '\0' => return Err(self.err_parse("string, not terminated")),
+                c if c == quote => {
                     self.next(i);
-                    return Ok(TomlTok::Equals)
-                }
-                '+' | '-' | '0'..='9' => {
-                    let mut num = String::new();
-                    let is_neg = if self.cur == '-' {
-                        num.push(self.cur);
-                        self.next(i);
-                        true
-                    }
-                    else {
-                        if self.cur == '+' {
-                            self.next(i);
-                        }
-                        false
-                    };
-                    if self.cur == 'n' {
-                        self.next(i);
-                        if self.cur == 'a' {
-                            self.next(i);
-                            if self.cur == 'n' {
-                                self.next(i);
-                                return Ok(TomlTok::Nan(is_neg))
-                            }
-                            else {
-                                return Err(self.err_parse("nan"))
-                            }
-                        }
-                        else {
-                            return Err(self.err_parse("nan"))
-                        }
+                    if !multiline {
+                        return Ok(val)
                     }
-                     if self.cur == 'i' {
+                    // up to two quotes can come right before the closing ones
+                    let mut quotes = 1;
+                    while self.cur == quote && quotes < 5 {
+                        quotes += 1;
                         self.next(i);
-                        if self.cur == 'n' {
-                            self.next(i);
-                            if self.cur == 'f' {
-                                self.next(i);
-                                return Ok(TomlTok::Inf(is_neg))
-                            }
-                            else {
-                                return Err(self.err_parse("inf"))
-                            }
-                        }
-                        else {
-                            return Err(self.err_parse("nan"))
-                        }
-                    }
-                    while self.cur >= '0' && self.cur <= '9' || self.cur == '_' {
-                        if self.cur != '_' {
-                            num.push(self.cur);
-                        }
-                        self.next(i);
-                    }
-                    if self.cur == '.' {
-                        num.push(self.cur);
-                        self.next(i);
-                        while self.cur >= '0' && self.cur <= '9' || self.cur == '_' {
-                            if self.cur != '_' {
-                                num.push(self.cur);
-                            }
-                            self.next(i);
-                        }
-                        if let Ok(num) = num.parse() {
-                            return Ok(TomlTok::F64(num))
-                        }
-                        else {
-                            return Err(self.err_parse("number"));
-                        }
                     }
-                    else if self.cur == '-' { // lets assume its a date. whatever. i don't feel like more parsing today
-                        num.push(self.cur);
-                        self.next(i);
-                        while self.cur >= '0' && self.cur <= '9' || self.cur == ':' || self.cur == '-' || self.cur == 'T' {
-                            num.push(self.cur);
-                            self.next(i);
-                        }
-                        return Ok(TomlTok::Date(num))
+                    let closing = quotes >= 3;
+                    for _ in 0..if closing {quotes - 3} else {quotes} {
+                        val.push(quote);
                     }
-                    else {
-                        if is_neg {
-                            if let Ok(num) = num.parse() {
-                                 return Ok(TomlTok::I64(num))
-                            }
-                            else {
-                                return Err(self.err_parse("number"));
-                            }
-                        }
-                        if let Ok(num) = num.parse() {
-                            return Ok(TomlTok::U64(num))
-                        }
-                        else {
-                            return Err(self.err_parse("number"));
-                        }
+                    if closing {
+                        return Ok(val)
                     }
                 },
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    let mut ident = String::new();
-                    while self.cur >= 'a' && self.cur <= 'z'
-                        || self.cur >= 'A' && self.cur <= 'Z'
-                        || self.cur == '_' || self.cur == '-' {
-                        ident.push(self.cur);
-                        self.next(i);
-                    }
-                    if self.cur == '.' {
-                        while self.cur == '.' {
-                            self.next(i);
-                            while self.cur >= 'a' && self.cur <= 'z'
-                                || self.cur >= 'A' && self.cur <= 'Z'
-                                || self.cur == '_' || self.cur == '-' {
-                                ident.push(self.cur);
-                                self.next(i);
-                            }
-                        }
-                        return Ok(TomlTok::Ident(ident))
-                    }
-                    if ident == "true" {
-                        return Ok(TomlTok::Bool(true))
-                    }
-                    if ident == "false" {
-                        return Ok(TomlTok::Bool(false))
-                    }
-                    if ident == "inf" {
-                        return Ok(TomlTok::Inf(false))
-                    }
-                    if ident == "nan" {
-                        return Ok(TomlTok::Nan(false))
-                    }
-                    return Ok(TomlTok::Ident(ident))
+                '\\' if quote == '"' => {
+                    self.next(i);
+                    self.parse_escape(i, multiline, &mut val) ?;
                 },
-                '#' =>{
-                    while self.cur !='\n' && self.cur != '\0'{
-                        self.next(i);
-                    }
+                '\n' | '\r' if multiline => {
+                    self.newline(i) ?;
+                    val.push('\n');
                 },
-                '"' => {
-                    let mut val = String::new();
+                c if is_control(c) => return Err(self.err_parse("string, control character")),
+                c => {
+                    val.push(c);
                     self.next(i);
-                    while self.cur != '"' {
-                        if self.cur == '\\' {
-                            self.next(i);
-                        }
-                        if self.cur == '\0' {
-                            return Err(self.err_parse("string"));
-                        }
-                        val.push(self.cur);
-                        self.next(i);
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self, i: &mut Chars, multiline: bool, val: &mut String) -> Result<(), TomlErr> {
+        let c = match self.cur {
+            'b' => '\u{8}',
+            't' => '\t',
+            'n' => '\n',
+            'f' => '\u{c}',
+            'r' => '\r',
+            '"' => '"',
+            '\\' => '\\',
+            'u' | 'U' => {
+                let len = if self.cur == 'u' {4} else {8};
+                let mut code = 0;
+                for _ in 0..len {
+                    self.next(i);
+                    code = code * 16 + self.cur.to_digit(16).ok_or_else( || self.err_parse("string, unicode escape")) ?;
+                }
+                std::char::from_u32(code).ok_or_else( || self.err_parse("string, unicode escape")) ?
+            },
+            ' ' | '\t' | '\n' | '\r' if multiline => {
+                // a backslash at the end of a line eats all whitespace up to the next text
+                let mut newlines = 0;
+                loop {
+                    match self.cur {
+                        ' ' | '\t' => self.next(i),
+                        '\n' | '\r' => {
+                            self.newline(i) ?;
+                            newlines += 1;
+                        },
+                        _ => break
                     }
+                }
+                if newlines == 0 {
+                    return Err(self.err_parse("string, escape"))
+                }
+                return Ok(())
+            },
+            _ => return Err(self.err_parse("string, escape"))
+        };
+        val.push(c);
+        self.next(i);
+        Ok(())
+    }
+
+    fn parse_value(&mut self, i: &mut Chars) -> Result<Toml, TomlErr> {
+        match self.cur {
+            '"' | '\'' => Ok(Toml::Str(self.parse_string(i, true) ?)),
+            '[' => self.parse_array(i),
+            '{' => self.parse_inline_table(i),
+            _ => self.parse_scalar(i)
+        }
+    }
+
+    fn parse_array(&mut self, i: &mut Chars) -> Result<Toml, TomlErr> {
+        self.next(i);
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws_nl(i) ?;
+            if self.cur == ']' {
+                self.next(i);
+                return Ok(Toml::Array(items))
+            }
+            items.push(self.parse_value(i) ?);
+            self.skip_ws_nl(i) ?;
+            match self.cur {
+                ',' => self.next(i),
+                ']' => {
                     self.next(i);
-                    return Ok(TomlTok::Str(val))
+                    return Ok(Toml::Array(items))
                 },
-                _ => {
-                    return Err(self.err_parse("tokenizer"));
+                _ => return Err(self.err_parse("array, expected , or ]"))
+            }
+        }
+    }
+
+    fn parse_inline_table(&mut self, i: &mut Chars) -> Result<Toml, TomlErr> {
+        self.next(i);
+        let mut table = TomlTable::default();
+        self.skip_ws(i);
+        if self.cur == '}' {
+            self.next(i);
+            return Ok(Toml::InlineTable(table))
+        }
+        loop {
+            let keys = self.parse_key(i) ?;
+            if self.cur != '=' {
+                return Err(self.err_parse("inline table, expected ="))
+            }
+            self.next(i);
+            self.skip_ws(i);
+            let value = self.parse_value(i) ?;
+            self.insert_inline(&mut table, &keys, value) ?;
+            self.skip_ws(i);
+            match self.cur {
+                ',' => self.next(i),
+                '}' => {
+                    self.next(i);
+                    return Ok(Toml::InlineTable(table))
+                },
+                _ => return Err(self.err_parse("inline table, expected , or }"))
+            }
+        }
+    }
+
+    // dotted keys in an inline table make tables, while nested inline tables are closed
+    fn insert_inline(&self, mut table: &mut TomlTable, keys: &[String], value: Toml) -> Result<(), TomlErr> {
+        let (last, dotted_keys) = keys.split_last().unwrap();
+        for key in dotted_keys {
+            if !table.contains_key(key) {
+                table.insert(key.clone(), Toml::Table(TomlTable::default()));
+            }
+            table = match table.get_mut(key).unwrap() {
+                Toml::Table(table) => table,
+                _ => return Err(self.err_parse(&format!("{} cannot be extended with dotted keys", key)))
+            };
+        }
+        if table.contains_key(last) {
+            return Err(self.err_parse(&format!("duplicate key {}", last)))
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    // booleans, numbers and datetimes
+    fn parse_scalar(&mut self, i: &mut Chars) -> Result<Toml, TomlErr> {
+        let is_word = | c: char | c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-' || c == '.' || c == ':';
+        let mut word = String::new();
+        while is_word(self.cur) {
+            word.push(self.cur);
+            self.next(i);
+        }
+        // a date and a time can also be separated by a space
+        if word.len() == 10 && self.cur == ' ' && parse_datetime(&word).is_some() {
+            if let Some(c) = i.clone().next() {
+                if c.is_ascii_digit() {
+                    word.push('T');
+                    self.next(i);
+                    while is_word(self.cur) {
+                        word.push(self.cur);
+                        self.next(i);
+                    }
                 }
             }
         }
+        match word.as_ref() {
+            "" => return Err(self.err_parse("value")),
+            "true" => return Ok(Toml::Bool(true)),
+            "false" => return Ok(Toml::Bool(false)),
+            "inf" | "+inf" => return Ok(Toml::Float(std::f64::INFINITY)),
+            "-inf" => return Ok(Toml::Float(std::f64::NEG_INFINITY)),
+            "nan" | "+nan" | "-nan" => return Ok(Toml::Float(std::f64::NAN)),
+            _ => ()
+        }
+        if let Some(dt) = parse_datetime(&word) {
+            return Ok(Toml::Date(dt))
+        }
+        let bytes = word.as_bytes();
+        if bytes.len() > 4 && bytes[0].is_ascii_digit() && (bytes[2] == b':' || bytes[4] == b'-') {
+            return Err(self.err_parse(&format!("datetime {}", word)))
+        }
+        parse_number(&word).ok_or_else( || self.err_parse(&format!("value {}", word)))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,121 @@
+// the path query syntax of JsonValue and RonValue:
+//
+// message.spans[0].file_name     keys and array indices
+// ["key with.dots"][1]           quoted keys
+// children[*].name  children.*   every item of an array or object
+//
+// an empty path is the value itself
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValuePathSeg {
+    Key(String),
+    Index(usize),
+    Any
+}
+
+pub fn parse_value_path(path: &str) -> Result<Vec<ValuePathSeg>, String> {
+    let mut segs = Vec::new();
+    let mut chars = path.chars().peekable();
+    let mut expect_key = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '.' if !segs.is_empty() && !expect_key => expect_key = true,
+            '[' => {
+                let seg = match chars.peek() {
+                    Some('"') => {
+                        chars.next();
+                        let mut key = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some('\\') => key.push(chars.next().ok_or_else( || format!("Path {} has an unterminated key", path)) ?),
+                                Some(c) => key.push(c),
+                                None => return Err(format!("Path {} has an unterminated key", path))
+                            }
+                        }
+                        ValuePathSeg::Key(key)
+                    }
+                    Some('*') => {
+                        chars.next();
+                        ValuePathSeg::Any
+                    }
+                    _ => {
+                        let mut index = String::new();
+                        while let Some(c) = chars.peek() {
+                            if !c.is_ascii_digit() {
+                                break
+                            }
+                            index.push(*c);
+                            chars.next();
+                        }
+                        ValuePathSeg::Index(index.parse().map_err( | _ | format!("Path {} has an invalid index", path)) ?)
+                    }
+                };
+                if chars.next() != Some(']') {
+                    return Err(format!("Path {} is missing a ]", path))
+                }
+                if expect_key {
+                    return Err(format!("Path {} has a . before a [", path))
+                }
+                segs.push(seg);
+            }
+            '*' if segs.is_empty() || expect_key => {
+                segs.push(ValuePathSeg::Any);
+                expect_key = false;
+            }
+            c if (segs.is_empty() || expect_key) && c != '.' && c != ']' => {
+                let mut key = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if *c == '.' || *c == '[' {
+                        break
+                    }
+                    key.push(*c);
+                    chars.next();
+                }
+                segs.push(ValuePathSeg::Key(key));
+                expect_key = false;
+            }
+            _ => return Err(format!("Path {} has an unexpected {}", path, c))
+        }
+    }
+    if expect_key {
+        return Err(format!("Path {} ends with a .", path))
+    }
+    Ok(segs)
+}
+
+// the tree walk shared by the value types
+pub(crate) trait PathValue: Sized {
+    fn child_key(&self, key: &str) -> Option<&Self>;
+    fn child_index(&self, index: usize) -> Option<&Self>;
+    fn children(&self) -> Vec<&Self>;
+
+    fn query_segs<'a>(&'a self, segs: &[ValuePathSeg], out: &mut Vec<&'a Self>) {
+        let (seg, rest) = match segs.split_first() {
+            Some(split) => split,
+            None => {
+                out.push(self);
+                return
+            }
+        };
+        match seg {
+            ValuePathSeg::Key(key) => if let Some(child) = self.child_key(key) {
+                child.query_segs(rest, out)
+            },
+            ValuePathSeg::Index(index) => if let Some(child) = self.child_index(*index) {
+                child.query_segs(rest, out)
+            },
+            ValuePathSeg::Any => for child in self.children() {
+                child.query_segs(rest, out)
+            }
+        }
+    }
+
+    fn query_path(&self, path: &str) -> Vec<&Self> {
+        let mut out = Vec::new();
+        if let Ok(segs) = parse_value_path(path) {
+            self.query_segs(&segs, &mut out);
+        }
+        out
+    }
+}
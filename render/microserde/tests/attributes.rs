@@ -0,0 +1,140 @@
+use makepad_microserde::*;
+
+#[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, SerToml, DeToml, PartialEq, Debug)]
+struct Fields {
+    #[rename = "renamed"]
+    a: u32,
+    #[default]
+    b: u32,
+    #[default = 5]
+    c: u32,
+    #[default(2 + 3)]
+    d: u32,
+    #[default = "String::from(\"text\")"]
+    e: String,
+    #[skip]
+    f: u32,
+    g: Option<u32>,
+    #[flatten]
+    inner: Inner,
+}
+
+#[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, SerToml, DeToml, PartialEq, Debug)]
+struct Inner {
+    x: u32,
+}
+
+// the way settings files are read, new fields get the value of Default
+#[derive(SerJson, DeJson, SerRon, DeRon, SerToml, DeToml, PartialEq, Debug)]
+#[default]
+#[ignore_unknown]
+struct Settings {
+    scale: f32,
+    dark: bool,
+    #[default = 3]
+    recent: u32,
+    name: Option<String>,
+    #[skip]
+    session: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {scale: 1.5, dark: true, recent: 10, name: Some("default".to_string()), session: 7}
+    }
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, SerToml, DeToml, PartialEq, Debug)]
+#[tag = "type"]
+#[ignore_unknown]
+enum Internal {
+    One,
+    Two(Inner),
+    Three {x: u32},
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+#[tag = "t"]
+#[content = "c"]
+enum Adjacent {
+    One(u32),
+    Two(u32, u32),
+    Named {x: u32},
+    Unit
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+#[untagged]
+enum Untagged {
+    Int(i64),
+    Text(String),
+    Pair {a: i64, b: i64},
+}
+
+#[test]
+fn field_attributes() {
+    let fields = Fields {a: 1, b: 2, c: 3, d: 4, e: "e".to_string(), f: 6, g: None, inner: Inner {x: 7}};
+    let skipped = Fields {a: 1, b: 2, c: 3, d: 4, e: "e".to_string(), f: 0, g: None, inner: Inner {x: 7}};
+
+    let json = fields.serialize_json();
+    assert!(json.contains("\"renamed\":1") && json.contains("\"x\":7") && !json.contains("\"f\""), "{}", json);
+    assert_eq!(Fields::deserialize_json(&json).unwrap(), skipped);
+    let ron = fields.serialize_ron();
+    assert_eq!(Fields::deserialize_ron(&ron).unwrap(), skipped);
+    let toml = fields.serialize_toml();
+    assert_eq!(Fields::deserialize_toml(&toml).unwrap(), skipped);
+    // the binary formats have no keys, so only skip changes them
+    assert_eq!(Fields::deserialize_bin(&fields.serialize_bin()).unwrap(), skipped);
+
+    let missing = Fields {a: 1, b: 0, c: 5, d: 5, e: "text".to_string(), f: 0, g: None, inner: Inner {x: 7}};
+    assert_eq!(Fields::deserialize_json("{\"renamed\":1,\"x\":7}").unwrap(), missing);
+    assert_eq!(Fields::deserialize_ron("(renamed:1,x:7)").unwrap(), missing);
+    assert_eq!(Fields::deserialize_toml("renamed = 1\nx = 7").unwrap(), missing);
+
+    // a field without a default is still required, and unknown keys are an error
+    assert!(Fields::deserialize_json("{\"x\":7}").unwrap_err().msg.contains("renamed"));
+    assert!(Fields::deserialize_ron("(x:7)").is_err());
+    assert!(Fields::deserialize_toml("x = 7").unwrap_err().msg.contains("renamed"));
+    assert!(Inner::deserialize_json("{\"x\":7,\"y\":8}").is_err());
+    assert!(Inner::deserialize_ron("(x:7,y:8)").is_err());
+    assert!(Inner::deserialize_toml("x = 7\ny = 8").unwrap_err().msg.contains("y"));
+}
+
+#[test]
+fn container_attributes() {
+    let expect = Settings {scale: 2.0, dark: true, recent: 3, name: Some("default".to_string()), session: 7};
+    assert_eq!(Settings::deserialize_json("{\"scale\":2.0,\"old\":[1,{\"a\":null}]}").unwrap(), expect);
+    assert_eq!(Settings::deserialize_ron("(scale:2.0,old:[1,(a:None)])").unwrap(), expect);
+    assert_eq!(Settings::deserialize_toml("scale = 2.0\nold = [1, 2]").unwrap(), expect);
+    assert_eq!(Settings::deserialize_json("{}").unwrap(), Settings {recent: 3, ..Settings::default()});
+
+    // a value that is there still has to parse
+    assert!(Settings::deserialize_json("{\"scale\":true}").is_err());
+
+    let value = Internal::Three {x: 1};
+    assert_eq!(Internal::deserialize_json("{\"type\":\"Three\",\"x\":1,\"y\":2}").unwrap(), value);
+    // the struct in a newtype variant reads its keys itself
+    assert!(Internal::deserialize_json("{\"type\":\"Two\",\"x\":1,\"y\":2}").is_err());
+}
+
+#[test]
+fn enum_representations() {
+    for value in vec![Internal::One, Internal::Two(Inner {x: 1}), Internal::Three {x: 2}] {
+        let json = value.serialize_json();
+        assert!(json.contains("\"type\""), "{}", json);
+        assert_eq!(Internal::deserialize_json(&json).unwrap(), value);
+        assert_eq!(Internal::deserialize_ron(&value.serialize_ron()).unwrap(), value);
+        assert_eq!(Internal::deserialize_toml(&value.serialize_toml()).unwrap(), value);
+    }
+    for value in vec![Adjacent::One(1), Adjacent::Two(1, 2), Adjacent::Named {x: 3}, Adjacent::Unit] {
+        let json = value.serialize_json();
+        assert!(json.contains("\"t\""), "{}", json);
+        assert_eq!(Adjacent::deserialize_json(&json).unwrap(), value);
+        assert_eq!(Adjacent::deserialize_ron(&value.serialize_ron()).unwrap(), value);
+    }
+    for value in vec![Untagged::Int(1), Untagged::Text("x".to_string()), Untagged::Pair {a: 1, b: 2}] {
+        assert_eq!(Untagged::deserialize_json(&value.serialize_json()).unwrap(), value);
+        assert_eq!(Untagged::deserialize_ron(&value.serialize_ron()).unwrap(), value);
+    }
+    assert!(Untagged::deserialize_json("true").is_err());
+}
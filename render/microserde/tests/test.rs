@@ -0,0 +1,773 @@
+use makepad_microserde::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+const CARGO: &str = r#"
+# the root manifest of a workspace
+[workspace]
+members = [
+    "makepad",
+    "examples/*", # globbed
+    'tools/webserver',
+]
+
+[package]
+name = "makepad"
+version = "0.1.0"
+authors = ["makepad <info@makepad.nl>"]
+
+[dependencies]
+makepad-render = { path = "render", version = "0.1" }
+serde.version = "1.0"
+serde.features = ["derive"]
+
+[target.'cfg(windows)'.dependencies.winapi]
+version = "0.3"
+
+[profile.release]
+opt-level = 3
+"#;
+
+#[test]
+fn cargo_workspace() {
+    let toml = TomlParser::parse(CARGO).unwrap();
+    let members = toml.get_path("workspace.members").unwrap().as_array().unwrap();
+    let members: Vec<&str> = members.iter().map( | m | m.as_str().unwrap()).collect();
+    assert_eq!(members, vec!["makepad", "examples/*", "tools/webserver"]);
+    assert_eq!(toml.get_path("package.name"), Some(&Toml::Str("makepad".to_string())));
+    assert_eq!(toml.get_path("dependencies.makepad-render.path").and_then( | v | v.as_str()), Some("render"));
+    assert!(matches!(toml.get_path("dependencies.makepad-render"), Some(Toml::InlineTable(_))));
+    assert_eq!(toml.get_path("dependencies.serde.version").and_then( | v | v.as_str()), Some("1.0"));
+    let winapi = toml.get("target").unwrap().get("cfg(windows)").unwrap().get_path("dependencies.winapi.version");
+    assert_eq!(winapi.and_then( | v | v.as_str()), Some("0.3"));
+    assert_eq!(toml.get_path("profile.release.opt-level").and_then( | v | v.as_i64()), Some(3));
+}
+
+#[test]
+fn values() {
+    let toml = TomlParser::parse(r#"
+        int = +1_000
+        neg = -17
+        hex = 0xDEAD_beef
+        oct = 0o755
+        bin = 0b1101
+        float = 6.626e-34
+        frac = -0.01
+        exp = 5e+22
+        inf = -inf
+        nan = nan
+        yes = true
+        basic = "tab\tquote\" \u00e9 \U0001F600"
+        literal = 'C:\Users\nodejs'
+        multi = """
+Roses are red \
+    Violets are blue"""
+        quotes = """two "" quotes"""""
+        raw = '''
+first line
+second line'''
+        "quoted key" = 1
+        dotted . "and quoted" = 2
+        empty = ""
+    "#).unwrap();
+    let get = | key: &str | toml.get(key).unwrap().clone();
+    assert_eq!(get("int"), Toml::Int(1000));
+    assert_eq!(get("neg"), Toml::Int(-17));
+    assert_eq!(get("hex"), Toml::Int(0xdeadbeef));
+    assert_eq!(get("oct"), Toml::Int(0o755));
+    assert_eq!(get("bin"), Toml::Int(13));
+    assert_eq!(get("float"), Toml::Float(6.626e-34));
+    assert_eq!(get("frac"), Toml::Float(-0.01));
+    assert_eq!(get("exp"), Toml::Float(5e22));
+    assert_eq!(get("inf"), Toml::Float(std::f64::NEG_INFINITY));
+    assert!(get("nan").as_f64().unwrap().is_nan());
+    assert_eq!(get("yes"), Toml::Bool(true));
+    assert_eq!(get("basic").as_str(), Some("tab\tquote\" \u{e9} \u{1F600}"));
+    assert_eq!(get("literal").as_str(), Some("C:\\Users\\nodejs"));
+    assert_eq!(get("multi").as_str(), Some("Roses are red Violets are blue"));
+    assert_eq!(get("quotes").as_str(), Some("two \"\" quotes\"\""));
+    assert_eq!(get("raw").as_str(), Some("first line\nsecond line"));
+    assert_eq!(get("quoted key"), Toml::Int(1));
+    assert_eq!(toml.get("dotted").unwrap().get("and quoted"), Some(&Toml::Int(2)));
+    assert_eq!(get("empty").as_str(), Some(""));
+}
+
+#[test]
+fn datetimes() {
+    let toml = TomlParser::parse(r#"
+        odt1 = 1979-05-27T07:32:00Z
+        odt2 = 1979-05-27T00:32:00.999999-07:00
+        odt3 = 1979-05-27 07:32:00+01:30
+        ldt = 1979-05-27T07:32:00
+        ld = 1979-05-27
+        lt = 00:32:00.5
+    "#).unwrap();
+    let date = | key: &str | *toml.get(key).unwrap().as_date().unwrap();
+    let odt2 = date("odt2");
+    assert_eq!(odt2.date, Some(TomlDate {year: 1979, month: 5, day: 27}));
+    assert_eq!(odt2.time, Some(TomlTime {hour: 0, minute: 32, second: 0, nanosecond: 999_999_000}));
+    assert_eq!(odt2.offset, Some(TomlOffset::Minutes(-7 * 60)));
+    assert_eq!(date("odt1").to_string(), "1979-05-27T07:32:00Z");
+    assert_eq!(date("odt3").to_string(), "1979-05-27T07:32:00+01:30");
+    assert_eq!(date("ldt").offset, None);
+    assert_eq!(date("ld").time, None);
+    assert_eq!(date("lt").date, None);
+    assert_eq!(date("lt").to_string(), "00:32:00.5");
+}
+
+#[test]
+fn tables() {
+    let toml = TomlParser::parse(r#"
+        [dog."tater.man"]
+        type.name = "pug"
+
+        [x.y.z.w]
+        [x]
+        a = 1
+
+        [[fruits]]
+        name = "apple"
+
+        [fruits.physical]
+        color = "red"
+
+        [[fruits.varieties]]
+        name = "red delicious"
+
+        [[fruits.varieties]]
+        name = "granny smith"
+
+        [[fruits]]
+        name = "banana"
+
+        [[fruits.varieties]]
+        name = "plantain"
+    "#).unwrap();
+    assert_eq!(toml.get_path("dog").unwrap().get("tater.man").unwrap().get_path("type.name").unwrap().as_str(), Some("pug"));
+    assert_eq!(toml.get_path("x.a"), Some(&Toml::Int(1)));
+    assert!(toml.get_path("x.y.z.w").unwrap().as_table().unwrap().is_empty());
+    let fruits = match toml.get("fruits") {
+        Some(Toml::ArrayOfTables(fruits)) => fruits,
+        _ => panic!("fruits is not an array of tables")
+    };
+    assert_eq!(fruits.len(), 2);
+    assert_eq!(fruits[0].get_path("physical.color").unwrap().as_str(), Some("red"));
+    match fruits[0].get("varieties") {
+        Some(Toml::ArrayOfTables(varieties)) => assert_eq!(varieties.len(), 2),
+        _ => panic!("varieties is not an array of tables")
+    }
+    assert_eq!(fruits[1].get("name").unwrap().as_str(), Some("banana"));
+}
+
+#[test]
+fn invalid() {
+    let invalid = [
+        "a = 1\na = 2",
+        "[a]\n[a]",
+        "a.b = 1\n[a]",
+        "[a]\nb = 1\n[a.b]",
+        "a = {b = 1}\n[a]",
+        "a = {b = 1}\na.c = 2",
+        "a = [1]\n[[a]]",
+        "a = {b = 1, b = 2}",
+        "a = 01",
+        "a = 1__0",
+        "a = _1",
+        "a = 1.",
+        "a = .5",
+        "a = +0x10",
+        "a = 1979-13-01",
+        "a = 24:00:00",
+        "a = \"\\q\"",
+        "a = \"open",
+        "a = 1 b = 2",
+        "a = ",
+        "\"\"\"multi\"\"\" = 1",
+        "[a]]",
+    ];
+    for input in invalid.iter() {
+        assert!(TomlParser::parse(input).is_err(), "parsed invalid toml {:?}", input);
+    }
+    let err = TomlParser::parse("a = 1\nb = 2\na = 3").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn write_roundtrip() {
+    let input = r#"
+        title = "roundtrip \"test\"\n"
+        nums = [1, 2.5, -inf]
+        when = 1979-05-27T07:32:00.25-07:00
+        point = { x = 1, y = 2 }
+        "odd key" = true
+
+        [owner]
+        name = "Tom"
+        dob = 1979-05-27
+
+        [servers.alpha]
+        ip = "10.0.0.1"
+
+        [servers.beta]
+
+        [[products]]
+        name = "Hammer"
+
+        [[products]]
+        name = "Nail"
+        sizes = [[1, 2], ["a"]]
+    "#;
+    let toml = TomlParser::parse(input).unwrap();
+    let output = toml.to_toml_string();
+    assert_eq!(TomlParser::parse(&output).unwrap(), toml, "{}", output);
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Package {
+    name: String,
+    version: String,
+    #[default]
+    authors: Vec<String>,
+    edition: Option<String>,
+    #[rename = "publish-crate"]
+    #[default(true)]
+    publish: bool,
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Dependency {
+    path: Option<String>,
+    version: Option<String>,
+    #[skip]
+    resolved: bool,
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Manifest {
+    package: Package,
+    dependencies: HashMap<String, Dependency>,
+    bins: Vec<Bin>,
+    color: Color,
+    shapes: Vec<Shape>,
+    values: Vec<Value>,
+    pairs: Vec<(u8, f32)>,
+    matrix: [[i64; 2]; 2],
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Bin {
+    name: String,
+    #[flatten]
+    extra: Extra
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Extra {
+    test: bool,
+    bench: bool
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+enum Color {
+    Named(String),
+    Rgb(u8, u8, u8),
+    Hsl {h: f32, s: f32, l: f32},
+    Black
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+#[tag = "kind"]
+enum Shape {
+    Circle {radius: f64},
+    Square(Side),
+    Empty
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+struct Side {
+    side: f64
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+#[untagged]
+enum Value {
+    Int(i64),
+    Text(String),
+    Pair {a: i64, b: i64},
+    Nothing
+}
+
+#[derive(SerToml, DeToml, PartialEq, Debug)]
+#[tag = "t"]
+#[content = "c"]
+enum Adjacent {
+    One(u32),
+    Two(u32, u32),
+    Named {x: u32},
+    Unit
+}
+
+#[test]
+fn derive_roundtrip() {
+    let mut dependencies = HashMap::new();
+    dependencies.insert("makepad-render".to_string(), Dependency {path: Some("render".to_string()), version: None, resolved: false});
+    dependencies.insert("serde".to_string(), Dependency {path: None, version: Some("1.0".to_string()), resolved: false});
+    let manifest = Manifest {
+        package: Package {
+            name: "makepad".to_string(),
+            version: "0.1.0".to_string(),
+            authors: vec!["makepad".to_string()],
+            edition: None,
+            publish: false
+        },
+        dependencies,
+        bins: vec![
+            Bin {name: "a".to_string(), extra: Extra {test: true, bench: false}},
+            Bin {name: "b".to_string(), extra: Extra {test: false, bench: true}},
+        ],
+        color: Color::Hsl {h: 0.5, s: 1.0, l: 0.25},
+        shapes: vec![Shape::Circle {radius: 2.0}, Shape::Square(Side {side: 3.0}), Shape::Empty],
+        values: vec![Value::Int(1), Value::Text("x".to_string()), Value::Pair {a: 1, b: 2}, Value::Nothing],
+        pairs: vec![(1, 0.5), (2, 1.5)],
+        matrix: [[1, 2], [3, 4]],
+    };
+    let output = manifest.serialize_toml();
+    assert!(output.contains("[[bins]]"), "{}", output);
+    assert!(output.contains("publish-crate = false"), "{}", output);
+    assert!(!output.contains("resolved"), "{}", output);
+    assert_eq!(Manifest::deserialize_toml(&output).unwrap(), manifest, "{}", output);
+
+    for color in vec![Color::Named("red".to_string()), Color::Rgb(1, 2, 3), Color::Black] {
+        let output = color.serialize_toml();
+        assert_eq!(Color::deserialize_toml(&output).unwrap(), color, "{}", output);
+    }
+    for adjacent in vec![Adjacent::One(1), Adjacent::Two(1, 2), Adjacent::Named {x: 3}, Adjacent::Unit] {
+        let output = adjacent.serialize_toml();
+        assert_eq!(Adjacent::deserialize_toml(&output).unwrap(), adjacent, "{}", output);
+    }
+}
+
+const CARGO_MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"hub 0.1.0","message":{
+    "message":"unused variable: `x`","level":"warning",
+    "spans":[{"file_name":"src/main.rs","line_start":3,"column_start":9,"is_primary":true}],
+    "children":[{"message":"first note","spans":[]},{"message":"second note","spans":[]}],
+    "code":null,"ratio":-0.5}}"#;
+
+#[derive(SerJson, DeJson, PartialEq, Debug)]
+struct Span {
+    file_name: String,
+    line_start: u32,
+    column_start: u32,
+    is_primary: bool
+}
+
+#[test]
+fn json_value() {
+    let value = JsonValue::deserialize_json(CARGO_MESSAGE).unwrap();
+    assert_eq!(value["reason"].as_str(), Some("compiler-message"));
+    assert_eq!(value["message"]["spans"][0]["line_start"].as_u64(), Some(3));
+    assert!(value["message"]["code"].is_null());
+    assert!(value["nothing"][5]["here"].is_null());
+    assert_eq!(value["message"]["ratio"].as_f64(), Some(-0.5));
+    assert_eq!(value.query("message.spans[0].file_name").and_then( | v | v.as_str()), Some("src/main.rs"));
+    assert_eq!(value.query("[\"message\"].level").and_then( | v | v.as_str()), Some("warning"));
+    let notes: Vec<&str> = value.query_all("message.children[*].message").iter().filter_map( | v | v.as_str()).collect();
+    assert_eq!(notes, vec!["first note", "second note"]);
+    assert_eq!(value.query_all("message.*").len(), 6);
+    assert!(value.query("message.spans[1]").is_none());
+    assert!(value.query("message..level").is_none());
+
+    let span: Span = value.query("message.spans[0]").unwrap().to_type().unwrap();
+    assert_eq!(span, Span {file_name: "src/main.rs".to_string(), line_start: 3, column_start: 9, is_primary: true});
+    let back = JsonValue::from_type(&span).unwrap();
+    assert_eq!(&back, value.query("message.spans[0]").unwrap());
+
+    let output = value.serialize_json();
+    assert!(!output.contains(",}"), "{}", output);
+    assert_eq!(JsonValue::deserialize_json(&output).unwrap(), value);
+
+    let mut object = JsonValue::Object(Vec::new());
+    object.insert("a", 1u64.into());
+    object.insert("b", "text".into());
+    object.insert("a", 2.0.into());
+    assert_eq!(object.serialize_json(), "{\"a\":2.0,\"b\":\"text\"}");
+}
+
+#[test]
+fn value_path() {
+    use ValuePathSeg::*;
+    assert_eq!(parse_value_path("").unwrap(), vec![]);
+    assert_eq!(parse_value_path("a.b[2][*].*").unwrap(), vec![Key("a".to_string()), Key("b".to_string()), Index(2), Any, Any]);
+    assert_eq!(parse_value_path("[\"x.y\"].z").unwrap(), vec![Key("x.y".to_string()), Key("z".to_string())]);
+    for invalid in ["a.", ".a", "a[", "a[x]", "a..b", "a[0]b", "a.[0]"].iter() {
+        assert!(parse_value_path(invalid).is_err(), "parsed invalid path {:?}", invalid);
+    }
+}
+
+#[derive(SerRon, DeRon, PartialEq, Debug)]
+struct Window {
+    title: String,
+    size: [u32; 2],
+    mode: Mode,
+    parent: Option<Box<Window>>,
+}
+
+#[derive(SerRon, DeRon, PartialEq, Debug)]
+enum Mode {
+    Windowed,
+    Fullscreen(u32),
+}
+
+#[test]
+fn ron_value() {
+    let value = RonValue::deserialize_ron(r#"
+        Window(
+            title: "main", // a comment
+            size: (800, 600),
+            key: 'k',
+            mode: Fullscreen(1),
+            parent: Some((title: "root", size: (1, 2), key: '\'', mode: Windowed, parent: None)),
+            map: {"a": [1.5, -2], b: ()},
+        )
+    "#).unwrap();
+    assert_eq!(value.name(), Some("Window"));
+    assert_eq!(value["title"].as_str(), Some("main"));
+    assert_eq!(value["size"][1].as_u64(), Some(600));
+    assert_eq!(value["key"].as_char(), Some('k'));
+    assert_eq!(value["mode"].name(), Some("Fullscreen"));
+    assert_eq!(value.query("parent[0].mode").and_then( | v | v.as_ident()), Some("Windowed"));
+    assert_eq!(value.query("parent[0].key").and_then( | v | v.as_char()), Some('\''));
+    assert_eq!(value.query("map.a[1]").and_then( | v | v.as_i64()), Some(-2));
+    assert!(value.query("map.b").unwrap().is_unit());
+    assert_eq!(value.query_all("size[*]").len(), 2);
+    assert_eq!(RonValue::deserialize_ron(&value.serialize_ron()).unwrap(), value);
+
+    let window = Window {
+        title: "w".to_string(),
+        size: [3, 4],
+        mode: Mode::Fullscreen(2),
+        parent: Some(Box::new(Window {title: "p".to_string(), size: [1, 1], mode: Mode::Windowed, parent: None}))
+    };
+    let tree = RonValue::from_type(&window).unwrap();
+    assert_eq!(tree.query("parent.title").and_then( | v | v.as_str()), Some("p"));
+    assert_eq!(tree.to_type::<Window>().unwrap(), window);
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, SerBin, DeBin, Debug, PartialEq)]
+struct SpanRef<'a> {
+    file_name: &'a str,
+    label: Option<&'a str>,
+    line: u32,
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, Debug, PartialEq)]
+struct Message<'a, T> where T: Clone {
+    level: &'a str,
+    text: Cow<'a, str>,
+    spans: Vec<SpanRef<'a>>,
+    extra: T,
+}
+
+#[derive(SerBin, DeBin, Debug, PartialEq)]
+enum Packet<'a> {
+    Data(u16, &'a [u8]),
+    Name {name: &'a str},
+}
+
+#[test]
+fn borrowed_fields() {
+    let json = r#"{"level": "error", "text": "a\nb", "spans": [{"file_name": "src/main.rs", "label": null, "line": 3}], "extra": 1.5}"#;
+    let msg: Message<f64> = DeJson::deserialize_json(json).unwrap();
+    assert_eq!(msg.level, "error");
+    assert!(matches!(msg.text, Cow::Owned(_)));
+    assert_eq!(msg.text, "a\nb");
+    assert_eq!(msg.spans[0], SpanRef {file_name: "src/main.rs", label: None, line: 3});
+    // the slice points into the input
+    let start = json.find("src/main.rs").unwrap();
+    assert_eq!(msg.spans[0].file_name.as_ptr(), json[start..].as_ptr());
+    let again = msg.serialize_json();
+    assert_eq!(<Message<f64> as DeJson>::deserialize_json(&again).unwrap(), msg);
+
+    let text: Cow<str> = DeJson::deserialize_json("\"plain\"").unwrap();
+    assert!(matches!(text, Cow::Borrowed("plain")));
+    // a &str can't hold an unescaped copy
+    assert!(<&str as DeJson>::deserialize_json(r#""a\"b""#).is_err());
+    assert_eq!(<&str as DeJson>::deserialize_json(r#""añb""#).unwrap(), "añb");
+
+    let ron = r#"(level: "warning", text: "t", spans: [(file_name: "lib.rs", label: "here", line: 1)], extra: "x")"#;
+    let msg: Message<String> = DeRon::deserialize_ron(ron).unwrap();
+    assert_eq!(msg.spans[0].label, Some("here"));
+    assert_eq!(msg.extra, "x");
+
+    let span = SpanRef {file_name: "a.rs", label: Some("l"), line: 7};
+    let bin = span.serialize_bin();
+    assert_eq!(SpanRef::deserialize_bin(&bin).unwrap(), span);
+    for packet in vec![Packet::Data(1, &[1, 2, 3]), Packet::Name {name: "n"}] {
+        let bin = packet.serialize_bin();
+        assert_eq!(Packet::deserialize_bin(&bin).unwrap(), packet);
+    }
+    // a &[u8] is written like a Vec<u8>
+    assert_eq!(Packet::Data(0, &[9]).serialize_bin(), (0u16, 0u16, vec![9u8]).serialize_bin());
+    assert!(<&str as DeBin>::deserialize_bin(&[1, 0, 0, 0, 0, 0, 0, 0, 0xff]).is_err());
+    // reads at odd offsets
+    assert_eq!(<(u8, u64) as DeBin>::deserialize_bin(&[1, 2, 0, 0, 0, 0, 0, 0, 0]).unwrap(), (1, 2));
+}
+
+#[test]
+fn json_lines() {
+    let input = "{\"file_name\": \"a.rs\", \"label\": null, \"line\": 1}\n\n{\"file_name\": \"b.rs\", \"label\": \"x\", \"line\": 2}\n{\"file_name\": 3}\n";
+    let mut reader = JsonLineReader::new(input.as_bytes());
+    let span: SpanRef = reader.read_next().unwrap().unwrap();
+    assert_eq!(span.file_name, "a.rs");
+    let span: SpanRef = reader.read_next().unwrap().unwrap();
+    assert_eq!(span.label, Some("x"));
+    let err = reader.read_next::<SpanRef>().unwrap().unwrap_err();
+    assert_eq!(err.line, 3);
+    assert!(reader.read_next::<SpanRef>().is_none());
+
+    let values: Vec<JsonValue> = JsonLineReader::new(input.as_bytes()).documents().map( | v | v.unwrap()).collect();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[1]["line"].as_u64(), Some(2));
+}
+
+// an old and a new version of the same message, as two programs would see it
+mod v1 {
+    use makepad_microserde::*;
+    
+    #[derive(SerBinTagged, DeBinTagged, Debug, PartialEq)]
+    pub enum Event {
+        Started {name: String},
+        Done(u64, bool),
+        Stop,
+    }
+    
+    #[derive(SerBinTagged, DeBinTagged, Debug, PartialEq)]
+    #[version = 1]
+    pub struct Build {
+        pub name: String,
+        pub jobs: u32,
+        pub events: Vec<Event>,
+    }
+}
+
+mod v2 {
+    use makepad_microserde::*;
+    
+    #[derive(SerBinTagged, DeBinTagged, Debug, PartialEq)]
+    pub enum Event {
+        Started {name: String, #[id = 2] pid: Option<u32>},
+        Done(u64, bool),
+        Stop,
+        Output(String),
+    }
+    
+    #[derive(SerBinTagged, DeBinTagged, Debug, PartialEq)]
+    #[version = 2]
+    pub struct Build {
+        pub name: String,
+        // jobs was removed, its id is not reused
+        #[id = 3] pub events: Vec<Event>,
+        #[default = 4] pub threads: u16,
+        pub target: Option<String>,
+        #[skip] pub cached: bool,
+    }
+    
+    #[derive(SerBinTagged, DeBinTagged, Debug, PartialEq)]
+    #[version = 3]
+    #[min_version = 3]
+    pub struct Incompatible {
+        pub name: u64,
+    }
+}
+
+#[test]
+fn bin_tagged() {
+    let old = v1::Build {
+        name: "makepad".to_string(),
+        jobs: 8,
+        events: vec![v1::Event::Started {name: "rustc".to_string()}, v1::Event::Done(3, true), v1::Event::Stop],
+    };
+    let bin = old.serialize_bin_tagged();
+    assert_eq!(v1::Build::deserialize_bin_tagged(&bin).unwrap(), old);
+    
+    // a newer reader skips the removed field and fills in the new ones
+    let new = v2::Build::deserialize_bin_tagged(&bin).unwrap();
+    assert_eq!(new, v2::Build {
+        name: "makepad".to_string(),
+        events: vec![v2::Event::Started {name: "rustc".to_string(), pid: None}, v2::Event::Done(3, true), v2::Event::Stop],
+        threads: 4,
+        target: None,
+        cached: false,
+    });
+    
+    // and an older reader skips fields it doesn't know
+    let new = v2::Build {
+        name: "render".to_string(),
+        events: vec![v2::Event::Started {name: "cc".to_string(), pid: Some(12)}],
+        threads: 2,
+        target: Some("wasm32".to_string()),
+        cached: true,
+    };
+    let bin = new.serialize_bin_tagged();
+    let old = v1::Build::deserialize_bin_tagged(&bin).unwrap_err();
+    assert!(old.msg.contains("Build.jobs"), "{:?}", old);
+    let again = v2::Build::deserialize_bin_tagged(&bin).unwrap();
+    assert_eq!(again.target, Some("wasm32".to_string()));
+    assert_eq!(again.cached, false);
+    
+    // but not variants it doesn't know
+    let bin = v2::Event::Output("x".to_string()).serialize_bin_tagged();
+    let err = v1::Event::deserialize_bin_tagged(&bin).unwrap_err();
+    assert!(err.msg.contains("no variant with id 4"), "{:?}", err);
+    
+    // versions out of range are reported
+    let bin = v2::Incompatible {name: 1}.serialize_bin_tagged();
+    let err = v2::Build::deserialize_bin_tagged(&bin).unwrap_err();
+    assert!(err.msg.contains("needs at least version 3"), "{:?}", err);
+    let bin = v1::Build {name: String::new(), jobs: 0, events: Vec::new()}.serialize_bin_tagged();
+    let err = v2::Incompatible::deserialize_bin_tagged(&bin).unwrap_err();
+    assert!(err.msg.contains("reads versions 3 to 3"), "{:?}", err);
+    
+    // and broken data is an error, not a panic
+    let bin = v2::Event::Done(300, false).serialize_bin_tagged();
+    for len in 0..bin.len() {
+        assert!(v2::Event::deserialize_bin_tagged(&bin[0..len]).is_err());
+    }
+    
+    let value = (vec![-1i32, 70000], Some(1.5f64), [3u8; 3], Some(None::<bool>));
+    let bin = value.serialize_bin_tagged();
+    assert_eq!(<(Vec<i32>, Option<f64>, [u8; 3], Option<Option<bool>>)>::deserialize_bin_tagged(&bin).unwrap(), value);
+    // bytes are written as they are
+    let bytes = vec![0u8, 200, 255];
+    assert_eq!(bytes.serialize_bin_tagged(), vec![3, 0, 200, 255]);
+    assert_eq!(<&[u8]>::deserialize_bin_tagged(&bytes.serialize_bin_tagged()).unwrap(), &bytes[..]);
+}
+
+#[derive(SerMsgPack, DeMsgPack, SerCbor, DeCbor, Debug, PartialEq)]
+struct Compact {
+    compact: bool,
+    schema: u32,
+}
+
+#[derive(SerMsgPack, DeMsgPack, SerCbor, DeCbor, Debug, PartialEq)]
+enum Figure {
+    Empty,
+    Circle(f32),
+    Line(i64, i64),
+    #[rename = "rect"] Rect {w: u16, h: u16, #[default = 1] depth: u8, label: Option<String>},
+}
+
+#[derive(SerMsgPack, DeMsgPack, SerCbor, DeCbor, Debug, PartialEq)]
+struct Drawing<'a> {
+    name: &'a str,
+    shapes: Vec<Figure>,
+    tags: HashMap<String, i8>,
+    data: &'a [u8],
+    points: Box<[(u8, u8); 2]>,
+    #[skip] cached: u32,
+}
+
+#[test]
+fn msgpack_cbor() {
+    // the example from msgpack.org
+    let compact = Compact {compact: true, schema: 0};
+    let msgpack = b"\x82\xa7compact\xc3\xa6schema\x00";
+    assert_eq!(compact.serialize_msgpack(), msgpack);
+    assert_eq!(Compact::deserialize_msgpack(msgpack).unwrap(), compact);
+    assert_eq!(compact.serialize_cbor(), b"\xa2\x67compact\xf5\x66schema\x00");
+    
+    let drawing = Drawing {
+        name: "logo",
+        shapes: vec![Figure::Empty, Figure::Circle(0.5), Figure::Line(-1, 1000000), Figure::Rect {w: 300, h: 2, depth: 3, label: None}],
+        tags: vec![("z".to_string(), -100)].into_iter().collect(),
+        data: &[1, 2, 255],
+        points: Box::new([(1, 2), (3, 4)]),
+        cached: 0,
+    };
+    let msgpack = drawing.serialize_msgpack();
+    assert_eq!(Drawing::deserialize_msgpack(&msgpack).unwrap(), drawing);
+    let cbor = drawing.serialize_cbor();
+    assert_eq!(Drawing::deserialize_cbor(&cbor).unwrap(), drawing);
+    for len in 0..cbor.len() {
+        assert!(Drawing::deserialize_cbor(&cbor[0..len]).is_err());
+    }
+    for len in 0..msgpack.len() {
+        assert!(Drawing::deserialize_msgpack(&msgpack[0..len]).is_err());
+    }
+    
+    // variants are tagged by name, missing fields get defaults and unknown ones are skipped
+    assert_eq!(Figure::Empty.serialize_msgpack(), b"\xa5Empty");
+    assert_eq!(Figure::Circle(1.0).serialize_cbor(), b"\xa1\x66Circle\xfa\x3f\x80\x00\x00");
+    let rect = b"\x81\xa4rect\x83\xa1w\x01\xa1h\xcd\x01\x00\xa5extra\x92\xc0\xa1x";
+    assert_eq!(Figure::deserialize_msgpack(rect).unwrap(), Figure::Rect {w: 1, h: 256, depth: 1, label: None});
+    let err = Figure::deserialize_msgpack(b"\xa6Square").unwrap_err();
+    assert!(err.msg.contains("no variant Square"), "{:?}", err);
+    let err = Compact::deserialize_cbor(b"\xa1\x67compact\x01").unwrap_err();
+    assert_eq!(err.msg, "Expected a bool found an integer");
+    let err = Compact::deserialize_cbor(b"\xa1\x66schema\x00").unwrap_err();
+    assert_eq!(err.msg, "Field Compact.compact not found");
+    assert!(u8::deserialize_msgpack(b"\xcd\x01\x00").is_err());
+    
+    // CBOR from other encoders: examples from RFC 8949
+    assert_eq!(i32::deserialize_cbor(b"\x39\x03\xe7").unwrap(), -1000);
+    assert_eq!(f64::deserialize_cbor(b"\xf9\x3e\x00").unwrap(), 1.5);
+    assert_eq!(u32::deserialize_cbor(b"\xc1\x1a\x51\x4b\x67\xb0").unwrap(), 1363896240);
+    let nested = b"\x9f\x01\x82\x02\x03\x9f\x04\x05\xff\xff";
+    assert_eq!(<(u8, Vec<u8>, Vec<u8>)>::deserialize_cbor(nested).unwrap(), (1, vec![2, 3], vec![4, 5]));
+    let map: HashMap<String, Vec<u8>> = DeCbor::deserialize_cbor(b"\xbf\x61a\x81\x01\x61b\x9f\x02\x03\xff\xff").unwrap();
+    assert_eq!(map["a"], vec![1]);
+    assert_eq!(map["b"], vec![2, 3]);
+    let chunks = b"\x7f\x65strea\x64ming\xff";
+    assert_eq!(String::deserialize_cbor(chunks).unwrap(), "streaming");
+    assert!(<&str>::deserialize_cbor(chunks).is_err());
+    assert_eq!(Compact::deserialize_cbor(b"\xbf\x66schema\x02\x61x\x9f\xff\x67compact\xf4\xff").unwrap(), Compact {compact: false, schema: 2});
+}
+
+#[derive(DeRon, DeJson, SerBin, DeBin, Debug)]
+struct Workspace {
+    name: String,
+    paths: Vec<String>,
+}
+
+#[derive(DeRon, DeJson, Debug)]
+struct BuilderSettings {
+    workspaces: HashMap<String, Workspace>,
+    port: u16,
+}
+
+#[derive(DeRon, DeJson, Debug)]
+struct Settings {
+    builders: HashMap<String, BuilderSettings>,
+}
+
+#[test]
+fn error_paths() {
+    let ron = "(\n    builders: {\n        \"main\": (\n\t\tworkspaces: {\"makepad\": (name: \"makepad\", paths: [\"a\", 3])},\n            port: 8000,\n        ),\n    },\n)";
+    let err = Settings::deserialize_ron(ron).unwrap_err();
+    assert_eq!(err.path, "builders.main.workspaces.makepad.paths[1]");
+    assert_eq!(err.expected.as_deref(), Some("string"));
+    assert_eq!(err.found.as_deref(), Some("number 3"));
+    assert_eq!((err.line, err.col), (3, 57));
+    assert_eq!(err.source_line.as_deref(), Some("\t\tworkspaces: {\"makepad\": (name: \"makepad\", paths: [\"a\", 3])},"));
+    let shown = format!("{}", err);
+    assert!(shown.starts_with("Ron Deserialize error: Expected string found number 3, at builders.main.workspaces.makepad.paths[1], line:4 col:58"), "{}", shown);
+    assert!(shown.ends_with(&format!("\n   | \t\t{}^", " ".repeat(55))), "{}", shown);
+    
+    let err = Settings::deserialize_ron("(builders: {\"main\": (workspaces: {})})").unwrap_err();
+    assert_eq!(err.msg, "Key not found port");
+    assert_eq!(err.path, "builders.main");
+    
+    let json = "{\"builders\": {\"main\": {\n  \"workspaces\": {},\n  \"port\": 100000\n}}}";
+    let err = Settings::deserialize_json(json).unwrap_err();
+    assert_eq!(err.path, "builders.main.port");
+    assert_eq!((err.line, err.col), (2, 10));
+    assert_eq!(err.source_line.as_deref(), Some("  \"port\": 100000"));
+    let err = Settings::deserialize_json("{\"builders\": {\"main\": {\"workspaces\": {\"x\": {\"name\": true}}}}}").unwrap_err();
+    assert_eq!(err.path, "builders.main.workspaces.x.name");
+    assert_eq!(err.found.as_deref(), Some("true"));
+    
+    let workspaces = vec![Workspace {name: "a".to_string(), paths: vec!["b".to_string(), "c".to_string()]}];
+    let bin = workspaces.serialize_bin();
+    let err = <Vec<Workspace>>::deserialize_bin(&bin[0..bin.len() - 1]).unwrap_err();
+    assert_eq!(err.path, "[0].paths[1]");
+}
@@ -290,14 +290,11 @@ impl<'a> ShaderAnalyser<'a> {
     
     fn analyse_texture_decl(&mut self, decl: &TextureDecl) -> Result<(), Error> {
         let ty = self.ty_checker().ty_check_ty_expr(&decl.ty_expr) ?;
-        match ty {
-            Ty::Texture2D => {}
-            _ => {
-                return Err(Error {
-                    span: decl.span,
-                    message: String::from("texture must be a texture2D"),
-                })
-            }
+        if !ty.is_texture() {
+            return Err(Error {
+                span: decl.span,
+                message: String::from("texture must be a texture2D, texture2DArray, textureCube or texture3D"),
+            })
         }
         self.env.insert_sym(
             decl.span,
@@ -1,6 +1,7 @@
 use crate::env::VarKind;
 use crate::ident::Ident;
 use crate::lit::{Lit, TyLit};
+use crate::pipeline::PipelineDecl;
 use crate::span::Span;
 use crate::ty::Ty;
 use crate::val::Val;
@@ -11,6 +12,7 @@ use std::fmt;
 #[derive(Clone, Debug)]
 pub struct ShaderAst {
     pub debug: bool,
+    pub pipeline: PipelineDecl,
     pub const_table: RefCell<Option<Vec<f32>>>,
     pub const_table_spans: RefCell<Option<Vec<(usize,Span)>>>,
     
@@ -21,6 +23,7 @@ impl ShaderAst {
     pub fn new() -> ShaderAst {
         ShaderAst {
             debug: false,
+            pipeline: PipelineDecl::default(),
             const_table: RefCell::new(None),
             const_table_spans: RefCell::new(None),
             decls: Vec::new(),
@@ -13,22 +13,38 @@ use {
     std::cell::Cell,
 };
 
-pub fn generate_vertex_shader(shader: &ShaderAst, use_const_table: bool) -> String {
+// desktop GL runs GLSL ES 3.00 with real array and 3d samplers. GLSL ES 1.0 (WebGL 1) has neither,
+// there those textures are stacked into a 2d texture and the sample functions get the layer count and height
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlslVersion {
+    Es100,
+    Es300,
+}
+
+impl GlslVersion {
+    fn stacks_layers(self) -> bool {
+        self == GlslVersion::Es100
+    }
+}
+
+pub fn generate_vertex_shader(shader: &ShaderAst, use_const_table: bool, version: GlslVersion) -> String {
     let mut string = String::new();
     ShaderGenerator {
         shader,
         use_const_table,
+        version,
         string: &mut string,
     }
     .generate_vertex_shader();
     string
 }
 
-pub fn generate_fragment_shader(shader: &ShaderAst, use_const_table: bool) -> String {
+pub fn generate_fragment_shader(shader: &ShaderAst, use_const_table: bool, version: GlslVersion) -> String {
     let mut string = String::new();
     ShaderGenerator {
         shader,
         use_const_table,
+        version,
         string: &mut string,
     }
     .generate_fragment_shader();
@@ -38,6 +54,7 @@ pub fn generate_fragment_shader(shader: &ShaderAst, use_const_table: bool) -> St
 struct ShaderGenerator<'a> {
     shader: &'a ShaderAst,
     use_const_table: bool,
+    version: GlslVersion,
     string: &'a mut String,
 }
 
@@ -223,6 +240,9 @@ impl<'a> ShaderGenerator<'a> {
             self.generate_cons_fn(ty_lit, param_tys);
         }
         self.generate_fn_decl(pixel_decl);
+        if self.version == GlslVersion::Es300 {
+            writeln!(self.string, "out vec4 mpsc_frag_color;").unwrap();
+        }
         writeln!(self.string, "void main() {{").unwrap();
         let mut varying_unpacker = VarUnpacker::new(
             "mpsc_packed_varying",
@@ -246,7 +266,10 @@ impl<'a> ShaderGenerator<'a> {
                 _ => {}
             }
         }
-        writeln!(self.string, "    gl_FragColor = pixel();").unwrap();
+        match self.version {
+            GlslVersion::Es100 => writeln!(self.string, "    gl_FragColor = pixel();").unwrap(),
+            GlslVersion::Es300 => writeln!(self.string, "    mpsc_frag_color = pixel();").unwrap(),
+        }
         writeln!(self.string, "}}").unwrap();
     }
 
@@ -292,9 +315,13 @@ impl<'a> ShaderGenerator<'a> {
             }
         }
 
+        let (attribute, varying) = match self.version {
+            GlslVersion::Es100 => ("attribute", "varying"),
+            GlslVersion::Es300 => ("in", if packed_attributes_size.is_some() {"out"} else {"in"}),
+        };
         if let Some(packed_attributes_size) = packed_attributes_size {
             self.generate_packed_var_decls(
-                "attribute",
+                attribute,
                 "mpsc_packed_geometry",
                 packed_attributes_size,
             );
@@ -302,13 +329,13 @@ impl<'a> ShaderGenerator<'a> {
 
         if let Some(packed_instances_size) = packed_instances_size {
             self.generate_packed_var_decls(
-                "attribute",
+                attribute,
                 "mpsc_packed_instance",
                 packed_instances_size,
             );
         }
 
-        self.generate_packed_var_decls("varying", "mpsc_packed_varying", packed_varyings_size);
+        self.generate_packed_var_decls(varying, "mpsc_packed_varying", packed_varyings_size);
     }
 
     fn generate_struct_decl(&mut self, decl: &StructDecl) {
@@ -358,6 +385,10 @@ impl<'a> ShaderGenerator<'a> {
             decl.ty_expr.ty.borrow().as_ref().unwrap(),
         );
         writeln!(self.string, ";").unwrap();
+        // the layer count and layer height of texture arrays and 3d textures stacked into a 2d texture
+        if self.version.stacks_layers() && decl.ty_expr.ty.borrow().as_ref().unwrap().is_layered_texture() {
+            writeln!(self.string, "uniform vec2 mpsc_{}_depth;", decl.ident).unwrap();
+        }
     }
 
     fn compute_packed_geometries_size(&self) -> usize {
@@ -435,7 +466,7 @@ impl<'a> ShaderGenerator<'a> {
         for param_ty in param_tys {
             write!(cons_name, "_{}", param_ty).unwrap();
         }
-        if !GlslBackendWriter.use_cons_fn(&cons_name){
+        if !(GlslBackendWriter {version: self.version}).use_cons_fn(&cons_name){
             return
         }
         
@@ -524,6 +555,7 @@ impl<'a> ShaderGenerator<'a> {
             shader: self.shader,
             decl,
             use_const_table: self.use_const_table,
+            version: self.version,
             visited: &mut HashSet::new(),
             string: self.string,
         }
@@ -534,7 +566,7 @@ impl<'a> ShaderGenerator<'a> {
         ExprGenerator {
             shader: self.shader,
             decl: None,
-            backend_writer: &GlslBackendWriter,
+            backend_writer: &GlslBackendWriter {version: self.version},
             use_const_table: self.use_const_table,
             //use_generated_cons_fns: false,
             string: self.string,
@@ -543,16 +575,16 @@ impl<'a> ShaderGenerator<'a> {
     }
 
     fn write_var_decl(&mut self, is_inout: bool, ident: Ident, ty: &Ty) {
-        GlslBackendWriter.write_var_decl(&mut self.string, is_inout, false, ident, ty);
+        GlslBackendWriter {version: self.version}.write_var_decl(&mut self.string, is_inout, false, ident, ty);
     }
     
     
     fn write_ident(&mut self, ident: Ident) {
-        GlslBackendWriter.write_ident(&mut self.string, ident);
+        GlslBackendWriter {version: self.version}.write_ident(&mut self.string, ident);
     }
     
     fn write_ty_lit(&mut self, ty_lit: TyLit) {
-        GlslBackendWriter.write_ty_lit(&mut self.string, ty_lit);
+        GlslBackendWriter {version: self.version}.write_ty_lit(&mut self.string, ty_lit);
     }
     
 }
@@ -561,6 +593,7 @@ struct FnDeclGenerator<'a> {
     shader: &'a ShaderAst,
     decl: &'a FnDecl,
     use_const_table: bool,
+    version: GlslVersion,
     visited: &'a mut HashSet<Ident>,
     string: &'a mut String,
 }
@@ -575,6 +608,7 @@ impl<'a> FnDeclGenerator<'a> {
                 shader: self.shader,
                 decl: self.shader.find_fn_decl(callee).unwrap(),
                 use_const_table: self.use_const_table,
+                version: self.version,
                 visited: self.visited,
                 string: self.string,
             }
@@ -606,7 +640,7 @@ impl<'a> FnDeclGenerator<'a> {
         BlockGenerator {
             shader: self.shader,
             decl: self.decl,
-            backend_writer: &GlslBackendWriter,
+            backend_writer: &GlslBackendWriter {version: self.version},
             use_const_table: self.use_const_table,
             //use_generated_cons_fns: false,
             indent_level: 0,
@@ -616,7 +650,7 @@ impl<'a> FnDeclGenerator<'a> {
     }
 
     fn write_var_decl(&mut self, is_inout: bool, ident: Ident, ty: &Ty) {
-        GlslBackendWriter.write_var_decl(&mut self.string, is_inout, false, ident, ty);
+        GlslBackendWriter {version: self.version}.write_var_decl(&mut self.string, is_inout, false, ident, ty);
     }
 }
 
@@ -798,15 +832,23 @@ impl<'a> VarUnpacker<'a> {
     }
 }
 
-struct GlslBackendWriter;
+struct GlslBackendWriter {
+    version: GlslVersion,
+}
 
 impl BackendWriter for GlslBackendWriter {
     fn write_call_expr_hidden_args(&self, _string: &mut String, _use_const_table: bool, _ident:Ident, _shader:&ShaderAst, _sep:&str){
         
     }
     
-    fn generate_var_expr(&self, string: &mut String, ident: Ident, _kind: &Cell<Option<VarKind>>, _shader: &ShaderAst, _decl: &FnDecl, _ty:&Option<Ty>){
-        write!(string, "{}", ident).unwrap()
+    fn generate_var_expr(&self, string: &mut String, ident: Ident, _kind: &Cell<Option<VarKind>>, _shader: &ShaderAst, _decl: &FnDecl, ty:&Option<Ty>){
+        write!(string, "{}", ident).unwrap();
+        // stacked layered textures only end up in the sample builtins, which take their depth as the next argument
+        if let Some(ty) = ty {
+            if self.version.stacks_layers() && ty.is_layered_texture() {
+                write!(string, ", mpsc_{}_depth", ident).unwrap();
+            }
+        }
     }
 
     fn needs_mul_fn_for_matrix_multiplication(&self)->bool{
@@ -922,6 +964,21 @@ impl BackendWriter for GlslBackendWriter {
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
+            Ty::Texture2DArray => {
+                self.write_ty_lit(string, TyLit::Texture2DArray);
+                write!(string, " ").unwrap();
+                self.write_ident(string, ident);
+            }
+            Ty::Texture3D => {
+                self.write_ty_lit(string, TyLit::Texture3D);
+                write!(string, " ").unwrap();
+                self.write_ident(string, ident);
+            }
+            Ty::TextureCube => {
+                self.write_ty_lit(string, TyLit::TextureCube);
+                write!(string, " ").unwrap();
+                self.write_ident(string, ident);
+            }
             Ty::Array { ref elem_ty, len } => {
                 self.write_var_decl(string, is_inout, is_packed, ident, elem_ty);
                 write!(string, "[{}]", len).unwrap();
@@ -956,6 +1013,10 @@ impl BackendWriter for GlslBackendWriter {
                 TyLit::Mat3 => "mat3",
                 TyLit::Mat4 => "mat4",
                 TyLit::Texture2D => "sampler2D",
+                TyLit::Texture2DArray | TyLit::Texture3D if self.version.stacks_layers() => "sampler2D",
+                TyLit::Texture2DArray => "sampler2DArray",
+                TyLit::Texture3D => "sampler3D",
+                TyLit::TextureCube => "samplerCube",
             }
         )
         .unwrap();
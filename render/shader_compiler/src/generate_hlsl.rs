@@ -37,8 +37,10 @@ struct ShaderGenerator<'a> {
 
 impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
-        writeln!(self.string, "SamplerState mpsc_default_texture_sampler{{Filter=MIN_MAX_MIP_LINEAR;AddressU = Wrap;AddressV=Wrap;}};").unwrap();
-        writeln!(self.string, "float4 sample2d(Texture2D tex, float2 pos){{return tex.Sample(mpsc_default_texture_sampler,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample2d(Texture2D tex, SamplerState samp, float2 pos){{return tex.Sample(samp,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample2d_array(Texture2DArray tex, SamplerState samp, float3 pos){{return tex.Sample(samp,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample3d(Texture3D tex, SamplerState samp, float3 pos){{return tex.Sample(samp,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample_cube(TextureCube tex, SamplerState samp, float3 dir){{return tex.Sample(samp,dir);}}").unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_defs();
@@ -157,10 +159,24 @@ impl<'a> ShaderGenerator<'a> {
         for decl in &self.shader.decls {
             match decl {
                 Decl::Texture(decl) => {
-                    assert_eq!(*decl.ty_expr.ty.borrow().as_ref().unwrap(), Ty::Texture2D);
+                    let ty_lit = match decl.ty_expr.ty.borrow().as_ref().unwrap() {
+                        Ty::Texture2D => TyLit::Texture2D,
+                        Ty::Texture2DArray => TyLit::Texture2DArray,
+                        Ty::TextureCube => TyLit::TextureCube,
+                        Ty::Texture3D => TyLit::Texture3D,
+                        _ => panic!()
+                    };
+                    self.write_ty_lit(ty_lit);
+                    writeln!(
+                        self.string,
+                        " {}: register(t{});",
+                        decl.ident,
+                        index
+                    )
+                        .unwrap();
                     writeln!(
                         self.string,
-                        "Texture2D {}: register(t{});",
+                        "SamplerState mpsc_sampler_{}: register(s{});",
                         decl.ident,
                         index
                     )
@@ -759,6 +775,10 @@ impl BackendWriter for HlslBackendWriter {
             }
         }
         write!(string, "{}", ident).unwrap();
+        // textures only end up in the sample builtins, which take their sampler as the next argument
+        if let Some(VarKind::Texture) = kind.get() {
+            write!(string, ", mpsc_sampler_{}", ident).unwrap();
+        }
     }
     
     fn needs_mul_fn_for_matrix_multiplication(&self) -> bool {
@@ -874,7 +894,7 @@ impl BackendWriter for HlslBackendWriter {
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
-            Ty::Texture2D => panic!(), // TODO
+            Ty::Texture2D | Ty::Texture2DArray | Ty::TextureCube | Ty::Texture3D => panic!(), // TODO
             Ty::Array {ref elem_ty, len} => {
                 self.write_var_decl(string, is_inout, is_packed, ident, elem_ty);
                 write!(string, " ").unwrap();
@@ -910,7 +930,10 @@ impl BackendWriter for HlslBackendWriter {
                 TyLit::Mat2 => "float2x2",
                 TyLit::Mat3 => "float3x3",
                 TyLit::Mat4 => "float4x4",
-                TyLit::Texture2D => panic!(), // TODO
+                TyLit::Texture2D => "Texture2D",
+                TyLit::Texture2DArray => "Texture2DArray",
+                TyLit::TextureCube => "TextureCube",
+                TyLit::Texture3D => "Texture3D",
             }
         )
             .unwrap();
@@ -35,7 +35,10 @@ impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
         writeln!(self.string, "#include <metal_stdlib>").unwrap();
         writeln!(self.string, "using namespace metal;").unwrap();
-        writeln!(self.string, "float4 sample2d(texture2d<float> tex, float2 pos){{return tex.sample(sampler(mag_filter::linear,min_filter::linear),pos);}}").unwrap();
+        writeln!(self.string, "float4 sample2d(texture2d<float> tex, sampler samp, float2 pos){{return tex.sample(samp,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample2d_array(texture2d_array<float> tex, sampler samp, float3 pos){{return tex.sample(samp,pos.xy,uint(round(pos.z)));}}").unwrap();
+        writeln!(self.string, "float4 sample3d(texture3d<float> tex, sampler samp, float3 pos){{return tex.sample(samp,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample_cube(texturecube<float> tex, sampler samp, float3 dir){{return tex.sample(samp,dir);}}").unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_struct();
@@ -128,10 +131,25 @@ impl<'a> ShaderGenerator<'a> {
         for decl in &self.shader.decls {
             match decl {
                 Decl::Texture(decl) => {
-                    assert_eq!(*decl.ty_expr.ty.borrow().as_ref().unwrap(), Ty::Texture2D);
+                    let ty_lit = match decl.ty_expr.ty.borrow().as_ref().unwrap() {
+                        Ty::Texture2D => TyLit::Texture2D,
+                        Ty::Texture2DArray => TyLit::Texture2DArray,
+                        Ty::TextureCube => TyLit::TextureCube,
+                        Ty::Texture3D => TyLit::Texture3D,
+                        _ => panic!()
+                    };
+                    write!(self.string, "    ").unwrap();
+                    self.write_ty_lit(ty_lit);
                     writeln!(
                         self.string,
-                        "    texture2d<float> {} [[texture({})]];",
+                        " {} [[texture({})]];",
+                        decl.ident,
+                        index
+                    )
+                        .unwrap();
+                    writeln!(
+                        self.string,
+                        "    sampler mpsc_sampler_{} [[sampler({})]];",
                         decl.ident,
                         index
                     )
@@ -750,7 +768,11 @@ impl BackendWriter for MetalBackendWriter {
                 _ => {}
             }
         }
-        write!(string, "{}", ident).unwrap()
+        write!(string, "{}", ident).unwrap();
+        // textures only end up in the sample builtins, which take their sampler as the next argument
+        if let Some(VarKind::Texture) = kind.get() {
+            write!(string, ", mpsc_textures.mpsc_sampler_{}", ident).unwrap();
+        }
     }
 
     fn needs_mul_fn_for_matrix_multiplication(&self)->bool{
@@ -880,7 +902,7 @@ impl BackendWriter for MetalBackendWriter {
                 write!(string, " {}", ref_prefix).unwrap();
                 self.write_ident(string, ident);
             }
-            Ty::Texture2D => panic!(), // TODO
+            Ty::Texture2D | Ty::Texture2DArray | Ty::TextureCube | Ty::Texture3D => panic!(), // TODO
             Ty::Array {ref elem_ty, len} => {
                 self.write_var_decl(string, is_inout, is_packed, ident, elem_ty);
                 write!(string, "[{}]", len).unwrap();
@@ -914,7 +936,10 @@ impl BackendWriter for MetalBackendWriter {
                 TyLit::Mat2 => "float2x2",
                 TyLit::Mat3 => "float3x3",
                 TyLit::Mat4 => "float4x4",
-                TyLit::Texture2D => panic!(), // TODO
+                TyLit::Texture2D => "texture2d<float>",
+                TyLit::Texture2DArray => "texture2d_array<float>",
+                TyLit::TextureCube => "texturecube<float>",
+                TyLit::Texture3D => "texture3d<float>",
             }
         )
             .unwrap();
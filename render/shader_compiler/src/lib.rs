@@ -28,4 +28,5 @@ pub mod val;
 #[macro_use]
 pub mod shadergen;
 pub mod colors;
-pub mod geometry;
\ No newline at end of file
+pub mod geometry;
+pub mod pipeline;
\ No newline at end of file
@@ -68,6 +68,9 @@ pub enum TyLit {
     Mat3,
     Mat4,
     Texture2D,
+    Texture2DArray,
+    TextureCube,
+    Texture3D,
 }
 
 impl TyLit {
@@ -89,6 +92,9 @@ impl TyLit {
             TyLit::Mat3 => Ty::Mat3,
             TyLit::Mat4 => Ty::Mat4,
             TyLit::Texture2D => Ty::Texture2D,
+            TyLit::Texture2DArray => Ty::Texture2DArray,
+            TyLit::TextureCube => Ty::TextureCube,
+            TyLit::Texture3D => Ty::Texture3D,
         }
     }
 }
@@ -115,6 +121,9 @@ impl fmt::Display for TyLit {
                 TyLit::Mat3 => "mat3",
                 TyLit::Mat4 => "mat4",
                 TyLit::Texture2D => "texture2D",
+                TyLit::Texture2DArray => "texture2DArray",
+                TyLit::TextureCube => "textureCube",
+                TyLit::Texture3D => "texture3D",
             }
         )
     }
@@ -2,6 +2,7 @@ use crate::ast::*;
 use crate::error::Error;
 use crate::ident::Ident;
 use crate::lit::Lit;
+use crate::pipeline::PipelineDecl;
 use crate::span::Span;
 use crate::token::{Token, TokenWithSpan};
 use std::cell::{Cell, RefCell};
@@ -78,6 +79,15 @@ impl<'a> Parser<'a> {
 					self.skip_token();
                     self.shader.debug = true;
                 }
+                Token::Ident(ident) if ident.with(PipelineDecl::is_state) => {
+                    self.skip_token();
+                    let name = self.parse_ident()?;
+                    self.expect_token(Token::Semi)?;
+                    let state = ident.to_string();
+                    if let Err(message) = name.with(|name| self.shader.pipeline.declare(&state, name)) {
+                        return Err(span.error(self, message))
+                    }
+                }
                 token => {
                     return Err(span.error(self, format!("unexpected token `{}`", token).into()))
                 }
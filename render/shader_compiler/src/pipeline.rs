@@ -0,0 +1,267 @@
+// Fixed function pipeline state a shader declares through its ShaderGen.
+// The defaults match what every backend used to hardcode:
+// premultiplied alpha blending, LEQUAL depth test with depth writes, no culling.
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstColor,
+    OneMinusDstColor,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub alpha_op: BlendOp,
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self::premultiplied()
+    }
+}
+
+impl BlendState {
+    // the blendmode all our pixelshaders assume, they output premultiplied colors
+    pub fn premultiplied() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::OneMinusSrcAlpha,
+            color_op: BlendOp::Add,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::OneMinusSrcAlpha,
+            alpha_op: BlendOp::Add,
+        }
+    }
+
+    // glows and light accumulation, destination alpha is left alone
+    pub fn additive() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::One,
+            color_op: BlendOp::Add,
+            src_alpha: BlendFactor::Zero,
+            dst_alpha: BlendFactor::One,
+            alpha_op: BlendOp::Add,
+        }
+    }
+
+    // darkens the destination by the source color
+    pub fn multiply() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::DstColor,
+            dst_color: BlendFactor::OneMinusSrcAlpha,
+            color_op: BlendOp::Add,
+            src_alpha: BlendFactor::DstAlpha,
+            dst_alpha: BlendFactor::OneMinusSrcAlpha,
+            alpha_op: BlendOp::Add,
+        }
+    }
+
+    pub fn opaque() -> Self {
+        Self {
+            enabled: false,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::Zero,
+            color_op: BlendOp::Add,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::Zero,
+            alpha_op: BlendOp::Add,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum DepthFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub func: DepthFunc,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            test: true,
+            write: true,
+            func: DepthFunc::LessEqual
+        }
+    }
+}
+
+impl DepthState {
+    // tests against the depthbuffer but leaves it alone, for overlays
+    pub fn read_only() -> Self {
+        Self {
+            write: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            test: false,
+            write: false,
+            func: DepthFunc::Always
+        }
+    }
+}
+
+// front faces are counter clockwise on all platforms
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+impl Default for CullMode {
+    fn default() -> Self {
+        CullMode::None
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ColorMask {
+    pub r: bool,
+    pub g: bool,
+    pub b: bool,
+    pub a: bool,
+}
+
+impl Default for ColorMask {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl ColorMask {
+    pub fn all() -> Self {
+        Self {r: true, g: true, b: true, a: true}
+    }
+
+    pub fn none() -> Self {
+        Self {r: false, g: false, b: false, a: false}
+    }
+
+    pub fn rgb() -> Self {
+        Self {r: true, g: true, b: true, a: false}
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct PipelineState {
+    pub blend: BlendState,
+    pub depth: DepthState,
+    pub cull: CullMode,
+    pub color_mask: ColorMask,
+}
+
+impl PipelineState {
+    pub fn additive() -> Self {
+        Self {
+            blend: BlendState::additive(),
+            depth: DepthState::read_only(),
+            ..Self::default()
+        }
+    }
+
+    pub fn overlay() -> Self {
+        Self {
+            depth: DepthState::read_only(),
+            ..Self::default()
+        }
+    }
+}
+
+// what a shader declares with `blend additive;`, `depth read_only;`, `cull back;` and
+// `color_mask rgb;` in its source. Later subs override earlier ones, and what
+// isn't declared comes from the PipelineState of the ShaderGen
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipelineDecl {
+    pub blend: Option<BlendState>,
+    pub depth: Option<DepthState>,
+    pub cull: Option<CullMode>,
+    pub color_mask: Option<ColorMask>,
+}
+
+impl PipelineDecl {
+    // sets the state named by a declaration, the error lists the names it knows
+    pub fn declare(&mut self, state: &str, name: &str) -> Result<(), String> {
+        match (state, name) {
+            ("blend", "premultiplied") => self.blend = Some(BlendState::premultiplied()),
+            ("blend", "additive") => self.blend = Some(BlendState::additive()),
+            ("blend", "multiply") => self.blend = Some(BlendState::multiply()),
+            ("blend", "opaque") => self.blend = Some(BlendState::opaque()),
+            ("depth", "default") => self.depth = Some(DepthState::default()),
+            ("depth", "read_only") => self.depth = Some(DepthState::read_only()),
+            ("depth", "disabled") => self.depth = Some(DepthState::disabled()),
+            ("cull", "none") => self.cull = Some(CullMode::None),
+            ("cull", "front") => self.cull = Some(CullMode::Front),
+            ("cull", "back") => self.cull = Some(CullMode::Back),
+            ("color_mask", "all") => self.color_mask = Some(ColorMask::all()),
+            ("color_mask", "none") => self.color_mask = Some(ColorMask::none()),
+            ("color_mask", "rgb") => self.color_mask = Some(ColorMask::rgb()),
+            _ => return Err(format!("unknown {} `{}`, expected one of {}", state, name, Self::names(state).join(", ")))
+        }
+        Ok(())
+    }
+
+    pub fn is_state(ident: &str) -> bool {
+        Self::names(ident).len() > 0
+    }
+
+    pub fn names(state: &str) -> &'static [&'static str] {
+        match state {
+            "blend" => &["premultiplied", "additive", "multiply", "opaque"],
+            "depth" => &["default", "read_only", "disabled"],
+            "cull" => &["none", "front", "back"],
+            "color_mask" => &["all", "none", "rgb"],
+            _ => &[]
+        }
+    }
+
+    pub fn apply(&self, pipeline: PipelineState) -> PipelineState {
+        PipelineState {
+            blend: self.blend.unwrap_or(pipeline.blend),
+            depth: self.depth.unwrap_or(pipeline.depth),
+            cull: self.cull.unwrap_or(pipeline.cull),
+            color_mask: self.color_mask.unwrap_or(pipeline.color_mask),
+        }
+    }
+}
@@ -5,6 +5,7 @@ use crate::token::TokenWithSpan;
 use crate::parse;
 use crate::ty::*;
 use crate::geometry::*;
+use crate::pipeline::PipelineState;
 use crate::token::Token;
 use crate::analyse::ShaderAnalyser;
 use crate::span::Span;
@@ -47,6 +48,7 @@ pub struct ShaderSub {
 #[derive(Default, Clone, PartialEq)]
 pub struct ShaderGen {
     pub geometry: Geometry,
+    pub pipeline: PipelineState,
     pub subs: Vec<ShaderSub>,
 }
 
@@ -365,6 +367,7 @@ impl Hash for ShaderGen {
         for vertex in &self.geometry.vertices {
             vertex.to_bits().hash(state);
         }
+        self.pipeline.hash(state);
         self.subs.hash(state);
     }
 }
@@ -372,6 +375,9 @@ impl Hash for ShaderGen {
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub enum PropId {
     Texture2d(Texture2dId),
+    Texture2dArray(Texture2dArrayId),
+    TextureCube(TextureCubeId),
+    Texture3d(Texture3dId),
     Color(ColorId),
     Vec4(Vec4Id),
     Vec3(Vec3Id),
@@ -384,6 +390,9 @@ impl PropId {
     pub fn shader_ty(&self) -> Ty {
         match self.clone() {
             PropId::Texture2d(t) => t.into(),
+            PropId::Texture2dArray(t) => t.into(),
+            PropId::TextureCube(t) => t.into(),
+            PropId::Texture3d(t) => t.into(),
             PropId::Color(t) => t.into(),
             PropId::Vec4(t) => t.into(),
             PropId::Vec3(t) => t.into(),
@@ -415,6 +424,69 @@ impl Into<Texture2dId> for TypeId {
     }
 }
 
+#[derive(Debug, Hash, PartialEq, Copy, Clone, Eq)]
+pub struct Texture2dArrayId(pub TypeId);
+
+impl Into<PropId> for Texture2dArrayId {
+    fn into(self) -> PropId {
+        PropId::Texture2dArray(self)
+    }
+}
+
+impl Into<Ty> for Texture2dArrayId {
+    fn into(self) -> Ty {
+        Ty::Texture2DArray
+    }
+}
+
+impl Into<Texture2dArrayId> for TypeId {
+    fn into(self) -> Texture2dArrayId {
+        Texture2dArrayId(self)
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Copy, Clone, Eq)]
+pub struct TextureCubeId(pub TypeId);
+
+impl Into<PropId> for TextureCubeId {
+    fn into(self) -> PropId {
+        PropId::TextureCube(self)
+    }
+}
+
+impl Into<Ty> for TextureCubeId {
+    fn into(self) -> Ty {
+        Ty::TextureCube
+    }
+}
+
+impl Into<TextureCubeId> for TypeId {
+    fn into(self) -> TextureCubeId {
+        TextureCubeId(self)
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Copy, Clone, Eq)]
+pub struct Texture3dId(pub TypeId);
+
+impl Into<PropId> for Texture3dId {
+    fn into(self) -> PropId {
+        PropId::Texture3d(self)
+    }
+}
+
+impl Into<Ty> for Texture3dId {
+    fn into(self) -> Ty {
+        Ty::Texture3D
+    }
+}
+
+impl Into<Texture3dId> for TypeId {
+    fn into(self) -> Texture3dId {
+        Texture3dId(self)
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Copy, Clone, Eq)]
 pub struct ColorId(pub TypeId);
 
@@ -21,6 +21,9 @@ pub enum Ty {
     Mat3,
     Mat4,
     Texture2D,
+    Texture2DArray,
+    TextureCube,
+    Texture3D,
     Array { elem_ty: Rc<Ty>, len: usize },
     Struct { ident: Ident },
 }
@@ -55,6 +58,21 @@ impl Ty {
         }
     }
 
+    pub fn is_texture(&self) -> bool {
+        match self {
+            Ty::Texture2D | Ty::Texture2DArray | Ty::TextureCube | Ty::Texture3D => true,
+            _ => false,
+        }
+    }
+
+    // texture arrays and 3d textures are stored as a stack of 2d layers on platforms without native support
+    pub fn is_layered_texture(&self) -> bool {
+        match self {
+            Ty::Texture2DArray | Ty::Texture3D => true,
+            _ => false,
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self {
             Ty::Void => 0,
@@ -64,7 +82,7 @@ impl Ty {
             Ty::Bvec4 | Ty::Ivec4 | Ty::Vec4 | Ty::Mat2 => 4,
             Ty::Mat3 => 9,
             Ty::Mat4 => 16,
-            Ty::Texture2D { .. } => panic!(),
+            Ty::Texture2D | Ty::Texture2DArray | Ty::TextureCube | Ty::Texture3D => panic!(),
             Ty::Array { elem_ty, len } => elem_ty.size() * len,
             Ty::Struct { .. } => panic!(),
         }
@@ -91,6 +109,9 @@ impl fmt::Display for Ty {
             Ty::Mat3 => write!(f, "mat3"),
             Ty::Mat4 => write!(f, "mat4"),
             Ty::Texture2D => write!(f, "texture2D"),
+            Ty::Texture2DArray => write!(f, "texture2DArray"),
+            Ty::TextureCube => write!(f, "textureCube"),
+            Ty::Texture3D => write!(f, "texture3D"),
             Ty::Array { elem_ty, len } => write!(f, "{}[{}]", elem_ty, len),
             Ty::Struct { ident, .. } => write!(f, "{}", ident),
         }
@@ -0,0 +1,49 @@
+use makepad_shader_compiler::ast::ShaderAst;
+use makepad_shader_compiler::lex;
+use makepad_shader_compiler::parse;
+use makepad_shader_compiler::pipeline::*;
+
+fn parse_subs(subs: &[&str]) -> Result<ShaderAst, String> {
+    let mut shader_ast = ShaderAst::new();
+    for (index, sub) in subs.iter().enumerate() {
+        let tokens = lex::lex(sub.chars(), index).collect::<Result<Vec<_>, _>>().map_err( | e | e.to_string()) ?;
+        parse::parse(&tokens, &mut shader_ast).map_err( | e | e.to_string()) ?;
+    }
+    Ok(shader_ast)
+}
+
+#[test]
+fn declared_pipeline_state() {
+    let shader_ast = parse_subs(&["
+        blend additive;
+        depth read_only;
+        cull back;
+        fn pixel() -> vec4 {
+            return vec4(1.0);
+        }
+    "]).unwrap();
+    let pipeline = shader_ast.pipeline.apply(PipelineState::default());
+    assert_eq!(pipeline, PipelineState {
+        blend: BlendState::additive(),
+        depth: DepthState {test: true, write: false, func: DepthFunc::LessEqual},
+        cull: CullMode::Back,
+        color_mask: ColorMask::all(),
+    });
+
+    // a later sub overrides what it declares, the rest comes from the ShaderGen
+    let shader_ast = parse_subs(&["blend additive; cull back;", "blend opaque; color_mask rgb;"]).unwrap();
+    let pipeline = shader_ast.pipeline.apply(PipelineState {depth: DepthState::disabled(), ..PipelineState::default()});
+    assert_eq!(pipeline.blend, BlendState::opaque());
+    assert!(!pipeline.blend.enabled);
+    assert_eq!(pipeline.cull, CullMode::Back);
+    assert_eq!(pipeline.color_mask, ColorMask {r: true, g: true, b: true, a: false});
+    assert_eq!(pipeline.depth, DepthState::disabled());
+
+    // nothing declared leaves the ShaderGen alone
+    let shader_ast = parse_subs(&["fn pixel() -> vec4 {return vec4(1.0);}"]).unwrap();
+    assert_eq!(shader_ast.pipeline.apply(PipelineState::additive()), PipelineState::additive());
+
+    let err = parse_subs(&["blend screen;"]).unwrap_err();
+    assert!(err.contains("unknown blend `screen`") && err.contains("additive"), "{}", err);
+    assert!(parse_subs(&["depth read_only"]).is_err());
+}
@@ -0,0 +1,93 @@
+use crate::cx::*;
+use std::ops::Range;
+
+// a draw call in the order a pass issues it, after its draw uniforms have been computed
+#[derive(Clone, Copy, Debug)]
+pub struct DrawItem {
+    pub view_id: usize,
+    pub draw_call_id: usize,
+    pub instance_dirty: bool,
+}
+
+// Batching merges consecutive compatible draw calls of a pass into one instanced draw.
+// The first draw call of a batch supplies the uniforms, the others only their instances.
+// Only the OpenGL backend batches, on Metal, DX11 and WebGL every draw call is drawn on its own
+pub const DRAW_BATCHING_SUPPORTED: bool = cfg!(all(not(feature = "ipc"), target_os = "linux"));
+
+#[derive(Clone, Default)]
+pub struct CxDrawBatcher {
+    pub enabled: bool,
+    pub items: Vec<DrawItem>,
+    pub batches: Vec<Range<usize>>,
+    pub instances: Vec<f32>,
+}
+
+impl Cx {
+
+    // returns if batching is on, which it never is on a backend that doesn't support it
+    pub fn set_draw_batching(&mut self, enabled: bool) -> bool {
+        let enabled = enabled && DRAW_BATCHING_SUPPORTED;
+        if self.draw_batcher.enabled != enabled {
+            self.draw_batcher.enabled = enabled;
+            self.redraw_child_area(Area::All);
+        }
+        enabled
+    }
+
+    pub fn is_draw_batching(&self) -> bool {
+        self.draw_batcher.enabled
+    }
+
+    // two consecutive draw calls can share a draw if nothing but their instances differ.
+    // with a depth test we rely on LessEqual keeping the painters order within the draw
+    pub fn draw_calls_can_batch(&self, a: &DrawItem, b: &DrawItem) -> bool {
+        let dc_a = &self.views[a.view_id].draw_calls[a.draw_call_id];
+        let dc_b = &self.views[b.view_id].draw_calls[b.draw_call_id];
+        if dc_a.shader_id != dc_b.shader_id || dc_a.shader_instance_id != dc_b.shader_instance_id {
+            return false
+        }
+        let depth = &self.shaders[dc_a.shader_id].pipeline.depth;
+        if depth.test && depth.func != DepthFunc::LessEqual && depth.func != DepthFunc::Always {
+            return false
+        }
+        if dc_a.textures_2d != dc_b.textures_2d || dc_a.uniforms != dc_b.uniforms {
+            return false
+        }
+        // every draw call gets its own zbias, the instances of a batch are all drawn at the zbias of the first
+        if !dc_a.draw_uniforms.same_clip_and_scroll(&dc_b.draw_uniforms) {
+            return false
+        }
+        a.view_id == b.view_id || self.views[a.view_id].view_uniforms == self.views[b.view_id].view_uniforms
+    }
+
+    // groups the collected draw items into batches. With batching off every item is its own batch
+    pub fn compute_draw_batches(&mut self) {
+        let mut batches = std::mem::take(&mut self.draw_batcher.batches);
+        batches.truncate(0);
+        let items = &self.draw_batcher.items;
+        let mut start = 0;
+        for index in 1..=items.len() {
+            if index == items.len()
+                || !self.draw_batcher.enabled
+                || !self.draw_calls_can_batch(&items[index - 1], &items[index]) {
+                batches.push(start..index);
+                start = index;
+            }
+        }
+        if self.profiler.enabled {
+            self.profiler.current.draw_calls_before_batching += items.len();
+            self.profiler.current.draw_calls_after_batching += batches.len();
+        }
+        self.draw_batcher.batches = batches;
+    }
+
+    // concatenates the instances of a batch into draw_batcher.instances
+    pub fn gather_batch_instances(&mut self, batch: Range<usize>) {
+        let mut instances = std::mem::take(&mut self.draw_batcher.instances);
+        instances.truncate(0);
+        for item in &self.draw_batcher.items[batch] {
+            instances.extend_from_slice(&self.views[item.view_id].draw_calls[item.draw_call_id].instance);
+        }
+        self.draw_batcher.instances = instances;
+    }
+}
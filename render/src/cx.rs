@@ -6,6 +6,7 @@ pub use makepad_shader_compiler::shadergen::*;
 pub use makepad_shader_compiler::colors::*;
 pub use makepad_shader_compiler::util::*;
 pub use makepad_shader_compiler::math::*;
+pub use makepad_shader_compiler::pipeline::*;
 pub use makepad_shader_compiler::uid;
 pub use makepad_live_macros::*;
 
@@ -26,6 +27,8 @@ pub use crate::area::*;
 pub use crate::menu::*;
 pub use crate::styling::*;
 pub use crate::shader::*;
+pub use crate::profiler::*;
+pub use crate::batch::*;
 
 #[cfg(all(not(feature = "ipc"), target_os = "linux"))]
 pub use crate::cx_linux::*;
@@ -165,6 +168,9 @@ pub struct Cx {
     pub live_macros: HashMap<CxLiveLoc, CxLiveMacro>,
     //pub live_client: Option<LiveClient>,
     
+    pub profiler: CxProfiler,
+    pub draw_batcher: CxDrawBatcher,
+    
     pub platform: CxPlatform,
 }
 
@@ -233,8 +239,13 @@ impl Default for Cx {
                 format: TextureFormat::ImageBGRA,
                 width: Some(4),
                 height: Some(4),
-                multisample: None
+                multisample: None,
+                kind: TextureKind::Texture2D,
+                depth: None,
+                mipmaps: false,
+                sampler: TextureSampler::default()
             },
+            image_u8: Vec::new(),
             image_u32: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             image_f32: Vec::new(),
             update_image: true,
@@ -324,6 +335,9 @@ impl Default for Cx {
             panic_now: false,
             panic_redraw: false,
             
+            profiler: CxProfiler::default(),
+            draw_batcher: CxDrawBatcher::default(),
+            
             platform: CxPlatform {..Default::default()},
             
             live_macros:HashMap::new(),
@@ -352,6 +366,7 @@ impl Cx {
         self.shader_map.insert(sg.clone(), new_id);
         self.shaders.push(CxShader {
             name: name.to_string(),
+            pipeline: sg.pipeline,
             shader_gen: sg,
             platform: None,
             mapping: CxShaderMapping::default()
@@ -445,6 +460,9 @@ impl Cx {
                 }
             }
         }
+        if passes_todo.len() > 0 {
+            self.profile_count_passes(passes_todo);
+        }
     }
     
     pub fn redraw_pass_of(&mut self, area: Area) {
@@ -729,6 +747,7 @@ impl Cx {
     pub fn call_event_handler<F>(&mut self, mut event_handler: F, event: &mut Event)
     where F: FnMut(&mut Cx, &mut Event)
     {
+        let profile_start = if self.is_in_redraw_cycle {None} else {self.profile_span_begin()};
         self.event_id += 1;
         event_handler(self, event);
         
@@ -740,17 +759,21 @@ impl Cx {
                 focus: self.key_focus
             }))
         }
+        self.profile_span_end(ProfileSpan::Event, profile_start);
     }
     
     pub fn call_draw_event<F>(&mut self, mut event_handler: F)
     where F: FnMut(&mut Cx, &mut Event)
     {
-        // self.profile();
+        let profile_start = self.profile_span_begin();
         self.is_in_redraw_cycle = true;
         self.redraw_id += 1;
         self.counter = 0;
         std::mem::swap(&mut self._redraw_child_areas, &mut self.redraw_child_areas);
         std::mem::swap(&mut self._redraw_parent_areas, &mut self.redraw_parent_areas);
+        if self.profiler.enabled {
+            self.profiler.current.redrawn_areas += self._redraw_child_areas.len() + self._redraw_parent_areas.len();
+        }
         self.align_list.truncate(0);
         self.redraw_child_areas.truncate(0);
         self.redraw_parent_areas.truncate(0);
@@ -771,7 +794,7 @@ impl Cx {
         if self.turtles.len()>0 {
             panic!("Turtle stack disaligned, forgot an end_turtle()");
         }
-        //self.profile();
+        self.profile_span_end(ProfileSpan::Draw, profile_start);
     }
     
     pub fn call_animation_event<F>(&mut self, mut event_handler: F, time: f64)
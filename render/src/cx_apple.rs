@@ -428,7 +428,11 @@ pub struct MTLClearColor {
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum MTLPixelFormat {
+    R8Unorm = 10,
+    RG8Unorm = 30,
+    R32Float = 55,
     BGRA8Unorm = 80,
+    RGBA16Float = 115,
     Depth32Float = 252,
     Stencil8 = 253,
     Depth24Unorm_Stencil8 = 255,
@@ -500,6 +504,51 @@ pub enum MTLCompareFunction {
     Always = 7,
 }
 
+#[repr(u64)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MTLCullMode {
+    None = 0,
+    Front = 1,
+    Back = 2,
+}
+
+#[repr(u64)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MTLWinding {
+    Clockwise = 0,
+    CounterClockwise = 1,
+}
+
+pub const MTLColorWriteMaskNone: u64 = 0;
+pub const MTLColorWriteMaskRed: u64 = 0x1 << 3;
+pub const MTLColorWriteMaskGreen: u64 = 0x1 << 2;
+pub const MTLColorWriteMaskBlue: u64 = 0x1 << 1;
+pub const MTLColorWriteMaskAlpha: u64 = 0x1 << 0;
+
+#[repr(u64)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MTLSamplerMinMagFilter {
+    Nearest = 0,
+    Linear = 1,
+}
+
+#[repr(u64)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MTLSamplerMipFilter {
+    NotMipmapped = 0,
+    Nearest = 1,
+    Linear = 2,
+}
+
+#[repr(u64)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MTLSamplerAddressMode {
+    ClampToEdge = 0,
+    MirrorClampToEdge = 1,
+    Repeat = 2,
+    MirrorRepeat = 3,
+}
+
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -284,16 +284,15 @@ impl Cx {
             })
         };
     }
-    /*
     
     pub fn profile(&mut self) {
         if let Some(start) = self.platform.desktop.profiler_start {
-            let delta = mach_absolute_time() - start;
+            let delta = self.profile_time_ns() - start;
             println!("Profile time:{} usec", delta / 1_000);
             self.platform.desktop.profiler_start = None
         }
         else {
-            self.platform.desktop.profiler_start = Some(precise_time_ns())
+            self.platform.desktop.profiler_start = Some(self.profile_time_ns())
         }
-    }*/
+    }
 }
@@ -93,6 +93,10 @@ impl Cx {
                 
                 d3d11_cx.set_shaders(&shp.vertex_shader, &shp.pixel_shader);
                 
+                d3d11_cx.set_blend_state(&shp.blend_state);
+                d3d11_cx.set_raster_state(&shp.raster_state);
+                d3d11_cx.set_depth_stencil_state(&shp.depth_stencil_state);
+                
                 d3d11_cx.set_primitive_topology();
                 
                 d3d11_cx.set_input_layout(&shp.input_layout);
@@ -112,15 +116,11 @@ impl Cx {
                 for (i, texture_id) in draw_call.textures_2d.iter().enumerate() {
                     let cxtexture = &mut self.textures[*texture_id as usize];
                     match cxtexture.desc.format { // we only allocate Image and Mapped textures.
-                        TextureFormat::Default | TextureFormat::ImageBGRA => {
+                        TextureFormat::Default | TextureFormat::ImageBGRA | TextureFormat::ImageR8 | TextureFormat::ImageRG8
+                            | TextureFormat::ImageRf32 | TextureFormat::ImageRGBAf16 => {
                             if cxtexture.update_image {
                                 cxtexture.update_image = false;
-                                d3d11_cx.update_platform_texture_image_bgra(
-                                    &mut cxtexture.platform,
-                                    cxtexture.desc.width.unwrap(),
-                                    cxtexture.desc.height.unwrap(),
-                                    &cxtexture.image_u32,
-                                );
+                                d3d11_cx.update_platform_texture_image2d(cxtexture);
                             }
                             d3d11_cx.set_shader_resource(i, &cxtexture.platform.shader_resource);
                         },
@@ -130,6 +130,8 @@ impl Cx {
                         },
                         _ => ()
                     }
+                    d3d11_cx.update_platform_texture_sampler(cxtexture);
+                    d3d11_cx.set_sampler_state(i, &cxtexture.platform.sampler_state);
                 }
                 d3d11_cx.draw_indexed_instanced(sh.shader_gen.geometry.indices.len(), instances);
             }
@@ -211,15 +213,16 @@ impl Cx {
         }
         
         // create depth, blend and raster states
+        let default_pipeline = PipelineState::default();
         if self.passes[pass_id].platform.blend_state.is_none() {
             self.passes[pass_id].platform.blend_state = Some(
-                d3d11_cx.create_blend_state().expect("Cannot create blend state")
+                d3d11_cx.create_blend_state(&default_pipeline).expect("Cannot create blend state")
             )
         }
         
         if self.passes[pass_id].platform.raster_state.is_none() {
             self.passes[pass_id].platform.raster_state = Some(
-                d3d11_cx.create_raster_state().expect("Cannot create raster state")
+                d3d11_cx.create_raster_state(&default_pipeline).expect("Cannot create raster state")
             )
         }
         
@@ -236,6 +239,7 @@ impl Cx {
         self.setup_pass_render_targets(pass_id, dpi_factor, d3d11_window.render_target_view.as_ref(), d3d11_cx);
         let mut zbias = 0.0;
         let zbias_step = self.passes[pass_id].zbias_step;
+        self.d3d11_begin_pass_timer(pass_id, d3d11_cx);
         self.render_view(
             pass_id,
             view_id,
@@ -246,6 +250,7 @@ impl Cx {
             &mut zbias,
             zbias_step
         );
+        self.d3d11_end_pass_timer(pass_id, d3d11_cx);
         d3d11_window.present(vsync);
         //println!("{}", (Cx::profile_time_ns() - time1)as f64 / 1000.0);
     }
@@ -256,6 +261,7 @@ impl Cx {
         self.setup_pass_render_targets(pass_id, dpi_factor, None, d3d11_cx);
         let mut zbias = 0.0;
         let zbias_step = self.passes[pass_id].zbias_step;
+        self.d3d11_begin_pass_timer(pass_id, d3d11_cx);
         self.render_view(
             pass_id,
             view_id,
@@ -266,16 +272,75 @@ impl Cx {
             &mut zbias,
             zbias_step
         );
+        self.d3d11_end_pass_timer(pass_id, d3d11_cx);
+    }
+    
+    // GPU time per pass via timestamp queries. Results are read back
+    // a frame or more later with DONOTFLUSH so we never stall on the GPU
+    pub fn d3d11_begin_pass_timer(&mut self, pass_id: usize, d3d11_cx: &D3d11Cx) {
+        if !self.profiler.enabled {
+            return
+        }
+        let platform = &mut self.passes[pass_id].platform;
+        if platform.timer_queries.is_none() {
+            let disjoint = d3d11_cx.create_query(d3d11::D3D11_QUERY_TIMESTAMP_DISJOINT);
+            let begin = d3d11_cx.create_query(d3d11::D3D11_QUERY_TIMESTAMP);
+            let end = d3d11_cx.create_query(d3d11::D3D11_QUERY_TIMESTAMP);
+            if let (Ok(disjoint), Ok(begin), Ok(end)) = (disjoint, begin, end) {
+                platform.timer_queries = Some(D3d11TimerQueries {disjoint, begin, end});
+            }
+            else {
+                return
+            }
+        }
+        let queries = platform.timer_queries.as_ref().unwrap();
+        if platform.timer_pending {
+            let mut disjoint: d3d11::D3D11_QUERY_DATA_TIMESTAMP_DISJOINT = unsafe {mem::zeroed()};
+            let mut begin: u64 = 0;
+            let mut end: u64 = 0;
+            if !d3d11_cx.get_query_data(&queries.disjoint, &mut disjoint)
+                || !d3d11_cx.get_query_data(&queries.begin, &mut begin)
+                || !d3d11_cx.get_query_data(&queries.end, &mut end) {
+                return
+            }
+            platform.timer_pending = false;
+            if disjoint.Disjoint == FALSE && disjoint.Frequency != 0 && end > begin {
+                let gpu_ns = ((end - begin) as f64 * 1_000_000_000.0 / disjoint.Frequency as f64) as u64;
+                self.profile_pass_gpu_time(pass_id, gpu_ns);
+            }
+        }
+        let platform = &mut self.passes[pass_id].platform;
+        let queries = platform.timer_queries.as_ref().unwrap();
+        unsafe {
+            d3d11_cx.context.Begin(queries.disjoint.as_raw() as *mut _);
+            d3d11_cx.context.End(queries.begin.as_raw() as *mut _);
+        }
+        platform.timer_active = true;
+    }
+    
+    pub fn d3d11_end_pass_timer(&mut self, pass_id: usize, d3d11_cx: &D3d11Cx) {
+        let platform = &mut self.passes[pass_id].platform;
+        if platform.timer_active {
+            let queries = platform.timer_queries.as_ref().unwrap();
+            unsafe {
+                d3d11_cx.context.End(queries.end.as_raw() as *mut _);
+                d3d11_cx.context.End(queries.disjoint.as_raw() as *mut _);
+            }
+            platform.timer_active = false;
+            platform.timer_pending = true;
+        }
     }
     
     
     pub fn hlsl_compile_all_shaders(&mut self, d3d11_cx: &D3d11Cx) {
+        let profile_start = self.profile_span_begin();
         for (index, sh) in &mut self.shaders.iter_mut().enumerate() {
             let result = Self::hlsl_compile_shader(index, false, sh, d3d11_cx, &mut self.shader_inherit_cache);
             if let ShaderCompileResult::Fail {err, ..} = result {
                 panic!("{}", err);
             }
         };
+        self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
     }
     
     fn slots_to_dxgi_format(slots: usize) -> u32 {
@@ -311,11 +376,17 @@ impl Cx {
             }
         };
         
+        // the blend, depth, cull and color_mask the shader declares, on top of its ShaderGen.
+        // they are baked into the platform shader, so a change of them compiles it again
+        let pipeline = shader_ast.pipeline.apply(sh.shader_gen.pipeline);
+        let pipeline_changed = sh.pipeline != pipeline;
+        sh.pipeline = pipeline;
+        
         let hlsl = generate_hlsl::generate_shader(&shader_ast, use_const_table);
         let mapping = CxShaderMapping::from_shader_gen(&sh.shader_gen, if use_const_table{shader_ast.const_table.borrow_mut().take()} else {None});
 
         if let Some(sh_platform) = &mut sh.platform{
-            if sh_platform.hlsl_shader == hlsl{
+            if !pipeline_changed && sh_platform.hlsl_shader == hlsl{
                 sh.mapping = mapping;
                 if let Some(const_table) = &sh.mapping.const_table{
                     if const_table.len()>0{
@@ -456,6 +527,9 @@ impl Cx {
             vertex_shader_blob: vs_blob,
             pixel_shader_blob: ps_blob,
             input_layout: input_layout,
+            blend_state: d3d11_cx.create_blend_state(&sh.pipeline).expect("Cannot create blend state"),
+            raster_state: d3d11_cx.create_raster_state(&sh.pipeline).expect("Cannot create raster state"),
+            depth_stencil_state: d3d11_cx.create_depth_stencil_state(&sh.pipeline).expect("Cannot create depth stencil state"),
         });
         
         return ShaderCompileResult::Ok{id:shader_id};
@@ -472,8 +546,8 @@ pub struct D3d11RenderTarget {
 /*
 impl D3d11RenderTarget {
     pub fn new(d3d11_cx: &D3d11Cx) -> D3d11RenderTarget {
-        let raster_state = d3d11_cx.create_raster_state().expect("Cannot create_raster_state");
-        let blend_state = d3d11_cx.create_blend_state().expect("Cannot create_blend_state");
+        let raster_state = d3d11_cx.create_raster_state(&PipelineState::default()).expect("Cannot create_raster_state");
+        let blend_state = d3d11_cx.create_blend_state(&PipelineState::default()).expect("Cannot create_blend_state");
         return D3d11RenderTarget {
             raster_state: raster_state,
             blend_state: blend_state,
@@ -654,6 +728,32 @@ impl D3d11Cx {
     //    unsafe {self.context.ClearDepthStencilView(d3d11_window.depth_stencil_view.as_ref().unwrap().as_raw() as *mut _, d3d11::D3D11_CLEAR_DEPTH, 1.0, 0)}
     //}
     
+    pub fn create_query(&self, query: d3d11::D3D11_QUERY) -> Result<ComPtr<d3d11::ID3D11Query>, winerror::HRESULT> {
+        let query_desc = d3d11::D3D11_QUERY_DESC {
+            Query: query,
+            MiscFlags: 0,
+        };
+        let mut d3d11_query = ptr::null_mut();
+        let hr = unsafe {self.device.CreateQuery(&query_desc, &mut d3d11_query as *mut *mut _)};
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe {ComPtr::from_raw(d3d11_query as *mut _)})
+        }
+        else {
+            Err(hr)
+        }
+    }
+    
+    // returns false while the result isn't available yet
+    pub fn get_query_data<T>(&self, query: &ComPtr<d3d11::ID3D11Query>, data: &mut T) -> bool {
+        let hr = unsafe {self.context.GetData(
+            query.as_raw() as *mut _,
+            data as *mut T as *mut _,
+            mem::size_of::<T>() as u32,
+            d3d11::D3D11_ASYNC_GETDATA_DONOTFLUSH
+        )};
+        hr == winerror::S_OK
+    }
+    
     pub fn set_raster_state(&self, raster_state: &ComPtr<d3d11::ID3D11RasterizerState>,) {
         unsafe {self.context.RSSetState(raster_state.as_raw() as *mut _)}
     }
@@ -663,6 +763,10 @@ impl D3d11Cx {
         unsafe {self.context.OMSetBlendState(blend_state.as_raw() as *mut _, &blend_factor, 0xffffffff)}
     }
     
+    pub fn set_depth_stencil_state(&self, depth_stencil_state: &ComPtr<d3d11::ID3D11DepthStencilState>,) {
+        unsafe {self.context.OMSetDepthStencilState(depth_stencil_state.as_raw() as *mut _, 0)}
+    }
+    
     pub fn set_input_layout(&self, input_layout: &ComPtr<d3d11::ID3D11InputLayout>) {
         unsafe {self.context.IASetInputLayout(input_layout.as_raw() as *mut _)}
     }
@@ -686,6 +790,14 @@ impl D3d11Cx {
         }
     }
     
+    pub fn set_sampler_state(&self, index: usize, sampler_state: &Option<ComPtr<d3d11::ID3D11SamplerState>>) {
+        if let Some(sampler_state) = sampler_state {
+            let raw = [sampler_state.as_raw() as *const std::ffi::c_void];
+            unsafe {self.context.PSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _)}
+            unsafe {self.context.VSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _)}
+        }
+    }
+    
     //fn set_raster_state(&self, d3d11_window: &D3d11Window) {
     //    unsafe {self.context.RSSetState(d3d11_window.raster_state.as_raw() as *mut _)};
     // }
@@ -835,17 +947,21 @@ impl D3d11Cx {
     }
     
     
-    pub fn create_raster_state(&self)
+    pub fn create_raster_state(&self, pipeline: &PipelineState)
         -> Result<ComPtr<d3d11::ID3D11RasterizerState>, winerror::HRESULT> {
         let mut raster_state = ptr::null_mut();
         let raster_desc = d3d11::D3D11_RASTERIZER_DESC {
             AntialiasedLineEnable: FALSE,
-            CullMode: d3d11::D3D11_CULL_NONE,
+            CullMode: match pipeline.cull {
+                CullMode::None => d3d11::D3D11_CULL_NONE,
+                CullMode::Front => d3d11::D3D11_CULL_FRONT,
+                CullMode::Back => d3d11::D3D11_CULL_BACK,
+            },
             DepthBias: 0,
             DepthBiasClamp: 0.0,
             DepthClipEnable: TRUE,
             FillMode: d3d11::D3D11_FILL_SOLID,
-            FrontCounterClockwise: FALSE,
+            FrontCounterClockwise: TRUE,
             MultisampleEnable: FALSE,
             ScissorEnable: FALSE,
             SlopeScaledDepthBias: 0.0,
@@ -859,20 +975,53 @@ impl D3d11Cx {
         }
     }
     
-    pub fn create_blend_state(&self)
+    // d3d11 doesn't allow color factors in the alpha channel, so those map onto their alpha equivalent
+    fn blend_factor(factor: BlendFactor, is_alpha: bool) -> d3d11::D3D11_BLEND {
+        match factor {
+            BlendFactor::Zero => d3d11::D3D11_BLEND_ZERO,
+            BlendFactor::One => d3d11::D3D11_BLEND_ONE,
+            BlendFactor::SrcColor => if is_alpha {d3d11::D3D11_BLEND_SRC_ALPHA} else {d3d11::D3D11_BLEND_SRC_COLOR},
+            BlendFactor::OneMinusSrcColor => if is_alpha {d3d11::D3D11_BLEND_INV_SRC_ALPHA} else {d3d11::D3D11_BLEND_INV_SRC_COLOR},
+            BlendFactor::SrcAlpha => d3d11::D3D11_BLEND_SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => d3d11::D3D11_BLEND_INV_SRC_ALPHA,
+            BlendFactor::DstColor => if is_alpha {d3d11::D3D11_BLEND_DEST_ALPHA} else {d3d11::D3D11_BLEND_DEST_COLOR},
+            BlendFactor::OneMinusDstColor => if is_alpha {d3d11::D3D11_BLEND_INV_DEST_ALPHA} else {d3d11::D3D11_BLEND_INV_DEST_COLOR},
+            BlendFactor::DstAlpha => d3d11::D3D11_BLEND_DEST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => d3d11::D3D11_BLEND_INV_DEST_ALPHA,
+        }
+    }
+    
+    fn blend_op(op: BlendOp) -> d3d11::D3D11_BLEND_OP {
+        match op {
+            BlendOp::Add => d3d11::D3D11_BLEND_OP_ADD,
+            BlendOp::Subtract => d3d11::D3D11_BLEND_OP_SUBTRACT,
+            BlendOp::ReverseSubtract => d3d11::D3D11_BLEND_OP_REV_SUBTRACT,
+            BlendOp::Min => d3d11::D3D11_BLEND_OP_MIN,
+            BlendOp::Max => d3d11::D3D11_BLEND_OP_MAX,
+        }
+    }
+    
+    pub fn create_blend_state(&self, pipeline: &PipelineState)
         -> Result<ComPtr<d3d11::ID3D11BlendState>, winerror::HRESULT> {
         let mut blend_state = ptr::null_mut();
         let mut blend_desc: d3d11::D3D11_BLEND_DESC = unsafe {mem::zeroed()};
+        let blend = &pipeline.blend;
+        let mask = &pipeline.color_mask;
         blend_desc.AlphaToCoverageEnable = FALSE;
         blend_desc.RenderTarget[0] = d3d11::D3D11_RENDER_TARGET_BLEND_DESC {
-            BlendEnable: TRUE,
-            SrcBlend: d3d11::D3D11_BLEND_ONE,
-            SrcBlendAlpha: d3d11::D3D11_BLEND_ONE,
-            DestBlend: d3d11::D3D11_BLEND_INV_SRC_ALPHA,
-            DestBlendAlpha: d3d11::D3D11_BLEND_INV_SRC_ALPHA,
-            BlendOp: d3d11::D3D11_BLEND_OP_ADD,
-            BlendOpAlpha: d3d11::D3D11_BLEND_OP_ADD,
-            RenderTargetWriteMask: d3d11::D3D11_COLOR_WRITE_ENABLE_ALL as u8,
+            BlendEnable: if blend.enabled {TRUE} else {FALSE},
+            SrcBlend: Self::blend_factor(blend.src_color, false),
+            SrcBlendAlpha: Self::blend_factor(blend.src_alpha, true),
+            DestBlend: Self::blend_factor(blend.dst_color, false),
+            DestBlendAlpha: Self::blend_factor(blend.dst_alpha, true),
+            BlendOp: Self::blend_op(blend.color_op),
+            BlendOpAlpha: Self::blend_op(blend.alpha_op),
+            RenderTargetWriteMask: (
+                if mask.r {d3d11::D3D11_COLOR_WRITE_ENABLE_RED} else {0}
+                | if mask.g {d3d11::D3D11_COLOR_WRITE_ENABLE_GREEN} else {0}
+                | if mask.b {d3d11::D3D11_COLOR_WRITE_ENABLE_BLUE} else {0}
+                | if mask.a {d3d11::D3D11_COLOR_WRITE_ENABLE_ALPHA} else {0}
+            ) as u8,
         };
         let hr = unsafe {self.device.CreateBlendState(&blend_desc, &mut blend_state as *mut *mut _)};
         if winerror::SUCCEEDED(hr) {
@@ -901,13 +1050,24 @@ impl D3d11Cx {
         }
     }
     
-    pub fn create_depth_stencil_state(&self)
+    pub fn create_depth_stencil_state(&self, pipeline: &PipelineState)
         -> Result<ComPtr<d3d11::ID3D11DepthStencilState>, winerror::HRESULT> {
         let mut depth_stencil_state = ptr::null_mut();
+        let depth = &pipeline.depth;
         let ds_desc = d3d11::D3D11_DEPTH_STENCIL_DESC {
-            DepthEnable: TRUE,
-            DepthWriteMask: d3d11::D3D11_DEPTH_WRITE_MASK_ALL,
-            DepthFunc: d3d11::D3D11_COMPARISON_LESS_EQUAL,
+            // depth writes need the depth test enabled, so emulate with ALWAYS
+            DepthEnable: if depth.test || depth.write {TRUE} else {FALSE},
+            DepthWriteMask: if depth.write {d3d11::D3D11_DEPTH_WRITE_MASK_ALL} else {d3d11::D3D11_DEPTH_WRITE_MASK_ZERO},
+            DepthFunc: if !depth.test {d3d11::D3D11_COMPARISON_ALWAYS} else {match depth.func {
+                DepthFunc::Never => d3d11::D3D11_COMPARISON_NEVER,
+                DepthFunc::Less => d3d11::D3D11_COMPARISON_LESS,
+                DepthFunc::Equal => d3d11::D3D11_COMPARISON_EQUAL,
+                DepthFunc::LessEqual => d3d11::D3D11_COMPARISON_LESS_EQUAL,
+                DepthFunc::Greater => d3d11::D3D11_COMPARISON_GREATER,
+                DepthFunc::NotEqual => d3d11::D3D11_COMPARISON_NOT_EQUAL,
+                DepthFunc::GreaterEqual => d3d11::D3D11_COMPARISON_GREATER_EQUAL,
+                DepthFunc::Always => d3d11::D3D11_COMPARISON_ALWAYS,
+            }},
             StencilEnable: FALSE,
             StencilReadMask: 0xff,
             StencilWriteMask: 0xff,
@@ -1199,54 +1359,195 @@ impl D3d11Cx {
         return true
     }
     
-    pub fn update_platform_texture_image_bgra(&self, res: &mut CxPlatformTexture, width: usize, height: usize, image_u32: &Vec<u32>) {
+    pub fn update_platform_texture_image2d(&self, cxtexture: &mut CxTexture) {
         
-        if image_u32.len() != width * height {
-            println!("update_platform_texture_image_bgra with wrong buffer_u32 size!");
-            return;
-        }
+        let width = cxtexture.desc.width.unwrap();
+        let height = cxtexture.desc.height.unwrap();
         
-        let sub_data = d3d11::D3D11_SUBRESOURCE_DATA {
-            pSysMem: image_u32.as_ptr() as *const _,
-            SysMemPitch: (width * 4) as u32,
-            SysMemSlicePitch: 0
+        let image_f16;
+        let (format, pixel_len, data, bytes_per_pixel) = match cxtexture.desc.format {
+            TextureFormat::ImageR8 => {
+                (dxgiformat::DXGI_FORMAT_R8_UNORM, cxtexture.image_u8.len(), cxtexture.image_u8.as_ptr() as *const std::ffi::c_void, 1)
+            },
+            TextureFormat::ImageRG8 => {
+                (dxgiformat::DXGI_FORMAT_R8G8_UNORM, cxtexture.image_u8.len() / 2, cxtexture.image_u8.as_ptr() as *const std::ffi::c_void, 2)
+            },
+            TextureFormat::ImageRf32 => {
+                (dxgiformat::DXGI_FORMAT_R32_FLOAT, cxtexture.image_f32.len(), cxtexture.image_f32.as_ptr() as *const std::ffi::c_void, 4)
+            },
+            TextureFormat::ImageRGBAf16 => {
+                image_f16 = cxtexture.image_f16();
+                (dxgiformat::DXGI_FORMAT_R16G16B16A16_FLOAT, image_f16.len() / 4, image_f16.as_ptr() as *const std::ffi::c_void, 8)
+            },
+            _ => {
+                (dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM, cxtexture.image_u32.len(), cxtexture.image_u32.as_ptr() as *const std::ffi::c_void, 4)
+            }
         };
         
-        let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
-            Width: width as u32,
-            Height: height as u32,
-            MipLevels: 1,
-            ArraySize: 1,
-            Format: dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
-            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0
-            },
-            Usage: d3d11::D3D11_USAGE_DEFAULT,
-            BindFlags: d3d11::D3D11_BIND_SHADER_RESOURCE,
-            CPUAccessFlags: 0,
-            MiscFlags: 0,
+        let layers = cxtexture.desc.layers();
+        if pixel_len != width * height * layers {
+            println!("update_platform_texture_image2d with wrong image buffer size!");
+            return;
+        }
+        
+        // mipmapped textures are created empty, filled and then get their chain generated on the gpu
+        let mipmaps = cxtexture.desc.mipmaps;
+        let mip_levels = cxtexture.desc.mip_levels() as u32;
+        let row_pitch = (width * bytes_per_pixel) as u32;
+        let slice_pitch = row_pitch * height as u32;
+        let bind_flags = if mipmaps {d3d11::D3D11_BIND_SHADER_RESOURCE | d3d11::D3D11_BIND_RENDER_TARGET} else {d3d11::D3D11_BIND_SHADER_RESOURCE};
+        let mips_flag = if mipmaps {d3d11::D3D11_RESOURCE_MISC_GENERATE_MIPS} else {0};
+        
+        let mut texture: *mut d3d11::ID3D11Resource = ptr::null_mut();
+        let mut srv_desc: d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC = unsafe {mem::zeroed()};
+        srv_desc.Format = format;
+        // 3d textures are one subresource holding all the layers, arrays and cubes have one per layer
+        let subresources = if cxtexture.desc.kind == TextureKind::Texture3D {1} else {layers};
+        let sub_data: Vec<d3d11::D3D11_SUBRESOURCE_DATA> = (0..subresources).map( | layer | d3d11::D3D11_SUBRESOURCE_DATA {
+            pSysMem: unsafe {(data as *const u8).add(layer * slice_pitch as usize)} as *const _,
+            SysMemPitch: row_pitch,
+            SysMemSlicePitch: slice_pitch
+        }).collect();
+        
+        let hr = if cxtexture.desc.kind == TextureKind::Texture3D {
+            let texture_desc = d3d11::D3D11_TEXTURE3D_DESC {
+                Width: width as u32,
+                Height: height as u32,
+                Depth: layers as u32,
+                MipLevels: mip_levels,
+                Format: format,
+                Usage: d3d11::D3D11_USAGE_DEFAULT,
+                BindFlags: bind_flags,
+                CPUAccessFlags: 0,
+                MiscFlags: mips_flag,
+            };
+            srv_desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE3D;
+            *unsafe {srv_desc.u.Texture3D_mut()} = d3d11::D3D11_TEX3D_SRV {
+                MostDetailedMip: 0,
+                MipLevels: mip_levels,
+            };
+            unsafe {self.device.CreateTexture3D(
+                &texture_desc,
+                if mipmaps {ptr::null()} else {sub_data.as_ptr()},
+                &mut texture as *mut *mut _ as *mut *mut _
+            )}
+        }
+        else {
+            let is_cube = cxtexture.desc.kind == TextureKind::TextureCube;
+            let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
+                Width: width as u32,
+                Height: height as u32,
+                MipLevels: mip_levels,
+                ArraySize: layers as u32,
+                Format: format,
+                SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0
+                },
+                Usage: d3d11::D3D11_USAGE_DEFAULT,
+                BindFlags: bind_flags,
+                CPUAccessFlags: 0,
+                MiscFlags: mips_flag | if is_cube {d3d11::D3D11_RESOURCE_MISC_TEXTURECUBE} else {0},
+            };
+            match cxtexture.desc.kind {
+                TextureKind::Texture2DArray => {
+                    srv_desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DARRAY;
+                    *unsafe {srv_desc.u.Texture2DArray_mut()} = d3d11::D3D11_TEX2D_ARRAY_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: mip_levels,
+                        FirstArraySlice: 0,
+                        ArraySize: layers as u32,
+                    };
+                },
+                TextureKind::TextureCube => {
+                    srv_desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBE;
+                    *unsafe {srv_desc.u.TextureCube_mut()} = d3d11::D3D11_TEXCUBE_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: mip_levels,
+                    };
+                },
+                _ => {
+                    srv_desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2D;
+                    *unsafe {srv_desc.u.Texture2D_mut()} = d3d11::D3D11_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: mip_levels,
+                    };
+                }
+            }
+            unsafe {self.device.CreateTexture2D(
+                &texture_desc,
+                if mipmaps {ptr::null()} else {sub_data.as_ptr()},
+                &mut texture as *mut *mut _ as *mut *mut _
+            )}
         };
-        let mut texture = ptr::null_mut();
-        let hr = unsafe {self.device.CreateTexture2D(&texture_desc, &sub_data, &mut texture as *mut *mut _)};
         if winerror::SUCCEEDED(hr) {
             let mut shader_resource = ptr::null_mut();
             unsafe {self.device.CreateShaderResourceView(
-                texture as *mut _,
-                ptr::null(),
+                texture,
+                &srv_desc,
                 &mut shader_resource as *mut *mut _
             )};
+            if mipmaps {
+                for (layer, sub) in sub_data.iter().enumerate() {
+                    unsafe {self.context.UpdateSubresource(
+                        texture,
+                        layer as u32 * mip_levels,
+                        ptr::null(),
+                        sub.pSysMem,
+                        sub.SysMemPitch,
+                        sub.SysMemSlicePitch
+                    )};
+                }
+                unsafe {self.context.GenerateMips(shader_resource as *mut _)};
+            }
+            let res = &mut cxtexture.platform;
             res.width = width;
             res.height = height;
-            res.texture = Some(unsafe {ComPtr::from_raw(texture as *mut _)});
+            res.d3d11_resource = Some(unsafe {ComPtr::from_raw(texture)});
             res.shader_resource = Some(unsafe {ComPtr::from_raw(shader_resource as *mut _)});
         }
         else {
-            panic!("update_platform_texture_image_bgra failed");
+            panic!("update_platform_texture_image2d failed");
         }
     }
     
-    
+    pub fn update_platform_texture_sampler(&self, cxtexture: &mut CxTexture) {
+        let sampler_desc = (cxtexture.desc.sampler, cxtexture.desc.mipmaps);
+        if cxtexture.platform.sampler_state.is_some() && cxtexture.platform.alloc_sampler == Some(sampler_desc) {
+            return
+        }
+        let filter = match cxtexture.desc.sampler.filter {
+            TextureFilter::Nearest => d3d11::D3D11_FILTER_MIN_MAG_MIP_POINT,
+            TextureFilter::Linear => d3d11::D3D11_FILTER_MIN_MAG_LINEAR_MIP_POINT,
+            TextureFilter::Trilinear => d3d11::D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+        };
+        let address = match cxtexture.desc.sampler.wrap {
+            TextureWrap::Clamp => d3d11::D3D11_TEXTURE_ADDRESS_CLAMP,
+            TextureWrap::Repeat => d3d11::D3D11_TEXTURE_ADDRESS_WRAP,
+            TextureWrap::Mirror => d3d11::D3D11_TEXTURE_ADDRESS_MIRROR,
+        };
+        let sampler_desc_d3d11 = d3d11::D3D11_SAMPLER_DESC {
+            Filter: filter,
+            AddressU: address,
+            AddressV: address,
+            AddressW: address,
+            MipLODBias: 0.0,
+            MaxAnisotropy: 1,
+            ComparisonFunc: d3d11::D3D11_COMPARISON_NEVER,
+            BorderColor: [0.0, 0.0, 0.0, 0.0],
+            MinLOD: 0.0,
+            MaxLOD: if cxtexture.desc.mipmaps {std::f32::MAX} else {0.0},
+        };
+        let mut sampler_state = ptr::null_mut();
+        let hr = unsafe {self.device.CreateSamplerState(&sampler_desc_d3d11, &mut sampler_state as *mut *mut _)};
+        if winerror::SUCCEEDED(hr) {
+            cxtexture.platform.sampler_state = Some(unsafe {ComPtr::from_raw(sampler_state as *mut _)});
+            cxtexture.platform.alloc_sampler = Some(sampler_desc);
+        }
+        else {
+            panic!("update_platform_texture_sampler failed");
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -1355,7 +1656,9 @@ pub struct CxPlatformTexture {
     //single: CxPlatformTextureResource,
     //mapped: Mutex<CxPlatformTextureMapped>,
     render_target_view: Option<ComPtr<d3d11::ID3D11RenderTargetView>>,
-    depth_stencil_view: Option<ComPtr<d3d11::ID3D11DepthStencilView>>
+    depth_stencil_view: Option<ComPtr<d3d11::ID3D11DepthStencilView>>,
+    sampler_state: Option<ComPtr<d3d11::ID3D11SamplerState>>,
+    alloc_sampler: Option<(TextureSampler, bool)>,
 }
 
 #[derive(Default, Clone)]
@@ -1363,7 +1666,17 @@ pub struct CxPlatformPass {
     pass_uniforms: D3d11Buffer,
     blend_state: Option<ComPtr<d3d11::ID3D11BlendState>>,
     raster_state: Option<ComPtr<d3d11::ID3D11RasterizerState>>,
-    depth_stencil_state: Option<ComPtr<d3d11::ID3D11DepthStencilState>>
+    depth_stencil_state: Option<ComPtr<d3d11::ID3D11DepthStencilState>>,
+    timer_queries: Option<D3d11TimerQueries>,
+    timer_pending: bool,
+    timer_active: bool,
+}
+
+#[derive(Clone)]
+pub struct D3d11TimerQueries {
+    disjoint: ComPtr<d3d11::ID3D11Query>,
+    begin: ComPtr<d3d11::ID3D11Query>,
+    end: ComPtr<d3d11::ID3D11Query>,
 }
 
 #[derive(Clone)]
@@ -1376,7 +1689,10 @@ pub struct CxPlatformShader {
     pub vertex_shader: ComPtr<d3d11::ID3D11VertexShader>,
     pub pixel_shader_blob: ComPtr<d3dcommon::ID3DBlob>,
     pub vertex_shader_blob: ComPtr<d3dcommon::ID3DBlob>,
-    pub input_layout: ComPtr<d3d11::ID3D11InputLayout>
+    pub input_layout: ComPtr<d3d11::ID3D11InputLayout>,
+    pub blend_state: ComPtr<d3d11::ID3D11BlendState>,
+    pub raster_state: ComPtr<d3d11::ID3D11RasterizerState>,
+    pub depth_stencil_state: ComPtr<d3d11::ID3D11DepthStencilState>,
 }
 
 /*pub const MAPPED_TEXTURE_BUFFER_COUNT: usize = 4;
@@ -159,6 +159,7 @@ impl Cx {
                         self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
                         
                         if passes_todo.len() > 0 {
+                            let profile_start = self.profile_span_begin();
                             for pass_id in &passes_todo {
                                 match self.passes[*pass_id].dep_of.clone() {
                                     CxPassDepOf::Window(window_id) => {
@@ -214,6 +215,8 @@ impl Cx {
                                     }
                                 }
                             }
+                            self.profile_span_end(ProfileSpan::Repaint, profile_start);
+                            self.profile_end_frame();
                         }
                     },
                     Event::Signal {..} => {
@@ -231,11 +234,13 @@ impl Cx {
                 }
             }
     
+            let profile_start = self.profile_span_begin();
             let mut shader_results = Vec::new();
             for shader_id in &self.shader_recompiles {
                 shader_results.push(Self::opengl_compile_shader(*shader_id, true, &mut self.shaders[*shader_id], &opengl_cx, &mut self.shader_inherit_cache));
             }
             self.shader_recompiles.truncate(0);
+            self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
             self.call_shader_recompile_event(shader_results, &mut event_handler);
             
             if !paint_dirty && self.playing_anim_areas.len() == 0 && self.redraw_parent_areas.len() == 0 && self.redraw_child_areas.len() == 0 && self.frame_callbacks.len() == 0 {
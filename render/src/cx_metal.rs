@@ -4,7 +4,7 @@
 //use core_graphics::geometry::CGSize;
 //use core_graphics::color::CGColor;
 use makepad_objc_sys::{msg_send};
-use makepad_objc_sys::runtime::YES;
+use makepad_objc_sys::runtime::{YES, NO};
 use makepad_shader_compiler::generate_metal;
 //use metal::*;
 use crate::cx_apple::*;
@@ -77,6 +77,19 @@ impl Cx {
                 }
                 let pipeline_state = shp.pipeline_state;
                 unsafe {let () = msg_send![encoder, setRenderPipelineState: pipeline_state];}
+                if self.passes[pass_id].depth_texture.is_some() {
+                    let depth_state = shp.depth_state;
+                    unsafe {let () = msg_send![encoder, setDepthStencilState: depth_state];}
+                }
+                let cull_mode = match sh.pipeline.cull {
+                    CullMode::None => MTLCullMode::None,
+                    CullMode::Front => MTLCullMode::Front,
+                    CullMode::Back => MTLCullMode::Back,
+                };
+                unsafe {
+                    let () = msg_send![encoder, setFrontFacingWinding: MTLWinding::CounterClockwise];
+                    let () = msg_send![encoder, setCullMode: cull_mode];
+                }
                 
                 if let Some(buf) = shp.geom_vbuf.multi_buffer_read().buffer {
                     unsafe {msg_send![
@@ -130,6 +143,19 @@ impl Cx {
                     if cxtexture.update_image {
                         metal_cx.update_platform_texture_image2d(cxtexture);
                     }
+                    metal_cx.update_platform_texture_sampler(cxtexture);
+                    if let Some(mtl_sampler) = cxtexture.platform.mtl_sampler {
+                        let () = unsafe {msg_send![
+                            encoder,
+                            setFragmentSamplerState: mtl_sampler
+                            atIndex: i as u64
+                        ]};
+                        let () = unsafe {msg_send![
+                            encoder,
+                            setVertexSamplerState: mtl_sampler
+                            atIndex: i as u64
+                        ]};
+                    }
                     if let Some(mtl_texture) = cxtexture.platform.mtl_texture {
                         let () = unsafe {msg_send![
                             encoder,
@@ -324,6 +350,7 @@ impl Cx {
             let () = unsafe {msg_send![encoder, endEncoding]};
             let () = unsafe {msg_send![command_buffer, presentDrawable: drawable]};
             let () = unsafe {msg_send![command_buffer, commit]};
+            self.metal_pass_timer(pass_id, command_buffer);
             //let () = unsafe {msg_send![command_buffer, waitUntilScheduled]};
             //command_buffer.wait_until_scheduled();
         }
@@ -365,9 +392,38 @@ impl Cx {
         let () = unsafe {msg_send![encoder, textureBarrier]};
         let () = unsafe {msg_send![encoder, endEncoding]};
         let () = unsafe {msg_send![command_buffer, commit]};
+        self.metal_pass_timer(pass_id, command_buffer);
         //command_buffer.wait_until_scheduled();
         let () = unsafe {msg_send![pool, release]};
     }
+    
+    // GPU time per pass from the GPUStartTime/GPUEndTime of the command buffer (10.15+).
+    // we keep the buffer and read it once it completed, a frame or more later, so we never
+    // wait on the GPU. Passes drawn while the last one is still in flight aren't timed
+    pub fn metal_pass_timer(&mut self, pass_id: usize, command_buffer: id) {
+        if let Some(timer_buffer) = self.passes[pass_id].platform.mtl_timer_buffer {
+            // MTLCommandBufferStatusCompleted is 4, MTLCommandBufferStatusError 5
+            let status: u64 = unsafe {msg_send![timer_buffer, status]};
+            if status < 4 {
+                return
+            }
+            if status == 4 {
+                let start: f64 = unsafe {msg_send![timer_buffer, GPUStartTime]};
+                let end: f64 = unsafe {msg_send![timer_buffer, GPUEndTime]};
+                self.profile_pass_gpu_time(pass_id, ((end - start).max(0.0) * 1_000_000_000.0) as u64);
+            }
+            let () = unsafe {msg_send![timer_buffer, release]};
+            self.passes[pass_id].platform.mtl_timer_buffer = None;
+        }
+        if !self.profiler.enabled {
+            return
+        }
+        let has_gpu_time: BOOL = unsafe {msg_send![command_buffer, respondsToSelector: sel!(GPUStartTime)]};
+        if has_gpu_time == YES {
+            let () = unsafe {msg_send![command_buffer, retain]};
+            self.passes[pass_id].platform.mtl_timer_buffer = Some(command_buffer);
+        }
+    }
 }
 
 pub struct MetalCx {
@@ -463,58 +519,137 @@ impl MetalCx {
         let width = cxtexture.desc.width.unwrap();
         let height = cxtexture.desc.height.unwrap();
         
+        let (pixel_format, pixel_len) = match cxtexture.desc.format {
+            TextureFormat::Default | TextureFormat::ImageBGRA => (MTLPixelFormat::BGRA8Unorm, cxtexture.image_u32.len()),
+            TextureFormat::ImageR8 => (MTLPixelFormat::R8Unorm, cxtexture.image_u8.len()),
+            TextureFormat::ImageRG8 => (MTLPixelFormat::RG8Unorm, cxtexture.image_u8.len() / 2),
+            TextureFormat::ImageRf32 => (MTLPixelFormat::R32Float, cxtexture.image_f32.len()),
+            TextureFormat::ImageRGBAf16 => (MTLPixelFormat::RGBA16Float, cxtexture.image_f32.len() / 4),
+            _ => {
+                println!("update_platform_texture_image2d with unsupported format");
+                return;
+            }
+        };
+        
+        let layers = cxtexture.desc.layers();
+        if pixel_len != width * height * layers {
+            println!("update_platform_texture_image2d with wrong image buffer size!");
+            return;
+        }
+        
         // allocate new texture if descriptor change
-        if cxtexture.platform.alloc_desc != cxtexture.desc {
-            cxtexture.platform.mtl_texture = None;
-            
+        if cxtexture.platform.alloc_desc != cxtexture.desc || cxtexture.platform.mtl_texture.is_none() {
             let mdesc: id = unsafe {msg_send![class!(MTLTextureDescriptor), new]};
             unsafe {
-                let () = msg_send![mdesc, setTextureType: MTLTextureType::D2];
+                match cxtexture.desc.kind {
+                    TextureKind::Texture2D => {
+                        let () = msg_send![mdesc, setTextureType: MTLTextureType::D2];
+                    },
+                    TextureKind::Texture2DArray => {
+                        let () = msg_send![mdesc, setTextureType: MTLTextureType::D2Array];
+                        let () = msg_send![mdesc, setArrayLength: layers as u64];
+                    },
+                    TextureKind::TextureCube => {
+                        let () = msg_send![mdesc, setTextureType: MTLTextureType::Cube];
+                    },
+                    TextureKind::Texture3D => {
+                        let () = msg_send![mdesc, setTextureType: MTLTextureType::D3];
+                        let () = msg_send![mdesc, setDepth: layers as u64];
+                    }
+                }
                 let () = msg_send![mdesc, setStorageMode: MTLStorageMode::Managed];
-                let () = msg_send![mdesc, setUsage: MTLTextureUsage::RenderTarget];
+                let () = msg_send![mdesc, setUsage: MTLTextureUsage::ShaderRead];
+                let () = msg_send![mdesc, setPixelFormat: pixel_format];
                 let () = msg_send![mdesc, setWidth: width as u64];
                 let () = msg_send![mdesc, setHeight: height as u64];
+                let () = msg_send![mdesc, setMipmapLevelCount: cxtexture.desc.mip_levels() as u64];
             }
-            
-            match cxtexture.desc.format {
-                TextureFormat::Default | TextureFormat::ImageBGRA => {
-                    let () = unsafe {msg_send![mdesc, setPixelFormat: MTLPixelFormat::BGRA8Unorm]};
-                    
-                    let tex: id = unsafe {msg_send![self.device, newTextureWithDescriptor: mdesc]};
-                    
-                    cxtexture.platform.mtl_texture = Some(tex);
-                    
-                    if cxtexture.image_u32.len() != width * height {
-                        println!("update_platform_texture_image2d with wrong buffer_u32 size!");
-                        cxtexture.platform.mtl_texture = None;
-                        return;
-                    }
-                    let region = MTLRegion {
-                        origin: MTLOrigin {x: 0, y: 0, z: 0},
-                        size: MTLSize {width: width as u64, height: height as u64, depth: 1}
-                    };
-                    if let Some(mtl_texture) = cxtexture.platform.mtl_texture {
-                        let () = unsafe {msg_send![
-                            mtl_texture,
-                            replaceRegion: region
-                            mipmapLevel: 0
-                            withBytes: cxtexture.image_u32.as_ptr() as *const std::ffi::c_void
-                            bytesPerRow: (width * std::mem::size_of::<u32>()) as u64
-                        ]};
-                    }
-                },
-                _ => {
-                    println!("update_platform_texture_image2d with unsupported format");
-                    return;
-                }
-            }
+            let tex: id = unsafe {msg_send![self.device, newTextureWithDescriptor: mdesc]};
+            cxtexture.platform.mtl_texture = Some(tex);
             cxtexture.platform.alloc_desc = cxtexture.desc.clone();
             cxtexture.platform.width = width as u64;
             cxtexture.platform.height = height as u64;
         }
         
+        let image_f16;
+        let (bytes, bytes_per_pixel) = match cxtexture.desc.format {
+            TextureFormat::ImageR8 => (cxtexture.image_u8.as_ptr() as *const std::ffi::c_void, 1),
+            TextureFormat::ImageRG8 => (cxtexture.image_u8.as_ptr() as *const std::ffi::c_void, 2),
+            TextureFormat::ImageRf32 => (cxtexture.image_f32.as_ptr() as *const std::ffi::c_void, 4),
+            TextureFormat::ImageRGBAf16 => {
+                image_f16 = cxtexture.image_f16();
+                (image_f16.as_ptr() as *const std::ffi::c_void, 8)
+            },
+            _ => (cxtexture.image_u32.as_ptr() as *const std::ffi::c_void, 4)
+        };
+        
+        let mtl_texture = cxtexture.platform.mtl_texture.unwrap();
+        let bytes_per_row = width * bytes_per_pixel;
+        let bytes_per_image = bytes_per_row * height;
+        // 3d textures take all their layers in one region, arrays and cubes get a slice per layer
+        let (slices, depth) = match cxtexture.desc.kind {
+            TextureKind::Texture3D => (1, layers),
+            _ => (layers, 1)
+        };
+        let region = MTLRegion {
+            origin: MTLOrigin {x: 0, y: 0, z: 0},
+            size: MTLSize {width: width as u64, height: height as u64, depth: depth as u64}
+        };
+        for slice in 0..slices {
+            let () = unsafe {msg_send![
+                mtl_texture,
+                replaceRegion: region
+                mipmapLevel: 0
+                slice: slice as u64
+                withBytes: (bytes as *const u8).add(slice * bytes_per_image) as *const std::ffi::c_void
+                bytesPerRow: bytes_per_row as u64
+                bytesPerImage: bytes_per_image as u64
+            ]};
+        }
+        
+        if cxtexture.desc.mipmaps {
+            unsafe {
+                let command_buffer: id = msg_send![self.command_queue, commandBuffer];
+                let blit_encoder: id = msg_send![command_buffer, blitCommandEncoder];
+                let () = msg_send![blit_encoder, generateMipmapsForTexture: mtl_texture];
+                let () = msg_send![blit_encoder, endEncoding];
+                let () = msg_send![command_buffer, commit];
+            }
+        }
+        
         cxtexture.update_image = false;
     }
+    
+    pub fn update_platform_texture_sampler(&self, cxtexture: &mut CxTexture) {
+        let has_mips = cxtexture.desc.mipmaps;
+        let sampler_desc = (cxtexture.desc.sampler, has_mips);
+        if cxtexture.platform.mtl_sampler.is_some() && cxtexture.platform.alloc_sampler == Some(sampler_desc) {
+            return
+        }
+        let (min_mag_filter, mip_filter) = match cxtexture.desc.sampler.filter {
+            TextureFilter::Nearest => (MTLSamplerMinMagFilter::Nearest, MTLSamplerMipFilter::NotMipmapped),
+            TextureFilter::Linear if has_mips => (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::Nearest),
+            TextureFilter::Linear => (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::NotMipmapped),
+            TextureFilter::Trilinear if has_mips => (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::Linear),
+            TextureFilter::Trilinear => (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::NotMipmapped),
+        };
+        let address_mode = match cxtexture.desc.sampler.wrap {
+            TextureWrap::Clamp => MTLSamplerAddressMode::ClampToEdge,
+            TextureWrap::Repeat => MTLSamplerAddressMode::Repeat,
+            TextureWrap::Mirror => MTLSamplerAddressMode::MirrorRepeat,
+        };
+        unsafe {
+            let sdesc: id = msg_send![class!(MTLSamplerDescriptor), new];
+            let () = msg_send![sdesc, setMinFilter: min_mag_filter];
+            let () = msg_send![sdesc, setMagFilter: min_mag_filter];
+            let () = msg_send![sdesc, setMipFilter: mip_filter];
+            let () = msg_send![sdesc, setSAddressMode: address_mode];
+            let () = msg_send![sdesc, setTAddressMode: address_mode];
+            let sampler: id = msg_send![self.device, newSamplerStateWithDescriptor: sdesc];
+            cxtexture.platform.mtl_sampler = Some(sampler);
+        }
+        cxtexture.platform.alloc_sampler = Some(sampler_desc);
+    }
 }
 
 #[derive(Clone)]
@@ -609,12 +744,15 @@ pub struct CxPlatformTexture {
     pub alloc_desc: TextureDesc,
     pub width: u64,
     pub height: u64,
-    pub mtl_texture: Option<id>
+    pub mtl_texture: Option<id>,
+    pub mtl_sampler: Option<id>,
+    pub alloc_sampler: Option<(TextureSampler, bool)>,
 }
 
 #[derive(Default, Clone)]
 pub struct CxPlatformPass {
-    pub mtl_depth_state: Option<id>
+    pub mtl_depth_state: Option<id>,
+    pub mtl_timer_buffer: Option<id>, // retained command buffer we read the gpu time of
 }
 
 #[derive(Default, Clone)]
@@ -730,6 +868,7 @@ pub struct CxPlatformShader {
     pub library: id,
     pub metal_shader: String,
     pub pipeline_state: id,
+    pub depth_state: id,
     pub geom_vbuf: MetalBuffer,
     pub geom_ibuf: MetalBuffer,
 }
@@ -749,7 +888,46 @@ pub struct SlErr {
 
 impl Cx {
     
+    fn mtl_blend_factor(factor: BlendFactor) -> MTLBlendFactor {
+        match factor {
+            BlendFactor::Zero => MTLBlendFactor::Zero,
+            BlendFactor::One => MTLBlendFactor::One,
+            BlendFactor::SrcColor => MTLBlendFactor::SourceColor,
+            BlendFactor::OneMinusSrcColor => MTLBlendFactor::OneMinusSourceColor,
+            BlendFactor::SrcAlpha => MTLBlendFactor::SourceAlpha,
+            BlendFactor::OneMinusSrcAlpha => MTLBlendFactor::OneMinusSourceAlpha,
+            BlendFactor::DstColor => MTLBlendFactor::DestinationColor,
+            BlendFactor::OneMinusDstColor => MTLBlendFactor::OneMinusDestinationColor,
+            BlendFactor::DstAlpha => MTLBlendFactor::DestinationAlpha,
+            BlendFactor::OneMinusDstAlpha => MTLBlendFactor::OneMinusDestinationAlpha,
+        }
+    }
+    
+    fn mtl_blend_op(op: BlendOp) -> MTLBlendOperation {
+        match op {
+            BlendOp::Add => MTLBlendOperation::Add,
+            BlendOp::Subtract => MTLBlendOperation::Subtract,
+            BlendOp::ReverseSubtract => MTLBlendOperation::ReverseSubtract,
+            BlendOp::Min => MTLBlendOperation::Min,
+            BlendOp::Max => MTLBlendOperation::Max,
+        }
+    }
+    
+    fn mtl_compare_function(func: DepthFunc) -> MTLCompareFunction {
+        match func {
+            DepthFunc::Never => MTLCompareFunction::Never,
+            DepthFunc::Less => MTLCompareFunction::Less,
+            DepthFunc::Equal => MTLCompareFunction::Equal,
+            DepthFunc::LessEqual => MTLCompareFunction::LessEqual,
+            DepthFunc::Greater => MTLCompareFunction::Greater,
+            DepthFunc::NotEqual => MTLCompareFunction::NotEqual,
+            DepthFunc::GreaterEqual => MTLCompareFunction::GreaterEqual,
+            DepthFunc::Always => MTLCompareFunction::Always,
+        }
+    }
+    
     pub fn mtl_compile_all_shaders(&mut self, metal_cx: &MetalCx) {
+        let profile_start = self.profile_span_begin();
         for (index, sh) in &mut self.shaders.iter_mut().enumerate() {
             let result = Self::mtl_compile_shader(index, false, sh, metal_cx, &mut self.shader_inherit_cache);
             if let ShaderCompileResult::Fail{err, ..} = result {
@@ -757,6 +935,7 @@ impl Cx {
                 panic!("{}", err);
             } 
         };
+        self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
     } 
     
     pub fn mtl_compile_shader(shader_id:usize, use_const_table:bool, sh: &mut CxShader, metal_cx: &MetalCx, inherit_cache: &mut ShaderInheritCache) -> ShaderCompileResult {
@@ -778,6 +957,12 @@ impl Cx {
             }
         };
 
+        // the blend, depth, cull and color_mask the shader declares, on top of its ShaderGen.
+        // they are baked into the platform shader, so a change of them compiles it again
+        let pipeline = shader_ast.pipeline.apply(sh.shader_gen.pipeline);
+        let pipeline_changed = sh.pipeline != pipeline;
+        sh.pipeline = pipeline;
+        
         let mtlsl =  generate_metal::generate_shader(&shader_ast, use_const_table);
         let mapping = CxShaderMapping::from_shader_gen(&sh.shader_gen, if use_const_table{shader_ast.const_table.borrow_mut().take()} else {None});
     
@@ -787,7 +972,7 @@ impl Cx {
         }
         
         if let Some(sh_platform) = &sh.platform{
-            if sh_platform.metal_shader == mtlsl{
+            if !pipeline_changed && sh_platform.metal_shader == mtlsl{
                 sh.mapping = mapping;
                 return ShaderCompileResult::Nop{id:shader_id}
             }
@@ -833,13 +1018,21 @@ impl Cx {
                 
                 let ca: id = msg_send![color_attachments, objectAtIndexedSubscript: 0u64];
                 let () = msg_send![ca, setPixelFormat: MTLPixelFormat::BGRA8Unorm];
-                let () = msg_send![ca, setBlendingEnabled: YES];
-                let () = msg_send![ca, setSourceRGBBlendFactor: MTLBlendFactor::One];
-                let () = msg_send![ca, setDestinationRGBBlendFactor: MTLBlendFactor::OneMinusSourceAlpha];
-                let () = msg_send![ca, setSourceAlphaBlendFactor: MTLBlendFactor::One];
-                let () = msg_send![ca, setDestinationAlphaBlendFactor: MTLBlendFactor::OneMinusSourceAlpha];
-                let () = msg_send![ca, setRgbBlendOperation: MTLBlendOperation::Add];
-                let () = msg_send![ca, setAlphaBlendOperation: MTLBlendOperation::Add];
+                let blend = &sh.pipeline.blend;
+                let () = msg_send![ca, setBlendingEnabled: if blend.enabled {YES} else {NO}];
+                let () = msg_send![ca, setSourceRGBBlendFactor: Self::mtl_blend_factor(blend.src_color)];
+                let () = msg_send![ca, setDestinationRGBBlendFactor: Self::mtl_blend_factor(blend.dst_color)];
+                let () = msg_send![ca, setSourceAlphaBlendFactor: Self::mtl_blend_factor(blend.src_alpha)];
+                let () = msg_send![ca, setDestinationAlphaBlendFactor: Self::mtl_blend_factor(blend.dst_alpha)];
+                let () = msg_send![ca, setRgbBlendOperation: Self::mtl_blend_op(blend.color_op)];
+                let () = msg_send![ca, setAlphaBlendOperation: Self::mtl_blend_op(blend.alpha_op)];
+                let mask = &sh.pipeline.color_mask;
+                let write_mask = MTLColorWriteMaskNone
+                    | if mask.r {MTLColorWriteMaskRed} else {0}
+                    | if mask.g {MTLColorWriteMaskGreen} else {0}
+                    | if mask.b {MTLColorWriteMaskBlue} else {0}
+                    | if mask.a {MTLColorWriteMaskAlpha} else {0};
+                let () = msg_send![ca, setWriteMask: write_mask];
                 let () = msg_send![rpd, setDepthAttachmentPixelFormat: MTLPixelFormat::Depth32Float_Stencil8];
                 
                 let mut err: id = nil;
@@ -853,6 +1046,13 @@ impl Cx {
                 }
                 rps //.expect("Could not create render pipeline state")
             },
+            depth_state: unsafe {
+                let depth = &sh.pipeline.depth;
+                let desc: id = msg_send![class!(MTLDepthStencilDescriptor), new];
+                let () = msg_send![desc, setDepthCompareFunction: if depth.test {Self::mtl_compare_function(depth.func)} else {MTLCompareFunction::Always}];
+                let () = msg_send![desc, setDepthWriteEnabled: depth.write];
+                msg_send![metal_cx.device, newDepthStencilStateWithDescriptor: desc]
+            },
             library: library,
             geom_ibuf: {
                 let mut geom_ibuf = MetalBuffer {..Default::default()};
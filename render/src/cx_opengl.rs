@@ -6,19 +6,18 @@ use std::ffi::{CStr, CString};
 use std::os::raw::{c_ulong, c_void};
 use std::ptr;
 use std::mem;
-use makepad_shader_compiler::generate_glsl;
+use std::ops::Range;
+use makepad_shader_compiler::generate_glsl::{self, GlslVersion};
 
 impl Cx {
     
     pub fn render_view(
         &mut self,
-        pass_id: usize,
         view_id: usize,
         scroll: Vec2,
         clip: (Vec2, Vec2),
         full_repaint: bool,
         view_rect: &Rect,
-        opengl_cx: &OpenglCx,
         zbias: &mut f32,
         zbias_step: f32
     ) {
@@ -36,83 +35,128 @@ impl Cx {
             let sub_view_id = self.views[view_id].draw_calls[draw_call_id].sub_view_id;
             if sub_view_id != 0 {
                 self.render_view(
-                    pass_id,
                     sub_view_id,
                     Vec2 {x: local_scroll.x + scroll.x, y: local_scroll.y + scroll.y},
                     clip,
                     full_repaint,
                     view_rect,
-                    opengl_cx,
                     zbias,
                     zbias_step
                 );
             }
             else {
-                let cxview = &mut self.views[view_id];
-                //view.platform.uni_vw.update_with_f32_data(device, &view.uniforms);
-                let draw_call = &mut cxview.draw_calls[draw_call_id];
-                let sh = &self.shaders[draw_call.shader_id];
-                let shp = sh.platform.as_ref().unwrap();
-                
-                if draw_call.instance_dirty {
-                    draw_call.instance_dirty = false;
-                    draw_call.platform.inst_vbuf.update_with_f32_data(opengl_cx, &draw_call.instance);
-                }
-                
-                draw_call.platform.check_vao(draw_call.shader_id, &shp);
-                
+                let draw_call = &mut self.views[view_id].draw_calls[draw_call_id];
                 draw_call.set_zbias(*zbias);
                 draw_call.set_local_scroll(scroll, local_scroll);
                 draw_call.set_clip(clip);
                 *zbias += zbias_step;
-                
-                if draw_call.uniforms_dirty {
-                    draw_call.uniforms_dirty = false;
+                let instance_dirty = draw_call.instance_dirty;
+                self.draw_batcher.items.push(DrawItem {view_id, draw_call_id, instance_dirty});
+            }
+        } 
+    }
+    
+    // draws the items render_view collected, merging batches into a single instanced draw
+    pub fn opengl_draw_batches(&mut self, pass_id: usize, opengl_cx: &OpenglCx) {
+        self.compute_draw_batches();
+        let batches = std::mem::take(&mut self.draw_batcher.batches);
+        for batch in &batches {
+            self.opengl_draw_batch(pass_id, batch.clone(), opengl_cx);
+        }
+        self.draw_batcher.batches = batches;
+    }
+    
+    fn opengl_draw_batch(&mut self, pass_id: usize, batch: Range<usize>, opengl_cx: &OpenglCx) {
+        for item in &self.draw_batcher.items[batch.clone()] {
+            let draw_call = &mut self.views[item.view_id].draw_calls[item.draw_call_id];
+            if draw_call.instance_dirty {
+                draw_call.instance_dirty = false;
+                draw_call.platform.inst_vbuf.update_with_f32_data(opengl_cx, &draw_call.instance);
+            }
+            draw_call.uniforms_dirty = false;
+        }
+        
+        let first = self.draw_batcher.items[batch.start];
+        let is_batch = batch.len() > 1;
+        let batch_dirty = is_batch && {
+            let items = &self.draw_batcher.items[batch.clone()];
+            let batch_items = &self.views[first.view_id].draw_calls[first.draw_call_id].platform.batch_items;
+            batch_items.len() != items.len() || items.iter().zip(batch_items).any( | (item, (view_id, draw_call_id)) | {
+                item.instance_dirty || item.view_id != *view_id || item.draw_call_id != *draw_call_id
+            })
+        };
+        if batch_dirty {
+            self.gather_batch_instances(batch.clone());
+        }
+        let instance_len: usize = self.draw_batcher.items[batch.clone()].iter().map( | item | {
+            self.views[item.view_id].draw_calls[item.draw_call_id].instance.len()
+        }).sum();
+        
+        let cxview = &mut self.views[first.view_id];
+        let draw_call = &mut cxview.draw_calls[first.draw_call_id];
+        let sh = &self.shaders[draw_call.shader_id];
+        let shp = sh.platform.as_ref().unwrap();
+        
+        let vao = if is_batch {
+            if batch_dirty {
+                draw_call.platform.batch_vbuf.update_with_f32_data(opengl_cx, &self.draw_batcher.instances);
+                draw_call.platform.batch_items = self.draw_batcher.items[batch.clone()].iter().map( | item | (item.view_id, item.draw_call_id)).collect();
+            }
+            draw_call.platform.check_batch_vao(draw_call.shader_id, shp);
+            draw_call.platform.batch_vao.unwrap()
+        }
+        else {
+            draw_call.platform.check_vao(draw_call.shader_id, shp);
+            draw_call.platform.vao.unwrap()
+        };
+        
+        Self::set_pipeline_state(&sh.pipeline);
+        
+        unsafe {
+            gl::UseProgram(shp.program);
+            gl::BindVertexArray(vao);
+            let instances = instance_len / sh.mapping.instance_props.total_slots;
+            let indices = sh.shader_gen.geometry.indices.len();
+            
+            let pass_uniforms = self.passes[pass_id].pass_uniforms.as_slice();
+            let view_uniforms = cxview.view_uniforms.as_slice();
+            let draw_uniforms = draw_call.draw_uniforms.as_slice();
+            
+            opengl_cx.set_uniform_buffer(&shp.pass_uniforms, pass_uniforms);
+            opengl_cx.set_uniform_buffer(&shp.view_uniforms, view_uniforms);
+            opengl_cx.set_uniform_buffer(&shp.draw_uniforms, draw_uniforms);
+            opengl_cx.set_uniform_buffer(&shp.uniforms, &draw_call.uniforms);
+            if let Some(ct) = &sh.mapping.const_table{
+                opengl_cx.set_uniform_array(&shp.const_table_uniform, ct);
+            }
+            // lets set our textures
+            for (i, texture_id) in draw_call.textures_2d.iter().enumerate() {
+                let cxtexture = &mut self.textures[*texture_id as usize];
+                if cxtexture.update_image {
+                    opengl_cx.update_platform_texture_image2d(cxtexture);
                 }
-                
-                unsafe {
-                    gl::UseProgram(shp.program);
-                    gl::BindVertexArray(draw_call.platform.vao.unwrap());
-                    let instances = draw_call.instance.len() / sh.mapping.instance_props.total_slots;
-                    let indices = sh.shader_gen.geometry.indices.len();
-                    
-                    let pass_uniforms = self.passes[pass_id].pass_uniforms.as_slice();
-                    let view_uniforms = cxview.view_uniforms.as_slice();
-                    let draw_uniforms = draw_call.draw_uniforms.as_slice();
-                    
-                    opengl_cx.set_uniform_buffer(&shp.pass_uniforms, pass_uniforms);
-                    opengl_cx.set_uniform_buffer(&shp.view_uniforms, view_uniforms);
-                    opengl_cx.set_uniform_buffer(&shp.draw_uniforms, draw_uniforms);
-                    opengl_cx.set_uniform_buffer(&shp.uniforms, &draw_call.uniforms);
-                    if let Some(ct) = &sh.mapping.const_table{
-                        opengl_cx.set_uniform_array(&shp.const_table_uniform, ct);
-                    }
-                    // lets set our textures
-                    for (i, texture_id) in draw_call.textures_2d.iter().enumerate() {
-                        let cxtexture = &mut self.textures[*texture_id as usize];
-                        if cxtexture.update_image {
-                            opengl_cx.update_platform_texture_image2d(cxtexture);
-                        }
-                        // get the loc
-                        gl::ActiveTexture(gl::TEXTURE0 + i as u32);
-                        if let Some(texture) = cxtexture.platform.gl_texture {
-                            gl::BindTexture(gl::TEXTURE_2D, texture);
-                        }
-                        else {
-                            gl::BindTexture(gl::TEXTURE_2D, 0);
-                        }
-                    }
-                    
-                    gl::DrawElementsInstanced(
-                        gl::TRIANGLES,
-                        indices as i32,
-                        gl::UNSIGNED_INT,
-                        ptr::null(),
-                        instances as i32
-                    );
+                // get the loc
+                gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                let target = OpenglCx::gl_texture_target(&cxtexture.desc);
+                if let Some(texture) = cxtexture.platform.gl_texture {
+                    gl::BindTexture(target, texture);
+                }
+                else {
+                    gl::BindTexture(target, 0);
+                }
+                if let Some(slot) = shp.texture_slots.get(i) {
+                    gl::Uniform1i(slot.loc, i as i32);
                 }
             }
-        } 
+            
+            gl::DrawElementsInstanced(
+                gl::TRIANGLES,
+                indices as i32,
+                gl::UNSIGNED_INT,
+                ptr::null(),
+                instances as i32
+            );
+        }
     }
     
     pub fn calc_dirty_bounds(&mut self, pass_id: usize, view_id: usize, view_bounds: &mut ViewBounds) {
@@ -136,12 +180,88 @@ impl Cx {
     }
     
     pub fn set_default_depth_and_blend_mode() {
+        Self::set_pipeline_state(&PipelineState::default());
+    }
+    
+    pub fn set_pipeline_state(pipeline: &PipelineState) {
+        fn blend_factor(factor: BlendFactor) -> u32 {
+            match factor {
+                BlendFactor::Zero => gl::ZERO,
+                BlendFactor::One => gl::ONE,
+                BlendFactor::SrcColor => gl::SRC_COLOR,
+                BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+                BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+                BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+                BlendFactor::DstColor => gl::DST_COLOR,
+                BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+                BlendFactor::DstAlpha => gl::DST_ALPHA,
+                BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+            }
+        }
+        fn blend_op(op: BlendOp) -> u32 {
+            match op {
+                BlendOp::Add => gl::FUNC_ADD,
+                BlendOp::Subtract => gl::FUNC_SUBTRACT,
+                BlendOp::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+                BlendOp::Min => gl::MIN,
+                BlendOp::Max => gl::MAX,
+            }
+        }
         unsafe {
-            gl::Enable(gl::DEPTH_TEST);
-            gl::DepthFunc(gl::LEQUAL);
-            gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
-            gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
-            gl::Enable(gl::BLEND);
+            let blend = &pipeline.blend;
+            if blend.enabled {
+                gl::Enable(gl::BLEND);
+                gl::BlendEquationSeparate(blend_op(blend.color_op), blend_op(blend.alpha_op));
+                gl::BlendFuncSeparate(
+                    blend_factor(blend.src_color),
+                    blend_factor(blend.dst_color),
+                    blend_factor(blend.src_alpha),
+                    blend_factor(blend.dst_alpha)
+                );
+            }
+            else {
+                gl::Disable(gl::BLEND);
+            }
+            
+            let depth = &pipeline.depth;
+            if depth.test || depth.write {
+                // GL skips depth writes when the test is disabled, so emulate with ALWAYS
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(if !depth.test {gl::ALWAYS} else {match depth.func {
+                    DepthFunc::Never => gl::NEVER,
+                    DepthFunc::Less => gl::LESS,
+                    DepthFunc::Equal => gl::EQUAL,
+                    DepthFunc::LessEqual => gl::LEQUAL,
+                    DepthFunc::Greater => gl::GREATER,
+                    DepthFunc::NotEqual => gl::NOTEQUAL,
+                    DepthFunc::GreaterEqual => gl::GEQUAL,
+                    DepthFunc::Always => gl::ALWAYS,
+                }});
+            }
+            else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+            gl::DepthMask(if depth.write {gl::TRUE} else {gl::FALSE});
+            
+            match pipeline.cull {
+                CullMode::None => gl::Disable(gl::CULL_FACE),
+                CullMode::Front => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::FRONT);
+                },
+                CullMode::Back => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::BACK);
+                }
+            }
+            
+            let mask = &pipeline.color_mask;
+            gl::ColorMask(
+                if mask.r {gl::TRUE} else {gl::FALSE},
+                if mask.g {gl::TRUE} else {gl::FALSE},
+                if mask.b {gl::TRUE} else {gl::FALSE},
+                if mask.a {gl::TRUE} else {gl::FALSE}
+            );
         }
     }
     
@@ -248,28 +368,31 @@ impl Cx {
             ClearDepth::ClearWith(depth) => depth
         };
         
+        // reset the masks before clearing, a previous drawcall might have disabled depth or color writes
+        Self::set_default_depth_and_blend_mode();
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
             gl::ClearDepth(clear_depth);
             gl::ClearColor(clear_color.r, clear_color.g, clear_color.b, clear_color.a);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
-        Self::set_default_depth_and_blend_mode();
         
         let mut zbias = 0.0;
         let zbias_step = self.passes[pass_id].zbias_step;
         
+        self.opengl_begin_pass_timer(pass_id);
+        self.draw_batcher.items.truncate(0);
         self.render_view(
-            pass_id,
             view_id,
             Vec2::default(),
             (Vec2 {x: -50000., y: -50000.}, Vec2 {x: 50000., y: 50000.}),
             full_repaint,
             &view_rect,
-            &opengl_cx,
             &mut zbias,
             zbias_step
         );
+        self.opengl_draw_batches(pass_id, opengl_cx);
+        self.opengl_end_pass_timer(pass_id);
         
         unsafe {
             glx_sys::glXSwapBuffers(opengl_cx.display, window);
@@ -360,6 +483,7 @@ impl Cx {
         unsafe {
             gl::Viewport(0, 0, (pass_size.x * dpi_factor) as i32, (pass_size.y * dpi_factor) as i32);
         }
+        Self::set_default_depth_and_blend_mode();
         if clear_flags != 0 {
             unsafe {
                 gl::ClearDepth(clear_depth);
@@ -368,24 +492,68 @@ impl Cx {
             }
         }
         
-        Self::set_default_depth_and_blend_mode();
-        
         let mut zbias = 0.0;
         let zbias_step = self.passes[pass_id].zbias_step;
         let view_id = self.passes[pass_id].main_view_id.unwrap();
         
+        self.opengl_begin_pass_timer(pass_id);
+        self.draw_batcher.items.truncate(0);
         self.render_view(
-            pass_id,
             view_id,
             Vec2::default(),
             (Vec2 {x: -50000., y: -50000.}, Vec2 {x: 50000., y: 50000.}),
             true,
             &Rect::default(),
-            &opengl_cx,
             &mut zbias,
             zbias_step
         );
-        
+        self.opengl_draw_batches(pass_id, opengl_cx);
+        self.opengl_end_pass_timer(pass_id);
+    }
+    
+    // GPU time per pass via GL_TIME_ELAPSED queries. Results are picked up
+    // a frame or more later so we never stall on the GPU
+    pub fn opengl_begin_pass_timer(&mut self, pass_id: usize) {
+        if !self.profiler.enabled || !gl::BeginQuery::is_loaded() || !gl::GetQueryObjectui64v::is_loaded() {
+            return
+        }
+        let platform = &mut self.passes[pass_id].platform;
+        if platform.gl_timer_query.is_none() {
+            unsafe {
+                let mut gl_query = std::mem::MaybeUninit::uninit();
+                gl::GenQueries(1, gl_query.as_mut_ptr());
+                platform.gl_timer_query = Some(gl_query.assume_init());
+            }
+        }
+        let gl_query = platform.gl_timer_query.unwrap();
+        if platform.gl_timer_pending {
+            let mut available = 0;
+            let mut elapsed = 0;
+            unsafe {
+                gl::GetQueryObjectui64v(gl_query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                if available == 0 {
+                    return
+                }
+                gl::GetQueryObjectui64v(gl_query, gl::QUERY_RESULT, &mut elapsed);
+            }
+            platform.gl_timer_pending = false;
+            self.profile_pass_gpu_time(pass_id, elapsed);
+        }
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, gl_query);
+        }
+        self.passes[pass_id].platform.gl_timer_active = true;
+    }
+    
+    pub fn opengl_end_pass_timer(&mut self, pass_id: usize) {
+        let platform = &mut self.passes[pass_id].platform;
+        if platform.gl_timer_active {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+            platform.gl_timer_active = false;
+            platform.gl_timer_pending = true;
+        }
     }
     
     //let view_id = self.passes[pass_id].main_view_id.unwrap();
@@ -422,12 +590,14 @@ impl Cx {
         unsafe {
             glx_sys::glXMakeCurrent(opengl_cx.display, opengl_cx.hidden_window, opengl_cx.context);
         }
+        let profile_start = self.profile_span_begin();
         for (index, sh) in self.shaders.iter_mut().enumerate() {
             let result = Self::opengl_compile_shader(index, false, sh, opengl_cx, &mut self.shader_inherit_cache);
             if let ShaderCompileResult::Fail{err, ..} = result {
                 panic!("{}", err);
             } 
         }; 
+        self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
     }
     
     
@@ -583,24 +753,24 @@ impl Cx {
             }
         };
         
+        // the blend, depth, cull and color_mask the shader declares, on top of its ShaderGen
+        sh.pipeline = shader_ast.pipeline.apply(sh.shader_gen.pipeline);
+        
         // lets generate the vertexshader
-        let vertex = generate_glsl::generate_vertex_shader(&shader_ast, use_const_table);
-        let fragment = generate_glsl::generate_fragment_shader(&shader_ast, use_const_table);
+        let vertex = generate_glsl::generate_vertex_shader(&shader_ast, use_const_table, GlslVersion::Es300);
+        let fragment = generate_glsl::generate_fragment_shader(&shader_ast, use_const_table, GlslVersion::Es300);
         let mapping = CxShaderMapping::from_shader_gen(&sh.shader_gen, shader_ast.const_table.borrow_mut().take());
     
-        let vertex = format!("
-            #version 100
+        let vertex = format!("#version 300 es
             precision highp float;
             precision highp int;
-            vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
-            {}\0", vertex);
-        let fragment = format!("
-            #version 100
-            #extension GL_OES_standard_derivatives : enable
+            {}
+            {}\0", GLSL_SAMPLE_ES300, vertex);
+        let fragment = format!("#version 300 es
             precision highp float;
             precision highp int;
-            vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
-            {}\0", fragment);
+            {}
+            {}\0", GLSL_SAMPLE_ES300, fragment);
         
         if shader_ast.debug{
             println!("--------------- Vertex shader {} --------------- \n{}\n---------------\n", shader_id, vertex);
@@ -679,6 +849,7 @@ impl Cx {
                 draw_uniforms: Self::opengl_get_uniforms(program, &mapping.draw_uniforms),
                 const_table_uniform: Self::opengl_get_uniform(program, "mpsc_const_table", 1),
                 uniforms: Self::opengl_get_uniforms(program, &mapping.uniforms),
+                texture_slots: Self::opengl_get_texture_slots(program, &mapping.textures),
             });
             sh.mapping = mapping;
             return ShaderCompileResult::Ok{id:shader_id};
@@ -935,6 +1106,35 @@ impl OpenglCx {
         let width = cxtexture.desc.width.unwrap();
         let height = cxtexture.desc.height.unwrap();
         
+        // the last one is the size of the image buffer in bytes
+        let (internal_format, format, ty, data, bytes_per_pixel, data_bytes) = match cxtexture.desc.format {
+            TextureFormat::Default | TextureFormat::ImageBGRA => {
+                (gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE, cxtexture.image_u32.as_ptr() as *const u8, 4, cxtexture.image_u32.len() * 4)
+            },
+            TextureFormat::ImageR8 => {
+                (gl::R8, gl::RED, gl::UNSIGNED_BYTE, cxtexture.image_u8.as_ptr() as *const u8, 1, cxtexture.image_u8.len())
+            },
+            TextureFormat::ImageRG8 => {
+                (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, cxtexture.image_u8.as_ptr() as *const u8, 2, cxtexture.image_u8.len())
+            },
+            TextureFormat::ImageRf32 => {
+                (gl::R32F, gl::RED, gl::FLOAT, cxtexture.image_f32.as_ptr() as *const u8, 4, cxtexture.image_f32.len() * 4)
+            },
+            TextureFormat::ImageRGBAf16 => {
+                (gl::RGBA16F, gl::RGBA, gl::FLOAT, cxtexture.image_f32.as_ptr() as *const u8, 16, cxtexture.image_f32.len() * 4)
+            },
+            _ => {
+                println!("update_platform_texture_image2d unsupported texture format");
+                return;
+            }
+        };
+        
+        // every layer, and all 6 faces of a cube, are read from the one buffer
+        if data_bytes != width * height * cxtexture.desc.layers() * bytes_per_pixel {
+            println!("update_platform_texture_image2d with wrong image buffer size!");
+            return;
+        }
+        
         // allocate new texture if descriptor change
         if cxtexture.platform.alloc_desc != cxtexture.desc {
             
@@ -942,32 +1142,89 @@ impl OpenglCx {
             cxtexture.platform.width = width as u64;
             cxtexture.platform.height = height as u64;
             
-            let gl_texture = match cxtexture.platform.gl_texture {
-                None => {
-                    unsafe {
-                        let mut gl_texture = std::mem::MaybeUninit::uninit();
-                        gl::GenTextures(1, gl_texture.as_mut_ptr());
-                        let gl_texture = gl_texture.assume_init();
-                        cxtexture.platform.gl_texture = Some(gl_texture);
-                        gl_texture
-                    }
+            if cxtexture.platform.gl_texture.is_none() {
+                unsafe {
+                    let mut gl_texture = std::mem::MaybeUninit::uninit();
+                    gl::GenTextures(1, gl_texture.as_mut_ptr());
+                    cxtexture.platform.gl_texture = Some(gl_texture.assume_init());
                 }
-                Some(gl_texture_old) => {
-                    gl_texture_old
+            }
+        }
+        
+        let target = Self::gl_texture_target(&cxtexture.desc);
+        let desc = &cxtexture.desc;
+        
+        unsafe {
+            gl::BindTexture(target, cxtexture.platform.gl_texture.unwrap());
+            // R8 and RG8 rows aren't 4 byte aligned
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            match desc.kind {
+                TextureKind::TextureCube => {
+                    let face_bytes = width * height * bytes_per_pixel;
+                    for face in 0..6 {
+                        gl::TexImage2D(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                            0,
+                            internal_format as i32,
+                            width as i32,
+                            height as i32,
+                            0,
+                            format,
+                            ty,
+                            data.add(face * face_bytes) as *const _
+                        );
+                    }
+                },
+                TextureKind::Texture2DArray | TextureKind::Texture3D => {
+                    gl::TexImage3D(target, 0, internal_format as i32, width as i32, height as i32, desc.layers() as i32, 0, format, ty, data as *const _);
+                },
+                TextureKind::Texture2D => {
+                    gl::TexImage2D(target, 0, internal_format as i32, width as i32, height as i32, 0, format, ty, data as *const _);
                 }
-            };
-            unsafe {
-                gl::BindTexture(gl::TEXTURE_2D, gl_texture);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, width as i32, height as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, cxtexture.image_u32.as_ptr() as *const _);
-                gl::BindTexture(gl::TEXTURE_2D, 0);
             }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            if desc.mipmaps {
+                gl::GenerateMipmap(target);
+            }
+            Self::set_texture_sampler(target, desc);
+            gl::BindTexture(target, 0);
         }
         
         cxtexture.update_image = false;
     }
     
+    pub fn gl_texture_target(desc: &TextureDesc) -> u32 {
+        match desc.kind {
+            TextureKind::Texture2D => gl::TEXTURE_2D,
+            TextureKind::Texture2DArray => gl::TEXTURE_2D_ARRAY,
+            TextureKind::TextureCube => gl::TEXTURE_CUBE_MAP,
+            TextureKind::Texture3D => gl::TEXTURE_3D,
+        }
+    }
+    
+    // applies the sampler of the desc to the currently bound texture
+    pub unsafe fn set_texture_sampler(target: u32, desc: &TextureDesc) {
+        let (min_filter, mag_filter) = match desc.sampler.filter {
+            TextureFilter::Nearest => (gl::NEAREST, gl::NEAREST),
+            TextureFilter::Linear if desc.mipmaps => (gl::LINEAR_MIPMAP_NEAREST, gl::LINEAR),
+            TextureFilter::Linear => (gl::LINEAR, gl::LINEAR),
+            TextureFilter::Trilinear if desc.mipmaps => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+            TextureFilter::Trilinear => (gl::LINEAR, gl::LINEAR),
+        };
+        let wrap = match desc.sampler.wrap {
+            TextureWrap::Clamp => gl::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::Mirror => gl::MIRRORED_REPEAT,
+        };
+        gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+        gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as i32);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as i32);
+        if target == gl::TEXTURE_2D_ARRAY || target == gl::TEXTURE_3D {
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_R, wrap as i32);
+        }
+    }
+    
     pub fn update_platform_render_target(&self, cxtexture: &mut CxTexture, dpi_factor: f32, size: Vec2, is_depth: bool) -> bool {
         let width = if let Some(width) = cxtexture.desc.width {width as u64} else {(size.x * dpi_factor) as u64};
         let height = if let Some(height) = cxtexture.desc.height {height as u64} else {(size.y * dpi_factor) as u64};
@@ -985,8 +1242,8 @@ impl OpenglCx {
             gl::GenTextures(1, gl_texture.as_mut_ptr());
             let gl_texture = gl_texture.assume_init();
             gl::BindTexture(gl::TEXTURE_2D, gl_texture);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            // render targets have no mip chain so trilinear falls back to linear
+            Self::set_texture_sampler(gl::TEXTURE_2D, &TextureDesc {mipmaps: false, ..cxtexture.desc.clone()});
             
             cxtexture.platform.alloc_desc = cxtexture.desc.clone();
             cxtexture.platform.width = width;
@@ -1034,7 +1291,8 @@ pub struct CxPlatformShader {
     pub view_uniforms: Vec<OpenglUniform>,
     pub draw_uniforms: Vec<OpenglUniform>,
     pub const_table_uniform: OpenglUniform,
-    pub uniforms: Vec<OpenglUniform>
+    pub uniforms: Vec<OpenglUniform>,
+    pub texture_slots: Vec<OpenglUniform>,
 }
 
 
@@ -1112,51 +1370,65 @@ pub struct CxPlatformView {
 pub struct CxPlatformDrawCall {
     pub inst_vbuf: OpenglBuffer,
     pub vao_shader_id: Option<usize>,
-    pub vao: Option<u32>
+    pub vao: Option<u32>,
+    pub batch_vbuf: OpenglBuffer,
+    pub batch_items: Vec<(usize, usize)>,
+    pub batch_vao_shader_id: Option<usize>,
+    pub batch_vao: Option<u32>,
 }
 
 impl CxPlatformDrawCall {
     
     pub fn check_vao(&mut self, shader_id: usize, shp: &CxPlatformShader) {
         if self.vao_shader_id.is_none() || self.vao_shader_id.unwrap() != shader_id {
-            self.free_vao();
-            // create the VAO
-            unsafe {
-                let mut vao = std::mem::MaybeUninit::uninit();
-                gl::GenVertexArrays(1, vao.as_mut_ptr());
-                let vao = vao.assume_init();
-                gl::BindVertexArray(vao);
-                
-                // bind the vertex and indexbuffers
-                gl::BindBuffer(gl::ARRAY_BUFFER, shp.geom_vbuf.gl_buffer.unwrap());
-                for attr in &shp.geometries {
-                    gl::VertexAttribPointer(attr.loc, attr.size, gl::FLOAT, 0, attr.stride, attr.offset as *const () as *const _);
-                    gl::EnableVertexAttribArray(attr.loc);
-                }
-                
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.inst_vbuf.gl_buffer.unwrap());
-                
-                for attr in &shp.instances {
-                    gl::VertexAttribPointer(attr.loc, attr.size, gl::FLOAT, 0, attr.stride, attr.offset as *const () as *const _);
-                    gl::EnableVertexAttribArray(attr.loc);
-                    gl::VertexAttribDivisor(attr.loc, 1 as gl::types::GLuint);
-                }
-                
-                // bind the indexbuffer
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, shp.geom_ibuf.gl_buffer.unwrap());
-                gl::BindVertexArray(0);
-                
-                self.vao_shader_id = Some(shader_id);
-                self.vao = Some(vao);
+            Self::free_vao(&mut self.vao);
+            self.vao = Some(Self::create_vao(shp, self.inst_vbuf.gl_buffer.unwrap()));
+            self.vao_shader_id = Some(shader_id);
+        }
+    }
+    
+    pub fn check_batch_vao(&mut self, shader_id: usize, shp: &CxPlatformShader) {
+        if self.batch_vao_shader_id.is_none() || self.batch_vao_shader_id.unwrap() != shader_id {
+            Self::free_vao(&mut self.batch_vao);
+            self.batch_vao = Some(Self::create_vao(shp, self.batch_vbuf.gl_buffer.unwrap()));
+            self.batch_vao_shader_id = Some(shader_id);
+        }
+    }
+    
+    fn create_vao(shp: &CxPlatformShader, inst_buffer: u32) -> u32 {
+        unsafe {
+            let mut vao = std::mem::MaybeUninit::uninit();
+            gl::GenVertexArrays(1, vao.as_mut_ptr());
+            let vao = vao.assume_init();
+            gl::BindVertexArray(vao);
+            
+            // bind the vertex and indexbuffers
+            gl::BindBuffer(gl::ARRAY_BUFFER, shp.geom_vbuf.gl_buffer.unwrap());
+            for attr in &shp.geometries {
+                gl::VertexAttribPointer(attr.loc, attr.size, gl::FLOAT, 0, attr.stride, attr.offset as *const () as *const _);
+                gl::EnableVertexAttribArray(attr.loc);
             }
+            
+            gl::BindBuffer(gl::ARRAY_BUFFER, inst_buffer);
+            
+            for attr in &shp.instances {
+                gl::VertexAttribPointer(attr.loc, attr.size, gl::FLOAT, 0, attr.stride, attr.offset as *const () as *const _);
+                gl::EnableVertexAttribArray(attr.loc);
+                gl::VertexAttribDivisor(attr.loc, 1 as gl::types::GLuint);
+            }
+            
+            // bind the indexbuffer
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, shp.geom_ibuf.gl_buffer.unwrap());
+            gl::BindVertexArray(0);
+            vao
         }
     }
     
-    fn free_vao(&mut self) {
+    fn free_vao(vao: &mut Option<u32>) {
         unsafe {
-            if let Some(mut vao) = self.vao {
-                gl::DeleteVertexArrays(1, &mut vao);
-                self.vao = None;
+            if let Some(mut gl_vao) = *vao {
+                gl::DeleteVertexArrays(1, &mut gl_vao);
+                *vao = None;
             }
         }
     }
@@ -1172,7 +1444,10 @@ pub struct CxPlatformTexture {
 
 #[derive(Default, Clone)]
 pub struct CxPlatformPass {
-    pub gl_framebuffer: Option<u32>
+    pub gl_framebuffer: Option<u32>,
+    pub gl_timer_query: Option<u32>,
+    pub gl_timer_pending: bool,
+    pub gl_timer_active: bool,
 }
 
 #[derive(Default, Clone)]
@@ -6,6 +6,7 @@ use makepad_shader_compiler::ty::Ty;
 
 extern "C" {
     fn _log_str(chars:u32, len:u32);
+    fn _performance_now() -> f64;
 }
 
 pub fn log_str(val:&str){
@@ -21,6 +22,10 @@ impl Cx {
         return self.platform.window_geom.inner_size;
     }
     
+    pub fn profile_time_ns(&self) -> u64 {
+        unsafe {(_performance_now() * 1_000_000.0) as u64}
+    }
+    
     // incoming to_wasm. There is absolutely no other entrypoint
     // to general rust codeflow than this function. Only the allocators and init
     pub fn process_to_wasm<F>(&mut self, msg: u32, mut event_handler: F) -> u32
@@ -484,6 +489,7 @@ impl Cx {
         
         if is_animation_frame {
             if passes_todo.len() > 0 {
+                let profile_start = self.profile_span_begin();
                 for pass_id in &passes_todo {
                     match self.passes[*pass_id].dep_of.clone() {
                         CxPassDepOf::Window(_) => {
@@ -502,6 +508,8 @@ impl Cx {
                         }
                     }
                 }
+                self.profile_span_end(ProfileSpan::Repaint, profile_start);
+                self.profile_end_frame();
             }
         }
         // free the received message
@@ -515,11 +523,13 @@ impl Cx {
         
         // lets check our recompile queue
         if !is_animation_frame {
+            let profile_start = self.profile_span_begin();
             let mut shader_results = Vec::new();
             for shader_id in &self.shader_recompiles {
                 shader_results.push(Self::webgl_compile_shader(*shader_id, !self.platform.gpu_spec_is_low_on_uniforms, true, &mut self.shaders[*shader_id], &mut self.platform, &mut self.shader_inherit_cache));
             }
             self.shader_recompiles.truncate(0);
+            self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
             self.call_shader_recompile_event(shader_results, &mut event_handler);
         }
         // mark the end of the message
@@ -552,8 +562,10 @@ impl Cx {
         FileRead {read_id: id, path: path.to_string()}
     }
     
-    pub fn file_write(&mut self, _path: &str, _data: &[u8]) -> u64 {
-        return 0
+    // the page keeps it, so a hot reload of the wasm module reads it back
+    pub fn file_write(&mut self, path: &str, data: &[u8]) -> u64 {
+        self.platform.from_wasm.write_file(path, data);
+        0
     }
     
     pub fn set_window_outer_size(&mut self, _size: Vec2) {
@@ -938,6 +950,9 @@ impl FromWasm {
                 Ty::Float => "float",
                 Ty::Mat4 => "mat4",
                 Ty::Texture2D => "sampler2D",
+                Ty::Texture2DArray => "sampler2DArray",
+                Ty::TextureCube => "samplerCube",
+                Ty::Texture3D => "sampler3D",
                 _ => panic!("unexpected type in add_propdefvec")
             });
             self.add_string(&shvar.name);
@@ -951,7 +966,7 @@ impl FromWasm {
         self.add_string(msg);
     }
     
-    pub fn compile_webgl_shader(&mut self, shader_id: usize, vertex: &str, fragment: &str, mapping: &CxShaderMapping) {
+    pub fn compile_webgl_shader(&mut self, shader_id: usize, vertex: &str, fragment: &str, mapping: &CxShaderMapping, pipeline: &PipelineState) {
         self.fit(2);
         self.mu32(2);
         self.mu32(shader_id as u32);
@@ -965,6 +980,32 @@ impl FromWasm {
         self.add_propdefvec(&mapping.draw_uniforms);
         self.add_propdefvec(&mapping.uniforms);
         self.add_propdefvec(&mapping.textures);
+        self.add_pipeline_state(pipeline);
+    }
+    
+    // the enum values index the lookup tables in cx_webgl.js
+    fn add_pipeline_state(&mut self, pipeline: &PipelineState) {
+        let blend = &pipeline.blend;
+        let depth = &pipeline.depth;
+        let mask = &pipeline.color_mask;
+        self.fit(12);
+        self.mu32(if blend.enabled {1} else {0});
+        self.mu32(blend.src_color as u32);
+        self.mu32(blend.dst_color as u32);
+        self.mu32(blend.color_op as u32);
+        self.mu32(blend.src_alpha as u32);
+        self.mu32(blend.dst_alpha as u32);
+        self.mu32(blend.alpha_op as u32);
+        self.mu32(if depth.test {1} else {0});
+        self.mu32(if depth.write {1} else {0});
+        self.mu32(depth.func as u32);
+        self.mu32(pipeline.cull as u32);
+        self.mu32(
+            if mask.r {1} else {0}
+            | if mask.g {2} else {0}
+            | if mask.b {4} else {0}
+            | if mask.a {8} else {0}
+        );
     }
     
     pub fn alloc_array_buffer(&mut self, buffer_id: usize, len: usize, data: *const f32) {
@@ -1043,13 +1084,30 @@ impl FromWasm {
     }
     
     pub fn update_texture_image2d(&mut self, texture_id: usize, texture: &mut CxTexture) {
-        //usize, width: usize, height: usize, data: &Vec<u32>
-        self.fit(5);
+        //usize, width: usize, height: usize, data: ptr, format, filter, wrap, mipmaps, kind, layers
+        self.fit(11);
         self.mu32(9);
         self.mu32(texture_id as u32);
         self.mu32(texture.desc.width.unwrap() as u32);
         self.mu32(texture.desc.height.unwrap() as u32);
-        self.mu32(texture.image_u32.as_ptr() as u32)
+        let (format, data_ptr) = match texture.desc.format {
+            TextureFormat::ImageR8 => (1, texture.image_u8.as_ptr() as u32),
+            TextureFormat::ImageRG8 => (2, texture.image_u8.as_ptr() as u32),
+            TextureFormat::ImageRf32 => (3, texture.image_f32.as_ptr() as u32),
+            TextureFormat::ImageRGBAf16 => (4, texture.image_f32.as_ptr() as u32),
+            _ => (0, texture.image_u32.as_ptr() as u32)
+        };
+        self.mu32(data_ptr);
+        self.mu32(format);
+        self.add_texture_sampler(&texture.desc.sampler);
+        self.mu32(if texture.desc.mipmaps {1} else {0});
+        self.mu32(texture.desc.kind as u32);
+        self.mu32(texture.desc.layers() as u32);
+    }
+    
+    fn add_texture_sampler(&mut self, sampler: &TextureSampler) {
+        self.mu32(sampler.filter as u32);
+        self.mu32(sampler.wrap as u32);
     }
     
     pub fn request_animation_frame(&mut self) {
@@ -1154,8 +1212,8 @@ impl FromWasm {
         self.mu32(height as u32);
     }
     
-    pub fn add_color_target(&mut self, texture_id: usize, init_only: bool, color: Color) {
-        self.fit(7);
+    pub fn add_color_target(&mut self, texture_id: usize, init_only: bool, color: Color, sampler: &TextureSampler) {
+        self.fit(9);
         self.mu32(22);
         self.mu32(texture_id as u32);
         self.mu32(if init_only {1} else {0});
@@ -1163,6 +1221,7 @@ impl FromWasm {
         self.mf32(color.g);
         self.mf32(color.b);
         self.mf32(color.a);
+        self.add_texture_sampler(sampler);
     }
     
     pub fn set_depth_target(&mut self, texture_id: usize, init_only: bool, depth: f32) {
@@ -1245,6 +1304,13 @@ impl FromWasm {
         self.fit(1);
         self.mu32(29);
     }
+    
+    pub fn write_file(&mut self, path: &str, data: &[u8]) {
+        self.fit(1);
+        self.mu32(30);
+        self.add_string(path);
+        self.add_u8slice(data);
+    }
 }
 
 #[derive(Clone)]
@@ -305,9 +305,6 @@
     class WasmApp {
         constructor(canvas, webasm) {
             this.canvas = canvas;
-            this.webasm = webasm;
-            this.exports = webasm.instance.exports;
-            this.memory = webasm.instance.exports.memory
             
             // local webgl resources
             this.shaders = [];
@@ -334,6 +331,15 @@
                 this.to_wasm.window_focus(false);
                 this.do_wasm_io();
             })
+            
+            this.start_app(webasm);
+        }
+        
+        start_app(webasm) {
+            this.webasm = webasm;
+            this.exports = webasm.instance.exports;
+            this.memory = webasm.instance.exports.memory
+            
             // lets create the wasm app and cx
             this.app = this.exports.create_wasm_app();
             
@@ -345,6 +351,8 @@
                 gpu_spec_is_low_on_uniforms: this.gpu_spec_is_low_on_uniforms
             });
             
+            // a hot reload blocks the old module, the new one has to get its load_deps out
+            this.do_wasm_block = false;
             this.do_wasm_io();
             
             this.do_wasm_block = true;
@@ -387,6 +395,43 @@
             })
         }
         
+        // swaps in a new build of the wasm module. the canvas, the gl context and the event bindings stay,
+        // everything the old module made goes. what it wrote with file_write is still in file_store,
+        // so the new app reads its state back as it starts
+        hot_reload(webasm) {
+            this.do_wasm_block = true;
+            if (this.req_anim_frame_id) {
+                window.cancelAnimationFrame(this.req_anim_frame_id);
+                this.req_anim_frame_id = 0;
+            }
+            for (let i = 0; i < this.timers.length; i ++) {
+                let timer = this.timers[i];
+                if (timer.repeats) {
+                    window.clearInterval(timer.sys_id);
+                }
+                else {
+                    window.clearTimeout(timer.sys_id);
+                }
+            }
+            let gl = this.gl;
+            this.shaders.forEach(shader => shader && gl.deleteProgram(shader.program));
+            this.vaos.forEach(vao => vao && gl.OES_vertex_array_object.deleteVertexArrayOES(vao));
+            this.array_buffers.forEach(buf => buf && gl.deleteBuffer(buf));
+            this.index_buffers.forEach(buf => buf && gl.deleteBuffer(buf));
+            this.textures.forEach(tex => tex && gl.deleteTexture(tex));
+            this.framebuffers.forEach(fb => fb && gl.deleteFramebuffer(fb));
+            this.shaders = [];
+            this.index_buffers = [];
+            this.array_buffers = [];
+            this.timers = [];
+            this.vaos = [];
+            this.textures = [];
+            this.framebuffers = [];
+            this.resources = [];
+            this.to_wasm = undefined;
+            this.start_app(webasm);
+        }
+        
         do_wasm_io() {
             
             if (this.do_wasm_block) {
@@ -463,7 +508,6 @@
                 data[u8_pos + 1] = (u32 >> 8) & 0xff;
                 data[u8_pos + 2] = (u32 >> 16) & 0xff;
             }
-            console.log(data);
             return data
         }
         
@@ -484,6 +528,23 @@
             }
             return vars
         }
+        
+        parse_pipeline_state() {
+            return {
+                blend_enabled: this.mu32[this.parse ++],
+                src_color: this.mu32[this.parse ++],
+                dst_color: this.mu32[this.parse ++],
+                color_op: this.mu32[this.parse ++],
+                src_alpha: this.mu32[this.parse ++],
+                dst_alpha: this.mu32[this.parse ++],
+                alpha_op: this.mu32[this.parse ++],
+                depth_test: this.mu32[this.parse ++],
+                depth_write: this.mu32[this.parse ++],
+                depth_func: this.mu32[this.parse ++],
+                cull: this.mu32[this.parse ++],
+                color_mask: this.mu32[this.parse ++]
+            }
+        }
         // i forgot how to do memcpy with typed arrays. so, we'll do this.
         copy_to_wasm(input_buffer, output_ptr) {
             let u8len = input_buffer.byteLength;
@@ -1047,8 +1108,9 @@
         }
         
         read_file(id, file_path) {
-            
-            fetch_path(file_path).then(result => {
+            let stored = file_store[file_path];
+            let read = stored? Promise.resolve({name: file_path, buffer: stored.buffer}): fetch_path(file_path);
+            read.then(result => {
                 let byte_len = result.buffer.byteLength
                 let output_ptr = this.exports.alloc_wasm_vec(byte_len);
                 this.copy_to_wasm(result.buffer, output_ptr);
@@ -1060,6 +1122,10 @@
             })
         }
         
+        write_file(file_path, data) {
+            file_store[file_path] = data;
+        }
+        
         start_timer(id, interval, repeats) {
             for (let i = 0; i < this.timers.length; i ++) {
                 if (this.timers[i].id == id) {
@@ -1292,11 +1358,11 @@
             }
             
             
-            //gl.EXT_blend_minmax = gl.getExtension('EXT_blend_minmax')
-            //gl.OES_texture_half_float_linear = gl.getExtension('OES_texture_half_float_linear')
-            //gl.OES_texture_float_linear = gl.getExtension('OES_texture_float_linear')
-            //gl.OES_texture_half_float = gl.getExtension('OES_texture_half_float')
-            //gl.OES_texture_float = gl.getExtension('OES_texture_float')
+            gl.EXT_blend_minmax = gl.getExtension('EXT_blend_minmax')
+            gl.OES_texture_half_float_linear = gl.getExtension('OES_texture_half_float_linear')
+            gl.OES_texture_float_linear = gl.getExtension('OES_texture_float_linear')
+            gl.OES_texture_half_float = gl.getExtension('OES_texture_half_float')
+            gl.OES_texture_float = gl.getExtension('OES_texture_float')
             //gl.WEBGL_depth_texture = gl.getExtension("WEBGL_depth_texture") || gl.getExtension("WEBKIT_WEBGL_depth_texture")
             this.on_screen_resize()
         }
@@ -1476,6 +1542,8 @@
                 gl.viewport(0, 0, this.canvas.width, this.canvas.height);
             }
             
+            // a previous drawcall might have masked off depth or color writes
+            this.set_default_depth_and_blend_mode();
             gl.clearColor(r, g, b, a);
             gl.clearDepth(depth);
             gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT);
@@ -1493,7 +1561,7 @@
             gl.bindFramebuffer(gl.FRAMEBUFFER, gl_framebuffer);
         }
         
-        add_color_target(texture_id, init_only, r, g, b, a) {
+        add_color_target(texture_id, init_only, r, g, b, a, filter, wrap) {
             // if use_default
             this.clear_r = r;
             this.clear_g = g;
@@ -1510,10 +1578,6 @@
                 
                 gl_tex.mp_width = this.target_width
                 gl_tex.mp_height = this.target_height
-                gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR)
-                gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR)
-                gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE)
-                gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE)
                 
                 gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, gl_tex.mp_width, gl_tex.mp_height, 0, gl.RGBA, gl.UNSIGNED_BYTE, null);
             }
@@ -1521,6 +1585,11 @@
                 this.clear_flags = gl.COLOR_BUFFER_BIT;
             }
             
+            if (gl_tex.mp_filter !== filter || gl_tex.mp_wrap !== wrap) {
+                gl.bindTexture(gl.TEXTURE_2D, gl_tex)
+                this.set_texture_sampler(gl.TEXTURE_2D, gl_tex, filter, wrap, false, gl_tex.mp_width, gl_tex.mp_height)
+            }
+            
             gl.framebufferTexture2D(gl.FRAMEBUFFER, gl.COLOR_ATTACHMENT0, gl.TEXTURE_2D, gl_tex, 0)
             this.color_targets += 1;
         }
@@ -1539,6 +1608,7 @@
             // check if we need to clear color, and depth
             // clear it
             if (this.clear_flags) {
+                this.set_default_depth_and_blend_mode();
                 gl.clearColor(this.clear_r, this.clear_g, this.clear_b, this.clear_a);
                 gl.clearDepth(this.clear_depth);
                 gl.clear(this.clear_flags);
@@ -1549,9 +1619,63 @@
             let gl = this.gl
             gl.enable(gl.DEPTH_TEST);
             gl.depthFunc(gl.LEQUAL);
+            gl.depthMask(true);
             gl.blendEquationSeparate(gl.FUNC_ADD, gl.FUNC_ADD);
             gl.blendFuncSeparate(gl.ONE, gl.ONE_MINUS_SRC_ALPHA, gl.ONE, gl.ONE_MINUS_SRC_ALPHA);
             gl.enable(gl.BLEND);
+            gl.disable(gl.CULL_FACE);
+            gl.colorMask(true, true, true, true);
+        }
+        
+        set_pipeline_state(ps) {
+            let gl = this.gl
+            if (ps.blend_enabled) {
+                let factors = [
+                    gl.ZERO,
+                    gl.ONE,
+                    gl.SRC_COLOR,
+                    gl.ONE_MINUS_SRC_COLOR,
+                    gl.SRC_ALPHA,
+                    gl.ONE_MINUS_SRC_ALPHA,
+                    gl.DST_COLOR,
+                    gl.ONE_MINUS_DST_COLOR,
+                    gl.DST_ALPHA,
+                    gl.ONE_MINUS_DST_ALPHA
+                ];
+                let minmax = gl.EXT_blend_minmax;
+                let ops = [
+                    gl.FUNC_ADD,
+                    gl.FUNC_SUBTRACT,
+                    gl.FUNC_REVERSE_SUBTRACT,
+                    minmax? minmax.MIN_EXT: gl.FUNC_ADD,
+                    minmax? minmax.MAX_EXT: gl.FUNC_ADD
+                ];
+                gl.enable(gl.BLEND);
+                gl.blendEquationSeparate(ops[ps.color_op], ops[ps.alpha_op]);
+                gl.blendFuncSeparate(factors[ps.src_color], factors[ps.dst_color], factors[ps.src_alpha], factors[ps.dst_alpha]);
+            }
+            else {
+                gl.disable(gl.BLEND);
+            }
+            if (ps.depth_test || ps.depth_write) {
+                // depth writes need the depth test enabled, so emulate with ALWAYS
+                let funcs = [gl.NEVER, gl.LESS, gl.EQUAL, gl.LEQUAL, gl.GREATER, gl.NOTEQUAL, gl.GEQUAL, gl.ALWAYS];
+                gl.enable(gl.DEPTH_TEST);
+                gl.depthFunc(ps.depth_test? funcs[ps.depth_func]: gl.ALWAYS);
+            }
+            else {
+                gl.disable(gl.DEPTH_TEST);
+            }
+            gl.depthMask(ps.depth_write != 0);
+            if (ps.cull == 0) {
+                gl.disable(gl.CULL_FACE);
+            }
+            else {
+                gl.enable(gl.CULL_FACE);
+                gl.cullFace(ps.cull == 1? gl.FRONT: gl.BACK);
+            }
+            let mask = ps.color_mask;
+            gl.colorMask((mask & 1) != 0, (mask & 2) != 0, (mask & 4) != 0, (mask & 8) != 0);
         }
         
         // new shader helpers
@@ -1641,7 +1765,9 @@
                 draw_uniforms: this.get_uniform_locations(program, ash.draw_uniforms),
                 uniforms: this.get_uniform_locations(program, ash.uniforms),
                 texture_slots: this.get_uniform_locations(program, ash.texture_slots),
+                texture_depths: ash.texture_slots.map(slot => gl.getUniformLocation(program, "mpsc_" + slot.name + "_depth")),
                 instance_slots: ash.instance_slots,
+                pipeline_state: ash.pipeline_state,
                 const_table_uniform: gl.getUniformLocation(program, "mpsc_const_table"),
                 program: program,
                 ash: ash
@@ -1666,22 +1792,115 @@
             this.index_buffers[index_buffer_id] = gl_buf;
         }
         
-        alloc_texture(texture_id, width, height, data_ptr) {
+        // kind: 0 2d, 1 2d array, 2 cube, 3 3d
+        alloc_texture(texture_id, width, height, data_ptr, format, filter, wrap, mipmaps, kind, layers) {
             var gl = this.gl;
             var gl_tex = this.textures[texture_id] || gl.createTexture()
+            gl_tex.mp_kind = kind;
+            gl_tex.mp_layers = layers;
+            gl_tex.mp_layer_height = height;
+            
+            let target = kind == 2? gl.TEXTURE_CUBE_MAP: gl.TEXTURE_2D;
+            gl.bindTexture(target, gl_tex)
+            
+            // webgl1 has no R8/RG8/R32F so those are expanded to rgba,
+            // this keeps the unused channels reading 0 like the other platforms
+            let pixels = width * height * layers;
+            let is_float = false;
+            let data;
+            switch (format) {
+                case 1: { // ImageR8
+                    let src = new Uint8Array(this.memory.buffer, data_ptr, pixels);
+                    data = new Uint8Array(pixels * 4);
+                    for (let i = 0; i < pixels; i ++) {
+                        data[i * 4] = src[i];
+                        data[i * 4 + 3] = 255;
+                    }
+                    break;
+                }
+                case 2: { // ImageRG8
+                    let src = new Uint8Array(this.memory.buffer, data_ptr, pixels * 2);
+                    data = new Uint8Array(pixels * 4);
+                    for (let i = 0; i < pixels; i ++) {
+                        data[i * 4] = src[i * 2];
+                        data[i * 4 + 1] = src[i * 2 + 1];
+                        data[i * 4 + 3] = 255;
+                    }
+                    break;
+                }
+                case 3: { // ImageRf32
+                    let src = new Float32Array(this.memory.buffer, data_ptr, pixels);
+                    data = new Float32Array(pixels * 4);
+                    for (let i = 0; i < pixels; i ++) {
+                        data[i * 4] = src[i];
+                        data[i * 4 + 3] = 1.0;
+                    }
+                    is_float = true;
+                    break;
+                }
+                case 4: { // ImageRGBAf16
+                    let src = new Float32Array(this.memory.buffer, data_ptr, pixels * 4);
+                    if (gl.OES_texture_half_float) {
+                        data = new Uint16Array(pixels * 4);
+                        for (let i = 0; i < pixels * 4; i ++) {
+                            data[i] = f32_to_f16(src[i]);
+                        }
+                    }
+                    else {
+                        data = new Float32Array(src);
+                    }
+                    is_float = true;
+                    break;
+                }
+                default: { // ImageBGRA
+                    data = new Uint8Array(this.memory.buffer, data_ptr, pixels * 4);
+                }
+            }
             
-            gl.bindTexture(gl.TEXTURE_2D, gl_tex)
-            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR)
-            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR)
-            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE)
-            gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE)
+            let ty = format == 4 && gl.OES_texture_half_float? gl.OES_texture_half_float.HALF_FLOAT_OES:
+                is_float? gl.FLOAT: gl.UNSIGNED_BYTE;
+            if (is_float && !gl.OES_texture_float) {
+                console.log("alloc_texture: float textures not supported")
+            }
+            if (kind == 2) {
+                let face_len = width * height * 4;
+                for (let face = 0; face < 6; face ++) {
+                    let face_data = data.subarray(face * face_len, (face + 1) * face_len);
+                    gl.texImage2D(gl.TEXTURE_CUBE_MAP_POSITIVE_X + face, 0, gl.RGBA, width, height, 0, gl.RGBA, ty, face_data);
+                }
+            }
+            else {
+                // arrays and 3d textures are stacked vertically, see GLSL_SAMPLE_LAYERED
+                gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, width, height * layers, 0, gl.RGBA, ty, data);
+            }
             
-            let data = new Uint8Array(this.memory.buffer, data_ptr, width * height * 4);
-            gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, width, height, 0, gl.RGBA, gl.UNSIGNED_BYTE, data);
+            // webgl1 can only build mipmaps for power of two byte textures, and stacked layers would bleed
+            let can_mip = mipmaps && !is_float && (kind == 0 || kind == 2) && is_pow2(width) && is_pow2(height);
+            if (can_mip) {
+                gl.generateMipmap(target);
+            }
+            let linear_ok = !is_float || (format == 4 && gl.OES_texture_half_float?
+                gl.OES_texture_half_float_linear: gl.OES_texture_float_linear);
+            this.set_texture_sampler(target, gl_tex, linear_ok? filter: 0, wrap, can_mip, width, kind == 2? height: height * layers);
             //gl.bindTexture(gl.TEXTURE_2D,0);
             this.textures[texture_id] = gl_tex;
         }
         
+        // filter: 0 nearest, 1 linear, 2 trilinear, wrap: 0 clamp, 1 repeat, 2 mirror
+        set_texture_sampler(target, gl_tex, filter, wrap, has_mips, width, height) {
+            var gl = this.gl;
+            gl_tex.mp_filter = filter;
+            gl_tex.mp_wrap = wrap;
+            let min_filter = filter == 0? gl.NEAREST: has_mips? (filter == 2? gl.LINEAR_MIPMAP_LINEAR: gl.LINEAR_MIPMAP_NEAREST): gl.LINEAR;
+            gl.texParameteri(target, gl.TEXTURE_MAG_FILTER, filter == 0? gl.NEAREST: gl.LINEAR)
+            gl.texParameteri(target, gl.TEXTURE_MIN_FILTER, min_filter)
+            // webgl1 only allows clamp on non power of two textures
+            let gl_wrap = !is_pow2(width) || !is_pow2(height)? gl.CLAMP_TO_EDGE:
+                wrap == 1? gl.REPEAT: wrap == 2? gl.MIRRORED_REPEAT: gl.CLAMP_TO_EDGE;
+            gl.texParameteri(target, gl.TEXTURE_WRAP_S, gl_wrap)
+            gl.texParameteri(target, gl.TEXTURE_WRAP_T, gl_wrap)
+        }
+        
         alloc_vao(shader_id, vao_id, geom_ib_id, geom_vb_id, inst_vb_id) {
             let gl = this.gl;
             
@@ -1742,6 +1961,7 @@
             var gl = this.gl;
             
             let shader = this.shaders[shader_id];
+            this.set_pipeline_state(shader.pipeline_state);
             gl.useProgram(shader.program);
             
             let vao = this.vaos[vao_id];
@@ -1778,8 +1998,12 @@
                 let tex_id = this.baseu32[(textures_ptr >> 2) + i];
                 let tex_obj = this.textures[tex_id];
                 gl.activeTexture(gl.TEXTURE0 + i);
-                gl.bindTexture(gl.TEXTURE_2D, tex_obj);
+                gl.bindTexture(tex_obj && tex_obj.mp_kind == 2? gl.TEXTURE_CUBE_MAP: gl.TEXTURE_2D, tex_obj);
                 gl.uniform1i(tex_slot.loc, i);
+                let depth_loc = shader.texture_depths[i];
+                if (depth_loc) {
+                    gl.uniform2f(depth_loc, tex_obj? tex_obj.mp_layers: 1, tex_obj? tex_obj.mp_layer_height: 1);
+                }
             }
             let indices = index_buffer.length;
             let instances = instance_buffer.length / shader.instance_slots;
@@ -1840,7 +2064,8 @@
                 view_uniforms: self.parse_shvarvec(),
                 draw_uniforms: self.parse_shvarvec(),
                 uniforms: self.parse_shvarvec(),
-                texture_slots: self.parse_shvarvec()
+                texture_slots: self.parse_shvarvec(),
+                pipeline_state: self.parse_pipeline_state()
             }
             self.compile_webgl_shader(ash);
         },
@@ -1908,7 +2133,13 @@
             let width = self.mu32[self.parse ++];
             let height = self.mu32[self.parse ++];
             let data_ptr = self.mu32[self.parse ++];
-            self.alloc_texture(texture_id, width, height, data_ptr);
+            let format = self.mu32[self.parse ++];
+            let filter = self.mu32[self.parse ++];
+            let wrap = self.mu32[self.parse ++];
+            let mipmaps = self.mu32[self.parse ++];
+            let kind = self.mu32[self.parse ++];
+            let layers = self.mu32[self.parse ++];
+            self.alloc_texture(texture_id, width, height, data_ptr, format, filter, wrap, mipmaps, kind, layers);
         },
         function request_animation_frame_10(self) {
             self.request_animation_frame()
@@ -1960,7 +2191,9 @@
             let g = self.mf32[self.parse ++];
             let b = self.mf32[self.parse ++];
             let a = self.mf32[self.parse ++];
-            self.add_color_target(texture_id, init_only, r, g, b, a)
+            let filter = self.mu32[self.parse ++];
+            let wrap = self.mu32[self.parse ++];
+            self.add_color_target(texture_id, init_only, r, g, b, a, filter, wrap)
         },
         function set_depth_target_23(self) {
             let texture_id = self.mu32[self.parse ++];
@@ -1999,7 +2232,12 @@
         },
         function normalscreen_29(self) {
             self.normalscreen();
-        }, 
+        },
+        function write_file_30(self) {
+            let path = self.parse_string();
+            let data = self.parse_u8slice();
+            self.write_file(path, data);
+        },
     ]
     
     WasmApp.prototype.uniform_fn_table = {
@@ -2058,6 +2296,29 @@
         return (e.shiftKey? 1: 0) | (e.ctrlKey? 2: 0) | (e.altKey? 4: 0) | (e.metaKey? 8: 0)
     }
     
+    function is_pow2(v) {
+        return v > 0 && (v & (v - 1)) == 0
+    }
+    
+    var f32_to_f16_buf = new Float32Array(1);
+    var f32_to_f16_bits = new Uint32Array(f32_to_f16_buf.buffer);
+    function f32_to_f16(v) {
+        f32_to_f16_buf[0] = v;
+        let x = f32_to_f16_bits[0];
+        let sign = (x >> 16) & 0x8000;
+        let exp = ((x >> 23) & 0xff) - 127 + 15;
+        let mant = x & 0x7fffff;
+        if (exp >= 31) { // overflow, inf and nan
+            return sign | 0x7c00 | (((x >> 23) & 0xff) == 0xff && mant? 0x200: 0);
+        }
+        if (exp <= 0) { // denormals and underflow
+            if (exp < -10) return sign;
+            mant = (mant | 0x800000) >> (1 - exp);
+            return sign | ((mant + 0x1000) >> 13);
+        }
+        return sign | ((exp << 10) + ((mant + 0x1000) >> 13));
+    }
+    
     function mat4_invert(out, a) {
         let a00 = a[0]
         let a01 = a[1]
@@ -2196,39 +2457,57 @@
     
     var wasm_instances = [];
     
+    // the files the apps wrote, they outlive a hot reload of the wasm module
+    var file_store = {};
+    
+    // instances a wasm file, with the etag it came with so we can ask the hub if it is still the latest
+    function fetch_wasm(wasmfile) {
+        let webasm = null;
+        let etag = null;
+        function _log_str(chars_ptr, len) {
+            let out = "";
+            let array = new Uint32Array(webasm.instance.exports.memory.buffer, chars_ptr, len);
+            for(let i = 0; i < len; i++){
+                out += String.fromCharCode(array[i]);
+            }
+            console.log(out);
+        }
+        
+        function _performance_now() {
+            return performance.now();
+        }
+        
+        return fetch(wasmfile)
+            .then(response => {
+            etag = response.headers.get("ETag");
+            return response.arrayBuffer()
+        })
+            .then(bytes => WebAssembly.instantiate(bytes, {env: {
+            _log_str,
+            _performance_now
+        }}))
+            .then(results => {
+            webasm = results
+            return {webasm: webasm, etag: etag}
+        })
+    }
+    
     function init() {
         for (let i = 0; i < canvasses.length; i ++) {
             // we found a canvas. instance the referenced wasm file
-            var canvas = canvasses[i]
+            let canvas = canvasses[i]
             let wasmfile = canvas.getAttribute("wasm");
             if (!wasmfile) continue
-            function fetch_wasm(wasmfile) {
-                let webasm = null;
-                function _log_str(chars_ptr, len) {
-                    let out = "";
-                    let array = new Uint32Array(webasm.instance.exports.memory.buffer, chars_ptr, len);
-                    for(let i = 0; i < len; i++){
-                        out += String.fromCharCode(array[i]);
-                    }
-                    console.log(out);
-                }
-                
-                fetch(wasmfile)
-                    .then(response => response.arrayBuffer())
-                    .then(bytes => WebAssembly.instantiate(bytes, {env: {
-                    _log_str
-                }}))
-                    .then(results => {
-                    webasm = results
-                    wasm_instances.push(
-                        new WasmApp(canvas, results)
-                    );
-                }, errors => {
-                    console.log("Error compiling wasm file", errors);
-                });
-            }
-            fetch_wasm(wasmfile);
-            // load this wasm file
+            fetch_wasm(wasmfile).then(loaded => {
+                let wasm_app = new WasmApp(canvas, loaded.webasm);
+                wasm_app.wasm_file = wasmfile;
+                wasm_app.wasm_etag = loaded.etag;
+                // the path the hub knows it by, workspace/file
+                wasm_app.wasm_path = decodeURIComponent(new URL(wasmfile, location.href).pathname).slice(1);
+                wasm_instances.push(wasm_app);
+            }, errors => {
+                console.log("Error compiling wasm file", errors);
+            });
         }
     }
     
@@ -2286,5 +2565,79 @@
         req.open("GET", "/$watch?" + ('' + Math.random()).slice(2))
         req.send()
     }
-    watchFileChange()
+    
+    var build_note = null;
+    var title_before_note = null;
+    
+    function set_build_note(note) {
+        if (note !== null) {
+            if (build_note === null) {
+                title_before_note = document.title;
+            }
+            document.title = note;
+            console.log(note);
+        }
+        else if (build_note !== null && document.title == build_note) {
+            document.title = title_before_note;
+        }
+        build_note = note;
+    }
+    
+    function hot_reload_wasm(wasm_app) {
+        // webxr sessions dont survive it
+        if (wasm_app.xr_is_presenting) {
+            location.href = location.href
+            return
+        }
+        fetch_wasm(wasm_app.wasm_file).then(loaded => {
+            wasm_app.wasm_etag = loaded.etag;
+            wasm_app.hot_reload(loaded.webasm);
+            console.log("Reloaded " + wasm_app.wasm_file);
+        }, errors => {
+            console.log("Error compiling wasm file, reloading the page", errors);
+            location.href = location.href
+        });
+    }
+    
+    // the live reload channel of the hub. a new build of a wasm file is swapped into the canvases
+    // that run it, any other file that changed reloads the page. a server without it gets the long poll
+    function watchLiveReload() {
+        if (typeof WebSocket === 'undefined') {
+            return watchFileChange();
+        }
+        let opened = false;
+        let socket = new WebSocket((location.protocol == "https:"? "wss://": "ws://") + location.host + "/$websocket");
+        socket.addEventListener("open", function() {
+            opened = true;
+            // a build can have finished while we were not connected
+            for (let i = 0; i < wasm_instances.length; i ++) {
+                let wasm_app = wasm_instances[i];
+                if (wasm_app.wasm_etag) socket.send(JSON.stringify({type: "reload_request", path: wasm_app.wasm_path, etag: wasm_app.wasm_etag}));
+            }
+        })
+        socket.addEventListener("message", function(e) {
+            var msg = JSON.parse(e.data);
+            if (msg.type == "file_change") {
+                let reload = wasm_instances.filter(wasm_app => wasm_app.wasm_path == msg.path);
+                if (reload.length == 0) {
+                    location.href = location.href
+                    return
+                }
+                reload.forEach(hot_reload_wasm);
+            }
+            if (msg.type == "build_start") {
+                set_build_note("Rebuilding application...");
+            }
+            if (msg.type == "build_end") {
+                set_build_note(msg.success? null: "Build failed");
+            }
+        })
+        socket.addEventListener("close", function() {
+            if (!opened) {
+                return watchFileChange();
+            }
+            setTimeout(watchLiveReload, 500)
+        })
+    }
+    watchLiveReload()
 })({})
\ No newline at end of file
@@ -1,6 +1,6 @@
 
 use crate::cx::*;
-use makepad_shader_compiler::generate_glsl;
+use makepad_shader_compiler::generate_glsl::{self, GlslVersion};
 
 impl Cx {
     pub fn render_view(
@@ -145,12 +145,13 @@ impl Cx {
         self.platform.from_wasm.begin_render_targets(pass_id, (pass_size.x * dpi_factor) as usize, (pass_size.y * dpi_factor) as usize);
         
         for color_texture in &self.passes[pass_id].color_textures {
+            let sampler = &self.textures[color_texture.texture_id].desc.sampler;
             match color_texture.clear_color {
                 ClearColor::InitWith(color) => {
-                    self.platform.from_wasm.add_color_target(color_texture.texture_id, true, color);
+                    self.platform.from_wasm.add_color_target(color_texture.texture_id, true, color, sampler);
                 },
                 ClearColor::ClearWith(color) => {
-                    self.platform.from_wasm.add_color_target(color_texture.texture_id, false, color);
+                    self.platform.from_wasm.add_color_target(color_texture.texture_id, false, color, sampler);
                 }
             }
         }
@@ -186,12 +187,14 @@ impl Cx {
     
     
     pub fn webgl_compile_all_shaders(&mut self) {
+        let profile_start = self.profile_span_begin();
         for (shader_id, sh) in self.shaders.iter_mut().enumerate() {
             let glsh = Self::webgl_compile_shader(shader_id, !self.platform.gpu_spec_is_low_on_uniforms, false, sh, &mut self.platform, &mut self.shader_inherit_cache);
             if let ShaderCompileResult::Fail{err,..} = glsh {
                 self.platform.from_wasm.log(&format!("Got GLSL shader compile error: {}", err))
             }
         }
+        self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
     }
     
     pub fn webgl_compile_shader(shader_id: usize, gather_all_consts:bool, use_const_table: bool, sh: &mut CxShader, platform: &mut CxPlatform, shader_inherit_cache:&mut ShaderInheritCache) -> ShaderCompileResult{
@@ -212,27 +215,35 @@ impl Cx {
             }
         };
         
-        let vertex = generate_glsl::generate_vertex_shader(&shader_ast,use_const_table);
-        let fragment = generate_glsl::generate_fragment_shader(&shader_ast,use_const_table);
+        // the blend, depth, cull and color_mask the shader declares, on top of its ShaderGen.
+        // they are baked into the platform shader, so a change of them compiles it again
+        let pipeline = shader_ast.pipeline.apply(sh.shader_gen.pipeline);
+        let pipeline_changed = sh.pipeline != pipeline;
+        sh.pipeline = pipeline;
+        
+        let vertex = generate_glsl::generate_vertex_shader(&shader_ast,use_const_table,GlslVersion::Es100);
+        let fragment = generate_glsl::generate_fragment_shader(&shader_ast,use_const_table,GlslVersion::Es100);
         let mapping = CxShaderMapping::from_shader_gen(&sh.shader_gen, if use_const_table{shader_ast.const_table.borrow_mut().take()} else {None});
     
         let vertex = format!("
             precision highp float;
             precision highp int;
             vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+            {}
             mat4 transpose(mat4 m){{return mat4(m[0][0],m[1][0],m[2][0],m[3][0],m[0][1],m[1][1],m[2][1],m[3][1],m[0][2],m[1][2],m[2][2],m[3][3], m[3][0], m[3][1], m[3][2], m[3][3]);}}
             mat3 transpose(mat3 m){{return mat3(m[0][0],m[1][0],m[2][0],m[0][1],m[1][1],m[2][1],m[0][2],m[1][2],m[2][2]);}}
             mat2 transpose(mat2 m){{return mat2(m[0][0],m[1][0],m[0][1],m[1][1]);}}
-            {}\0", vertex);
+            {}\0", GLSL_SAMPLE_LAYERED, vertex);
         let fragment = format!("
             #extension GL_OES_standard_derivatives : enable
             precision highp float;
             precision highp int;
             vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+            {}
             mat4 transpose(mat4 m){{return mat4(m[0][0],m[1][0],m[2][0],m[3][0],m[0][1],m[1][1],m[2][1],m[3][1],m[0][2],m[1][2],m[2][2],m[3][3], m[3][0], m[3][1], m[3][2], m[3][3]);}}
             mat3 transpose(mat3 m){{return mat3(m[0][0],m[1][0],m[2][0],m[0][1],m[1][1],m[2][1],m[0][2],m[1][2],m[2][2]);}}
             mat2 transpose(mat2 m){{return mat2(m[0][0],m[1][0],m[0][1],m[1][1]);}}
-            {}\0", fragment);
+            {}\0", GLSL_SAMPLE_LAYERED, fragment);
 
         if shader_ast.debug{
             platform.from_wasm.log(&format!(
@@ -243,13 +254,13 @@ impl Cx {
              
         // lets check if we need to recompile the shader at all
         if let Some(sh_platform) = &sh.platform{
-            if sh_platform.vertex == vertex && sh_platform.fragment == fragment{
+            if !pipeline_changed && sh_platform.vertex == vertex && sh_platform.fragment == fragment{
                 sh.mapping = mapping;
                 return ShaderCompileResult::Nop{id:shader_id}
             }
         } 
         //let shader_id = self.compiled_shaders.len();
-        platform.from_wasm.compile_webgl_shader(shader_id, &vertex, &fragment, &mapping);
+        platform.from_wasm.compile_webgl_shader(shader_id, &vertex, &fragment, &mapping, &sh.pipeline);
         
         let geom_ib_id = platform.get_free_index_buffer();
         let geom_vb_id = platform.get_free_index_buffer();
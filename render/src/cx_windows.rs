@@ -190,6 +190,7 @@ impl Cx {
                         self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
                         
                         if passes_todo.len() > 0 {
+                            let profile_start = self.profile_span_begin();
                             for pass_id in &passes_todo {
                                 match self.passes[*pass_id].dep_of.clone() {
                                     CxPassDepOf::Window(window_id) => {
@@ -235,6 +236,8 @@ impl Cx {
                                     }
                                 }
                             }
+                            self.profile_span_end(ProfileSpan::Repaint, profile_start);
+                            self.profile_end_frame();
                         }
                     },
                     Event::None => {
@@ -250,11 +253,13 @@ impl Cx {
                 self.process_desktop_post_event(event);
             }
 
+            let profile_start = self.profile_span_begin();
             let mut shader_results = Vec::new();
             for shader_id in &self.shader_recompiles {
                 shader_results.push(Self::hlsl_compile_shader(*shader_id, true, &mut self.shaders[*shader_id], &d3d11_cx, &mut self.shader_inherit_cache));
             }
             self.shader_recompiles.truncate(0);
+            self.profile_span_end(ProfileSpan::ShaderCompile, profile_start);
             self.call_shader_recompile_event(shader_results, &mut event_handler);
 
             if self.playing_anim_areas.len() == 0 && self.redraw_parent_areas.len() == 0 && self.redraw_child_areas.len() == 0 && self.frame_callbacks.len() == 0 {
@@ -83,6 +83,8 @@ mod text;
 mod events;
 mod menu; 
 mod shader;
+mod profiler;
+mod batch;
 
 mod cube;
 
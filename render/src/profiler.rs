@@ -0,0 +1,265 @@
+use crate::cx::*;
+use crate::quad::*;
+use std::collections::VecDeque;
+
+pub const PROFILER_HISTORY_LEN: usize = 120;
+
+// timings of one repainted frame. All times are in nanoseconds
+#[derive(Clone, Default, Debug)]
+pub struct FrameStats {
+    pub frame: u64,
+    pub event_ns: u64,
+    pub draw_ns: u64,
+    pub shader_compile_ns: u64,
+    pub repaint_ns: u64,
+    pub pass_gpu_ns: Vec<(usize, u64)>, // (pass_id, gpu time) from timer queries, lags a few frames
+    pub views: usize,
+    pub draw_calls: usize,
+    pub instances: usize,
+    pub redrawn_areas: usize,
+    pub passes_repainted: usize,
+    pub draw_calls_before_batching: usize,
+    pub draw_calls_after_batching: usize,
+}
+
+impl FrameStats {
+    pub fn cpu_ns(&self) -> u64 {
+        self.event_ns + self.draw_ns + self.shader_compile_ns + self.repaint_ns
+    }
+
+    pub fn gpu_ns(&self) -> u64 {
+        self.pass_gpu_ns.iter().map( | (_, ns) | ns).sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProfileSpan {
+    Event,
+    Draw,
+    ShaderCompile,
+    Repaint
+}
+
+#[derive(Clone)]
+pub struct CxProfiler {
+    pub enabled: bool,
+    pub current: FrameStats,
+    pub history: VecDeque<FrameStats>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub epoch: std::time::Instant, // profile_time_ns counts from here
+}
+
+impl Default for CxProfiler {
+    fn default() -> Self {
+        CxProfiler {
+            enabled: false,
+            current: FrameStats::default(),
+            history: VecDeque::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            epoch: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Cx {
+    pub fn profile_time_ns(&self) -> u64 {
+        self.profiler.epoch.elapsed().as_nanos() as u64
+    }
+}
+
+impl Cx {
+
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if self.profiler.enabled != enabled {
+            self.profiler.enabled = enabled;
+            self.profiler.current = FrameStats::default();
+            self.profiler.history.truncate(0);
+        }
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profiler.enabled
+    }
+
+    // the last completed frame, if profiling is on
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.profiler.history.back()
+    }
+
+    pub fn frame_stats_history(&self) -> &VecDeque<FrameStats> {
+        &self.profiler.history
+    }
+
+    pub fn profile_span_begin(&self) -> Option<u64> {
+        if self.profiler.enabled {
+            Some(self.profile_time_ns())
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn profile_span_end(&mut self, span: ProfileSpan, start: Option<u64>) {
+        if let Some(start) = start {
+            let delta = self.profile_time_ns().saturating_sub(start);
+            let stats = &mut self.profiler.current;
+            match span {
+                ProfileSpan::Event => stats.event_ns += delta,
+                ProfileSpan::Draw => stats.draw_ns += delta,
+                ProfileSpan::ShaderCompile => stats.shader_compile_ns += delta,
+                ProfileSpan::Repaint => stats.repaint_ns += delta,
+            }
+        }
+    }
+
+    pub fn profile_pass_gpu_time(&mut self, pass_id: usize, gpu_ns: u64) {
+        if self.profiler.enabled {
+            self.profiler.current.pass_gpu_ns.push((pass_id, gpu_ns));
+        }
+    }
+
+    // called from compute_passes_to_repaint to count what the coming repaint will touch
+    pub fn profile_count_passes(&mut self, passes_todo: &[usize]) {
+        fn count_view(cx: &Cx, view_id: usize, stats: &mut FrameStats) {
+            stats.views += 1;
+            let cxview = &cx.views[view_id];
+            for draw_call in &cxview.draw_calls[0..cxview.draw_calls_len] {
+                if draw_call.sub_view_id != 0 {
+                    count_view(cx, draw_call.sub_view_id, stats);
+                }
+                else {
+                    stats.draw_calls += 1;
+                    let slots = cx.shaders[draw_call.shader_id].mapping.instance_props.total_slots;
+                    stats.instances += draw_call.instance.len().checked_div(slots).unwrap_or(0);
+                }
+            }
+        }
+        if !self.profiler.enabled {
+            return
+        }
+        let mut stats = std::mem::take(&mut self.profiler.current);
+        stats.views = 0;
+        stats.draw_calls = 0;
+        stats.instances = 0;
+        stats.passes_repainted = passes_todo.len();
+        for pass_id in passes_todo {
+            if let Some(view_id) = self.passes[*pass_id].main_view_id {
+                count_view(self, view_id, &mut stats);
+            }
+        }
+        self.profiler.current = stats;
+    }
+
+    // closes the current frame after a repaint and pushes it into the history
+    pub fn profile_end_frame(&mut self) {
+        if !self.profiler.enabled {
+            return
+        }
+        let mut stats = std::mem::take(&mut self.profiler.current);
+        stats.frame = self.repaint_id;
+        if self.profiler.history.len() >= PROFILER_HISTORY_LEN {
+            self.profiler.history.pop_front();
+        }
+        self.profiler.history.push_back(stats);
+    }
+}
+
+// Draws the statistics of the last frame in an overlay view on top of the current window.
+// Call handle_profiler_overlay from the event handler so it refreshes periodically.
+#[derive(Clone)]
+pub struct ProfilerOverlay {
+    pub view: View,
+    pub bg: Quad,
+    pub text: Text,
+    pub origin: Vec2,
+    pub refresh_interval: f64,
+    pub timer: Timer,
+}
+
+impl ProfilerOverlay {
+    pub fn new(cx: &mut Cx) -> Self {
+        let mut text = Text::new(cx);
+        text.text_style.font = cx.load_font("resources/LiberationMono-Regular.ttf");
+        text.wrapping = Wrapping::None;
+        let mut bg = Quad::new(cx);
+        bg.z = 100.0;
+        bg.color = Color {r: 0.0, g: 0.0, b: 0.0, a: 0.75};
+        text.z = 100.1;
+        Self {
+            view: View::proto_overlay(cx),
+            bg,
+            text,
+            origin: Vec2 {x: 8.0, y: 8.0},
+            refresh_interval: 0.5,
+            timer: Timer::empty(),
+        }
+    }
+
+    pub fn handle_profiler_overlay(&mut self, cx: &mut Cx, event: &mut Event) {
+        match event {
+            Event::Construct => {
+                cx.set_profiling(true);
+                self.timer = cx.start_timer(self.refresh_interval, true);
+            },
+            Event::Timer(te) => if self.timer.is_timer(te) {
+                self.view.redraw_view_area(cx);
+            },
+            _ => ()
+        }
+    }
+
+    pub fn draw_profiler_overlay(&mut self, cx: &mut Cx) {
+        if !cx.is_profiling() {
+            return
+        }
+        if let Err(()) = self.view.begin_view(cx, Layout {
+            abs_origin: Some(self.origin),
+            ..Default::default()
+        }) {
+            return
+        }
+
+        let lines = if let Some(stats) = cx.frame_stats() {
+            let ms = | ns: u64 | ns as f64 / 1_000_000.0;
+            let gpu = if stats.pass_gpu_ns.len() > 0 {
+                format!("{:.2}ms", ms(stats.gpu_ns()))
+            }
+            else {
+                "n/a".to_string()
+            };
+            vec![
+                format!("frame {}", stats.frame),
+                format!("cpu {:.2}ms gpu {}", ms(stats.cpu_ns()), gpu),
+                format!("event {:.2}ms draw {:.2}ms", ms(stats.event_ns), ms(stats.draw_ns)),
+                format!("shader {:.2}ms repaint {:.2}ms", ms(stats.shader_compile_ns), ms(stats.repaint_ns)),
+                format!("passes {} views {}", stats.passes_repainted, stats.views),
+                format!("draw calls {} instances {}", stats.draw_calls, stats.instances),
+                if DRAW_BATCHING_SUPPORTED {
+                    format!("batched {} -> {}", stats.draw_calls_before_batching, stats.draw_calls_after_batching)
+                }
+                else {
+                    "batched n/a".to_string()
+                },
+                format!("redrawn areas {}", stats.redrawn_areas),
+            ]
+        }
+        else {
+            vec!["no frame stats yet".to_string()]
+        };
+
+        let inst = self.bg.begin_quad(cx, Layout {
+            padding: Padding::all(6.0),
+            line_wrap: LineWrap::None,
+            walk: Walk::wh(Width::Compute, Height::Compute),
+            ..Default::default()
+        });
+        for line in &lines {
+            self.text.draw_text(cx, line);
+            cx.turtle_new_line();
+        }
+        self.bg.end_quad(cx, inst);
+
+        self.view.end_view(cx);
+    }
+}
@@ -1,5 +1,47 @@
 use crate::cx::*;
 
+// GLSL ES 1.0 (WebGL 1) has no array or 3d samplers, so there those textures are uploaded as their layers
+// stacked vertically into one 2d texture and the generator passes the layer count and layer height along.
+// the v coordinate is clamped half a texel inside its layer so filtering doesn't bleed into the next one
+pub const GLSL_SAMPLE_LAYERED: &str = "
+    float mpsc_layer_v(vec2 depth, float l, float v){
+        float half_texel = 0.5 / depth.y;
+        return (l + clamp(1.0 - v, half_texel, 1.0 - half_texel)) / depth.x;
+    }
+    vec4 sample2d_array(sampler2D sampler, vec2 depth, vec3 pos){
+        float l = clamp(floor(pos.z + 0.5), 0.0, depth.x - 1.0);
+        return texture2D(sampler, vec2(pos.x, mpsc_layer_v(depth, l, pos.y)));
+    }
+    vec4 sample3d(sampler2D sampler, vec2 depth, vec3 pos){
+        float z = clamp(pos.z * depth.x - 0.5, 0.0, depth.x - 1.0);
+        float l = floor(z);
+        vec4 a = texture2D(sampler, vec2(pos.x, mpsc_layer_v(depth, l, pos.y)));
+        vec4 b = texture2D(sampler, vec2(pos.x, mpsc_layer_v(depth, min(l + 1.0, depth.x - 1.0), pos.y)));
+        return mix(a, b, z - l);
+    }
+    vec4 sample_cube(samplerCube sampler, vec3 dir){
+        return textureCube(sampler, dir);
+    }
+";
+
+// GLSL ES 3.00 has real array and 3d samplers
+pub const GLSL_SAMPLE_ES300: &str = "
+    precision highp sampler2DArray;
+    precision highp sampler3D;
+    vec4 sample2d(sampler2D sampler, vec2 pos){
+        return texture(sampler, vec2(pos.x, 1.0 - pos.y));
+    }
+    vec4 sample2d_array(sampler2DArray sampler, vec3 pos){
+        return texture(sampler, vec3(pos.x, 1.0 - pos.y, pos.z));
+    }
+    vec4 sample3d(sampler3D sampler, vec3 pos){
+        return texture(sampler, vec3(pos.x, 1.0 - pos.y, pos.z));
+    }
+    vec4 sample_cube(samplerCube sampler, vec3 dir){
+        return texture(sampler, dir);
+    }
+";
+
 #[derive(Default, Copy, Clone, PartialEq)]
 pub struct Shader {
     pub shader_id: Option<(usize, usize)>,
@@ -499,6 +541,7 @@ impl CxShaderMapping {
 #[derive(Default, Clone)]
 pub struct CxShader {
     pub name: String,
+    pub pipeline: PipelineState,
     pub shader_gen: ShaderGen,
     pub platform: Option<CxPlatformShader>,
     pub mapping: CxShaderMapping
@@ -4,12 +4,15 @@ use crate::cx::*;
 pub enum TextureFormat {
     Default,
     ImageBGRA,
+    ImageR8,
+    ImageRG8,
+    ImageRf32,
+    ImageRGBAf16,
     Depth32Stencil8,
     RenderBGRA,
     RenderBGRAf16,
     RenderBGRAf32,
     //    ImageBGRAf32,
-    //    ImageRf32,
     //    ImageRGf32,
     //    MappedBGRA,
     //    MappedBGRAf32,
@@ -17,12 +20,100 @@ pub enum TextureFormat {
     //    MappedRGf32,
 }
 
+// Layered textures take their image data layer after layer, cube faces go in +X -X +Y -Y +Z -Z order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureKind {
+    Texture2D,
+    Texture2DArray,
+    TextureCube,
+    Texture3D,
+}
+
+impl Default for TextureKind {
+    fn default() -> Self {
+        TextureKind::Texture2D
+    }
+}
+
+// Trilinear only differs from Linear when the texture has mipmaps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    Trilinear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureWrap {
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureSampler {
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+}
+
+impl Default for TextureSampler {
+    fn default() -> Self {
+        TextureSampler {
+            filter: TextureFilter::Linear,
+            wrap: TextureWrap::Clamp
+        }
+    }
+}
+
+impl TextureSampler {
+    pub fn nearest() -> Self {
+        TextureSampler {
+            filter: TextureFilter::Nearest,
+            wrap: TextureWrap::Clamp
+        }
+    }
+    
+    pub fn trilinear_repeat() -> Self {
+        TextureSampler {
+            filter: TextureFilter::Trilinear,
+            wrap: TextureWrap::Repeat
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct TextureDesc {
     pub format: TextureFormat,
     pub width: Option<usize>,
     pub height: Option<usize>,
-    pub multisample: Option<usize>
+    pub multisample: Option<usize>,
+    pub kind: TextureKind,
+    // number of layers for arrays and 3d textures
+    pub depth: Option<usize>,
+    // generates the full mip chain whenever the image is uploaded, image formats only
+    pub mipmaps: bool,
+    pub sampler: TextureSampler,
+}
+
+impl TextureDesc {
+    pub fn layers(&self) -> usize {
+        match self.kind {
+            TextureKind::Texture2D => 1,
+            TextureKind::TextureCube => 6,
+            TextureKind::Texture2DArray | TextureKind::Texture3D => self.depth.unwrap_or(1).max(1)
+        }
+    }
+    
+    pub fn mip_levels(&self) -> usize {
+        if !self.mipmaps {
+            return 1
+        }
+        let mut size = self.width.unwrap_or(1).max(self.height.unwrap_or(1)).max(1);
+        if self.kind == TextureKind::Texture3D {
+            size = size.max(self.layers());
+        }
+        (usize::BITS - size.leading_zeros()) as usize
+    }
 }
 
 #[derive(Clone)]
@@ -36,7 +127,11 @@ impl Default for TextureDesc {
             format: TextureFormat::Default,
             width: None,
             height: None,
-            multisample: None
+            multisample: None,
+            kind: TextureKind::Texture2D,
+            depth: None,
+            mipmaps: false,
+            sampler: TextureSampler::default()
         }
     }
 }
@@ -79,8 +174,37 @@ impl Texture {
 #[derive(Default)]
 pub struct CxTexture {
     pub desc: TextureDesc,
+    // BGRA images are u32 per pixel, R8 and RG8 images are tightly packed bytes
+    // and Rf32 / RGBAf16 images are given as f32 per channel
+    pub image_u8: Vec<u8>,
     pub image_u32: Vec<u32>,
     pub image_f32: Vec<f32>,
     pub update_image: bool,
     pub platform: CxPlatformTexture
 }
+
+impl CxTexture {
+    // ImageRGBAf16 is given as f32, for the backends that need the halfs themselves
+    pub fn image_f16(&self) -> Vec<u16> {
+        self.image_f32.iter().map( | v | f32_to_f16(*v)).collect()
+    }
+}
+
+fn f32_to_f16(v: f32) -> u16 {
+    let x = v.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32 - 127 + 15;
+    let mant = x & 0x7fffff;
+    if exp >= 31 { // overflow, inf and nan
+        let nan = if (x >> 23) & 0xff == 0xff && mant != 0 {0x200} else {0};
+        return sign | 0x7c00 | nan;
+    }
+    if exp <= 0 { // denormals and underflow
+        if exp < -10 {
+            return sign
+        }
+        let mant = (mant | 0x800000) >> (1 - exp);
+        return sign | ((mant + 0x1000) >> 13) as u16;
+    }
+    sign | (((exp as u32) << 10) + ((mant + 0x1000) >> 13)) as u16
+}
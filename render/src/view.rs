@@ -402,6 +402,18 @@ pub struct DrawUniforms {
 }
 
 impl DrawUniforms {
+    // everything but the zbias
+    pub fn same_clip_and_scroll(&self, other: &DrawUniforms) -> bool {
+        self.draw_clip_x1 == other.draw_clip_x1
+            && self.draw_clip_y1 == other.draw_clip_y1
+            && self.draw_clip_x2 == other.draw_clip_x2
+            && self.draw_clip_y2 == other.draw_clip_y2
+            && self.draw_scroll_x == other.draw_scroll_x
+            && self.draw_scroll_y == other.draw_scroll_y
+            && self.draw_scroll_z == other.draw_scroll_z
+            && self.draw_scroll_w == other.draw_scroll_w
+    }
+    
     pub fn as_slice(&self) -> &[f32; std::mem::size_of::<DrawUniforms>()] {
         unsafe {std::mem::transmute(self)}
     }
@@ -493,7 +505,7 @@ impl DrawCall {
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, PartialEq)]
 #[repr(C)]
 pub struct ViewUniforms {
     view_transform: [f32; 16],
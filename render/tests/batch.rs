@@ -0,0 +1,35 @@
+use makepad_render::*;
+
+fn draw_call(shader: &Shader, clip_x2: f32) -> DrawCall {
+    let (shader_id, shader_instance_id) = shader.shader_id.unwrap();
+    let mut draw_call = DrawCall {shader_id: shader_id, shader_instance_id: shader_instance_id, instance: vec![1.0; 4], ..DrawCall::default()};
+    draw_call.set_clip((Vec2 {x: 0., y: 0.}, Vec2 {x: clip_x2, y: 100.}));
+    draw_call
+}
+
+#[test]
+fn same_shader_draw_calls_merge() {
+    let mut cx = Cx::default();
+    let shader = cx.add_shader(Quad::def_quad_shader(), "batch");
+    let mut view = CxView::default();
+    // the zbias differs between all of them like render_view sets it, the third has another clip
+    view.draw_calls = vec![draw_call(&shader, 100.), draw_call(&shader, 100.), draw_call(&shader, 50.)];
+    for (index, draw_call) in view.draw_calls.iter_mut().enumerate() {
+        draw_call.set_zbias(index as f32 * 0.001);
+    }
+    view.draw_calls_len = 3;
+    cx.views.push(view);
+    let view_id = cx.views.len() - 1;
+    
+    cx.draw_batcher.enabled = true;
+    cx.draw_batcher.items = (0..3).map( | draw_call_id | DrawItem {view_id: view_id, draw_call_id: draw_call_id, instance_dirty: true}).collect();
+    cx.compute_draw_batches();
+    assert_eq!(cx.draw_batcher.batches, vec![0..2, 2..3]);
+    
+    cx.gather_batch_instances(0..2);
+    assert_eq!(cx.draw_batcher.instances.len(), 8);
+    
+    cx.draw_batcher.enabled = false;
+    cx.compute_draw_batches();
+    assert_eq!(cx.draw_batcher.batches, vec![0..1, 1..2, 2..3]);
+}
@@ -0,0 +1,15 @@
+use std::process::Command;
+
+// cx_webgl.js needs a browser, tests/webgl_hot_reload.js runs it in node with just enough of one
+#[test]
+fn hot_reload_twice() {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/webgl_hot_reload.js");
+    let output = match Command::new("node").arg(script).output() {
+        Ok(output) => output,
+        Err(_) => {
+            println!("no node to run {} with", script);
+            return
+        }
+    };
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,110 @@
+// runs cx_webgl.js without a browser and checks that every start of the wasm module, the first one
+// and each hot reload after it, fetches its deps and hands them over before init
+const fs = require("fs");
+const path = require("path");
+const vm = require("vm");
+
+let source = fs.readFileSync(path.join(__dirname, "../src/cx_webgl.js"), "utf8");
+let end = source.lastIndexOf("})({})");
+source = source.slice(0, end) + "root.WasmApp = WasmApp;\n})(exported)";
+
+class XMLHttpRequest {
+    constructor() {this.listeners = {};}
+    addEventListener(name, f) {this.listeners[name] = f;}
+    open(method, url) {this.url = url;}
+    send() {
+        setTimeout(() => {
+            this.status = 200;
+            this.response = new ArrayBuffer(8);
+            this.listeners.load();
+        }, 0);
+    }
+}
+
+let exported = {};
+let document = {
+    getElementsByClassName: () => [],
+    addEventListener: () => {},
+    body: {},
+};
+vm.runInNewContext(source, {
+    exported,
+    document,
+    window: {navigator: {userAgent: "node", platform: "node"}, addEventListener: () => {}},
+    navigator: {userAgent: "node", platform: "node", maxTouchPoints: 0},
+    XMLHttpRequest,
+    WebSocket: class {addEventListener() {}},
+    location: {protocol: "http:", host: "localhost", href: "http://localhost/"},
+    setTimeout,
+    console,
+    Promise,
+});
+
+// a wasm app that asks for one font and logs what it got
+function fake_webasm(log) {
+    let memory = {buffer: new ArrayBuffer(1 << 20)};
+    let heap = 8;
+    function alloc(bytes) {
+        let ptr = heap;
+        heap += (bytes + 7) & ~7;
+        return ptr;
+    }
+    function message(slots) {
+        let ptr = alloc(slots.length * 4);
+        new Uint32Array(memory.buffer, ptr, slots.length).set(slots);
+        return ptr;
+    }
+    let exports = {
+        memory,
+        create_wasm_app: () => 1,
+        alloc_wasm_message: alloc,
+        alloc_wasm_vec: alloc,
+        realloc_wasm_message(ptr, bytes) {
+            let new_ptr = alloc(bytes);
+            new Uint8Array(memory.buffer, new_ptr, bytes).set(new Uint8Array(memory.buffer, ptr, Math.min(bytes, new_ptr - ptr)));
+            return new_ptr;
+        },
+        dealloc_wasm_message: () => {},
+        process_to_wasm(app, ptr) {
+            let to_wasm = new Uint32Array(memory.buffer, ptr);
+            if (to_wasm[2] == 1) {
+                log.push("fetch_deps");
+                let name = Array.from("font.ttf").map(c => c.charCodeAt(0));
+                return message([0, 0, 8, 1, name.length, ...name, 0]);
+            }
+            if (to_wasm[2] == 2) {
+                log.push("deps_loaded " + to_wasm[3]);
+            }
+            return message([0, 0, 0]);
+        }
+    };
+    return {instance: {exports}};
+}
+
+function settle() {
+    return new Promise(resolve => setTimeout(resolve, 10));
+}
+
+async function main() {
+    let app = Object.create(exported.WasmApp.prototype);
+    Object.assign(app, {
+        resources: [], shaders: [], index_buffers: [], array_buffers: [], timers: [], vaos: [], textures: [], framebuffers: [],
+        req_anim_frame_id: 0, width: 100, height: 100, dpi_factor: 1,
+        gl: {OES_vertex_array_object: {}},
+    });
+    let logs = [[], [], []];
+    app.start_app(fake_webasm(logs[0]));
+    await settle();
+    app.hot_reload(fake_webasm(logs[1]));
+    await settle();
+    app.hot_reload(fake_webasm(logs[2]));
+    await settle();
+    for (let log of logs) {
+        if (JSON.stringify(log) != JSON.stringify(["fetch_deps", "deps_loaded 1"])) {
+            console.log("unexpected start " + JSON.stringify(logs));
+            process.exit(1);
+        }
+    }
+}
+
+main();
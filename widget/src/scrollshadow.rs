@@ -24,6 +24,8 @@ impl ScrollShadow {
         Self::shadow_size().set(cx, 4.0);
         
         Self::shader_bg().set(cx, Quad::def_quad_shader().compose(shader!{"
+            // it fades out over what it is drawn on, so it doesn't hide what comes after it
+            depth read_only;
             varying is_viz: float;
             instance shadow_top: Self::shadow_top();
             fn scroll() -> vec2 {
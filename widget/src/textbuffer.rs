@@ -28,6 +28,8 @@ pub struct TextBuffer {
     pub old_token_chunks: Vec<TokenChunk>,
     pub token_chunks_id: u32,
     pub keyboard: TextBufferKeyboard,
+    // the edits since it was last taken, only kept when it is some
+    pub edit_log: Option<Vec<TextBufferEdit>>,
 } 
 
 impl TextBuffer {
@@ -36,6 +38,8 @@ impl TextBuffer {
     pub fn status_search_update() -> StatusId {uid!()}
     pub fn status_data_update() -> StatusId {uid!()}
     pub fn status_keyboard_update() -> StatusId {uid!()}
+    pub fn status_debug_update() -> StatusId {uid!()}
+    pub fn status_completion_update() -> StatusId {uid!()}
 }
 
 
@@ -54,6 +58,12 @@ pub struct TextBufferMarkers {
     pub search_cursors: Vec<TextCursor>,
     pub message_cursors: Vec<TextCursor>,
     pub message_bodies: Vec<TextBufferMessage>,
+    // rows toggled in the gutter, and the row a debugged program is stopped at
+    pub breakpoints: Vec<usize>,
+    pub debug_row: Option<usize>,
+    // what the language server offers at the cursor, and the selected one
+    pub completions: Vec<TextBufferCompletion>,
+    pub completion_index: usize,
 }
 
 #[derive(Clone, PartialEq)]
@@ -66,7 +76,28 @@ pub enum TextBufferMessageLevel {
 #[derive(Clone)]
 pub struct TextBufferMessage {
     pub level: TextBufferMessageLevel,
-    pub body: String
+    pub body: String,
+    pub fixes: Vec<TextBufferFix>
+}
+
+#[derive(Clone)]
+pub struct TextBufferFix {
+    pub label: String,
+    pub edits: Vec<TextBufferEdit>
+}
+
+#[derive(Clone)]
+pub struct TextBufferEdit {
+    pub start: TextPos,
+    pub end: TextPos,
+    pub replacement: String
+}
+
+#[derive(Clone)]
+pub struct TextBufferCompletion {
+    pub label: String,
+    pub detail: String,
+    pub insert_text: String
 }
 
 #[derive(Clone, Copy, PartialEq, Default)]
@@ -99,6 +130,7 @@ pub enum TextUndoGrouping {
     Tab,
     Cut,
     Format,
+    Fix,
     Other
 }
 
@@ -120,6 +152,7 @@ impl TextUndoGrouping {
             TextUndoGrouping::Block => false,
             TextUndoGrouping::Tab => false,
             TextUndoGrouping::Format => false,
+            TextUndoGrouping::Fix => false,
             TextUndoGrouping::Cut => false,
             TextUndoGrouping::Other => false
         }
@@ -423,6 +456,10 @@ impl TextBuffer {
     }
     
     pub fn load_from_utf8(&mut self, utf8: &str) {
+        if let Some(edit_log) = &mut self.edit_log {
+            let end = TextPos {row: self.lines.len().max(1) - 1, col: self.lines.last().map( | l | l.len()).unwrap_or(0)};
+            edit_log.push(TextBufferEdit {start: TextPos::zero(), end: end, replacement: utf8.to_string()});
+        }
         self.is_loaded = true;
         self.is_crlf = !utf8.find("\r\n").is_none();
         self.lines = TextBuffer::split_string_to_lines(utf8);
@@ -431,6 +468,13 @@ impl TextBuffer {
     
     pub fn replace_line(&mut self, row: usize, start_col: usize, len: usize, rep_line: Vec<char>) -> Vec<char> {
         self.mutation_id += 1;
+        if let Some(edit_log) = &mut self.edit_log {
+            edit_log.push(TextBufferEdit {
+                start: TextPos {row: row, col: start_col},
+                end: TextPos {row: row, col: start_col + len},
+                replacement: rep_line.iter().collect()
+            });
+        }
         self.lines[row].splice(start_col..(start_col + len), rep_line).collect()
     }
     
@@ -455,6 +499,10 @@ impl TextBuffer {
         self.mutation_id += 1;
         let start_pos = self.offset_to_text_pos(start);
         let end_pos = self.offset_to_text_pos_next(start + len, start_pos, start);
+        if let Some(edit_log) = &mut self.edit_log {
+            let replacement: Vec<String> = rep_lines.iter().map( | line | line.iter().collect()).collect();
+            edit_log.push(TextBufferEdit {start: start_pos, end: end_pos, replacement: replacement.join("\n")});
+        }
         
         if start_pos.row == end_pos.row && rep_lines.len() == 1 { // replace in one line
             let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
@@ -531,6 +531,27 @@ impl TextCursorSet {
         })
     }
     
+    // the edits are (start, end, text) with offsets from before any of them, they are one undo step
+    pub fn replace_ranges(&mut self, mut edits: Vec<(usize, usize, String)>, text_buffer: &mut TextBuffer) {
+        let cursors_clone = self.clone();
+        // back to front, so the offsets of the edits before it stay valid
+        edits.sort_by( | a, b | b.0.cmp(&a.0));
+        let mut ops = Vec::new();
+        for (start, end, text) in &edits {
+            ops.push(text_buffer.replace_lines_with_string(*start, end.max(start) - start, text));
+        }
+        if let Some((start, _, text)) = edits.last() {
+            let pos = start + text.chars().count();
+            self.clear_and_set_last_cursor_head_and_tail(pos, pos, text_buffer);
+        }
+        text_buffer.redo_stack.truncate(0);
+        text_buffer.undo_stack.push(TextUndo {
+            ops: ops,
+            grouping: TextUndoGrouping::Fix,
+            cursors: cursors_clone
+        })
+    }
+    
     pub fn insert_around(&mut self, pre: &str, post: &str, text_buffer: &mut TextBuffer) {
         let mut delta: isize = 0;
         // rolling delta to displace cursors
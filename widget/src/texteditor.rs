@@ -17,6 +17,8 @@ pub struct TextEditor {
     pub token_highlight: Quad,
     //pub select_highlight: Quad,
     pub cursor_row: Quad,
+    pub debug_row: Quad,
+    pub breakpoint: Quad,
     pub paren_pair: Quad,
     pub indent_lines: Quad,
     pub shadow: ScrollShadow,
@@ -24,6 +26,8 @@ pub struct TextEditor {
     pub search_marker: Quad,
     pub text: Text,
     pub line_number_text: Text,
+    pub completion_bg: Quad,
+    pub completion_text: Text,
     pub cursors: TextCursorSet,
     
     //pub base_font_size: f32,
@@ -117,7 +121,13 @@ pub enum TextEditorEvent {
     KeyFocusLost,
     Escape,
     Search(String),
-    Decl(String)
+    Decl(String),
+    Breakpoints,
+    // the language server requests, at the last cursor
+    Hover(TextPos),
+    Definition(TextPos),
+    References(TextPos),
+    Completion(TextPos),
 }
 
 #[derive(Default, Clone)]
@@ -185,6 +195,11 @@ impl TextEditor {
             token_highlight: Quad::new(cx),
             cursor: Quad::new(cx),
             cursor_row: Quad::new(cx),
+            debug_row: Quad::new(cx),
+            breakpoint: Quad {
+                z: 2.15,
+                ..Quad::new(cx)
+            },
             paren_pair: Quad::new(cx),
             message_marker: Quad::new(cx),
             search_marker: Quad::new(cx),
@@ -200,6 +215,15 @@ impl TextEditor {
                 wrapping: Wrapping::Line,
                 ..Text::new(cx)
             },
+            completion_bg: Quad {
+                z: 3.0,
+                ..Quad::new(cx)
+            },
+            completion_text: Text {
+                z: 3.1,
+                wrapping: Wrapping::None,
+                ..Text::new(cx)
+            },
             //base_font_size: 8.0,
             open_font_scale: 1.0,
             folded_font_scale: 0.07,
@@ -291,6 +315,10 @@ impl TextEditor {
     pub fn color_highlight() -> ColorId {uid!()}
     pub fn color_cursor() -> ColorId {uid!()}
     pub fn color_cursor_row() -> ColorId {uid!()}
+    pub fn color_debug_row() -> ColorId {uid!()}
+    pub fn color_breakpoint() -> ColorId {uid!()}
+    pub fn color_completion_bg() -> ColorId {uid!()}
+    pub fn color_completion_selected() -> ColorId {uid!()}
     
     pub fn color_indent_line_unknown() -> ColorId {uid!()}
     pub fn color_indent_line_fn() -> ColorId {uid!()}
@@ -333,6 +361,7 @@ impl TextEditor {
     pub fn shader_selection() -> ShaderId {uid!()}
     pub fn shader_paren_pair() -> ShaderId {uid!()}
     pub fn shader_cursor_row() -> ShaderId {uid!()}
+    pub fn shader_breakpoint() -> ShaderId {uid!()}
     //pub fn shader_token_highlight() -> ShaderId {uid!()}
     pub fn shader_search_marker() -> ShaderId {uid!()}
     pub fn shader_message_marker() -> ShaderId {uid!()}
@@ -449,6 +478,14 @@ impl TextEditor {
                 returndf_stroke(color,0.75+dpi_dilate*0.75);*/
             }
         "}));
+        
+        Self::shader_breakpoint().set(cx, Quad::def_quad_shader().compose(shader!{"
+            fn pixel() -> vec4 {
+                let cx = Df::viewport(pos * vec2(w, h));
+                cx.circle(0.5 * w, 0.5 * h, 0.5 * min(w, h) - 1.);
+                return cx.fill(color);
+            }
+        "}));
         /*
         Self::shader_select_highlight().set(cx,   Quad::def_quad_shader().compose(shader_ast!({
             fn pixel() -> vec4 {
@@ -552,8 +589,11 @@ impl TextEditor {
         self.token_highlight.color = Self::color_highlight().get(cx);
         self.cursor.color = Self::color_cursor().get(cx);
         self.cursor_row.color = Self::color_cursor_row().get(cx);
+        self.debug_row.color = Self::color_debug_row().get(cx);
+        self.breakpoint.color = Self::color_breakpoint().get(cx);
         self.text.text_style = Self::text_style_editor_text().get(cx);
         self.line_number_text.text_style = Self::text_style_editor_text().get(cx);
+        self.completion_text.text_style = Self::text_style_editor_text().get(cx);
         
         self.bg.shader = Self::shader_bg().get(cx);
         self.indent_lines.shader = Self::shader_indent_lines().get(cx);
@@ -561,6 +601,8 @@ impl TextEditor {
         self.selection.shader = Self::shader_selection().get(cx);
         self.paren_pair.shader = Self::shader_paren_pair().get(cx);
         self.cursor_row.shader = Self::shader_cursor_row().get(cx);
+        self.debug_row.shader = Self::shader_cursor_row().get(cx);
+        self.breakpoint.shader = Self::shader_breakpoint().get(cx);
         //self.token_highlight.shader = Self::shader_token_highlight().get(cx);
         self.message_marker.shader = Self::shader_message_marker().get(cx);
         self.search_marker.shader = Self::shader_search_marker().get(cx);
@@ -974,6 +1016,31 @@ impl TextEditor {
         
     }
     
+    // applies the first fix of the message under the cursor, the messages are only valid
+    // until the text changes
+    pub fn apply_quick_fix(&mut self, cx: &mut Cx, text_buffer: &mut TextBuffer) -> bool {
+        if text_buffer.markers.mutation_id != text_buffer.mutation_id {
+            return false
+        }
+        let pos = self.cursors.get_last_cursor_head();
+        let markers = &text_buffer.markers;
+        let fix = markers.message_cursors.iter().zip(markers.message_bodies.iter()).find( | (cursor, body) | {
+            body.fixes.len() > 0 && pos >= cursor.head.min(cursor.tail) && pos <= cursor.head.max(cursor.tail)
+        }).map( | (_, body) | body.fixes[0].clone());
+        let fix = if let Some(fix) = fix {fix} else {return false};
+        let edits = fix.edits.iter().map( | edit | (
+            text_buffer.text_pos_to_offset(edit.start),
+            text_buffer.text_pos_to_offset(edit.end),
+            edit.replacement.clone()
+        )).collect();
+        self.cursors.replace_ranges(edits, text_buffer);
+        self.scroll_last_cursor_visible(cx, text_buffer, 0.);
+        self.view.redraw_view_area(cx);
+        self.reset_cursor_blinker(cx);
+        cx.send_signal(text_buffer.signal, TextBuffer::status_data_update());
+        true
+    }
+    
     pub fn handle_live_replace(&mut self, cx: &mut Cx, range:(usize, usize), what:&str, text_buffer: &mut TextBuffer, group:u64)->bool{
         // let set the cursor selection
         self.cursors.clear_and_set_last_cursor_head_and_tail(range.1, range.0, text_buffer);
@@ -1049,7 +1116,9 @@ impl TextEditor {
                     if *status == TextBuffer::status_loaded()
                         || *status == TextBuffer::status_message_update()
                         || *status == TextBuffer::status_search_update()
-                        || *status == TextBuffer::status_data_update() {
+                        || *status == TextBuffer::status_data_update()
+                        || *status == TextBuffer::status_debug_update()
+                        || *status == TextBuffer::status_completion_update() {
                         self.view.redraw_view_area(cx);
                     }
                     //else if *status == TextBuffer::status_jump_to_offset() {
@@ -1094,6 +1163,13 @@ impl TextEditor {
                 return TextEditorEvent::KeyFocusLost
             },
             Event::FingerDown(fe) => {
+                // double clicking the gutter toggles a breakpoint
+                if fe.tap_count == 2 && self.draw_line_numbers && fe.rel.x < self.line_number_width - self.line_number_click_margin {
+                    let offset = self.compute_offset_from_ypos(cx, fe.abs.y, text_buffer, false);
+                    self.toggle_breakpoint(cx, text_buffer.offset_to_text_pos(offset).row, text_buffer);
+                    return TextEditorEvent::Breakpoints
+                }
+                text_buffer.markers.completions.truncate(0);
                 self.handle_finger_down(cx, &fe, text_buffer);
             },
             Event::FingerHover(_fe) => {
@@ -1106,6 +1182,9 @@ impl TextEditor {
                 self.handle_finger_move(cx, &fe, text_buffer);
             },
             Event::KeyDown(ke) => {
+                if let Some(event) = self.handle_completion_key(cx, &ke, text_buffer) {
+                    return event
+                }
                 if ke.key_code == KeyCode::Escape {
                     let pos =  self.cursors.get_last_cursor_head();
                     self.cursors.clear_and_set_last_cursor_head_and_tail(pos, pos, text_buffer);
@@ -1140,6 +1219,26 @@ impl TextEditor {
                 if ke.key_code == KeyCode::Return && (ke.modifiers.logo || ke.modifiers.control) {
                     return TextEditorEvent::AutoFormat
                 }
+                if ke.key_code == KeyCode::KeyI && (ke.modifiers.logo || ke.modifiers.control) {
+                    return TextEditorEvent::Hover(self.cursors.get_last_cursor_text_pos(text_buffer))
+                }
+                if ke.key_code == KeyCode::F12 {
+                    let pos = self.cursors.get_last_cursor_text_pos(text_buffer);
+                    return if ke.modifiers.shift {TextEditorEvent::References(pos)} else {TextEditorEvent::Definition(pos)}
+                }
+                if ke.key_code == KeyCode::Space && ke.modifiers.control && !self.read_only {
+                    return TextEditorEvent::Completion(self.cursors.get_last_cursor_text_pos(text_buffer))
+                }
+                if ke.key_code == KeyCode::F9 && self.draw_line_numbers {
+                    self.toggle_breakpoint(cx, self.cursors.get_last_cursor_text_pos(text_buffer).row, text_buffer);
+                    return TextEditorEvent::Breakpoints
+                }
+                if ke.key_code == KeyCode::Period && (ke.modifiers.logo || ke.modifiers.control) {
+                    if !self.read_only && self.apply_quick_fix(cx, text_buffer) {
+                        return TextEditorEvent::Change
+                    }
+                    return TextEditorEvent::None
+                }
                 self.handle_key_down(cx, &ke, text_buffer);
             },
             Event::KeyUp(ke) => {
@@ -1208,6 +1307,8 @@ impl TextEditor {
                 let inst = self.gutter_bg.draw_quad_rel(cx, Rect {x: 0., y: 0., w: self.line_number_width, h: cx.get_height_total()});
                 inst.set_do_scroll(cx, false, false);
             }
+            let inst = cx.new_instance_draw_call(&self.breakpoint.shader, 0);
+            inst.set_do_scroll(cx, false, true);
             let inst = self.line_number_text.begin_text(cx);
             inst.inst.set_do_scroll(cx, false, true);
             self._line_number_inst = Some(inst);
@@ -1816,6 +1917,7 @@ impl TextEditor {
         
         if self.draw_line_numbers {
             self.line_number_text.end_text(cx, self._line_number_inst.as_ref().unwrap());
+            self.draw_breakpoints(cx, text_buffer);
         }
         
         // unmatched highlighting
@@ -1834,6 +1936,8 @@ impl TextEditor {
         // last bits
         self.do_selection_scrolling(cx, text_buffer);
         self.place_ime_and_draw_cursor_row(cx);
+        self.draw_debug_row(cx, text_buffer);
+        self.draw_completions(cx, text_buffer);
         self.set_indent_line_highlight_id(cx);
         self.draw_shadows(cx);
         self.bg.end_quad_fill(cx, &self._bg_inst.take().unwrap());
@@ -1881,6 +1985,144 @@ impl TextEditor {
         }
     }
     
+    fn draw_breakpoints(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        let origin = cx.get_turtle_origin();
+        for row in &text_buffer.markers.breakpoints {
+            if let Some(geom) = self._line_geometry.get(*row) {
+                let size = self._monospace_base.y * geom.font_size;
+                self.breakpoint.draw_quad_abs(cx, Rect {
+                    x: origin.x + 2.,
+                    y: origin.y + geom.walk.y,
+                    w: size,
+                    h: size
+                });
+            }
+        }
+    }
+    
+    fn draw_debug_row(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        if let Some(row) = text_buffer.markers.debug_row {
+            if let Some(geom) = self._line_geometry.get(row) {
+                let origin = cx.get_turtle_origin();
+                self.debug_row.draw_quad_abs(cx, Rect {
+                    x: self.line_number_width + origin.x,
+                    y: origin.y + geom.walk.y,
+                    w: cx.get_width_total().max(cx.get_turtle_bounds().x) - self.line_number_width,
+                    h: self._monospace_base.y * geom.font_size
+                });
+            }
+        }
+    }
+    
+    // the identifier before the cursor, which an accepted completion replaces
+    fn completion_prefix(&self, text_buffer: &TextBuffer) -> (usize, String) {
+        let pos = self.cursors.get_last_cursor_text_pos(text_buffer);
+        let head = self.cursors.get_last_cursor_head();
+        let line = if let Some(line) = text_buffer.lines.get(pos.row) {line} else {return (head, String::new())};
+        let end = pos.col.min(line.len());
+        let mut start = end;
+        while start > 0 && (line[start - 1].is_alphanumeric() || line[start - 1] == '_') {
+            start -= 1;
+        }
+        (head - (end - start), line[start..end].iter().collect())
+    }
+    
+    // the completions that fit what was typed since they came in
+    fn visible_completions<'a>(&self, text_buffer: &'a TextBuffer) -> Vec<&'a TextBufferCompletion> {
+        let (_, prefix) = self.completion_prefix(text_buffer);
+        let prefix = prefix.to_lowercase();
+        text_buffer.markers.completions.iter().filter( | c | {
+            c.label.to_lowercase().starts_with(&prefix) || c.insert_text.to_lowercase().starts_with(&prefix)
+        }).collect()
+    }
+    
+    // up and down pick a completion, return or tab puts it in and escape closes the list
+    fn handle_completion_key(&mut self, cx: &mut Cx, ke: &KeyEvent, text_buffer: &mut TextBuffer) -> Option<TextEditorEvent> {
+        let visible = self.visible_completions(text_buffer).len();
+        if visible == 0 {
+            return None
+        }
+        let index = text_buffer.markers.completion_index.min(visible - 1);
+        match ke.key_code {
+            KeyCode::ArrowUp => {
+                text_buffer.markers.completion_index = if index == 0 {visible - 1} else {index - 1};
+            },
+            KeyCode::ArrowDown => {
+                text_buffer.markers.completion_index = if index + 1 == visible {0} else {index + 1};
+            },
+            KeyCode::Return | KeyCode::Tab => {
+                if self.read_only {
+                    return None
+                }
+                let insert_text = self.visible_completions(text_buffer)[index].insert_text.clone();
+                let (start, _) = self.completion_prefix(text_buffer);
+                let head = self.cursors.get_last_cursor_head();
+                text_buffer.markers.completions.truncate(0);
+                self.cursors.replace_ranges(vec![(start, head, insert_text)], text_buffer);
+                self.scroll_last_cursor_visible(cx, text_buffer, 0.);
+                self.view.redraw_view_area(cx);
+                self.reset_cursor_blinker(cx);
+                cx.send_signal(text_buffer.signal, TextBuffer::status_data_update());
+                return Some(TextEditorEvent::Change)
+            },
+            KeyCode::Escape => {
+                text_buffer.markers.completions.truncate(0);
+            },
+            _ => return None
+        }
+        self.view.redraw_view_area(cx);
+        Some(TextEditorEvent::None)
+    }
+    
+    fn draw_completions(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        if !self.has_key_focus(cx) {
+            return
+        }
+        let rc = if let Some(last_cursor) = self._draw_cursors.last_cursor {self._draw_cursors.cursors[last_cursor]} else {return};
+        let visible = self.visible_completions(text_buffer);
+        if visible.len() == 0 {
+            return
+        }
+        let max_rows = 10;
+        let index = text_buffer.markers.completion_index.min(visible.len() - 1);
+        let first = if index >= max_rows {index + 1 - max_rows} else {0};
+        let rows = &visible[first..visible.len().min(first + max_rows)];
+        let line_height = self._monospace_size.y;
+        let chars = rows.iter().map( | c | c.label.chars().count() + c.detail.chars().count() + 2).max().unwrap_or(0).min(80);
+        let width = self._monospace_size.x * chars as f32 + 8.;
+        
+        cx.new_instance_draw_call(&self.completion_bg.shader, 0);
+        self.completion_bg.color = Self::color_completion_bg().get(cx);
+        self.completion_bg.draw_quad_abs(cx, Rect {x: rc.x, y: rc.y + rc.h, w: width, h: line_height * rows.len() as f32});
+        self.completion_bg.color = Self::color_completion_selected().get(cx);
+        self.completion_bg.draw_quad_abs(cx, Rect {x: rc.x, y: rc.y + rc.h + line_height * (index - first) as f32, w: width, h: line_height});
+        
+        let mut aligned = self.completion_text.begin_text(cx);
+        for (i, completion) in rows.iter().enumerate() {
+            let y = rc.y + rc.h + line_height * i as f32;
+            let label: Vec<char> = completion.label.chars().collect();
+            self.completion_text.color = self.colors.identifier;
+            self.completion_text.add_text(cx, rc.x + 4., y, 0, &mut aligned, &label, | _, _, _, _ | {0.});
+            let detail: Vec<char> = completion.detail.chars().collect();
+            self.completion_text.color = self.colors.comment;
+            let x = rc.x + 4. + self._monospace_size.x * (label.len() + 2) as f32;
+            self.completion_text.add_text(cx, x, y, 0, &mut aligned, &detail, | _, _, _, _ | {0.});
+        }
+        self.completion_text.end_text(cx, &aligned);
+    }
+    
+    pub fn toggle_breakpoint(&mut self, cx: &mut Cx, row: usize, text_buffer: &mut TextBuffer) {
+        let breakpoints = &mut text_buffer.markers.breakpoints;
+        if let Some(pos) = breakpoints.iter().position( | r | *r == row) {
+            breakpoints.remove(pos);
+        }
+        else {
+            breakpoints.push(row);
+            breakpoints.sort();
+        }
+        self.view.redraw_view_area(cx);
+    }
+    
     fn draw_shadows(&mut self, cx: &mut Cx) {
         let gutter_width = Self::gutter_width().get(cx);
         self.shadow.draw_shadow_left_at(cx, Rect {
@@ -1906,6 +2148,10 @@ impl TextEditor {
                 TextBufferMessageLevel::Log => self.colors.message_marker_log,
             };
             self.message_marker.draw_quad_rel(cx, Rect {x: mark.rc.x - origin.x, y: mark.rc.y - origin.y, w: mark.rc.w, h: mark.rc.h});
+            // a second line under the ones ctrl/cmd + . can fix
+            if body.fixes.len() > 0 {
+                self.message_marker.draw_quad_rel(cx, Rect {x: mark.rc.x - origin.x, y: mark.rc.y - origin.y - 2., w: mark.rc.w, h: mark.rc.h});
+            }
         }
     }
     
@@ -103,6 +103,10 @@ pub fn set_widget_style(cx: &mut Cx, opt: &StyleOptions) {
         TextEditor::color_highlight().set(cx, pick!(75, 75, 95, 128).get(cx));
         TextEditor::color_cursor().set(cx, pick!(176, 176, 176).get(cx));
         TextEditor::color_cursor_row().set(cx, pick!(45, 45, 45).get(cx));
+        TextEditor::color_debug_row().set(cx, pick!(70, 60, 20).get(cx));
+        TextEditor::color_breakpoint().set(cx, pick!(200, 40, 40).get(cx));
+        TextEditor::color_completion_bg().set(cx, pick!(37, 37, 38).get(cx));
+        TextEditor::color_completion_selected().set(cx, pick!(4, 57, 94).get(cx));
         
         TextEditor::color_paren_pair_match().set(cx, pick!(255, 255, 255).get(cx));
         TextEditor::color_paren_pair_fail().set(cx, pick!(255, 0, 0).get(cx));